@@ -1,19 +1,70 @@
-use serde::Serialize;
-use std::thread;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use rescue_chess::{
-    piece_move::GameType,
+    explanation::explain,
+    game::Game,
+    game_review::{review_game, EvaluationPoint, GameReview, MoveReview},
+    opening_explorer::{OpeningTree, ResultStats},
+    pgn::{export_pgn, parse_pgn},
+    piece_move::{GameType, MoveList, MoveType, PieceNotation},
+    position::{GameStatus, Handicap, PositionError, PositionSetup, REPETITION_COUNT},
     search::{
-        alpha_beta::{self, SearchParams},
-        search_results::{SearchResults, SearchState},
+        alpha_beta::{AlphaBetaError, MoveScore, SearchParams},
+        backend,
+        iterative_deepening::IterativeDeepeningData,
+        params_builder::MAX_SEARCH_PLY,
+        score::Score,
+        search_results::{SearchInfo, SearchMemory, SearchResults, SearchState},
+        strength::StrengthLimit,
+        time_control::TimeControl,
+        transposition_table::TranspositionTable,
     },
-    Color, PieceMove,
+    threats::{HangingPiece, Threats},
+    Color, PieceMove, Pos, Position,
 };
 use tauri::{command, Manager, State};
 
-use crate::global_state::GlobalState;
+use crate::{
+    global_state::{EngineColor, GlobalState, GlobalStateData},
+    settings::{EngineOptions, EngineSettings},
+};
+
+pub(crate) const GAME_TYPE: GameType = GameType::Rescue;
 
-const GAME_TYPE: GameType = GameType::Rescue;
+/// Emits an event about the game in the session with the given ID. Events from any session but
+/// the active one are dropped, since the frontend only shows the active session's game and
+/// loads it afresh when it's switched to.
+fn emit_for<S: Serialize + Clone>(
+    app: &tauri::AppHandle,
+    session_id: u64,
+    event: &str,
+    payload: S,
+) {
+    if app.state::<GlobalState>().is_active(session_id) {
+        app.emit(event, payload).unwrap();
+    }
+}
+
+/// Runs `f` on the session with the given ID from a background thread, unless the session has
+/// been closed.
+fn with_session<T>(
+    app: &tauri::AppHandle,
+    session_id: u64,
+    f: impl FnOnce(&mut GlobalStateData) -> T,
+) -> Option<T> {
+    let session = app.state::<GlobalState>().session(session_id)?;
+    let mut gs = session.lock().unwrap();
+    Some(f(&mut gs))
+}
 
 #[command]
 pub fn get_valid_positions_for(
@@ -21,137 +72,388 @@ pub fn get_valid_positions_for(
     y: u8,
     state: State<GlobalState>,
 ) -> Result<Vec<PieceMove>, String> {
-    let gs = state.lock().unwrap();
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    legal_moves_from(&gs.position, (x, y).into())
+}
+
+/// What the piece on a square can do, split up so the board can show rescues and drops as well
+/// as where the piece can go. Squares are sorted and listed once each.
+#[derive(Clone, Serialize)]
+pub struct MoveTargets {
+    /// Every legal move for the piece, to look up the one the player picks.
+    moves: Vec<PieceMove>,
+
+    /// The squares the piece can move to, including its own square if it can rescue a piece
+    /// without moving.
+    destinations: Vec<Pos>,
+
+    /// The squares of adjacent friendly pieces the piece can pick up as it moves.
+    rescues: Vec<Pos>,
+
+    /// The squares next to a destination where the piece can drop what it's holding.
+    drops: Vec<Pos>,
+}
 
-    let all_moves = gs
-        .position
-        .get_all_legal_moves(GAME_TYPE)
+/// The legal moves for the piece at `x`, `y`, along with the rescues and drops it can make.
+#[command]
+pub fn get_move_targets(x: u8, y: u8, state: State<GlobalState>) -> Result<MoveTargets, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    let moves = legal_moves_from(&gs.position, (x, y).into())?;
+
+    let destinations = sorted_squares(moves.iter().map(|m| m.to));
+    let rescues = sorted_squares(moves.iter().filter_map(|m| match m.move_type {
+        MoveType::Normal { rescued_pos, .. } => rescued_pos,
+        MoveType::Castle { .. } => None,
+    }));
+    let drops = sorted_squares(moves.iter().filter_map(|m| match m.move_type {
+        MoveType::Normal { dropped_pos, .. } => dropped_pos,
+        MoveType::Castle { .. } => None,
+    }));
+
+    Ok(MoveTargets {
+        moves,
+        destinations,
+        rescues,
+        drops,
+    })
+}
+
+fn sorted_squares(squares: impl Iterator<Item = Pos>) -> Vec<Pos> {
+    let mut squares: Vec<Pos> = squares.collect();
+    squares.sort();
+    squares.dedup();
+    squares
+}
+
+fn legal_moves_from(position: &Position, from: Pos) -> Result<Vec<PieceMove>, String> {
+    let mut all_moves = MoveList::new();
+    position
+        .get_all_legal_moves(GAME_TYPE, &mut all_moves)
         .map_err(|e| e.to_string())?;
 
-    let moves_for_piece = all_moves
+    Ok(all_moves
         .iter()
-        .filter(|m| m.from == (x, y).into())
+        .filter(|m| m.from == from)
         .cloned()
+        .collect())
+}
+
+#[derive(Clone, Serialize)]
+pub struct MateThreatResponse {
+    move_from_whites_perspective: PieceMove,
+    notation: String,
+
+    /// How many moves the opponent needs to mate.
+    moves: u32,
+}
+
+/// What the opponent of the side to move threatens, for highlighting dangers on the board. Squares
+/// and moves are from white's perspective.
+#[derive(Clone, Serialize)]
+pub struct ThreatsResponse {
+    /// Every square the opponent attacks.
+    attacked: Vec<Pos>,
+
+    /// Pieces the opponent can win material by capturing, most material first.
+    hanging: Vec<HangingPiece>,
+
+    mate_threat: Option<MateThreatResponse>,
+}
+
+#[command]
+pub fn get_threats(state: State<GlobalState>) -> Result<ThreatsResponse, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    let position = gs.position_for_side_to_move();
+    let threats = Threats::find(&position, GAME_TYPE).map_err(|e| e.to_string())?;
+
+    let from_whites_perspective = |pos: Pos| match gs.to_move {
+        Color::White => pos,
+        Color::Black => pos.invert(),
+    };
+    let move_from_whites_perspective = |mv: PieceMove| match gs.to_move {
+        Color::White => mv,
+        Color::Black => mv.inverted(),
+    };
+
+    let attacked = sorted_squares(threats.attacked.into_iter().map(from_whites_perspective));
+
+    let hanging = threats
+        .hanging
+        .into_iter()
+        .map(|piece| HangingPiece {
+            pos: from_whites_perspective(piece.pos),
+            capture: move_from_whites_perspective(piece.capture),
+            ..piece
+        })
         .collect();
 
-    Ok(moves_for_piece)
+    // The mating move is made by the opponent, so it's written out from their side of the board
+    let mate_threat = threats.mate_threat.map(|threat| MateThreatResponse {
+        move_from_whites_perspective: move_from_whites_perspective(threat.mv),
+        notation: threat
+            .mv
+            .inverted()
+            .to_san_with_notation(&position.inverted(), gs.notation)
+            .unwrap_or_else(|_| threat.mv.to_notation(gs.notation)),
+        moves: threat.moves,
+    });
+
+    Ok(ThreatsResponse {
+        attacked,
+        hanging,
+        mate_threat,
+    })
 }
 
 #[command]
 pub fn reset(state: State<GlobalState>) {
-    let mut gs = state.lock().unwrap();
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
 
     gs.reset();
 }
 
 #[command]
 pub fn get_position_fen(state: State<GlobalState>) -> String {
-    let gs = state.lock().unwrap();
+    let session = state.active();
+    let gs = session.lock().unwrap();
 
     gs.position.to_fen()
 }
 
+/// Sets how moves are formatted, by notation name (e.g. `figurine`, `english`) or five custom
+/// piece letters. Notations whose letters clash with the rescue and drop markers, like German,
+/// are refused.
 #[command]
-pub fn move_piece(mv: PieceMove, state: State<GlobalState>) -> Result<(), String> {
-    let mut gs = state.lock().unwrap();
+pub fn set_notation(notation: String, state: State<GlobalState>) -> Result<(), String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
 
-    match gs.position.get_piece_at(mv.from) {
-        Some(piece) => match piece.color {
-            Color::White => {
-                let all_moves = gs
-                    .position
-                    .get_all_legal_moves(GAME_TYPE)
-                    .map_err(|e| e.to_string())?;
+    let notation: PieceNotation = notation.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    notation.validate(GAME_TYPE).map_err(|e| e.to_string())?;
 
-                let matching_move = all_moves
-                    .into_iter()
-                    .find(|m| *m == mv)
-                    .ok_or_else(|| "Invalid move".to_string())?;
+    gs.notation = notation;
 
-                gs.position
-                    .apply_move(matching_move)
-                    .map_err(|e| e.to_string())?;
-            }
-            Color::Black => {
-                // Invert the position, apply the move, and invert back
-                let mut inverted_position = gs.position.inverted();
-                let mv = mv.inverted();
+    Ok(())
+}
+
+/// Writes a move in the current position, given from white's perspective, in SAN.
+#[command]
+pub fn format_move(mv: PieceMove, state: State<GlobalState>) -> Result<String, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
 
-                let all_moves = inverted_position
-                    .get_all_legal_moves(GAME_TYPE)
-                    .map_err(|e| e.to_string())?;
+    let mv = match gs.to_move {
+        Color::White => mv,
+        Color::Black => mv.inverted(),
+    };
 
-                let matching_move = all_moves
-                    .into_iter()
-                    .find(|m| *m == mv)
-                    .ok_or_else(|| "Invalid move".to_string())?;
+    mv.to_san_with_notation(&gs.position_for_side_to_move(), gs.notation)
+        .map_err(|e| e.to_string())
+}
 
-                inverted_position
-                    .apply_move(matching_move)
-                    .map_err(|e| e.to_string())?;
+/// Checks a FEN string for every problem, for highlighting while it's being edited.
+#[command]
+pub fn validate_fen(fen: String) -> Vec<PositionError> {
+    Position::validate_fen(&fen).err().unwrap_or_default()
+}
 
-                gs.position = inverted_position.inverted();
-            }
-        },
-        None => return Err("No piece at that position".to_string()),
+/// Starts a new game from a FEN string, emitting a `position_loaded` event. If the FEN is
+/// invalid the current game is left alone and every problem found is returned.
+#[command]
+pub fn load_fen(
+    fen: String,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<GameStatus, Vec<PositionError>> {
+    let position = Position::validate_fen(&fen)?;
+    Ok(start_from_position(position, &state, &app))
+}
+
+/// Starts a new game from a position set up in the board editor, emitting a
+/// `position_loaded` event. Like `load_fen`, an invalid position is rejected with every problem
+/// found: missing kings, pawns on the back rank, illegal holdings and so on.
+#[command]
+pub fn set_custom_position(
+    setup: PositionSetup,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<GameStatus, Vec<PositionError>> {
+    let position = Position::from_setup(&setup)?;
+    Ok(start_from_position(position, &state, &app))
+}
+
+fn start_from_position(
+    position: Position,
+    state: &GlobalState,
+    app: &tauri::AppHandle,
+) -> GameStatus {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    gs.load_position(position);
+
+    emit_for(
+        app,
+        gs.session_id,
+        "position_loaded",
+        MoveListPosition::of(&gs),
+    );
+
+    // A position can be loaded that's already over, e.g. checkmate
+    let status = gs.game_status(GAME_TYPE).unwrap_or(GameStatus::Ongoing);
+    if status.is_game_over() {
+        end_game(&mut gs, app, status);
     }
 
-    Ok(())
+    status
 }
 
-#[derive(Clone, Serialize)]
-struct BlackMoveResponse {
-    results: SearchResults,
-    move_from_whites_perspective: PieceMove,
+/// The formats a game can be saved in. JSON keeps everything about the game, PGN can be read
+/// by other programs.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum GameFormat {
+    Pgn,
+    Json,
 }
 
-#[derive(Clone, Serialize)]
-struct WhiteMoveResponse {
-    results: SearchResults,
-    move_from_whites_perspective: PieceMove,
+/// Writes the whole game, with its variant and result, in `format`, to save it or share it.
+#[command]
+pub fn export_game(format: GameFormat, state: State<GlobalState>) -> Result<String, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+    let game = gs.to_game(GAME_TYPE).map_err(|e| e.to_string())?;
+
+    match format {
+        GameFormat::Pgn => Ok(export_pgn(&game)),
+        GameFormat::Json => serde_json::to_string(&game).map_err(|e| e.to_string()),
+    }
 }
 
+/// Restores a game written by `export_game`, or any PGN game of the app's variant, at the end of
+/// its moves, emitting a `position_loaded` event. Returns how the game stands.
 #[command]
-pub fn get_black_move(state: State<GlobalState>, app: tauri::AppHandle) -> Result<(), String> {
-    let gs = state.lock().unwrap();
-    let transposition_table = gs.transposition_table.clone();
-    let depth = gs.depth;
+pub fn import_game(
+    text: String,
+    format: GameFormat,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<GameStatus, String> {
+    let game = match format {
+        GameFormat::Pgn => {
+            let pgn = parse_pgn(&text)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .next()
+                .ok_or_else(|| "There's no game in the PGN".to_string())?;
 
-    let from_black = gs.position.inverted();
+            let variant = GAME_TYPE.rules().name();
+            if let Some(other) = pgn
+                .tag("Variant")
+                .filter(|tag| !tag.eq_ignore_ascii_case(variant))
+            {
+                return Err(format!("The game is {}, not {}", other, variant));
+            }
 
-    println!("Getting black move");
-    println!(
-        "Position\n{}",
-        from_black.to_board_string_with_rank_file_holding()
+            pgn.to_game(GAME_TYPE).map_err(|e| e.to_string())?
+        }
+        GameFormat::Json => {
+            let game: Game = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+            if game.game_type() != GAME_TYPE {
+                return Err(format!(
+                    "The game is {:?}, not {:?}",
+                    game.game_type(),
+                    GAME_TYPE
+                ));
+            }
+
+            game
+        }
+    };
+
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    gs.load_game(&game).map_err(|e| e.to_string())?;
+
+    emit_for(
+        &app,
+        gs.session_id,
+        "position_loaded",
+        MoveListPosition::of(&gs),
     );
 
-    thread::spawn(move || -> () {
-        let mut transposition_table = transposition_table.lock().unwrap();
-        let mut state = SearchState::new(&mut transposition_table);
+    let status = match gs.game_over {
+        Some(status) => status,
+        None => gs.game_status(GAME_TYPE).map_err(|e| e.to_string())?,
+    };
+    if status.is_game_over() {
+        emit_for(&app, gs.session_id, "game_over", status);
+    }
 
-        let params = SearchParams {
-            depth,
-            game_type: GAME_TYPE,
-            ..Default::default()
-        };
+    Ok(status)
+}
 
-        let results = alpha_beta::search(&from_black, &mut state, params, 0);
+/// Starts a new game from the starting position with the engine giving a handicap, emitting a
+/// `position_loaded` event. The engine has to play exactly one side.
+#[command]
+pub fn start_handicap_game(
+    handicap: Handicap,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
 
-        match results {
-            Ok(results) => {
-                let move_from_whites_perspective = results.best_move.unwrap().inverted();
+    let color = match gs.engine_color {
+        EngineColor::White => Color::White,
+        EngineColor::Black => Color::Black,
+        EngineColor::Both | EngineColor::None => {
+            return Err("Choose a side for the engine to play to give it a handicap".to_string())
+        }
+    };
 
-                app.emit(
-                    "black_move",
-                    BlackMoveResponse {
-                        results,
-                        move_from_whites_perspective,
-                    },
-                )
-                .unwrap();
+    let position =
+        Position::start_position_with_handicap(handicap, color).map_err(|e| e.to_string())?;
+    gs.load_position(position);
+
+    emit_for(
+        &app,
+        gs.session_id,
+        "position_loaded",
+        MoveListPosition::of(&gs),
+    );
+
+    Ok(())
+}
+
+/// How many plies into each game the opening database indexes.
+const OPENING_TREE_MAX_PLY: usize = 30;
+
+/// Indexes the .pgn files in a folder as the opening database on a background thread. An
+/// `opening_database_loaded` event with an `IndexSummary` is emitted when it's done, or an
+/// `opening_database_error` event if the files couldn't be read.
+#[command]
+pub fn load_opening_database(path: String, app: tauri::AppHandle) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.is_dir() {
+        return Err(format!("{} is not a folder", path.display()));
+    }
+
+    thread::spawn(move || {
+        let mut tree = OpeningTree::new(OPENING_TREE_MAX_PLY, GAME_TYPE);
+
+        match tree.index_directory(&path) {
+            Ok(summary) => {
+                *app.state::<GlobalState>().opening_tree.lock().unwrap() = Some(tree);
+                app.emit("opening_database_loaded", summary).unwrap();
             }
-            Err(_) => {
-                eprintln!("Error getting black move");
+            Err(e) => {
+                app.emit("opening_database_error", e.to_string()).unwrap();
             }
         }
     });
@@ -159,50 +461,1815 @@ pub fn get_black_move(state: State<GlobalState>, app: tauri::AppHandle) -> Resul
     Ok(())
 }
 
+#[derive(Clone, Serialize)]
+pub struct OpeningExplorerMove {
+    move_from_whites_perspective: PieceMove,
+    notation: String,
+    stats: ResultStats,
+}
+
+#[derive(Clone, Serialize)]
+pub struct OpeningExplorerResponse {
+    /// How the database's games that reached the current position ended.
+    stats: ResultStats,
+
+    /// The moves played from the current position, most popular first.
+    moves: Vec<OpeningExplorerMove>,
+}
+
+/// The moves played from the current position in the opening database.
 #[command]
-pub fn get_white_move(state: State<GlobalState>, app: tauri::AppHandle) -> Result<(), String> {
-    let gs = state.lock().unwrap();
-    let from_white = gs.position.clone();
+pub fn explore_position(state: State<GlobalState>) -> Result<OpeningExplorerResponse, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
 
-    println!("Getting white move");
-    println!(
-        "Position\n{}",
-        gs.position.to_board_string_with_rank_file_holding()
+    let opening_tree = state.opening_tree.lock().unwrap();
+    let tree = opening_tree
+        .as_ref()
+        .ok_or_else(|| "No opening database has been loaded".to_string())?;
+
+    let position = gs.position_for_side_to_move();
+
+    let moves = tree
+        .moves(&position)
+        .into_iter()
+        .map(|explored| {
+            let move_from_whites_perspective = match gs.to_move {
+                Color::White => explored.mv,
+                Color::Black => explored.mv.inverted(),
+            };
+
+            OpeningExplorerMove {
+                move_from_whites_perspective,
+                notation: explored
+                    .mv
+                    .to_san_with_notation(&position, gs.notation)
+                    .unwrap_or_else(|_| move_from_whites_perspective.to_notation(gs.notation)),
+                stats: explored.stats,
+            }
+        })
+        .collect();
+
+    Ok(OpeningExplorerResponse {
+        stats: tree.position_stats(&position),
+        moves,
+    })
+}
+
+#[command]
+pub fn get_game_status(state: State<GlobalState>) -> Result<GameStatus, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    if let Some(game_over) = gs.game_over {
+        return Ok(game_over);
+    }
+
+    gs.game_status(GAME_TYPE).map_err(|e| e.to_string())
+}
+
+/// Ends the game, stopping any engine searches and emitting a `game_over` event.
+fn end_game(gs: &mut GlobalStateData, app: &tauri::AppHandle, status: GameStatus) {
+    gs.game_over = Some(status);
+    gs.draw_offer = None;
+    gs.cancel_search(None);
+    if let Some(clock) = &mut gs.clock {
+        clock.stop();
+    }
+
+    emit_for(app, gs.session_id, "game_over", status);
+}
+
+fn ensure_game_in_progress(gs: &GlobalStateData) -> Result<(), String> {
+    match gs.game_over {
+        Some(status) => Err(format!("The game is over: {}", status)),
+        None => Ok(()),
+    }
+}
+
+fn ensure_not_autoplaying(gs: &GlobalStateData) -> Result<(), String> {
+    match gs.autoplay {
+        Some(_) => Err("The engines are playing each other".to_string()),
+        None => Ok(()),
+    }
+}
+
+/// The side to move resigns.
+#[command]
+pub fn resign(state: State<GlobalState>, app: tauri::AppHandle) -> Result<GameStatus, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let status = GameStatus::Resignation(gs.to_move.invert());
+    end_game(&mut gs, &app, status);
+
+    Ok(status)
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub enum DrawOfferResponse {
+    Accepted,
+    Declined,
+
+    /// The offer is waiting for the other player to accept or decline it.
+    Pending,
+}
+
+#[derive(Clone, Serialize)]
+struct DrawOfferedEvent {
+    color: Color,
+}
+
+/// The side to move offers a draw. If the engine plays the other side it answers straight
+/// away, otherwise a `draw_offered` event is emitted and the offer stands until it is accepted,
+/// declined, or a move is made.
+#[command]
+pub fn offer_draw(
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<DrawOfferResponse, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let color = gs.to_move;
+    let opponent = color.invert();
+
+    if gs.engine_color.plays(opponent) {
+        if gs.engine_accepts_draw(opponent) {
+            end_game(&mut gs, &app, GameStatus::DrawByAgreement);
+            return Ok(DrawOfferResponse::Accepted);
+        }
+
+        return Ok(DrawOfferResponse::Declined);
+    }
+
+    gs.draw_offer = Some(color);
+    emit_for(
+        &app,
+        gs.session_id,
+        "draw_offered",
+        DrawOfferedEvent { color },
     );
 
-    let transposition_table = gs.transposition_table.clone();
-    let depth = gs.depth;
+    Ok(DrawOfferResponse::Pending)
+}
 
-    thread::spawn(move || -> () {
-        let mut transposition_table = transposition_table.lock().unwrap();
-        let mut state = SearchState::new(&mut transposition_table);
+#[command]
+pub fn accept_draw(state: State<GlobalState>, app: tauri::AppHandle) -> Result<GameStatus, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
 
-        let params = SearchParams {
-            depth,
-            game_type: GAME_TYPE,
-            ..Default::default()
-        };
+    if gs.draw_offer.is_none() {
+        return Err("No draw has been offered".to_string());
+    }
 
-        let results = alpha_beta::search(&from_white, &mut state, params, 0);
+    end_game(&mut gs, &app, GameStatus::DrawByAgreement);
 
-        match results {
-            Ok(results) => {
-                let move_from_whites_perspective = results.best_move.unwrap();
+    Ok(GameStatus::DrawByAgreement)
+}
 
-                app.emit(
-                    "white_move",
-                    WhiteMoveResponse {
-                        results,
-                        move_from_whites_perspective,
-                    },
-                )
-                .unwrap();
-            }
-            Err(_) => {
-                eprintln!("Error getting white move");
-            }
+/// Declines the pending draw offer. Returns whether there was one.
+#[command]
+pub fn decline_draw(state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    gs.draw_offer.take().is_some()
+}
+
+/// Whether the current position has occurred often enough for a draw to be claimed.
+#[command]
+pub fn can_claim_draw(state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    gs.repetition_count() >= REPETITION_COUNT
+}
+
+#[command]
+pub fn claim_draw(state: State<GlobalState>, app: tauri::AppHandle) -> Result<GameStatus, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let count = gs.repetition_count();
+    if count < REPETITION_COUNT {
+        return Err(format!(
+            "Position has only occurred {} time(s), a draw can't be claimed",
+            count
+        ));
+    }
+
+    end_game(&mut gs, &app, GameStatus::DrawByRepetition);
+
+    Ok(GameStatus::DrawByRepetition)
+}
+
+#[command]
+pub fn move_piece(
+    mv: PieceMove,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<GameStatus, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+    ensure_not_autoplaying(&gs)?;
+
+    if let Some(status) = flag_if_out_of_time(&mut gs, &app) {
+        return Err(status.to_string());
+    }
+
+    let color = match gs.position.get_piece_at(mv.from) {
+        Some(piece) => piece.color,
+        None => return Err("No piece at that position".to_string()),
+    };
+
+    // Moves are matched against the legal moves from the mover's perspective
+    let (position, mv) = match color {
+        Color::White => (gs.position.clone(), mv),
+        Color::Black => (gs.position.inverted(), mv.inverted()),
+    };
+
+    // Only the moving piece's moves are generated, to fill in what the move captures
+    let mut piece_moves = MoveList::new();
+    position.get_piece_moves_unchecked(GAME_TYPE, mv.from, &mut piece_moves);
+
+    let matching_move = piece_moves
+        .into_iter()
+        .find(|m| is_same_move(m, &mv))
+        .ok_or_else(|| "Invalid move".to_string())?;
+
+    if !position
+        .is_legal(GAME_TYPE, &matching_move)
+        .map_err(|e| e.to_string())?
+    {
+        return Err("Invalid move".to_string());
+    }
+
+    gs.play_move(color, matching_move)
+        .map_err(|e| e.to_string())?;
+    gs.draw_offer = None;
+
+    emit_for(
+        &app,
+        gs.session_id,
+        "move_played",
+        MoveListPosition::of(&gs),
+    );
+
+    let status = gs.game_status(GAME_TYPE).map_err(|e| e.to_string())?;
+    if status.is_game_over() {
+        end_game(&mut gs, &app, status);
+    } else if !gs.engine_color.plays(color) {
+        if let Some(depth) = gs.quick_evaluation_depth {
+            start_quick_evaluation(&gs, app, depth);
         }
-    });
+    }
 
-    Ok(())
+    Ok(status)
+}
+
+#[derive(Clone, Serialize)]
+pub struct MoveListPosition {
+    /// The number of moves played to reach the current position.
+    ply: usize,
+
+    /// The number of moves in the game, including any after the current position.
+    move_count: usize,
+}
+
+impl MoveListPosition {
+    fn of(gs: &GlobalStateData) -> Self {
+        MoveListPosition {
+            ply: gs.ply(),
+            move_count: gs.moves.len(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MoveListEntry {
+    ply: usize,
+    color: Color,
+    move_from_whites_perspective: PieceMove,
+    notation: String,
+}
+
+/// Every move of the game, including any after the current position, for the move list.
+#[command]
+pub fn get_move_list(state: State<GlobalState>) -> Vec<MoveListEntry> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    gs.moves
+        .iter()
+        .zip(gs.move_notations())
+        .enumerate()
+        .map(|(i, (played, notation))| MoveListEntry {
+            ply: i + 1,
+            color: played.color,
+            move_from_whites_perspective: played.from_whites_perspective(),
+            notation,
+        })
+        .collect()
+}
+
+/// Moves to the position after the first `ply` moves of the game, stopping any engine search
+/// and emitting a `navigated` event.
+fn navigate(
+    gs: &mut GlobalStateData,
+    app: &tauri::AppHandle,
+    ply: usize,
+) -> Result<MoveListPosition, String> {
+    gs.goto_ply(ply).map_err(|e| e.to_string())?;
+
+    let position = MoveListPosition::of(gs);
+    emit_for(app, gs.session_id, "navigated", position.clone());
+
+    if let Some(depth) = gs.quick_evaluation_depth {
+        start_quick_evaluation(gs, app.clone(), depth);
+    }
+
+    Ok(position)
+}
+
+#[command]
+pub fn goto_ply(
+    ply: usize,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<MoveListPosition, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    navigate(&mut gs, &app, ply)
+}
+
+#[command]
+pub fn first(state: State<GlobalState>, app: tauri::AppHandle) -> Result<MoveListPosition, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    navigate(&mut gs, &app, 0)
+}
+
+#[command]
+pub fn prev(state: State<GlobalState>, app: tauri::AppHandle) -> Result<MoveListPosition, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let ply = gs.ply().saturating_sub(1);
+    navigate(&mut gs, &app, ply)
+}
+
+#[command]
+pub fn next(state: State<GlobalState>, app: tauri::AppHandle) -> Result<MoveListPosition, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let ply = (gs.ply() + 1).min(gs.moves.len());
+    navigate(&mut gs, &app, ply)
+}
+
+#[command]
+pub fn last(state: State<GlobalState>, app: tauri::AppHandle) -> Result<MoveListPosition, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let ply = gs.moves.len();
+    navigate(&mut gs, &app, ply)
+}
+
+/// Whether the possible move `candidate` is the move `mv` sent by the frontend. Only the parts
+/// of the move a player chooses are compared, since what gets captured follows from the position.
+fn is_same_move(candidate: &PieceMove, mv: &PieceMove) -> bool {
+    if candidate.from != mv.from || candidate.to != mv.to {
+        return false;
+    }
+
+    match (candidate.move_type, mv.move_type) {
+        (
+            MoveType::Normal {
+                rescued_pos,
+                dropped_pos,
+                promoted_to,
+                dropped_promoted_to,
+                ..
+            },
+            MoveType::Normal {
+                rescued_pos: mv_rescued_pos,
+                dropped_pos: mv_dropped_pos,
+                promoted_to: mv_promoted_to,
+                dropped_promoted_to: mv_dropped_promoted_to,
+                ..
+            },
+        ) => {
+            rescued_pos == mv_rescued_pos
+                && dropped_pos == mv_dropped_pos
+                && promoted_to == mv_promoted_to
+                && dropped_promoted_to == mv_dropped_promoted_to
+        }
+        (MoveType::Castle { .. }, MoveType::Castle { .. }) => true,
+        _ => false,
+    }
+}
+
+/// Converts the score of a search for `color` into a score from white's point of view, for the
+/// evaluation bar.
+fn white_score(score: i32, color: Color) -> Score {
+    match color {
+        Color::White => Score::from_search(score),
+        Color::Black => -Score::from_search(score),
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct EvaluationResponse {
+    evaluation: Score,
+    depth: u32,
+
+    /// The ply of the game the evaluation is for, so stale evaluations can be ignored.
+    ply: usize,
+}
+
+fn emit_evaluation(
+    app: &tauri::AppHandle,
+    session_id: u64,
+    color: Color,
+    results: &SearchResults,
+    ply: usize,
+) {
+    emit_for(
+        app,
+        session_id,
+        "evaluation",
+        EvaluationResponse {
+            evaluation: white_score(results.score, color),
+            depth: results.depth,
+            ply,
+        },
+    );
+}
+
+/// Runs a shallow search of the current position on a background thread, emitting the result
+/// as an `evaluation` event. It uses its own transposition table so it never waits on the
+/// engine's search.
+fn start_quick_evaluation(gs: &GlobalStateData, app: tauri::AppHandle, depth: u32) {
+    let color = gs.to_move;
+    let position = gs.position_for_side_to_move();
+    let history = gs.history.clone();
+    let ply = gs.ply();
+    let session_id = gs.session_id;
+
+    thread::spawn(move || evaluate_position(app, session_id, position, color, history, ply, depth));
+}
+
+fn evaluate_position(
+    app: tauri::AppHandle,
+    session_id: u64,
+    position: Position,
+    color: Color,
+    history: Vec<u64>,
+    ply: usize,
+    depth: u32,
+) {
+    let params = match SearchParams::builder()
+        .depth(depth)
+        .game_type(GAME_TYPE)
+        .build()
+    {
+        Ok(params) => params,
+        Err(e) => {
+            eprintln!("Invalid quick evaluation parameters: {}", e);
+            return;
+        }
+    };
+
+    let mut transposition_table = TranspositionTable::new();
+    let mut state = SearchState::new(&mut transposition_table);
+    state.data.history = history;
+
+    match backend::search(&position, &mut state, params, 0) {
+        Ok(results) => emit_evaluation(&app, session_id, color, &results, ply),
+        Err(e) => eprintln!("Quick evaluation failed: {}", e),
+    }
+}
+
+/// The depth a background analysis deepens to if it isn't stopped first, leaving room under
+/// `MAX_SEARCH_PLY` for quiescence.
+const ANALYSIS_MAX_DEPTH: u32 = MAX_SEARCH_PLY / 2;
+
+/// The size of a background analysis' own transposition table.
+const ANALYSIS_HASH_MB: usize = 64;
+
+#[derive(Clone, Serialize)]
+struct AnalysisResponse {
+    analysis_id: u64,
+
+    /// The ply of the game the analysis is for, so stale updates can be ignored.
+    ply: usize,
+
+    evaluation: Score,
+    depth: u32,
+    nodes: u32,
+    time_ms: u128,
+
+    /// The line the engine expects, from white's perspective.
+    principal_variation: Vec<PieceMove>,
+
+    /// The line written with the display notation.
+    line: Vec<String>,
+
+    /// Set on the last update, once the analysis has reached its deepest depth.
+    done: bool,
+}
+
+impl AnalysisResponse {
+    /// The update for a search of `position`, from the perspective of `color` to move.
+    fn from_info(
+        analysis_id: u64,
+        ply: usize,
+        info: &SearchInfo,
+        position: &Position,
+        color: Color,
+        notation: PieceNotation,
+    ) -> Self {
+        let mut principal_variation = Vec::with_capacity(info.principal_variation.len());
+        let mut line = Vec::with_capacity(info.principal_variation.len());
+
+        // Moves in the principal variation alternate between each side's perspective
+        let mut position = position.clone();
+        let mut mover = color;
+        for mv in &info.principal_variation {
+            let Ok(san) = mv.to_san_with_notation(&position, notation) else {
+                break;
+            };
+            if position.apply_move(*mv).is_err() {
+                break;
+            }
+            position.invert();
+
+            principal_variation.push(match mover {
+                Color::White => *mv,
+                Color::Black => mv.inverted(),
+            });
+            line.push(san);
+            mover = mover.invert();
+        }
+
+        AnalysisResponse {
+            analysis_id,
+            ply,
+            evaluation: white_score(info.score, color),
+            depth: info.depth,
+            nodes: info.nodes,
+            time_ms: info.time_ms,
+            principal_variation,
+            line,
+            done: false,
+        }
+    }
+}
+
+/// Analyzes the current position on a background thread until it's stopped or the position
+/// changes, returning the analysis' ID straight away. An `analysis` event is emitted each time
+/// the best line changes or a depth finishes, for a live evaluation bar. The analysis has its
+/// own transposition table, so the engine's moves never wait on it. Starting an analysis stops
+/// the one running.
+#[command]
+pub fn start_analysis(state: State<GlobalState>, app: tauri::AppHandle) -> Result<u64, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let color = gs.to_move;
+    let position = gs.position_for_side_to_move();
+    let history = gs.history.clone();
+    let ply = gs.ply();
+    let notation = gs.notation;
+    let session_id = gs.session_id;
+
+    let params = SearchParams::builder()
+        .depth(ANALYSIS_MAX_DEPTH)
+        .game_type(GAME_TYPE)
+        .features(gs.settings.features)
+        .weights(gs.settings.weights)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let (analysis_id, stop) = gs.begin_analysis();
+
+    thread::spawn(move || {
+        let last_update = Arc::new(Mutex::new(None::<AnalysisResponse>));
+
+        let mut data = IterativeDeepeningData::new();
+        data.transposition_table = TranspositionTable::with_size_mb(ANALYSIS_HASH_MB);
+        data.update_position(position.clone());
+        data.update_history(history);
+        data.ply = ply;
+        data.stop = stop;
+        data.on_new_best_move = Some(Box::new({
+            let app = app.clone();
+            let last_update = last_update.clone();
+
+            move |info: &SearchInfo| {
+                let update =
+                    AnalysisResponse::from_info(analysis_id, ply, info, &position, color, notation);
+                emit_for(&app, session_id, "analysis", update.clone());
+                *last_update.lock().unwrap() = Some(update);
+            }
+        }));
+
+        data.search(params);
+
+        let Some(session) = app.state::<GlobalState>().session(session_id) else {
+            return;
+        };
+        let mut gs = session.lock().unwrap();
+
+        // Only an analysis that reached its deepest depth is done, a stopped one just stops
+        if matches!(gs.analysis, Some((id, _)) if id == analysis_id) {
+            gs.end_analysis(analysis_id);
+
+            if let Some(update) = last_update.lock().unwrap().take() {
+                emit_for(
+                    &app,
+                    session_id,
+                    "analysis",
+                    AnalysisResponse {
+                        done: true,
+                        ..update
+                    },
+                );
+            }
+        }
+    });
+
+    Ok(analysis_id)
+}
+
+/// Stops the background analysis, returning whether one was running.
+#[command]
+pub fn stop_analysis(state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    gs.stop_analysis()
+}
+
+#[derive(Clone, Serialize)]
+struct GameReviewProgressResponse {
+    search_id: u64,
+
+    /// The move just reviewed, with its centipawn loss and classification.
+    review: MoveReview,
+    reviewed: usize,
+    total: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct GameReviewResponse {
+    search_id: u64,
+    review: GameReview,
+
+    /// The evaluation after each move, for the advantage graph.
+    evaluation_graph: Vec<EvaluationPoint>,
+}
+
+/// Reviews every move of the game on a background thread, for a review screen once the game
+/// is over, returning the search ID straight away. Each position is searched to `depth`, or
+/// the engine's depth if not given, scoring every move to find each move's centipawn loss.
+///
+/// A `game_review_progress` event is emitted as each move is reviewed, and a `game_review`
+/// event with the whole report at the end. If the review is cancelled with `cancel_search` or
+/// fails, a `search_stopped` event is emitted instead.
+#[command]
+pub fn analyze_game(
+    depth: Option<u32>,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<u64, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let game = gs.to_game(GAME_TYPE).map_err(|e| e.to_string())?;
+    if game.moves().is_empty() {
+        return Err("There are no moves to review".to_string());
+    }
+
+    let params = SearchParams::builder()
+        .depth(depth.unwrap_or(gs.settings.depth))
+        .game_type(GAME_TYPE)
+        .features(gs.settings.features)
+        .weights(gs.settings.weights)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let color = gs.to_move;
+    let session_id = gs.session_id;
+    let (search_id, stop) = gs.begin_search();
+
+    thread::spawn(move || {
+        let total = game.moves().len();
+
+        // Like the live analysis, the review has its own transposition table
+        let mut transposition_table = TranspositionTable::with_size_mb(ANALYSIS_HASH_MB);
+        let review = review_game(&game, &mut transposition_table, &params, stop, |review| {
+            emit_for(
+                &app,
+                session_id,
+                "game_review_progress",
+                GameReviewProgressResponse {
+                    search_id,
+                    review: review.clone(),
+                    reviewed: review.ply + 1,
+                    total,
+                },
+            );
+        });
+
+        with_session(&app, session_id, |gs| gs.end_search(search_id));
+
+        match review {
+            Ok(review) => {
+                emit_for(
+                    &app,
+                    session_id,
+                    "game_review",
+                    GameReviewResponse {
+                        search_id,
+                        evaluation_graph: review.evaluation_graph(),
+                        review,
+                    },
+                );
+            }
+            Err(e) => {
+                emit_for(
+                    &app,
+                    session_id,
+                    "search_stopped",
+                    SearchStoppedResponse {
+                        search_id,
+                        color,
+                        reason: e.to_string(),
+                    },
+                );
+            }
+        }
+    });
+
+    Ok(search_id)
+}
+
+/// How a move the player is thinking of playing compares to the engine's choice, e.g. to ask
+/// before playing a blunder. The move is from white's perspective and the position is searched
+/// to `depth`, or the engine's depth if not given.
+#[command]
+pub fn check_move_quality(
+    move_from_whites_perspective: PieceMove,
+    depth: Option<u32>,
+    state: State<GlobalState>,
+) -> Result<MoveReview, String> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    let game = gs.to_game(GAME_TYPE).map_err(|e| e.to_string())?;
+    let mv = match gs.to_move {
+        Color::White => move_from_whites_perspective,
+        Color::Black => move_from_whites_perspective.inverted(),
+    };
+
+    let params = SearchParams::builder()
+        .depth(depth.unwrap_or(gs.settings.depth))
+        .game_type(GAME_TYPE)
+        .features(gs.settings.features)
+        .weights(gs.settings.weights)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut transposition_table = TranspositionTable::with_size_mb(ANALYSIS_HASH_MB);
+    game.check_move_quality(mv, &mut transposition_table, &params)
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn get_engine_settings(state: State<GlobalState>) -> EngineSettings {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    gs.settings.clone()
+}
+
+/// Applies new engine settings to the next search and saves them for future sessions.
+#[command]
+pub fn set_engine_settings(
+    settings: EngineSettings,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    settings.validate().map_err(|e| e.to_string())?;
+
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    gs.apply_settings(settings.clone())
+        .map_err(|e| e.to_string())?;
+
+    settings.save(&app).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Changes some of the engine's settings for the rest of the session without saving them, for
+/// difficulty sliders that change often. Returns the settings as they are now.
+#[command]
+pub fn set_engine_options(
+    options: EngineOptions,
+    state: State<GlobalState>,
+) -> Result<EngineSettings, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let settings = gs
+        .settings
+        .with_options(&options)
+        .map_err(|e| e.to_string())?;
+    gs.apply_settings(settings.clone())
+        .map_err(|e| e.to_string())?;
+
+    Ok(settings)
+}
+
+/// Goes back to the default engine settings, saving them for future sessions.
+#[command]
+pub fn reset_engine_settings(
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<EngineSettings, String> {
+    let settings = EngineSettings::default();
+    settings.save(&app).map_err(|e| e.to_string())?;
+
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    gs.apply_settings(settings.clone())
+        .map_err(|e| e.to_string())?;
+
+    Ok(settings)
+}
+
+/// Sets the depth of the quick search run after each human move to update the evaluation
+/// bar, or turns it off with `None`.
+#[command]
+pub fn set_quick_evaluation_depth(depth: Option<u32>, state: State<GlobalState>) {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    gs.quick_evaluation_depth = depth;
+}
+
+#[derive(Clone, Serialize)]
+struct BestMoveResponse {
+    search_id: u64,
+    color: Color,
+    results: SearchResults,
+    move_from_whites_perspective: PieceMove,
+
+    /// Whether the move was played from the opening book rather than searched for.
+    from_book: bool,
+}
+
+/// Emits `color`'s move from the opening book as the result of a search. The evaluation is
+/// left as it was, since nothing was searched.
+fn emit_book_move(
+    app: &tauri::AppHandle,
+    session_id: u64,
+    search_id: u64,
+    color: Color,
+    book_move: PieceMove,
+    stop: &AtomicBool,
+) {
+    with_session(app, session_id, |gs| gs.end_search(search_id));
+
+    // Cancelled before it was sent
+    if stop.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let results = SearchResults {
+        best_move: Some(book_move),
+        score: 0,
+        nodes_searched: 0,
+        cached_positions: 0,
+        depth: 0,
+        time_taken_ms: 0,
+        pruned: 0,
+        principal_variation: Some(vec![book_move]),
+        alpha: 0,
+        beta: 0,
+        statistics: Default::default(),
+    };
+
+    let move_from_whites_perspective = match color {
+        Color::White => book_move,
+        Color::Black => book_move.inverted(),
+    };
+
+    emit_for(
+        app,
+        session_id,
+        "best_move",
+        BestMoveResponse {
+            search_id,
+            color,
+            results,
+            move_from_whites_perspective,
+            from_book: true,
+        },
+    );
+}
+
+#[derive(Clone, Serialize)]
+struct SearchStoppedResponse {
+    search_id: u64,
+    color: Color,
+    reason: String,
+}
+
+/// An engine search for one side's move, with everything it needs taken from the global state
+/// so it can run on another thread.
+struct EngineSearch {
+    /// The position from the perspective of the side searched for.
+    position: Position,
+    history: Vec<u64>,
+    transposition_table: Arc<Mutex<TranspositionTable>>,
+    search_memory: Arc<Mutex<SearchMemory>>,
+    ply: usize,
+    params: SearchParams,
+    strength_limit: Option<StrengthLimit>,
+}
+
+impl EngineSearch {
+    /// A search for `color`'s move in the current position, played with `settings`.
+    /// With a clock, the engine doesn't think for longer than its remaining time allows.
+    fn new(gs: &GlobalStateData, settings: &EngineSettings, color: Color) -> Result<Self, String> {
+        let mut params = settings
+            .search_params(GAME_TYPE)
+            .map_err(|e| e.to_string())?;
+        params.algorithm = settings.search_algorithm;
+        if let Some(time_limit) = gs.engine_time_limit(color) {
+            params.time_limit = params.time_limit.min(time_limit);
+        }
+
+        Ok(EngineSearch {
+            position: match color {
+                Color::White => gs.position.clone(),
+                Color::Black => gs.position.inverted(),
+            },
+            history: gs.history.clone(),
+            transposition_table: gs.transposition_table.clone(),
+            search_memory: gs.search_memory.clone(),
+            ply: gs.ply(),
+            params,
+            strength_limit: settings.strength_limit,
+        })
+    }
+
+    /// Runs the search until it finishes or `stop` is set, carrying what it learned over to
+    /// the next search of the game.
+    fn run(self, stop: Arc<AtomicBool>) -> Result<SearchResults, AlphaBetaError> {
+        let mut transposition_table = self.transposition_table.lock().unwrap();
+        transposition_table.new_search();
+
+        let mut search_memory = self.search_memory.lock().unwrap();
+        search_memory.advance_to(self.ply);
+
+        let mut state = SearchState::with_memory(
+            &mut transposition_table,
+            std::mem::take(&mut *search_memory),
+        );
+        state.data.history = self.history;
+        state.data.stop = stop;
+
+        let results = match self.strength_limit {
+            Some(strength_limit) => strength_limit.search(&self.position, &mut state, self.params),
+            None => search_within_time_limit(&self.position, &mut state, self.params),
+        };
+        *search_memory = state.into_memory(self.ply);
+
+        if let Ok(results) = &results {
+            search_memory.previous_pv = results.principal_variation.clone();
+        }
+
+        results
+    }
+}
+
+/// Searches to the depth in `params`. With a time limit the search deepens one ply at a time,
+/// returning the deepest search that finished in time.
+fn search_within_time_limit(
+    position: &Position,
+    state: &mut SearchState,
+    params: SearchParams,
+) -> Result<SearchResults, AlphaBetaError> {
+    if params.time_limit == u64::MAX {
+        return backend::search(position, state, params, 0);
+    }
+
+    state.data.start_time = Instant::now();
+    state.data.time_limit = params.time_limit;
+
+    let mut best: Option<SearchResults> = None;
+
+    for depth in 1..=params.depth {
+        let mut iteration_params = params.clone();
+        iteration_params.depth = depth;
+        iteration_params.previous_score = best.as_ref().map(|results| results.score);
+
+        match backend::search(position, state, iteration_params, 0) {
+            Ok(results) => {
+                state.data.previous_pv = results.principal_variation.clone();
+                best = Some(results);
+            }
+            Err(AlphaBetaError::Timeout) if best.is_some() => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    best.ok_or(AlphaBetaError::Timeout)
+}
+
+/// Starts the engine searching for `color`'s move on a background thread and returns the
+/// search's ID straight away. The result is emitted as a `best_move` event along with an
+/// `evaluation` event, or as a `search_stopped` event if the search is cancelled or fails.
+fn start_search(
+    state: &State<GlobalState>,
+    app: tauri::AppHandle,
+    color: Color,
+) -> Result<u64, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+    ensure_not_autoplaying(&gs)?;
+
+    let search = EngineSearch::new(&gs, &gs.settings, color)?;
+    let ply = search.ply;
+
+    let book_move = match &mut gs.book {
+        Some(book) => book
+            .next_move(&search.position, ply, GAME_TYPE, &mut rand::thread_rng())
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+
+    let session_id = gs.session_id;
+    let (search_id, stop) = gs.begin_search();
+
+    // The move is emitted from another thread all the same, so the frontend has the search's
+    // ID before it arrives
+    if let Some(book_move) = book_move {
+        println!("Playing book move {} (search {})", book_move, search_id);
+        thread::spawn(move || emit_book_move(&app, session_id, search_id, color, book_move, &stop));
+        return Ok(search_id);
+    }
+
+    println!("Getting {:?} move (search {})", color, search_id);
+    println!(
+        "Position\n{}",
+        search.position.to_board_string_with_rank_file_holding()
+    );
+
+    thread::spawn(move || -> () {
+        let results = search.run(stop);
+
+        with_session(&app, session_id, |gs| {
+            gs.end_search(search_id);
+
+            if let Ok(results) = &results {
+                gs.last_engine_score = Some((color, results.score));
+            }
+        });
+
+        let results =
+            results
+                .map_err(|e| e.to_string())
+                .and_then(|results| match results.best_move {
+                    Some(best_move) => Ok((results, best_move)),
+                    None => Err("No legal moves".to_string()),
+                });
+
+        match results {
+            Ok((results, best_move)) => {
+                emit_evaluation(&app, session_id, color, &results, ply);
+
+                let move_from_whites_perspective = match color {
+                    Color::White => best_move,
+                    Color::Black => best_move.inverted(),
+                };
+
+                emit_for(
+                    &app,
+                    session_id,
+                    "best_move",
+                    BestMoveResponse {
+                        search_id,
+                        color,
+                        results,
+                        move_from_whites_perspective,
+                        from_book: false,
+                    },
+                );
+            }
+            Err(reason) => {
+                eprintln!("Search {} for {:?} stopped: {}", search_id, color, reason);
+
+                emit_for(
+                    &app,
+                    session_id,
+                    "search_stopped",
+                    SearchStoppedResponse {
+                        search_id,
+                        color,
+                        reason,
+                    },
+                );
+            }
+        }
+    });
+
+    Ok(search_id)
+}
+
+/// The number of moves of each candidate's principal variation sent to the frontend.
+const CANDIDATE_PV_LENGTH: usize = 5;
+
+#[derive(Clone, Serialize)]
+struct CandidateMove {
+    move_from_whites_perspective: PieceMove,
+    notation: String,
+    evaluation: Score,
+
+    /// The start of the line the engine expects, beginning with this move, from white's
+    /// perspective.
+    principal_variation: Vec<PieceMove>,
+    /// Why the move is good, as hints to show the player, e.g. "Creates a passed pawn".
+    reasons: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct CandidateMovesResponse {
+    search_id: u64,
+    color: Color,
+
+    /// The best moves scored so far, best first.
+    candidates: Vec<CandidateMove>,
+
+    /// How many of the legal moves have been scored.
+    scored: usize,
+    total: usize,
+    done: bool,
+}
+
+impl CandidateMove {
+    /// The candidate for a move scored in `position`, from the perspective of its side to
+    /// move.
+    fn from_move_score(
+        move_score: &MoveScore,
+        position: &Position,
+        color: Color,
+        notation: PieceNotation,
+    ) -> Self {
+        // Moves in the principal variation alternate between each side's perspective
+        let line = std::iter::once(move_score.mv)
+            .chain(move_score.principal_variation.iter().flatten().copied())
+            .take(CANDIDATE_PV_LENGTH)
+            .enumerate()
+            .map(|(i, mv)| {
+                let mover = if i % 2 == 0 { color } else { color.invert() };
+                match mover {
+                    Color::White => mv,
+                    Color::Black => mv.inverted(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let move_from_whites_perspective = line[0];
+
+        CandidateMove {
+            move_from_whites_perspective,
+            notation: move_score
+                .mv
+                .to_san_with_notation(position, notation)
+                .unwrap_or_else(|_| move_from_whites_perspective.to_notation(notation)),
+            evaluation: white_score(move_score.score, color),
+            principal_variation: line,
+            reasons: explain(position, move_score.mv, GAME_TYPE)
+                .map(|reasons| reasons.iter().map(|reason| reason.to_string()).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The best `count` of the scores, best first.
+fn top_candidates(
+    scores: &[MoveScore],
+    count: usize,
+    position: &Position,
+    color: Color,
+    notation: PieceNotation,
+) -> Vec<CandidateMove> {
+    let mut scores = scores.iter().collect::<Vec<_>>();
+    scores.sort_by(|a, b| b.score.cmp(&a.score));
+
+    scores
+        .into_iter()
+        .take(count)
+        .map(|move_score| CandidateMove::from_move_score(move_score, position, color, notation))
+        .collect()
+}
+
+/// Scores every move for the side to move on a background thread, returning the search ID
+/// straight away. A `candidate_moves` event with the best `count` moves so far is emitted as
+/// each move is scored, and a final one with `done` set once they all have been. If the search
+/// is cancelled or fails a `search_stopped` event is emitted instead.
+#[command]
+pub fn get_top_moves(
+    count: usize,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<u64, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let color = gs.to_move;
+    let position = gs.position_for_side_to_move();
+    let history = gs.history.clone();
+    let transposition_table = gs.transposition_table.clone();
+    let notation = gs.notation;
+    let session_id = gs.session_id;
+
+    let mut legal_moves = MoveList::new();
+    position
+        .get_all_legal_moves(GAME_TYPE, &mut legal_moves)
+        .map_err(|e| e.to_string())?;
+    let total = legal_moves.len();
+
+    // Every move is scored to the full depth, so the time limit doesn't apply here
+    let params = gs
+        .settings
+        .search_params(GAME_TYPE)
+        .map_err(|e| e.to_string())?;
+
+    let (search_id, stop) = gs.begin_search();
+
+    thread::spawn(move || {
+        let scored = Arc::new(Mutex::new(Vec::<MoveScore>::new()));
+
+        let on_move_scored = {
+            let app = app.clone();
+            let scored = scored.clone();
+            let position = position.clone();
+
+            move |move_score: &MoveScore| {
+                let mut scored = scored.lock().unwrap();
+                scored.push(move_score.clone());
+
+                emit_for(
+                    &app,
+                    session_id,
+                    "candidate_moves",
+                    CandidateMovesResponse {
+                        search_id,
+                        color,
+                        candidates: top_candidates(&scored, count, &position, color, notation),
+                        scored: scored.len(),
+                        total,
+                        done: false,
+                    },
+                );
+            }
+        };
+
+        let mut transposition_table = transposition_table.lock().unwrap();
+        transposition_table.new_search();
+
+        let mut state = SearchState::new(&mut transposition_table);
+        state.data.history = history;
+        state.data.stop = stop;
+        state.callbacks.on_move_scored = Some(&on_move_scored);
+
+        let results = backend::score_all_moves(&position, &mut state, params, 0);
+
+        with_session(&app, session_id, |gs| gs.end_search(search_id));
+
+        match results {
+            Ok(scores) => {
+                emit_for(
+                    &app,
+                    session_id,
+                    "candidate_moves",
+                    CandidateMovesResponse {
+                        search_id,
+                        color,
+                        candidates: top_candidates(&scores, count, &position, color, notation),
+                        scored: scores.len(),
+                        total,
+                        done: true,
+                    },
+                );
+            }
+            Err(e) => {
+                emit_for(
+                    &app,
+                    session_id,
+                    "search_stopped",
+                    SearchStoppedResponse {
+                        search_id,
+                        color,
+                        reason: e.to_string(),
+                    },
+                );
+            }
+        }
+    });
+
+    Ok(search_id)
+}
+
+/// Starts the engine searching for the side to move, whichever color that is.
+#[command]
+pub fn get_engine_move(state: State<GlobalState>, app: tauri::AppHandle) -> Result<u64, String> {
+    let to_move = state.active().lock().unwrap().to_move;
+
+    start_search(&state, app, to_move)
+}
+
+#[command]
+pub fn get_black_move(state: State<GlobalState>, app: tauri::AppHandle) -> Result<u64, String> {
+    start_search(&state, app, Color::Black)
+}
+
+#[command]
+pub fn get_white_move(state: State<GlobalState>, app: tauri::AppHandle) -> Result<u64, String> {
+    start_search(&state, app, Color::White)
+}
+
+#[command]
+pub fn set_engine_color(engine_color: EngineColor, state: State<GlobalState>) {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    gs.engine_color = engine_color;
+}
+
+#[command]
+pub fn get_engine_color(state: State<GlobalState>) -> EngineColor {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    gs.engine_color
+}
+
+/// Whether the engine plays the side to move, so the frontend should ask it for a move. Never
+/// true while the engines are playing each other, as they make their own moves.
+#[command]
+pub fn is_engine_to_move(state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    gs.autoplay.is_none() && gs.engine_color.plays(gs.to_move)
+}
+
+/// Stops the search with the given ID, or every running search if no ID is given. Returns
+/// whether any search was running.
+#[command]
+pub fn cancel_search(search_id: Option<u64>, state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    gs.cancel_search(search_id)
+}
+
+#[derive(Clone, Serialize)]
+struct AutoplayMoveResponse {
+    autoplay_id: u64,
+    color: Color,
+    results: SearchResults,
+    move_from_whites_perspective: PieceMove,
+}
+
+#[derive(Clone, Serialize)]
+struct AutoplayStoppedResponse {
+    autoplay_id: u64,
+    reason: String,
+}
+
+/// How often a waiting engine-vs-engine game checks whether it has been stopped.
+const AUTOPLAY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Has the engine play itself from the current position, with `white` and `black` applied to
+/// the current settings for each side, waiting `move_delay_ms` before each move so the game
+/// can be followed. Returns the game's ID straight away.
+///
+/// Each move is played and emitted as an `autoplay_move` event, along with the usual
+/// `move_played` and `evaluation` events. An `autoplay_stopped` event is emitted when the game
+/// ends, when `stop_autoplay` is called, or when the position changes some other way. The
+/// opening book isn't used, so the two configurations are compared on their search alone.
+#[command]
+pub fn start_autoplay(
+    white: EngineOptions,
+    black: EngineOptions,
+    move_delay_ms: u64,
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<u64, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let white = gs
+        .settings
+        .with_options(&white)
+        .map_err(|e| e.to_string())?;
+    let black = gs
+        .settings
+        .with_options(&black)
+        .map_err(|e| e.to_string())?;
+
+    gs.cancel_search(None);
+    let session_id = gs.session_id;
+    let (autoplay_id, stop) = gs.begin_autoplay();
+
+    println!("Starting autoplay {}", autoplay_id);
+
+    let move_delay = Duration::from_millis(move_delay_ms);
+    thread::spawn(move || {
+        let reason = play_autoplay_game(
+            &app,
+            session_id,
+            autoplay_id,
+            &stop,
+            &white,
+            &black,
+            move_delay,
+        );
+
+        println!("Autoplay {} stopped: {}", autoplay_id, reason);
+
+        with_session(&app, session_id, |gs| gs.end_autoplay(autoplay_id));
+
+        emit_for(
+            &app,
+            session_id,
+            "autoplay_stopped",
+            AutoplayStoppedResponse {
+                autoplay_id,
+                reason,
+            },
+        );
+    });
+
+    Ok(autoplay_id)
+}
+
+/// Stops the engine-vs-engine game, returning whether one was running. The move being searched
+/// for isn't played.
+#[command]
+pub fn stop_autoplay(state: State<GlobalState>) -> bool {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    let running = gs.stop_autoplay();
+    gs.cancel_search(None);
+
+    running
+}
+
+/// Plays engine moves until the game ends or `stop` is set, returning why it stopped.
+fn play_autoplay_game(
+    app: &tauri::AppHandle,
+    session_id: u64,
+    autoplay_id: u64,
+    stop: &AtomicBool,
+    white: &EngineSettings,
+    black: &EngineSettings,
+    move_delay: Duration,
+) -> String {
+    let Some(session) = app.state::<GlobalState>().session(session_id) else {
+        return "The session was closed".to_string();
+    };
+
+    loop {
+        let started = Instant::now();
+        while started.elapsed() < move_delay {
+            if stop.load(Ordering::Relaxed) {
+                return "Stopped".to_string();
+            }
+            thread::sleep(AUTOPLAY_POLL_INTERVAL.min(move_delay.saturating_sub(started.elapsed())));
+        }
+
+        let (color, search, search_id, search_stop) = {
+            let mut gs = session.lock().unwrap();
+            if stop.load(Ordering::Relaxed) {
+                return "Stopped".to_string();
+            }
+
+            let color = gs.to_move;
+            let settings = match color {
+                Color::White => white,
+                Color::Black => black,
+            };
+
+            let search = match EngineSearch::new(&gs, settings, color) {
+                Ok(search) => search,
+                Err(reason) => return reason,
+            };
+            let (search_id, search_stop) = gs.begin_search();
+
+            (color, search, search_id, search_stop)
+        };
+
+        let ply = search.ply;
+        let results = search.run(search_stop.clone());
+
+        let mut gs = session.lock().unwrap();
+        gs.end_search(search_id);
+
+        // The position may have changed while searching
+        if stop.load(Ordering::Relaxed) || search_stop.load(Ordering::Relaxed) {
+            return "Stopped".to_string();
+        }
+
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => return e.to_string(),
+        };
+        let Some(best_move) = results.best_move else {
+            return "No legal moves".to_string();
+        };
+
+        if let Some(status) = flag_if_out_of_time(&mut gs, app) {
+            return status.to_string();
+        }
+        if let Err(e) = gs.play_move(color, best_move) {
+            return e.to_string();
+        }
+        gs.last_engine_score = Some((color, results.score));
+
+        let move_from_whites_perspective = match color {
+            Color::White => best_move,
+            Color::Black => best_move.inverted(),
+        };
+
+        emit_evaluation(app, session_id, color, &results, ply);
+        emit_for(
+            app,
+            gs.session_id,
+            "autoplay_move",
+            AutoplayMoveResponse {
+                autoplay_id,
+                color,
+                results,
+                move_from_whites_perspective,
+            },
+        );
+        emit_for(app, gs.session_id, "move_played", MoveListPosition::of(&gs));
+
+        let status = match gs.game_status(GAME_TYPE) {
+            Ok(status) => status,
+            Err(e) => return e.to_string(),
+        };
+        if status.is_game_over() {
+            end_game(&mut gs, app, status);
+            return status.to_string();
+        }
+    }
+}
+
+/// The state of the clocks, for showing them.
+#[derive(Clone, Serialize)]
+pub struct ClockResponse {
+    /// The time control, e.g. "5+3", or "1+0:10+0" with different clocks for each side.
+    time_control: String,
+
+    white_ms: u64,
+    black_ms: u64,
+
+    /// Whether the side to move's clock is running.
+    running: bool,
+    to_move: Color,
+}
+
+impl ClockResponse {
+    fn of(gs: &GlobalStateData) -> Option<Self> {
+        gs.clock.as_ref().map(|clock| ClockResponse {
+            time_control: clock.clock.time_control.to_string(),
+            white_ms: clock.remaining(Color::White, gs.to_move),
+            black_ms: clock.remaining(Color::Black, gs.to_move),
+            running: clock.is_running(),
+            to_move: gs.to_move,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct FlagFellResponse {
+    /// The side that ran out of time.
+    color: Color,
+}
+
+/// How often the running clock is checked for the side to move running out of time.
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Plays the game with a clock, given as e.g. "5+3" for five minutes plus three seconds a
+/// move, or "1+0:10+0" for different clocks for white and black. `None` plays without a clock.
+/// The clocks are reset and stay stopped until `start_clock` is called.
+#[command]
+pub fn set_time_control(
+    time_control: Option<String>,
+    state: State<GlobalState>,
+) -> Result<Option<ClockResponse>, String> {
+    let time_control = time_control
+        .map(|time_control| time_control.parse::<TimeControl>())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    gs.set_time_control(time_control);
+
+    Ok(ClockResponse::of(&gs))
+}
+
+/// Starts the side to move's clock. A `flag_fell` event is emitted if it runs out of time,
+/// followed by `game_over`.
+#[command]
+pub fn start_clock(
+    state: State<GlobalState>,
+    app: tauri::AppHandle,
+) -> Result<ClockResponse, String> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+    ensure_game_in_progress(&gs)?;
+
+    let Some(clock) = &mut gs.clock else {
+        return Err("The game isn't played with a clock".to_string());
+    };
+    clock.start();
+
+    if !gs.clock_watched {
+        gs.clock_watched = true;
+        let session_id = gs.session_id;
+        thread::spawn(move || watch_clock(&app, session_id));
+    }
+
+    Ok(ClockResponse::of(&gs).unwrap())
+}
+
+/// Stops the clocks until `start_clock` is called again.
+#[command]
+pub fn pause_clock(state: State<GlobalState>) -> Option<ClockResponse> {
+    let session = state.active();
+    let mut gs = session.lock().unwrap();
+
+    if let Some(clock) = &mut gs.clock {
+        clock.stop();
+    }
+
+    ClockResponse::of(&gs)
+}
+
+#[command]
+pub fn get_clock(state: State<GlobalState>) -> Option<ClockResponse> {
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    ClockResponse::of(&gs)
+}
+
+/// Ends the game if the side to move has run out of time, returning how it ended.
+fn flag_if_out_of_time(gs: &mut GlobalStateData, app: &tauri::AppHandle) -> Option<GameStatus> {
+    let to_move = gs.to_move;
+    let clock = gs.clock.as_mut()?;
+
+    if gs.game_over.is_some() || clock.remaining(to_move, to_move) > 0 {
+        return None;
+    }
+
+    clock.clock.flagged = Some(to_move);
+    emit_for(
+        app,
+        gs.session_id,
+        "flag_fell",
+        FlagFellResponse { color: to_move },
+    );
+
+    let status = GameStatus::Timeout(to_move.invert());
+    end_game(gs, app, status);
+
+    Some(status)
+}
+
+/// Waits for the side to move to run out of time, until the clocks are stopped.
+fn watch_clock(app: &tauri::AppHandle, session_id: u64) {
+    let Some(session) = app.state::<GlobalState>().session(session_id) else {
+        return;
+    };
+
+    loop {
+        thread::sleep(CLOCK_POLL_INTERVAL);
+
+        let mut gs = session.lock().unwrap();
+        let running = gs.clock.as_ref().is_some_and(|clock| clock.is_running());
+
+        if !running || flag_if_out_of_time(&mut gs, app).is_some() {
+            gs.clock_watched = false;
+            return;
+        }
+    }
+}
+
+/// A game session, for showing the open sessions as tabs.
+#[derive(Clone, Serialize)]
+pub struct SessionInfo {
+    session_id: u64,
+
+    /// Whether this is the session the commands act on.
+    active: bool,
+
+    /// The number of moves played to reach the current position.
+    ply: usize,
+    to_move: Color,
+    game_over: Option<GameStatus>,
+
+    /// Whether the engines are playing each other in the session.
+    autoplaying: bool,
+}
+
+impl SessionInfo {
+    fn of(gs: &GlobalStateData, active: bool) -> Self {
+        SessionInfo {
+            session_id: gs.session_id,
+            active,
+            ply: gs.ply(),
+            to_move: gs.to_move,
+            game_over: gs.game_over,
+            autoplaying: gs.autoplay.is_some(),
+        }
+    }
+}
+
+/// Every open game session, in the order they were opened.
+#[command]
+pub fn list_sessions(state: State<GlobalState>) -> Vec<SessionInfo> {
+    let active = state.active_id();
+
+    state
+        .sessions()
+        .iter()
+        .map(|(session_id, session)| {
+            SessionInfo::of(&session.lock().unwrap(), *session_id == active)
+        })
+        .collect()
+}
+
+/// Opens a session with a new game from the starting position, using the active session's
+/// engine settings. It isn't switched to.
+#[command]
+pub fn create_session(state: State<GlobalState>) -> Result<SessionInfo, String> {
+    let session_id = state.create_session().map_err(|e| e.to_string())?;
+    let session = state.session(session_id).unwrap();
+    let gs = session.lock().unwrap();
+
+    Ok(SessionInfo::of(&gs, false))
+}
+
+/// Makes the commands act on another session, after which the frontend loads its game. The
+/// engine searches and analysis of the session switched away from are stopped, while an
+/// engine-vs-engine game and the clocks carry on in the background.
+#[command]
+pub fn switch_session(session_id: u64, state: State<GlobalState>) -> Result<SessionInfo, String> {
+    state
+        .switch_session(session_id)
+        .map_err(|e| e.to_string())?;
+
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    Ok(SessionInfo::of(&gs, true))
+}
+
+/// Closes a session, stopping everything running in it, and returns the active session. If the
+/// closed session was active, the first remaining session is switched to.
+#[command]
+pub fn close_session(session_id: u64, state: State<GlobalState>) -> Result<SessionInfo, String> {
+    state.close_session(session_id).map_err(|e| e.to_string())?;
+
+    let session = state.active();
+    let gs = session.lock().unwrap();
+
+    Ok(SessionInfo::of(&gs, true))
 }
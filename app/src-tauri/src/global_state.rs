@@ -1,44 +1,778 @@
 use std::{
-    ops::Deref,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
 };
 
-use rescue_chess::{search::transposition_table::TranspositionTable, Position};
+use serde::{Deserialize, Serialize};
 
-pub struct GlobalState(pub Arc<Mutex<GlobalStateData>>);
+use crate::{commands::GAME_TYPE, settings::EngineSettings};
+
+use rescue_chess::{
+    game::Game,
+    opening_book::{BookPlayer, OpeningBook},
+    opening_explorer::OpeningTree,
+    piece_move::{GameType, PieceNotation},
+    position::{repetition_count, GameStatus},
+    search::{
+        search_results::SearchMemory,
+        time_control::{time_for_move, GameClock, TimeControl},
+        transposition_table::TranspositionTable,
+    },
+    Color, PieceMove, Position,
+};
+
+/// The engine accepts a draw offer when its last evaluation, from its own point of view,
+/// is no better than this.
+const DRAW_ACCEPT_SCORE: i32 = 0;
+
+/// Which side(s) the engine plays. With `None` the engine only moves when asked, for analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EngineColor {
+    White,
+    Black,
+    Both,
+    None,
+}
+
+impl EngineColor {
+    pub fn plays(&self, color: Color) -> bool {
+        match self {
+            EngineColor::White => color == Color::White,
+            EngineColor::Black => color == Color::Black,
+            EngineColor::Both => true,
+            EngineColor::None => false,
+        }
+    }
+}
+
+/// A move made in the game, from the perspective of the side that made it.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlayedMove {
+    pub color: Color,
+    pub mv: PieceMove,
+
+    /// How long the move took, when the game is played with a clock.
+    pub elapsed_ms: Option<u64>,
+}
+
+impl PlayedMove {
+    /// The move from white's perspective, as the board is shown.
+    pub fn from_whites_perspective(&self) -> PieceMove {
+        match self.color {
+            Color::White => self.mv,
+            Color::Black => self.mv.inverted(),
+        }
+    }
+}
+
+/// The clocks of a game played with a time control. Only the side to move's clock runs, and
+/// only once the clocks have been started.
+#[derive(Debug, Clone)]
+pub struct LiveClock {
+    pub clock: GameClock,
+
+    /// When the side to move's clock was last started, or `None` while the clocks are stopped.
+    running_since: Option<Instant>,
+
+    /// The time the side to move spent on its move before its clock was last stopped.
+    used_ms: u64,
+}
+
+impl LiveClock {
+    /// Stopped clocks at `clock`'s times.
+    pub fn new(clock: GameClock) -> Self {
+        LiveClock {
+            clock,
+            running_since: None,
+            used_ms: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /// The time the side to move has spent on its move so far.
+    pub fn used_ms(&self) -> u64 {
+        self.used_ms
+            + self
+                .running_since
+                .map_or(0, |since| since.elapsed().as_millis() as u64)
+    }
+
+    /// The time left on `color`'s clock, while it's `to_move`'s move.
+    pub fn remaining(&self, color: Color, to_move: Color) -> u64 {
+        match color == to_move {
+            true => self.clock.remaining(color).saturating_sub(self.used_ms()),
+            false => self.clock.remaining(color),
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.used_ms = self.used_ms();
+        self.running_since = None;
+    }
+
+    /// Takes the time `color` spent on its move off its clock, adds its increment and starts
+    /// the other side's clock if the clocks are running. Returns the time spent.
+    fn record_move(&mut self, color: Color) -> Result<u64, anyhow::Error> {
+        let elapsed_ms = self.used_ms();
+        if !self.clock.record_move(color, elapsed_ms) {
+            return Err(anyhow::anyhow!("{:?} ran out of time", color));
+        }
+
+        self.used_ms = 0;
+        if self.is_running() {
+            self.running_since = Some(Instant::now());
+        }
+
+        Ok(elapsed_ms)
+    }
+}
+
+/// A game session: one game with its own engine, searches and clocks. Background work started
+/// in a session looks it up by ID, so it keeps to its own game whichever session is active.
+pub type Session = Arc<Mutex<GlobalStateData>>;
+
+struct Sessions {
+    sessions: BTreeMap<u64, Session>,
+
+    /// The session the commands act on.
+    active: u64,
+    next_id: u64,
+}
+
+/// Every open game session, so several games can be played at once without affecting each
+/// other, and what's shared between them. Commands act on the active session.
+///
+/// A session is never locked while the sessions are, so the sessions can be looked up from
+/// anywhere, including with a session locked.
+pub struct GlobalState {
+    sessions: Mutex<Sessions>,
+
+    /// The games indexed from the opening database folder, once one has been loaded.
+    pub opening_tree: Mutex<Option<OpeningTree>>,
+}
 
 impl Default for GlobalState {
     fn default() -> Self {
-        GlobalState(Arc::new(Mutex::new(GlobalStateData::default())))
+        GlobalState {
+            sessions: Mutex::new(Sessions {
+                sessions: BTreeMap::from([(1, Arc::new(Mutex::new(GlobalStateData::new(1))))]),
+                active: 1,
+                next_id: 2,
+            }),
+            opening_tree: Mutex::new(None),
+        }
     }
 }
 
-impl Deref for GlobalState {
-    type Target = Arc<Mutex<GlobalStateData>>;
+impl GlobalState {
+    /// The session the commands act on.
+    pub fn active(&self) -> Session {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.sessions[&sessions.active].clone()
+    }
+
+    pub fn active_id(&self) -> u64 {
+        self.sessions.lock().unwrap().active
+    }
 
-    fn deref(&self) -> &Arc<Mutex<GlobalStateData>> {
-        &self.0
+    pub fn is_active(&self, session_id: u64) -> bool {
+        self.active_id() == session_id
+    }
+
+    /// The session with the given ID, or `None` if it has been closed.
+    pub fn session(&self, session_id: u64) -> Option<Session> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .sessions
+            .get(&session_id)
+            .cloned()
+    }
+
+    /// Every open session, by ID.
+    pub fn sessions(&self) -> Vec<(u64, Session)> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .sessions
+            .iter()
+            .map(|(&id, session)| (id, session.clone()))
+            .collect()
+    }
+
+    /// Opens a session with a new game, using the active session's engine settings, and returns
+    /// its ID. It isn't switched to.
+    pub fn create_session(&self) -> Result<u64, anyhow::Error> {
+        let settings = self.active().lock().unwrap().settings.clone();
+
+        let session_id = {
+            let mut sessions = self.sessions.lock().unwrap();
+            sessions.next_id += 1;
+            sessions.next_id - 1
+        };
+
+        // Loading the opening book can take a while, so it's done without holding the sessions
+        let mut data = GlobalStateData::new(session_id);
+        data.apply_settings(settings)?;
+
+        self.sessions
+            .lock()
+            .unwrap()
+            .sessions
+            .insert(session_id, Arc::new(Mutex::new(data)));
+
+        Ok(session_id)
+    }
+
+    /// Makes the session with the given ID the one the commands act on. The session switched
+    /// away from is suspended.
+    pub fn switch_session(&self, session_id: u64) -> Result<(), anyhow::Error> {
+        let previous = {
+            let mut sessions = self.sessions.lock().unwrap();
+            if !sessions.sessions.contains_key(&session_id) {
+                return Err(anyhow::anyhow!("There's no session {}", session_id));
+            }
+            if sessions.active == session_id {
+                return Ok(());
+            }
+
+            let previous = sessions.sessions[&sessions.active].clone();
+            sessions.active = session_id;
+            previous
+        };
+
+        previous.lock().unwrap().suspend();
+
+        Ok(())
+    }
+
+    /// Closes the session with the given ID, stopping everything running in it. If it was the
+    /// active session the first remaining session becomes active. The last session can't be
+    /// closed.
+    pub fn close_session(&self, session_id: u64) -> Result<(), anyhow::Error> {
+        let closed = {
+            let mut sessions = self.sessions.lock().unwrap();
+            if sessions.sessions.len() == 1 {
+                return Err(anyhow::anyhow!("The last session can't be closed"));
+            }
+
+            let closed = sessions
+                .sessions
+                .remove(&session_id)
+                .ok_or_else(|| anyhow::anyhow!("There's no session {}", session_id))?;
+
+            if sessions.active == session_id {
+                sessions.active = *sessions.sessions.keys().next().unwrap();
+            }
+
+            closed
+        };
+
+        let mut gs = closed.lock().unwrap();
+        gs.cancel_search(None);
+        gs.stop_analysis();
+        if let Some(clock) = &mut gs.clock {
+            clock.stop();
+        }
+
+        Ok(())
     }
 }
 
 pub struct GlobalStateData {
+    pub session_id: u64,
+
+    /// The position the game started from, from the perspective of its side to move.
+    pub start_position: Position,
+
     pub position: Position,
-    pub depth: u32,
+
+    /// The engine's settings, loaded from the app's data directory on startup.
+    pub settings: EngineSettings,
+
     pub transposition_table: Arc<Mutex<TranspositionTable>>,
+
+    /// Killer moves, history scores and the principal variation carried over between the
+    /// engine's searches in this game.
+    pub search_memory: Arc<Mutex<SearchMemory>>,
+
+    /// The side to move. The position is always kept from white's perspective.
+    pub to_move: Color,
+
+    /// The side(s) the engine plays.
+    pub engine_color: EngineColor,
+
+    /// How the game ended, once it's over.
+    pub game_over: Option<GameStatus>,
+
+    /// The color that has offered a draw which hasn't been answered yet.
+    pub draw_offer: Option<Color>,
+
+    /// The score of the engine's last search and the color it searched for.
+    pub last_engine_score: Option<(Color, i32)>,
+
+    /// The depth of the quick search run after each human move to update the evaluation bar,
+    /// or `None` to only evaluate when the engine searches.
+    pub quick_evaluation_depth: Option<u32>,
+
+    /// The Zobrist keys of every position up to and including the current position.
+    pub history: Vec<u64>,
+
+    /// Every move of the game. When browsing back through the game this continues past the
+    /// current position, until a different move is played.
+    pub moves: Vec<PlayedMove>,
+
+    /// How moves are written for display, e.g. in the move list.
+    pub notation: PieceNotation,
+
+    /// The stop flags of the engine searches that are still running, by search ID.
+    pub searches: HashMap<u64, Arc<AtomicBool>>,
+    pub next_search_id: u64,
+
+    /// The ID and stop flag of the background analysis of the current position, if one is
+    /// running. It's stopped whenever the position changes.
+    pub analysis: Option<(u64, Arc<AtomicBool>)>,
+
+    /// The ID and stop flag of the engine-vs-engine game being played, if one is running.
+    pub autoplay: Option<(u64, Arc<AtomicBool>)>,
+
+    /// The opening book the engine plays from, when the settings turn it on.
+    pub book: Option<BookPlayer>,
+
+    /// The clocks, when the game is played with a time control.
+    pub clock: Option<LiveClock>,
+
+    /// Whether a thread is watching the running clock for the side to move running out of time.
+    pub clock_watched: bool,
 }
 
-impl Default for GlobalStateData {
-    fn default() -> Self {
+impl GlobalStateData {
+    pub fn new(session_id: u64) -> Self {
+        let position = Position::start_position();
+        let history = vec![position.zobrist_key()];
+        let settings = EngineSettings::default();
+
         GlobalStateData {
-            position: Position::start_position(),
-            depth: 5,
-            transposition_table: Arc::new(Mutex::new(TranspositionTable::new())),
+            session_id,
+            start_position: position.clone(),
+            position,
+            settings: settings.clone(),
+            transposition_table: Arc::new(Mutex::new(TranspositionTable::with_size_mb(
+                settings.transposition_table_mb,
+            ))),
+            search_memory: Arc::new(Mutex::new(SearchMemory::default())),
+            to_move: Color::White,
+            engine_color: EngineColor::Black,
+            game_over: None,
+            draw_offer: None,
+            last_engine_score: None,
+            quick_evaluation_depth: None,
+            history,
+            moves: Vec::new(),
+            notation: PieceNotation::default(),
+            searches: HashMap::new(),
+            next_search_id: 1,
+            analysis: None,
+            autoplay: None,
+            book: None,
+            clock: None,
+            clock_watched: false,
         }
     }
-}
 
-impl GlobalStateData {
     pub fn reset(&mut self) {
-        self.position = Position::start_position();
+        self.load_position(Position::start_position());
+    }
+
+    /// Starts a new game from `position`, which is from the perspective of its side to move.
+    pub fn load_position(&mut self, position: Position) {
+        self.start_position = position;
+        self.game_over = None;
+        self.draw_offer = None;
+        self.last_engine_score = None;
+        self.moves.clear();
+        self.search_memory = Arc::new(Mutex::new(SearchMemory::default()));
+        if let Some(book) = &mut self.book {
+            book.new_game();
+        }
+        if let Some(clock) = &self.clock {
+            self.set_time_control(Some(clock.clock.time_control));
+        }
+        self.cancel_search(None);
+        self.rewind();
+    }
+
+    /// Plays the game with `time_control`, or without a clock if `None`. Both clocks are reset
+    /// and stopped.
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.clock = time_control.map(|time_control| LiveClock::new(GameClock::new(time_control)));
+    }
+
+    /// How long the engine should think about `color`'s move given the time left on its clock,
+    /// or `None` if the game has no clock.
+    pub fn engine_time_limit(&self, color: Color) -> Option<u64> {
+        self.clock.as_ref().map(|clock| {
+            time_for_move(
+                clock.remaining(color, self.to_move),
+                clock.clock.time_control.clock(color).increment_ms,
+                None,
+            )
+        })
+    }
+
+    /// Goes back to the start position, keeping the moves of the game.
+    fn rewind(&mut self) {
+        self.stop_analysis();
+        self.to_move = self.start_position.true_active_color;
+        self.position = match self.to_move {
+            Color::White => self.start_position.clone(),
+            Color::Black => self.start_position.inverted(),
+        };
+        self.history = vec![self.start_position.zobrist_key()];
+    }
+
+    /// Switches to new engine settings, resizing the transposition table and loading the
+    /// opening book to match. Nothing changes if the book can't be loaded.
+    pub fn apply_settings(&mut self, settings: EngineSettings) -> Result<(), anyhow::Error> {
+        let book = match (&settings.book_path, &self.book) {
+            (Some(_), _) if !settings.own_book => None,
+            (None, _) => None,
+            // Keep playing from the same book, without forgetting whether the game left it
+            (Some(path), Some(book)) if self.settings.book_path.as_ref() == Some(path) => {
+                let mut book = book.clone();
+                book.variety = settings.book_variety;
+                Some(book)
+            }
+            (Some(path), _) => {
+                let book = OpeningBook::load(path)?;
+                if book.game_type != GAME_TYPE {
+                    return Err(anyhow::anyhow!(
+                        "{} is a book for {:?} games",
+                        path.display(),
+                        book.game_type
+                    ));
+                }
+
+                Some(BookPlayer::new(Arc::new(book), settings.book_variety))
+            }
+        };
+
+        if settings.transposition_table_mb != self.settings.transposition_table_mb {
+            self.transposition_table
+                .lock()
+                .unwrap()
+                .set_size_mb(settings.transposition_table_mb);
+        }
+
+        self.book = book;
+        self.settings = settings;
+
+        Ok(())
+    }
+
+    /// Registers a new search, returning its ID and the flag that stops it.
+    pub fn begin_search(&mut self) -> (u64, Arc<AtomicBool>) {
+        let search_id = self.next_search_id;
+        self.next_search_id += 1;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.searches.insert(search_id, stop.clone());
+
+        (search_id, stop)
+    }
+
+    pub fn end_search(&mut self, search_id: u64) {
+        self.searches.remove(&search_id);
+    }
+
+    /// Stops the search with the given ID, or all searches and any autoplay game if `None`.
+    /// Returns whether any search was stopped.
+    pub fn cancel_search(&mut self, search_id: Option<u64>) -> bool {
+        let stopped: Vec<Arc<AtomicBool>> = match search_id {
+            Some(search_id) => self.searches.remove(&search_id).into_iter().collect(),
+            None => {
+                self.stop_autoplay();
+                self.searches.drain().map(|(_, stop)| stop).collect()
+            }
+        };
+
+        for stop in &stopped {
+            stop.store(true, Ordering::Relaxed);
+        }
+
+        !stopped.is_empty()
+    }
+
+    /// Registers a new background analysis, stopping the one running, if any. Returns its ID
+    /// and the flag that stops it.
+    pub fn begin_analysis(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.stop_analysis();
+
+        let analysis_id = self.next_search_id;
+        self.next_search_id += 1;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.analysis = Some((analysis_id, stop.clone()));
+
+        (analysis_id, stop)
+    }
+
+    /// Stops the background analysis, returning whether one was running.
+    pub fn stop_analysis(&mut self) -> bool {
+        match self.analysis.take() {
+            Some((_, stop)) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets the analysis with the given ID once it has finished, unless another one has
+    /// taken its place.
+    pub fn end_analysis(&mut self, analysis_id: u64) {
+        if matches!(self.analysis, Some((id, _)) if id == analysis_id) {
+            self.analysis = None;
+        }
+    }
+
+    /// Registers a new engine-vs-engine game, stopping any that was running, and returns its ID
+    /// and the flag that stops it.
+    pub fn begin_autoplay(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.stop_autoplay();
+
+        let autoplay_id = self.next_search_id;
+        self.next_search_id += 1;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.autoplay = Some((autoplay_id, stop.clone()));
+
+        (autoplay_id, stop)
+    }
+
+    /// Stops the engine-vs-engine game, returning whether one was running.
+    pub fn stop_autoplay(&mut self) -> bool {
+        match self.autoplay.take() {
+            Some((_, stop)) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets the engine-vs-engine game with the given ID once it has finished, unless another
+    /// one has taken its place.
+    pub fn end_autoplay(&mut self, autoplay_id: u64) {
+        if matches!(self.autoplay, Some((id, _)) if id == autoplay_id) {
+            self.autoplay = None;
+        }
+    }
+
+    /// Stops the searches and analysis run for the frontend when another session is switched
+    /// to, since it only follows the active session. They're started again when it's switched
+    /// back. An engine-vs-engine game and the clocks carry on.
+    pub fn suspend(&mut self) {
+        self.stop_analysis();
+        if self.autoplay.is_none() {
+            self.cancel_search(None);
+        }
+    }
+
+    /// The number of plies played so far in the game.
+    pub fn ply(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// The position from the perspective of the side to move.
+    pub fn position_for_side_to_move(&self) -> Position {
+        match self.to_move {
+            Color::White => self.position.clone(),
+            Color::Black => self.position.inverted(),
+        }
+    }
+
+    /// Plays `mv`, given from `color`'s perspective, and passes the turn to the other side. If
+    /// the game was being browsed, any moves after the current position are discarded. With a
+    /// clock the time the move took is taken off `color`'s clock, and the move isn't played if
+    /// it ran out of time.
+    pub fn play_move(&mut self, color: Color, mv: PieceMove) -> Result<(), anyhow::Error> {
+        let elapsed_ms = match &mut self.clock {
+            Some(clock) => Some(clock.record_move(color)?),
+            None => None,
+        };
+
+        self.apply_move(color, mv)?;
+
+        let ply = self.ply();
+        self.moves.truncate(ply - 1);
+        self.moves.push(PlayedMove {
+            color,
+            mv,
+            elapsed_ms,
+        });
+
+        Ok(())
+    }
+
+    fn apply_move(&mut self, color: Color, mv: PieceMove) -> Result<(), anyhow::Error> {
+        self.stop_analysis();
+
+        match color {
+            Color::White => {
+                self.position.apply_move(mv)?;
+            }
+            Color::Black => {
+                // Invert the position, apply the move, and invert back
+                let mut inverted_position = self.position.inverted();
+                inverted_position.apply_move(mv)?;
+                self.position = inverted_position.inverted();
+            }
+        }
+
+        self.to_move = color.invert();
+
+        let key = self.position_for_side_to_move().zobrist_key();
+        self.history.push(key);
+
+        Ok(())
+    }
+
+    /// Moves to the position after the first `ply` moves of the game, replaying them from the
+    /// start position. The moves after it are kept so the game can be stepped forward again.
+    pub fn goto_ply(&mut self, ply: usize) -> Result<(), anyhow::Error> {
+        if ply > self.moves.len() {
+            return Err(anyhow::anyhow!(
+                "Ply {} is past the end of the game ({} moves)",
+                ply,
+                self.moves.len()
+            ));
+        }
+
+        self.cancel_search(None);
+        self.draw_offer = None;
+
+        // Browsing back doesn't wind the clocks back, they just stop
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+
+        self.rewind();
+
+        let moves = self.moves[..ply].to_vec();
+        for played in moves {
+            self.apply_move(played.color, played.mv)?;
+        }
+
+        Ok(())
+    }
+
+    /// Every move of the game in SAN, written with the display notation, replayed from the
+    /// start position. Stops at the first move that can't be replayed.
+    pub fn move_notations(&self) -> Vec<String> {
+        let mut position = self.start_position.clone();
+        let mut notations = Vec::with_capacity(self.moves.len());
+
+        for played in &self.moves {
+            let Ok(notation) = played.mv.to_san_with_notation(&position, self.notation) else {
+                break;
+            };
+            if position.apply_move(played.mv).is_err() {
+                break;
+            }
+            position.invert();
+
+            notations.push(notation);
+        }
+
+        notations
+    }
+
+    /// The whole game as a `Game`, including any moves after the current position, to save it.
+    pub fn to_game(&self, game_type: GameType) -> Result<Game, anyhow::Error> {
+        let mut game = Game::from_position(self.start_position.clone(), game_type)?;
+        if let Some(clock) = &self.clock {
+            game = game.with_clock(clock.clock.time_control);
+        }
+
+        for played in &self.moves {
+            match (played.elapsed_ms, &self.clock) {
+                (Some(elapsed_ms), Some(_)) => game.make_timed_move(played.mv, elapsed_ms)?,
+                _ => game.make_move(played.mv)?,
+            };
+        }
+
+        // Resignations, agreed draws and running out of time waiting to move aren't in the
+        // moves
+        if !game.is_over() {
+            match self.game_over {
+                Some(GameStatus::Resignation(winner)) => game.resign(winner.invert())?,
+                Some(GameStatus::DrawByAgreement) => game.agree_draw()?,
+                Some(GameStatus::Timeout(winner)) => game.time_out(winner.invert())?,
+                _ => {}
+            }
+        }
+
+        Ok(game)
+    }
+
+    /// Starts again from a saved game, at the end of its moves.
+    pub fn load_game(&mut self, game: &Game) -> Result<(), anyhow::Error> {
+        let start_position = game
+            .positions_before_moves()
+            .next()
+            .unwrap_or(game.position())
+            .clone();
+
+        // The moves are replayed without the clock, which is taken from the game afterwards
+        let time_control = self.clock.take().map(|clock| clock.clock.time_control);
+        self.load_position(start_position);
+
+        for game_move in game.moves() {
+            self.play_move(game_move.color, game_move.mv)?;
+            if let Some(played) = self.moves.last_mut() {
+                played.elapsed_ms = game_move.elapsed_ms;
+            }
+        }
+
+        self.clock = match game.clock() {
+            Some(clock) => Some(LiveClock::new(clock.clone())),
+            None => time_control.map(|time_control| LiveClock::new(GameClock::new(time_control))),
+        };
+
+        self.game_over = game.status().is_game_over().then(|| game.status());
+
+        Ok(())
+    }
+
+    pub fn game_status(&self, game_type: GameType) -> Result<GameStatus, anyhow::Error> {
+        self.position_for_side_to_move()
+            .game_status(game_type, &self.history)
+    }
+
+    /// Whether the engine, playing `color`, accepts a draw given its last evaluation.
+    pub fn engine_accepts_draw(&self, color: Color) -> bool {
+        matches!(self.last_engine_score, Some((engine, score)) if engine == color && score <= DRAW_ACCEPT_SCORE)
+    }
+
+    /// The number of times the current position has occurred in the game.
+    pub fn repetition_count(&self) -> usize {
+        repetition_count(
+            &self.history,
+            self.position_for_side_to_move().zobrist_key(),
+        )
     }
 }
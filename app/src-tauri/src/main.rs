@@ -3,20 +3,88 @@
 
 mod commands;
 mod global_state;
+mod settings;
 
 use global_state::GlobalState;
+use settings::EngineSettings;
+use tauri::Manager;
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(GlobalState::default())
+        .setup(|app| {
+            let settings = EngineSettings::load(app.handle()).unwrap_or_else(|e| {
+                eprintln!("Couldn't load engine settings, using the defaults: {}", e);
+                EngineSettings::default()
+            });
+
+            let session = app.state::<GlobalState>().active();
+            let mut gs = session.lock().unwrap();
+            if let Err(e) = gs.apply_settings(settings) {
+                eprintln!("Couldn't apply engine settings, using the defaults: {}", e);
+                gs.apply_settings(EngineSettings::default())?;
+            }
+
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::get_valid_positions_for,
+            commands::get_move_targets,
+            commands::get_threats,
             commands::reset,
             commands::get_position_fen,
+            commands::validate_fen,
+            commands::load_fen,
+            commands::set_custom_position,
+            commands::export_game,
+            commands::import_game,
+            commands::start_handicap_game,
+            commands::load_opening_database,
+            commands::explore_position,
             commands::move_piece,
             commands::get_black_move,
             commands::get_white_move,
+            commands::cancel_search,
+            commands::start_autoplay,
+            commands::stop_autoplay,
+            commands::set_time_control,
+            commands::start_clock,
+            commands::pause_clock,
+            commands::get_clock,
+            commands::start_analysis,
+            commands::stop_analysis,
+            commands::analyze_game,
+            commands::check_move_quality,
+            commands::get_engine_move,
+            commands::set_engine_color,
+            commands::get_engine_color,
+            commands::is_engine_to_move,
+            commands::resign,
+            commands::offer_draw,
+            commands::accept_draw,
+            commands::decline_draw,
+            commands::get_game_status,
+            commands::can_claim_draw,
+            commands::claim_draw,
+            commands::set_notation,
+            commands::format_move,
+            commands::set_quick_evaluation_depth,
+            commands::get_engine_settings,
+            commands::set_engine_settings,
+            commands::set_engine_options,
+            commands::reset_engine_settings,
+            commands::get_top_moves,
+            commands::get_move_list,
+            commands::goto_ply,
+            commands::first,
+            commands::prev,
+            commands::next,
+            commands::last,
+            commands::list_sessions,
+            commands::create_session,
+            commands::switch_session,
+            commands::close_session,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use rescue_chess::{
+    features::{EvaluationWeights, Features, WEIGHT_OPTION_MAX, WEIGHT_OPTION_MIN},
+    opening_book::{DEFAULT_VARIETY, MAX_VARIETY},
+    piece_move::GameType,
+    search::{alpha_beta::SearchParams, backend::SearchAlgorithm, strength::StrengthLimit},
+};
+
+use crate::commands::GAME_TYPE;
+
+const SETTINGS_FILE: &str = "engine_settings.json";
+
+/// The engine's settings, saved to the app's data directory so they survive restarts. Fields
+/// missing from the saved file keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    /// How many plies deep the engine searches. This is the main difficulty setting.
+    pub depth: u32,
+
+    /// How long the engine may think per move. With a limit, the engine deepens one ply at a
+    /// time and plays the best move from the deepest search that finished in time.
+    pub time_limit_ms: Option<u64>,
+
+    pub features: Features,
+    pub weights: EvaluationWeights,
+
+    /// The most memory the transposition table may use.
+    pub transposition_table_mb: usize,
+
+    /// Plays below full strength when set, for beginners to play against.
+    pub strength_limit: Option<StrengthLimit>,
+
+    /// How the engine picks its moves in games. Hints and analysis always use the full search.
+    pub search_algorithm: SearchAlgorithm,
+
+    /// Whether the engine plays from the opening book at `book_path` until the game leaves it.
+    pub own_book: bool,
+    pub book_path: Option<PathBuf>,
+
+    /// How far from the book's heaviest moves to stray: 0 always plays the heaviest, 100 plays
+    /// in proportion to their weights.
+    pub book_variety: u32,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            depth: 5,
+            time_limit_ms: None,
+            features: Features::default(),
+            weights: EvaluationWeights::for_game_type(GAME_TYPE),
+            transposition_table_mb: 256,
+            strength_limit: None,
+            search_algorithm: SearchAlgorithm::default(),
+            own_book: false,
+            book_path: None,
+            book_variety: DEFAULT_VARIETY,
+        }
+    }
+}
+
+/// Changes to some of the engine's settings, e.g. from difficulty sliders. Anything left out
+/// keeps its current value.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EngineOptions {
+    pub depth: Option<u32>,
+
+    /// How long the engine may think per move, with 0 for no limit.
+    pub movetime_ms: Option<u64>,
+
+    /// Features to turn on or off, by field name.
+    pub features: HashMap<String, bool>,
+
+    /// Weights to change, by field name.
+    pub weights: HashMap<String, i32>,
+}
+
+impl EngineSettings {
+    /// These settings with `options` applied.
+    pub fn with_options(&self, options: &EngineOptions) -> Result<Self, anyhow::Error> {
+        let mut settings = self.clone();
+
+        if let Some(depth) = options.depth {
+            settings.depth = depth;
+        }
+
+        if let Some(movetime_ms) = options.movetime_ms {
+            settings.time_limit_ms = (movetime_ms > 0).then_some(movetime_ms);
+        }
+
+        for (name, &enabled) in &options.features {
+            let option = Features::options()
+                .iter()
+                .find(|option| option.field == *name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown feature: {}", name))?;
+            (option.set)(&mut settings.features, enabled);
+        }
+
+        for (name, &value) in &options.weights {
+            let option = EvaluationWeights::options()
+                .iter()
+                .find(|option| option.field == *name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown weight: {}", name))?;
+
+            if !(WEIGHT_OPTION_MIN..=WEIGHT_OPTION_MAX).contains(&value) {
+                return Err(anyhow::anyhow!(
+                    "The {} weight must be between {} and {}",
+                    name,
+                    WEIGHT_OPTION_MIN,
+                    WEIGHT_OPTION_MAX
+                ));
+            }
+            (option.set)(&mut settings.weights, value);
+        }
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn path(app: &tauri::AppHandle) -> Result<PathBuf, anyhow::Error> {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| anyhow::anyhow!("Couldn't find the app data directory: {}", e))?;
+
+        Ok(dir.join(SETTINGS_FILE))
+    }
+
+    /// Loads the saved settings, or the defaults if none have been saved yet.
+    pub fn load(app: &tauri::AppHandle) -> Result<Self, anyhow::Error> {
+        let path = Self::path(app)?;
+
+        if !path.exists() {
+            return Ok(EngineSettings::default());
+        }
+
+        let json = fs::read_to_string(&path)?;
+        let settings: EngineSettings = serde_json::from_str(&json)
+            .map_err(|e| anyhow::anyhow!("Invalid settings in {}: {}", path.display(), e))?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    pub fn save(&self, app: &tauri::AppHandle) -> Result<(), anyhow::Error> {
+        let path = Self::path(app)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.depth == 0 {
+            return Err(anyhow::anyhow!("Depth must be at least 1"));
+        }
+
+        if self.time_limit_ms == Some(0) {
+            return Err(anyhow::anyhow!("Time limit must be greater than 0"));
+        }
+
+        if self.transposition_table_mb == 0 {
+            return Err(anyhow::anyhow!(
+                "Transposition table size must be at least 1 MB"
+            ));
+        }
+
+        if self.own_book && self.book_path.is_none() {
+            return Err(anyhow::anyhow!("Choose an opening book to play from"));
+        }
+
+        if self.book_variety > MAX_VARIETY {
+            return Err(anyhow::anyhow!(
+                "Book variety must be at most {}",
+                MAX_VARIETY
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn search_params(&self, game_type: GameType) -> Result<SearchParams, anyhow::Error> {
+        let mut builder = SearchParams::builder()
+            .depth(self.depth)
+            .game_type(game_type)
+            .features(self.features)
+            .weights(self.weights);
+
+        if let Some(time_limit_ms) = self.time_limit_ms {
+            builder = builder.time_ms(time_limit_ms);
+        }
+
+        builder.build()
+    }
+}
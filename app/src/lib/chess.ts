@@ -2,60 +2,31 @@ export function positionToXy(position: number): [number, number] {
   return [position % 8, Math.floor(position / 8)];
 }
 
+/** The algebraic name of a square, e.g. `e4`. */
+export function squareName(position: number): string {
+  const [x, y] = positionToXy(position);
+  return `${'abcdefgh'[x]}${8 - y}`;
+}
+
 export type PieceType = 'Pawn' | 'Knight' | 'Bishop' | 'Rook' | 'Queen' | 'King';
 export type PawnPromotion = 'Queen' | 'Rook' | 'Bishop' | 'Knight';
 
+/** Mirrors the serde representation of `PieceMove` in the engine. Squares are indexes from 0 (a8) to 63 (h1). */
 export type PieceMove = {
+  piece_type: PieceType;
   from: number;
   to: number;
   move_type:
-    | {
-        type: 'Unknown';
-      }
     | {
         type: 'Normal';
-      }
-    | {
-        type: 'NormalAndRescue';
-        value: number;
-      }
-    | {
-        type: 'NormalAndDrop';
         value: {
-          pos: number;
-          promoted_to: PawnPromotion | null;
-        };
-      }
-    | {
-        type: 'Capture';
-        value: {
-          captured: PieceType;
+          captured: PieceType | null;
+          captured_pos: number | null;
           captured_holding: PieceType | null;
-        };
-      }
-    | {
-        type: 'CaptureAndRescue';
-        value: {
-          captured_type: PieceType;
-          rescued_pos: number;
-          captured_holding: PieceType | null;
-        };
-      }
-    | {
-        type: 'CaptureAndDrop';
-        value: {
-          captured_type: PieceType;
-          drop_pos: number;
+          rescued_pos: number | null;
+          dropped_pos: number | null;
           promoted_to: PawnPromotion | null;
-          captured_holding: PieceType | null;
-        };
-      }
-    | {
-        type: 'EnPassant';
-        value: {
-          captured_pos: number;
-          captured: PieceType;
-          captured_holding: PieceType | null;
+          dropped_promoted_to: PawnPromotion | null;
         };
       }
     | {
@@ -64,21 +35,72 @@ export type PieceMove = {
           king: number;
           rook: number;
         };
-      }
-    | {
-        type: 'Promotion';
-        value: PawnPromotion;
-      }
-    | {
-        type: 'CapturePromotion';
-        value: {
-          captured: PieceType;
-          promoted_to: PawnPromotion;
-          captured_holding: PieceType | null;
-        };
       };
 };
 
+export function normalMove(move: PieceMove) {
+  return move.move_type.type === 'Normal' ? move.move_type.value : undefined;
+}
+
+export function isCapture(move: PieceMove): boolean {
+  return normalMove(move)?.captured != null;
+}
+
+/** The square a captured piece was taken from, which differs from `to` for en passant. */
+export function capturedPos(move: PieceMove): number | undefined {
+  const normal = normalMove(move);
+  if (normal?.captured == null) {
+    return undefined;
+  }
+  return normal.captured_pos ?? move.to;
+}
+
+export function rescuedPos(move: PieceMove): number | undefined {
+  return normalMove(move)?.rescued_pos ?? undefined;
+}
+
+export function droppedPos(move: PieceMove): number | undefined {
+  return normalMove(move)?.dropped_pos ?? undefined;
+}
+
+export function promotedTo(move: PieceMove): PawnPromotion | undefined {
+  return normalMove(move)?.promoted_to ?? undefined;
+}
+
+export function droppedPromotedTo(move: PieceMove): PawnPromotion | undefined {
+  return normalMove(move)?.dropped_promoted_to ?? undefined;
+}
+
+/** What a selected piece can do, from `get_move_targets`. */
+export type MoveTargets = {
+  moves: PieceMove[];
+  destinations: number[];
+  rescues: number[];
+  drops: number[];
+};
+
+export type PossibleMoveType = 'normal' | 'capture' | 'rescue' | 'drop';
+
+/** A piece of the side to move that the opponent can win material by capturing. */
+export type HangingPiece = {
+  pos: number;
+  piece_type: PieceType;
+  holding: PieceType | null;
+  capture: PieceMove;
+  material: number;
+};
+
+/** The opponent's threats against the side to move, from `get_threats`. */
+export type ThreatsResponse = {
+  attacked: number[];
+  hanging: HangingPiece[];
+  mate_threat: {
+    move_from_whites_perspective: PieceMove;
+    notation: string;
+    moves: number;
+  } | null;
+};
+
 export type SearchResults = {
   best_move: PieceMove;
   score: number;
@@ -87,12 +109,294 @@ export type SearchResults = {
   time_taken_ms: number;
 };
 
-export type BlackMoveResponse = {
+export type BestMoveResponse = {
+  search_id: number;
+  color: 'White' | 'Black';
   results: SearchResults;
   move_from_whites_perspective: PieceMove;
+  from_book: boolean;
 };
 
-export type WhiteMoveResponse = {
+export type AutoplayMoveResponse = {
+  autoplay_id: number;
+  color: 'White' | 'Black';
   results: SearchResults;
   move_from_whites_perspective: PieceMove;
 };
+
+export type AutoplayStoppedResponse = {
+  autoplay_id: number;
+  reason: string;
+};
+
+export type SearchStoppedResponse = {
+  search_id: number;
+  color: 'White' | 'Black';
+  reason: string;
+};
+
+/** A score from white's point of view. Mate is in moves, negative when black is mating. */
+export type Evaluation = { type: 'Centipawns'; value: number } | { type: 'Mate'; value: number };
+
+export type EvaluationResponse = {
+  evaluation: Evaluation;
+  depth: number;
+  ply: number;
+};
+
+export type AnalysisResponse = {
+  analysis_id: number;
+  ply: number;
+  evaluation: Evaluation;
+  depth: number;
+  nodes: number;
+  time_ms: number;
+  principal_variation: PieceMove[];
+  line: string[];
+  done: boolean;
+};
+
+export type CandidateMove = {
+  move_from_whites_perspective: PieceMove;
+  notation: string;
+  evaluation: Evaluation;
+  principal_variation: PieceMove[];
+  reasons: string[];
+};
+
+export type CandidateMovesResponse = {
+  search_id: number;
+  color: 'White' | 'Black';
+  candidates: CandidateMove[];
+  scored: number;
+  total: number;
+  done: boolean;
+};
+
+export function formatEvaluation(evaluation: Evaluation): string {
+  if (evaluation.type === 'Mate') {
+    return `${evaluation.value < 0 ? '-' : ''}M${Math.abs(evaluation.value)}`;
+  }
+
+  const pawns = evaluation.value / 100;
+  return `${pawns > 0 ? '+' : ''}${pawns.toFixed(2)}`;
+}
+
+export type FenField =
+  | 'PiecePlacement'
+  | 'ActiveColor'
+  | 'CastlingRights'
+  | 'EnPassant'
+  | 'HalfmoveClock'
+  | 'FullmoveNumber';
+
+export type PositionRule =
+  | 'MissingField'
+  | 'InvalidSyntax'
+  | 'WrongSquareCount'
+  | 'TooManyPieces'
+  | 'TooManyPawns'
+  | 'KingCount'
+  | 'PawnOnBackRank'
+  | 'InvalidHolding'
+  | 'OpponentInCheck'
+  | 'CastlingWithoutKingOrRook'
+  | 'InvalidEnPassant';
+
+export type PositionError = {
+  field: FenField;
+  square: number | null;
+  rule: PositionRule;
+  message: string;
+};
+
+export type PieceColor = 'White' | 'Black';
+
+/** Mirrors `SetupPiece` in the engine. */
+export type SetupPiece = {
+  piece_type: PieceType;
+  color: PieceColor;
+  position: number;
+  holding: PieceType | null;
+};
+
+/** Mirrors `PositionSetup` in the engine, for starting a game from the board editor. */
+export type PositionSetup = {
+  pieces: SetupPiece[];
+  to_move: PieceColor;
+  castling_rights: string;
+};
+
+export type SearchAlgorithm = 'alpha_beta' | 'mcts' | 'random' | 'greedy' | 'depth_one';
+
+export type EngineSettings = {
+  depth: number;
+  time_limit_ms: number | null;
+  features: Record<string, boolean>;
+  weights: Record<string, number>;
+  transposition_table_mb: number;
+  strength_limit: { elo: number } | null;
+  search_algorithm: SearchAlgorithm;
+  own_book: boolean;
+  book_path: string | null;
+  book_variety: number;
+};
+
+export type MoveListPosition = {
+  ply: number;
+  move_count: number;
+};
+
+export type MoveListEntry = {
+  ply: number;
+  color: 'White' | 'Black';
+  move_from_whites_perspective: PieceMove;
+  notation: string;
+};
+
+export type GameFormat = 'Pgn' | 'Json';
+
+export type EngineColor = 'White' | 'Black' | 'Both' | 'None';
+
+/** A game session, from `list_sessions`. Each session is a separate game, shown as a tab. */
+export type SessionInfo = {
+  session_id: number;
+  active: boolean;
+  ply: number;
+  to_move: 'White' | 'Black';
+  game_over: GameStatus | null;
+  autoplaying: boolean;
+};
+
+export type GameStatus =
+  | { type: 'Ongoing' }
+  | { type: 'Checkmate'; value: 'White' | 'Black' }
+  | { type: 'Stalemate' }
+  | { type: 'DrawByRepetition' }
+  | { type: 'DrawByFiftyMoves' }
+  | { type: 'DrawByInsufficientMaterial' }
+  | { type: 'Resignation'; value: 'White' | 'Black' }
+  | { type: 'DrawByAgreement' }
+  | { type: 'Timeout'; value: 'White' | 'Black' };
+
+export type MoveClassification = 'Best' | 'Good' | 'Inaccuracy' | 'Mistake' | 'Blunder';
+
+/** Mirrors `MoveReview` in the engine. Moves and scores are from the mover's perspective. */
+export type MoveReview = {
+  ply: number;
+  color: 'White' | 'Black';
+  played: PieceMove;
+  san: string;
+  played_score: Evaluation;
+  best_move: PieceMove;
+  best_san: string;
+  best_score: Evaluation;
+  centipawn_loss: number;
+  classification: MoveClassification;
+};
+
+export type SideReview = {
+  moves: number;
+  average_centipawn_loss: number;
+  inaccuracies: number;
+  mistakes: number;
+  blunders: number;
+};
+
+export type GameReview = {
+  moves: MoveReview[];
+  white: SideReview;
+  black: SideReview;
+};
+
+export type GameReviewProgressResponse = {
+  search_id: number;
+  review: MoveReview;
+  reviewed: number;
+  total: number;
+};
+
+/** Mirrors `EvaluationPoint` in the engine. Evaluations are from white's perspective. */
+export type EvaluationPoint = {
+  ply: number;
+  evaluation: Evaluation;
+  centipawns: number;
+};
+
+export type GameReviewResponse = {
+  search_id: number;
+  review: GameReview;
+  evaluation_graph: EvaluationPoint[];
+};
+
+export type ClockResponse = {
+  time_control: string;
+  white_ms: number;
+  black_ms: number;
+  running: boolean;
+  to_move: 'White' | 'Black';
+};
+
+export function formatClock(ms: number): string {
+  const seconds = Math.ceil(ms / 1000);
+  const minutes = Math.floor(seconds / 60);
+  return `${minutes}:${String(seconds % 60).padStart(2, '0')}`;
+}
+
+export type DrawOfferResponse = 'Accepted' | 'Declined' | 'Pending';
+
+export function describeGameStatus(status: GameStatus): string {
+  switch (status.type) {
+    case 'Ongoing':
+      return 'Game in progress';
+    case 'Checkmate':
+      return `Checkmate, ${status.value.toLowerCase()} wins`;
+    case 'Stalemate':
+      return 'Draw by stalemate';
+    case 'DrawByRepetition':
+      return 'Draw by threefold repetition';
+    case 'DrawByFiftyMoves':
+      return 'Draw by fifty-move rule';
+    case 'DrawByInsufficientMaterial':
+      return 'Draw by insufficient material';
+    case 'Resignation':
+      return `${status.value === 'White' ? 'Black' : 'White'} resigned, ${status.value.toLowerCase()} wins`;
+    case 'DrawByAgreement':
+      return 'Draw by agreement';
+    case 'Timeout':
+      return `${status.value === 'White' ? 'Black' : 'White'} ran out of time, ${status.value.toLowerCase()} wins`;
+  }
+}
+
+export type ResultStats = {
+  white_wins: number;
+  draws: number;
+  black_wins: number;
+  unknown: number;
+};
+
+export type IndexSummary = {
+  files: number;
+  games: number;
+  skipped: number;
+};
+
+export type OpeningExplorerMove = {
+  move_from_whites_perspective: PieceMove;
+  notation: string;
+  stats: ResultStats;
+};
+
+export type OpeningExplorerResponse = {
+  stats: ResultStats;
+  moves: OpeningExplorerMove[];
+};
+
+export function totalGames(stats: ResultStats): number {
+  return stats.white_wins + stats.draws + stats.black_wins + stats.unknown;
+}
+
+export type Handicap =
+  | { type: 'Odds'; value: Exclude<PieceType, 'King'> }
+  | { type: 'Held'; value: Exclude<PieceType, 'King'> }
+  | { type: 'Extra'; value: Exclude<PieceType, 'King'> };
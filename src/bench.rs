@@ -0,0 +1,173 @@
+//! A fixed benchmark for tracking the engine's speed between commits: a suite of classic and
+//! Rescue positions, each searched to the same depth from an empty transposition table.
+//!
+//! The total number of nodes is a signature of the search: any change to the search or the
+//! evaluation that changes it changes the engine's play, and a change meant only to make the
+//! engine faster should leave it alone. The nodes per second then tell whether it did.
+
+use std::{fmt::Display, time::Instant};
+
+use crate::{
+    features::EvaluationWeights,
+    piece_move::GameType,
+    search::{
+        alpha_beta::SearchParams,
+        backend,
+        search_results::{SearchResults, SearchState},
+        transposition_table::TranspositionTable,
+    },
+    Position,
+};
+
+/// The depth the benchmark searches to unless told otherwise.
+pub const DEFAULT_BENCH_DEPTH: u32 = 5;
+
+/// The benchmark's positions, as FEN. Rescue positions write a held piece after its holder.
+pub const BENCH_POSITIONS: [(GameType, &str); 10] = [
+    (
+        GameType::Classic,
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        GameType::Classic,
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    ),
+    (
+        GameType::Classic,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+    (
+        GameType::Classic,
+        "r2q1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R b KQ - 0 9",
+    ),
+    (
+        GameType::Classic,
+        "8/2k5/3p4/p2P1p2/P2P1P2/8/5K2/8 w - - 0 1",
+    ),
+    (
+        GameType::Rescue,
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        GameType::Rescue,
+        "rnbqkbnr/ppp2ppp/8/3pp3/4P3/5NxP2/PPPP1P1P/RNBQKB1R b KQkq - 1 3",
+    ),
+    (
+        GameType::Rescue,
+        "r1bqk2r/ppppbppp/2n2n2/4p3/2BxP1P3/5N2/PPP2PPP/RNBQK2R w KQkq - 4 5",
+    ),
+    (GameType::Rescue, "4k3/3qxp4/8/8/8/8/3RxN4/4K3 w - - 0 1"),
+    (GameType::Rescue, "8/5k2/8/3KxR4/8/8/8/8 w - - 0 1"),
+];
+
+/// The benchmark's totals over all of its positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResults {
+    pub positions: usize,
+    pub nodes: u64,
+    pub time_ms: u128,
+}
+
+impl BenchResults {
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.time_ms.max(1)) as u64
+    }
+}
+
+impl Display for BenchResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Positions: {}", self.positions)?;
+        writeln!(f, "Time: {}ms", self.time_ms)?;
+        writeln!(f, "Nodes per second: {}", self.nodes_per_second())?;
+        write!(f, "Nodes searched: {}", self.nodes)
+    }
+}
+
+/// Searches every benchmark position to `depth`, calling `on_position` with each position's
+/// index and results as it finishes.
+pub fn run_bench(
+    depth: u32,
+    mut on_position: impl FnMut(usize, &SearchResults),
+) -> Result<BenchResults, anyhow::Error> {
+    let start_time = Instant::now();
+    let mut nodes = 0;
+
+    for (i, (game_type, fen)) in BENCH_POSITIONS.iter().enumerate() {
+        let position = Position::parse_from_fen(fen)?;
+        let results = search(&position, *game_type, depth)?;
+
+        nodes += results.nodes_searched as u64;
+        on_position(i, &results);
+    }
+
+    Ok(BenchResults {
+        positions: BENCH_POSITIONS.len(),
+        nodes,
+        time_ms: start_time.elapsed().as_millis(),
+    })
+}
+
+/// Deepens the search one ply at a time to `depth`, with no time limit so the node count
+/// doesn't depend on the machine.
+fn search(
+    position: &Position,
+    game_type: GameType,
+    depth: u32,
+) -> Result<SearchResults, anyhow::Error> {
+    let params = SearchParams::builder()
+        .game_type(game_type)
+        .weights(EvaluationWeights::for_game_type(game_type))
+        .build()?;
+
+    let mut transposition_table = TranspositionTable::new();
+    let mut state = SearchState::new(&mut transposition_table);
+    state.data.start_time = Instant::now();
+
+    let mut best: Option<SearchResults> = None;
+
+    for depth in 1..=depth {
+        let mut iteration_params = params.clone();
+        iteration_params.depth = depth;
+        iteration_params.previous_score = best.as_ref().map(|results| results.score);
+
+        let results = backend::search(position, &mut state, iteration_params, 0)
+            .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+        state.data.previous_pv = results.principal_variation.clone();
+
+        // Nothing deeper to search without legal moves
+        let finished = results.best_move.is_none();
+        best = Some(results);
+
+        if finished {
+            break;
+        }
+    }
+
+    let mut best = best.ok_or_else(|| anyhow::anyhow!("Search depth is at least 1"))?;
+    best.nodes_searched = state.data.nodes_searched;
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_positions_are_valid() {
+        for (_, fen) in BENCH_POSITIONS {
+            Position::parse_from_fen(fen).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_bench_is_deterministic() {
+        let mut searched = vec![];
+        let first = run_bench(2, |i, _| searched.push(i)).unwrap();
+        let second = run_bench(2, |_, _| {}).unwrap();
+
+        assert_eq!(searched, (0..BENCH_POSITIONS.len()).collect::<Vec<_>>());
+        assert!(first.nodes > 0);
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
@@ -0,0 +1,31 @@
+use clap::Parser;
+use rescue_chess::bench::{run_bench, BENCH_POSITIONS, DEFAULT_BENCH_DEPTH};
+
+/// Searches a fixed suite of positions to a fixed depth, printing the total nodes searched as
+/// a signature of the search and the nodes per second it ran at.
+#[derive(Parser)]
+struct Cli {
+    #[arg(short = 'd', long, default_value_t = DEFAULT_BENCH_DEPTH)]
+    pub depth: u32,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let results = run_bench(args.depth, |i, results| {
+        let (game_type, fen) = BENCH_POSITIONS[i];
+        println!(
+            "Position {}/{} ({:?}): {} nodes, {}ms  {}",
+            i + 1,
+            BENCH_POSITIONS.len(),
+            game_type,
+            results.nodes_searched,
+            results.time_taken_ms,
+            fen
+        );
+    })?;
+
+    println!("\n{}", results);
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use clap::Parser;
+use rescue_chess::bot::{Bot, BotConfig};
+
+/// Plays classic chess online as a bot, through the Lichess Bot API.
+#[derive(Parser)]
+struct Cli {
+    /// The server to connect to
+    #[arg(short = 'e', long, default_value = "https://lichess.org")]
+    pub endpoint: String,
+
+    /// The bot account's API token
+    #[arg(short = 't', long, env = "LICHESS_BOT_TOKEN")]
+    pub token: String,
+
+    /// The deepest to search each move, though the clock usually stops the search first
+    #[arg(short = 'd', long, default_value = "20")]
+    pub depth: u32,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .init();
+
+    let mut config = BotConfig::new(&args.endpoint, &args.token);
+    config.depth = args.depth;
+
+    Bot::new(config).run()
+}
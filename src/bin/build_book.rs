@@ -0,0 +1,85 @@
+use clap::Parser;
+use rand::{rngs::StdRng, SeedableRng};
+use rescue_chess::{opening_book::BookBuilder, piece_move::GameType};
+use std::path::PathBuf;
+
+/// Builds an opening book from games the engine plays against itself.
+#[derive(Parser)]
+struct Cli {
+    /// The file to write the book to
+    pub output: PathBuf,
+
+    #[arg(short = 'n', long, default_value = "100")]
+    pub games: usize,
+
+    /// How deep to search each move
+    #[arg(short = 'd', long, default_value = "4")]
+    pub depth: u32,
+
+    /// How many plies of each game to add to the book
+    #[arg(short = 'p', long, default_value = "16")]
+    pub book_plies: usize,
+
+    /// How many plies at the start of each game to pick at random from the good moves
+    #[arg(short = 'r', long, default_value = "8")]
+    pub random_plies: usize,
+
+    /// How much worse than the best move, in centipawns, a randomly picked move can be
+    #[arg(short = 'm', long, default_value = "30")]
+    pub margin: i32,
+
+    #[arg(short = 'c', long)]
+    pub classic: bool,
+
+    #[arg(short = 's', long)]
+    pub seed: Option<u64>,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let builder = BookBuilder {
+        game_type: if args.classic {
+            GameType::Classic
+        } else {
+            GameType::Rescue
+        },
+        depth: args.depth,
+        book_plies: args.book_plies,
+        random_plies: args.random_plies,
+        margin: args.margin,
+        ..Default::default()
+    };
+
+    let book = builder.build(args.games, &mut rng, |i, game| {
+        let moves = game
+            .moves()
+            .iter()
+            .take(args.book_plies)
+            .map(|played| played.san.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        println!(
+            "Game {}/{}: {} ({})",
+            i + 1,
+            args.games,
+            moves,
+            game.status()
+        );
+    })?;
+
+    book.save(&args.output)?;
+    println!(
+        "Wrote {} positions to {}",
+        book.len(),
+        args.output.display()
+    );
+
+    Ok(())
+}
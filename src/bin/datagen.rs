@@ -0,0 +1,92 @@
+use clap::Parser;
+use rand::{rngs::StdRng, SeedableRng};
+use rescue_chess::{
+    piece_move::GameType,
+    training_data::{write_header, TrainingDataGenerator},
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+/// Generates training data for an NNUE or weight tuning from games the engine plays against
+/// itself.
+#[derive(Parser)]
+struct Cli {
+    /// The file to write the training data to
+    pub output: PathBuf,
+
+    #[arg(short = 'n', long, default_value = "1000")]
+    pub games: usize,
+
+    /// How deep to search each move
+    #[arg(short = 'd', long, default_value = "4")]
+    pub depth: u32,
+
+    /// How many plies at the start of each game to play at random
+    #[arg(short = 'r', long, default_value = "8")]
+    pub random_plies: usize,
+
+    /// Games still going after this many plies are thrown away
+    #[arg(short = 'm', long, default_value = "400")]
+    pub max_plies: usize,
+
+    /// Also record positions the side to move is in check in
+    #[arg(long)]
+    pub keep_checks: bool,
+
+    #[arg(short = 'c', long)]
+    pub classic: bool,
+
+    #[arg(short = 's', long)]
+    pub seed: Option<u64>,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let generator = TrainingDataGenerator {
+        game_type: if args.classic {
+            GameType::Classic
+        } else {
+            GameType::Rescue
+        },
+        depth: args.depth,
+        random_plies: args.random_plies,
+        max_plies: args.max_plies,
+        skip_checks: !args.keep_checks,
+    };
+
+    let file = File::create(&args.output)
+        .map_err(|e| anyhow::anyhow!("Couldn't create {}: {}", args.output.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    write_header(&mut writer, generator.game_type)?;
+
+    let mut total = 0;
+    for i in 0..args.games {
+        let records = generator.play_game(&mut rng)?;
+        for record in &records {
+            writer.write_all(&record.to_bytes())?;
+        }
+        total += records.len();
+
+        println!(
+            "Game {}/{}: {} positions ({} in total)",
+            i + 1,
+            args.games,
+            records.len(),
+            total
+        );
+    }
+
+    writer.flush()?;
+    println!("Wrote {} positions to {}", total, args.output.display());
+
+    Ok(())
+}
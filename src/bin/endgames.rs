@@ -0,0 +1,76 @@
+use clap::Parser;
+use rand::{rngs::StdRng, SeedableRng};
+use rescue_chess::endgame_training::{EndgameGenerator, EndgameTheme};
+use std::{fs, path::PathBuf};
+
+/// Generates endgame training positions, checked by a search, as EPD.
+#[derive(Parser)]
+struct Cli {
+    /// The kind of endgame: KPK, KRPKR, KRKP, KxQK or KRxPKR
+    pub theme: EndgameTheme,
+
+    #[arg(short = 'n', long, default_value = "10")]
+    pub count: usize,
+
+    /// How deep to search each position to check its result
+    #[arg(short = 'd', long, default_value = "8")]
+    pub depth: u32,
+
+    #[arg(short = 's', long)]
+    pub seed: Option<u64>,
+
+    /// Write plain FENs instead of EPD with the best move and result
+    #[arg(long)]
+    pub fen: bool,
+
+    /// The file to write the positions to, instead of stdout
+    #[arg(short = 'o', long)]
+    pub output: Option<PathBuf>,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let generator = EndgameGenerator {
+        depth: args.depth,
+        ..Default::default()
+    };
+
+    let positions = generator.generate(args.theme, args.count, &mut rng)?;
+
+    if positions.len() < args.count {
+        eprintln!(
+            "Only found {} of {} {} positions",
+            positions.len(),
+            args.count,
+            args.theme
+        );
+    }
+
+    let lines = positions
+        .iter()
+        .enumerate()
+        .map(|(i, generated)| {
+            if args.fen {
+                generated.position.to_fen()
+            } else {
+                generated
+                    .to_extended_position(&format!("{}.{}", args.theme, i + 1))
+                    .to_epd()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match args.output {
+        Some(path) => fs::write(path, lines + "\n")?,
+        None => println!("{}", lines),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use clap::Parser;
+use rescue_chess::{
+    opening_explorer::{OpeningTree, ResultStats},
+    pgn::parse_san,
+    piece_move::GameType,
+    Position,
+};
+use std::path::PathBuf;
+
+/// Shows the moves played from a position in a folder of PGN games.
+#[derive(Parser)]
+struct Cli {
+    /// The folder of .pgn files to index
+    pub database: PathBuf,
+
+    /// How many plies into each game to index
+    #[arg(short = 'p', long, default_value = "30")]
+    pub max_ply: usize,
+
+    /// The position to explore, defaulting to the starting position
+    #[arg(short = 'f', long)]
+    pub fen: Option<String>,
+
+    /// Moves in SAN to play from the position before exploring, e.g. "e4 c5 Nf3"
+    #[arg(short = 'm', long)]
+    pub moves: Option<String>,
+
+    #[arg(short = 'r', long)]
+    pub rescue: bool,
+}
+
+fn format_stats(stats: &ResultStats) -> String {
+    let percent = |count: u32| 100.0 * count as f32 / stats.games().max(1) as f32;
+
+    format!(
+        "{:>7} games  +{:>5.1}%  ={:>5.1}%  -{:>5.1}%",
+        stats.games(),
+        percent(stats.white_wins),
+        percent(stats.draws),
+        percent(stats.black_wins),
+    )
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let game_type = if args.rescue {
+        GameType::Rescue
+    } else {
+        GameType::Classic
+    };
+
+    let mut position = match args.fen {
+        Some(fen) => Position::parse_from_fen(&fen)?,
+        None => Position::start_position(),
+    };
+
+    for notation in args.moves.iter().flat_map(|moves| moves.split_whitespace()) {
+        let mv = parse_san(&position, notation, game_type)?;
+        position.apply_move(mv)?;
+        position.invert();
+    }
+
+    let mut tree = OpeningTree::new(args.max_ply, game_type);
+    let summary = tree.index_directory(&args.database)?;
+
+    println!(
+        "Indexed {} games from {} files ({} skipped), {} positions",
+        summary.games,
+        summary.files,
+        summary.skipped,
+        tree.len()
+    );
+    println!();
+    println!("{}", position.to_fen());
+    println!(
+        "{:<10}{}",
+        "",
+        format_stats(&tree.position_stats(&position))
+    );
+    println!();
+
+    let moves = tree.moves(&position);
+    if moves.is_empty() {
+        println!("No games reached this position");
+    }
+
+    for explored in moves {
+        println!("{:<10}{}", explored.notation, format_stats(&explored.stats));
+    }
+
+    Ok(())
+}
@@ -1,11 +1,10 @@
 use clap::Parser;
 use rescue_chess::{
-    piece_move::GameType,
+    features::EvaluationWeights,
+    piece_move::{GameType, MoveList},
     search::{
-        alpha_beta::{self, SearchParams},
-        iterative_deepening::IterativeDeepeningData,
-        search_results::SearchState,
-        transposition_table::TranspositionTable,
+        alpha_beta::SearchParams, backend, iterative_deepening::IterativeDeepeningData,
+        search_results::SearchState, transposition_table::TranspositionTable,
     },
     Color, PieceMove, Position,
 };
@@ -42,7 +41,7 @@ struct Cli {
     pub fen: String,
 }
 
-fn main() {
+fn main() -> Result<(), anyhow::Error> {
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::TRACE)
         .with_level(false)
@@ -74,11 +73,12 @@ fn main() {
             }
 
             if args.print_valid_moves {
+                let mut moves = MoveList::new();
+                position.get_all_legal_moves(game_type, &mut moves).unwrap();
+
                 println!(
                     "{}",
-                    position
-                        .get_all_legal_moves(game_type)
-                        .unwrap()
+                    moves
                         .iter()
                         .map(|m| m.to_string())
                         .collect::<Vec<String>>()
@@ -86,15 +86,23 @@ fn main() {
                 );
             }
 
+            // There's no best move once the game is over, so say how it ended instead
+            let status = position.game_status(game_type, &[])?;
+            if status.is_game_over() {
+                println!("{}", status);
+                return Ok(());
+            }
+
             let mut iterative_deepening_data = IterativeDeepeningData::new();
 
             iterative_deepening_data.update_position(position.clone());
 
-            let params = SearchParams {
-                depth,
-                game_type,
-                ..Default::default()
-            };
+            let params = SearchParams::builder()
+                .depth(depth)
+                .game_type(game_type)
+                .weights(EvaluationWeights::for_game_type(game_type))
+                .build()
+                .expect("Invalid search parameters");
 
             iterative_deepening_data.search(params.clone());
 
@@ -124,7 +132,7 @@ fn main() {
                 let mut state = SearchState::new(&mut transposition_table);
 
                 let scored_moves =
-                    alpha_beta::score_all_moves(&position, &mut state, params.clone(), 0).unwrap();
+                    backend::score_all_moves(&position, &mut state, params.clone(), 0).unwrap();
 
                 for scored_move in scored_moves {
                     let mut principal_variation = vec![scored_move.mv];
@@ -169,19 +177,7 @@ fn main() {
 
             if args.stats {
                 println!("------------------------------------");
-                println!(
-                    "Nodes searched: {}",
-                    iterative_deepening_data.stats.nodes_searched
-                );
-                println!(
-                    "Cached positions: {}",
-                    iterative_deepening_data.stats.cached_positions
-                );
-                println!(
-                    "Time taken: {}ms",
-                    iterative_deepening_data.stats.time_taken_ms
-                );
-                println!("Pruned: {}", iterative_deepening_data.stats.pruned);
+                print!("{}", iterative_deepening_data.stats);
                 println!("Score: {}", best_score.unwrap());
 
                 let mut principal_variation = vec![];
@@ -214,6 +210,8 @@ fn main() {
             eprintln!("Error: {}", e);
         }
     }
+
+    Ok(())
 }
 
 pub fn pv_move(
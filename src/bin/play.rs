@@ -0,0 +1,191 @@
+use clap::Parser;
+use rescue_chess::{
+    features::EvaluationWeights, game::Game, pgn::to_san, piece_move::GameType, position::Handicap,
+    search::game_state::GameState, Color, PieceMove, Position,
+};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+/// Plays a game against the engine in the terminal.
+#[derive(Parser)]
+struct Cli {
+    /// How deep the engine searches each move
+    #[arg(short = 'd', long, default_value = "6")]
+    pub depth: u32,
+
+    /// How long the engine thinks about each move, in milliseconds
+    #[arg(short = 't', long = "time", default_value = "3000")]
+    pub think_time_ms: u64,
+
+    #[arg(short = 'c', long)]
+    pub classic: bool,
+
+    /// Play black instead of white
+    #[arg(short = 'b', long)]
+    pub black: bool,
+
+    /// Carry on with a game saved with `save`
+    #[arg(short = 'l', long)]
+    pub load: Option<String>,
+
+    /// Have the engine give a handicap, e.g. "knight" for knight odds, "held-queen" for the
+    /// engine's queen starting held or "extra-rook" for an extra rook held by your king
+    #[arg(long, conflicts_with = "load")]
+    pub handicap: Option<Handicap>,
+}
+
+const HELP: &str = "Enter a move in SAN (e4, Nf3, e2Sf2) or UCI notation (e2e4), or one of:
+  moves        list the legal moves
+  undo         take back your last move and the engine's reply
+  save <file>  save the game
+  load <file>  load a saved game
+  fen          print the position's FEN
+  resign       resign the game
+  help         show this message
+  quit         leave without finishing the game";
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let game_type = if args.classic {
+        GameType::Classic
+    } else {
+        GameType::Rescue
+    };
+
+    let player = if args.black {
+        Color::Black
+    } else {
+        Color::White
+    };
+
+    let mut game = match (&args.load, args.handicap) {
+        (Some(path), _) => load(path)?,
+        (None, Some(handicap)) => Game::from_position(
+            Position::start_position_with_handicap(handicap, player.invert())?,
+            game_type,
+        )?,
+        (None, None) => Game::new(game_type),
+    };
+
+    let mut engine = GameState {
+        search_depth: args.depth,
+        time_limit_ms: args.think_time_ms,
+        weights: EvaluationWeights::for_game_type(game.game_type()),
+        ..GameState::new()
+    };
+    engine.game_type = game.game_type();
+
+    println!("{}", HELP);
+    print_board(&game);
+
+    let stdin = io::stdin();
+    let mut input = String::new();
+
+    while !game.is_over() {
+        if game.to_move() != player {
+            let mv = engine_move(&game, &mut engine)?;
+            let played = game.make_move(mv)?;
+            println!("\nThe engine plays {}", played.san);
+            print_board(&game);
+            continue;
+        }
+
+        print!("\nYour move: ");
+        io::stdout().flush()?;
+
+        input.clear();
+        if stdin.lock().read_line(&mut input)? == 0 {
+            return Ok(());
+        }
+
+        let input = input.trim();
+        let (command, argument) = input.split_once(' ').unwrap_or((input, ""));
+
+        match command {
+            "" => {}
+            "help" => println!("{}", HELP),
+            "quit" => return Ok(()),
+            "fen" => println!("{}", game.position().to_fen()),
+            "moves" => {
+                let moves = game
+                    .legal_moves()?
+                    .into_iter()
+                    .filter_map(|mv| to_san(game.position(), mv, game.game_type()).ok())
+                    .collect::<Vec<_>>();
+                println!("{}", moves.join(" "));
+            }
+            "undo" => {
+                // Take back the engine's reply too, so it's the player's move again
+                game.undo();
+                if game.to_move() != player {
+                    game.undo();
+                }
+                print_board(&game);
+            }
+            "save" => match save(&game, argument) {
+                Ok(()) => println!("Saved to {}", argument),
+                Err(e) => println!("Couldn't save the game: {}", e),
+            },
+            "load" => match load(argument) {
+                Ok(loaded) => {
+                    game = loaded;
+                    engine.game_type = game.game_type();
+                    print_board(&game);
+                }
+                Err(e) => println!("Couldn't load the game: {}", e),
+            },
+            "resign" => game.resign(player)?,
+            _ => {
+                let played = match game.make_san_move(input) {
+                    Ok(_) => Ok(()),
+                    Err(e) => game.make_uci_move(input).map(|_| ()).map_err(|_| e),
+                };
+
+                match played {
+                    Ok(()) => print_board(&game),
+                    Err(e) => println!("Invalid move {}: {}", input, e),
+                }
+            }
+        }
+    }
+
+    println!("\nGame over! {}", game.status());
+
+    Ok(())
+}
+
+/// Searches the game's position for the engine's move, from the perspective of the side to
+/// move. The engine keeps its transposition table from move to move.
+fn engine_move(game: &Game, engine: &mut GameState) -> Result<PieceMove, anyhow::Error> {
+    engine.current_position = game.position().clone();
+    engine.current_turn = game.to_move();
+    engine.reset_history();
+    engine.history = game.history().to_vec();
+
+    let (mv, _) = engine.search_and_apply()?;
+
+    Ok(mv)
+}
+
+fn print_board(game: &Game) {
+    // Boards are stored from the side to move's perspective, so black's are turned around
+    let position = match game.to_move() {
+        Color::White => game.position().clone(),
+        Color::Black => game.position().inverted(),
+    };
+
+    println!("\n{}", position.to_board_string_with_rank_file_holding());
+}
+
+fn save(game: &Game, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+    fs::write(path, serde_json::to_string_pretty(game)?)?;
+    Ok(())
+}
+
+fn load(path: impl AsRef<Path>) -> Result<Game, anyhow::Error> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
@@ -1,8 +1,18 @@
 use clap::Parser;
 use rescue_chess::{
-    features::Features, piece_move::GameType, search::game_state::GameState, Color, Position,
+    features::{EvaluationWeights, Features},
+    piece_move::GameType,
+    position::Handicap,
+    search::{
+        game_state::GameState,
+        time_control::{GameClock, TimeControl},
+    },
+    Color, Position,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
 };
-use std::{thread, time::Duration};
 
 #[derive(Parser)]
 struct Cli {
@@ -12,6 +22,11 @@ struct Cli {
     #[arg(short = 't', long = "time")]
     pub think_time_ms: Option<u64>,
 
+    /// Play on a clock instead of a fixed time per move, e.g. "5+3", or "1+0:10+0" to give
+    /// white one minute against black's ten
+    #[arg(long, conflicts_with = "think_time_ms")]
+    pub time_control: Option<TimeControl>,
+
     #[arg(short = 'c', long)]
     pub classic: bool,
 
@@ -24,6 +39,15 @@ struct Cli {
     #[arg(long)]
     pub starting_fen: Option<String>,
 
+    /// Start with black giving a handicap, e.g. "knight" for knight odds, "held-queen" or
+    /// "extra-rook"
+    #[arg(long, conflicts_with = "starting_fen")]
+    pub handicap: Option<Handicap>,
+
+    /// White gives the handicap instead of black
+    #[arg(long, requires = "handicap")]
+    pub white_gives_handicap: bool,
+
     #[arg(short = 'v', long)]
     pub verbose: bool,
 }
@@ -43,9 +67,17 @@ fn main() {
         GameType::Rescue
     };
 
-    let position = match args.starting_fen {
-        Some(fen) => fen.parse::<Position>().expect("Invalid FEN string"),
-        None => Position::start_position(),
+    let position = match (args.starting_fen, args.handicap) {
+        (Some(fen), _) => fen.parse::<Position>().expect("Invalid FEN string"),
+        (None, Some(handicap)) => {
+            let color = if args.white_gives_handicap {
+                Color::White
+            } else {
+                Color::Black
+            };
+            Position::start_position_with_handicap(handicap, color).expect("Invalid handicap")
+        }
+        (None, None) => Position::start_position(),
     };
 
     let think_time_ms = args.think_time_ms.unwrap_or(5_000);
@@ -57,10 +89,17 @@ fn main() {
         debug_logs_verbose: true,
         search_depth: args.depth,
         time_limit_ms: think_time_ms,
+        weights: EvaluationWeights::for_game_type(game_type),
         ..GameState::from_position(position)
     };
     game_state.game_type = game_type;
 
+    let mut clock = args.time_control.map(GameClock::new);
+
+    if let Some(time_control) = args.time_control {
+        println!("\nTime control: {}", time_control);
+    }
+
     println!("\nStarting position:");
     println!(
         "{}",
@@ -69,7 +108,7 @@ fn main() {
             .to_board_string_with_rank_file(args.unicode)
     );
 
-    while !game_state.current_position.is_checkmate(game_type).unwrap() {
+    while game_state.game_status().unwrap().is_ongoing() {
         let mut is_blacks_turn = game_state.current_turn == Color::Black;
 
         println!(
@@ -78,16 +117,33 @@ fn main() {
             if is_blacks_turn { "Black" } else { "White" }
         );
 
+        let turn = game_state.current_turn;
+        if let Some(clock) = &clock {
+            game_state.time_limit_ms = clock.time_for_move(turn);
+        }
+
+        let start = Instant::now();
         let (best_move, stats) = game_state.search_and_apply().unwrap();
+        let elapsed_ms = start.elapsed().as_millis() as u64;
 
         println!("Best move: {}", best_move);
 
+        if let Some(clock) = &mut clock {
+            let in_time = clock.record_move(turn, elapsed_ms);
+            println!(
+                "Clock: White {:.1}s, Black {:.1}s",
+                clock.remaining(Color::White) as f64 / 1000.0,
+                clock.remaining(Color::Black) as f64 / 1000.0
+            );
+
+            if !in_time {
+                break;
+            }
+        }
+
         is_blacks_turn = !is_blacks_turn;
 
-        println!("Nodes searched: {}", stats.nodes_searched);
-        println!("Time taken: {}", stats.time_taken_ms);
-        println!("Cache hits: {}", stats.cached_positions);
-        println!("Pruned: {}", stats.pruned);
+        print!("{}", stats);
 
         println!("\nPosition after {}:", best_move);
         println!(
@@ -116,8 +172,11 @@ fn main() {
         thread::sleep(Duration::from_millis(args.pause_ms));
     }
 
-    println!("\nGame Over!");
-    // Note: You might need to adjust the winner detection based on your engine's implementation
+    match clock.as_ref().and_then(|clock| clock.flagged) {
+        Some(Color::White) => println!("\nGame Over! White lost on time"),
+        Some(Color::Black) => println!("\nGame Over! Black lost on time"),
+        None => println!("\nGame Over! {}", game_state.game_status().unwrap()),
+    }
     println!("Final position:");
     println!(
         "{}",
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use clap::Parser;
+use rescue_chess::server::{Server, ServerConfig};
+
+/// Serves the engine over HTTP and WebSocket, for web frontends and other languages.
+#[derive(Parser)]
+struct Cli {
+    /// The address to listen on
+    #[arg(short = 'a', long, default_value = "127.0.0.1:8080")]
+    pub address: String,
+
+    /// The deepest to search when asked for a best move without a depth
+    #[arg(short = 'd', long, default_value = "8")]
+    pub depth: u32,
+
+    /// The longest to search when asked for a best move without a time limit, in milliseconds
+    #[arg(short = 't', long, default_value = "5000")]
+    pub time_limit_ms: u64,
+
+    /// The size of each game's transposition table, in megabytes
+    #[arg(long, default_value = "16")]
+    pub hash_mb: usize,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .init();
+
+    let mut config = ServerConfig::new();
+    config.depth = args.depth;
+    config.time_limit_ms = args.time_limit_ms;
+    config.hash_mb = args.hash_mb;
+
+    Arc::new(Server::new(config)).serve(&args.address)
+}
@@ -1,28 +1,25 @@
 use clap::Parser;
 use crossbeam::channel;
 use num_cpus;
-use rescue_chess::{
-    piece_move::GameType,
-    position::extended_fen::{EpdOperand, ExtendedPosition},
-    search::{
-        alpha_beta::{self, SearchParams},
-        search_results::SearchState,
-        transposition_table::TranspositionTable,
-    },
-    Color,
-};
-use std::fs::File;
-use std::io::{self, BufRead};
+use rescue_chess::test_suite::{self, TestResult, TestSuiteRunner};
+use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Instant;
 
+/// Runs an EPD test suite, such as WAC or STS, searching each position for a fixed time and
+/// reporting how many were solved.
 #[derive(Parser)]
 struct Cli {
-    #[arg(short = 'd', long)]
-    pub depth: Option<u32>,
+    /// How long to search each position for, in milliseconds
+    #[arg(short = 't', long, default_value_t = 1000)]
+    pub time: u64,
+
+    /// The deepest to search each position, even with time left
+    #[arg(short = 'd', long, default_value_t = 30)]
+    pub depth: u32,
 
     #[arg(short = 'v', long)]
     pub verbose: bool,
@@ -33,130 +30,36 @@ struct Cli {
     #[arg(short = 'j', long, default_value_t = num_cpus::get())]
     pub jobs: usize,
 
-    /// Path to the EPD file containing the Strategic Test Suite
+    /// Path to the EPD file containing the test suite
     pub epd_file: PathBuf,
 }
 
-#[derive(Debug)]
-struct TestResult {
-    position_number: usize,
-    success: bool,
-    error: Option<String>,
-
-    #[allow(dead_code)]
-    time_taken_ms: u128,
-    position_output: String,
-    stats_output: Option<String>,
-}
-
-fn run_position_test(
-    position: ExtendedPosition,
-    depth: u32,
-    stats: bool,
-    position_number: usize,
-) -> TestResult {
-    let start_time = Instant::now();
-    let mut output = String::new();
-    let mut stats_output = None;
-
-    let mut transposition_table = TranspositionTable::new();
-    let mut state = SearchState::new(&mut transposition_table);
-
-    let params = SearchParams {
-        depth,
-        game_type: GameType::Classic,
-        debug_print_verbose: false,
-        ..Default::default()
-    };
-
-    let result = match alpha_beta::search(&position.position, &mut state, params, 0) {
-        Ok(r) => r,
-        Err(e) => {
-            return TestResult {
-                position_number,
-                success: false,
-                error: Some(format!("Search error: {}", e)),
-                time_taken_ms: start_time.elapsed().as_millis(),
-                position_output: String::new(),
-                stats_output: None,
+fn print_result(number: usize, result: &Result<TestResult, String>, stats: bool) {
+    match result {
+        Ok(result) => {
+            println!(
+                "\nPosition {}{}: {}",
+                number,
+                result
+                    .id
+                    .as_ref()
+                    .map(|id| format!(" ({})", id))
+                    .unwrap_or_default(),
+                if result.passed { "passed" } else { "failed" }
+            );
+            println!(
+                "Found move: {}",
+                result.found.as_deref().unwrap_or("(none)")
+            );
+
+            if stats {
+                println!("Score: {}", result.score);
+                println!("Depth: {}", result.depth);
+                println!("Nodes searched: {}", result.nodes_searched);
+                println!("Time taken: {}ms", result.time_taken_ms);
             }
         }
-    };
-
-    let best_move = match result.best_move {
-        Some(m) => {
-            if position.position.true_active_color == Color::White {
-                m.to_string()
-            } else {
-                m.inverted().to_string()
-            }
-        }
-        None => {
-            return TestResult {
-                position_number,
-                success: false,
-                error: Some("No best move found".to_string()),
-                time_taken_ms: start_time.elapsed().as_millis(),
-                position_output: String::new(),
-                stats_output: None,
-            }
-        }
-    };
-
-    let expected_best_move = match position.get_operation("bm").and_then(|ops| ops.first()) {
-        Some(EpdOperand::SanMove(expected)) => expected,
-        _ => {
-            return TestResult {
-                position_number,
-                success: false,
-                error: Some("Invalid or missing best move in EPD".to_string()),
-                time_taken_ms: start_time.elapsed().as_millis(),
-                position_output: String::new(),
-                stats_output: None,
-            }
-        }
-    };
-
-    let success = best_move == *expected_best_move;
-
-    if !success {
-        if let Some(id) = position.get_operation("id").and_then(|ops| ops.first()) {
-            output.push_str(&format!("\nPosition ID: {:?}\n", id));
-        }
-        output.push_str(&format!("Position {}\n", position_number));
-        output.push_str(&format!("Expected best move: {}\n", expected_best_move));
-        output.push_str(&format!("Found best move: {}\n", best_move));
-        output.push_str(&format!(
-            "Principal variation: {:?}\n",
-            result.principal_variation
-        ));
-        output.push_str(&format!("{}\n", position.position.to_fen()))
-    }
-
-    if stats {
-        let mut stats = String::new();
-        stats.push_str(&format!("Position {} stats:\n", position_number));
-        stats.push_str(&format!("Nodes searched: {}\n", state.data.nodes_searched));
-        stats.push_str(&format!(
-            "Cached positions: {}\n",
-            state.data.cached_positions
-        ));
-        stats.push_str(&format!(
-            "Time taken: {}ms\n",
-            start_time.elapsed().as_millis()
-        ));
-        stats.push_str(&format!("Pruned: {}\n", state.data.pruned));
-        stats.push_str(&format!("Score: {}\n", result.score));
-        stats_output = Some(stats);
-    }
-
-    TestResult {
-        position_number,
-        success,
-        error: None,
-        time_taken_ms: start_time.elapsed().as_millis(),
-        position_output: output,
-        stats_output,
+        Err(error) => println!("\nPosition {}: Error - {}", number, error),
     }
 }
 
@@ -174,23 +77,22 @@ fn main() -> Result<(), anyhow::Error> {
         .init();
 
     let args = Cli::parse();
-    let depth = args.depth.unwrap_or(3);
 
     println!("Running with {} worker threads", args.jobs);
 
-    let file = File::open(&args.epd_file)?;
-    let reader = io::BufReader::new(file);
-    let positions: Vec<_> = reader
-        .lines()
-        .enumerate()
-        .filter_map(|(i, line)| match line {
-            Ok(l) if !l.trim().is_empty() => Some((i + 1, l)),
-            _ => None,
-        })
-        .collect();
+    let positions = test_suite::parse_suite(&fs::read_to_string(&args.epd_file)?)?;
 
     let total_positions = positions.len();
-    println!("Loaded {} positions", total_positions);
+    println!(
+        "Loaded {} positions, searching each for {}ms",
+        total_positions, args.time
+    );
+
+    let runner = TestSuiteRunner {
+        time_limit_ms: args.time,
+        max_depth: args.depth,
+        ..Default::default()
+    };
 
     let (sender, receiver) = channel::unbounded();
     let completed = Arc::new(AtomicUsize::new(0));
@@ -219,25 +121,15 @@ fn main() -> Result<(), anyhow::Error> {
         .build()?;
 
     pool.scope(|s| {
-        for (position_number, line) in positions {
+        for (i, position) in positions.iter().enumerate() {
             let sender = sender.clone();
             let completed = completed.clone();
-            let stats = args.stats;
+            let runner = &runner;
 
             s.spawn(move |_| {
-                let result = match ExtendedPosition::parse_from_epd(&line) {
-                    Ok(position) => run_position_test(position, depth, stats, position_number),
-                    Err(e) => TestResult {
-                        position_number,
-                        success: false,
-                        error: Some(format!("Parse error: {}", e)),
-                        time_taken_ms: 0,
-                        position_output: String::new(),
-                        stats_output: None,
-                    },
-                };
+                let result = runner.run_position(position).map_err(|e| e.to_string());
 
-                sender.send(result).unwrap();
+                sender.send((i + 1, result)).unwrap();
                 completed.fetch_add(1, Ordering::Relaxed);
             });
         }
@@ -245,47 +137,31 @@ fn main() -> Result<(), anyhow::Error> {
 
     drop(sender);
 
-    let mut results: Vec<TestResult> = receiver.iter().collect();
-    results.sort_by_key(|r| r.position_number);
+    let mut results: Vec<(usize, Result<TestResult, String>)> = receiver.iter().collect();
+    results.sort_by_key(|(number, _)| *number);
 
-    let successful_positions = results.iter().filter(|r| r.success).count();
+    let results = test_suite::SuiteResults {
+        results: results.into_iter().map(|(_, result)| result).collect(),
+    };
     let total_time = start_time.elapsed();
 
     println!("\nFinal Results:");
     println!("Total positions tested: {}", total_positions);
-    println!("Successful positions: {}", successful_positions);
-    println!(
-        "Success rate: {:.1}%",
-        (successful_positions as f64 / total_positions as f64) * 100.0
-    );
+    println!("Successful positions: {}", results.passed());
+    println!("Success rate: {:.1}%", results.pass_rate() * 100.0);
     println!("Total time: {:.2}s", total_time.as_secs_f64());
 
     println!("\nFailed Positions:");
-    for result in results.iter().filter(|r| !r.success) {
-        if let Some(error) = &result.error {
-            println!("\nPosition {}: Error - {}", result.position_number, error);
-        } else {
-            print!("{}", result.position_output);
-        }
-
-        if args.stats {
-            if let Some(stats) = &result.stats_output {
-                print!("{}", stats);
-            }
+    for (i, result) in results.results.iter().enumerate() {
+        if !matches!(result, Ok(TestResult { passed: true, .. })) {
+            print_result(i + 1, result, args.stats);
         }
     }
 
     if args.verbose {
         println!("\nAll Positions:");
-        for result in &results {
-            if !result.position_output.is_empty() {
-                print!("{}", result.position_output);
-            }
-            if args.stats {
-                if let Some(stats) = &result.stats_output {
-                    print!("{}", stats);
-                }
-            }
+        for (i, result) in results.results.iter().enumerate() {
+            print_result(i + 1, result, args.stats);
         }
     }
 
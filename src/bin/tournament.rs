@@ -0,0 +1,167 @@
+use clap::Parser;
+use rescue_chess::{
+    piece_move::GameType,
+    tournament::{run_match, Adjudication, EngineConfig, MatchConfig, Sprt},
+};
+use std::{fs, path::Path};
+
+/// Plays a match between two engine configurations to test whether a change to the search or
+/// the evaluation makes the engine stronger, e.g.
+/// `tournament --first new.json --second base.json --sprt`.
+///
+/// Engine configurations are JSON with a name, features, and optionally weights and a search
+/// algorithm. Anything left out keeps its default, so `{"name": "no-lmr", "features":
+/// {"enable_lmr": false}}` is the default engine without late move reductions, and `{"name":
+/// "greedy", "algorithm": "greedy"}` is a baseline that only grabs material.
+#[derive(Parser)]
+struct Cli {
+    /// The engine being tested, the default engine if left out
+    #[arg(long)]
+    pub first: Option<String>,
+
+    /// The engine it's tested against, the default engine if left out
+    #[arg(long)]
+    pub second: Option<String>,
+
+    #[arg(short = 'c', long)]
+    pub classic: bool,
+
+    /// The most games to play
+    #[arg(short = 'g', long, default_value_t = 1000)]
+    pub games: usize,
+
+    #[arg(short = 'd', long, default_value_t = 6)]
+    pub depth: u32,
+
+    /// How long each engine thinks about each move, in milliseconds
+    #[arg(short = 't', long = "time", default_value_t = 100)]
+    pub time_limit_ms: u64,
+
+    /// How many pairs of games to play at the same time
+    #[arg(short = 'j', long, default_value_t = num_cpus::get())]
+    pub jobs: usize,
+
+    /// A file of opening lines in SAN, one per line, instead of the built-in ones
+    #[arg(short = 'o', long)]
+    pub openings: Option<String>,
+
+    /// Draw games this many plies long
+    #[arg(long, default_value_t = 300)]
+    pub max_plies: usize,
+
+    /// Resign games both engines agree are this many centipawns in one side's favor...
+    #[arg(long, default_value_t = 1000)]
+    pub resign_score: i32,
+
+    /// ...for this many plies in a row
+    #[arg(long, default_value_t = 8)]
+    pub resign_plies: usize,
+
+    /// Stop as soon as an SPRT decides between elo0 and elo1
+    #[arg(long)]
+    pub sprt: bool,
+
+    #[arg(long, default_value_t = 0.0, requires = "sprt")]
+    pub elo0: f64,
+
+    #[arg(long, default_value_t = 5.0, requires = "sprt")]
+    pub elo1: f64,
+
+    #[arg(long, default_value_t = 0.05, requires = "sprt")]
+    pub alpha: f64,
+
+    #[arg(long, default_value_t = 0.05, requires = "sprt")]
+    pub beta: f64,
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let args = Cli::parse();
+
+    let first = load_engine(args.first.as_deref(), "first")?;
+    let second = load_engine(args.second.as_deref(), "second")?;
+
+    let openings = match &args.openings {
+        Some(path) => fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect(),
+        None => MatchConfig::default().openings,
+    };
+
+    let sprt = args.sprt.then_some(Sprt {
+        elo0: args.elo0,
+        elo1: args.elo1,
+        alpha: args.alpha,
+        beta: args.beta,
+    });
+
+    let config = MatchConfig {
+        game_type: if args.classic {
+            GameType::Classic
+        } else {
+            GameType::Rescue
+        },
+        depth: args.depth,
+        time_limit_ms: args.time_limit_ms,
+        games: args.games,
+        concurrency: args.jobs,
+        openings,
+        adjudication: Adjudication {
+            max_plies: args.max_plies,
+            resign_score: args.resign_score,
+            resign_plies: args.resign_plies,
+        },
+        sprt,
+    };
+
+    println!("{} vs {}", first.name, second.name);
+
+    let results = run_match(&config, &first, &second, |record, score| {
+        println!(
+            "Game {}: {} as {:?}, {} ({}{}) after {}",
+            score.games(),
+            first.name,
+            record.first_color,
+            record.outcome,
+            record.game.status(),
+            if record.adjudicated {
+                ", adjudicated"
+            } else {
+                ""
+            },
+            record.opening,
+        );
+        print!("  {}", score);
+        if let Some(sprt) = &sprt {
+            let (lower, upper) = sprt.bounds();
+            print!(", LLR {:.2} ({:.2}, {:.2})", sprt.llr(score), lower, upper);
+        }
+        println!();
+    })?;
+
+    println!("\n{} vs {}: {}", first.name, second.name, results.score);
+    match results.sprt {
+        Some(decision) => println!("SPRT: {}", decision),
+        None if args.sprt => println!("SPRT: no decision"),
+        None => {}
+    }
+
+    Ok(())
+}
+
+fn load_engine(path: Option<&str>, default_name: &str) -> Result<EngineConfig, anyhow::Error> {
+    let Some(path) = path else {
+        return Ok(EngineConfig::new(default_name));
+    };
+
+    let mut engine: EngineConfig = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if engine.name.is_empty() {
+        engine.name = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| default_name.to_string());
+    }
+
+    Ok(engine)
+}
@@ -8,36 +8,36 @@ pub struct Bitboard(pub u64);
 impl Bitboard {
     /// Gets whether the specified position is occupied by a piece.
     #[inline(always)]
-    pub fn get(&self, position: Pos) -> bool {
+    pub const fn get(&self, position: Pos) -> bool {
         self.0 & (1 << position.0) != 0
     }
 
     /// Sets the specified position to be occupied by a piece.
     #[inline(always)]
-    pub fn set(&mut self, position: Pos) {
+    pub const fn set(&mut self, position: Pos) {
         self.0 |= 1 << position.0;
     }
 
     /// Sets the specified position to be occupied by a piece and returns. For use in method chaining.
     #[inline(always)]
-    pub fn with(mut self, position: Pos) -> Self {
+    pub const fn with(mut self, position: Pos) -> Self {
         self.0 |= 1 << position.0;
         self
     }
 
     #[inline(always)]
-    pub fn clear(&mut self, position: Pos) {
+    pub const fn clear(&mut self, position: Pos) {
         self.0 &= !(1 << position.0);
     }
 
     /// Creates a new empty bitboard. All positions are set to 0.
     #[inline(always)]
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Bitboard(0)
     }
 
     #[inline(always)]
-    pub fn count(&self) -> u8 {
+    pub const fn count(&self) -> u8 {
         self.0.count_ones() as u8
     }
 
@@ -150,6 +150,82 @@ impl Bitboard {
         }
         board
     }
+
+    /// The squares strictly between two squares on a shared rank, file or diagonal, or an empty
+    /// bitboard if they aren't aligned.
+    #[inline(always)]
+    pub fn between(a: Pos, b: Pos) -> Self {
+        BETWEEN[a.0 as usize][b.0 as usize]
+    }
+
+    /// The whole rank, file or diagonal through two aligned squares, from edge to edge, or an
+    /// empty bitboard if they aren't aligned.
+    #[inline(always)]
+    pub fn line(a: Pos, b: Pos) -> Self {
+        LINE[a.0 as usize][b.0 as usize]
+    }
+}
+
+const LINE_DIRECTIONS: [(i8, i8); 8] = [
+    (0, -1),
+    (0, 1),
+    (-1, 0),
+    (1, 0),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+static BETWEEN: [[Bitboard; 64]; 64] = line_table(false);
+static LINE: [[Bitboard; 64]; 64] = line_table(true);
+
+/// The squares from a square in one direction to the edge of the board, not including the
+/// square itself.
+const fn ray(square: usize, (dx, dy): (i8, i8)) -> u64 {
+    let mut ray = 0u64;
+    let mut x = (square % 8) as i8 + dx;
+    let mut y = (square / 8) as i8 + dy;
+
+    while x >= 0 && x < 8 && y >= 0 && y < 8 {
+        ray |= 1 << (x + y * 8);
+        x += dx;
+        y += dy;
+    }
+
+    ray
+}
+
+/// Builds the between table, or with `full_line` the line table, by walking every direction
+/// from every square.
+const fn line_table(full_line: bool) -> [[Bitboard; 64]; 64] {
+    let mut table = [[Bitboard(0); 64]; 64];
+
+    let mut a = 0;
+    while a < 64 {
+        let mut d = 0;
+        while d < LINE_DIRECTIONS.len() {
+            let (dx, dy) = LINE_DIRECTIONS[d];
+            let line = ray(a, (dx, dy)) | ray(a, (-dx, -dy)) | (1 << a);
+
+            let mut between = 0u64;
+            let mut x = (a % 8) as i8 + dx;
+            let mut y = (a / 8) as i8 + dy;
+
+            while x >= 0 && x < 8 && y >= 0 && y < 8 {
+                let b = (x + y * 8) as usize;
+                table[a][b] = Bitboard(if full_line { line } else { between });
+                between |= 1 << b;
+                x += dx;
+                y += dy;
+            }
+
+            d += 1;
+        }
+        a += 1;
+    }
+
+    table
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -543,4 +619,33 @@ mod tests {
     fn adjacent_files() {
         println!("{}", Bitboard::adjacent_files(3));
     }
+
+    #[test]
+    fn between_and_line() {
+        use crate::pos;
+
+        assert_eq!(
+            Bitboard::between(pos::A1, pos::H8),
+            Bitboard::from_squares(&[
+                Pos::xy(1, 6),
+                Pos::xy(2, 5),
+                Pos::xy(3, 4),
+                Pos::xy(4, 3),
+                Pos::xy(5, 2),
+                Pos::xy(6, 1),
+            ])
+        );
+        assert_eq!(
+            Bitboard::between(pos::H8, pos::A1),
+            Bitboard::between(pos::A1, pos::H8)
+        );
+        assert_eq!(Bitboard::between(pos::A1, pos::B1), Bitboard::new());
+        assert_eq!(Bitboard::between(pos::A1, Pos::xy(1, 5)), Bitboard::new());
+
+        assert_eq!(
+            Bitboard::line(Pos::xy(2, 7), Pos::xy(2, 5)),
+            Bitboard::for_file(2)
+        );
+        assert_eq!(Bitboard::line(pos::A1, Pos::xy(1, 5)), Bitboard::new());
+    }
 }
@@ -0,0 +1,448 @@
+//! An adapter for playing online as a bot through the Lichess Bot API, or any server that
+//! speaks it. Only built with the `bot` feature.
+//!
+//! The bot listens to its account's event stream, accepts standard chess challenges and
+//! declines the rest, and plays each game it starts on its own thread, streaming the game's
+//! state and answering with the engine's moves. Only classic chess is played, since no server
+//! knows Rescue's rules.
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    thread,
+};
+
+use serde::Deserialize;
+use tracing::{error, trace};
+
+use crate::{
+    piece_move::GameType,
+    search::{game_state::GameState, time_control::time_for_move},
+    Color, PieceMove, Position,
+};
+
+/// Where the bot connects, and how it plays.
+#[derive(Debug, Clone)]
+pub struct BotConfig {
+    /// The server's base URL, e.g. `https://lichess.org`.
+    pub endpoint: String,
+
+    /// The bot account's API token.
+    pub token: String,
+
+    /// The deepest the engine searches, though the clock usually stops it first.
+    pub depth: u32,
+}
+
+impl BotConfig {
+    pub fn new(endpoint: &str, token: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            depth: 20,
+        }
+    }
+}
+
+/// An event from the account's event stream. Events the bot has no use for are `Other`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BotEvent {
+    Challenge {
+        challenge: Challenge,
+    },
+    GameStart {
+        game: GameStart,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Challenge {
+    pub id: String,
+    pub variant: Variant,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Variant {
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GameStart {
+    #[serde(rename = "gameId")]
+    pub id: String,
+}
+
+/// An event from a game's stream. The first is always `GameFull`, followed by a `GameState`
+/// after every move and whenever the clocks or the game's status change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum GameEvent {
+    #[serde(rename_all = "camelCase")]
+    GameFull {
+        white: Player,
+        black: Player,
+
+        /// The starting position's FEN, or `startpos`.
+        initial_fen: String,
+        state: GameStateEvent,
+    },
+    GameState(GameStateEvent),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Player {
+    /// The player's account id, missing for the AI and anonymous players.
+    pub id: Option<String>,
+}
+
+/// A game's moves and clocks. Times are in milliseconds.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GameStateEvent {
+    /// Every move played so far in UCI notation, separated by spaces.
+    pub moves: String,
+    pub wtime: u64,
+    pub btime: u64,
+    pub winc: u64,
+    pub binc: u64,
+
+    /// `started` while the game is being played.
+    pub status: String,
+}
+
+impl GameStateEvent {
+    pub fn is_started(&self) -> bool {
+        self.status == "started"
+    }
+
+    /// How long a side should think about its move, given the time on its clock.
+    pub fn time_for_move(&self, color: Color) -> u64 {
+        match color {
+            Color::White => time_for_move(self.wtime, self.winc, None),
+            Color::Black => time_for_move(self.btime, self.binc, None),
+        }
+    }
+}
+
+/// One game the bot is playing: the engine's state and the color it plays.
+pub struct BotGame {
+    pub color: Color,
+    pub game_state: GameState,
+
+    /// How many of the game's moves have been played on `game_state`.
+    moves_played: usize,
+}
+
+impl BotGame {
+    /// A game from its `GameFull` event, playing the side whose player is `account_id`.
+    pub fn new(
+        account_id: &str,
+        white: &Player,
+        black: &Player,
+        initial_fen: &str,
+        depth: u32,
+    ) -> Result<Self, anyhow::Error> {
+        let color = if white.id.as_deref() == Some(account_id) {
+            Color::White
+        } else if black.id.as_deref() == Some(account_id) {
+            Color::Black
+        } else {
+            return Err(anyhow::anyhow!("{} isn't playing this game", account_id));
+        };
+
+        let position = if initial_fen == "startpos" {
+            Position::start_position()
+        } else {
+            Position::parse_from_fen(initial_fen)?
+        };
+
+        let mut game_state = GameState::from_position(position);
+        game_state.current_turn = game_state.current_position.true_active_color;
+        game_state.game_type = GameType::Classic;
+        game_state.search_depth = depth;
+
+        Ok(Self {
+            color,
+            game_state,
+            moves_played: 0,
+        })
+    }
+
+    /// Catches up with the game's moves, then if it's the bot's turn, searches for a move and
+    /// returns it in UCI notation to send to the server.
+    pub fn update(&mut self, state: &GameStateEvent) -> Result<Option<String>, anyhow::Error> {
+        let moves: Vec<&str> = state.moves.split_whitespace().collect();
+
+        for notation in moves.iter().skip(self.moves_played) {
+            self.apply_uci_move(notation)?;
+        }
+
+        if !state.is_started() || self.game_state.current_turn != self.color {
+            return Ok(None);
+        }
+
+        self.game_state.time_limit_ms = state.time_for_move(self.color);
+        trace!("Time limit: {} ms", self.game_state.time_limit_ms);
+
+        let (best_move, _) = self.game_state.search_and_apply()?;
+        self.moves_played += 1;
+
+        let best_move = match self.color {
+            Color::White => best_move,
+            Color::Black => best_move.inverted(),
+        };
+
+        Ok(Some(best_move.to_uci()))
+    }
+
+    fn apply_uci_move(&mut self, notation: &str) -> Result<(), anyhow::Error> {
+        let game_state = &mut self.game_state;
+
+        let mv = if game_state.current_turn == Color::White {
+            PieceMove::from_uci(&game_state.current_position, notation, game_state.game_type)
+        } else {
+            PieceMove::from_uci_inverted(
+                &game_state.current_position,
+                notation,
+                game_state.game_type,
+            )
+        }?;
+
+        game_state.apply_move(mv)?;
+        self.moves_played += 1;
+
+        Ok(())
+    }
+}
+
+/// The bot, connected to a server.
+#[derive(Clone)]
+pub struct Bot {
+    config: BotConfig,
+    agent: ureq::Agent,
+}
+
+impl Bot {
+    pub fn new(config: BotConfig) -> Self {
+        Self {
+            config,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    /// Listens for challenges and games until the event stream closes.
+    pub fn run(&self) -> Result<(), anyhow::Error> {
+        let account_id = self.account_id()?;
+        trace!("Connected as {}", account_id);
+
+        self.stream("/api/stream/event", |event: BotEvent| {
+            match event {
+                BotEvent::Challenge { challenge } => {
+                    if let Err(e) = self.answer_challenge(&challenge) {
+                        error!("Error answering challenge {}: {}", challenge.id, e);
+                    }
+                }
+                BotEvent::GameStart { game } => {
+                    let bot = self.clone();
+                    let account_id = account_id.clone();
+
+                    thread::spawn(move || {
+                        if let Err(e) = bot.play_game(&account_id, &game.id) {
+                            error!("Error playing game {}: {}", game.id, e);
+                        }
+                    });
+                }
+                BotEvent::Other => {}
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Plays a game until it ends or its stream closes.
+    pub fn play_game(&self, account_id: &str, game_id: &str) -> Result<(), anyhow::Error> {
+        let mut game = None;
+
+        self.stream(
+            &format!("/api/bot/game/stream/{}", game_id),
+            |event: GameEvent| {
+                let state = match event {
+                    GameEvent::GameFull {
+                        white,
+                        black,
+                        initial_fen,
+                        state,
+                    } => {
+                        game = Some(BotGame::new(
+                            account_id,
+                            &white,
+                            &black,
+                            &initial_fen,
+                            self.config.depth,
+                        )?);
+                        state
+                    }
+                    GameEvent::GameState(state) => state,
+                    GameEvent::Other => return Ok(()),
+                };
+
+                let Some(game) = game.as_mut() else {
+                    return Err(anyhow::anyhow!("Game state before the full game"));
+                };
+
+                if let Some(mv) = game.update(&state)? {
+                    trace!("Playing {} in game {}", mv, game_id);
+                    self.post(&format!("/api/bot/game/{}/move/{}", game_id, mv), &[])?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+
+    fn account_id(&self) -> Result<String, anyhow::Error> {
+        #[derive(Deserialize)]
+        struct Account {
+            id: String,
+        }
+
+        let account: Account = self.request("GET", "/api/account").call()?.into_json()?;
+
+        Ok(account.id)
+    }
+
+    fn answer_challenge(&self, challenge: &Challenge) -> Result<(), anyhow::Error> {
+        let id = &challenge.id;
+
+        if challenge.variant.key == "standard" {
+            trace!("Accepting challenge {}", id);
+            self.post(&format!("/api/challenge/{}/accept", id), &[])
+        } else {
+            trace!("Declining {} challenge {}", challenge.variant.key, id);
+            self.post(
+                &format!("/api/challenge/{}/decline", id),
+                &[("reason", "variant")],
+            )
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.agent
+            .request(method, &format!("{}{}", self.config.endpoint, path))
+            .set("Authorization", &format!("Bearer {}", self.config.token))
+    }
+
+    fn post(&self, path: &str, form: &[(&str, &str)]) -> Result<(), anyhow::Error> {
+        self.request("POST", path).send_form(form)?;
+        Ok(())
+    }
+
+    /// Reads a stream of newline-delimited JSON events, skipping the empty lines the server
+    /// sends to keep the connection alive.
+    fn stream<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        handle: impl FnMut(T) -> Result<(), anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        let response = self.request("GET", path).call()?;
+        read_events(response.into_reader(), handle)
+    }
+}
+
+/// Reads newline-delimited JSON events, skipping empty lines, until the reader ends or
+/// `handle` fails.
+fn read_events<T: for<'de> Deserialize<'de>>(
+    reader: impl Read,
+    mut handle: impl FnMut(T) -> Result<(), anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        trace!("Received: {}", line);
+        handle(serde_json::from_str(&line)?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_FULL: &str = r#"{"type":"gameFull","id":"abc","white":{"id":"opponent","name":"Opponent"},"black":{"id":"rescuebot","name":"RescueBot"},"initialFen":"startpos","state":{"type":"gameState","moves":"","wtime":60000,"btime":60000,"winc":0,"binc":0,"status":"started"}}"#;
+
+    #[test]
+    fn test_read_events() {
+        let stream = format!(
+            "{}\n\n{}\n{}\n",
+            r#"{"type":"challenge","challenge":{"id":"c1","variant":{"key":"standard"}}}"#,
+            r#"{"type":"gameStart","game":{"gameId":"g1"}}"#,
+            r#"{"type":"challengeCanceled","challenge":{"id":"c2"}}"#,
+        );
+
+        let mut events = vec![];
+        read_events(stream.as_bytes(), |event: BotEvent| {
+            events.push(event);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                BotEvent::Challenge {
+                    challenge: Challenge {
+                        id: "c1".to_string(),
+                        variant: Variant {
+                            key: "standard".to_string()
+                        },
+                    },
+                },
+                BotEvent::GameStart {
+                    game: GameStart {
+                        id: "g1".to_string()
+                    },
+                },
+                BotEvent::Other,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plays_its_side() {
+        let GameEvent::GameFull {
+            white,
+            black,
+            initial_fen,
+            mut state,
+        } = serde_json::from_str(GAME_FULL).unwrap()
+        else {
+            panic!("Expected the full game");
+        };
+
+        let mut game = BotGame::new("rescuebot", &white, &black, &initial_fen, 2).unwrap();
+        assert_eq!(game.color, Color::Black);
+
+        // Not the bot's turn yet
+        assert_eq!(game.update(&state).unwrap(), None);
+
+        state.moves = "e2e4".to_string();
+        let reply = game.update(&state).unwrap().unwrap();
+
+        // The reply is a legal move for black, in the board's own squares
+        let position = Position::from_uci_moves(&["e2e4"], GameType::Classic).unwrap();
+        assert!(PieceMove::from_uci_inverted(&position, &reply, GameType::Classic).is_ok());
+
+        // The server echoes the reply back, which was already played
+        state.moves = format!("e2e4 {}", reply);
+        assert_eq!(game.update(&state).unwrap(), None);
+        assert_eq!(game.game_state.current_turn, Color::White);
+        assert_eq!(game.game_state.num_plies, 2);
+    }
+}
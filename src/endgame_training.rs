@@ -0,0 +1,491 @@
+//! Generates themed endgame positions for training, checking each one with a search so that
+//! only positions with a clear result are kept.
+
+use std::{collections::HashSet, str::FromStr};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::{
+    piece_move::GameType,
+    position::extended_fen::{EpdOperand, ExtendedPosition},
+    search::{
+        alpha_beta::SearchParams, backend, score::Score, search_results::SearchState,
+        transposition_table::TranspositionTable,
+    },
+    Color, PieceMove, PieceType, Position,
+};
+
+/// A search score at least this good for the side to move counts as a win, e.g. a pawn that
+/// can't be stopped from queening.
+pub const WINNING_SCORE: i32 = 500;
+
+/// A search score at most this far from zero counts as a draw.
+pub const DRAWN_SCORE: i32 = 25;
+
+/// The kinds of endgame that can be generated. White is always the stronger side, and is
+/// always to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum EndgameTheme {
+    /// King and pawn against king.
+    KingAndPawn,
+
+    /// King, rook and pawn against king and rook.
+    RookAndPawnVsRook,
+
+    /// King and rook against king and pawn.
+    RookVsPawn,
+
+    /// Rescue: a king holding a queen against a lone king. The queen has to be dropped
+    /// before it can help to mate.
+    KingHoldingQueen,
+
+    /// Rescue: a king and a rook holding a pawn against king and rook.
+    RookHoldingPawnVsRook,
+}
+
+/// A piece to place for a theme: its type, the piece it's holding and its color.
+type ThemePiece = (PieceType, Option<PieceType>, Color);
+
+impl EndgameTheme {
+    pub const ALL: [EndgameTheme; 5] = [
+        EndgameTheme::KingAndPawn,
+        EndgameTheme::RookAndPawnVsRook,
+        EndgameTheme::RookVsPawn,
+        EndgameTheme::KingHoldingQueen,
+        EndgameTheme::RookHoldingPawnVsRook,
+    ];
+
+    /// The theme's short name, e.g. "KPK", as used in EPD output and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EndgameTheme::KingAndPawn => "KPK",
+            EndgameTheme::RookAndPawnVsRook => "KRPKR",
+            EndgameTheme::RookVsPawn => "KRKP",
+            EndgameTheme::KingHoldingQueen => "KxQK",
+            EndgameTheme::RookHoldingPawnVsRook => "KRxPKR",
+        }
+    }
+
+    pub fn game_type(&self) -> GameType {
+        match self {
+            EndgameTheme::KingAndPawn
+            | EndgameTheme::RookAndPawnVsRook
+            | EndgameTheme::RookVsPawn => GameType::Classic,
+            EndgameTheme::KingHoldingQueen | EndgameTheme::RookHoldingPawnVsRook => {
+                GameType::Rescue
+            }
+        }
+    }
+
+    fn pieces(&self) -> &'static [ThemePiece] {
+        use Color::*;
+        use PieceType::*;
+
+        match self {
+            EndgameTheme::KingAndPawn => &[
+                (King, None, White),
+                (Pawn, None, White),
+                (King, None, Black),
+            ],
+            EndgameTheme::RookAndPawnVsRook => &[
+                (King, None, White),
+                (Rook, None, White),
+                (Pawn, None, White),
+                (King, None, Black),
+                (Rook, None, Black),
+            ],
+            EndgameTheme::RookVsPawn => &[
+                (King, None, White),
+                (Rook, None, White),
+                (King, None, Black),
+                (Pawn, None, Black),
+            ],
+            EndgameTheme::KingHoldingQueen => &[(King, Some(Queen), White), (King, None, Black)],
+            EndgameTheme::RookHoldingPawnVsRook => &[
+                (King, None, White),
+                (Rook, Some(Pawn), White),
+                (King, None, Black),
+                (Rook, None, Black),
+            ],
+        }
+    }
+
+    /// Places the theme's pieces on random squares, returning None if the position is illegal.
+    fn random_position<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Position> {
+        let mut board: Vec<Option<String>> = vec![None; 64];
+
+        for &(piece_type, holding, color) in self.pieces() {
+            let square = loop {
+                let square = rng.gen_range(0..64usize);
+                let row = square / 8;
+
+                let pawn_on_back_rank = piece_type == PieceType::Pawn && (row == 0 || row == 7);
+                if board[square].is_none() && !pawn_on_back_rank {
+                    break square;
+                }
+            };
+
+            let mut letters = piece_type.to_algebraic(color).to_string();
+            if let Some(held) = holding {
+                letters.push('x');
+                letters.push_str(held.to_algebraic(color));
+            }
+
+            board[square] = Some(letters);
+        }
+
+        let ranks = board
+            .chunks(8)
+            .map(|rank| {
+                let mut fen = String::new();
+                let mut empty = 0;
+
+                for square in rank {
+                    match square {
+                        Some(letters) => {
+                            if empty > 0 {
+                                fen.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            fen.push_str(letters);
+                        }
+                        None => empty += 1,
+                    }
+                }
+
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                }
+
+                fen
+            })
+            .collect::<Vec<_>>();
+
+        Position::validate_fen(&format!("{} w - - 0 1", ranks.join("/"))).ok()
+    }
+}
+
+impl std::fmt::Display for EndgameTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for EndgameTheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EndgameTheme::ALL
+            .into_iter()
+            .find(|theme| theme.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names = EndgameTheme::ALL.map(|theme| theme.name()).join(", ");
+                format!("Unknown endgame theme {}, expected one of {}", s, names)
+            })
+    }
+}
+
+/// The result of a position with best play, for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Verdict {
+    /// The side to move wins, with a forced mate in this many moves if the search found one.
+    Win {
+        mate_in: Option<u32>,
+    },
+    Draw,
+    Loss {
+        mate_in: Option<u32>,
+    },
+
+    /// The search couldn't tell.
+    Unclear,
+}
+
+impl Verdict {
+    /// Classifies the score of a search, from the point of view of the side to move.
+    pub fn from_score(score: i32) -> Self {
+        if let Score::Mate(moves) = Score::from_search(score) {
+            let mate_in = Some(moves.unsigned_abs().max(1));
+            return if moves > 0 {
+                Verdict::Win { mate_in }
+            } else {
+                Verdict::Loss { mate_in }
+            };
+        }
+
+        match score {
+            score if score >= WINNING_SCORE => Verdict::Win { mate_in: None },
+            score if score <= -WINNING_SCORE => Verdict::Loss { mate_in: None },
+            score if score.abs() <= DRAWN_SCORE => Verdict::Draw,
+            _ => Verdict::Unclear,
+        }
+    }
+
+    /// The verdict as written in the EPD `c1` operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Win { .. } => "win",
+            Verdict::Draw => "draw",
+            Verdict::Loss { .. } => "loss",
+            Verdict::Unclear => "unclear",
+        }
+    }
+}
+
+/// A generated training position along with the search that checked it.
+#[derive(Debug, Clone)]
+pub struct EndgamePosition {
+    pub theme: EndgameTheme,
+
+    /// The position, from the perspective of the side to move.
+    pub position: Position,
+
+    pub verdict: Verdict,
+
+    /// The best move found, from the perspective of the side to move.
+    pub best_move: PieceMove,
+
+    pub score: i32,
+    pub depth: u32,
+}
+
+impl EndgamePosition {
+    /// The best move in algebraic notation on the real board.
+    pub fn best_move_notation(&self) -> String {
+        match self.position.true_active_color {
+            Color::White => self.best_move.to_string(),
+            Color::Black => self.best_move.inverted().to_string(),
+        }
+    }
+
+    /// The position as EPD, with the best move (`bm`), evaluation (`ce`), mate distance (`dm`),
+    /// search depth (`acd`), theme (`c0`) and verdict (`c1`) as operations.
+    pub fn to_extended_position(&self, id: &str) -> ExtendedPosition {
+        let mut epd = ExtendedPosition::new(self.position.clone());
+
+        epd.set_operation("id".to_string(), vec![EpdOperand::String(id.to_string())]);
+        epd.set_operation(
+            "bm".to_string(),
+            vec![EpdOperand::SanMove(self.best_move_notation())],
+        );
+        epd.set_operation("ce".to_string(), vec![EpdOperand::Integer(self.score)]);
+        epd.set_operation("acd".to_string(), vec![EpdOperand::Unsigned(self.depth)]);
+        epd.set_operation(
+            "c0".to_string(),
+            vec![EpdOperand::String(self.theme.name().to_string())],
+        );
+        epd.set_operation(
+            "c1".to_string(),
+            vec![EpdOperand::String(self.verdict.name().to_string())],
+        );
+
+        if let Verdict::Win {
+            mate_in: Some(mate_in),
+        } = self.verdict
+        {
+            epd.set_operation("dm".to_string(), vec![EpdOperand::Unsigned(mate_in)]);
+        }
+
+        epd
+    }
+}
+
+/// Generates positions for a theme, keeping the ones a search shows to be won for the side
+/// to move.
+pub struct EndgameGenerator {
+    /// How deep to search each position to check its result.
+    pub depth: u32,
+
+    /// How many random positions to try per position generated before giving up.
+    pub attempts_per_position: usize,
+}
+
+impl Default for EndgameGenerator {
+    fn default() -> Self {
+        Self {
+            depth: 8,
+            attempts_per_position: 200,
+        }
+    }
+}
+
+impl EndgameGenerator {
+    /// Searches a position from the perspective of its side to move to find its result.
+    pub fn solve(
+        &self,
+        position: &Position,
+        game_type: GameType,
+    ) -> Result<(Verdict, Option<PieceMove>, i32), anyhow::Error> {
+        let params = SearchParams::builder()
+            .depth(self.depth)
+            .game_type(game_type)
+            .build()?;
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+
+        let results = backend::search(position, &mut state, params, 0)
+            .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+
+        Ok((
+            Verdict::from_score(results.score),
+            results.best_move,
+            results.score,
+        ))
+    }
+
+    /// Generates up to `count` distinct winning positions for the theme. Fewer are returned if
+    /// not enough are found within the attempt limit.
+    pub fn generate<R: Rng + ?Sized>(
+        &self,
+        theme: EndgameTheme,
+        count: usize,
+        rng: &mut R,
+    ) -> Result<Vec<EndgamePosition>, anyhow::Error> {
+        let game_type = theme.game_type();
+        let mut positions = Vec::new();
+        let mut seen = HashSet::new();
+
+        for _ in 0..count * self.attempts_per_position {
+            if positions.len() == count {
+                break;
+            }
+
+            let Some(position) = theme.random_position(rng) else {
+                continue;
+            };
+
+            if !seen.insert(position.zobrist_key())
+                || position.game_status(game_type, &[])?.is_game_over()
+            {
+                continue;
+            }
+
+            let (verdict, best_move, score) = self.solve(&position, game_type)?;
+
+            if let (Verdict::Win { .. }, Some(best_move)) = (verdict, best_move) {
+                positions.push(EndgamePosition {
+                    theme,
+                    position,
+                    verdict,
+                    best_move,
+                    score,
+                    depth: self.depth,
+                });
+            }
+        }
+
+        Ok(positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::score::{mate_in, mated_in};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn solve(fen: &str, game_type: GameType, depth: u32) -> (Verdict, String) {
+        let generator = EndgameGenerator {
+            depth,
+            ..Default::default()
+        };
+        let position = Position::parse_from_fen(fen).unwrap();
+        let (verdict, best_move, _) = generator.solve(&position, game_type).unwrap();
+
+        let best_move = best_move.unwrap();
+        let notation = match position.true_active_color {
+            Color::White => best_move.to_string(),
+            Color::Black => best_move.inverted().to_string(),
+        };
+
+        (verdict, notation)
+    }
+
+    #[test]
+    fn test_verdict_from_score() {
+        assert_eq!(Verdict::from_score(0), Verdict::Draw);
+        assert_eq!(Verdict::from_score(150), Verdict::Unclear);
+        assert_eq!(Verdict::from_score(900), Verdict::Win { mate_in: None });
+        assert_eq!(Verdict::from_score(-900), Verdict::Loss { mate_in: None });
+
+        // Mating 3 plies from the root: mate in 2
+        assert_eq!(
+            Verdict::from_score(mate_in(3)),
+            Verdict::Win { mate_in: Some(2) }
+        );
+        assert_eq!(
+            Verdict::from_score(mated_in(2)),
+            Verdict::Loss { mate_in: Some(1) }
+        );
+    }
+
+    #[test]
+    fn test_solve() {
+        // The pawn queens
+        let (verdict, best_move) = solve("8/4P3/4K3/8/8/8/8/k7 w - - 0 1", GameType::Classic, 3);
+        assert_eq!(verdict, Verdict::Win { mate_in: None });
+        assert_eq!(best_move, "e8=Q");
+
+        // Black wins the last pawn
+        let (verdict, _) = solve("8/8/8/8/8/8/Pk6/7K b - - 0 1", GameType::Classic, 3);
+        assert_eq!(verdict, Verdict::Draw);
+    }
+
+    #[test]
+    fn test_theme_names() {
+        for theme in EndgameTheme::ALL {
+            assert_eq!(theme.name().parse::<EndgameTheme>(), Ok(theme));
+        }
+        assert_eq!("kpk".parse::<EndgameTheme>(), Ok(EndgameTheme::KingAndPawn));
+        assert!("KQKQ".parse::<EndgameTheme>().is_err());
+    }
+
+    #[test]
+    fn test_random_positions_are_legal() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for theme in EndgameTheme::ALL {
+            let mut generated = 0;
+
+            for _ in 0..50 {
+                if let Some(position) = theme.random_position(&mut rng) {
+                    assert!(position.validate().is_empty());
+                    assert_eq!(position.true_active_color, Color::White);
+                    generated += 1;
+                }
+            }
+
+            assert!(generated > 0, "No legal {} positions", theme);
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let generator = EndgameGenerator {
+            depth: 3,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let positions = generator
+            .generate(EndgameTheme::KingHoldingQueen, 2, &mut rng)
+            .unwrap();
+        assert_eq!(positions.len(), 2);
+
+        for (i, generated) in positions.iter().enumerate() {
+            assert!(matches!(generated.verdict, Verdict::Win { .. }));
+
+            let epd = generated
+                .to_extended_position(&format!("KxQK.{}", i + 1))
+                .to_epd();
+            assert!(epd.contains("Kx"), "{}", epd);
+            assert!(epd.contains("c0 \"KxQK\";"), "{}", epd);
+            assert!(epd.contains("c1 \"win\";"), "{}", epd);
+
+            let parsed = ExtendedPosition::parse_from_epd(&epd).unwrap();
+            assert_eq!(parsed.position, generated.position);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! An async facade over the search, for embedding the engine in async servers and apps.
+//!
+//! Searches run on their own thread, so they never block the executor, and any executor can
+//! await them: only tokio's channels are used, not its runtime.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use serde::Serialize;
+use tokio::sync::{oneshot, watch};
+
+use crate::{
+    search::{
+        alpha_beta::{AlphaBetaError, SearchParams},
+        backend,
+        search_results::{SearchResults, SearchState},
+        transposition_table::TranspositionTable,
+    },
+    PieceMove, Position,
+};
+
+/// The best line found so far by a running search, published after each finished depth.
+#[derive(Debug, Clone, Serialize)]
+pub struct PvUpdate {
+    pub depth: u32,
+    pub score: i32,
+    pub principal_variation: Vec<PieceMove>,
+    pub nodes_searched: u32,
+}
+
+/// An engine that searches one position at a time, keeping its transposition table between
+/// searches.
+pub struct Engine {
+    position: Position,
+
+    /// Zobrist keys of the positions in the game up to and including `position`.
+    history: Vec<u64>,
+
+    transposition_table: Arc<Mutex<TranspositionTable>>,
+
+    /// The stop flag of the latest search. Each search gets its own, so starting one never
+    /// un-stops another that's still winding down.
+    stop: Mutex<Arc<AtomicBool>>,
+    pv_sender: watch::Sender<Option<PvUpdate>>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let position = Position::start_position();
+        let (pv_sender, _) = watch::channel(None);
+
+        Self {
+            history: vec![position.zobrist_key()],
+            position,
+            transposition_table: Arc::new(Mutex::new(TranspositionTable::new())),
+            stop: Mutex::new(Arc::new(AtomicBool::new(false))),
+            pv_sender,
+        }
+    }
+
+    /// Sets the position to search, from the perspective of its side to move, and the Zobrist
+    /// keys of the game's positions up to and including it.
+    pub fn set_position(&mut self, position: Position, history: Vec<u64>) {
+        self.position = position;
+        self.history = history;
+    }
+
+    /// Receives the principal variation of the running search after each depth it finishes.
+    /// Holds `None` until the first search has finished a depth.
+    pub fn pv_updates(&self) -> watch::Receiver<Option<PvUpdate>> {
+        self.pv_sender.subscribe()
+    }
+
+    /// Stops the running search, which then resolves with the deepest depth it finished.
+    pub fn stop(&self) {
+        self.stop.lock().unwrap().store(true, Ordering::Relaxed);
+    }
+
+    /// Searches the current position, deepening one ply at a time up to the depth in `params`
+    /// until the time limit runs out or the search is stopped. Resolves with the deepest depth
+    /// that finished, or an error if not even the first one did.
+    pub fn go_async(
+        &self,
+        params: SearchParams,
+    ) -> impl Future<Output = Result<SearchResults, AlphaBetaError>> {
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let position = self.position.clone();
+        let history = self.history.clone();
+        let transposition_table = self.transposition_table.clone();
+        let pv_sender = self.pv_sender.clone();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        *self.stop.lock().unwrap() = stop.clone();
+
+        thread::spawn(move || {
+            let mut transposition_table = transposition_table.lock().unwrap();
+            transposition_table.new_search();
+
+            let mut state = SearchState::new(&mut transposition_table);
+            state.data.history = history;
+            state.data.stop = stop;
+            state.data.start_time = Instant::now();
+            state.data.time_limit = params.time_limit;
+
+            let results = search_deepening(&position, &mut state, params, |results| {
+                pv_sender.send_replace(Some(PvUpdate {
+                    depth: results.depth,
+                    score: results.score,
+                    principal_variation: results.principal_variation.clone().unwrap_or_default(),
+                    nodes_searched: results.nodes_searched,
+                }));
+            });
+
+            // The caller may have stopped waiting for the result
+            let _ = result_sender.send(results);
+        });
+
+        async move {
+            result_receiver
+                .await
+                .unwrap_or(Err(AlphaBetaError::Stopped))
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn search_deepening(
+    position: &Position,
+    state: &mut SearchState,
+    params: SearchParams,
+    mut on_depth: impl FnMut(&SearchResults),
+) -> Result<SearchResults, AlphaBetaError> {
+    let mut best: Option<SearchResults> = None;
+
+    for depth in 1..=params.depth {
+        let mut iteration_params = params.clone();
+        iteration_params.depth = depth;
+        iteration_params.previous_score = best.as_ref().map(|results| results.score);
+
+        match backend::search(position, state, iteration_params, 0) {
+            Ok(results) => {
+                state.data.previous_pv = results.principal_variation.clone();
+                on_depth(&results);
+
+                // Nothing deeper to search without legal moves
+                let finished = results.best_move.is_none();
+                best = Some(results);
+
+                if finished {
+                    break;
+                }
+            }
+            Err(_) if best.is_some() => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(best.expect("Search depth is at least 1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_go_async() {
+        let engine = Engine::new();
+        let updates = engine.pv_updates();
+
+        let params = SearchParams::builder().depth(4).build().unwrap();
+        let results = block_on(engine.go_async(params)).unwrap();
+
+        assert_eq!(results.depth, 4);
+        assert!(results.best_move.is_some());
+
+        let update = updates.borrow().clone().unwrap();
+        assert_eq!(update.depth, 4);
+        assert_eq!(
+            update.principal_variation.first(),
+            results.best_move.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_stop() {
+        let engine = Engine::new();
+
+        let params = SearchParams::builder().depth(30).build().unwrap();
+        let search = engine.go_async(params);
+        engine.stop();
+
+        // Stopped before finishing a depth, or resolving with the shallow depths that did
+        match block_on(search) {
+            Ok(results) => assert!(results.depth < 30),
+            Err(e) => assert!(matches!(e, AlphaBetaError::Stopped)),
+        }
+    }
+
+    #[test]
+    fn test_new_search_leaves_stopped_search_stopped() {
+        let engine = Engine::new();
+
+        let first = engine.go_async(SearchParams::builder().depth(30).build().unwrap());
+        engine.stop();
+        let second = engine.go_async(SearchParams::builder().depth(2).build().unwrap());
+
+        match block_on(first) {
+            Ok(results) => assert!(results.depth < 30),
+            Err(e) => assert!(matches!(e, AlphaBetaError::Stopped)),
+        }
+        assert_eq!(block_on(second).unwrap().depth, 2);
+    }
+}
@@ -1,49 +1,31 @@
 pub mod ordering;
+pub mod see;
 pub mod square_bonus;
 
+use std::cell::LazyCell;
+
 use crate::{
-    piece::pawn, piece_move::GameType, search::alpha_beta::SearchParams, Bitboard, Color,
-    PieceType, Pos, Position,
+    piece::{bishop, knight, pawn, queen, rook},
+    piece_move::GameType,
+    position::PieceMaps,
+    search::alpha_beta::SearchParams,
+    Bitboard, Color, PieceType, Pos, Position,
 };
 
-pub fn evaluate_position(board: &Position, _game_type: GameType, params: &SearchParams) -> i32 {
-    let mut score = 0;
-
-    let inverted = board.inverted();
-
-    // Bonuses for white pieces
-    for piece in board.white_pieces.iter() {
-        if let Some(piece) = piece {
-            let value = piece_value(piece.piece_type);
-            let piece_score = value + piece.square_bonus();
-
-            score += piece_score * params.weights.material / 100;
-
-            let holding_value = match piece.holding {
-                Some(piece_type) => piece_value(piece_type),
-                None => 0,
-            };
-
-            score += holding_value * params.weights.material / 100;
-        }
+pub fn evaluate_position(board: &Position, game_type: GameType, params: &SearchParams) -> i32 {
+    // A bishop or knight up is worth nothing if it can't be used to mate
+    if board.is_insufficient_material(game_type) {
+        return 0;
     }
 
-    // Penalties for black pieces
-    for piece in inverted.white_pieces.iter() {
-        if let Some(piece) = piece {
-            let value = piece_value(piece.piece_type);
-            let piece_score = value + piece.square_bonus();
+    let mut score = 0;
 
-            score -= piece_score * params.weights.material / 100;
+    // Only the dynamic terms look at the position from black's side
+    let inverted = LazyCell::new(|| board.inverted());
 
-            let holding_value = match piece.holding {
-                Some(piece_type) => piece_value(piece_type),
-                None => 0,
-            };
-
-            score -= holding_value * params.weights.material / 100;
-        }
-    }
+    // Material and square bonuses, including held pieces, are kept up to date as moves are
+    // made, leaving only the dynamic terms to compute here
+    score += board.material.score(game_type) * params.weights.material / 100;
 
     if params.features.evaluate_bishop_pairs {
         if has_bishop_pair(board, Color::White) {
@@ -125,15 +107,43 @@ pub fn evaluate_position(board: &Position, _game_type: GameType, params: &Search
         score += (white_defense - black_defense) * params.weights.pawn_defense_quality / 100;
     }
 
+    if params.features.evaluate_rooks {
+        let white_rooks = evaluate_rooks(board);
+        let black_rooks = evaluate_rooks(&inverted);
+        score += (white_rooks - black_rooks) * params.weights.rooks / 100;
+    }
+
+    if params.features.evaluate_pinned_pieces {
+        let white_pinned = evaluate_pinned_pieces(board);
+        let black_pinned = evaluate_pinned_pieces(&inverted);
+        score += (white_pinned - black_pinned) * params.weights.pinned_pieces / 100;
+    }
+
+    // Space and development only matter while there are pieces to develop and room to use
+    if params.features.evaluate_space || params.features.evaluate_development {
+        let phase = game_phase(board);
+
+        if params.features.evaluate_space {
+            let white_space = evaluate_space(board);
+            let black_space = evaluate_space(&inverted);
+            score +=
+                (white_space - black_space) * phase / MAX_GAME_PHASE * params.weights.space / 100;
+        }
+
+        if params.features.evaluate_development {
+            let white_development = evaluate_development(board);
+            let black_development = evaluate_development(&inverted);
+            score += (white_development - black_development) * phase / MAX_GAME_PHASE
+                * params.weights.development
+                / 100;
+        }
+    }
+
     score
 }
 
 fn has_bishop_pair(position: &Position, color: Color) -> bool {
-    let bishop_map = if color == Color::White {
-        position.get_piece_maps().white_bishops
-    } else {
-        position.get_piece_maps().black_bishops
-    };
+    let bishop_map = position.pieces(color, PieceType::Bishop);
 
     bishop_map.count() >= 2
         && bishop_map.intersects(Bitboard::light_squares())
@@ -164,8 +174,20 @@ fn evaluate_pawn_structure(position: &Position) -> i32 {
         }
     }
 
-    // Passed pawns
-    for pawn_pos in maps.white_pawns.into_iter() {
+    for pawn_pos in passed_pawns(position) {
+        score += 50 + (7 - pawn_pos.get_row() as i32) * 10;
+    }
+
+    score
+}
+
+/// White's passed pawns: those with no black pawns ahead of them on their own or an adjacent
+/// file.
+pub fn passed_pawns(position: &Position) -> Bitboard {
+    let maps = position.get_piece_maps();
+    let mut passed = Bitboard::new();
+
+    for pawn_pos in maps.white_pawns {
         let file = pawn_pos.get_col();
         let rank = pawn_pos.get_row();
 
@@ -174,16 +196,18 @@ fn evaluate_pawn_structure(position: &Position) -> i32 {
         let passed_mask = passed_mask & Bitboard::ahead_of_rank_white(rank);
 
         if !maps.black_pawns.intersects(passed_mask) {
-            score += 50 + (7 - rank as i32) * 10;
+            passed.set(pawn_pos);
         }
     }
 
-    score
+    passed
 }
 
 fn evaluate_pawn_structure_quality(position: &Position, inverted: &Position) -> i32 {
     let mut score = 0;
     let maps = position.get_piece_maps();
+    let attacks = position.attack_counts();
+    let enemy_attacks = inverted.attack_counts();
 
     // Evaluate pawn chains
     for pawn_pos in maps.white_pawns.into_iter() {
@@ -191,7 +215,7 @@ fn evaluate_pawn_structure_quality(position: &Position, inverted: &Position) ->
         let rank = pawn_pos.get_row();
 
         // Check for pawn chain
-        let protected_by_pawn = maps.white_pawns & *pawn::attack_map_black(pawn_pos);
+        let protected_by_pawn = maps.white_pawns & position.attackers_to(pawn_pos, Color::White);
 
         if protected_by_pawn.count() > 0 {
             score += 15; // Base chain bonus
@@ -211,8 +235,7 @@ fn evaluate_pawn_structure_quality(position: &Position, inverted: &Position) ->
             let enemy_pawn_rank = enemy_pawn_file.into_iter().next().unwrap().get_row();
             if (enemy_pawn_rank as i32 - rank as i32).abs() == 1 {
                 // Reward tension maintenance in good positions
-                if position.count_attackers(pawn_pos) >= inverted.count_attackers(pawn_pos.invert())
-                {
+                if attacks.get(pawn_pos) >= enemy_attacks.get(pawn_pos.invert()) {
                     score += 20; // Keep tension when stronger
                 }
             }
@@ -222,6 +245,41 @@ fn evaluate_pawn_structure_quality(position: &Position, inverted: &Position) ->
     score
 }
 
+/// Rooks are strongest on files without pawns of their own side, and on the seventh rank while
+/// there are pawns left at home there or the king is stuck behind it.
+fn evaluate_rooks(position: &Position) -> i32 {
+    let maps = position.get_piece_maps();
+    let mut score = 0;
+
+    for rook_pos in maps.white_rooks.into_iter() {
+        let file = Bitboard::for_file(rook_pos.get_col());
+
+        if !maps.white_pawns.intersects(file) {
+            if maps.black_pawns.intersects(file) {
+                score += 10; // Semi-open file
+            } else {
+                score += 25; // Open file
+            }
+        }
+
+        if rook_pos.get_row() == 1
+            && (maps.black_pawns.intersects(Bitboard::for_rank(1))
+                || maps.black_king.intersects(Bitboard::for_rank(0)))
+        {
+            score += 20;
+        }
+    }
+
+    // Doubled rooks back each other up
+    for file in 0..8 {
+        if (maps.white_rooks & Bitboard::for_file(file)).count() >= 2 {
+            score += 15;
+        }
+    }
+
+    score
+}
+
 fn evaluate_king_safety(position: &Position) -> i32 {
     let maps = position.get_piece_maps();
     let mut score = 0;
@@ -295,21 +353,23 @@ fn evaluate_pawn_control(position: &Position) -> i32 {
 
 fn evaluate_piece_protection(position: &Position, inverted: &Position) -> i32 {
     let mut score: i32 = 0;
-
-    for piece in &position.white_pieces {
-        if let Some(piece) = piece {
-            let pos = piece.position;
-            let attackers = inverted.count_attackers(pos.invert()) as i32;
-            let defenders = position.count_attackers(pos) as i32;
-
-            // Base the importance of protection on piece value
-            let piece_importance: i32 = match piece.piece_type {
-                PieceType::Pawn => 1,
-                PieceType::Knight | PieceType::Bishop => 3,
-                PieceType::Rook => 4,
-                PieceType::Queen => 5,
-                PieceType::King => 6,
-            };
+    let attacks = position.attack_counts();
+    let enemy_attacks = inverted.attack_counts();
+
+    // Base the importance of protection on piece value
+    let importances = [
+        (PieceType::Pawn, 1),
+        (PieceType::Knight, 3),
+        (PieceType::Bishop, 3),
+        (PieceType::Rook, 4),
+        (PieceType::Queen, 5),
+        (PieceType::King, 6),
+    ];
+
+    for (piece_type, piece_importance) in importances {
+        for pos in position.pieces(Color::White, piece_type) {
+            let attackers = enemy_attacks.get(pos.invert());
+            let defenders = attacks.get(pos);
 
             // Higher bonus for pieces that are well protected vs attacked
             // Scale by piece importance
@@ -350,6 +410,28 @@ fn evaluate_trapped_pieces(position: &Position) -> i32 {
     score
 }
 
+fn evaluate_pinned_pieces(position: &Position) -> i32 {
+    let mut score = 0;
+
+    for pos in position.pinned_pieces(Color::White) {
+        let pinned_value = piece_value(position.get_piece_at(pos).unwrap().piece_type);
+
+        // A piece pinned by a cheaper one can't simply trade itself off for the pinning piece
+        let pinner = (position.pin_ray(pos) & position.black_map)
+            .into_iter()
+            .next()
+            .and_then(|pinner| position.get_piece_at(pinner));
+
+        if pinner.is_some_and(|pinner| piece_value(pinner.piece_type) < pinned_value) {
+            score -= pinned_value / 4;
+        } else {
+            score -= pinned_value / 10;
+        }
+    }
+
+    score
+}
+
 fn evaluate_strategic_squares(position: &Position) -> i32 {
     let mut score = 0;
 
@@ -387,13 +469,15 @@ fn evaluate_strategic_squares(position: &Position) -> i32 {
 
 fn evaluate_piece_pressure(position: &Position, inverted: &Position) -> i32 {
     let mut score = 0;
+    let attacks = position.attack_counts();
+    let enemy_attacks = inverted.attack_counts();
 
     for piece in &inverted.white_pieces {
         // Evaluate pressure on black pieces
         if let Some(piece) = piece {
             let pos_from_white = piece.position.invert();
-            let attackers = position.count_attackers(pos_from_white);
-            let defenders = inverted.count_attackers(pos_from_white.invert());
+            let attackers = attacks.get(pos_from_white);
+            let defenders = enemy_attacks.get(pos_from_white.invert());
 
             // Reward pressure even without capture possibility
             if attackers > 0 {
@@ -424,9 +508,10 @@ fn evaluate_piece_pressure(position: &Position, inverted: &Position) -> i32 {
 fn evaluate_pawn_defense_quality(position: &Position) -> i32 {
     let mut score = 0;
     let maps = position.get_piece_maps();
+    let attacks = position.attack_counts();
 
     for pawn_pos in maps.white_pawns.into_iter() {
-        let defenders = position.count_attackers(pawn_pos);
+        let defenders = attacks.get(pawn_pos);
         let pawn_defenders = count_pawn_defenders(position, pawn_pos);
 
         let mut queen_defending = false;
@@ -468,7 +553,7 @@ fn evaluate_pawn_defense_quality(position: &Position) -> i32 {
 
 fn count_pawn_defenders(position: &Position, target: Pos) -> i32 {
     let maps = position.get_piece_maps();
-    (*pawn::attack_map_black(target) & maps.white_pawns).count() as i32
+    (position.attackers_to(target, Color::White) & maps.white_pawns).count() as i32
 }
 
 fn get_pawn_importance(pos: Pos) -> i32 {
@@ -501,6 +586,100 @@ fn manhattan_distance(a: Pos, b: Pos) -> u8 {
     file_diff + rank_diff
 }
 
+/// The game phase when all of the starting pieces are on the board.
+const MAX_GAME_PHASE: i32 = 24;
+
+/// How far from the endgame the position is, from `MAX_GAME_PHASE` with every piece on the
+/// board (or held) down to 0 with only pawns and kings left. Minor pieces count 1, rooks 2 and
+/// queens 4.
+fn game_phase(position: &Position) -> i32 {
+    let phase = |piece_type| match piece_type {
+        PieceType::Knight | PieceType::Bishop => 1,
+        PieceType::Rook => 2,
+        PieceType::Queen => 4,
+        PieceType::Pawn | PieceType::King => 0,
+    };
+
+    let total: i32 = position
+        .white_pieces
+        .iter()
+        .chain(position.black_pieces.iter())
+        .flatten()
+        .map(|piece| phase(piece.piece_type) + piece.holding.map(phase).unwrap_or(0))
+        .sum();
+
+    // Promotions can take it past the starting material
+    total.min(MAX_GAME_PHASE)
+}
+
+/// The squares black's pawns attack.
+fn black_pawn_attacks(maps: &PieceMaps) -> Bitboard {
+    maps.black_pawns
+        .into_iter()
+        .fold(Bitboard::new(), |attacks, pawn_pos| {
+            attacks | *pawn::attack_map_black(pawn_pos)
+        })
+}
+
+/// Squares in the center files of the side's own half that enemy pawns don't attack, counting
+/// double when they're behind the side's pawns, where pieces can maneuver safely.
+fn evaluate_space(position: &Position) -> i32 {
+    let maps = position.get_piece_maps();
+
+    let center_files = Bitboard::for_file(2)
+        | Bitboard::for_file(3)
+        | Bitboard::for_file(4)
+        | Bitboard::for_file(5);
+    let area =
+        center_files & (Bitboard::for_rank(4) | Bitboard::for_rank(5) | Bitboard::for_rank(6));
+
+    let safe = area & !maps.white_pawns & !black_pawn_attacks(maps);
+
+    // Up to three squares behind each pawn
+    let mut behind = Bitboard::new();
+    for pawn_pos in maps.white_pawns.into_iter() {
+        for distance in 1..=3 {
+            if let Some(square) = pawn_pos.moved(0, distance) {
+                behind.set(square);
+            }
+        }
+    }
+
+    (safe.count() + (safe & behind).count()) as i32 * 2
+}
+
+/// Penalizes knights and bishops still on the back rank and rewards a castled king, and a king
+/// that can't castle anymore but hasn't.
+fn evaluate_development(position: &Position) -> i32 {
+    let maps = position.get_piece_maps();
+    let back_rank = Bitboard::for_rank(7);
+    let mut score = 0;
+
+    score -= ((maps.white_knights | maps.white_bishops) & back_rank).count() as i32 * 15;
+
+    if let Some(king_pos) = position.white_king {
+        // Boards are stored from the side to move's perspective, so black's king starts on d1
+        let start_col = match position.true_active_color {
+            Color::White => 4,
+            Color::Black => 3,
+        };
+
+        let castling_rights = &position.castling_rights;
+        let can_castle = match position.true_active_color {
+            Color::White => castling_rights.white_king_side || castling_rights.white_queen_side,
+            Color::Black => castling_rights.black_king_side || castling_rights.black_queen_side,
+        };
+
+        if king_pos.get_row() == 7 && king_pos.get_col().abs_diff(start_col) >= 2 {
+            score += 30;
+        } else if !can_castle {
+            score -= 20;
+        }
+    }
+
+    score
+}
+
 /// Checks if the position is likely in the endgame based on material
 fn is_endgame(position: &Position) -> bool {
     let maps = position.get_piece_maps();
@@ -525,17 +704,39 @@ fn is_endgame(position: &Position) -> bool {
             && black_minor <= 1)
 }
 
+/// Counts the squares each piece attacks that aren't taken by its own side or attacked by an
+/// enemy pawn, straight from the attack tables rather than generating each piece's moves.
 fn evaluate_mobility(position: &Position) -> i32 {
-    let mut white_score = 0;
+    let maps = position.get_piece_maps();
+
+    // A piece can't do much on a square an enemy pawn would take it on
+    let available = !position.white_map & !black_pawn_attacks(maps);
+    let occupied = position.all_map;
+
+    let moves = |attacks: Bitboard| (attacks & available).count() as i32;
+
+    let mut score = 0;
 
-    let legal_moves = position.count_pseudolegal_moves();
+    for pos in maps.white_knights.into_iter() {
+        score += moves(*knight::attack_map(pos)) * mobility_bonus(PieceType::Knight);
+    }
 
-    for (piece_type, move_count) in legal_moves.iter() {
-        let mobility_bonus = mobility_bonus(*piece_type) * *move_count as i32;
-        white_score += mobility_bonus;
+    for pos in maps.white_bishops.into_iter() {
+        score += moves(bishop::magic::get_bishop_moves_magic(pos, occupied))
+            * mobility_bonus(PieceType::Bishop);
     }
 
-    white_score
+    for pos in maps.white_rooks.into_iter() {
+        score += moves(rook::magic::get_rook_moves_magic(pos, occupied))
+            * mobility_bonus(PieceType::Rook);
+    }
+
+    for pos in maps.white_queens.into_iter() {
+        score += moves(queen::magic::get_queen_moves_magic(pos, occupied))
+            * mobility_bonus(PieceType::Queen);
+    }
+
+    score
 }
 
 fn evaluate_piece_coordination(position: &Position) -> i32 {
@@ -607,6 +808,17 @@ mod tests {
     use super::*;
     use crate::{Color, Piece, PieceType, Pos, Position};
 
+    #[test]
+    fn test_insufficient_material_is_drawn() {
+        let params = SearchParams::default();
+
+        let position: Position = "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1".parse().unwrap();
+        assert_eq!(evaluate_position(&position, GameType::Classic, &params), 0);
+
+        let position: Position = "4k3/8/8/8/8/8/8/2R1K3 w - - 0 1".parse().unwrap();
+        assert!(evaluate_position(&position, GameType::Classic, &params) > 0);
+    }
+
     #[test]
     fn test_has_bishop_pair_starting_position() {
         let position = Position::start_position();
@@ -1088,6 +1300,14 @@ mod tests {
         assert_eq!(white_score, 27);
     }
 
+    #[test]
+    fn test_evaluate_mobility_enemy_pawns() {
+        // The e7 pawn covers d6 and f6, leaving the knight 6 useful squares
+        let position = Position::parse_from_fen("4k3/4p3/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(evaluate_mobility(&position), 24);
+    }
+
     #[test]
     fn test_evaluate_mobility_blocked_pieces() {
         // Position with pieces blocked by friendly pieces
@@ -1190,4 +1410,113 @@ mod tests {
 
         assert!(score > 20);
     }
+
+    #[test]
+    fn test_evaluate_rooks() {
+        let rooks = |fen: &str| evaluate_rooks(&Position::parse_from_fen(fen).unwrap());
+
+        // Behind its own pawn
+        assert_eq!(rooks("4k3/p7/8/8/8/8/P7/R3K3 w - - 0 1"), 0);
+
+        // Semi-open and open files
+        assert_eq!(rooks("4k3/p7/8/8/8/8/8/R3K3 w - - 0 1"), 10);
+        assert_eq!(rooks("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), 25);
+
+        // Doubled on an open file
+        assert_eq!(rooks("4k3/8/8/8/8/8/R7/R3K3 w - - 0 1"), 25 + 25 + 15);
+
+        // On the seventh with pawns to attack, but not once they're gone and the king is out
+        assert_eq!(rooks("4k3/R6p/8/8/8/8/8/4K3 w - - 0 1"), 25 + 20);
+        assert_eq!(rooks("8/R7/4k3/8/8/8/8/4K3 w - - 0 1"), 25);
+
+        // Black's rook on a semi-open file counts against white's rook behind its pawn
+        let params = SearchParams::builder()
+            .feature(|f| f.evaluate_rooks = true)
+            .build()
+            .unwrap();
+        let position = Position::parse_from_fen("r3k3/8/8/8/8/8/P7/R3K3 w - - 0 1").unwrap();
+        let without_rooks = evaluate_position(&position, GameType::Classic, &Default::default());
+
+        assert_eq!(
+            evaluate_position(&position, GameType::Classic, &params),
+            without_rooks - 10
+        );
+    }
+
+    #[test]
+    fn test_evaluate_pinned_pieces() {
+        let pinned = |fen: &str| evaluate_pinned_pieces(&Position::parse_from_fen(fen).unwrap());
+
+        assert_eq!(pinned("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1"), -320 / 10);
+        assert_eq!(pinned("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1"), 0);
+
+        // Pinned by a cheaper piece
+        assert_eq!(pinned("4k3/8/8/b7/8/8/3R4/4K3 w - - 0 1"), -500 / 4);
+
+        // Black's pinned pieces count against black
+        let params = SearchParams::builder()
+            .feature(|f| f.evaluate_pinned_pieces = true)
+            .build()
+            .unwrap();
+        let position = Position::parse_from_fen("4k3/4n3/8/8/8/8/8/4RK2 w - - 0 1").unwrap();
+        let without_pins = evaluate_position(&position, GameType::Classic, &Default::default());
+
+        assert_eq!(
+            evaluate_position(&position, GameType::Classic, &params),
+            without_pins + 320 / 10
+        );
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(game_phase(&Position::start_position()), MAX_GAME_PHASE);
+        assert_eq!(
+            game_phase(&Position::parse_from_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap()),
+            0
+        );
+
+        // Held pieces are still in the game
+        assert_eq!(
+            game_phase(&Position::parse_from_fen("4k3/8/8/8/8/8/8/RxN3K3 w - - 0 1").unwrap()),
+            3
+        );
+    }
+
+    #[test]
+    fn test_evaluate_space() {
+        let space = |fen: &str| evaluate_space(&Position::parse_from_fen(fen).unwrap());
+
+        let start = space("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start, 16);
+
+        // Advancing the e pawn frees up the squares behind it
+        assert_eq!(
+            space("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1"),
+            start + 4
+        );
+
+        // Squares black's pawns attack aren't safe
+        assert!(space("rnbqkbnr/ppp2ppp/8/8/3pp3/8/PPP2PPP/RNBQKBNR w KQkq - 0 1") < start);
+    }
+
+    #[test]
+    fn test_evaluate_development() {
+        let development = |fen: &str| evaluate_development(&Position::parse_from_fen(fen).unwrap());
+
+        assert_eq!(
+            development("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            -60
+        );
+        assert_eq!(
+            development("rnbqkbnr/pppppppp/8/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 0 1"),
+            -30
+        );
+
+        // Castled, and stuck in the center without castling rights
+        assert_eq!(development("4k3/8/8/8/8/8/8/5RK1 w - - 0 1"), 30);
+        assert_eq!(development("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), -20);
+
+        // Black's king castles to g8
+        assert_eq!(development("rnbq1rk1/8/8/8/8/8/8/4K3 b - - 0 1"), 0);
+    }
 }
@@ -1,11 +1,13 @@
+use arrayvec::ArrayVec;
+
 use crate::{
     piece::{knight, pawn},
-    piece_move::MoveType,
+    piece_move::{MoveList, MoveType, MAX_MOVES},
     search::{alpha_beta::SearchParams, search_results::SearchState},
     PieceMove, PieceType, Position,
 };
 
-use super::piece_value;
+use super::{piece_value, see::piece_worth};
 
 #[derive(PartialEq, Eq)]
 struct ScoredMove {
@@ -25,17 +27,18 @@ impl Ord for ScoredMove {
     }
 }
 
+/// Sorts the moves so the ones most likely to be best come first.
 pub fn order_moves(
     position: &mut Position,
-    moves: Vec<PieceMove>,
+    moves: &mut MoveList,
     prev_pv: Option<&Vec<PieceMove>>,
     state: &SearchState,
     ply: usize,
     params: &SearchParams,
-) -> Vec<PieceMove> {
-    let mut scored_moves: Vec<ScoredMove> = moves
-        .into_iter()
-        .map(|mv| {
+) {
+    let mut scored_moves: ArrayVec<ScoredMove, MAX_MOVES> = moves
+        .iter()
+        .map(|&mv| {
             let prev_best_move = prev_pv.as_ref().and_then(|pv| pv.get(ply)).cloned();
 
             let score = score_move(position, &mv, prev_best_move, state, ply, params);
@@ -46,7 +49,9 @@ pub fn order_moves(
     // Sort in descending order (highest score first)
     scored_moves.sort_by(|a, b| b.score.cmp(&a.score));
 
-    scored_moves.into_iter().map(|sm| sm.mv).collect()
+    for (mv, scored) in moves.iter_mut().zip(scored_moves) {
+        *mv = scored.mv;
+    }
 }
 
 fn score_move(
@@ -105,8 +110,13 @@ fn score_move(
             }
         }
 
-        // Subtract attacker value - prefer capturing with less valuable pieces
-        score -= piece_value(mv.piece_type) * 10;
+        // Subtract attacker value - prefer capturing with less valuable pieces, and avoid
+        // risking a piece that is holding another
+        let attacker_value = position
+            .get_piece_at(mv.from)
+            .map(piece_worth)
+            .unwrap_or_else(|| piece_value(mv.piece_type));
+        score -= attacker_value * 10;
     }
 
     if params.features.enable_killer_moves {
@@ -141,6 +151,10 @@ fn score_move(
         score += state.history.get_history_score(mv);
     }
 
+    if params.features.enable_continuation_history {
+        score += state.history.get_continuation_score(&state.data.moves, mv) / 8;
+    }
+
     score += quick_threat_score(position, mv);
 
     score
@@ -0,0 +1,176 @@
+use crate::{piece_move::MoveType, Bitboard, Color, Piece, PieceMove, Pos, Position};
+
+use super::piece_value;
+
+/// The material lost when `piece` is captured. In Rescue, capturing a piece that is holding
+/// another piece takes both.
+pub fn piece_worth(piece: &Piece) -> i32 {
+    piece_value(piece.piece_type) + piece.holding.map(piece_value).unwrap_or(0)
+}
+
+/// Static exchange evaluation: the material white (the side to move) can expect to win or lose
+/// from `mv` if both sides keep recapturing on the destination square with their least valuable
+/// attacker, stopping whenever continuing would lose material.
+///
+/// Held pieces count towards the value of both the victims and the attackers, since a
+/// recaptured piece loses whatever it is holding along with it.
+pub fn static_exchange_evaluation(position: &Position, mv: &PieceMove) -> i32 {
+    let Some(mover) = position.get_piece_at(mv.from) else {
+        return 0;
+    };
+
+    let MoveType::Normal {
+        captured,
+        captured_pos,
+        captured_holding,
+        rescued_pos,
+        dropped_pos,
+        ..
+    } = mv.move_type
+    else {
+        return 0;
+    };
+
+    let target = mv.to;
+    let mut occupied = position.all_map;
+    occupied.clear(mv.from);
+    if let Some(captured_pos) = captured_pos {
+        occupied.clear(captured_pos);
+    }
+
+    // The moving piece may pick up or put down its held piece on the way, which changes what
+    // it's worth standing on the target square.
+    let mover_holding = if dropped_pos.is_some() {
+        None
+    } else if let Some(rescued_pos) = rescued_pos {
+        position.get_piece_at(rescued_pos).map(|p| p.piece_type)
+    } else {
+        mover.holding
+    };
+    let mut on_target = piece_value(mover.piece_type) + mover_holding.map(piece_value).unwrap_or(0);
+
+    let mut gains = vec![
+        captured.map(piece_value).unwrap_or(0) + captured_holding.map(piece_value).unwrap_or(0),
+    ];
+    let mut side = Color::Black;
+
+    while let Some((from, worth)) = least_valuable_attacker(position, target, side, occupied) {
+        let previous = *gains.last().unwrap();
+        gains.push(on_target - previous);
+
+        // Sliders behind the capturing piece attack through its square from now on
+        occupied.clear(from);
+        on_target = worth;
+        side = side.invert();
+    }
+
+    // Each side can choose to stop recapturing if continuing would lose material.
+    while gains.len() > 1 {
+        let last = gains.pop().unwrap();
+        let previous = gains.last_mut().unwrap();
+        *previous = -(-*previous).max(last);
+    }
+
+    gains[0]
+}
+
+fn least_valuable_attacker(
+    position: &Position,
+    target: Pos,
+    color: Color,
+    occupied: Bitboard,
+) -> Option<(Pos, i32)> {
+    position
+        .attackers_to_occupied(target, color, occupied)
+        .into_iter()
+        .filter_map(|pos| position.get_piece_at(pos))
+        .map(|piece| (piece.position, piece_worth(piece)))
+        .min_by_key(|&(_, worth)| worth)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::piece_move::GameType;
+
+    use super::*;
+
+    fn see(fen: &str, mv: &str, game_type: GameType) -> i32 {
+        let position = Position::parse_from_fen(fen).unwrap();
+        let mv = PieceMove::from_algebraic(&position, mv, game_type).unwrap();
+        static_exchange_evaluation(&position, &mv)
+    }
+
+    #[test]
+    fn test_undefended_capture() {
+        assert_eq!(
+            see(
+                "4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1",
+                "Rxd5",
+                GameType::Classic
+            ),
+            100
+        );
+    }
+
+    #[test]
+    fn test_defended_capture() {
+        // Rook takes a pawn defended by a pawn
+        assert_eq!(
+            see(
+                "4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1",
+                "Rxd5",
+                GameType::Classic
+            ),
+            -400
+        );
+
+        // Pawn takes a knight defended by a pawn
+        assert_eq!(
+            see(
+                "4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1",
+                "exd5",
+                GameType::Classic
+            ),
+            220
+        );
+    }
+
+    #[test]
+    fn test_x_ray_recapture() {
+        // Doubled rooks win a pawn defended by a single rook
+        assert_eq!(
+            see(
+                "3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1",
+                "Rxd5",
+                GameType::Classic
+            ),
+            100
+        );
+    }
+
+    #[test]
+    fn test_captured_holding() {
+        // Capturing a knight that is holding a pawn wins both
+        assert_eq!(
+            see(
+                "4k3/8/8/3nxp4/8/8/8/3RK3 w - - 0 1",
+                "Rxd5",
+                GameType::Rescue
+            ),
+            420
+        );
+    }
+
+    #[test]
+    fn test_attacker_holding() {
+        // A rook holding a knight can't afford to take a defended pawn
+        assert_eq!(
+            see(
+                "4k3/8/2p5/3p4/8/8/8/3RxNK3 w - - 0 1",
+                "Rxd5",
+                GameType::Rescue
+            ),
+            -720
+        );
+    }
+}
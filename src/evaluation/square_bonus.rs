@@ -1,9 +1,69 @@
-use crate::Pos;
+use crate::{piece_move::GameType, variant::SquareTable, Pos};
 
 pub trait SquareBonus {
     fn square_bonus(pos: Pos) -> i32;
+
+    /// The bonus in Rescue games. Pieces can be carried off and dropped somewhere else, so the
+    /// square a piece stands on matters less than in Classic, and the Classic bonus is halved
+    /// unless a piece has its own table.
+    fn rescue_square_bonus(pos: Pos) -> i32 {
+        Self::square_bonus(pos) / 2
+    }
+}
+
+pub fn square_bonus<T: SquareBonus>(pos: Pos, game_type: GameType) -> i32 {
+    match game_type.rules().square_table() {
+        SquareTable::Classic => T::square_bonus(pos),
+        SquareTable::Rescue => T::rescue_square_bonus(pos),
+    }
 }
 
-pub fn square_bonus<T: SquareBonus>(pos: Pos) -> i32 {
-    T::square_bonus(pos)
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece::{king::King, knight::Knight},
+        pos, Piece, PieceType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_rescue_square_bonus() {
+        assert_eq!(square_bonus::<Knight>(pos::A1, GameType::Classic), -50);
+        assert_eq!(square_bonus::<Knight>(pos::A1, GameType::Rescue), -25);
+
+        // The king can't be carried, so its shelter matters as much as ever
+        assert_eq!(square_bonus::<King>(pos::G1, GameType::Classic), 30);
+        assert_eq!(square_bonus::<King>(pos::G1, GameType::Rescue), 30);
+    }
+
+    #[test]
+    fn test_held_square_bonus() {
+        let carrying = |position| Piece {
+            holding: Some(PieceType::Knight),
+            ..Piece::new_white(PieceType::Queen, position)
+        };
+
+        let queen_bonus =
+            |position| square_bonus::<crate::piece::Queen>(position, GameType::Rescue);
+
+        let d5 = Pos::xy(3, 3);
+
+        // A knight carried into the center is worth more than one carried in the corner
+        let center = carrying(d5).square_bonus(GameType::Rescue) - queen_bonus(d5);
+        let corner = carrying(pos::A1).square_bonus(GameType::Rescue) - queen_bonus(pos::A1);
+
+        assert_eq!(center, square_bonus::<Knight>(d5, GameType::Rescue) / 2);
+        assert_eq!(
+            corner,
+            square_bonus::<Knight>(pos::A1, GameType::Rescue) / 2
+        );
+        assert!(center > corner);
+
+        // Nothing held, no extra bonus
+        assert_eq!(
+            Piece::new_white(PieceType::Queen, d5).square_bonus(GameType::Rescue),
+            queen_bonus(d5)
+        );
+    }
 }
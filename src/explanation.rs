@@ -0,0 +1,340 @@
+//! Why the engine likes a move, for showing hints to learners: the move wins material, creates
+//! a passed pawn, saves a piece that was hanging, or drops a piece that forks the opponent's.
+//! The reasons are found from the position around the move rather than from the search, so
+//! they explain what the move does, not the whole line the engine expects.
+
+use serde::Serialize;
+
+use crate::{
+    evaluation::{passed_pawns, piece_value, see::static_exchange_evaluation},
+    piece_move::{GameType, MoveType},
+    search::search_results::SearchResults,
+    threats::find_hanging_pieces,
+    Bitboard, Color, PieceMove, PieceType, Position,
+};
+
+/// A reason a move is good. Found from the perspective of the side making the move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum MoveReason {
+    /// The move captures and comes out ahead, by this much material in centipawns, even after
+    /// the opponent recaptures.
+    WinsMaterial { material: i32 },
+
+    /// A pawn has no enemy pawns in front of it on its own or an adjacent file after the move
+    /// that it didn't have before.
+    CreatesPassedPawn,
+
+    /// The opponent could win this piece before the move and can't after it.
+    EscapesThreat { piece_type: PieceType },
+
+    /// The move drops a piece that attacks two or more of the opponent's pieces at once: the
+    /// king, pieces worth more than it, or undefended pieces.
+    DropFork {
+        piece_type: PieceType,
+        targets: Vec<PieceType>,
+    },
+}
+
+impl std::fmt::Display for MoveReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveReason::WinsMaterial { material } => {
+                write!(f, "Wins material (+{:.1})", *material as f64 / 100.0)
+            }
+            MoveReason::CreatesPassedPawn => write!(f, "Creates a passed pawn"),
+            MoveReason::EscapesThreat { piece_type } => {
+                write!(f, "Saves the {} from capture", piece_name(*piece_type))
+            }
+            MoveReason::DropFork {
+                piece_type,
+                targets,
+            } => {
+                let targets: Vec<&str> = targets.iter().map(|&target| piece_name(target)).collect();
+                write!(
+                    f,
+                    "Drops a {} forking the {}",
+                    piece_name(*piece_type),
+                    targets.join(" and ")
+                )
+            }
+        }
+    }
+}
+
+fn piece_name(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "pawn",
+        PieceType::Knight => "knight",
+        PieceType::Bishop => "bishop",
+        PieceType::Rook => "rook",
+        PieceType::Queen => "queen",
+        PieceType::King => "king",
+    }
+}
+
+/// The reasons for the best move of `results`, a search of `position`. Empty if the search
+/// found no move or nothing the move does stands out.
+pub fn explain_move(
+    position: &Position,
+    results: &SearchResults,
+    game_type: GameType,
+) -> Result<Vec<MoveReason>, anyhow::Error> {
+    match results.best_move {
+        Some(mv) => explain(position, mv, game_type),
+        None => Ok(vec![]),
+    }
+}
+
+/// The reasons white (the side to move) would play `mv` in `position`.
+pub fn explain(
+    position: &Position,
+    mv: PieceMove,
+    game_type: GameType,
+) -> Result<Vec<MoveReason>, anyhow::Error> {
+    let mut reasons = Vec::new();
+
+    let material = static_exchange_evaluation(position, &mv);
+    if mv.is_capture() && material > 0 {
+        reasons.push(MoveReason::WinsMaterial { material });
+    }
+
+    let mut after = position.clone();
+    after.apply_move(mv)?;
+
+    if creates_passed_pawn(position, &after, mv) {
+        reasons.push(MoveReason::CreatesPassedPawn);
+    }
+
+    for piece_type in escaped_threats(position, &after, mv, game_type)? {
+        reasons.push(MoveReason::EscapesThreat { piece_type });
+    }
+
+    if let Some(fork) = drop_fork(&after, mv) {
+        reasons.push(fork);
+    }
+
+    Ok(reasons)
+}
+
+fn creates_passed_pawn(before: &Position, after: &Position, mv: PieceMove) -> bool {
+    let passed_before = passed_pawns(before);
+    let mut new_passed = passed_pawns(after) & !passed_before;
+
+    // A passed pawn moving forward is still the same passed pawn
+    if passed_before.get(mv.from) {
+        new_passed.clear(mv.to);
+    }
+
+    new_passed != Bitboard::new()
+}
+
+/// The types of the pieces that were hanging before `mv` and aren't after it.
+fn escaped_threats(
+    before: &Position,
+    after: &Position,
+    mv: PieceMove,
+    game_type: GameType,
+) -> Result<Vec<PieceType>, anyhow::Error> {
+    // As in `Threats::find`, the opponent has no en passant before the move
+    let mut opponent = before.inverted();
+    opponent.en_passant = None;
+    let hanging_before = find_hanging_pieces(&opponent, game_type)?;
+    if hanging_before.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let hanging_after = find_hanging_pieces(&after.inverted(), game_type)?;
+
+    let rescued_pos = match mv.move_type {
+        MoveType::Normal { rescued_pos, .. } => rescued_pos,
+        MoveType::Castle { .. } => None,
+    };
+
+    Ok(hanging_before
+        .into_iter()
+        .filter(|piece| {
+            // A piece that moved, or was rescued by the moving piece, is now on its square
+            let pos = if piece.pos == mv.from || rescued_pos == Some(piece.pos) {
+                mv.to
+            } else {
+                piece.pos
+            };
+
+            !hanging_after.iter().any(|hanging| hanging.pos == pos)
+        })
+        .map(|piece| piece.piece_type)
+        .collect())
+}
+
+/// The fork made by the piece `mv` drops, if it makes one.
+fn drop_fork(after: &Position, mv: PieceMove) -> Option<MoveReason> {
+    let MoveType::Normal {
+        dropped_pos: Some(dropped_pos),
+        ..
+    } = mv.move_type
+    else {
+        return None;
+    };
+
+    let dropped = after.get_piece_at(dropped_pos)?;
+
+    let mut targets: Vec<PieceType> = after
+        .black_map
+        .into_iter()
+        .filter(|&pos| after.attackers_to(pos, Color::White).get(dropped_pos))
+        .filter_map(|pos| after.get_piece_at(pos))
+        .filter(|target| {
+            target.piece_type == PieceType::King
+                || piece_value(target.piece_type) > piece_value(dropped.piece_type)
+                || !after.is_attacked_by(target.position, Color::Black)
+        })
+        .map(|target| target.piece_type)
+        .collect();
+
+    if targets.len() < 2 {
+        return None;
+    }
+
+    // The most valuable first, king included
+    targets.sort_by_key(|&target| std::cmp::Reverse(piece_value(target)));
+
+    Some(MoveReason::DropFork {
+        piece_type: dropped.piece_type,
+        targets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::{
+        alpha_beta::{search, SearchParams},
+        search_results::SearchState,
+        transposition_table::TranspositionTable,
+    };
+
+    use super::*;
+
+    fn reasons(fen: &str, uci: &str, game_type: GameType) -> Vec<MoveReason> {
+        let position = Position::parse_from_fen(fen).unwrap();
+        let mv = PieceMove::from_uci(&position, uci, game_type).unwrap();
+        explain(&position, mv, game_type).unwrap()
+    }
+
+    #[test]
+    fn test_wins_material() {
+        assert_eq!(
+            reasons(
+                "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1",
+                "d2d5",
+                GameType::Classic
+            ),
+            vec![MoveReason::WinsMaterial { material: 900 }]
+        );
+
+        // Taking a defended knight with the rook loses material
+        assert_eq!(
+            reasons(
+                "4k3/2p5/3n4/8/8/8/3R4/4K3 w - - 0 1",
+                "d2d6",
+                GameType::Classic
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_creates_passed_pawn() {
+        // Taking the only pawn that could stop the d-pawn, which the c-pawn defends
+        assert_eq!(
+            reasons(
+                "4k3/8/4p3/3P4/2P5/8/8/4K3 w - - 0 1",
+                "d5e6",
+                GameType::Classic
+            ),
+            vec![
+                MoveReason::WinsMaterial { material: 100 },
+                MoveReason::CreatesPassedPawn
+            ]
+        );
+
+        // Pushing a pawn that was already passed doesn't create one
+        assert_eq!(
+            reasons("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1", "d5d6", GameType::Classic),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_escapes_threat() {
+        // The knight on d4 is attacked by the pawn on e5
+        assert_eq!(
+            reasons(
+                "4k3/8/8/4p3/3N4/8/8/4K3 w - - 0 1",
+                "d4f3",
+                GameType::Classic
+            ),
+            vec![MoveReason::EscapesThreat {
+                piece_type: PieceType::Knight
+            }]
+        );
+    }
+
+    #[test]
+    fn test_drop_fork() {
+        // The king steps to c6 and drops the knight it's holding on c7, forking the king and
+        // the rook
+        assert_eq!(
+            reasons(
+                "r3k3/8/8/1KxN6/8/8/8/8 w - - 0 1",
+                "b5c6Dc7",
+                GameType::Rescue
+            ),
+            vec![MoveReason::DropFork {
+                piece_type: PieceType::Knight,
+                targets: vec![PieceType::King, PieceType::Rook],
+            }]
+        );
+
+        // Dropped on d5 it attacks nothing
+        assert_eq!(
+            reasons(
+                "r3k3/8/8/1KxN6/8/8/8/8 w - - 0 1",
+                "b5c5Dd5",
+                GameType::Rescue
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_explain_move() {
+        let position = Position::parse_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+        let results = search(&position, &mut state, params, 0).unwrap();
+
+        assert_eq!(
+            explain_move(&position, &results, GameType::Classic).unwrap(),
+            vec![MoveReason::WinsMaterial { material: 900 }]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let fork = MoveReason::DropFork {
+            piece_type: PieceType::Knight,
+            targets: vec![PieceType::King, PieceType::Rook],
+        };
+        assert_eq!(fork.to_string(), "Drops a knight forking the king and rook");
+        assert_eq!(
+            MoveReason::WinsMaterial { material: 320 }.to_string(),
+            "Wins material (+3.2)"
+        );
+    }
+}
@@ -1,12 +1,55 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::piece_move::GameType;
+
+/// Search and evaluation features that can be turned on and off. Fields missing when
+/// deserializing keep their defaults, so settings saved by older versions still load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Features {
     pub enable_transposition_table: bool,
     pub enable_lmr: bool,
     pub enable_window_search: bool,
+
+    /// Search every move after the first with a null window, and only search it again with the
+    /// full window if it turns out to be better.
+    pub enable_pvs: bool,
+
     pub enable_killer_moves: bool,
     pub enable_null_move_pruning: bool,
+
+    /// Cut nodes near the leaves whose static evaluation is far enough above beta that they
+    /// would almost certainly fail high.
+    pub enable_reverse_futility_pruning: bool,
+
+    /// At the last few plies, only run a quiescence search on nodes whose static evaluation is
+    /// far below alpha.
+    pub enable_razoring: bool,
+
     pub enable_history: bool,
 
+    /// Order quiet moves by how well they did after the moves one and two plies before them.
+    pub enable_continuation_history: bool,
+
+    pub enable_see_pruning: bool,
+
+    /// In Rescue games, search drops that give check one ply deeper and include them in
+    /// quiescence, so drop mates just past the horizon aren't missed.
+    pub enable_drop_check_extension: bool,
+
+    /// Search quiet moves that give check in the first quiescence plies, so simple mates just
+    /// past the horizon aren't missed.
+    pub enable_quiescence_checks: bool,
+
+    /// In classic games, look positions with few enough pieces up in the endgame tablebases
+    /// instead of searching them. Only has an effect when built with the `syzygy` feature and
+    /// tablebases have been loaded.
+    pub enable_tablebases: bool,
+
+    /// Keep the static evaluations of positions already seen in the search, so transposed
+    /// leaves aren't evaluated again.
+    pub enable_eval_cache: bool,
+
     pub evaluate_bishop_pairs: bool,
     pub evaluate_pawn_structure: bool,
     pub evaluate_king_safety: bool,
@@ -19,6 +62,18 @@ pub struct Features {
     pub evaluate_piece_pressure: bool,
     pub evaluate_pawn_structure_quality: bool,
     pub evaluate_pawn_defense_quality: bool,
+
+    /// Rooks on open and semi-open files, doubled on a file, or on the seventh rank.
+    pub evaluate_rooks: bool,
+
+    /// Safe squares behind the pawns in the center, counting for less as pieces come off.
+    pub evaluate_space: bool,
+
+    /// Minor pieces off the back rank and a castled king, counting for less as pieces come off.
+    pub evaluate_development: bool,
+
+    /// Pieces pinned to their king, more so when the pinning piece is worth less.
+    pub evaluate_pinned_pieces: bool,
 }
 
 impl Default for Features {
@@ -27,14 +82,23 @@ impl Default for Features {
             enable_transposition_table: true,
             enable_lmr: true,
             enable_window_search: true,
+            enable_pvs: true,
             enable_killer_moves: true,
             enable_null_move_pruning: true,
+            enable_reverse_futility_pruning: true,
+            enable_razoring: true,
             enable_history: true,
+            enable_continuation_history: true,
+            enable_see_pruning: true,
+            enable_drop_check_extension: true,
+            enable_quiescence_checks: true,
+            enable_tablebases: true,
+            enable_eval_cache: true,
 
             evaluate_bishop_pairs: false,
             evaluate_pawn_structure: false,
             evaluate_king_safety: false,
-            evaluate_mobility: false, // slow, over doubles search time
+            evaluate_mobility: false,
             evaluate_piece_coordination: false,
             evaluate_pawn_control: false,
             evaluate_piece_protection: false,
@@ -43,11 +107,18 @@ impl Default for Features {
             evaluate_piece_pressure: false,
             evaluate_pawn_structure_quality: false,
             evaluate_pawn_defense_quality: false,
+            evaluate_rooks: false,
+            evaluate_space: false,
+            evaluate_development: false,
+            evaluate_pinned_pieces: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How much each evaluation term counts, as a percentage. Like `Features`, missing weights
+/// keep their defaults when deserializing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EvaluationWeights {
     pub material: i32,
     pub bishop_pair: i32,
@@ -62,6 +133,10 @@ pub struct EvaluationWeights {
     pub piece_pressure: i32,
     pub pawn_structure_quality: i32,
     pub pawn_defense_quality: i32,
+    pub rooks: i32,
+    pub space: i32,
+    pub development: i32,
+    pub pinned_pieces: i32,
 }
 
 impl Default for EvaluationWeights {
@@ -80,6 +155,240 @@ impl Default for EvaluationWeights {
             piece_pressure: 65,         // Good bonus for long-term pressure
             pawn_structure_quality: 95, // Almost as important as basic structure
             pawn_defense_quality: 95,   // Almost as important as basic structure
+            rooks: 100,                 // Full weight for rook placement
+            space: 60,                  // Gentle nudge toward controlling the center
+            development: 100,           // Full weight, it only matters in the opening
+            pinned_pieces: 100,         // Full weight for pinned pieces
         }
     }
 }
+
+impl EvaluationWeights {
+    /// The default weights for a type of game. Rescue starts from the Classic weights.
+    pub fn for_game_type(game_type: GameType) -> Self {
+        game_type.rules().evaluation_weights()
+    }
+}
+
+/// A `Features` flag that can be toggled by name, e.g. from a UCI `setoption` command.
+pub struct FeatureOption {
+    pub field: &'static str,
+    pub get: fn(&Features) -> bool,
+    pub set: fn(&mut Features, bool),
+}
+
+/// An `EvaluationWeights` weight that can be adjusted by name, e.g. from a UCI `setoption` command.
+pub struct WeightOption {
+    pub field: &'static str,
+    pub get: fn(&EvaluationWeights) -> i32,
+    pub set: fn(&mut EvaluationWeights, i32),
+}
+
+/// The range that weights can be set to through options.
+pub const WEIGHT_OPTION_MIN: i32 = 0;
+pub const WEIGHT_OPTION_MAX: i32 = 1000;
+
+macro_rules! feature_options {
+    ($($field:ident),* $(,)?) => {
+        &[$(FeatureOption {
+            field: stringify!($field),
+            get: |features| features.$field,
+            set: |features, value| features.$field = value,
+        }),*]
+    };
+}
+
+macro_rules! weight_options {
+    ($($field:ident),* $(,)?) => {
+        &[$(WeightOption {
+            field: stringify!($field),
+            get: |weights| weights.$field,
+            set: |weights, value| weights.$field = value,
+        }),*]
+    };
+}
+
+static FEATURE_OPTIONS: &[FeatureOption] = feature_options![
+    enable_transposition_table,
+    enable_lmr,
+    enable_window_search,
+    enable_pvs,
+    enable_killer_moves,
+    enable_null_move_pruning,
+    enable_reverse_futility_pruning,
+    enable_razoring,
+    enable_history,
+    enable_continuation_history,
+    enable_see_pruning,
+    enable_drop_check_extension,
+    enable_quiescence_checks,
+    enable_tablebases,
+    enable_eval_cache,
+    evaluate_bishop_pairs,
+    evaluate_pawn_structure,
+    evaluate_king_safety,
+    evaluate_mobility,
+    evaluate_piece_coordination,
+    evaluate_pawn_control,
+    evaluate_piece_protection,
+    evaluate_trapped_pieces,
+    evaluate_strategic_squares,
+    evaluate_piece_pressure,
+    evaluate_pawn_structure_quality,
+    evaluate_pawn_defense_quality,
+    evaluate_rooks,
+    evaluate_space,
+    evaluate_development,
+    evaluate_pinned_pieces,
+];
+
+static WEIGHT_OPTIONS: &[WeightOption] = weight_options![
+    material,
+    bishop_pair,
+    pawn_structure,
+    king_safety,
+    mobility,
+    piece_coordination,
+    pawn_control,
+    piece_protection,
+    trapped_pieces,
+    strategic_squares,
+    piece_pressure,
+    pawn_structure_quality,
+    pawn_defense_quality,
+    rooks,
+    space,
+    development,
+    pinned_pieces,
+];
+
+impl FeatureOption {
+    /// The option name, e.g. `EnableLmr` for `enable_lmr`.
+    pub fn name(&self) -> String {
+        option_name("", self.field)
+    }
+}
+
+impl WeightOption {
+    /// The option name, e.g. `WeightKingSafety` for `king_safety`.
+    pub fn name(&self) -> String {
+        option_name("Weight", self.field)
+    }
+}
+
+impl Features {
+    /// All of the toggleable features.
+    pub fn options() -> &'static [FeatureOption] {
+        FEATURE_OPTIONS
+    }
+
+    /// Finds a feature by its option name, ignoring case.
+    pub fn find_option(name: &str) -> Option<&'static FeatureOption> {
+        FEATURE_OPTIONS
+            .iter()
+            .find(|option| option.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl EvaluationWeights {
+    /// All of the adjustable weights.
+    pub fn options() -> &'static [WeightOption] {
+        WEIGHT_OPTIONS
+    }
+
+    /// Finds a weight by its option name, ignoring case.
+    pub fn find_option(name: &str) -> Option<&'static WeightOption> {
+        WEIGHT_OPTIONS
+            .iter()
+            .find(|option| option.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Converts a snake_case field name into a CamelCase option name.
+fn option_name(prefix: &str, field: &str) -> String {
+    let mut name = prefix.to_string();
+
+    for word in field.split('_') {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.push(first.to_ascii_uppercase());
+            name.push_str(chars.as_str());
+        }
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the field names of a struct from its Debug output, so that new fields
+    /// can't be added without also being exposed as options.
+    fn debug_field_names(debug: &str) -> Vec<String> {
+        let inner = &debug[debug.find('{').unwrap() + 1..debug.rfind('}').unwrap()];
+        inner
+            .split(',')
+            .filter_map(|field| field.split(':').next())
+            .map(|field| field.trim().to_string())
+            .filter(|field| !field.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_weights_for_game_type() {
+        assert_eq!(
+            EvaluationWeights::for_game_type(GameType::Classic),
+            EvaluationWeights::default()
+        );
+
+        let rescue = EvaluationWeights::for_game_type(GameType::Rescue);
+        assert!(rescue.trapped_pieces < EvaluationWeights::default().trapped_pieces);
+        assert_eq!(rescue.material, EvaluationWeights::default().material);
+    }
+
+    #[test]
+    fn test_all_features_are_options() {
+        let fields = debug_field_names(&format!("{:?}", Features::default()));
+        let options: Vec<_> = Features::options().iter().map(|o| o.field).collect();
+        assert_eq!(fields, options);
+    }
+
+    #[test]
+    fn test_all_weights_are_options() {
+        let fields = debug_field_names(&format!("{:?}", EvaluationWeights::default()));
+        let options: Vec<_> = EvaluationWeights::options()
+            .iter()
+            .map(|o| o.field)
+            .collect();
+        assert_eq!(fields, options);
+    }
+
+    #[test]
+    fn test_option_names() {
+        let lmr = Features::find_option("EnableLMR").unwrap();
+        assert_eq!(lmr.field, "enable_lmr");
+        assert_eq!(lmr.name(), "EnableLmr");
+
+        let king_safety = EvaluationWeights::find_option("WeightKingSafety").unwrap();
+        assert_eq!(king_safety.field, "king_safety");
+
+        assert!(Features::find_option("Hash").is_none());
+        assert!(EvaluationWeights::find_option("KingSafety").is_none());
+    }
+
+    #[test]
+    fn test_options_get_and_set() {
+        let mut features = Features::default();
+        let lmr = Features::find_option("EnableLmr").unwrap();
+        assert!((lmr.get)(&features));
+        (lmr.set)(&mut features, false);
+        assert!(!features.enable_lmr);
+
+        let mut weights = EvaluationWeights::default();
+        let mobility = EvaluationWeights::find_option("WeightMobility").unwrap();
+        (mobility.set)(&mut weights, 10);
+        assert_eq!(weights.mobility, 10);
+        assert_eq!((mobility.get)(&weights), 10);
+    }
+}
@@ -0,0 +1,486 @@
+//! A game from its first move to its result, with the bookkeeping that goes with it: the moves
+//! played in both notations, draws by repetition, taking moves back, and the players' clocks.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pgn::{parse_san, to_san, PgnResult},
+    piece_move::{GameType, MoveList},
+    position::GameStatus,
+    search::time_control::{GameClock, TimeControl},
+    Color, PieceMove, Position,
+};
+
+/// A move played in a `Game`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameMove {
+    pub color: Color,
+
+    /// The move from the perspective of the side that played it, as `Position::apply_move`
+    /// takes it.
+    pub mv: PieceMove,
+
+    /// The move in standard algebraic notation, e.g. "Nbd2" or "Qxf7#".
+    pub san: String,
+
+    /// The move in UCI's long algebraic notation, e.g. "b1d2".
+    pub uci: String,
+
+    /// How long the player took over the move, if it was timed.
+    pub elapsed_ms: Option<u64>,
+}
+
+/// What a game looked like before one of its moves, to go back to when the move is undone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    position: Position,
+    clock: Option<GameClock>,
+    status: GameStatus,
+}
+
+/// A game in progress or finished. Serializes with everything needed to carry on with it,
+/// undo and redo included, so it can be saved or sent elsewhere as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    game_type: GameType,
+
+    /// The current position, from the perspective of its side to move.
+    position: Position,
+
+    moves: Vec<GameMove>,
+
+    /// The game before each of `moves`.
+    snapshots: Vec<Snapshot>,
+
+    /// Moves taken back with `undo`, the most recently undone last, for `redo` to play again.
+    undone: Vec<GameMove>,
+
+    /// The Zobrist keys of every position in the game, including the current position.
+    history: Vec<u64>,
+
+    clock: Option<GameClock>,
+    status: GameStatus,
+}
+
+impl Game {
+    /// A game from the standard starting position.
+    pub fn new(game_type: GameType) -> Self {
+        let position = Position::start_position();
+
+        Self {
+            game_type,
+            history: vec![position.zobrist_key()],
+            position,
+            moves: vec![],
+            snapshots: vec![],
+            undone: vec![],
+            clock: None,
+            status: GameStatus::Ongoing,
+        }
+    }
+
+    /// A game from a position, given from the perspective of its side to move. The position
+    /// may already be decided, e.g. if it's checkmate.
+    pub fn from_position(position: Position, game_type: GameType) -> Result<Self, anyhow::Error> {
+        let history = vec![position.zobrist_key()];
+        let status = position.game_status(game_type, &history)?;
+
+        Ok(Self {
+            game_type,
+            position,
+            moves: vec![],
+            snapshots: vec![],
+            undone: vec![],
+            history,
+            clock: None,
+            status,
+        })
+    }
+
+    pub fn from_fen(fen: &str, game_type: GameType) -> Result<Self, anyhow::Error> {
+        Self::from_position(Position::parse_from_fen(fen)?, game_type)
+    }
+
+    /// Plays the game with clocks, which are only run by `make_timed_move`.
+    pub fn with_clock(mut self, time_control: TimeControl) -> Self {
+        self.clock = Some(GameClock::new(time_control));
+        self
+    }
+
+    pub fn game_type(&self) -> GameType {
+        self.game_type
+    }
+
+    /// The current position, from the perspective of its side to move.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// The position before each move of the game, in order, each from the perspective of the
+    /// side about to play the move.
+    pub fn positions_before_moves(&self) -> impl Iterator<Item = &Position> {
+        self.snapshots.iter().map(|snapshot| &snapshot.position)
+    }
+
+    pub fn to_move(&self) -> Color {
+        self.position.true_active_color
+    }
+
+    pub fn moves(&self) -> &[GameMove] {
+        &self.moves
+    }
+
+    pub fn last_move(&self) -> Option<&GameMove> {
+        self.moves.last()
+    }
+
+    /// The Zobrist keys of every position in the game, including the current position, as
+    /// the search takes them to score repetitions.
+    pub fn history(&self) -> &[u64] {
+        &self.history
+    }
+
+    pub fn clock(&self) -> Option<&GameClock> {
+        self.clock.as_ref()
+    }
+
+    /// The legal moves in the current position, none once the game is over.
+    pub fn legal_moves(&self) -> Result<Vec<PieceMove>, anyhow::Error> {
+        if self.is_over() {
+            return Ok(vec![]);
+        }
+
+        let mut moves = MoveList::new();
+        self.position
+            .get_all_legal_moves(self.game_type, &mut moves)?;
+
+        Ok(moves.to_vec())
+    }
+
+    /// Plays a move, given from the perspective of the side to move.
+    pub fn make_move(&mut self, mv: PieceMove) -> Result<&GameMove, anyhow::Error> {
+        self.undone.clear();
+        self.play(mv, None)
+    }
+
+    /// Plays a move that took `elapsed_ms`, taking the time off the mover's clock. A player
+    /// who runs out of time loses, and the move isn't played.
+    pub fn make_timed_move(
+        &mut self,
+        mv: PieceMove,
+        elapsed_ms: u64,
+    ) -> Result<&GameMove, anyhow::Error> {
+        if self.clock.is_none() {
+            return Err(anyhow::anyhow!("The game isn't played with a clock"));
+        }
+
+        self.undone.clear();
+        self.play(mv, Some(elapsed_ms))
+    }
+
+    /// Plays a move written in SAN, e.g. "Nf3".
+    pub fn make_san_move(&mut self, notation: &str) -> Result<&GameMove, anyhow::Error> {
+        let mv = parse_san(&self.position, notation, self.game_type)?;
+        self.make_move(mv)
+    }
+
+    /// Plays a move written in UCI's long algebraic notation, e.g. "g1f3".
+    pub fn make_uci_move(&mut self, notation: &str) -> Result<&GameMove, anyhow::Error> {
+        let mv = match self.to_move() {
+            Color::White => PieceMove::from_uci(&self.position, notation, self.game_type)?,
+            Color::Black => PieceMove::from_uci_inverted(&self.position, notation, self.game_type)?,
+        };
+
+        self.make_move(mv)
+    }
+
+    fn play(&mut self, mv: PieceMove, elapsed_ms: Option<u64>) -> Result<&GameMove, anyhow::Error> {
+        if self.is_over() {
+            return Err(anyhow::anyhow!("The game is over: {}", self.status));
+        }
+
+        let color = self.to_move();
+        let san = to_san(&self.position, mv, self.game_type)?;
+        let uci = match color {
+            Color::White => mv.to_uci(),
+            Color::Black => mv.inverted().to_uci(),
+        };
+
+        let snapshot = Snapshot {
+            position: self.position.clone(),
+            clock: self.clock.clone(),
+            status: self.status,
+        };
+
+        if let (Some(clock), Some(elapsed_ms)) = (&mut self.clock, elapsed_ms) {
+            if !clock.record_move(color, elapsed_ms) {
+                self.status = GameStatus::Timeout(color.invert());
+                return Err(anyhow::anyhow!("{:?} ran out of time", color));
+            }
+        }
+
+        self.position.apply_move(mv)?;
+        self.position.invert();
+        self.history.push(self.position.zobrist_key());
+        self.status = self.position.game_status(self.game_type, &self.history)?;
+
+        self.snapshots.push(snapshot);
+        self.moves.push(GameMove {
+            color,
+            mv,
+            san,
+            uci,
+            elapsed_ms,
+        });
+
+        Ok(self.moves.last().unwrap())
+    }
+
+    /// Takes back the last move, along with the time it took and any result it led to.
+    /// Returns the move, or `None` if there's nothing to take back.
+    pub fn undo(&mut self) -> Option<&GameMove> {
+        let snapshot = self.snapshots.pop()?;
+        let game_move = self.moves.pop()?;
+
+        self.position = snapshot.position;
+        self.clock = snapshot.clock;
+        self.status = snapshot.status;
+        self.history.pop();
+
+        self.undone.push(game_move);
+        self.undone.last()
+    }
+
+    /// Plays the last move taken back with `undo` again. Returns the move, or `None` if no
+    /// move has been taken back since the last one played.
+    pub fn redo(&mut self) -> Result<Option<&GameMove>, anyhow::Error> {
+        let Some(game_move) = self.undone.pop() else {
+            return Ok(None);
+        };
+
+        self.play(game_move.mv, game_move.elapsed_ms).map(Some)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.moves.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// How the game stands, including whether a player has run out of time.
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// The side that ran out of time, if any.
+    pub fn flagged(&self) -> Option<Color> {
+        self.clock.as_ref().and_then(|clock| clock.flagged)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.status.is_game_over() || self.flagged().is_some()
+    }
+
+    /// The result as PGN records it.
+    pub fn result(&self) -> PgnResult {
+        let winner = match self.flagged() {
+            Some(flagged) if self.status.is_ongoing() => Some(flagged.invert()),
+            _ => self.status.winner(),
+        };
+
+        match winner {
+            Some(Color::White) => PgnResult::WhiteWins,
+            Some(Color::Black) => PgnResult::BlackWins,
+            None if self.status.is_draw() => PgnResult::Draw,
+            None => PgnResult::Unknown,
+        }
+    }
+
+    /// Ends the game with `color` resigning.
+    pub fn resign(&mut self, color: Color) -> Result<(), anyhow::Error> {
+        self.end(GameStatus::Resignation(color.invert()))
+    }
+
+    /// Ends the game with `color` running out of time, for when the clocks are kept outside
+    /// the game.
+    pub fn time_out(&mut self, color: Color) -> Result<(), anyhow::Error> {
+        self.end(GameStatus::Timeout(color.invert()))
+    }
+
+    /// Ends the game in a draw both players agreed to.
+    pub fn agree_draw(&mut self) -> Result<(), anyhow::Error> {
+        self.end(GameStatus::DrawByAgreement)
+    }
+
+    fn end(&mut self, status: GameStatus) -> Result<(), anyhow::Error> {
+        if self.is_over() {
+            return Err(anyhow::anyhow!("The game is over: {}", self.status));
+        }
+
+        self.status = status;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::time_control::Clock;
+
+    use super::*;
+
+    #[test]
+    fn test_make_move() {
+        let mut game = Game::new(GameType::Classic);
+
+        game.make_uci_move("e2e4").unwrap();
+        game.make_san_move("e5").unwrap();
+        let game_move = game.make_san_move("Nf3").unwrap();
+        assert_eq!(game_move.uci, "g1f3");
+        assert_eq!(game_move.color, Color::White);
+
+        let game_move = game.make_uci_move("b8c6").unwrap();
+        assert_eq!(game_move.san, "Nc6");
+
+        let sans: Vec<&str> = game.moves().iter().map(|mv| mv.san.as_str()).collect();
+        assert_eq!(sans, ["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(game.to_move(), Color::White);
+        assert_eq!(game.history().len(), 5);
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        assert!(game.make_san_move("Ke3").is_err());
+        assert_eq!(game.moves().len(), 4);
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let mut game = Game::new(GameType::Classic);
+
+        for mv in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(mv).unwrap();
+        }
+
+        assert_eq!(game.last_move().unwrap().san, "Qh4#");
+        assert_eq!(game.status(), GameStatus::Checkmate(Color::Black));
+        assert_eq!(game.result(), PgnResult::BlackWins);
+        assert!(game.legal_moves().unwrap().is_empty());
+        assert!(game.make_san_move("a3").is_err());
+
+        // Taking the mate back reopens the game
+        game.undo().unwrap();
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.result(), PgnResult::Unknown);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = Game::new(GameType::Classic);
+        game.make_san_move("e4").unwrap();
+        game.make_san_move("e5").unwrap();
+
+        let after_e5 = game.position().clone();
+
+        assert_eq!(game.undo().unwrap().san, "e5");
+        assert_eq!(game.undo().unwrap().san, "e4");
+        assert!(game.undo().is_none());
+        assert_eq!(game.position(), &Position::start_position());
+        assert_eq!(game.history().len(), 1);
+
+        assert_eq!(game.redo().unwrap().unwrap().san, "e4");
+        assert_eq!(game.redo().unwrap().unwrap().san, "e5");
+        assert!(game.redo().unwrap().is_none());
+        assert_eq!(game.position(), &after_e5);
+
+        // A new move replaces the moves that were taken back
+        game.undo();
+        game.make_san_move("c5").unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.last_move().unwrap().san, "c5");
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = Game::new(GameType::Classic);
+
+        for mv in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"] {
+            game.make_san_move(mv).unwrap();
+        }
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        game.make_san_move("Ng8").unwrap();
+        assert_eq!(game.status(), GameStatus::DrawByRepetition);
+        assert_eq!(game.result(), PgnResult::Draw);
+    }
+
+    #[test]
+    fn test_clock() {
+        let mut game = Game::new(GameType::Classic).with_clock(TimeControl::new(
+            Clock::new(1_000, 100),
+            Clock::new(60_000, 0),
+        ));
+
+        let mv = game.legal_moves().unwrap()[0];
+        game.make_timed_move(mv, 400).unwrap();
+        assert_eq!(game.clock().unwrap().remaining(Color::White), 700);
+
+        // Undoing a move gives its time back
+        game.undo();
+        assert_eq!(game.clock().unwrap().remaining(Color::White), 1_000);
+        game.redo().unwrap();
+        assert_eq!(game.clock().unwrap().remaining(Color::White), 700);
+
+        let mv = game.legal_moves().unwrap()[0];
+        game.make_timed_move(mv, 1_000).unwrap();
+
+        let mv = game.legal_moves().unwrap()[0];
+        assert!(game.make_timed_move(mv, 701).is_err());
+        assert_eq!(game.flagged(), Some(Color::White));
+        assert_eq!(game.status(), GameStatus::Timeout(Color::Black));
+        assert_eq!(game.result(), PgnResult::BlackWins);
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut game = Game::new(GameType::Classic);
+        game.make_san_move("e4").unwrap();
+
+        game.resign(Color::Black).unwrap();
+        assert_eq!(game.status(), GameStatus::Resignation(Color::White));
+        assert_eq!(game.result(), PgnResult::WhiteWins);
+        assert!(game.agree_draw().is_err());
+
+        let mut game = Game::new(GameType::Classic);
+        game.time_out(Color::White).unwrap();
+        assert_eq!(game.status(), GameStatus::Timeout(Color::Black));
+        assert_eq!(game.result(), PgnResult::BlackWins);
+        assert!(game.resign(Color::Black).is_err());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut game = Game::new(GameType::Rescue)
+            .with_clock(TimeControl::symmetric(Clock::new(60_000, 1_000)));
+
+        for mv in ["e2Sf2", "d5", "Kf2Sf1", "e6"] {
+            game.make_san_move(mv).unwrap();
+        }
+        game.undo();
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut loaded: Game = serde_json::from_str(&json).unwrap();
+
+        // The king is still holding the pawn it rescued
+        assert_eq!(loaded.position(), game.position());
+        assert_eq!(loaded.position().to_fen(), game.position().to_fen());
+        assert_eq!(loaded.moves(), game.moves());
+        assert_eq!(loaded.history(), game.history());
+        assert_eq!(loaded.clock(), game.clock());
+
+        assert_eq!(loaded.redo().unwrap().unwrap().san, "e6");
+        assert_eq!(loaded.undo().unwrap().san, "e6");
+        assert_eq!(loaded.undo().unwrap().san, "Kf2Sf1");
+        assert_eq!(loaded.to_move(), Color::White);
+    }
+}
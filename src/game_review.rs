@@ -0,0 +1,430 @@
+//! Reviewing a game after it's over: how much worse each move was than the engine's choice,
+//! and which moves were inaccuracies, mistakes or blunders.
+
+use std::sync::{atomic::AtomicBool, Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::Game,
+    pgn::to_san,
+    search::{
+        alpha_beta::SearchParams, backend::score_all_moves, score::Score,
+        search_results::SearchState, transposition_table::TranspositionTable,
+    },
+    Color, PieceMove, Position,
+};
+
+/// A move losing at least this many centipawns is an inaccuracy.
+pub const INACCURACY_LOSS: i32 = 50;
+
+/// A move losing at least this many centipawns is a mistake.
+pub const MISTAKE_LOSS: i32 = 100;
+
+/// A move losing at least this many centipawns is a blunder.
+pub const BLUNDER_LOSS: i32 = 300;
+
+/// The most a single move counts as losing, so a missed mate doesn't swamp a player's average.
+pub const MAX_LOSS: i32 = 1000;
+
+/// The largest advantage an evaluation graph shows, in centipawns. Mates are plotted at it.
+pub const MAX_GRAPH_CENTIPAWNS: i32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveClassification {
+    /// The engine's choice, or a move scoring just as well.
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClassification {
+    pub fn from_loss(centipawn_loss: i32) -> Self {
+        match centipawn_loss {
+            loss if loss >= BLUNDER_LOSS => MoveClassification::Blunder,
+            loss if loss >= MISTAKE_LOSS => MoveClassification::Mistake,
+            loss if loss >= INACCURACY_LOSS => MoveClassification::Inaccuracy,
+            loss if loss > 0 => MoveClassification::Good,
+            _ => MoveClassification::Best,
+        }
+    }
+}
+
+/// How one move compared to the engine's choice. Moves and scores are from the perspective of
+/// the side that made the move.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MoveReview {
+    /// The index of the move in the game, from 0.
+    pub ply: usize,
+    pub color: Color,
+
+    pub played: PieceMove,
+    pub san: String,
+    pub played_score: Score,
+
+    pub best_move: PieceMove,
+    pub best_san: String,
+    pub best_score: Score,
+
+    /// How many centipawns worse the played move scored than the best move, up to `MAX_LOSS`.
+    pub centipawn_loss: i32,
+    pub classification: MoveClassification,
+}
+
+/// How well one side played over the game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct SideReview {
+    pub moves: usize,
+    pub average_centipawn_loss: f64,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+impl SideReview {
+    fn of<'a>(reviews: impl Iterator<Item = &'a MoveReview>) -> Self {
+        let mut side = SideReview::default();
+        let mut total_loss = 0;
+
+        for review in reviews {
+            side.moves += 1;
+            total_loss += review.centipawn_loss as i64;
+
+            match review.classification {
+                MoveClassification::Inaccuracy => side.inaccuracies += 1,
+                MoveClassification::Mistake => side.mistakes += 1,
+                MoveClassification::Blunder => side.blunders += 1,
+                MoveClassification::Best | MoveClassification::Good => {}
+            }
+        }
+
+        if side.moves > 0 {
+            side.average_centipawn_loss = total_loss as f64 / side.moves as f64;
+        }
+
+        side
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameReview {
+    pub moves: Vec<MoveReview>,
+    pub white: SideReview,
+    pub black: SideReview,
+}
+
+/// The evaluation of one position of a reviewed game, for plotting who was ahead over the game.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EvaluationPoint {
+    /// How many moves into the game the position is, 0 for the position before the first move.
+    pub ply: usize,
+
+    /// From white's perspective.
+    pub evaluation: Score,
+
+    /// The evaluation to plot, from white's perspective: centipawns capped at
+    /// `MAX_GRAPH_CENTIPAWNS` either way, with mates at the cap.
+    pub centipawns: i32,
+}
+
+impl EvaluationPoint {
+    /// The point for the position `ply` moves in, scored `score` for `color`.
+    fn new(ply: usize, score: Score, color: Color) -> Self {
+        // Worked out before switching to white's perspective, since a mate already delivered
+        // is `Mate(0)` for both sides
+        let centipawns = match score {
+            Score::Centipawns(centipawns) => {
+                centipawns.clamp(-MAX_GRAPH_CENTIPAWNS, MAX_GRAPH_CENTIPAWNS)
+            }
+            Score::Mate(moves) if moves >= 0 => MAX_GRAPH_CENTIPAWNS,
+            Score::Mate(_) => -MAX_GRAPH_CENTIPAWNS,
+        };
+
+        match color {
+            Color::White => EvaluationPoint {
+                ply,
+                evaluation: score,
+                centipawns,
+            },
+            Color::Black => EvaluationPoint {
+                ply,
+                evaluation: -score,
+                centipawns: -centipawns,
+            },
+        }
+    }
+}
+
+impl GameReview {
+    pub fn new(moves: Vec<MoveReview>) -> Self {
+        let white = SideReview::of(moves.iter().filter(|review| review.color == Color::White));
+        let black = SideReview::of(moves.iter().filter(|review| review.color == Color::Black));
+
+        GameReview {
+            moves,
+            white,
+            black,
+        }
+    }
+
+    /// The evaluation of every position of the game, from the one before the first move to the
+    /// one after the last. Positions are scored by the review's search: the first by its best
+    /// move, and the rest by the move played to reach them.
+    pub fn evaluation_graph(&self) -> Vec<EvaluationPoint> {
+        let start = self
+            .moves
+            .first()
+            .map(|first| EvaluationPoint::new(first.ply, first.best_score, first.color));
+
+        start
+            .into_iter()
+            .chain(self.moves.iter().map(|review| {
+                EvaluationPoint::new(review.ply + 1, review.played_score, review.color)
+            }))
+            .collect()
+    }
+}
+
+/// Reviews every move of `game` by scoring all the moves in the position before it with
+/// `params`. `on_move_reviewed` is called as each move is reviewed, e.g. to show progress, and
+/// setting `stop` abandons the review.
+pub fn review_game(
+    game: &Game,
+    transposition_table: &mut TranspositionTable,
+    params: &SearchParams,
+    stop: Arc<AtomicBool>,
+    mut on_move_reviewed: impl FnMut(&MoveReview),
+) -> Result<GameReview, anyhow::Error> {
+    let mut reviews = Vec::with_capacity(game.moves().len());
+
+    for (ply, (position, game_move)) in game.positions_before_moves().zip(game.moves()).enumerate()
+    {
+        let review = review_move(
+            position,
+            &game.history()[..=ply],
+            ply,
+            game_move.mv,
+            transposition_table,
+            params,
+            stop.clone(),
+        )?;
+
+        on_move_reviewed(&review);
+        reviews.push(review);
+    }
+
+    Ok(GameReview::new(reviews))
+}
+
+impl Game {
+    /// Reviews `mv` as the next move of the game before it's played, by scoring all the moves in
+    /// the current position with `params`, e.g. to warn a player that it's a blunder.
+    pub fn check_move_quality(
+        &self,
+        mv: PieceMove,
+        transposition_table: &mut TranspositionTable,
+        params: &SearchParams,
+    ) -> Result<MoveReview, anyhow::Error> {
+        if self.is_over() {
+            return Err(anyhow::anyhow!("The game is over"));
+        }
+
+        review_move(
+            self.position(),
+            self.history(),
+            self.moves().len(),
+            mv,
+            transposition_table,
+            params,
+            Arc::new(AtomicBool::new(false)),
+        )
+    }
+}
+
+/// Reviews `mv`, played as the game's move `ply` in `position`. `history` has the Zobrist keys
+/// of the game's positions up to and including `position`.
+fn review_move(
+    position: &Position,
+    history: &[u64],
+    ply: usize,
+    mv: PieceMove,
+    transposition_table: &mut TranspositionTable,
+    params: &SearchParams,
+    stop: Arc<AtomicBool>,
+) -> Result<MoveReview, anyhow::Error> {
+    transposition_table.new_search();
+
+    let mut state = SearchState::new(transposition_table);
+    state.data.history = history.to_vec();
+    state.data.stop = stop;
+
+    let scores = score_all_moves(position, &mut state, params.clone(), 0)
+        .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+
+    // Sorted best first
+    let best = scores
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("No legal moves before move {}", ply + 1))?;
+    let played = scores
+        .iter()
+        .find(|move_score| move_score.mv == mv)
+        .ok_or_else(|| anyhow::anyhow!("Move {} ({}) isn't legal", ply + 1, mv.to_uci()))?;
+
+    let centipawn_loss = (best.score - played.score).clamp(0, MAX_LOSS);
+
+    let game_type = params.game_type;
+
+    Ok(MoveReview {
+        ply,
+        color: position.true_active_color,
+        played: mv,
+        san: to_san(position, mv, game_type)?,
+        played_score: Score::from_search(played.score),
+        best_move: best.mv,
+        best_san: to_san(position, best.mv, game_type)?,
+        best_score: Score::from_search(best.score),
+        centipawn_loss,
+        classification: MoveClassification::from_loss(centipawn_loss),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pgn::parse_san, piece_move::GameType};
+
+    #[test]
+    fn test_classification() {
+        assert_eq!(MoveClassification::from_loss(0), MoveClassification::Best);
+        assert_eq!(MoveClassification::from_loss(20), MoveClassification::Good);
+        assert_eq!(
+            MoveClassification::from_loss(INACCURACY_LOSS),
+            MoveClassification::Inaccuracy
+        );
+        assert_eq!(
+            MoveClassification::from_loss(150),
+            MoveClassification::Mistake
+        );
+        assert_eq!(
+            MoveClassification::from_loss(MAX_LOSS),
+            MoveClassification::Blunder
+        );
+    }
+
+    #[test]
+    fn test_review_game() {
+        let mut game = Game::new(GameType::Classic);
+        for san in ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"] {
+            game.make_san_move(san).unwrap();
+        }
+
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut reviewed = 0;
+        let review = review_game(
+            &game,
+            &mut transposition_table,
+            &params,
+            Arc::new(AtomicBool::new(false)),
+            |_| reviewed += 1,
+        )
+        .unwrap();
+
+        assert_eq!(reviewed, 7);
+        assert_eq!(review.moves.len(), 7);
+
+        // Nf6 allows mate in one
+        let blunder = &review.moves[5];
+        assert_eq!(blunder.san, "Nf6");
+        assert_eq!(blunder.classification, MoveClassification::Blunder);
+        assert_eq!(blunder.centipawn_loss, MAX_LOSS);
+        assert_ne!(blunder.best_san, "Nf6");
+
+        let mate = &review.moves[6];
+        assert_eq!(mate.classification, MoveClassification::Best);
+        assert!(matches!(mate.played_score, Score::Mate(moves) if moves >= 0));
+
+        let graph = review.evaluation_graph();
+        assert_eq!(graph.len(), 8);
+        assert_eq!(graph[0].ply, 0);
+        assert_eq!(graph[7].ply, 7);
+
+        // Black's blunder hands white a mate, which white then plays
+        assert!(matches!(graph[6].evaluation, Score::Mate(moves) if moves > 0));
+        assert!(matches!(graph[5].evaluation, Score::Centipawns(_)));
+        assert_eq!(graph[6].centipawns, MAX_GRAPH_CENTIPAWNS);
+        assert_eq!(graph[7].centipawns, MAX_GRAPH_CENTIPAWNS);
+        assert!(graph[5].centipawns.abs() < MAX_GRAPH_CENTIPAWNS);
+
+        assert_eq!(review.black.blunders, 1);
+        assert_eq!(review.black.moves, 3);
+        assert_eq!(review.white.moves, 4);
+        assert!(review.black.average_centipawn_loss > review.white.average_centipawn_loss);
+    }
+
+    #[test]
+    fn test_check_move_quality() {
+        let mut game = Game::new(GameType::Classic);
+        for san in ["e4", "e5", "Bc4", "Nc6", "Qh5"] {
+            game.make_san_move(san).unwrap();
+        }
+
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+        let mut transposition_table = TranspositionTable::new();
+
+        let nf6 = parse_san(game.position(), "Nf6", GameType::Classic).unwrap();
+        let review = game
+            .check_move_quality(nf6, &mut transposition_table, &params)
+            .unwrap();
+        assert_eq!(review.ply, 5);
+        assert_eq!(review.color, Color::Black);
+        assert_eq!(review.san, "Nf6");
+        assert_eq!(review.classification, MoveClassification::Blunder);
+
+        let g6 = parse_san(game.position(), "g6", GameType::Classic).unwrap();
+        let review = game
+            .check_move_quality(g6, &mut transposition_table, &params)
+            .unwrap();
+        assert!(review.centipawn_loss < BLUNDER_LOSS);
+
+        // The game isn't changed by checking moves
+        assert_eq!(game.moves().len(), 5);
+    }
+
+    #[test]
+    fn test_evaluation_graph_when_black_mates() {
+        let mut game = Game::new(GameType::Classic);
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san).unwrap();
+        }
+
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+        let review = review_game(
+            &game,
+            &mut TranspositionTable::new(),
+            &params,
+            Arc::new(AtomicBool::new(false)),
+            |_| {},
+        )
+        .unwrap();
+
+        let graph = review.evaluation_graph();
+        let plotted: Vec<i32> = graph.iter().map(|point| point.centipawns).collect();
+        assert_eq!(plotted[3..], [-MAX_GRAPH_CENTIPAWNS, -MAX_GRAPH_CENTIPAWNS]);
+    }
+}
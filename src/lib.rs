@@ -1,15 +1,35 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc; // 14% faster on Windows!
 
+pub mod bench;
 pub mod bitboard;
+#[cfg(feature = "bot")]
+pub mod bot;
+pub mod endgame_training;
+#[cfg(feature = "async")]
+pub mod engine;
 pub mod evaluation;
+pub mod explanation;
 pub mod features;
+pub mod game;
+pub mod game_review;
+pub mod opening_book;
+pub mod opening_explorer;
+pub mod pgn;
 pub mod piece;
 pub mod piece_move;
 pub mod pos;
 pub mod position;
 pub mod search;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod test_suite;
+pub mod threats;
+pub mod tournament;
+pub mod training_data;
 pub mod uci;
+pub mod variant;
+pub mod xboard;
 
 pub use bitboard::Bitboard;
 pub use piece::{Color, Piece, PieceType};
@@ -0,0 +1,686 @@
+//! An opening book in a format of its own, since Polyglot books have no way to write Rescue's
+//! rescues and drops, along with a builder that fills one from self-play games.
+//!
+//! A book file starts with an 8 byte header: the bytes `RCBK`, the format version, the game
+//! type (0 for classic, 1 for Rescue) and two zero bytes. Then come its entries, sorted by
+//! position key, 16 little endian bytes each:
+//!
+//! | Bytes | Contents                                                                         |
+//! |-------|----------------------------------------------------------------------------------|
+//! | 0-7   | The position's Zobrist key, from the perspective of its side to move            |
+//! | 8-11  | The move, from the same perspective (see `encode_move`)                          |
+//! | 12-13 | The move's weight: two for each game the mover won with it, and one for each draw |
+//! | 14-15 | The number of games it was played in                                            |
+
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    game::Game,
+    pgn::PgnResult,
+    piece_move::{GameType, MoveList, MoveType},
+    search::{
+        alpha_beta::SearchParams, backend, search_results::SearchState,
+        transposition_table::TranspositionTable,
+    },
+    Color, PieceMove, PieceType, Pos, Position,
+};
+
+const MAGIC: &[u8; 4] = b"RCBK";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 16;
+
+/// The variety that picks book moves in proportion to their weights.
+pub const DEFAULT_VARIETY: u32 = 100;
+
+/// The most variety the engine's `BookVariety` option can be set to.
+pub const MAX_VARIETY: u32 = 200;
+
+/// A move in a book, resolved against the legal moves of the position it's played in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    /// The move, from the perspective of the side to move.
+    pub mv: PieceMove,
+
+    pub weight: u16,
+    pub games: u16,
+}
+
+/// A move as it's stored in the book, before it's been matched to a legal move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BookEntry {
+    mv: u32,
+    weight: u16,
+    games: u16,
+}
+
+/// The moves to play from each position of the book, keyed by Zobrist hash so transpositions
+/// share their moves.
+#[derive(Debug, Clone)]
+pub struct OpeningBook {
+    pub game_type: GameType,
+    positions: HashMap<u64, Vec<BookEntry>>,
+}
+
+impl OpeningBook {
+    pub fn new(game_type: GameType) -> Self {
+        Self {
+            game_type,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Records a move played from a position (both from the perspective of its side to move)
+    /// in a game where the mover scored `points`: two for a win and one for a draw.
+    pub fn add_move(&mut self, position: &Position, mv: PieceMove, points: u16) {
+        let entries = self.positions.entry(position.zobrist_key()).or_default();
+        let encoded = encode_move(&mv);
+
+        let index = match entries.iter().position(|entry| entry.mv == encoded) {
+            Some(index) => index,
+            None => {
+                entries.push(BookEntry {
+                    mv: encoded,
+                    weight: 0,
+                    games: 0,
+                });
+                entries.len() - 1
+            }
+        };
+
+        let entry = &mut entries[index];
+        entry.weight = entry.weight.saturating_add(points);
+        entry.games = entry.games.saturating_add(1);
+    }
+
+    /// Records the first `max_ply` moves of a game, weighted by how the game ended.
+    pub fn add_game(&mut self, game: &Game, max_ply: usize) {
+        let result = game.result();
+
+        for (position, played) in game
+            .positions_before_moves()
+            .zip(game.moves())
+            .take(max_ply)
+        {
+            let points = match (result, played.color) {
+                (PgnResult::WhiteWins, Color::White) | (PgnResult::BlackWins, Color::Black) => 2,
+                (PgnResult::Draw, _) => 1,
+                _ => 0,
+            };
+
+            self.add_move(position, played.mv, points);
+        }
+    }
+
+    /// The book's moves from a position (from the perspective of its side to move), heaviest
+    /// first. Moves that aren't legal in the position, e.g. from a hash collision, are left out.
+    pub fn moves(&self, position: &Position) -> Result<Vec<BookMove>, anyhow::Error> {
+        let Some(entries) = self.positions.get(&position.zobrist_key()) else {
+            return Ok(vec![]);
+        };
+
+        let mut legal_moves = MoveList::new();
+        position.get_all_legal_moves(self.game_type, &mut legal_moves)?;
+
+        let mut moves = entries
+            .iter()
+            .filter_map(|entry| {
+                let mv = legal_moves.iter().find(|mv| encode_move(mv) == entry.mv)?;
+
+                Some(BookMove {
+                    mv: *mv,
+                    weight: entry.weight,
+                    games: entry.games,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        Ok(moves)
+    }
+
+    /// Picks one of the book's moves from a position at random, in proportion to their
+    /// weights. Returns `None` if the book has no move with any weight for the position.
+    pub fn pick_move<R: Rng + ?Sized>(
+        &self,
+        position: &Position,
+        rng: &mut R,
+    ) -> Result<Option<PieceMove>, anyhow::Error> {
+        self.pick_move_with_variety(position, DEFAULT_VARIETY, rng)
+    }
+
+    /// Picks one of the book's moves from a position at random, with `variety` how far from
+    /// the heaviest move to stray: 0 always picks the heaviest, 100 picks in proportion to the
+    /// weights, and more than that evens the weights out. Moves without weight are never picked.
+    pub fn pick_move_with_variety<R: Rng + ?Sized>(
+        &self,
+        position: &Position,
+        variety: u32,
+        rng: &mut R,
+    ) -> Result<Option<PieceMove>, anyhow::Error> {
+        // Heaviest first
+        let moves = self
+            .moves(position)?
+            .into_iter()
+            .filter(|book_move| book_move.weight > 0)
+            .collect::<Vec<_>>();
+
+        if variety == 0 {
+            return Ok(moves.first().map(|book_move| book_move.mv));
+        }
+
+        let exponent = DEFAULT_VARIETY as f64 / variety as f64;
+        let weights = moves
+            .iter()
+            .map(|book_move| (book_move.weight as f64).powf(exponent))
+            .collect::<Vec<_>>();
+        let total: f64 = weights.iter().sum();
+
+        if total <= 0.0 {
+            return Ok(None);
+        }
+
+        let mut choice = rng.gen_range(0.0..total);
+        for (book_move, weight) in moves.iter().zip(&weights) {
+            if choice < *weight {
+                return Ok(Some(book_move.mv));
+            }
+            choice -= weight;
+        }
+
+        // Rounding can leave a sliver of the total past the last move
+        Ok(moves.last().map(|book_move| book_move.mv))
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Writes the book in its file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut keys = self.positions.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + keys.len() * ENTRY_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.game_type {
+            GameType::Classic => 0,
+            GameType::Rescue => 1,
+        });
+        bytes.extend_from_slice(&[0, 0]);
+
+        for key in keys {
+            for entry in &self.positions[&key] {
+                bytes.extend_from_slice(&key.to_le_bytes());
+                bytes.extend_from_slice(&entry.mv.to_le_bytes());
+                bytes.extend_from_slice(&entry.weight.to_le_bytes());
+                bytes.extend_from_slice(&entry.games.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Reads a book written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(anyhow::anyhow!("Not an opening book"));
+        }
+
+        if bytes[4] != VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported opening book version {}",
+                bytes[4]
+            ));
+        }
+
+        let game_type = match bytes[5] {
+            0 => GameType::Classic,
+            1 => GameType::Rescue,
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Invalid game type in opening book: {}",
+                    other
+                ))
+            }
+        };
+
+        let entries = &bytes[HEADER_SIZE..];
+        if !entries.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(anyhow::anyhow!("Opening book is truncated"));
+        }
+
+        let mut book = OpeningBook::new(game_type);
+        for entry in entries.chunks_exact(ENTRY_SIZE) {
+            let key = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+
+            book.positions.entry(key).or_default().push(BookEntry {
+                mv: u32::from_le_bytes(entry[8..12].try_into().unwrap()),
+                weight: u16::from_le_bytes(entry[12..14].try_into().unwrap()),
+                games: u16::from_le_bytes(entry[14..16].try_into().unwrap()),
+            });
+        }
+
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        fs::write(path, self.to_bytes())
+            .map_err(|e| anyhow::anyhow!("Couldn't write {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let bytes = fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Couldn't read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// A book the engine plays its moves from until the game leaves it. Once a position has no book
+/// move, the book isn't looked at again for the rest of the game, so a transposition back into
+/// it later on doesn't pull the engine out of its own plans.
+#[derive(Debug, Clone)]
+pub struct BookPlayer {
+    pub book: Arc<OpeningBook>,
+
+    /// How far from the heaviest moves to stray, see `OpeningBook::pick_move_with_variety`.
+    pub variety: u32,
+
+    /// The ply the game left the book at, if it has. An earlier ply means a new game.
+    left_book_at: Option<usize>,
+}
+
+impl BookPlayer {
+    pub fn new(book: Arc<OpeningBook>, variety: u32) -> Self {
+        Self {
+            book,
+            variety,
+            left_book_at: None,
+        }
+    }
+
+    /// The book move to play from `position`, `ply` plies into a game of `game_type`, or `None`
+    /// if the game is out of the book.
+    pub fn next_move<R: Rng + ?Sized>(
+        &mut self,
+        position: &Position,
+        ply: usize,
+        game_type: GameType,
+        rng: &mut R,
+    ) -> Result<Option<PieceMove>, anyhow::Error> {
+        if self.book.game_type != game_type {
+            return Ok(None);
+        }
+
+        if let Some(left_book_at) = self.left_book_at {
+            if ply >= left_book_at {
+                return Ok(None);
+            }
+        }
+
+        let mv = self
+            .book
+            .pick_move_with_variety(position, self.variety, rng)?;
+
+        self.left_book_at = match mv {
+            Some(_) => None,
+            None => Some(ply),
+        };
+
+        Ok(mv)
+    }
+
+    /// Whether the game has left the book.
+    pub fn is_out_of_book(&self) -> bool {
+        self.left_book_at.is_some()
+    }
+
+    /// Starts looking in the book again, for a new game.
+    pub fn new_game(&mut self) {
+        self.left_book_at = None;
+    }
+}
+
+/// Packs the parts of a move that tell it apart from the position's other legal moves into 32
+/// bits:
+///
+/// | Bits  | Contents                                          |
+/// |-------|---------------------------------------------------|
+/// | 0-5   | The square moved from                             |
+/// | 6-11  | The square moved to                               |
+/// | 12-14 | The piece promoted to, see `piece_code`            |
+/// | 15-20 | The square of the piece rescued                   |
+/// | 21    | Set if a piece is rescued                         |
+/// | 22-27 | The square the held piece is dropped on           |
+/// | 28    | Set if a piece is dropped                         |
+/// | 29-31 | The piece the dropped pawn is promoted to, if any |
+///
+/// Castling is told apart by its squares, and captures by the squares they're made on.
+pub fn encode_move(mv: &PieceMove) -> u32 {
+    let square = |pos: Pos| pos.0 as u32;
+    let mut encoded = square(mv.from) | square(mv.to) << 6;
+
+    if let MoveType::Normal {
+        promoted_to,
+        rescued_pos,
+        dropped_pos,
+        dropped_promoted_to,
+        ..
+    } = mv.move_type
+    {
+        encoded |= piece_code(promoted_to) << 12;
+
+        if let Some(rescued_pos) = rescued_pos {
+            encoded |= square(rescued_pos) << 15 | 1 << 21;
+        }
+
+        if let Some(dropped_pos) = dropped_pos {
+            encoded |= square(dropped_pos) << 22 | 1 << 28;
+        }
+
+        encoded |= piece_code(dropped_promoted_to) << 29;
+    }
+
+    encoded
+}
+
+/// A promotion as 3 bits: 0 for none, otherwise 1 for a knight up to 4 for a queen.
+fn piece_code(piece_type: Option<PieceType>) -> u32 {
+    match piece_type {
+        None | Some(PieceType::Pawn) | Some(PieceType::King) => 0,
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+    }
+}
+
+/// Builds a book by having the engine play itself. The first few moves of each game are
+/// picked at random from the ones the engine scores nearly as well as its best, so the games
+/// branch out into different openings.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    pub game_type: GameType,
+
+    /// How deep to search each move.
+    pub depth: u32,
+
+    /// How many plies of each game are added to the book.
+    pub book_plies: usize,
+
+    /// How many plies at the start of each game are picked at random.
+    pub random_plies: usize,
+
+    /// How much worse than the best move, in centipawns, a move picked at random can be.
+    pub margin: i32,
+
+    /// Games still going after this many plies are left unfinished, and add nothing to the
+    /// weights of their moves.
+    pub max_plies: usize,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        Self {
+            game_type: GameType::Rescue,
+            depth: 4,
+            book_plies: 16,
+            random_plies: 8,
+            margin: 30,
+            max_plies: 200,
+        }
+    }
+}
+
+impl BookBuilder {
+    /// Plays one game of the engine against itself.
+    pub fn play_game<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Game, anyhow::Error> {
+        let mut game = Game::new(self.game_type);
+        let mut transposition_table = TranspositionTable::new();
+
+        let params = SearchParams::builder()
+            .depth(self.depth)
+            .game_type(self.game_type)
+            .build()?;
+
+        while !game.is_over() && game.moves().len() < self.max_plies {
+            transposition_table.new_search();
+
+            let mut state = SearchState::new(&mut transposition_table);
+            state.data.history = game.history().to_vec();
+
+            let scores = backend::score_all_moves(game.position(), &mut state, params.clone(), 0)
+                .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+
+            // Sorted best first
+            let Some(best) = scores.first() else {
+                break;
+            };
+
+            let mv = if game.moves().len() < self.random_plies {
+                let candidates = scores
+                    .iter()
+                    .take_while(|move_score| move_score.score >= best.score - self.margin)
+                    .collect::<Vec<_>>();
+
+                candidates[rng.gen_range(0..candidates.len())].mv
+            } else {
+                best.mv
+            };
+
+            game.make_move(mv)?;
+        }
+
+        Ok(game)
+    }
+
+    /// Plays `games` games and adds their openings to a new book, calling `on_game` with each
+    /// game as it finishes.
+    pub fn build<R: Rng + ?Sized>(
+        &self,
+        games: usize,
+        rng: &mut R,
+        mut on_game: impl FnMut(usize, &Game),
+    ) -> Result<OpeningBook, anyhow::Error> {
+        let mut book = OpeningBook::new(self.game_type);
+
+        for i in 0..games {
+            let game = self.play_game(rng)?;
+            book.add_game(&game, self.book_plies);
+            on_game(i, &game);
+        }
+
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::import_pgn;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const GAMES: &str = r#"[Result "1-0"]
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Result "1/2-1/2"]
+1. e4 c5 1/2-1/2
+
+[Result "0-1"]
+1. d4 d5 0-1
+"#;
+
+    fn book() -> OpeningBook {
+        let mut book = OpeningBook::new(GameType::Classic);
+        for game in import_pgn(GAMES, GameType::Classic).unwrap() {
+            book.add_game(&game, 20);
+        }
+        book
+    }
+
+    #[test]
+    fn test_weights() {
+        let book = book();
+        let moves = book.moves(&Position::start_position()).unwrap();
+
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].mv.to_string(), "e4");
+        assert_eq!((moves[0].weight, moves[0].games), (3, 2));
+        assert_eq!((moves[1].weight, moves[1].games), (0, 1));
+
+        // Black's replies are weighted for black
+        let mut game = Game::new(GameType::Classic);
+        game.make_san_move("d4").unwrap();
+        let moves = book.moves(game.position()).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].weight, 2);
+
+        // A move without weight is never picked
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let mv = book
+                .pick_move(&Position::start_position(), &mut rng)
+                .unwrap();
+            assert_eq!(mv.map(|mv| mv.to_string()).as_deref(), Some("e4"));
+        }
+    }
+
+    #[test]
+    fn test_variety() {
+        let mut book = OpeningBook::new(GameType::Classic);
+        let games = "[Result \"1-0\"]\n1. e4 1-0\n\n[Result \"1-0\"]\n1. e4 1-0\n\n\
+                     [Result \"1-0\"]\n1. e4 1-0\n\n[Result \"1-0\"]\n1. d4 1-0\n";
+        for game in import_pgn(games, GameType::Classic).unwrap() {
+            book.add_game(&game, 20);
+        }
+
+        let position = Position::start_position();
+        let count_e4 = |variety| {
+            let mut rng = StdRng::seed_from_u64(3);
+            (0..1_000)
+                .filter(|_| {
+                    let mv = book.pick_move_with_variety(&position, variety, &mut rng);
+                    mv.unwrap().unwrap().to_string() == "e4"
+                })
+                .count()
+        };
+
+        // e4 is three times as heavy as d4
+        assert_eq!(count_e4(0), 1_000);
+        assert!((700..800).contains(&count_e4(DEFAULT_VARIETY)));
+        assert!(count_e4(MAX_VARIETY) < count_e4(DEFAULT_VARIETY));
+    }
+
+    #[test]
+    fn test_book_player_leaves_book() {
+        let mut player = BookPlayer::new(Arc::new(book()), 0);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut game = Game::new(GameType::Classic);
+
+        let mv = player
+            .next_move(game.position(), 0, GameType::Classic, &mut rng)
+            .unwrap();
+        assert_eq!(mv.map(|mv| mv.to_string()).as_deref(), Some("e4"));
+
+        // Out of the book after 1. a3, and it stays out even if the game transposes back in
+        game.make_san_move("a3").unwrap();
+        let mv = player.next_move(game.position(), 1, GameType::Classic, &mut rng);
+        assert_eq!(mv.unwrap(), None);
+        assert!(player.is_out_of_book());
+
+        let start = Position::start_position();
+        let mv = player.next_move(&start, 4, GameType::Classic, &mut rng);
+        assert_eq!(mv.unwrap(), None);
+
+        // An earlier ply is a new game
+        assert!(player
+            .next_move(&start, 0, GameType::Classic, &mut rng)
+            .unwrap()
+            .is_some());
+
+        // A book for another variant is never played from
+        player.new_game();
+        let mv = player.next_move(&start, 0, GameType::Rescue, &mut rng);
+        assert_eq!(mv.unwrap(), None);
+    }
+
+    #[test]
+    fn test_rescue_moves() {
+        let mut rescue = Game::new(GameType::Rescue);
+        for notation in ["Nf3Sf2", "Nf6", "Ng1", "Ng8", "Nf3Df2"] {
+            rescue.make_san_move(notation).unwrap();
+        }
+        rescue.resign(Color::Black).unwrap();
+
+        let mut plain = Game::new(GameType::Rescue);
+        plain.make_san_move("Nf3").unwrap();
+        plain.agree_draw().unwrap();
+
+        let mut book = OpeningBook::new(GameType::Rescue);
+        book.add_game(&rescue, 20);
+        book.add_game(&plain, 20);
+
+        let book = OpeningBook::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(book.game_type, GameType::Rescue);
+
+        // The knight's move and the same move rescuing a pawn are kept apart
+        let moves = book.moves(&Position::start_position()).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].mv, rescue.moves()[0].mv);
+        assert_eq!(moves[0].weight, 2);
+        assert_eq!(moves[1].mv, plain.moves()[0].mv);
+        assert_eq!(moves[1].weight, 1);
+
+        let before_drop = rescue.positions_before_moves().nth(4).unwrap();
+        let moves = book.moves(before_drop).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].mv, rescue.moves()[4].mv);
+        assert_eq!(moves[0].mv.to_san(before_drop).unwrap(), "Nf3Df2");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let book = book();
+        let bytes = book.to_bytes();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(bytes.len(), HEADER_SIZE + 7 * ENTRY_SIZE);
+
+        let read = OpeningBook::from_bytes(&bytes).unwrap();
+        assert_eq!(read.len(), book.len());
+        assert_eq!(read.to_bytes(), bytes);
+
+        assert!(OpeningBook::from_bytes(b"RCBK").is_err());
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_build() {
+        let builder = BookBuilder {
+            depth: 2,
+            book_plies: 4,
+            random_plies: 4,
+            max_plies: 6,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut played = 0;
+        let book = builder.build(2, &mut rng, |_, _| played += 1).unwrap();
+
+        assert_eq!(played, 2);
+        assert!(!book.is_empty());
+
+        // Unfinished games count, but add no weight
+        let moves = book.moves(&Position::start_position()).unwrap();
+        assert_eq!(moves.iter().map(|mv| mv.games).sum::<u16>(), 2);
+        assert!(moves.iter().all(|mv| mv.weight == 0));
+    }
+}
@@ -0,0 +1,319 @@
+//! An opening tree built from databases of PGN games, recording which moves were played from
+//! each position and how those games ended.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    pgn::{parse_pgn, PgnGame, PgnResult},
+    piece_move::GameType,
+    Color, PieceMove, Position,
+};
+
+/// How the games that reached a position or played a move ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResultStats {
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+
+    /// Games without a recorded result.
+    pub unknown: u32,
+}
+
+impl ResultStats {
+    pub fn games(&self) -> u32 {
+        self.white_wins + self.draws + self.black_wins + self.unknown
+    }
+
+    /// White's score from the finished games, between 0 and 1, counting draws as half a point.
+    pub fn white_score(&self) -> Option<f32> {
+        let finished = self.white_wins + self.draws + self.black_wins;
+
+        if finished == 0 {
+            return None;
+        }
+
+        Some((self.white_wins as f32 + self.draws as f32 / 2.0) / finished as f32)
+    }
+
+    fn record(&mut self, result: PgnResult) {
+        match result {
+            PgnResult::WhiteWins => self.white_wins += 1,
+            PgnResult::BlackWins => self.black_wins += 1,
+            PgnResult::Draw => self.draws += 1,
+            PgnResult::Unknown => self.unknown += 1,
+        }
+    }
+
+    fn add(&mut self, other: &ResultStats) {
+        self.white_wins += other.white_wins;
+        self.draws += other.draws;
+        self.black_wins += other.black_wins;
+        self.unknown += other.unknown;
+    }
+}
+
+/// A move played from a position in the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplorerMove {
+    /// The move, from the perspective of the side to move.
+    pub mv: PieceMove,
+
+    /// The move in algebraic notation on the real board.
+    pub notation: String,
+
+    pub stats: ResultStats,
+}
+
+/// How many files and games were read by `OpeningTree::index_directory`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct IndexSummary {
+    pub files: usize,
+    pub games: usize,
+
+    /// Games that couldn't be read, e.g. because of an illegal move.
+    pub skipped: usize,
+}
+
+impl IndexSummary {
+    fn add(&mut self, other: &IndexSummary) {
+        self.files += other.files;
+        self.games += other.games;
+        self.skipped += other.skipped;
+    }
+}
+
+/// The moves played from every position in the indexed games, up to a maximum number of
+/// plies into each game. Positions are keyed by their Zobrist hash, so transpositions share
+/// their statistics.
+#[derive(Debug, Clone)]
+pub struct OpeningTree {
+    positions: HashMap<u64, Vec<ExplorerMove>>,
+
+    /// How many plies of each game are indexed.
+    pub max_ply: usize,
+
+    pub game_type: GameType,
+
+    /// The number of games indexed.
+    pub games: usize,
+}
+
+impl OpeningTree {
+    pub fn new(max_ply: usize, game_type: GameType) -> Self {
+        Self {
+            positions: HashMap::new(),
+            max_ply,
+            game_type,
+            games: 0,
+        }
+    }
+
+    /// Adds the opening of a game to the tree. If any of its indexed moves are illegal, the
+    /// whole game is left out.
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<(), anyhow::Error> {
+        let opening = PgnGame {
+            moves: game.moves.iter().take(self.max_ply).cloned().collect(),
+            ..game.clone()
+        };
+
+        let mut played = Vec::new();
+        opening.replay(self.game_type, |position, mv| {
+            played.push((position.zobrist_key(), mv, position.true_active_color));
+        })?;
+
+        for (key, mv, color) in played {
+            let moves = self.positions.entry(key).or_default();
+
+            let index = match moves.iter().position(|explored| explored.mv == mv) {
+                Some(index) => index,
+                None => {
+                    let notation = match color {
+                        Color::White => mv.to_string(),
+                        Color::Black => mv.inverted().to_string(),
+                    };
+
+                    moves.push(ExplorerMove {
+                        mv,
+                        notation,
+                        stats: ResultStats::default(),
+                    });
+                    moves.len() - 1
+                }
+            };
+
+            moves[index].stats.record(game.result);
+        }
+
+        self.games += 1;
+
+        Ok(())
+    }
+
+    /// Adds every game in a PGN file's contents, skipping games that can't be read.
+    pub fn add_pgn(&mut self, pgn: &str) -> Result<IndexSummary, anyhow::Error> {
+        let mut summary = IndexSummary {
+            files: 1,
+            ..Default::default()
+        };
+
+        for game in parse_pgn(pgn)? {
+            match self.add_game(&game) {
+                Ok(()) => summary.games += 1,
+                Err(_) => summary.skipped += 1,
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Adds the games in every .pgn file in a directory and its subdirectories.
+    pub fn index_directory(&mut self, dir: &Path) -> Result<IndexSummary, anyhow::Error> {
+        let mut summary = IndexSummary::default();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                summary.add(&self.index_directory(&path)?);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"))
+            {
+                let pgn = fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("Couldn't read {}: {}", path.display(), e))?;
+                summary.add(&self.add_pgn(&pgn)?);
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// The moves played from a position (from the perspective of its side to move), most
+    /// popular first.
+    pub fn moves(&self, position: &Position) -> Vec<ExplorerMove> {
+        let mut moves = self
+            .positions
+            .get(&position.zobrist_key())
+            .cloned()
+            .unwrap_or_default();
+
+        moves.sort_by_key(|explored| std::cmp::Reverse(explored.stats.games()));
+        moves
+    }
+
+    /// How the games that continued from a position ended.
+    pub fn position_stats(&self, position: &Position) -> ResultStats {
+        let mut stats = ResultStats::default();
+
+        for explored in self
+            .positions
+            .get(&position.zobrist_key())
+            .into_iter()
+            .flatten()
+        {
+            stats.add(&explored.stats);
+        }
+
+        stats
+    }
+
+    /// The number of distinct positions in the tree.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_san;
+
+    const GAMES: &str = r#"[Result "1-0"]
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0
+
+[Result "1/2-1/2"]
+1. e4 e5 2. Nf3 Nf6 1/2-1/2
+
+[Result "0-1"]
+1. d4 d5 2. c4 e6 0-1
+
+[Result "0-1"]
+1. e4 c5 2. Nf3 d6 3. d4 *
+"#;
+
+    fn tree() -> OpeningTree {
+        let mut tree = OpeningTree::new(20, GameType::Classic);
+        let summary = tree.add_pgn(GAMES).unwrap();
+        assert_eq!(summary.games, 4);
+        assert_eq!(summary.skipped, 0);
+        tree
+    }
+
+    fn play(moves: &[&str]) -> Position {
+        let mut position = Position::start_position();
+        for notation in moves {
+            let mv = parse_san(&position, notation, GameType::Classic).unwrap();
+            position.apply_move(mv).unwrap();
+            position.invert();
+        }
+        position
+    }
+
+    #[test]
+    fn test_start_position_moves() {
+        let tree = tree();
+        let moves = tree.moves(&Position::start_position());
+
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].notation, "e4");
+        assert_eq!(moves[0].stats.games(), 3);
+        assert_eq!(moves[0].stats.white_wins, 1);
+        assert_eq!(moves[0].stats.draws, 1);
+        assert_eq!(moves[0].stats.unknown, 1);
+        assert_eq!(moves[1].stats.black_wins, 1);
+
+        let stats = tree.position_stats(&Position::start_position());
+        assert_eq!(stats.games(), 4);
+        assert_eq!(stats.white_score(), Some(0.5));
+    }
+
+    #[test]
+    fn test_black_moves_use_real_squares() {
+        let tree = tree();
+        let moves = tree.moves(&play(&["e4", "e5", "Nf3"]));
+
+        assert_eq!(moves.len(), 2);
+        assert!(moves
+            .iter()
+            .any(|explored| explored.notation == "Nc6" && explored.stats.white_wins == 1));
+        assert!(moves
+            .iter()
+            .any(|explored| explored.notation == "Nf6" && explored.stats.draws == 1));
+    }
+
+    #[test]
+    fn test_max_ply() {
+        let mut tree = OpeningTree::new(1, GameType::Classic);
+        tree.add_pgn(GAMES).unwrap();
+
+        assert_eq!(tree.len(), 1);
+        assert!(tree.moves(&play(&["e4"])).is_empty());
+    }
+
+    #[test]
+    fn test_illegal_games_are_skipped() {
+        let mut tree = OpeningTree::new(20, GameType::Classic);
+        let summary = tree.add_pgn("1. e4 e5 2. Ke3 *\n\n1. d4 *").unwrap();
+
+        assert_eq!(summary.games, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(tree.moves(&Position::start_position()).len(), 1);
+    }
+}
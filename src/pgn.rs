@@ -0,0 +1,730 @@
+//! Reading and writing games in PGN (Portable Game Notation).
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{
+    game::Game,
+    piece_move::{GameType, MoveList, MoveType, ParserOptions, PieceNotation},
+    search::time_control::{Clock, TimeControl},
+    Color, PieceMove, Position,
+};
+
+/// The result of a game, as recorded in its movetext.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+
+    /// The game is unfinished or its result is unknown ("*").
+    #[default]
+    Unknown,
+}
+
+impl PgnResult {
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(PgnResult::WhiteWins),
+            "0-1" => Some(PgnResult::BlackWins),
+            "1/2-1/2" => Some(PgnResult::Draw),
+            "*" => Some(PgnResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn token(&self) -> &'static str {
+        match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unknown => "*",
+        }
+    }
+}
+
+/// A single game read from a PGN file. Variations and annotations are dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PgnGame {
+    /// The tag pairs, e.g. ("White", "Carlsen, Magnus"), in the order they appear.
+    pub tags: Vec<(String, String)>,
+
+    /// The moves of the main line, in the notation they were written in.
+    pub moves: Vec<String>,
+
+    /// The comments on the main line, each with the number of moves played before it.
+    pub comments: Vec<(usize, String)>,
+
+    pub result: PgnResult,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from: the FEN tag's position if there is one, otherwise the
+    /// standard starting position.
+    pub fn starting_position(&self) -> Result<Position, anyhow::Error> {
+        match self.tag("FEN") {
+            Some(fen) => Position::parse_from_fen(fen),
+            None => Ok(Position::start_position()),
+        }
+    }
+
+    /// Plays through the game's moves, calling `visit` with each position (from the perspective
+    /// of its side to move) and the move played from it. Returns the final position.
+    pub fn replay(
+        &self,
+        game_type: GameType,
+        mut visit: impl FnMut(&Position, PieceMove),
+    ) -> Result<Position, anyhow::Error> {
+        let mut position = self.starting_position()?;
+
+        for (i, notation) in self.moves.iter().enumerate() {
+            let mv = parse_san(&position, notation, game_type).map_err(|e| {
+                anyhow::anyhow!("Invalid move {} at ply {}: {}", notation, i + 1, e)
+            })?;
+
+            visit(&position, mv);
+
+            position.apply_move(mv)?;
+            position.invert();
+        }
+
+        Ok(position)
+    }
+
+    /// Plays through the game's moves into a `Game`. A result the final position doesn't
+    /// account for is taken as a resignation or an agreed draw, so the game ends the same way
+    /// the PGN says it did. Games with a time control, as `export_pgn` writes them, are played
+    /// with clocks, taking the time in each move's `%emt` comment off them.
+    pub fn to_game(&self, game_type: GameType) -> Result<Game, anyhow::Error> {
+        let mut game = Game::from_position(self.starting_position()?, game_type)?;
+
+        if let Some(time_control) = self.time_control()? {
+            game = game.with_clock(time_control);
+        }
+
+        for (i, notation) in self.moves.iter().enumerate() {
+            let played = match self.elapsed_ms(i).filter(|_| game.clock().is_some()) {
+                Some(elapsed_ms) => parse_san(game.position(), notation, game_type)
+                    .and_then(|mv| game.make_timed_move(mv, elapsed_ms).map(|_| ())),
+                None => game.make_san_move(notation).map(|_| ()),
+            };
+
+            played.map_err(|e| {
+                anyhow::anyhow!("Invalid move {} at ply {}: {}", notation, i + 1, e)
+            })?;
+        }
+
+        if !game.is_over() {
+            match self.result {
+                PgnResult::WhiteWins => game.resign(Color::Black)?,
+                PgnResult::BlackWins => game.resign(Color::White)?,
+                PgnResult::Draw => game.agree_draw()?,
+                PgnResult::Unknown => {}
+            }
+        }
+
+        Ok(game)
+    }
+
+    /// The clocks from the `TimeControl` tag, or from the `WhiteTimeControl` and
+    /// `BlackTimeControl` tags when the sides' clocks differ.
+    fn time_control(&self) -> Result<Option<TimeControl>, anyhow::Error> {
+        let white = self.tag("WhiteTimeControl").or(self.tag("TimeControl"));
+        let black = self.tag("BlackTimeControl").or(self.tag("TimeControl"));
+
+        match (white, black) {
+            (Some(white), Some(black)) => Ok(Some(TimeControl::new(
+                parse_pgn_clock(white)?,
+                parse_pgn_clock(black)?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// The time the move at `ply` (counting from 0) took, from an `[%emt h:mm:ss]` comment
+    /// after it.
+    fn elapsed_ms(&self, ply: usize) -> Option<u64> {
+        self.comments
+            .iter()
+            .filter(|(moves_before, _)| *moves_before == ply + 1)
+            .find_map(|(_, comment)| {
+                let emt = comment.split("[%emt ").nth(1)?.split(']').next()?;
+                parse_duration_ms(emt.trim())
+            })
+    }
+}
+
+/// Writes a game in PGN, with a `Variant` tag for its variant, its starting position if it
+/// isn't the standard one, and for games played with clocks, the time control and the time each
+/// move took in `%emt` comments. `to_game` reads it all back.
+pub fn export_pgn(game: &Game) -> String {
+    let sans: Vec<&str> = game
+        .moves()
+        .iter()
+        .map(|game_move| game_move.san.as_str())
+        .collect();
+
+    write_pgn(game, &sans)
+}
+
+/// Writes a game in PGN like `export_pgn`, with the pieces written in `notation`, e.g. as
+/// figurines or in another language's letters. Only English letters can be read back in.
+pub fn export_pgn_with_notation(
+    game: &Game,
+    notation: PieceNotation,
+) -> Result<String, anyhow::Error> {
+    notation.validate(game.game_type())?;
+
+    let sans = game
+        .positions_before_moves()
+        .zip(game.moves())
+        .map(|(position, game_move)| game_move.mv.to_san_with_notation(position, notation))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sans: Vec<&str> = sans.iter().map(String::as_str).collect();
+
+    Ok(write_pgn(game, &sans))
+}
+
+/// Writes a game in PGN with its moves written as `sans`.
+fn write_pgn(game: &Game, sans: &[&str]) -> String {
+    let result = game.result();
+    let start = game
+        .positions_before_moves()
+        .next()
+        .unwrap_or(game.position());
+
+    let mut tags = vec![
+        ("Event", "Casual game".to_string()),
+        ("Variant", game.game_type().rules().name().to_string()),
+    ];
+
+    if *start != Position::start_position() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", start.to_fen()));
+    }
+
+    if let Some(clock) = game.clock() {
+        let time_control = clock.time_control;
+        if time_control.is_symmetric() {
+            tags.push(("TimeControl", pgn_clock(time_control.white)));
+        } else {
+            tags.push(("WhiteTimeControl", pgn_clock(time_control.white)));
+            tags.push(("BlackTimeControl", pgn_clock(time_control.black)));
+        }
+    }
+
+    tags.push(("Result", result.token().to_string()));
+
+    let mut pgn = String::new();
+    for (name, value) in tags {
+        let _ = writeln!(pgn, "[{} \"{}\"]", name, value.replace('"', "\\\""));
+    }
+    pgn.push('\n');
+
+    let mut movetext = Vec::new();
+    let mut move_number = start.fullmove_number.max(1);
+
+    for (i, (game_move, san)) in game.moves().iter().zip(sans).enumerate() {
+        match game_move.color {
+            Color::White => movetext.push(format!("{}.", move_number)),
+            Color::Black if i == 0 => movetext.push(format!("{}...", move_number)),
+            Color::Black => {}
+        }
+
+        movetext.push(san.to_string());
+
+        if let Some(elapsed_ms) = game_move.elapsed_ms {
+            movetext.push(format!("{{[%emt {}]}}", format_duration(elapsed_ms)));
+        }
+
+        if game_move.color == Color::Black {
+            move_number += 1;
+        }
+    }
+
+    movetext.push(result.token().to_string());
+    pgn.push_str(&movetext.join(" "));
+    pgn.push('\n');
+
+    pgn
+}
+
+/// A clock in PGN's `TimeControl` form: seconds, then the increment in seconds, e.g. "300+3".
+fn pgn_clock(clock: Clock) -> String {
+    format!(
+        "{}+{}",
+        clock.initial_ms as f64 / 1000.0,
+        clock.increment_ms as f64 / 1000.0
+    )
+}
+
+fn parse_pgn_clock(value: &str) -> Result<Clock, anyhow::Error> {
+    let (initial, increment) = value.split_once('+').unwrap_or((value, "0"));
+
+    let seconds = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| *seconds >= 0.0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid time control: {}", value))
+    };
+
+    Ok(Clock::new(
+        (seconds(initial)? * 1000.0) as u64,
+        (seconds(increment)? * 1000.0) as u64,
+    ))
+}
+
+/// A duration as "h:mm:ss", with milliseconds if there are any, e.g. "0:01:05.250".
+fn format_duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    let duration = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+
+    match ms % 1000 {
+        0 => duration,
+        millis => format!("{}.{:03}", duration, millis),
+    }
+}
+
+fn parse_duration_ms(duration: &str) -> Option<u64> {
+    let mut seconds = 0.0;
+    for part in duration.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+
+    Some((seconds * 1000.0).round() as u64)
+}
+
+/// Parses a move in SAN as written in a PGN file, including castling and annotation marks like
+/// "!?". The position is from the perspective of its side to move, and the notation uses the
+/// real board's squares.
+pub fn parse_san(
+    position: &Position,
+    notation: &str,
+    game_type: GameType,
+) -> Result<PieceMove, anyhow::Error> {
+    let notation = notation.trim_end_matches(['!', '?', '+', '#']);
+
+    let castle_file = match notation {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None,
+    };
+
+    if let Some(file) = castle_file {
+        let mut moves = MoveList::new();
+        position.get_all_legal_moves(game_type, &mut moves)?;
+
+        return moves
+            .into_iter()
+            .find(|mv| {
+                let to = match position.true_active_color {
+                    Color::White => mv.to,
+                    Color::Black => mv.to.invert(),
+                };
+                matches!(mv.move_type, MoveType::Castle { .. }) && to.get_col() == file
+            })
+            .ok_or_else(|| anyhow::anyhow!("Castling {} is not legal", notation));
+    }
+
+    match position.true_active_color {
+        Color::White => PieceMove::from_algebraic_with_options(
+            position,
+            notation,
+            game_type,
+            ParserOptions::lenient(),
+        ),
+        Color::Black => PieceMove::from_algebraic_inverted_with_options(
+            position,
+            notation,
+            game_type,
+            ParserOptions::lenient(),
+        ),
+    }
+}
+
+/// Writes a legal move in SAN, the reverse of `parse_san`. The position is from the perspective
+/// of its side to move, and the notation uses the real board's squares. See
+/// `PieceMove::to_san`, which this checks the move is legal in `game_type` for.
+pub fn to_san(
+    position: &Position,
+    mv: PieceMove,
+    game_type: GameType,
+) -> Result<String, anyhow::Error> {
+    let mut legal_moves = MoveList::new();
+    position.get_all_legal_moves(game_type, &mut legal_moves)?;
+
+    if !legal_moves.contains(&mv) {
+        return Err(anyhow::anyhow!("{} is not a legal move", mv));
+    }
+
+    mv.to_san(position)
+}
+
+/// Reads and plays through every game in a PGN file's contents, e.g. a lichess export.
+pub fn import_pgn(text: &str, game_type: GameType) -> Result<Vec<Game>, anyhow::Error> {
+    parse_pgn(text)?
+        .iter()
+        .enumerate()
+        .map(|(i, game)| {
+            game.to_game(game_type)
+                .map_err(|e| anyhow::anyhow!("Game {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Reads every game in a PGN file's contents.
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, anyhow::Error> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut in_movetext = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                // A tag after movetext starts a new game, even if the last one had no result
+                if in_movetext {
+                    games.push(std::mem::take(&mut game));
+                    in_movetext = false;
+                }
+
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                game.tags.push(parse_tag(&tag)?);
+            }
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                add_comment(&mut game, &comment);
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                add_comment(&mut game, &comment);
+            }
+            '%' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some('{') => {
+                            chars.by_ref().find(|&c| c == '}');
+                        }
+                        Some(_) => {}
+                        None => return Err(anyhow::anyhow!("Unterminated variation")),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();[".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+
+                in_movetext = true;
+
+                if let Some(result) = PgnResult::from_token(&token) {
+                    game.result = result;
+                    games.push(std::mem::take(&mut game));
+                    in_movetext = false;
+                } else if let Some(mv) = movetext_move(&token) {
+                    game.moves.push(mv.to_string());
+                }
+            }
+        }
+    }
+
+    if !game.tags.is_empty() || !game.moves.is_empty() {
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+fn add_comment(game: &mut PgnGame, comment: &str) {
+    let comment = comment.trim();
+
+    if !comment.is_empty() {
+        game.comments.push((game.moves.len(), comment.to_string()));
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), anyhow::Error> {
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow::anyhow!("Invalid tag: [{}]", tag))?;
+
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| anyhow::anyhow!("Invalid tag value: [{}]", tag))?;
+
+    Ok((name.to_string(), value.replace("\\\"", "\"")))
+}
+
+/// The move in a movetext token, skipping move numbers ("12." or "12...") and NAGs ("$1").
+fn movetext_move(token: &str) -> Option<&str> {
+    if token.starts_with('$') {
+        return None;
+    }
+
+    let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let token = if digits > 0 && token[digits..].starts_with('.') {
+        token[digits..].trim_start_matches('.')
+    } else {
+        token
+    };
+
+    if token.is_empty() {
+        None
+    } else {
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{position::GameStatus, Pos};
+
+    const GAME: &str = r#"[Event "Casual game"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 {A comment} b5?! 5. Bxb5 Nf6 6. Nf3 Qh6
+(6... Qh5 (6... Qg4) 7. d3) 7. d3 $1 Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 1-0
+
+[Event "Another game"]
+[Result "1/2-1/2"]
+
+1.e4 c5 2.Nf3 d6 3.d4 cxd4 4.Nxd4 Nf6 5.Nc3 a6 6.Be2 e5 7.Nb3 Be7 8.O-O O-O 1/2-1/2
+"#;
+
+    #[test]
+    fn test_parse_pgn() {
+        let games = parse_pgn(GAME).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].tag("White"), Some("Anderssen, Adolf"));
+        assert_eq!(games[0].result, PgnResult::WhiteWins);
+        assert_eq!(games[0].moves.len(), 20);
+        assert_eq!(games[0].moves[7], "b5?!");
+        assert_eq!(games[0].moves[12], "d3");
+
+        assert_eq!(games[0].comments, [(7, "A comment".to_string())]);
+
+        assert_eq!(games[1].result, PgnResult::Draw);
+        assert_eq!(games[1].moves.len(), 16);
+        assert_eq!(games[1].moves[0], "e4");
+    }
+
+    #[test]
+    fn test_import_pgn() {
+        let games = import_pgn(GAME, GameType::Classic).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].moves().len(), 20);
+        assert_eq!(games[0].moves()[5].san, "Qh4+");
+        assert_eq!(games[0].status(), GameStatus::Resignation(Color::White));
+        assert_eq!(games[0].result(), PgnResult::WhiteWins);
+
+        assert_eq!(games[1].status(), GameStatus::DrawByAgreement);
+
+        let error = import_pgn("1. e4 e5 2. Ke3 *", GameType::Classic).unwrap_err();
+        assert!(error.to_string().contains("Ke3 at ply 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_import_rescue_pgn() {
+        let pgn = r#"[Event "Rescue game"]
+
+1. e2Sf2 { [%clk 0:03:00] } d5 { [%clk 0:02:59] } 2. Kf2Sf1 *
+"#;
+
+        let games = import_pgn(pgn, GameType::Rescue).unwrap();
+        let game = &games[0];
+
+        let sans: Vec<&str> = game.moves().iter().map(|mv| mv.san.as_str()).collect();
+        assert_eq!(sans, ["e2Sf2", "d5", "Kf2Sf1"]);
+        assert_eq!(game.result(), PgnResult::Unknown);
+        assert_eq!(
+            game.position(),
+            &Position::from_moves(&["e2Sf2", "d5", "Kf2Sf1"], GameType::Rescue).unwrap()
+        );
+
+        let pgn_game = &parse_pgn(pgn).unwrap()[0];
+        assert_eq!(pgn_game.comments[1], (2, "[%clk 0:02:59]".to_string()));
+    }
+
+    #[test]
+    fn test_replay() {
+        let games = parse_pgn(GAME).unwrap();
+
+        for game in &games {
+            let mut plies = 0;
+            game.replay(GameType::Classic, |_, _| plies += 1).unwrap();
+            assert_eq!(plies, game.moves.len());
+        }
+
+        // Both sides castled kingside
+        let position = games[1].replay(GameType::Classic, |_, _| {}).unwrap();
+        assert_eq!(position.true_active_color, Color::White);
+        assert!(position
+            .get_piece_at(Pos::from_algebraic("g1").unwrap())
+            .is_some());
+        assert!(position
+            .get_piece_at(Pos::from_algebraic("f8").unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn test_replay_reports_illegal_move() {
+        let games = parse_pgn("1. e4 e5 2. Ke3 *").unwrap();
+        assert_eq!(games[0].result, PgnResult::Unknown);
+
+        let error = games[0].replay(GameType::Classic, |_, _| {}).unwrap_err();
+        assert!(error.to_string().contains("Ke3 at ply 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_to_san() {
+        let san = |fen: &str, notation: &str| {
+            let position = Position::parse_from_fen(fen).unwrap();
+            let mv = parse_san(&position, notation, GameType::Classic).unwrap();
+            to_san(&position, mv, GameType::Classic).unwrap()
+        };
+
+        let position = "r3k2r/8/8/3p4/4P3/8/8/RN2K2R w KQkq - 0 1";
+        assert_eq!(san(position, "exd5"), "exd5");
+        assert_eq!(san(position, "Nc3"), "Nc3");
+        assert_eq!(san(position, "O-O"), "O-O");
+
+        // Both rooks can reach d1, but only one can reach a2
+        let position = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(san(position, "Rad1"), "Rad1");
+        assert_eq!(san(position, "Ra2"), "Ra2");
+
+        // Knights on the same file are told apart by rank
+        let position = "4k3/8/8/8/8/1N6/8/1N2K3 w - - 0 1";
+        assert_eq!(san(position, "N3d2"), "N3d2");
+
+        // Check and mate, from black's side of the board
+        let position = "6k1/5ppp/8/8/8/1r6/r7/6K1 b - - 0 1";
+        assert_eq!(san(position, "Rb1"), "Rb1#");
+        assert_eq!(san(position, "Rbb2"), "Rbb2");
+        assert_eq!(san(position, "Raa3"), "Raa3");
+        assert_eq!(san(position, "Rg3"), "Rg3+");
+    }
+
+    #[test]
+    fn test_to_san_round_trip() {
+        let games = parse_pgn(GAME).unwrap();
+
+        for game in &games {
+            let mut written = vec![];
+            game.replay(GameType::Classic, |position, mv| {
+                written.push(to_san(position, mv, GameType::Classic).unwrap());
+            })
+            .unwrap();
+
+            let read: Vec<&str> = game
+                .moves
+                .iter()
+                .map(|mv| mv.trim_end_matches(['!', '?']))
+                .collect();
+            assert_eq!(written, read);
+        }
+    }
+
+    #[test]
+    fn test_invalid_tag() {
+        assert!(parse_pgn("[Event]\n1. e4 *").is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut game = Game::new(GameType::Classic);
+        for mv in ["e4", "e5", "Nf3"] {
+            game.make_san_move(mv).unwrap();
+        }
+        game.resign(Color::Black).unwrap();
+
+        let pgn = export_pgn(&game);
+        assert!(pgn.contains("[Variant \"chess\"]"));
+        assert!(pgn.ends_with("1. e4 e5 2. Nf3 1-0\n"), "{}", pgn);
+
+        let read = import_pgn(&pgn, GameType::Classic).unwrap().remove(0);
+        assert_eq!(read.position(), game.position());
+        assert_eq!(read.status(), game.status());
+    }
+
+    #[test]
+    fn test_export_with_notation() {
+        let mut game = Game::new(GameType::Classic);
+        for mv in ["e4", "e5", "Nf3", "Nc6", "Bb5"] {
+            game.make_san_move(mv).unwrap();
+        }
+
+        let pgn = export_pgn_with_notation(&game, PieceNotation::Figurine).unwrap();
+        assert!(pgn.contains("1. e4 e5 2. ♘f3 ♘c6 3. ♗b5 *"), "{}", pgn);
+
+        let pgn = export_pgn_with_notation(&game, "german".parse().unwrap()).unwrap();
+        assert!(pgn.contains("1. e4 e5 2. Sf3 Sc6 3. Lb5 *"), "{}", pgn);
+
+        // German's knight and queen letters are Rescue's rescue and drop markers
+        let game = Game::new(GameType::Rescue);
+        assert!(export_pgn_with_notation(&game, "german".parse().unwrap()).is_err());
+        assert!(export_pgn_with_notation(&game, PieceNotation::Figurine).is_ok());
+    }
+
+    #[test]
+    fn test_export_clocks_and_start_position() {
+        let time_control = TimeControl::new(Clock::new(60_000, 1_000), Clock::new(300_000, 0));
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12", GameType::Rescue)
+            .unwrap()
+            .with_clock(time_control);
+
+        for (mv, elapsed_ms) in [("Kd7", 2_500), ("e4", 41_000), ("Kc6", 3_000)] {
+            let mv = parse_san(game.position(), mv, GameType::Rescue).unwrap();
+            game.make_timed_move(mv, elapsed_ms).unwrap();
+        }
+
+        let pgn = export_pgn(&game);
+        assert!(
+            pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]"),
+            "{}",
+            pgn
+        );
+        assert!(pgn.contains("[WhiteTimeControl \"60+1\"]"), "{}", pgn);
+        assert!(pgn.contains("12... Kd7 {[%emt 0:00:02.500]} 13. e4 {[%emt 0:00:41]}"));
+
+        let read = import_pgn(&pgn, GameType::Rescue).unwrap().remove(0);
+        assert_eq!(read.position(), game.position());
+        assert_eq!(read.clock(), game.clock());
+        assert_eq!(read.moves(), game.moves());
+    }
+}
@@ -1,10 +1,17 @@
-use crate::{evaluation::square_bonus::SquareBonus, piece_move::CanMove, Bitboard, Pos, Position};
+use crate::{
+    evaluation::square_bonus::{square_bonus, SquareBonus},
+    piece_move::{CanMove, GameType},
+    Bitboard, Pos, Position,
+};
 
 pub mod bishop;
 pub mod king;
 pub mod knight;
+pub mod magic;
 pub mod occupancy;
 pub mod pawn;
+#[cfg(all(feature = "bmi2", target_arch = "x86_64"))]
+pub mod pext;
 pub mod queen;
 pub mod rescue_drop;
 pub mod rook;
@@ -45,6 +52,16 @@ impl std::fmt::Display for PieceType {
 }
 
 impl PieceType {
+    /// Every type of piece, from least to most valuable.
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
     pub fn can_hold(&self, other: PieceType) -> bool {
         match self {
             // Right now pieces can rescue pieces of the same type, but maybe
@@ -59,7 +76,7 @@ impl PieceType {
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Serialize)]
+#[derive(Debug, PartialEq, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
 pub enum Color {
     White,
     Black,
@@ -137,14 +154,13 @@ impl Piece {
         }
     }
 
-    pub fn square_bonus(&self) -> i32 {
-        match self.piece_type {
-            PieceType::Pawn => Pawn::square_bonus(self.position),
-            PieceType::Knight => Knight::square_bonus(self.position),
-            PieceType::Bishop => Bishop::square_bonus(self.position),
-            PieceType::Rook => Rook::square_bonus(self.position),
-            PieceType::Queen => Queen::square_bonus(self.position),
-            PieceType::King => King::square_bonus(self.position),
+    /// The bonus for the piece's square, including the bonus for the piece it holds, if any.
+    pub fn square_bonus(&self, game_type: GameType) -> i32 {
+        let bonus = type_square_bonus(self.piece_type, self.position, game_type);
+
+        match self.holding {
+            Some(held) => bonus + held_square_bonus(held, self.position, game_type),
+            None => bonus,
         }
     }
 
@@ -161,6 +177,25 @@ impl Piece {
     }
 }
 
+fn type_square_bonus(piece_type: PieceType, pos: Pos, game_type: GameType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => square_bonus::<Pawn>(pos, game_type),
+        PieceType::Knight => square_bonus::<Knight>(pos, game_type),
+        PieceType::Bishop => square_bonus::<Bishop>(pos, game_type),
+        PieceType::Rook => square_bonus::<Rook>(pos, game_type),
+        PieceType::Queen => square_bonus::<Queen>(pos, game_type),
+        PieceType::King => square_bonus::<King>(pos, game_type),
+    }
+}
+
+/// The bonus for a piece held by a piece on `pos`. A held piece can only come back into play
+/// by being dropped next to its carrier, so it gets half the bonus it would have on the
+/// carrier's square: a queen carried deep into enemy territory is worth more than one carried
+/// along the back rank.
+pub fn held_square_bonus(held: PieceType, pos: Pos, game_type: GameType) -> i32 {
+    type_square_bonus(held, pos, game_type) / 2
+}
+
 impl std::fmt::Display for Piece {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.piece_type.to_algebraic(self.color))
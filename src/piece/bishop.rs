@@ -1,8 +1,6 @@
 pub mod magic;
 pub mod occupancy;
 
-use std::sync::LazyLock;
-
 use crate::{
     bitboard::Bitboard, evaluation::square_bonus::SquareBonus, piece::Piece, piece_move::CanMove,
     Pos, Position,
@@ -31,10 +29,11 @@ impl ChessPiece for Bishop {
     }
 }
 
-static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = Pos(i as u8);
 
@@ -63,10 +62,11 @@ static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 #[inline(always)]
 pub fn attack_map(pos: Pos) -> &'static Bitboard {
@@ -109,6 +109,7 @@ mod tests {
     use crate::{
         bitboard::Bitboard,
         piece::{Piece, PieceType},
+        piece_move::GameType,
         pos::Pos,
         Position,
     };
@@ -256,9 +257,9 @@ mod tests {
     #[test]
     fn square_bonus() {
         let bishop = Piece::new_white(PieceType::Bishop, (3, 3).into());
-        assert_eq!(bishop.square_bonus(), 10);
+        assert_eq!(bishop.square_bonus(GameType::Classic), 10);
 
         let bishop = Piece::new_white(PieceType::Bishop, Pos::from_algebraic("c5").unwrap());
-        assert_eq!(bishop.square_bonus(), 10);
+        assert_eq!(bishop.square_bonus(GameType::Classic), 10);
     }
 }
@@ -1,20 +1,9 @@
 use crate::bitboard::Bitboard;
-use crate::piece::occupancy::generate_occupancy_patterns;
+use crate::piece::magic::{magic_table_size, MagicTable, BISHOP_DIRECTIONS};
 use crate::pos::Pos;
-use std::sync::LazyLock;
-
-use super::occupancy::{generate_bishop_move_table, generate_bishop_occupancy_mask};
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct MagicEntry {
-    magic: u64,
-    shift: u32,
-    mask: Bitboard,
-    moves: Vec<Bitboard>,
-}
 
 // The magic numbers you found, stored as (magic_number, shift) pairs
-static BISHOP_MAGICS: [(u64, u32); 64] = [
+const BISHOP_MAGICS: [(u64, u32); 64] = [
     (0x8090408840100, 58),
     (0x20010101110009, 59),
     (0x41020091001200, 59),
@@ -81,44 +70,20 @@ static BISHOP_MAGICS: [(u64, u32); 64] = [
     (0x4080801012200, 58),
 ];
 
-static MAGIC_TABLE: LazyLock<[MagicEntry; 64]> = LazyLock::new(|| {
-    let mut entries = Vec::with_capacity(64);
+const TABLE_SIZE: usize = magic_table_size(&BISHOP_MAGICS);
 
-    for sq in 0..64 {
-        let pos = Pos(sq);
-        let (magic, shift) = BISHOP_MAGICS[sq as usize];
-        let mask = generate_bishop_occupancy_mask(pos);
-        let patterns = generate_occupancy_patterns(mask);
-        let move_table = generate_bishop_move_table(pos);
-
-        // Initialize lookup table
-        let table_size = 1 << (64 - shift);
-        let mut moves = vec![Bitboard::new(); table_size];
-
-        // Fill lookup table
-        for (i, &pattern) in patterns.iter().enumerate() {
-            let index = ((pattern.0.wrapping_mul(magic)) >> shift) as usize;
-            moves[index] = move_table[i];
-        }
+static MAGIC_TABLE: MagicTable<TABLE_SIZE> = MagicTable::new(&BISHOP_MAGICS, &BISHOP_DIRECTIONS);
 
-        entries.push(MagicEntry {
-            magic,
-            shift,
-            mask,
-            moves,
-        });
-    }
-
-    entries.try_into().unwrap()
-});
-
-/// Gets bishop moves using magic lookup
+/// Gets bishop moves using magic lookup, or a `PEXT` lookup when built with the `bmi2`
+/// feature and running on a CPU that supports it
 #[inline]
 pub fn get_bishop_moves_magic(pos: Pos, occupied: Bitboard) -> Bitboard {
-    let entry = &MAGIC_TABLE[pos.0 as usize];
-    let relevant = occupied & entry.mask;
-    let index = ((relevant.0.wrapping_mul(entry.magic)) >> entry.shift) as usize;
-    entry.moves[index]
+    #[cfg(all(feature = "bmi2", target_arch = "x86_64"))]
+    if crate::piece::pext::available() {
+        return crate::piece::pext::get_bishop_moves_pext(pos, occupied);
+    }
+
+    MAGIC_TABLE.get(pos, occupied)
 }
 
 #[cfg(test)]
@@ -127,7 +92,8 @@ mod tests {
 
     use rand::Rng;
 
-    use crate::piece::bishop::occupancy::generate_bishop_moves;
+    use crate::piece::bishop::occupancy::{generate_bishop_moves, generate_bishop_occupancy_mask};
+    use crate::piece::occupancy::generate_occupancy_patterns;
 
     use super::*;
 
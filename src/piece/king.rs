@@ -1,11 +1,9 @@
-use std::sync::LazyLock;
-
 use crate::{
-    bitboard::Bitboard, evaluation::square_bonus::SquareBonus, piece_move::CanMove, pos, Pos,
-    Position,
+    bitboard::Bitboard, evaluation::square_bonus::SquareBonus, piece_move::CanMove,
+    position::CastlingSide, Pos, Position,
 };
 
-use super::{pawn, ChessPiece, Color, Piece, PieceType, RescueChessPiece};
+use super::{ChessPiece, Color, Piece, RescueChessPiece};
 
 pub struct King;
 
@@ -15,10 +13,11 @@ impl RescueChessPiece for King {
     }
 }
 
-static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let pos = Pos(i as u8);
 
@@ -55,10 +54,11 @@ static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i as usize] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 #[inline(always)]
 pub fn attack_map(pos: Pos) -> &'static Bitboard {
@@ -81,6 +81,11 @@ impl SquareBonus for King {
     fn square_bonus(pos: crate::Pos) -> i32 {
         KING_MIDDLEGAME_TABLE[pos.0 as usize]
     }
+
+    // The king can't be carried off, so its shelter matters as much as in Classic
+    fn rescue_square_bonus(pos: crate::Pos) -> i32 {
+        KING_MIDDLEGAME_TABLE[pos.0 as usize]
+    }
 }
 
 impl ChessPiece for King {
@@ -93,85 +98,14 @@ impl ChessPiece for King {
     }
 }
 
-static WHITE_QUEEN_SIDE: LazyLock<Bitboard> = LazyLock::new(|| {
-    let mut board = Bitboard::new();
-    board.set(pos::B1);
-    board.set(pos::C1);
-    board.set(pos::D1);
-    board
-});
-
-static WHITE_KING_SIDE: LazyLock<Bitboard> = LazyLock::new(|| {
-    let mut board = Bitboard::new();
-    board.set(pos::F1);
-    board.set(pos::G1);
-    board
-});
-
-static BLACK_QUEEN_SIDE: LazyLock<Bitboard> = LazyLock::new(|| {
-    let mut board = Bitboard::new();
-    board.set(pos::E1);
-    board.set(pos::F1);
-    board.set(pos::G1);
-    board
-});
-
-static BLACK_KING_SIDE: LazyLock<Bitboard> = LazyLock::new(|| {
-    let mut board = Bitboard::new();
-    board.set(pos::B1);
-    board.set(pos::C1);
-    board
-});
-
 impl CanMove for King {
     fn get_legal_moves(piece: &Piece, position: &Position, exclude_white: bool) -> Bitboard {
         let pos = piece.position;
 
         let mut board = ATTACK_MAPS[pos.0 as usize] & !position.white_map;
-        let all = position.all_map;
-
-        let maps = position.get_piece_maps();
-
-        if position.true_active_color == Color::White {
-            if position.castling_rights.white_queen_side {
-                if !all.intersects(*WHITE_QUEEN_SIDE)
-                    && maps.white_rooks.get(pos::A1)
-                    && !King::is_white_king_in_check(&position)
-                {
-                    board.set(pos::C1);
-                }
-            }
-
-            if position.castling_rights.white_king_side {
-                if !all.intersects(*WHITE_KING_SIDE)
-                    && maps.white_rooks.get(pos::H1)
-                    && !King::is_white_king_in_check(&position)
-                {
-                    board.set(pos::G1);
-                }
-            }
-        } else {
-            // Black, but piece.color is white and we're on the first row, so we invert
-            // the queen side and king side castling squares because the chess board is mirrored,
-            // not rotationally symmetrical
-            if position.castling_rights.black_queen_side {
-                // Black queen side is e1 + f1 + g1
-                if !all.intersects(*BLACK_QUEEN_SIDE)
-                    && maps.white_rooks.get(pos::H1)
-                    && !King::is_white_king_in_check(&position)
-                {
-                    board.set(pos::F1);
-                }
-            }
-
-            if position.castling_rights.black_king_side {
-                // Black king side is b1 + c1
-                if !all.intersects(*BLACK_KING_SIDE)
-                    && maps.white_rooks.get(pos::A1)
-                    && !King::is_white_king_in_check(&position)
-                {
-                    board.set(pos::B1);
-                }
+        for side in CastlingSide::ALL {
+            if position.can_castle(side) {
+                board.set(position.castling(side).king_to);
             }
         }
 
@@ -185,60 +119,9 @@ impl CanMove for King {
 
 impl King {
     pub fn is_white_king_in_check(position: &Position) -> bool {
-        let king = position.white_king;
-        let maps = position.get_piece_maps();
-
-        match king {
-            Some(king) => {
-                // Pawns
-                if maps.black_pawns.intersects(*pawn::attack_map(king)) {
-                    return true;
-                }
-                // Use magic bitboards to get sliding piece attacks
-                let blockers = position.all_map;
-
-                // Get all possible rook attacks from king's position
-                let rook_attacks = super::rook::magic::get_rook_moves_magic(king, blockers);
-                let rook_attackers = rook_attacks & position.black_map;
-
-                // Check if any of those squares have a rook or queen
-                if rook_attackers.into_iter().any(|pos| {
-                    position.is_piece_at(pos, &[PieceType::Rook, PieceType::Queen], Color::Black)
-                }) {
-                    return true;
-                }
-
-                // Get all possible bishop attacks from king's position
-                let bishop_attacks = super::bishop::magic::get_bishop_moves_magic(king, blockers);
-                let bishop_attackers = bishop_attacks & position.black_map;
-
-                // Check if any of those squares have a bishop or queen
-                if bishop_attackers.into_iter().any(|pos| {
-                    position.is_piece_at(pos, &[PieceType::Bishop, PieceType::Queen], Color::Black)
-                }) {
-                    return true;
-                }
-
-                let knight_attackers = *super::knight::attack_map(king) & position.black_map;
-                if knight_attackers
-                    .into_iter()
-                    .any(|pos| position.is_piece_at(pos, &[PieceType::Knight], Color::Black))
-                {
-                    return true;
-                }
-
-                let king_attackers = *attack_map(king) & position.black_map;
-                if king_attackers
-                    .into_iter()
-                    .any(|pos| position.is_piece_at(pos, &[PieceType::King], Color::Black))
-                {
-                    return true;
-                }
-
-                false
-            }
-            None => false,
-        }
+        position
+            .white_king
+            .is_some_and(|king| position.is_attacked_by(king, Color::Black))
     }
 }
 
@@ -1,5 +1,3 @@
-use std::sync::LazyLock;
-
 use crate::{
     bitboard::Bitboard, evaluation::square_bonus::SquareBonus, piece::Piece, piece_move::CanMove,
     Pos, Position,
@@ -28,10 +26,11 @@ impl ChessPiece for Knight {
     }
 }
 
-static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = crate::Pos(i as u8);
 
@@ -76,10 +75,11 @@ static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 #[inline(always)]
 pub fn attack_map(pos: Pos) -> &'static Bitboard {
@@ -104,12 +104,13 @@ impl SquareBonus for Knight {
     }
 }
 
-static KNIGHT_MOVE_TABLE: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static KNIGHT_MOVE_TABLE: [Bitboard; 64] = {
     let mut boards = [Bitboard::new(); 64];
 
-    for pos in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
-        let pos = Pos(pos as u8);
+        let pos = Pos(i as u8);
 
         // Up left
         if let Some(pos) = pos.moved(-1, -2) {
@@ -151,11 +152,12 @@ static KNIGHT_MOVE_TABLE: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
             board.set(pos);
         }
 
-        boards[pos.0 as usize] = board;
+        boards[i] = board;
+        i += 1;
     }
 
     boards
-});
+};
 
 impl CanMove for Knight {
     #[inline]
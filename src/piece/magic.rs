@@ -0,0 +1,144 @@
+//! Magic bitboard lookup tables for the sliding pieces, built at compile time.
+
+use crate::{bitboard::Bitboard, pos::Pos};
+
+pub const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+pub const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MagicEntry {
+    magic: u64,
+    shift: u32,
+    mask: Bitboard,
+
+    /// Where the square's moves start in `MagicTable::moves`.
+    offset: usize,
+}
+
+/// The moves of a sliding piece from every square for every arrangement of blockers, indexed
+/// by magic numbers. The moves for all squares share one array of `N` entries, which
+/// `magic_table_size` gives for a set of magic numbers.
+pub struct MagicTable<const N: usize> {
+    entries: [MagicEntry; 64],
+    moves: [Bitboard; N],
+}
+
+/// The number of moves entries needed by a table of (magic number, shift) pairs.
+pub const fn magic_table_size(magics: &[(u64, u32); 64]) -> usize {
+    let mut size = 0;
+    let mut sq = 0;
+
+    while sq < 64 {
+        size += 1 << (64 - magics[sq].1);
+        sq += 1;
+    }
+
+    size
+}
+
+impl<const N: usize> MagicTable<N> {
+    /// Builds the table for a piece that slides in `directions`, for use in a `static`.
+    pub const fn new(magics: &[(u64, u32); 64], directions: &[(i8, i8)]) -> Self {
+        let mut entries = [MagicEntry {
+            magic: 0,
+            shift: 0,
+            mask: Bitboard::new(),
+            offset: 0,
+        }; 64];
+        let mut moves = [Bitboard::new(); N];
+        let mut offset = 0;
+
+        let mut sq = 0;
+        while sq < 64 {
+            let (magic, shift) = magics[sq];
+            let mask = Bitboard(sliding_moves(sq, 0, directions, true));
+
+            // Every arrangement of blockers on the mask's squares, by counting up through the
+            // subsets of the mask
+            let mut occupied: u64 = 0;
+            loop {
+                let index = (occupied.wrapping_mul(magic) >> shift) as usize;
+                moves[offset + index] = Bitboard(sliding_moves(sq, occupied, directions, false));
+
+                occupied = occupied.wrapping_sub(mask.0) & mask.0;
+                if occupied == 0 {
+                    break;
+                }
+            }
+
+            entries[sq] = MagicEntry {
+                magic,
+                shift,
+                mask,
+                offset,
+            };
+            offset += 1 << (64 - shift);
+            sq += 1;
+        }
+
+        assert!(
+            offset == N,
+            "Magic table size doesn't match its magic numbers"
+        );
+
+        Self { entries, moves }
+    }
+
+    /// The squares the piece on `pos` can move to, up to and including the first blocker in
+    /// each direction.
+    #[inline]
+    pub fn get(&self, pos: Pos, occupied: Bitboard) -> Bitboard {
+        let entry = &self.entries[pos.0 as usize];
+        let relevant = occupied & entry.mask;
+        let index = (relevant.0.wrapping_mul(entry.magic) >> entry.shift) as usize;
+        self.moves[entry.offset + index]
+    }
+}
+
+/// The squares a piece on `sq` sliding in `directions` can reach, stopping at the first
+/// occupied square in each direction.
+///
+/// With `blockers_only`, gives the squares that can block the piece instead: the last square
+/// in each direction is left out, since whatever is on it, the piece can still reach it.
+///
+/// Works on plain coordinates rather than `Pos`, which keeps building the tables quick enough
+/// to do at compile time.
+pub(super) const fn sliding_moves(
+    sq: usize,
+    occupied: u64,
+    directions: &[(i8, i8)],
+    blockers_only: bool,
+) -> u64 {
+    let mut moves = 0;
+
+    let mut d = 0;
+    while d < directions.len() {
+        let (dx, dy) = directions[d];
+        let mut x = (sq % 8) as i8 + dx;
+        let mut y = (sq / 8) as i8 + dy;
+
+        while on_board(x, y) {
+            if blockers_only && !on_board(x + dx, y + dy) {
+                break;
+            }
+
+            let bit = 1 << (x as u64 + y as u64 * 8);
+            moves |= bit;
+
+            if occupied & bit != 0 {
+                break;
+            }
+
+            x += dx;
+            y += dy;
+        }
+
+        d += 1;
+    }
+
+    moves
+}
+
+const fn on_board(x: i8, y: i8) -> bool {
+    x >= 0 && x < 8 && y >= 0 && y < 8
+}
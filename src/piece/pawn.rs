@@ -1,5 +1,3 @@
-use std::sync::LazyLock;
-
 use crate::{
     bitboard::Bitboard, evaluation::square_bonus::SquareBonus, piece::Piece, piece_move::CanMove,
     Pos, Position,
@@ -28,10 +26,11 @@ impl ChessPiece for Pawn {
     }
 }
 
-static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = crate::Pos(i as u8);
 
@@ -46,15 +45,17 @@ static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i as usize] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
-static ATTACK_MAPS_BLACK: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS_BLACK: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = crate::Pos(i as u8);
 
@@ -69,10 +70,11 @@ static ATTACK_MAPS_BLACK: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i as usize] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 #[inline(always)]
 pub fn attack_map(pos: Pos) -> &'static Bitboard {
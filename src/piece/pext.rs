@@ -0,0 +1,166 @@
+//! Sliding piece lookups indexed with the BMI2 `PEXT` instruction instead of magic numbers.
+//! Only built with the `bmi2` feature on x86-64.
+//!
+//! `PEXT` packs the blockers on a square's mask into a dense index, so the tables need no
+//! magic numbers and no multiply, but on CPUs that implement it in microcode (AMD before Zen 3)
+//! it's far slower than a magic lookup, which is why it has to be asked for. Even then, the
+//! CPU is checked when the program runs, and the magic tables are used if it has no BMI2.
+
+use std::arch::x86_64::_pext_u64;
+
+use crate::{bitboard::Bitboard, pos::Pos};
+
+use super::magic::{sliding_moves, BISHOP_DIRECTIONS, ROOK_DIRECTIONS};
+
+/// The moves of a sliding piece from every square for every arrangement of blockers, indexed
+/// by the blockers packed together with `PEXT`. The moves for all squares share one array of
+/// `N` entries, which `pext_table_size` gives for a set of directions.
+pub struct PextTable<const N: usize> {
+    masks: [Bitboard; 64],
+
+    /// Where each square's moves start in `moves`.
+    offsets: [usize; 64],
+    moves: [Bitboard; N],
+}
+
+/// The number of moves entries needed by a table for a piece sliding in `directions`.
+pub const fn pext_table_size(directions: &[(i8, i8)]) -> usize {
+    let mut size = 0;
+    let mut sq = 0;
+
+    while sq < 64 {
+        size += 1 << sliding_moves(sq, 0, directions, true).count_ones();
+        sq += 1;
+    }
+
+    size
+}
+
+impl<const N: usize> PextTable<N> {
+    /// Builds the table for a piece that slides in `directions`, for use in a `static`.
+    pub const fn new(directions: &[(i8, i8)]) -> Self {
+        let mut masks = [Bitboard::new(); 64];
+        let mut offsets = [0; 64];
+        let mut moves = [Bitboard::new(); N];
+        let mut offset = 0;
+
+        let mut sq = 0;
+        while sq < 64 {
+            let mask = sliding_moves(sq, 0, directions, true);
+
+            // Counting up through the subsets of the mask visits them in the order `PEXT`
+            // numbers them, so the nth subset's moves go in the nth entry
+            let mut occupied: u64 = 0;
+            let mut index = 0;
+            loop {
+                moves[offset + index] = Bitboard(sliding_moves(sq, occupied, directions, false));
+                index += 1;
+
+                occupied = occupied.wrapping_sub(mask) & mask;
+                if occupied == 0 {
+                    break;
+                }
+            }
+
+            masks[sq] = Bitboard(mask);
+            offsets[sq] = offset;
+            offset += index;
+            sq += 1;
+        }
+
+        assert!(offset == N, "PEXT table size doesn't match its directions");
+
+        Self {
+            masks,
+            offsets,
+            moves,
+        }
+    }
+
+    /// The squares the piece on `pos` can move to, up to and including the first blocker in
+    /// each direction.
+    ///
+    /// # Safety
+    ///
+    /// The CPU has to support BMI2, see `available`.
+    #[inline]
+    #[target_feature(enable = "bmi2")]
+    pub unsafe fn get(&self, pos: Pos, occupied: Bitboard) -> Bitboard {
+        let sq = pos.0 as usize;
+        let index = _pext_u64(occupied.0, self.masks[sq].0) as usize;
+        self.moves[self.offsets[sq] + index]
+    }
+}
+
+const ROOK_TABLE_SIZE: usize = pext_table_size(&ROOK_DIRECTIONS);
+const BISHOP_TABLE_SIZE: usize = pext_table_size(&BISHOP_DIRECTIONS);
+
+// Filling in the table takes longer than the compiler expects of a constant
+#[allow(long_running_const_eval)]
+static ROOK_TABLE: PextTable<ROOK_TABLE_SIZE> = PextTable::new(&ROOK_DIRECTIONS);
+static BISHOP_TABLE: PextTable<BISHOP_TABLE_SIZE> = PextTable::new(&BISHOP_DIRECTIONS);
+
+/// Whether the CPU running the program supports BMI2. Detected once and cached, and known at
+/// compile time when building for a CPU that has it.
+#[inline(always)]
+pub fn available() -> bool {
+    std::is_x86_feature_detected!("bmi2")
+}
+
+/// Gets rook moves using a `PEXT` lookup. Only call when `available` is true.
+#[inline]
+pub fn get_rook_moves_pext(pos: Pos, occupied: Bitboard) -> Bitboard {
+    debug_assert!(available());
+
+    // Safety: callers check that the CPU supports BMI2
+    unsafe { ROOK_TABLE.get(pos, occupied) }
+}
+
+/// Gets bishop moves using a `PEXT` lookup. Only call when `available` is true.
+#[inline]
+pub fn get_bishop_moves_pext(pos: Pos, occupied: Bitboard) -> Bitboard {
+    debug_assert!(available());
+
+    // Safety: callers check that the CPU supports BMI2
+    unsafe { BISHOP_TABLE.get(pos, occupied) }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::piece::{
+        bishop::occupancy::generate_bishop_moves, rook::occupancy::generate_rook_moves,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_pext_matches_slow() {
+        if !available() {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..10_000 {
+            let pos = Pos(rng.gen_range(0..64));
+            let occupied = Bitboard(rng.gen::<u64>() & rng.gen::<u64>());
+
+            assert_eq!(
+                get_rook_moves_pext(pos, occupied),
+                generate_rook_moves(pos, occupied),
+                "Rook on {} with blockers\n{}",
+                pos,
+                occupied
+            );
+            assert_eq!(
+                get_bishop_moves_pext(pos, occupied),
+                generate_bishop_moves(pos, occupied),
+                "Bishop on {} with blockers\n{}",
+                pos,
+                occupied
+            );
+        }
+    }
+}
@@ -1,8 +1,6 @@
 pub mod magic;
 pub mod occupancy;
 
-use std::sync::LazyLock;
-
 use crate::evaluation::square_bonus::SquareBonus;
 use crate::piece_move::CanMove;
 use crate::{bitboard::Bitboard, piece::Piece};
@@ -32,10 +30,11 @@ impl ChessPiece for Queen {
     }
 }
 
-static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static ATTACK_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = crate::Pos(i as u8);
 
@@ -88,10 +87,11 @@ static ATTACK_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 #[inline(always)]
 pub fn attack_map(pos: Pos) -> &'static Bitboard {
@@ -1,11 +1,10 @@
-use std::sync::LazyLock;
-
 use crate::{Bitboard, Pos};
 
-static RESCUE_DROP_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
+static RESCUE_DROP_MAPS: [Bitboard; 64] = {
     let mut maps = [Bitboard::new(); 64];
 
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let mut board = Bitboard::new();
         let start_pos = Pos(i as u8);
 
@@ -34,10 +33,11 @@ static RESCUE_DROP_MAPS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| {
         }
 
         maps[i] = board;
+        i += 1;
     }
 
     maps
-});
+};
 
 pub fn rescue_drop_map(pos: Pos) -> &'static Bitboard {
     &RESCUE_DROP_MAPS[pos.0 as usize]
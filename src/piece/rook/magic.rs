@@ -1,20 +1,9 @@
 use crate::bitboard::Bitboard;
-use crate::piece::occupancy::generate_occupancy_patterns;
+use crate::piece::magic::{magic_table_size, MagicTable, ROOK_DIRECTIONS};
 use crate::pos::Pos;
-use std::sync::LazyLock;
-
-use super::occupancy::{generate_rook_move_table, generate_rook_occupancy_mask};
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct MagicEntry {
-    magic: u64,
-    shift: u32,
-    mask: Bitboard,
-    moves: Vec<Bitboard>,
-}
 
 // Pre-computed magic numbers for rooks. Each entry is (magic_number, shift)
-static ROOK_MAGICS: [(u64, u32); 64] = [
+const ROOK_MAGICS: [(u64, u32); 64] = [
     (0x8000400a102081, 52),
     (0x80210014408080, 52),
     (0x2040082000431000, 52),
@@ -81,50 +70,29 @@ static ROOK_MAGICS: [(u64, u32); 64] = [
     (0x1000030420083, 52),
 ];
 
-static MAGIC_TABLE: LazyLock<[MagicEntry; 64]> = LazyLock::new(|| {
-    let mut entries = Vec::with_capacity(64);
+const TABLE_SIZE: usize = magic_table_size(&ROOK_MAGICS);
 
-    for sq in 0..64 {
-        let pos = Pos(sq);
-        let (magic, shift) = ROOK_MAGICS[sq as usize];
-        let mask = generate_rook_occupancy_mask(pos);
-        let patterns = generate_occupancy_patterns(mask);
-        let move_table = generate_rook_move_table(pos);
-
-        // Initialize lookup table
-        let table_size = 1 << (64 - shift);
-        let mut moves = vec![Bitboard::new(); table_size];
-
-        // Fill lookup table
-        for (i, &pattern) in patterns.iter().enumerate() {
-            let index = ((pattern.0.wrapping_mul(magic)) >> shift) as usize;
-            moves[index] = move_table[i];
-        }
+// Filling in the table takes longer than the compiler expects of a constant
+#[allow(long_running_const_eval)]
+static MAGIC_TABLE: MagicTable<TABLE_SIZE> = MagicTable::new(&ROOK_MAGICS, &ROOK_DIRECTIONS);
 
-        entries.push(MagicEntry {
-            magic,
-            shift,
-            mask,
-            moves,
-        });
-    }
-
-    entries.try_into().unwrap()
-});
-
-/// Gets rook moves using magic lookup
+/// Gets rook moves using magic lookup, or a `PEXT` lookup when built with the `bmi2`
+/// feature and running on a CPU that supports it
 #[inline]
 pub fn get_rook_moves_magic(pos: Pos, occupied: Bitboard) -> Bitboard {
-    let entry = &MAGIC_TABLE[pos.0 as usize];
-    let relevant = occupied & entry.mask;
-    let index = ((relevant.0.wrapping_mul(entry.magic)) >> entry.shift) as usize;
-    entry.moves[index]
+    #[cfg(all(feature = "bmi2", target_arch = "x86_64"))]
+    if crate::piece::pext::available() {
+        return crate::piece::pext::get_rook_moves_pext(pos, occupied);
+    }
+
+    MAGIC_TABLE.get(pos, occupied)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::piece::rook::occupancy::generate_rook_moves;
+    use crate::piece::occupancy::generate_occupancy_patterns;
+    use crate::piece::rook::occupancy::{generate_rook_moves, generate_rook_occupancy_mask};
     use rand::Rng;
     use std::time::Instant;
 
@@ -1,10 +1,27 @@
+use arrayvec::ArrayVec;
 use parser::RescueOrDrop;
 use serde::{Deserialize, Serialize};
 
-use crate::{pos, Bitboard, Color, Piece, PieceType, Pos, Position};
+use crate::{
+    position::{Castling, CastlingSide},
+    Bitboard, Color, Piece, PieceType, Pos, Position,
+};
 
+mod notation;
 mod parser;
 
+pub use notation::{PieceLetters, PieceNotation};
+pub use parser::ParserOptions;
+
+/// The most moves a list can hold. Rescues and drops multiply the moves a piece has by the
+/// squares around its destination, so Rescue positions can have several times as many moves
+/// as Classic ones.
+pub const MAX_MOVES: usize = 1024;
+
+/// A list of moves kept on the stack, for generating moves without allocating at every node of
+/// a search.
+pub type MoveList = ArrayVec<PieceMove, MAX_MOVES>;
+
 pub trait CanMove {
     fn get_legal_moves(piece: &Piece, position: &Position, exclude_white: bool) -> Bitboard;
 }
@@ -96,6 +113,10 @@ impl PieceMove {
         }
     }
 
+    pub fn is_castle(&self) -> bool {
+        matches!(self.move_type, MoveType::Castle { .. })
+    }
+
     pub fn is_rescue_or_drop(&self) -> bool {
         match self.move_type {
             MoveType::Normal {
@@ -135,8 +156,7 @@ impl PieceMove {
         notation: &str,
         game_type: GameType,
     ) -> Result<PieceMove, anyhow::Error> {
-        let parsed = parser::PieceMoveParser::parse(notation)?;
-        Self::from_algebraic_impl(position, parsed, game_type)
+        Self::from_algebraic_with_options(position, notation, game_type, ParserOptions::default())
     }
 
     pub fn from_algebraic_inverted(
@@ -144,9 +164,57 @@ impl PieceMove {
         notation_inverted: &str,
         game_type: GameType,
     ) -> Result<PieceMove, anyhow::Error> {
-        let mut parsed = parser::PieceMoveParser::parse(notation_inverted)?;
-        parsed.invert();
-        Self::from_algebraic_impl(position, parsed, game_type)
+        Self::from_algebraic_inverted_with_options(
+            position,
+            notation_inverted,
+            game_type,
+            ParserOptions::default(),
+        )
+    }
+
+    /// Parses a move in algebraic notation using the given parser options.
+    ///
+    /// In lenient mode a leading lowercase 'b' is first read as a b-file pawn move, and
+    /// if no such pawn move exists it is retried as a bishop move.
+    pub fn from_algebraic_with_options(
+        position: &Position,
+        notation: &str,
+        game_type: GameType,
+        options: ParserOptions,
+    ) -> Result<PieceMove, anyhow::Error> {
+        Self::from_algebraic_lenient_bishop(notation, options, |notation| {
+            let parsed = parser::PieceMoveParser::parse_with_options(notation, options)?;
+            Self::from_algebraic_impl(position, parsed, game_type)
+        })
+    }
+
+    pub fn from_algebraic_inverted_with_options(
+        position: &Position,
+        notation_inverted: &str,
+        game_type: GameType,
+        options: ParserOptions,
+    ) -> Result<PieceMove, anyhow::Error> {
+        Self::from_algebraic_lenient_bishop(notation_inverted, options, |notation| {
+            let mut parsed = parser::PieceMoveParser::parse_with_options(notation, options)?;
+            parsed.invert();
+            Self::from_algebraic_impl(position, parsed, game_type)
+        })
+    }
+
+    fn from_algebraic_lenient_bishop(
+        notation: &str,
+        options: ParserOptions,
+        from_notation: impl Fn(&str) -> Result<PieceMove, anyhow::Error>,
+    ) -> Result<PieceMove, anyhow::Error> {
+        match from_notation(notation) {
+            Ok(mv) => Ok(mv),
+            Err(e) => match notation.trim_start().strip_prefix('b') {
+                Some(rest) if options.lenient => {
+                    from_notation(&format!("B{}", rest)).map_err(|_| e)
+                }
+                _ => Err(e),
+            },
+        }
     }
 
     pub fn from_uci(
@@ -173,10 +241,12 @@ impl PieceMove {
         game_type: GameType,
     ) -> Result<PieceMove, anyhow::Error> {
         // Get all legal moves for pieces of this type
-        let legal_moves = position.get_all_legal_moves(game_type)?;
+        let mut legal_moves = MoveList::new();
+        position.get_all_legal_moves(game_type, &mut legal_moves)?;
 
         let mut matching_moves: Vec<PieceMove> = legal_moves
-            .into_iter()
+            .iter()
+            .copied()
             .filter(|mv| {
                 // Helper function to check if move matches rescue/drop pattern
                 let rescue_drop_matches = match (&parsed.rescue_drop, &mv.move_type) {
@@ -233,32 +303,68 @@ impl PieceMove {
                     _ => false,
                 };
 
+                // Promotions only need to match when the notation specifies them
+                let promotion_matches = match (parsed.promotion_to, &mv.move_type) {
+                    (None, _) => true,
+                    (
+                        Some(promotion_to),
+                        MoveType::Normal {
+                            promoted_to: Some(promoted_to),
+                            ..
+                        },
+                    ) => promotion_to == *promoted_to,
+                    _ => false,
+                };
+
+                let dropped_promotion_matches = match (parsed.dropped_promotion_to, &mv.move_type) {
+                    (None, _) => true,
+                    (
+                        Some(promotion_to),
+                        MoveType::Normal {
+                            dropped_promoted_to: Some(promoted_to),
+                            ..
+                        },
+                    ) => promotion_to == *promoted_to,
+                    _ => false,
+                };
+
                 // Full move matching logic
+                let to = Pos::xy(parsed.to_file, parsed.to_rank);
+
+                // Chess960 castling is written as the king taking its own rook
+                let castles_with_rook =
+                    matches!(mv.move_type, MoveType::Castle { rook, .. } if rook == to);
+
                 let res = mv.piece_type == parsed.piece_type &&  // Match piece type
-                        mv.to == Pos::xy(parsed.to_file, parsed.to_rank) &&  // Match destination square
+                        (mv.to == to || castles_with_rook) &&  // Match destination square
                         (!parsed.is_capture || mv.is_capture()) &&  // Match capture flag if specified
                         parsed.from_file.map_or(true, |file| mv.from.get_col() == file) &&  // Match source file if specified
                         parsed.from_rank.map_or(true, |rank| mv.from.get_row() == rank) &&  // Match source rank if specified
-                        rescue_drop_matches; // Match rescue/drop pattern if specified
+                        rescue_drop_matches && // Match rescue/drop pattern if specified
+                        promotion_matches && // Match promotion piece if specified
+                        dropped_promotion_matches; // Match dropped promotion piece if specified
 
                 res
             })
             .collect();
 
-        // If no moves match, the notation is invalid
+        // If no moves match, the notation is invalid. The error's message is kept to one line,
+        // with the possible moves and the board in its cause for `{:?}` and `{:#}`
         if matching_moves.is_empty() {
             return Err(anyhow::anyhow!(
-                "No piece can make this move: {}. Possible moves are: {}. Board state\n{}\n{}",
-                parsed.to_algebraic(),
-                position
-                    .get_all_legal_moves(game_type)?
+                "Possible moves are: {}. Board state\n{}\n{}",
+                legal_moves
                     .iter()
                     .map(|mv| mv.to_string())
                     .collect::<Vec<String>>()
                     .join(", "),
                 position.to_board_string_with_rank_file_holding(),
                 position.to_fen()
-            ));
+            )
+            .context(format!(
+                "No piece can make this move: {}",
+                parsed.to_algebraic()
+            )));
         }
 
         // If exactly one move matches, that's our move
@@ -283,61 +389,175 @@ impl PieceMove {
         Ok(matching_moves.remove(0))
     }
 
+    /// Formats the move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`. Rescues and drops
+    /// are written after the move with `S` or `D` and the square, as `ParsedMove::from_uci`
+    /// reads them, e.g. `e2e4Sf2`, `e4e4Dd5` or `b7b7Db8q` for a dropped pawn that promotes.
     pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", self.from.to_algebraic(), self.to.to_algebraic());
+
+        if let MoveType::Normal {
+            promoted_to,
+            rescued_pos,
+            dropped_pos,
+            dropped_promoted_to,
+            ..
+        } = self.move_type
+        {
+            if let Some(promoted_to) = promoted_to {
+                uci.push_str(promoted_to.to_algebraic(Color::Black));
+            }
+
+            if let Some(rescued_pos) = rescued_pos {
+                uci.push('S');
+                uci.push_str(&rescued_pos.to_algebraic());
+            }
+
+            if let Some(dropped_pos) = dropped_pos {
+                uci.push('D');
+                uci.push_str(&dropped_pos.to_algebraic());
+
+                if let Some(dropped_promoted_to) = dropped_promoted_to {
+                    uci.push_str(dropped_promoted_to.to_algebraic(Color::Black));
+                }
+            }
+        }
+
+        uci
+    }
+
+    /// Formats the move in UCI notation as it's written in Chess960, where castling is the king
+    /// taking its own rook, e.g. `e1h1` rather than `e1g1`.
+    pub fn to_uci_chess960(&self) -> String {
         match self.move_type {
-            MoveType::Normal { promoted_to, .. } => {
-                if let Some(promoted_to) = promoted_to {
-                    format!(
-                        "{}{}{}",
-                        self.from.to_algebraic(),
-                        self.to.to_algebraic(),
-                        promoted_to.to_algebraic(Color::White),
-                    )
+            MoveType::Castle { king, rook } => {
+                format!("{}{}", king.to_algebraic(), rook.to_algebraic())
+            }
+            MoveType::Normal { .. } => self.to_uci(),
+        }
+    }
+
+    /// Formats the move in algebraic notation, writing pieces with the given notation, e.g.
+    /// figurines (`♘f3`) or another language's piece letters (`Sf3`).
+    pub fn to_notation(&self, notation: PieceNotation) -> String {
+        let mut result = String::new();
+        self.write_notation(&mut result, notation, "").unwrap();
+        result
+    }
+
+    /// Formats the move in standard algebraic notation as it's played in `position`, as seen
+    /// from white's side of the board: disambiguated from the side's other moves to the same
+    /// square, marked `+` or `#` for check or mate, and with any rescue or drop written after
+    /// the destination, e.g. `Nbd2`, `exd5Sd4+` or `Kb7Db8N`.
+    pub fn to_san(&self, position: &Position) -> Result<String, anyhow::Error> {
+        self.to_san_with_notation(position, PieceNotation::default())
+    }
+
+    /// Formats the move in standard algebraic notation like `to_san`, writing pieces with the
+    /// given notation.
+    pub fn to_san_with_notation(
+        &self,
+        position: &Position,
+        notation: PieceNotation,
+    ) -> Result<String, anyhow::Error> {
+        // Rescue's moves include all of classic chess's, and the extra ones can't get out of
+        // check in a classic game since no piece is ever held there
+        let mut legal_moves = MoveList::new();
+        position.get_all_legal_moves(GameType::Rescue, &mut legal_moves)?;
+
+        let board_move = match (self.move_type, position.true_active_color) {
+            // Castling is written the same way from either side
+            (MoveType::Castle { .. }, _) | (_, Color::White) => *self,
+            (_, Color::Black) => self.inverted(),
+        };
+
+        let from = board_move.from.to_algebraic();
+        let (file, rank) = from.split_at(1);
+
+        let disambiguation = match self.move_type {
+            MoveType::Castle { .. } => "",
+
+            // Pawn captures are written with the file they're made from
+            MoveType::Normal { .. } if self.piece_type == PieceType::Pawn => {
+                if self.is_capture() {
+                    file
+                } else {
+                    ""
+                }
+            }
+
+            MoveType::Normal { .. } => {
+                let others = legal_moves
+                    .iter()
+                    .filter(|other| {
+                        other.piece_type == self.piece_type
+                            && other.to == self.to
+                            && other.from != self.from
+                    })
+                    .collect::<Vec<_>>();
+
+                if others.is_empty() {
+                    ""
+                } else if others
+                    .iter()
+                    .all(|other| other.from.get_col() != self.from.get_col())
+                {
+                    file
+                } else if others
+                    .iter()
+                    .all(|other| other.from.get_row() != self.from.get_row())
+                {
+                    rank
                 } else {
-                    format!("{}{}", self.from.to_algebraic(), self.to.to_algebraic())
+                    &from
                 }
             }
-            MoveType::Castle { .. } => {
-                format!("{}{}", self.from.to_algebraic(), self.to.to_algebraic())
+        };
+
+        let mut san = String::new();
+        board_move
+            .write_notation(&mut san, notation, disambiguation)
+            .unwrap();
+
+        let mut after = position.clone();
+        after.apply_move(*self)?;
+        after.invert();
+
+        if after.is_king_in_check()? {
+            let mut replies = MoveList::new();
+            after.get_all_legal_moves(GameType::Rescue, &mut replies)?;
+
+            if replies.is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
             }
         }
+
+        Ok(san)
     }
-}
 
-/// Displays the move in algebraic notation.
-impl std::fmt::Display for PieceMove {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Writes the move in algebraic notation, with `disambiguation` between the piece and the
+    /// rest of the move.
+    fn write_notation(
+        &self,
+        f: &mut impl std::fmt::Write,
+        notation: PieceNotation,
+        disambiguation: &str,
+    ) -> std::fmt::Result {
         if let MoveType::Castle { .. } = self.move_type {
-            if self.from == pos::D1 && self.to == pos::B1 {
-                write!(f, "O-O")?;
-            } else if self.from == pos::D1 && self.to == pos::F1 {
-                write!(f, "O-O-O")?;
-            } else if self.from == pos::E1 && self.to == pos::C1 {
-                write!(f, "O-O-O")?;
-            } else if self.from == pos::E1 && self.to == pos::G1 {
-                write!(f, "O-O")?;
-            } else {
-                panic!(
-                    "Invalid rook position for castling: {} to {}",
-                    self.from, self.to
-                );
+            match Castling::side_of(self.to) {
+                CastlingSide::KingSide => write!(f, "O-O")?,
+                CastlingSide::QueenSide => write!(f, "O-O-O")?,
             }
 
             return Ok(());
         }
 
-        write!(
-            f,
-            "{}",
-            match self.piece_type {
-                PieceType::Pawn => "",
-                PieceType::Knight => "N",
-                PieceType::Bishop => "B",
-                PieceType::Rook => "R",
-                PieceType::Queen => "Q",
-                PieceType::King => "K",
-            }
-        )?;
+        if let Some(symbol) = notation.symbol(self.piece_type) {
+            write!(f, "{}", symbol)?;
+        }
+
+        write!(f, "{}", disambiguation)?;
 
         if let MoveType::Normal {
             captured: Some(_), ..
@@ -353,7 +573,7 @@ impl std::fmt::Display for PieceMove {
             ..
         } = &self.move_type
         {
-            write!(f, "={}", promoted_to.to_algebraic(Color::White))?;
+            write!(f, "={}", promotion_symbol(notation, *promoted_to))?;
         }
 
         if let MoveType::Normal {
@@ -372,7 +592,7 @@ impl std::fmt::Display for PieceMove {
         {
             write!(f, "D{}", dropped_pos.to_algebraic())?;
             if let Some(dropped_promoted_to) = dropped_promoted_to {
-                write!(f, "{}", dropped_promoted_to.to_algebraic(Color::White))?;
+                write!(f, "{}", promotion_symbol(notation, *dropped_promoted_to))?;
             }
         }
 
@@ -380,6 +600,17 @@ impl std::fmt::Display for PieceMove {
     }
 }
 
+fn promotion_symbol(notation: PieceNotation, piece_type: PieceType) -> char {
+    notation.symbol(piece_type).unwrap_or('P')
+}
+
+/// Displays the move in algebraic notation.
+impl std::fmt::Display for PieceMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_notation(f, PieceNotation::default(), "")
+    }
+}
+
 impl std::fmt::Debug for PieceMove {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self)
@@ -388,6 +619,8 @@ impl std::fmt::Debug for PieceMove {
 
 #[cfg(test)]
 mod tests {
+    use crate::pos;
+
     use super::*;
 
     #[test]
@@ -485,6 +718,77 @@ mod tests {
     //     assert!(matches!(mv.move_type, MoveType::EnPassant(_)));
     // }
 
+    #[test]
+    fn test_uci_round_trip() {
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "4k3/3nxp4/8/8/3NxB4/2P5/8/4K3 w - - 0 1",
+            // A held pawn dropped on the last rank promotes
+            "4k3/1KxP6/8/8/8/8/8/8 w - - 0 1",
+            "8/4P2k/8/8/8/8/8/4K3 w - - 0 1",
+        ];
+
+        for fen in positions {
+            let position = Position::parse_from_fen(fen).unwrap();
+            let mut moves = MoveList::new();
+            position
+                .get_all_legal_moves(GameType::Rescue, &mut moves)
+                .unwrap();
+
+            for mv in moves {
+                let uci = mv.to_uci();
+                assert_eq!(
+                    PieceMove::from_uci(&position, &uci, GameType::Rescue).unwrap(),
+                    mv,
+                    "{} in {}",
+                    uci,
+                    fen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rescue_and_drop_uci() {
+        let position = Position::parse_from_fen("4k3/1KxP6/8/8/8/8/8/8 w - - 0 1").unwrap();
+        let mv = PieceMove::from_uci(&position, "b7b7Db8q", GameType::Rescue).unwrap();
+        assert_eq!(mv.from, mv.to);
+        assert!(matches!(
+            mv.move_type,
+            MoveType::Normal {
+                dropped_promoted_to: Some(PieceType::Queen),
+                ..
+            }
+        ));
+        assert_eq!(mv.to_uci(), "b7b7Db8q");
+
+        let position = Position::start_position();
+        let mv = PieceMove::from_algebraic(&position, "e4Sf2", GameType::Rescue);
+        assert!(mv.is_err());
+        let mv = PieceMove::from_algebraic(&position, "Nf3Sf2", GameType::Rescue).unwrap();
+        assert_eq!(mv.to_uci(), "g1f3Sf2");
+    }
+
+    #[test]
+    fn test_chess960_castling_uci() {
+        let position =
+            Position::parse_from_fen("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1")
+                .unwrap();
+
+        let mv = PieceMove::from_uci(&position, "f1g1", GameType::Rescue).unwrap();
+        assert!(mv.is_castle());
+        assert_eq!(mv.to_uci(), "f1g1");
+        assert_eq!(mv.to_uci_chess960(), "f1g1");
+        assert_eq!(mv.to_string(), "O-O");
+
+        // The king taking its own rook is castling queen side
+        let mv = PieceMove::from_uci(&position, "f1b1", GameType::Rescue).unwrap();
+        assert!(mv.is_castle());
+        assert_eq!(mv.to, pos::C1);
+        assert_eq!(mv.to_uci_chess960(), "f1b1");
+        assert_eq!(mv.to_string(), "O-O-O");
+    }
+
     #[test]
     fn test_invalid_move() {
         let position =
@@ -685,6 +989,129 @@ mod tests {
         assert!(PieceMove::from_algebraic(&position, "e4Sa4", GameType::Rescue).is_err());
     }
 
+    #[test]
+    fn test_promotion_moves() {
+        let position = Position::parse_from_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+
+        let mv = PieceMove::from_algebraic(&position, "e8=Q", GameType::Classic).unwrap();
+        assert_eq!(mv.from, Pos::from_algebraic("e7").unwrap());
+        assert_eq!(mv.to, Pos::from_algebraic("e8").unwrap());
+        assert!(matches!(
+            mv.move_type,
+            MoveType::Normal {
+                promoted_to: Some(PieceType::Queen),
+                ..
+            }
+        ));
+
+        let mv = PieceMove::from_algebraic(&position, "exd8=N+", GameType::Classic).unwrap();
+        assert_eq!(mv.to, Pos::from_algebraic("d8").unwrap());
+        assert!(mv.is_capture());
+        assert!(matches!(
+            mv.move_type,
+            MoveType::Normal {
+                promoted_to: Some(PieceType::Knight),
+                ..
+            }
+        ));
+
+        // Bare trailing piece letter
+        let mv = PieceMove::from_algebraic(&position, "e8R", GameType::Classic).unwrap();
+        assert!(matches!(
+            mv.move_type,
+            MoveType::Normal {
+                promoted_to: Some(PieceType::Rook),
+                ..
+            }
+        ));
+
+        // Promotions round-trip through their algebraic notation
+        let mv = PieceMove::from_algebraic(&position, "e8=B", GameType::Classic).unwrap();
+        assert_eq!(mv.to_string(), "e8=B");
+        assert_eq!(
+            PieceMove::from_algebraic(&position, &mv.to_string(), GameType::Classic).unwrap(),
+            mv
+        );
+    }
+
+    #[test]
+    fn test_drop_promotion_moves() {
+        let position = Position::parse_from_fen("7k/KxP7/8/8/8/8/8/8 w - - 0 1").unwrap();
+
+        let mv = PieceMove::from_algebraic(&position, "Kb7Db8Q", GameType::Rescue).unwrap();
+        assert_eq!(mv.piece_type, PieceType::King);
+        assert_eq!(mv.to, Pos::from_algebraic("b7").unwrap());
+        assert!(matches!(
+            mv.move_type,
+            MoveType::Normal {
+                dropped_pos: Some(pos),
+                dropped_promoted_to: Some(PieceType::Queen),
+                ..
+            } if pos == Pos::from_algebraic("b8").unwrap()
+        ));
+
+        let mv = PieceMove::from_algebraic(&position, "Kb7Db8=N", GameType::Rescue).unwrap();
+        assert_eq!(mv.to_string(), "Kb7Db8N");
+        assert_eq!(
+            PieceMove::from_algebraic(&position, &mv.to_string(), GameType::Rescue).unwrap(),
+            mv
+        );
+    }
+
+    #[test]
+    fn test_lenient_moves() {
+        let position = Position::parse_from_fen(
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+        )
+        .unwrap();
+        let lenient = ParserOptions::lenient();
+
+        let mv =
+            PieceMove::from_algebraic_with_options(&position, "Nb1-c3", GameType::Classic, lenient)
+                .unwrap();
+        assert_eq!(mv.from, Pos::from_algebraic("b1").unwrap());
+        assert_eq!(mv.to, Pos::from_algebraic("c3").unwrap());
+
+        let mv =
+            PieceMove::from_algebraic_with_options(&position, "e4xd5", GameType::Classic, lenient)
+                .unwrap();
+        assert!(mv.is_capture());
+
+        // Lowercase b that can only be a bishop move
+        let mv =
+            PieceMove::from_algebraic_with_options(&position, "bb5+", GameType::Classic, lenient)
+                .unwrap();
+        assert_eq!(mv.piece_type, PieceType::Bishop);
+        assert_eq!(mv.from, Pos::from_algebraic("f1").unwrap());
+        assert_eq!(mv.to, Pos::from_algebraic("b5").unwrap());
+
+        // Lowercase b that is a pawn move
+        let mv =
+            PieceMove::from_algebraic_with_options(&position, "b3", GameType::Classic, lenient)
+                .unwrap();
+        assert_eq!(mv.piece_type, PieceType::Pawn);
+
+        assert!(PieceMove::from_algebraic(&position, "bb5+", GameType::Classic).is_err());
+    }
+
+    #[test]
+    fn test_lenient_en_passant() {
+        let position = Position::parse_from_fen(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        )
+        .unwrap();
+
+        let mv = PieceMove::from_algebraic_with_options(
+            &position,
+            "exf6 e.p.",
+            GameType::Classic,
+            ParserOptions::lenient(),
+        )
+        .unwrap();
+        assert_eq!(mv.to, Pos::from_algebraic("f6").unwrap());
+        assert!(mv.is_capture());
+    }
+
     #[test]
     fn test_move_and_rescue_moves() {
         let position = Position::start_position();
@@ -697,4 +1124,91 @@ mod tests {
             matches!(mv.move_type, MoveType::Normal{ rescued_pos: Some(pos), .. } if pos == Pos::from_algebraic("f2").unwrap())
         );
     }
+
+    #[test]
+    fn test_figurine_and_localized_notation() {
+        let position = Position::start_position();
+        let mv = PieceMove::from_algebraic(&position, "Nf3", GameType::Classic).unwrap();
+
+        assert_eq!(mv.to_notation(PieceNotation::default()), "Nf3");
+        assert_eq!(mv.to_notation(PieceNotation::Figurine), "♘f3");
+        assert_eq!(
+            mv.to_notation(PieceNotation::Letters(PieceLetters::GERMAN)),
+            "Sf3"
+        );
+        assert_eq!(mv.to_notation("fr".parse().unwrap()), "Cf3");
+
+        let pawn = PieceMove::from_algebraic(&position, "e4", GameType::Classic).unwrap();
+        assert_eq!(pawn.to_notation(PieceNotation::Figurine), "e4");
+
+        let position = Position::parse_from_fen("7k/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let promotion = PieceMove::from_algebraic(&position, "e8=Q", GameType::Classic).unwrap();
+        assert_eq!(promotion.to_notation(PieceNotation::Figurine), "e8=♕");
+        assert_eq!(promotion.to_notation("german".parse().unwrap()), "e8=D");
+    }
+
+    #[test]
+    fn test_notation_rescue_markers() {
+        let german = PieceNotation::Letters(PieceLetters::GERMAN);
+        assert!(german.validate(GameType::Classic).is_ok());
+        assert!(german.validate(GameType::Rescue).is_err());
+        assert!("fr"
+            .parse::<PieceNotation>()
+            .unwrap()
+            .validate(GameType::Rescue)
+            .is_err());
+
+        assert!(PieceNotation::default().validate(GameType::Rescue).is_ok());
+        assert!(PieceNotation::Figurine.validate(GameType::Rescue).is_ok());
+    }
+
+    #[test]
+    fn test_to_san() {
+        let position = Position::parse_from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 1",
+        )
+        .unwrap();
+
+        let mv = PieceMove::from_algebraic(&position, "Nce2", GameType::Classic).unwrap();
+        assert_eq!(mv.to_san(&position).unwrap(), "Nce2");
+        assert_eq!(
+            mv.to_san_with_notation(&position, PieceNotation::Figurine)
+                .unwrap(),
+            "♘ce2"
+        );
+
+        // Display leaves out the disambiguation
+        assert_eq!(mv.to_string(), "Ne2");
+
+        let position = Position::start_position();
+        let mv = PieceMove::from_algebraic(&position, "Nf3Sf2", GameType::Rescue).unwrap();
+        assert_eq!(mv.to_san(&position).unwrap(), "Nf3Sf2");
+
+        let position = Position::parse_from_fen("7k/KxP7/8/8/8/8/8/8 w - - 0 1").unwrap();
+        let mv = PieceMove::from_algebraic(&position, "Kb7Db8Q", GameType::Rescue).unwrap();
+        assert_eq!(mv.to_san(&position).unwrap(), "Kb7Db8Q+");
+
+        // Black's moves are written with the board's squares
+        let position = Position::parse_from_fen("r3k2r/8/8/8/8/8/8/4K2R b kq - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+        let castle = moves
+            .into_iter()
+            .find(|mv| matches!(mv.move_type, MoveType::Castle { .. }) && mv.to == pos::B1)
+            .unwrap();
+        assert_eq!(castle.to_san(&position).unwrap(), "O-O");
+
+        let position = Position::parse_from_fen("r6r/4k3/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        let mv = crate::pgn::parse_san(&position, "Rad8", GameType::Classic).unwrap();
+        assert_eq!(mv.to_san(&position).unwrap(), "Rad8");
+    }
+
+    #[test]
+    fn test_custom_piece_letters() {
+        let notation: PieceNotation = "SLTDK".parse().unwrap();
+        assert_eq!(notation, PieceNotation::Letters(PieceLetters::GERMAN));
+        assert!("NBRQ".parse::<PieceNotation>().is_err());
+    }
 }
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{piece_move::GameType, PieceType};
+
+/// The letters rescues and drops are marked with in Rescue moves, e.g. `Kd2Sd1` or `Kd2Dd3`.
+const RESCUE_MARKERS: [char; 2] = ['S', 'D'];
+
+/// The letters used for each piece when writing moves. Pawns are never given a letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceLetters {
+    pub knight: char,
+    pub bishop: char,
+    pub rook: char,
+    pub queen: char,
+    pub king: char,
+}
+
+impl PieceLetters {
+    pub const ENGLISH: PieceLetters = PieceLetters::new('N', 'B', 'R', 'Q', 'K');
+    pub const GERMAN: PieceLetters = PieceLetters::new('S', 'L', 'T', 'D', 'K');
+    pub const FRENCH: PieceLetters = PieceLetters::new('C', 'F', 'T', 'D', 'R');
+    pub const SPANISH: PieceLetters = PieceLetters::new('C', 'A', 'T', 'D', 'R');
+    pub const ITALIAN: PieceLetters = PieceLetters::new('C', 'A', 'T', 'D', 'R');
+    pub const DUTCH: PieceLetters = PieceLetters::new('P', 'L', 'T', 'D', 'K');
+
+    pub const fn new(knight: char, bishop: char, rook: char, queen: char, king: char) -> Self {
+        Self {
+            knight,
+            bishop,
+            rook,
+            queen,
+            king,
+        }
+    }
+
+    /// Parses custom letters given in the order knight, bishop, rook, queen, king, e.g. `"SLTDK"`.
+    pub fn from_letters(letters: &str) -> Result<Self, anyhow::Error> {
+        let chars: Vec<char> = letters.chars().collect();
+
+        match chars.as_slice() {
+            &[knight, bishop, rook, queen, king] => {
+                Ok(PieceLetters::new(knight, bishop, rook, queen, king))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Expected five piece letters (knight, bishop, rook, queen, king), got {}",
+                letters
+            )),
+        }
+    }
+
+    pub fn letter(&self, piece_type: PieceType) -> Option<char> {
+        match piece_type {
+            PieceType::Pawn => None,
+            PieceType::Knight => Some(self.knight),
+            PieceType::Bishop => Some(self.bishop),
+            PieceType::Rook => Some(self.rook),
+            PieceType::Queen => Some(self.queen),
+            PieceType::King => Some(self.king),
+        }
+    }
+
+    fn letters(&self) -> [char; 5] {
+        [self.knight, self.bishop, self.rook, self.queen, self.king]
+    }
+}
+
+impl Default for PieceLetters {
+    fn default() -> Self {
+        PieceLetters::ENGLISH
+    }
+}
+
+/// How pieces are written when formatting moves with `PieceMove::to_notation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum PieceNotation {
+    /// Letters for each piece, e.g. `Nf3` in English or `Sf3` in German.
+    Letters(PieceLetters),
+
+    /// Figurine algebraic notation, e.g. `♘f3`.
+    Figurine,
+}
+
+impl Default for PieceNotation {
+    fn default() -> Self {
+        PieceNotation::Letters(PieceLetters::ENGLISH)
+    }
+}
+
+impl PieceNotation {
+    /// The symbol for a piece, or `None` for pawns.
+    pub fn symbol(&self, piece_type: PieceType) -> Option<char> {
+        match self {
+            PieceNotation::Letters(letters) => letters.letter(piece_type),
+            PieceNotation::Figurine => match piece_type {
+                PieceType::Pawn => None,
+                PieceType::Knight => Some('♘'),
+                PieceType::Bishop => Some('♗'),
+                PieceType::Rook => Some('♖'),
+                PieceType::Queen => Some('♕'),
+                PieceType::King => Some('♔'),
+            },
+        }
+    }
+
+    /// Checks moves of `game_type` can be written in the notation without being misread. Rescue
+    /// moves mark rescues and drops with `S` and `D`, so Rescue games can't be written with piece
+    /// letters that use either, like German's `S` for the knight or the `D` most languages use
+    /// for the queen.
+    pub fn validate(&self, game_type: GameType) -> Result<(), anyhow::Error> {
+        let PieceNotation::Letters(letters) = self else {
+            return Ok(());
+        };
+
+        if !game_type.rules().has_rescues() {
+            return Ok(());
+        }
+
+        match letters
+            .letters()
+            .into_iter()
+            .find(|letter| RESCUE_MARKERS.contains(letter))
+        {
+            Some(letter) => Err(anyhow::anyhow!(
+                "The piece letter {} can't be used in {} games, where it marks a rescue or drop",
+                letter,
+                game_type.rules().name()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a notation by name (`english`, `german`, `french`, `spanish`, `italian`, `dutch` or
+/// `figurine`), or five custom piece letters as accepted by `PieceLetters::from_letters`.
+impl std::str::FromStr for PieceNotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let notation = match s.to_ascii_lowercase().as_str() {
+            "english" | "en" => PieceNotation::Letters(PieceLetters::ENGLISH),
+            "german" | "de" => PieceNotation::Letters(PieceLetters::GERMAN),
+            "french" | "fr" => PieceNotation::Letters(PieceLetters::FRENCH),
+            "spanish" | "es" => PieceNotation::Letters(PieceLetters::SPANISH),
+            "italian" | "it" => PieceNotation::Letters(PieceLetters::ITALIAN),
+            "dutch" | "nl" => PieceNotation::Letters(PieceLetters::DUTCH),
+            "figurine" => PieceNotation::Figurine,
+            _ => PieceNotation::Letters(PieceLetters::from_letters(s)?),
+        };
+
+        Ok(notation)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{PieceType, Pos, Position};
+use crate::{Color, PieceType, Pos, Position};
 
 #[derive(Debug)]
 enum ParserState {
@@ -6,7 +6,10 @@ enum ParserState {
     AfterPiece,
     AfterPosition,
     AfterCapture,
+    AfterPromotionMarker,
+    AfterPromotion,
     AfterRescueOrDrop,
+    AfterDropPromotionMarker,
     Done,
 }
 
@@ -27,9 +30,8 @@ pub struct ParsedMove {
     pub rescue_drop: Option<RescueOrDrop>,
     pub rescue_drop_file: Option<u8>,
     pub rescue_drop_rank: Option<u8>,
-
-    #[allow(dead_code)]
     pub promotion_to: Option<PieceType>,
+    pub dropped_promotion_to: Option<PieceType>,
 }
 
 impl std::fmt::Display for ParsedMove {
@@ -39,47 +41,77 @@ impl std::fmt::Display for ParsedMove {
 }
 
 impl ParsedMove {
+    /// Parses a move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`.
+    ///
+    /// Rescue moves are written with the rescue or drop square after the move in the same
+    /// way as SAN, e.g. `e2e4Sf2` or `e4e4Dd5`, with an optional promotion letter after a
+    /// dropped pawn's square.
     pub fn from_uci(uci: &str, position: &Position, inverted: bool) -> Result<Self, anyhow::Error> {
-        let from_file = uci
-            .chars()
-            .nth(0)
-            .ok_or_else(|| anyhow::anyhow!("Invalid UCI"))?;
-        let from_rank = uci
-            .chars()
-            .nth(1)
-            .ok_or_else(|| anyhow::anyhow!("Invalid UCI"))?;
-        let to_file = uci
-            .chars()
-            .nth(2)
-            .ok_or_else(|| anyhow::anyhow!("Invalid UCI"))?;
-        let to_rank = uci
-            .chars()
-            .nth(3)
-            .ok_or_else(|| anyhow::anyhow!("Invalid UCI"))?;
-        let promotion = uci.chars().nth(4).map(|c| match c {
-            'q' => PieceType::Queen,
-            'r' => PieceType::Rook,
-            'b' => PieceType::Bishop,
-            'n' => PieceType::Knight,
-            _ => PieceType::Queen,
-        });
-
-        let from_pos = if inverted {
-            Pos::from_algebraic(&format!("{}{}", from_file, from_rank))
-                .unwrap()
-                .invert()
-        } else {
-            Pos::from_algebraic(&format!("{}{}", from_file, from_rank)).unwrap()
+        let uci = uci.trim();
+
+        let square = |range: std::ops::Range<usize>| -> Result<Pos, anyhow::Error> {
+            let square = uci
+                .get(range)
+                .ok_or_else(|| anyhow::anyhow!("Invalid UCI: {}", uci))?;
+            let pos = Pos::from_algebraic(square)
+                .map_err(|_| anyhow::anyhow!("Invalid square {} in UCI move {}", square, uci))?;
+
+            Ok(if inverted { pos.invert() } else { pos })
         };
 
-        let to_pos = if inverted {
-            Pos::from_algebraic(&format!("{}{}", to_file, to_rank))
-                .unwrap()
-                .invert()
-        } else {
-            Pos::from_algebraic(&format!("{}{}", to_file, to_rank)).unwrap()
+        let uci_promotion = |c: char| match c {
+            'q' => Ok(PieceType::Queen),
+            'r' => Ok(PieceType::Rook),
+            'b' => Ok(PieceType::Bishop),
+            'n' => Ok(PieceType::Knight),
+            _ => Err(anyhow::anyhow!(
+                "Invalid promotion {} in UCI move {}",
+                c,
+                uci
+            )),
         };
 
+        let from_pos = square(0..2)?;
+        let to_pos = square(2..4)?;
+
+        let mut rest = uci.get(4..).unwrap_or("");
+
+        let mut promotion = None;
+        if let Some(c) = rest.chars().next().filter(|c| c.is_ascii_lowercase()) {
+            promotion = Some(uci_promotion(c)?);
+            rest = &rest[1..];
+        }
+
+        let mut rescue_drop = None;
+        let mut rescue_drop_pos = None;
+        let mut dropped_promotion = None;
+
+        if let Some(marker) = rest.chars().next() {
+            rescue_drop = Some(match marker {
+                'S' => RescueOrDrop::Rescue,
+                'D' => RescueOrDrop::Drop,
+                _ => return Err(anyhow::anyhow!("Unexpected {} in UCI move {}", marker, uci)),
+            });
+
+            let offset = uci.len() - rest.len() + 1;
+            rescue_drop_pos = Some(square(offset..offset + 2)?);
+
+            let mut remaining = uci[offset + 2..].chars();
+            if let Some(c) = remaining.next() {
+                if rescue_drop != Some(RescueOrDrop::Drop) {
+                    return Err(anyhow::anyhow!(
+                        "Only dropped pieces can be promoted in UCI move {}",
+                        uci
+                    ));
+                }
+                dropped_promotion = Some(uci_promotion(c.to_ascii_lowercase())?);
+            }
+
+            if remaining.next().is_some() {
+                return Err(anyhow::anyhow!("Invalid UCI: {}", uci));
+            }
+        }
+
         let piece_type = position
             .get_piece_at(from_pos)
             .ok_or_else(|| anyhow::anyhow!("No piece at source square"))?
@@ -92,10 +124,11 @@ impl ParsedMove {
             to_file: to_pos.get_col(),
             to_rank: to_pos.get_row(),
             is_capture: false,
-            rescue_drop: None,
-            rescue_drop_file: None,
-            rescue_drop_rank: None,
+            rescue_drop,
+            rescue_drop_file: rescue_drop_pos.map(|pos| pos.get_col()),
+            rescue_drop_rank: rescue_drop_pos.map(|pos| pos.get_row()),
             promotion_to: promotion,
+            dropped_promotion_to: dropped_promotion,
         })
     }
 
@@ -144,6 +177,11 @@ impl ParsedMove {
         result.push((b'a' + self.to_file) as char);
         result.push((b'1' + (7 - self.to_rank)) as char);
 
+        if let Some(promotion_to) = self.promotion_to {
+            result.push('=');
+            result.push_str(promotion_to.to_algebraic(Color::White));
+        }
+
         if let Some(rescue_drop) = &self.rescue_drop {
             match rescue_drop {
                 RescueOrDrop::Rescue => result.push('S'),
@@ -155,12 +193,29 @@ impl ParsedMove {
             if let Some(rank) = self.rescue_drop_rank {
                 result.push((b'1' + rank) as char);
             }
+            if let Some(dropped_promotion_to) = self.dropped_promotion_to {
+                result.push_str(dropped_promotion_to.to_algebraic(Color::White));
+            }
         }
 
         result
     }
 }
 
+/// Options controlling how move notation is parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Tolerate notation found in real-world PGN files that isn't strict SAN, such as
+    /// "Nb1-c3", "exd6 e.p.", lowercase piece letters and stray whitespace.
+    pub lenient: bool,
+}
+
+impl ParserOptions {
+    pub fn lenient() -> Self {
+        Self { lenient: true }
+    }
+}
+
 pub struct PieceMoveParser {
     state: ParserState,
     result: ParsedMove,
@@ -184,6 +239,7 @@ impl PieceMoveParser {
                 rescue_drop_file: None,
                 rescue_drop_rank: None,
                 promotion_to: None,
+                dropped_promotion_to: None,
             },
             last_file: None,
             last_rank: None,
@@ -205,6 +261,63 @@ impl PieceMoveParser {
         Ok(7 - (rank as u8 - b'1'))
     }
 
+    fn promotion_piece(piece: char) -> Option<PieceType> {
+        match piece {
+            'Q' => Some(PieceType::Queen),
+            'R' => Some(PieceType::Rook),
+            'B' => Some(PieceType::Bishop),
+            'N' => Some(PieceType::Knight),
+            _ => None,
+        }
+    }
+
+    /// Commits the last seen square as the destination of the move.
+    fn commit_destination(&mut self, context: &str) -> Result<(), anyhow::Error> {
+        match (self.last_file, self.last_rank) {
+            (Some(file), Some(rank)) => {
+                self.result.to_file = file;
+                self.result.to_rank = rank;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("Incomplete position before {}", context)),
+        }
+    }
+
+    fn set_promotion(&mut self, piece: char) -> Result<(), anyhow::Error> {
+        let promotion_to = Self::promotion_piece(piece)
+            .ok_or_else(|| anyhow::anyhow!("Invalid promotion piece: {}", piece))?;
+
+        if self.result.piece_type != PieceType::Pawn {
+            return Err(anyhow::anyhow!("Only pawns can promote"));
+        }
+
+        // The notation may be parsed from either side's perspective, so accept both back ranks
+        if self.result.to_rank != 0 && self.result.to_rank != 7 {
+            return Err(anyhow::anyhow!(
+                "Promotion is only possible on the first or last rank"
+            ));
+        }
+
+        self.result.promotion_to = Some(promotion_to);
+        self.state = ParserState::AfterPromotion;
+
+        Ok(())
+    }
+
+    fn set_dropped_promotion(&mut self, piece: char) -> Result<(), anyhow::Error> {
+        let dropped_promotion_to = Self::promotion_piece(piece)
+            .ok_or_else(|| anyhow::anyhow!("Invalid promotion piece: {}", piece))?;
+
+        if self.result.rescue_drop != Some(RescueOrDrop::Drop) {
+            return Err(anyhow::anyhow!("Only dropped pieces can promote"));
+        }
+
+        self.result.dropped_promotion_to = Some(dropped_promotion_to);
+        self.state = ParserState::Done;
+
+        Ok(())
+    }
+
     pub fn feed_char(&mut self, c: char) -> Result<(), anyhow::Error> {
         match self.state {
             ParserState::Start => match c {
@@ -302,6 +415,15 @@ impl PieceMoveParser {
                         _ => unreachable!(),
                     }
                 }
+                '=' => {
+                    self.commit_destination("promotion")?;
+                    self.state = ParserState::AfterPromotionMarker;
+                }
+                'Q' | 'R' | 'B' | 'N' => {
+                    // Promotion written without the '=' marker, e.g. "e8Q"
+                    self.commit_destination("promotion")?;
+                    self.set_promotion(c)?;
+                }
                 '+' | '#' | '!' | '?' => {
                     if self.last_file.is_none() || self.last_rank.is_none() {
                         return Err(anyhow::anyhow!("Incomplete position before annotation"));
@@ -313,6 +435,28 @@ impl PieceMoveParser {
                 _ => return Err(anyhow::anyhow!("Unexpected character: {}", c)),
             },
 
+            ParserState::AfterPromotionMarker => self.set_promotion(c)?,
+
+            ParserState::AfterPromotion => match c {
+                'S' => {
+                    self.result.rescue_drop = Some(RescueOrDrop::Rescue);
+                    self.state = ParserState::AfterRescueOrDrop;
+                }
+                'D' => {
+                    self.result.rescue_drop = Some(RescueOrDrop::Drop);
+                    self.state = ParserState::AfterRescueOrDrop;
+                }
+                '+' | '#' | '!' | '?' => {
+                    self.state = ParserState::Done;
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unexpected character after promotion: {}",
+                        c
+                    ))
+                }
+            },
+
             ParserState::AfterCapture => match c {
                 'a'..='h' => {
                     self.last_file = Some(Self::file_to_index(c)?);
@@ -328,12 +472,21 @@ impl PieceMoveParser {
                 '1'..='8' => {
                     self.result.rescue_drop_rank = Some(Self::rank_to_index(c)?);
                 }
+                '=' => {
+                    self.state = ParserState::AfterDropPromotionMarker;
+                }
+                'Q' | 'R' | 'B' | 'N' => {
+                    // Dropped pawn promotion, e.g. "Kb7Db8Q"
+                    self.set_dropped_promotion(c)?;
+                }
                 '+' | '#' | '!' | '?' => {
                     self.state = ParserState::Done;
                 }
                 _ => return Err(anyhow::anyhow!("Unexpected character after rescue/drop")),
             },
 
+            ParserState::AfterDropPromotionMarker => self.set_dropped_promotion(c)?,
+
             ParserState::Done => match c {
                 '+' | '#' | '!' | '?' => {} // Ignore annotation symbols
                 _ => return Err(anyhow::anyhow!("Unexpected character after move: {}", c)),
@@ -355,7 +508,7 @@ impl PieceMoveParser {
 
         if !matches!(
             self.state,
-            ParserState::Done | ParserState::AfterRescueOrDrop
+            ParserState::Done | ParserState::AfterPromotion | ParserState::AfterRescueOrDrop
         ) {
             return Err(anyhow::anyhow!("Incomplete move notation"));
         }
@@ -364,10 +517,21 @@ impl PieceMoveParser {
     }
 
     pub fn parse(notation: &str) -> Result<ParsedMove, anyhow::Error> {
+        Self::parse_with_options(notation, ParserOptions::default())
+    }
+
+    pub fn parse_with_options(
+        notation: &str,
+        options: ParserOptions,
+    ) -> Result<ParsedMove, anyhow::Error> {
         let mut parser = Self::new();
 
-        // Strip any whitespace
-        let clean_notation = notation.trim();
+        let clean_notation = if options.lenient {
+            Self::normalize_lenient(notation)
+        } else {
+            // Strip any whitespace
+            notation.trim().to_string()
+        };
 
         for c in clean_notation.chars() {
             parser.feed_char(c)?;
@@ -375,6 +539,28 @@ impl PieceMoveParser {
 
         parser.finalize()
     }
+
+    /// Rewrites common deviations from strict SAN into notation the parser understands:
+    /// whitespace anywhere in the move, hyphens between squares ("Nb1-c3"), en passant
+    /// suffixes ("exd6 e.p.") and lowercase piece letters ("nf3").
+    ///
+    /// A lowercase 'b' is left alone since it's also a file, see `PieceMove::from_algebraic_with_options`.
+    fn normalize_lenient(notation: &str) -> String {
+        let mut normalized: String = notation
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect();
+
+        normalized = normalized.replace("e.p.", "").replace("e.p", "");
+
+        let mut chars = normalized.chars();
+        match chars.next() {
+            Some(c @ ('n' | 'r' | 'q' | 'k')) => {
+                format!("{}{}", c.to_ascii_uppercase(), chars.as_str())
+            }
+            _ => normalized,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -898,6 +1084,111 @@ mod tests {
         assert_eq!(parsed.rescue_drop_rank, None);
     }
 
+    #[test]
+    fn test_promotions() {
+        let parsed = PieceMoveParser::parse("e8=Q").unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Pawn);
+        assert_eq!(parsed.to_file, 4);
+        assert_eq!(parsed.to_rank, 0);
+        assert_eq!(parsed.promotion_to, Some(PieceType::Queen));
+
+        let parsed = PieceMoveParser::parse("exd8=N+").unwrap();
+        assert_eq!(parsed.from_file, Some(4));
+        assert_eq!(parsed.to_file, 3);
+        assert_eq!(parsed.to_rank, 0);
+        assert!(parsed.is_capture);
+        assert_eq!(parsed.promotion_to, Some(PieceType::Knight));
+
+        // Bare trailing piece letter
+        let parsed = PieceMoveParser::parse("a1R").unwrap();
+        assert_eq!(parsed.to_rank, 7);
+        assert_eq!(parsed.promotion_to, Some(PieceType::Rook));
+
+        // Promotion followed by a rescue
+        let parsed = PieceMoveParser::parse("e8=QSd8").unwrap();
+        assert_eq!(parsed.promotion_to, Some(PieceType::Queen));
+        assert_eq!(parsed.rescue_drop, Some(RescueOrDrop::Rescue));
+        assert_eq!(parsed.rescue_drop_file, Some(3));
+
+        // Dropped pawn promotions
+        let parsed = PieceMoveParser::parse("Kb7Db8Q").unwrap();
+        assert_eq!(parsed.rescue_drop, Some(RescueOrDrop::Drop));
+        assert_eq!(parsed.promotion_to, None);
+        assert_eq!(parsed.dropped_promotion_to, Some(PieceType::Queen));
+
+        let parsed = PieceMoveParser::parse("Kb7Db8=N#").unwrap();
+        assert_eq!(parsed.dropped_promotion_to, Some(PieceType::Knight));
+
+        assert_eq!(
+            PieceMoveParser::parse("exd8=N").unwrap().to_algebraic(),
+            "exd8=N"
+        );
+    }
+
+    #[test]
+    fn test_promotion_errors() {
+        assert!(PieceMoveParser::parse("e8=").is_err()); // Missing piece
+        assert!(PieceMoveParser::parse("e8=K").is_err()); // Can't promote to a king
+        assert!(PieceMoveParser::parse("e8=P").is_err()); // Can't promote to a pawn
+        assert!(PieceMoveParser::parse("e4=Q").is_err()); // Not on a back rank
+        assert!(PieceMoveParser::parse("Ne8=Q").is_err()); // Only pawns promote
+        assert!(PieceMoveParser::parse("e8=Q=R").is_err()); // Double promotion
+        assert!(PieceMoveParser::parse("Kb7Sb8Q").is_err()); // Rescues can't promote
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let lenient = ParserOptions::lenient();
+
+        // Hyphenated long algebraic notation
+        let parsed = PieceMoveParser::parse_with_options("Nb1-c3", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Knight);
+        assert_eq!(parsed.from_file, Some(1));
+        assert_eq!(parsed.from_rank, Some(7));
+        assert_eq!(parsed.to_file, 2);
+        assert_eq!(parsed.to_rank, 5);
+
+        let parsed = PieceMoveParser::parse_with_options("e2-e4", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Pawn);
+        assert_eq!(parsed.to_file, 4);
+        assert_eq!(parsed.to_rank, 4);
+
+        // En passant suffixes
+        let parsed = PieceMoveParser::parse_with_options("exd6 e.p.", lenient).unwrap();
+        assert!(parsed.is_capture);
+        assert_eq!(parsed.to_file, 3);
+        assert_eq!(parsed.to_rank, 2);
+
+        let parsed = PieceMoveParser::parse_with_options("exd6e.p+", lenient).unwrap();
+        assert!(parsed.is_capture);
+
+        // Lowercase piece letters
+        let parsed = PieceMoveParser::parse_with_options("nf3", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Knight);
+
+        let parsed = PieceMoveParser::parse_with_options("qxf7#", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Queen);
+        assert!(parsed.is_capture);
+
+        // Lowercase b is still a pawn on the b-file
+        let parsed = PieceMoveParser::parse_with_options("bxc3", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Pawn);
+
+        // Stray whitespace
+        let parsed = PieceMoveParser::parse_with_options(" N f 3 + ", lenient).unwrap();
+        assert_eq!(parsed.piece_type, PieceType::Knight);
+        assert_eq!(parsed.to_file, 5);
+        assert_eq!(parsed.to_rank, 5);
+    }
+
+    #[test]
+    fn test_strict_parsing_rejects_lenient_notation() {
+        assert!(PieceMoveParser::parse("Nb1-c3").is_err());
+        assert!(PieceMoveParser::parse("exd6 e.p.").is_err());
+        assert!(PieceMoveParser::parse("nf3").is_err());
+        assert!(PieceMoveParser::parse("N f3").is_err());
+    }
+
     #[test]
     fn test_pathological_cases() {
         // Test extremely long move notation (but still valid)
@@ -12,12 +12,12 @@ pub struct Pos(pub u8);
 
 impl Pos {
     #[inline(always)]
-    pub fn xy(x: u8, y: u8) -> Pos {
+    pub const fn xy(x: u8, y: u8) -> Pos {
         Pos(x + y * 8)
     }
 
     #[inline(always)]
-    pub fn get_xy(&self) -> (u8, u8) {
+    pub const fn get_xy(&self) -> (u8, u8) {
         (self.0 % 8, self.0 / 8)
     }
 
@@ -79,7 +79,7 @@ impl Pos {
     }
 
     #[inline]
-    pub fn moved(&self, x: i8, y: i8) -> Option<Pos> {
+    pub const fn moved(&self, x: i8, y: i8) -> Option<Pos> {
         let cur_x = (self.0 as i8) % 8;
         let cur_y = (self.0 as i8) / 8;
 
@@ -91,27 +91,27 @@ impl Pos {
     }
 
     #[inline(always)]
-    pub fn moved_unchecked(&self, x: i8, y: i8) -> Pos {
+    pub const fn moved_unchecked(&self, x: i8, y: i8) -> Pos {
         Pos(((self.0 as i8) + x + y * 8) as u8)
     }
 
     #[inline(always)]
-    pub fn moved_up_unchecked(&self) -> Pos {
+    pub const fn moved_up_unchecked(&self) -> Pos {
         Pos(self.0 - 8)
     }
 
     #[inline(always)]
-    pub fn moved_down_unchecked(&self) -> Pos {
+    pub const fn moved_down_unchecked(&self) -> Pos {
         Pos(self.0 + 8)
     }
 
     #[inline(always)]
-    pub fn moved_left_unchecked(&self) -> Pos {
+    pub const fn moved_left_unchecked(&self) -> Pos {
         Pos(self.0 - 1)
     }
 
     #[inline(always)]
-    pub fn moved_right_unchecked(&self) -> Pos {
+    pub const fn moved_right_unchecked(&self) -> Pos {
         Pos(self.0 + 1)
     }
 
@@ -132,47 +132,47 @@ impl Pos {
     }
 
     #[inline(always)]
-    pub fn is_col(&self, col: u8) -> bool {
+    pub const fn is_col(&self, col: u8) -> bool {
         self.0 % 8 == col
     }
 
     #[inline(always)]
-    pub fn is_row(&self, row: u8) -> bool {
+    pub const fn is_row(&self, row: u8) -> bool {
         self.0 / 8 == row
     }
 
     #[inline(always)]
-    pub fn get_col(&self) -> u8 {
+    pub const fn get_col(&self) -> u8 {
         self.0 % 8
     }
 
     #[inline(always)]
-    pub fn get_row(&self) -> u8 {
+    pub const fn get_row(&self) -> u8 {
         self.0 / 8
     }
 
     #[inline(always)]
-    pub fn can_move_up(&self) -> bool {
+    pub const fn can_move_up(&self) -> bool {
         self.0 > 7
     }
 
     #[inline(always)]
-    pub fn can_move_down(&self) -> bool {
+    pub const fn can_move_down(&self) -> bool {
         self.0 < 56
     }
 
     #[inline(always)]
-    pub fn can_move_left(&self) -> bool {
+    pub const fn can_move_left(&self) -> bool {
         self.0 % 8 != 0
     }
 
     #[inline(always)]
-    pub fn can_move_right(&self) -> bool {
+    pub const fn can_move_right(&self) -> bool {
         self.0 % 8 != 7
     }
 
     #[inline(always)]
-    pub fn invert(&self) -> Pos {
+    pub const fn invert(&self) -> Pos {
         let x = self.0 % 8;
         let y = self.0 / 8;
 
@@ -180,7 +180,7 @@ impl Pos {
     }
 
     #[inline(always)]
-    pub fn as_tuple(&self) -> (u8, u8) {
+    pub const fn as_tuple(&self) -> (u8, u8) {
         (self.0 % 8, self.0 / 8)
     }
 
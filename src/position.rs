@@ -1,28 +1,43 @@
+mod attacks;
+mod castling;
 pub mod extended_fen;
 mod fen;
-
-use std::{
-    cell::{Ref, RefCell},
-    hash::Hash,
-    mem,
+mod game_status;
+mod handicap;
+mod legality;
+mod material;
+mod validation;
+mod zobrist;
+
+pub use castling::{
+    Castling, CastlingFiles, CastlingSide, CHESS960_POSITIONS, STANDARD_CHESS960_POSITION,
 };
+pub use game_status::{repetition_count, GameStatus, FIFTY_MOVE_RULE_PLIES, REPETITION_COUNT};
+pub use handicap::Handicap;
+pub use material::{Material, SideMaterial};
+pub use validation::{FenField, PositionError, PositionRule, PositionSetup, SetupPiece};
+
+use std::{hash::Hash, mem};
 
 use arrayvec::ArrayVec;
 use colored::Colorize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     bitboard::{Bitboard, SumBitboards},
-    piece::{rescue_drop::rescue_drop_map, Color, King, PieceType, PAWN_PROMOTION_TYPES},
-    piece_move::{GameType, MoveType, PieceMove},
-    pos::{self, Pos},
+    piece::{king, Color, King, PieceType, PAWN_PROMOTION_TYPES},
+    piece_move::{GameType, MoveList, MoveType, PieceMove},
+    pos::Pos,
+    variant::{Capture, RescueConfig},
 };
 
 use super::piece::Piece;
+use legality::Legality;
 
 /// Records the castling rights that each player has at a point in the game. Once
 /// a player moves their king, or the rook that is involved in castling, the
 /// castling rights are removed.
-#[derive(Debug, Clone, PartialEq, Copy, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Copy, Hash, Eq, Serialize, Deserialize)]
 pub struct CastlingRights {
     /// Can white castle kingside
     pub white_king_side: bool,
@@ -35,6 +50,11 @@ pub struct CastlingRights {
 
     /// Can black castle queenside
     pub black_queen_side: bool,
+
+    /// The files the kings and castling rooks start on, which only differ from the standard
+    /// ones in Chess960
+    #[serde(default)]
+    pub files: CastlingFiles,
 }
 
 impl Default for CastlingRights {
@@ -44,6 +64,7 @@ impl Default for CastlingRights {
             white_queen_side: true,
             black_king_side: true,
             black_queen_side: true,
+            files: CastlingFiles::STANDARD,
         }
     }
 }
@@ -51,16 +72,15 @@ impl Default for CastlingRights {
 pub struct RestorePosition {
     pub en_passant: Option<Pos>,
     pub castling_rights: CastlingRights,
-
-    pub piece_maps: Option<PieceMaps>,
-    pub attack_map: Option<SumBitboards>,
-    pub pseudolegal_moves: Option<ArrayVec<(PieceType, u8), 16>>,
-    pub all_legal_moves: Option<Vec<PieceMove>>,
+    pub halfmove_clock: u8,
 }
 
 /// A game position in chess. Contains all state to represent a single position
 /// in a game of chess.
-#[derive(Debug, Eq)]
+///
+/// A position serializes as its FEN, which includes held pieces, rather than field by field,
+/// since everything but the pieces and the game state can be worked out from them.
+#[derive(Debug, Clone, Eq)]
 pub struct Position {
     /// The pieces on the board
     pub white_pieces: [Option<Piece>; 16],
@@ -95,17 +115,31 @@ pub struct Position {
     pub white_king: Option<Pos>,
     pub black_king: Option<Pos>,
 
-    pub piece_maps: RefCell<Option<PieceMaps>>,
-    pub attack_map: RefCell<Option<SumBitboards>>,
-    pub pseudolegal_moves: RefCell<Option<ArrayVec<(PieceType, u8), 16>>>,
-    pub all_legal_moves: RefCell<Option<Vec<PieceMove>>>,
+    /// Where each type of piece is, kept up to date by the methods that change the pieces.
+    pub piece_maps: PieceMaps,
 
     pub true_active_color: Color,
+
+    /// The material and square bonuses of each side, kept up to date by the methods that
+    /// change the pieces. Changing the pieces directly leaves it out of date.
+    pub material: Material,
+
+    /// The pieces' part of the Zobrist key, kept up to date in the same way as `material`.
+    /// See `zobrist_key`.
+    pub pieces_key: u64,
+
+    /// The rules pieces rescue and drop by in Rescue games.
+    pub rescue_config: RescueConfig,
 }
 
+/// The key positions are stored under in the transposition table.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct HashablePosition {
     pub cells: [HashableCell; 64],
+
+    /// The castling rights, including the Chess960 castling files. Every key carries the files,
+    /// so the same position in different Chess960 setups doesn't share entries, at the cost of a
+    /// slightly larger entry and so fewer entries in a table of a given size.
     pub castling_rights: CastlingRights,
     pub en_passant: Option<Pos>,
 }
@@ -120,7 +154,7 @@ pub enum HashableCell {
     },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PieceMaps {
     pub white_pawns: Bitboard,
     pub white_knights: Bitboard,
@@ -138,6 +172,42 @@ pub struct PieceMaps {
 }
 
 impl PieceMaps {
+    /// The bitboard for pieces of a type and color.
+    pub fn bitboard(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        match (color, piece_type) {
+            (Color::White, PieceType::Pawn) => self.white_pawns,
+            (Color::White, PieceType::Knight) => self.white_knights,
+            (Color::White, PieceType::Bishop) => self.white_bishops,
+            (Color::White, PieceType::Rook) => self.white_rooks,
+            (Color::White, PieceType::Queen) => self.white_queens,
+            (Color::White, PieceType::King) => self.white_king,
+            (Color::Black, PieceType::Pawn) => self.black_pawns,
+            (Color::Black, PieceType::Knight) => self.black_knights,
+            (Color::Black, PieceType::Bishop) => self.black_bishops,
+            (Color::Black, PieceType::Rook) => self.black_rooks,
+            (Color::Black, PieceType::Queen) => self.black_queens,
+            (Color::Black, PieceType::King) => self.black_king,
+        }
+    }
+
+    /// The bitboard for pieces of a type and color, mutably.
+    fn bitboard_mut(&mut self, piece_type: PieceType, color: Color) -> &mut Bitboard {
+        match (color, piece_type) {
+            (Color::White, PieceType::Pawn) => &mut self.white_pawns,
+            (Color::White, PieceType::Knight) => &mut self.white_knights,
+            (Color::White, PieceType::Bishop) => &mut self.white_bishops,
+            (Color::White, PieceType::Rook) => &mut self.white_rooks,
+            (Color::White, PieceType::Queen) => &mut self.white_queens,
+            (Color::White, PieceType::King) => &mut self.white_king,
+            (Color::Black, PieceType::Pawn) => &mut self.black_pawns,
+            (Color::Black, PieceType::Knight) => &mut self.black_knights,
+            (Color::Black, PieceType::Bishop) => &mut self.black_bishops,
+            (Color::Black, PieceType::Rook) => &mut self.black_rooks,
+            (Color::Black, PieceType::Queen) => &mut self.black_queens,
+            (Color::Black, PieceType::King) => &mut self.black_king,
+        }
+    }
+
     pub fn invert(&mut self) {
         mem::swap(&mut self.white_pawns, &mut self.black_pawns);
         mem::swap(&mut self.white_knights, &mut self.black_knights);
@@ -162,30 +232,6 @@ impl PieceMaps {
     }
 }
 
-impl Clone for Position {
-    fn clone(&self) -> Self {
-        Position {
-            white_pieces: self.white_pieces.clone(),
-            black_pieces: self.black_pieces.clone(),
-            castling_rights: self.castling_rights,
-            en_passant: self.en_passant,
-            halfmove_clock: self.halfmove_clock,
-            fullmove_number: self.fullmove_number,
-            white_map: self.white_map,
-            black_map: self.black_map,
-            all_map: self.all_map,
-            position_lookup: self.position_lookup,
-            white_king: self.white_king,
-            black_king: self.black_king,
-            true_active_color: self.true_active_color,
-            all_legal_moves: RefCell::new(None), // Don't clone the cache, it's slow
-            piece_maps: self.piece_maps.clone(),
-            attack_map: RefCell::new(None),
-            pseudolegal_moves: RefCell::new(None),
-        }
-    }
-}
-
 /// Given a list of pieces, returns a bitboard with the positions of the pieces for the given color.
 #[inline]
 fn to_bitboard(pieces: &[Option<Piece>]) -> Bitboard {
@@ -198,6 +244,56 @@ fn to_bitboard(pieces: &[Option<Piece>]) -> Bitboard {
     bb
 }
 
+/// Builds the bitboards of each type of piece from scratch.
+fn calculate_piece_maps(
+    white_pieces: &[Option<Piece>],
+    black_pieces: &[Option<Piece>],
+) -> PieceMaps {
+    let mut maps = PieceMaps {
+        white_pawns: Bitboard::new(),
+        white_bishops: Bitboard::new(),
+        white_knights: Bitboard::new(),
+        white_rooks: Bitboard::new(),
+        white_queens: Bitboard::new(),
+        white_king: Bitboard::new(),
+
+        black_pawns: Bitboard::new(),
+        black_bishops: Bitboard::new(),
+        black_knights: Bitboard::new(),
+        black_rooks: Bitboard::new(),
+        black_queens: Bitboard::new(),
+        black_king: Bitboard::new(),
+    };
+
+    for piece in white_pieces.iter() {
+        if let Some(piece) = piece {
+            match piece.piece_type {
+                PieceType::Pawn => maps.white_pawns.set(piece.position),
+                PieceType::Bishop => maps.white_bishops.set(piece.position),
+                PieceType::Knight => maps.white_knights.set(piece.position),
+                PieceType::Rook => maps.white_rooks.set(piece.position),
+                PieceType::Queen => maps.white_queens.set(piece.position),
+                PieceType::King => maps.white_king.set(piece.position),
+            }
+        }
+    }
+
+    for piece in black_pieces.iter() {
+        if let Some(piece) = piece {
+            match piece.piece_type {
+                PieceType::Pawn => maps.black_pawns.set(piece.position),
+                PieceType::Bishop => maps.black_bishops.set(piece.position),
+                PieceType::Knight => maps.black_knights.set(piece.position),
+                PieceType::Rook => maps.black_rooks.set(piece.position),
+                PieceType::Queen => maps.black_queens.set(piece.position),
+                PieceType::King => maps.black_king.set(piece.position),
+            }
+        }
+    }
+
+    maps
+}
+
 /// Calculates the position_lookup by iterating over the pieces and setting the
 /// index of the piece in the pieces vector at the position of the piece.
 fn calc_position_lookup(
@@ -262,6 +358,15 @@ impl Position {
 
         let position_lookup = calc_position_lookup(&white_pieces, &black_pieces);
 
+        let piece_maps = calculate_piece_maps(&white_pieces, &black_pieces);
+
+        let material =
+            Material::from_pieces(white_pieces.iter().chain(black_pieces.iter()).flatten());
+        let pieces_key = zobrist::pieces_key(
+            white_pieces.iter().chain(black_pieces.iter()).flatten(),
+            Color::White,
+        );
+
         let white_king = white_pieces
             .iter()
             .filter_map(|piece| piece.as_ref())
@@ -288,10 +393,10 @@ impl Position {
             black_king,
             all_map,
             true_active_color: Color::White,
-            all_legal_moves: RefCell::new(None),
-            piece_maps: RefCell::new(None),
-            attack_map: RefCell::new(None),
-            pseudolegal_moves: RefCell::new(None),
+            piece_maps,
+            material,
+            pieces_key,
+            rescue_config: RescueConfig::default(),
         }
     }
 
@@ -339,22 +444,13 @@ impl Position {
         }
 
         self.invert_position_lookup();
+        self.material.invert();
 
         self.white_map = to_bitboard(&self.white_pieces);
         self.black_map = to_bitboard(&self.black_pieces);
         self.all_map = self.white_map | self.black_map;
 
-        *self.all_legal_moves.borrow_mut() = None;
-        *self.attack_map.borrow_mut() = None;
-        *self.pseudolegal_moves.borrow_mut() = None;
-
-        let piece_maps_inverted = self.piece_maps.borrow().as_ref().map(|m| {
-            let mut m = m.clone();
-            m.invert();
-            m
-        });
-
-        *self.piece_maps.borrow_mut() = piece_maps_inverted;
+        self.piece_maps.invert();
     }
 
     #[inline(always)]
@@ -462,8 +558,11 @@ impl Position {
             ));
         }
 
-        if rescuer_piece.piece_type.can_hold(rescued_piece.piece_type) {
-            self.get_piece_at_mut(rescuer).unwrap().holding = Some(rescued_piece.piece_type);
+        if self
+            .rescue_config
+            .can_hold(rescuer_piece.piece_type, rescued_piece.piece_type)
+        {
+            self.set_holding(rescuer, Some(rescued_piece.piece_type))?;
             self.remove_piece_at(rescued)?;
 
             Ok(())
@@ -495,7 +594,7 @@ impl Position {
 
         self.add_piece(Piece::new(holding_type, rescuer.color, drop_pos))?;
 
-        self.get_piece_at_mut(rescuer_pos).unwrap().holding = None;
+        self.set_holding(rescuer_pos, None)?;
 
         Ok(())
     }
@@ -524,8 +623,19 @@ impl Position {
                         .expect("White piece missing")
                 };
 
+                let before = piece.clone();
                 piece.position = to;
 
+                self.material.replace(&before, piece);
+                self.pieces_key ^= zobrist::piece_key(&before, self.true_active_color)
+                    ^ zobrist::piece_key(piece, self.true_active_color);
+
+                let piece_map = self
+                    .piece_maps
+                    .bitboard_mut(before.piece_type, before.color);
+                piece_map.clear(from);
+                piece_map.set(to);
+
                 self.position_lookup.swap(from.0 as usize, to.0 as usize);
 
                 if is_black {
@@ -559,12 +669,20 @@ impl Position {
     /// Removes the piece at a specific position.
     pub fn remove_piece_at(&mut self, position: Pos) -> Result<(), anyhow::Error> {
         if let Some(index) = self.position_lookup[position.0 as usize] {
-            if index >= 16 {
-                self.black_pieces[index as usize - 16] = None;
+            let piece = if index >= 16 {
                 self.black_map.clear(position);
+                self.black_pieces[index as usize - 16].take()
             } else {
-                self.white_pieces[index as usize] = None;
                 self.white_map.clear(position);
+                self.white_pieces[index as usize].take()
+            };
+
+            if let Some(piece) = piece {
+                self.material.remove(&piece);
+                self.pieces_key ^= zobrist::piece_key(&piece, self.true_active_color);
+                self.piece_maps
+                    .bitboard_mut(piece.piece_type, piece.color)
+                    .clear(position);
             }
 
             self.all_map = self.white_map | self.black_map;
@@ -598,6 +716,12 @@ impl Position {
         let position = piece.position;
         let color = piece.color;
 
+        self.material.add(&piece);
+        self.pieces_key ^= zobrist::piece_key(&piece, self.true_active_color);
+        self.piece_maps
+            .bitboard_mut(piece.piece_type, piece.color)
+            .set(piece.position);
+
         if piece.color == Color::White {
             let idx = add_to_slot_map(&mut self.white_pieces, piece);
             self.white_map.set(position);
@@ -623,7 +747,14 @@ impl Position {
 
     /// Returns true if white is in checkmate. Returns an error if the position is invalid (no king)
     pub fn is_checkmate(&self, game_type: GameType) -> Result<bool, anyhow::Error> {
-        Ok(self.is_king_in_check()? && self.get_all_legal_moves(game_type)?.is_empty())
+        if !self.is_king_in_check()? {
+            return Ok(false);
+        }
+
+        let mut moves = MoveList::new();
+        self.get_all_legal_moves(game_type, &mut moves)?;
+
+        Ok(moves.is_empty())
     }
 
     /// Returns true if the white king is currently in check. Returns an error if there is no king.
@@ -632,10 +763,9 @@ impl Position {
     }
 
     pub fn is_black_king_in_check(&self) -> Result<bool, anyhow::Error> {
-        match self.black_king {
-            Some(black_king) => Ok(self.count_attackers(black_king) > 0),
-            None => Ok(false),
-        }
+        Ok(self
+            .black_king
+            .is_some_and(|black_king| self.is_attacked_by(black_king, Color::White)))
     }
 
     pub fn is_piece_at(&self, position: Pos, piece_type: &[PieceType], color: Color) -> bool {
@@ -649,11 +779,7 @@ impl Position {
         }
     }
 
-    pub fn count_pseudolegal_moves(&self) -> Ref<ArrayVec<(PieceType, u8), 16>> {
-        if self.pseudolegal_moves.borrow().is_some() {
-            return Ref::map(self.pseudolegal_moves.borrow(), |m| m.as_ref().unwrap());
-        }
-
+    pub fn count_pseudolegal_moves(&self) -> ArrayVec<(PieceType, u8), 16> {
         let mut moves: ArrayVec<(PieceType, u8), 16> = ArrayVec::new();
 
         for piece in self.white_pieces.iter() {
@@ -663,280 +789,226 @@ impl Position {
             }
         }
 
-        *self.pseudolegal_moves.borrow_mut() = Some(moves.clone());
-
-        Ref::map(self.pseudolegal_moves.borrow(), |m| m.as_ref().unwrap())
+        moves
     }
 
-    /// Gets all legal moves for the current position. Takes into account
-    /// whether the king is in check, etc.
+    /// Fills `moves` with all legal moves for the current position, replacing whatever it held.
+    /// Takes into account whether the king is in check, etc.
     pub fn get_all_legal_moves(
         &self,
         game_type: GameType,
-    ) -> Result<Vec<PieceMove>, anyhow::Error> {
-        if let Some(all_legal_moves) = self.all_legal_moves.borrow().as_ref() {
-            return Ok(all_legal_moves.clone());
-        }
+        moves: &mut MoveList,
+    ) -> Result<(), anyhow::Error> {
+        self.get_all_moves_unchecked(game_type, moves);
 
-        let possible_moves = self.get_all_moves_unchecked(game_type);
-        let mut moves = Vec::with_capacity(possible_moves.len());
+        let Some(white_king) = self.white_king else {
+            return Ok(());
+        };
 
-        let mut position = self.clone();
+        let legality = Legality::new(self, white_king);
 
-        let mut unimpeded_moves = Bitboard::new();
-        for piece in self.black_pieces.iter() {
-            if let Some(piece) = piece {
-                unimpeded_moves = unimpeded_moves | piece.get_attack_map();
-            }
-        }
+        // A copy of the position to make the moves the bitboards can't decide on
+        let mut position = None;
 
-        for mv in possible_moves.into_iter() {
-            let restore = position.apply_move(mv)?;
+        // Legal moves are moved down over the illegal ones, keeping their order
+        let mut legal_count = 0;
 
-            if let Some(white_king) = position.white_king {
-                if unimpeded_moves.get(white_king) {
-                    if !position.is_king_in_check()? {
-                        moves.push(mv);
-                    }
-                } else {
-                    moves.push(mv);
+        for index in 0..moves.len() {
+            let mv = moves[index];
+
+            let mut legal = match legality.is_legal(&mv) {
+                Some(legal) => legal,
+                None => {
+                    let position = position.get_or_insert_with(|| self.clone());
+                    let restore = position.apply_move(mv)?;
+                    let in_check = position.is_king_in_check()?;
+                    position.unapply_move(mv, restore)?;
+
+                    !in_check
                 }
-            } else {
-                moves.push(mv);
+            };
+
+            if legal && !self.rescue_config.drops_can_mate {
+                legal = !self.is_mating_drop(game_type, &mv)?;
             }
 
-            position.unapply_move(mv, restore)?;
+            if legal {
+                moves[legal_count] = mv;
+                legal_count += 1;
+            }
         }
 
-        *self.all_legal_moves.borrow_mut() = Some(moves.clone());
+        moves.truncate(legal_count);
 
-        Ok(moves)
+        Ok(())
     }
 
-    /// Gets all moves that are possible by white, without checking for
-    /// check, use this to check whether a king is in check, etc.
-    pub fn get_all_moves_unchecked(&self, game_type: GameType) -> Vec<PieceMove> {
-        let mut moves = Vec::with_capacity(self.white_pieces.len() * 8);
+    /// White's pieces of the types a piece of `piece_type` can hold.
+    pub(crate) fn rescuable_map(&self, piece_type: PieceType) -> Bitboard {
+        let maps = &self.piece_maps;
 
-        for piece in self.white_pieces.iter() {
-            if let Some(piece) = piece {
-                let from = piece.position;
-                let piece_type = piece.piece_type;
-                let mut legal_moves = piece.get_legal_moves(self, true);
+        [
+            (PieceType::Pawn, maps.white_pawns),
+            (PieceType::Knight, maps.white_knights),
+            (PieceType::Bishop, maps.white_bishops),
+            (PieceType::Rook, maps.white_rooks),
+            (PieceType::Queen, maps.white_queens),
+            (PieceType::King, maps.white_king),
+        ]
+        .into_iter()
+        .filter(|&(held_type, _)| self.rescue_config.can_hold(piece_type, held_type))
+        .fold(Bitboard::new(), |map, (_, pieces)| map | pieces)
+    }
+
+    /// Fills `moves` with all moves that are possible by white, replacing whatever it held,
+    /// without checking for check, use this to check whether a king is in check, etc.
+    pub fn get_all_moves_unchecked(&self, game_type: GameType, moves: &mut MoveList) {
+        moves.clear();
 
-                if game_type == GameType::Rescue {
-                    // Piece can stay still and rescue, as long as there's a neighboring piece
-                    legal_moves.set(piece.position);
+        let holders = self.holders(game_type);
+        for piece in self.white_pieces.iter().flatten() {
+            self.add_piece_moves_unchecked(piece, game_type, holders, moves);
+        }
+    }
+
+    /// Fills `moves` with the moves of white's piece at `from`, without checking for check.
+    /// Leaves `moves` empty if there's no white piece there.
+    pub fn get_piece_moves_unchecked(&self, game_type: GameType, from: Pos, moves: &mut MoveList) {
+        moves.clear();
+
+        if let Some(piece) = self.get_piece_at(from).filter(|p| p.color == Color::White) {
+            self.add_piece_moves_unchecked(piece, game_type, self.holders(game_type), moves);
+        }
+    }
+
+    /// White's pieces that are already holding one, which can't be rescued.
+    fn holders(&self, game_type: GameType) -> Bitboard {
+        let mut holders = Bitboard::new();
+        if game_type.rules().has_rescues() {
+            for piece in self.white_pieces.iter().flatten() {
+                if piece.holding.is_some() {
+                    holders.set(piece.position);
                 }
+            }
+        }
+        holders
+    }
 
-                for to in legal_moves.into_iter() {
-                    let mut captured = None;
-                    let mut captured_pos = None;
-                    let mut captured_holding = None;
-
-                    if self.black_map.get(to) {
-                        captured = Some(
-                            self.get_piece_at(to)
-                                .expect("No piece at position")
-                                .piece_type,
-                        );
-                        captured_pos = Some(to);
-                        captured_holding = self.get_piece_at(to).unwrap().holding;
-                    }
+    fn add_piece_moves_unchecked(
+        &self,
+        piece: &Piece,
+        game_type: GameType,
+        holders: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        let from = piece.position;
+        let piece_type = piece.piece_type;
 
-                    let mut can_normal_move = false;
-
-                    if game_type == GameType::Rescue {
-                        for dir in rescue_drop_map(to).into_iter() {
-                            match piece.holding {
-                                Some(holding) => {
-                                    let mut can_drop_map = self.all_map.clone();
-                                    can_drop_map.clear(from);
-
-                                    // We're holding, so we can drop into an empty spot
-                                    if !can_drop_map.get(dir) {
-                                        if holding == PieceType::Pawn && dir.get_row() == 0 {
-                                            for promoted_to in PAWN_PROMOTION_TYPES {
-                                                moves.push(PieceMove {
-                                                    from,
-                                                    to: dir,
-                                                    piece_type,
-                                                    move_type: MoveType::Normal {
-                                                        captured_pos,
-                                                        captured,
-                                                        captured_holding,
-                                                        promoted_to: None,
-                                                        dropped_pos: Some(dir),
-                                                        dropped_promoted_to: Some(promoted_to),
-                                                        rescued_pos: None,
-                                                    },
-                                                });
-                                            }
-                                        } else {
-                                            moves.push(PieceMove {
-                                                from,
-                                                to: dir,
-                                                piece_type,
-                                                move_type: MoveType::Normal {
-                                                    captured_pos,
-                                                    captured,
-                                                    captured_holding,
-                                                    promoted_to: None,
-                                                    dropped_pos: Some(dir),
-                                                    dropped_promoted_to: None,
-                                                    rescued_pos: None,
-                                                },
-                                            });
-                                        }
-                                    }
-                                }
-                                None => {
-                                    let mut can_pick_up_map = self.white_map.clone();
-                                    can_pick_up_map.clear(from);
-
-                                    // We're not holding, but we can rescue any adjacent piece
-                                    if can_pick_up_map.get(dir)
-                                        && piece_type.can_hold(
-                                            self.get_piece_at(dir)
-                                                .expect("No piece at position")
-                                                .piece_type,
-                                        )
-                                    {
-                                        moves.push(PieceMove {
-                                            from,
-                                            to,
-                                            piece_type,
-                                            move_type: MoveType::Normal {
-                                                captured_pos,
-                                                captured,
-                                                captured_holding,
-                                                promoted_to: None,
-                                                dropped_pos: None,
-                                                dropped_promoted_to: None,
-                                                rescued_pos: Some(dir),
-                                            },
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                    }
+        // Castling is added separately, so that the king's two-square moves don't also become
+        // normal moves, rescues and drops
+        let mut legal_moves = match piece_type {
+            PieceType::King => *king::attack_map(from) & !self.white_map,
+            _ => piece.get_legal_moves(self, true),
+        };
 
-                    if piece.piece_type == PieceType::Pawn && to.is_row(0) {
-                        for promoted_to in PAWN_PROMOTION_TYPES {
-                            moves.push(PieceMove {
-                                from,
-                                to,
-                                piece_type,
-                                move_type: MoveType::Normal {
-                                    captured_pos,
-                                    captured,
-                                    captured_holding,
-                                    promoted_to: Some(promoted_to),
-                                    dropped_pos: None,
-                                    dropped_promoted_to: None,
-                                    rescued_pos: None,
-                                },
-                            });
-                        }
-                    } else if piece_type == PieceType::King && from == pos::E1 && to == pos::G1 {
-                        // White kingside castle
-                        moves.push(PieceMove {
-                            from,
-                            to,
-                            piece_type,
-                            move_type: MoveType::Castle {
-                                king: pos::E1,
-                                rook: pos::H1,
-                            },
-                        });
-                    } else if piece_type == PieceType::King && from == pos::E1 && to == pos::B1 {
-                        // White queenside castle
-                        moves.push(PieceMove {
-                            from,
-                            to,
-                            piece_type,
-                            move_type: MoveType::Castle {
-                                king: pos::E1,
-                                rook: pos::A1,
-                            },
-                        });
-                    } else if piece_type == PieceType::King && from == pos::D1 && to == pos::F1 {
-                        // Black queenside castle
-                        moves.push(PieceMove {
-                            from,
-                            to,
-                            piece_type,
-                            move_type: MoveType::Castle {
-                                king: pos::D1,
-                                rook: pos::H1,
-                            },
-                        });
-                    } else if piece_type == PieceType::King && from == pos::D1 && to == pos::B1 {
-                        // Black kingside castle
-                        moves.push(PieceMove {
-                            from,
-                            to,
-                            piece_type,
-                            move_type: MoveType::Castle {
-                                king: pos::D1,
-                                rook: pos::A1,
-                            },
-                        });
-                    } else if piece_type == PieceType::Pawn {
-                        // En passant
-                        if let Some(en_passant) = self.en_passant {
-                            if to == en_passant {
-                                let captured_pos = en_passant.moved_down_unchecked();
-
-                                moves.push(PieceMove {
-                                    from,
-                                    to,
-                                    piece_type,
-                                    move_type: MoveType::Normal {
-                                        captured: Some(PieceType::Pawn),
-                                        captured_pos: Some(captured_pos),
-                                        captured_holding: self
-                                            .get_piece_at(captured_pos)
-                                            .expect("No pawn below en passant position")
-                                            .holding,
-                                        promoted_to: None,
-                                        dropped_pos: None,
-                                        dropped_promoted_to: None,
-                                        rescued_pos: None,
-                                    },
-                                });
-                            } else {
-                                can_normal_move = true;
-                            }
-                        } else {
-                            can_normal_move = true;
-                        }
-                    } else {
-                        can_normal_move = true;
-                    }
+        let rules = game_type.rules();
+        legal_moves = legal_moves | rules.in_place_moves(piece);
+
+        for to in legal_moves.into_iter() {
+            let mut captured = None;
+            let mut captured_pos = None;
+            let mut captured_holding = None;
+
+            if self.black_map.get(to) {
+                captured = Some(
+                    self.get_piece_at(to)
+                        .expect("No piece at position")
+                        .piece_type,
+                );
+                captured_pos = Some(to);
+                captured_holding = self.get_piece_at(to).unwrap().holding;
+            }
+
+            let capture = Capture {
+                captured,
+                captured_pos,
+                captured_holding,
+            };
+            rules.add_extra_moves(self, piece, to, capture, holders, moves);
+
+            let mut can_normal_move = false;
+
+            if piece.piece_type == PieceType::Pawn && to.is_row(0) {
+                for promoted_to in PAWN_PROMOTION_TYPES {
+                    moves.push(PieceMove {
+                        from,
+                        to,
+                        piece_type,
+                        move_type: MoveType::Normal {
+                            captured_pos,
+                            captured,
+                            captured_holding,
+                            promoted_to: Some(promoted_to),
+                            dropped_pos: None,
+                            dropped_promoted_to: None,
+                            rescued_pos: None,
+                        },
+                    });
+                }
+            } else if piece_type == PieceType::Pawn {
+                // En passant
+                if let Some(en_passant) = self.en_passant {
+                    if to == en_passant {
+                        let captured_pos = en_passant.moved_down_unchecked();
 
-                    if can_normal_move && from != to {
                         moves.push(PieceMove {
                             from,
                             to,
                             piece_type,
                             move_type: MoveType::Normal {
-                                captured_pos,
-                                captured,
-                                captured_holding,
+                                captured: Some(PieceType::Pawn),
+                                captured_pos: Some(captured_pos),
+                                captured_holding: self
+                                    .get_piece_at(captured_pos)
+                                    .expect("No pawn below en passant position")
+                                    .holding,
                                 promoted_to: None,
                                 dropped_pos: None,
                                 dropped_promoted_to: None,
                                 rescued_pos: None,
                             },
                         });
+                    } else {
+                        can_normal_move = true;
                     }
+                } else {
+                    can_normal_move = true;
                 }
+            } else {
+                can_normal_move = true;
+            }
+
+            if can_normal_move && from != to {
+                moves.push(PieceMove {
+                    from,
+                    to,
+                    piece_type,
+                    move_type: MoveType::Normal {
+                        captured_pos,
+                        captured,
+                        captured_holding,
+                        promoted_to: None,
+                        dropped_pos: None,
+                        dropped_promoted_to: None,
+                        rescued_pos: None,
+                    },
+                });
             }
         }
 
-        moves
+        if piece_type == PieceType::King {
+            self.add_castling_moves(moves);
+        }
     }
 
     /// Prints the board as ASCII characters.
@@ -1056,6 +1128,7 @@ impl Position {
     pub fn apply_move(&mut self, mv: PieceMove) -> Result<RestorePosition, anyhow::Error> {
         let en_passant = self.en_passant;
         let castling_rights = self.castling_rights.clone();
+        let halfmove_clock = self.halfmove_clock;
 
         let piece = self.get_piece_at(mv.from).ok_or_else(|| {
             anyhow::anyhow!(
@@ -1067,7 +1140,7 @@ impl Position {
 
         let legal_moves = piece.get_legal_moves(self, true);
 
-        if !legal_moves.get(mv.to) && !mv.is_rescue_or_drop() {
+        if !legal_moves.get(mv.to) && !mv.is_rescue_or_drop() && !mv.is_castle() {
             return Err(anyhow::anyhow!(
                 "Illegal move {}! Board state:\n{}\n{}, legal moves: {}",
                 mv.to_string(),
@@ -1096,15 +1169,15 @@ impl Position {
                     self.remove_piece_at(captured_pos)?;
                 }
 
-                // Movement
+                // Movement. A rescue or drop in place still ends the chance to capture en passant.
                 if mv.from != mv.to {
                     self.move_piece(mv.from, mv.to)?;
-                    self.try_en_passant_set(mv);
                 }
+                self.try_en_passant_set(mv);
 
                 // Promotion
                 if let Some(promoted_to) = promoted_to {
-                    self.get_piece_at_mut(mv.to).unwrap().piece_type = promoted_to;
+                    self.promote_piece(mv.to, promoted_to)?;
                 }
 
                 // Rescuing
@@ -1116,79 +1189,46 @@ impl Position {
                 if let Some(dropped_pos) = dropped_pos {
                     self.drop_piece(mv.to, dropped_pos)?;
                     if let Some(promoted_to) = dropped_promoted_to {
-                        self.get_piece_at_mut(dropped_pos).unwrap().piece_type = promoted_to;
+                        self.promote_piece(dropped_pos, promoted_to)?;
                     }
                 }
             }
-            MoveType::Castle { king: _, rook: _ } => {
-                self.move_piece(mv.from, mv.to)?;
-
-                if mv.from == Pos::xy(4, 7) {
-                    // White castle
-                    if mv.to == Pos::xy(6, 7) {
-                        self.move_piece(Pos::xy(7, 7), Pos::xy(5, 7))?;
-                    } else {
-                        self.move_piece(Pos::xy(0, 7), Pos::xy(3, 7))?;
-                    }
-                } else if mv.from == Pos::xy(3, 7) {
-                    // Black castle
-                    if mv.to == Pos::xy(1, 7) {
-                        self.move_piece(Pos::xy(0, 7), Pos::xy(2, 7))?;
-                    } else {
-                        self.move_piece(Pos::xy(7, 7), Pos::xy(4, 7))?;
-                    }
-                } else {
-                    panic!("Illegal castle move");
-                }
+            MoveType::Castle { king, rook } => {
+                let castling = self.castling(Castling::side_of(mv.to));
+                self.move_castling_pieces(king, mv.to, rook, castling.rook_to)?;
             }
         }
 
         self.try_remove_castling_rights(mv);
 
+        if mv.piece_type == PieceType::Pawn || mv.is_capture() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+
         let restore = RestorePosition {
             en_passant,
             castling_rights,
-
-            all_legal_moves: self.all_legal_moves.borrow().as_ref().map(|m| m.clone()),
-            piece_maps: self.piece_maps.borrow().as_ref().map(|m| m.clone()),
-            attack_map: self.attack_map.borrow().as_ref().map(|m| m.clone()),
-            pseudolegal_moves: self.pseudolegal_moves.borrow().as_ref().map(|m| m.clone()),
+            halfmove_clock,
         };
 
-        *self.all_legal_moves.borrow_mut() = None;
-        *self.attack_map.borrow_mut() = None;
-        *self.pseudolegal_moves.borrow_mut() = None;
-        *self.piece_maps.borrow_mut() = None;
-
         Ok(restore)
     }
 
     fn try_remove_castling_rights(&mut self, mv: PieceMove) {
-        // If a rook moved from a corner, remove, if a king moved from its start position, remove both
-        if mv.piece_type == PieceType::Rook && mv.from == pos::A1 {
-            if self.true_active_color == Color::White {
-                self.castling_rights.white_queen_side = false;
-            } else {
-                self.castling_rights.black_queen_side = false;
-            }
-        } else if mv.piece_type == PieceType::Rook && mv.from == pos::H1 {
-            if self.true_active_color == Color::White {
-                self.castling_rights.white_king_side = false;
-            } else {
-                self.castling_rights.black_king_side = false;
+        // If a castling rook moved, remove its side, if the king moved from its start position,
+        // remove both
+        let color = self.true_active_color;
+
+        for side in CastlingSide::ALL {
+            let castling = self.castling(side);
+
+            if (mv.piece_type == PieceType::King && mv.from == castling.king_from)
+                || (mv.piece_type == PieceType::Rook && mv.from == castling.rook_from)
+            {
+                self.castling_rights.remove(color, side);
             }
-        } else if mv.piece_type == PieceType::King
-            && mv.from == pos::E1
-            && self.true_active_color == Color::White
-        {
-            self.castling_rights.white_king_side = false;
-            self.castling_rights.white_queen_side = false;
-        } else if mv.piece_type == PieceType::King
-            && mv.from == pos::D1
-            && self.true_active_color == Color::Black
-        {
-            self.castling_rights.black_king_side = false;
-            self.castling_rights.black_queen_side = false;
         }
     }
 
@@ -1234,7 +1274,7 @@ impl Position {
 
                 // Promotion
                 if let Some(_) = promoted_to {
-                    self.get_piece_at_mut(mv.to).unwrap().piece_type = PieceType::Pawn;
+                    self.unpromote_piece(mv.to)?;
                 }
 
                 // Movement
@@ -1250,28 +1290,13 @@ impl Position {
                         captured_pos,
                     ))?;
                     if let Some(captured_holding) = captured_holding {
-                        self.get_piece_at_mut(captured_pos).unwrap().holding =
-                            Some(captured_holding);
+                        self.set_holding(captured_pos, Some(captured_holding))?;
                     }
                 }
             }
-            MoveType::Castle { king: _, rook: _ } => {
-                self.move_piece(mv.to, mv.from)?;
-                if mv.from == Pos::xy(4, 7) {
-                    if mv.to == Pos::xy(6, 7) {
-                        self.move_piece(Pos::xy(5, 7), Pos::xy(7, 7))?;
-                    } else {
-                        self.move_piece(Pos::xy(3, 7), Pos::xy(0, 7))?;
-                    }
-                } else if mv.from == Pos::xy(3, 7) {
-                    if mv.to == Pos::xy(1, 7) {
-                        self.move_piece(Pos::xy(2, 7), Pos::xy(0, 7))?;
-                    } else {
-                        self.move_piece(Pos::xy(4, 7), Pos::xy(7, 7))?;
-                    }
-                } else {
-                    panic!("Illegal castle move");
-                }
+            MoveType::Castle { king, rook } => {
+                let castling = self.castling(Castling::side_of(mv.to));
+                self.move_castling_pieces(mv.to, king, castling.rook_to, rook)?;
             }
         }
 
@@ -1281,11 +1306,7 @@ impl Position {
 
         self.en_passant = restore_position.en_passant;
         self.castling_rights = restore_position.castling_rights;
-
-        *self.piece_maps.borrow_mut() = restore_position.piece_maps;
-        *self.attack_map.borrow_mut() = restore_position.attack_map;
-        *self.pseudolegal_moves.borrow_mut() = restore_position.pseudolegal_moves;
-        *self.all_legal_moves.borrow_mut() = restore_position.all_legal_moves;
+        self.halfmove_clock = restore_position.halfmove_clock;
 
         Ok(())
     }
@@ -1303,7 +1324,19 @@ impl Position {
             .get_piece_at_mut(pos)
             .ok_or_else(|| anyhow::anyhow!("No piece at pos"))?;
 
+        let before = piece.clone();
         piece.piece_type = promoted_to;
+        let after = piece.clone();
+
+        self.material.replace(&before, &after);
+        self.pieces_key ^= zobrist::piece_key(&before, self.true_active_color)
+            ^ zobrist::piece_key(&after, self.true_active_color);
+        self.piece_maps
+            .bitboard_mut(before.piece_type, before.color)
+            .clear(pos);
+        self.piece_maps
+            .bitboard_mut(after.piece_type, after.color)
+            .set(pos);
 
         Ok(())
     }
@@ -1313,87 +1346,75 @@ impl Position {
             .get_piece_at_mut(pos)
             .ok_or_else(|| anyhow::anyhow!("No piece at pos"))?;
 
+        let before = piece.clone();
         piece.piece_type = PieceType::Pawn;
+        let after = piece.clone();
+
+        self.material.replace(&before, &after);
+        self.pieces_key ^= zobrist::piece_key(&before, self.true_active_color)
+            ^ zobrist::piece_key(&after, self.true_active_color);
+        self.piece_maps
+            .bitboard_mut(before.piece_type, before.color)
+            .clear(pos);
+        self.piece_maps
+            .bitboard_mut(after.piece_type, after.color)
+            .set(pos);
 
         Ok(())
     }
 
-    /// Returns the number of attackers to a specific position.
-    pub fn count_attackers(&self, pos: Pos) -> u8 {
-        if let Some(attack_map) = self.attack_map.borrow().as_ref() {
-            return attack_map.get(pos) as u8;
-        }
-
-        let mut attack_map = SumBitboards::new();
+    /// Sets the piece held by the piece at a position.
+    fn set_holding(&mut self, pos: Pos, holding: Option<PieceType>) -> Result<(), anyhow::Error> {
+        let piece = self
+            .get_piece_at_mut(pos)
+            .ok_or_else(|| anyhow::anyhow!("No piece at pos"))?;
 
-        for piece in self.white_pieces.iter() {
-            if let Some(piece) = piece {
-                let legal_moves = piece.get_legal_moves(self, false);
-                attack_map.add(legal_moves);
-            }
-        }
+        let before = piece.clone();
+        piece.holding = holding;
+        let after = piece.clone();
 
-        *self.attack_map.borrow_mut() = Some(attack_map);
+        self.material.replace(&before, &after);
+        self.pieces_key ^= zobrist::piece_key(&before, self.true_active_color)
+            ^ zobrist::piece_key(&after, self.true_active_color);
 
-        self.attack_map.borrow().as_ref().unwrap().get(pos) as u8
+        Ok(())
     }
 
-    pub fn get_piece_maps(&self) -> PieceMaps {
-        if let Some(piece_maps) = self.piece_maps.borrow().as_ref() {
-            return piece_maps.clone();
-        }
+    /// Returns the number of white pieces attacking a specific position.
+    pub fn count_attackers(&self, pos: Pos) -> u8 {
+        self.attackers_to(pos, Color::White).count()
+    }
 
-        let piece_maps = self.calculate_piece_maps();
+    /// How many white pieces attack each square. Worth computing once when looking up many
+    /// squares.
+    pub fn attack_counts(&self) -> SumBitboards {
+        let mut attack_map = SumBitboards::new();
 
-        *self.piece_maps.borrow_mut() = Some(piece_maps.clone());
+        for piece in self.white_pieces.iter().flatten() {
+            attack_map.add(piece.get_legal_moves(self, false));
+        }
 
-        piece_maps
+        attack_map
     }
 
-    fn calculate_piece_maps(&self) -> PieceMaps {
-        let mut maps = PieceMaps {
-            white_pawns: Bitboard::new(),
-            white_bishops: Bitboard::new(),
-            white_knights: Bitboard::new(),
-            white_rooks: Bitboard::new(),
-            white_queens: Bitboard::new(),
-            white_king: Bitboard::new(),
-
-            black_pawns: Bitboard::new(),
-            black_bishops: Bitboard::new(),
-            black_knights: Bitboard::new(),
-            black_rooks: Bitboard::new(),
-            black_queens: Bitboard::new(),
-            black_king: Bitboard::new(),
-        };
+    pub fn get_piece_maps(&self) -> &PieceMaps {
+        &self.piece_maps
+    }
 
-        for piece in self.white_pieces.iter() {
-            if let Some(piece) = piece {
-                match piece.piece_type {
-                    PieceType::Pawn => maps.white_pawns.set(piece.position),
-                    PieceType::Bishop => maps.white_bishops.set(piece.position),
-                    PieceType::Knight => maps.white_knights.set(piece.position),
-                    PieceType::Rook => maps.white_rooks.set(piece.position),
-                    PieceType::Queen => maps.white_queens.set(piece.position),
-                    PieceType::King => maps.white_king.set(piece.position),
-                }
-            }
-        }
+    /// The squares of `color`'s pieces of a type. As everywhere in `Position`, white is the side
+    /// to move.
+    #[inline(always)]
+    pub fn pieces(&self, color: Color, piece_type: PieceType) -> Bitboard {
+        self.piece_maps.bitboard(piece_type, color)
+    }
 
-        for piece in self.black_pieces.iter() {
-            if let Some(piece) = piece {
-                match piece.piece_type {
-                    PieceType::Pawn => maps.black_pawns.set(piece.position),
-                    PieceType::Bishop => maps.black_bishops.set(piece.position),
-                    PieceType::Knight => maps.black_knights.set(piece.position),
-                    PieceType::Rook => maps.black_rooks.set(piece.position),
-                    PieceType::Queen => maps.black_queens.set(piece.position),
-                    PieceType::King => maps.black_king.set(piece.position),
-                }
-            }
+    /// The squares of all of `color`'s pieces.
+    #[inline(always)]
+    pub fn occupied(&self, color: Color) -> Bitboard {
+        match color {
+            Color::White => self.white_map,
+            Color::Black => self.black_map,
         }
-
-        maps
     }
 
     pub fn parse_from_fen(fen: &str) -> Result<Position, anyhow::Error> {
@@ -1437,6 +1458,33 @@ impl Position {
         Ok(position)
     }
 
+    /// Creates a new position by applying a sequence of moves in UCI long algebraic
+    /// notation (e.g. "e2e4", "e7e8q", or "e2e4Sf2" for rescue moves) to the starting position.
+    /// The moves are always from white's point of view on the board, whichever side is moving.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rescue_chess::{piece_move::GameType, Position};
+    ///
+    /// let position = Position::from_uci_moves(&["e2e4", "e7e5", "g1f3"], GameType::Classic).unwrap();
+    /// ```
+    pub fn from_uci_moves(moves: &[&str], game_type: GameType) -> Result<Position, anyhow::Error> {
+        let mut position = Position::start_position();
+
+        for &mv_str in moves {
+            let mv = if position.true_active_color == Color::White {
+                PieceMove::from_uci(&position, mv_str, game_type)?
+            } else {
+                PieceMove::from_uci_inverted(&position, mv_str, game_type)?
+            };
+
+            position.apply_move(mv)?;
+            position.invert();
+        }
+
+        Ok(position)
+    }
+
     pub fn to_hashable(&self) -> HashablePosition {
         let mut hashable = HashablePosition {
             castling_rights: self.castling_rights.clone(),
@@ -1482,6 +1530,19 @@ impl std::str::FromStr for Position {
     }
 }
 
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Position::parse_from_fen(&fen).map_err(serde::de::Error::custom)
+    }
+}
+
 impl std::convert::From<&'static str> for Position {
     fn from(s: &'static str) -> Self {
         Position::parse_from_fen(s).unwrap()
@@ -1494,12 +1555,70 @@ impl Default for Position {
     }
 }
 
+/// Positions to make and take back moves in, between them covering castling, en passant,
+/// promotions with and without captures, pins, checks, held pieces and black to move.
+#[cfg(test)]
+pub(crate) const MAKE_MOVE_TEST_POSITIONS: [&str; 10] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/pPpp1ppp/8/3Pp3/8/8/PPP2PPP/R3K2R w KQkq e6 0 1",
+    "1n2k3/P1P5/8/8/8/8/5p1p/4K1N1 w - - 0 1",
+    "4k3/1P6/8/8/8/8/6p1/4K3 b - - 0 1",
+    // En passant that would uncover a check along the rank
+    "8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1",
+    // Pins, and a king that can't retreat along the line of a check
+    "4k3/4r3/8/b7/8/2N5/4R3/4K3 w - - 0 1",
+    "4k3/8/8/8/8/8/4q3/4K3 w - - 0 1",
+    // Held pieces, some of which can be dropped to block
+    "4k3/4r3/8/8/8/2NxB5/8/4K3 w - - 0 1",
+    "4k3/3nxp4/8/8/3NxB4/2P5/8/4K3 w - - 0 1",
+    "4k3/8/8/3p4/8/8/8/1KxN6 w - - 0 1",
+];
+
+/// Makes and takes back every legal move two plies deep from each of the test positions, in
+/// both game types, calling `check` on every position reached, and again on each position after
+/// a move from it has been taken back.
+#[cfg(test)]
+pub(crate) fn check_make_and_unmake(check: impl Fn(&Position, GameType)) {
+    fn walk(
+        position: &mut Position,
+        game_type: GameType,
+        depth: u32,
+        check: &impl Fn(&Position, GameType),
+    ) {
+        check(position, game_type);
+
+        if depth == 0 {
+            return;
+        }
+
+        let mut moves = MoveList::new();
+        position.get_all_legal_moves(game_type, &mut moves).unwrap();
+
+        for mv in moves {
+            let restore = position.apply_move(mv).unwrap();
+            position.invert();
+            walk(position, game_type, depth - 1, check);
+            position.invert();
+            position.unapply_move(mv, restore).unwrap();
+
+            check(position, game_type);
+        }
+    }
+
+    for fen in MAKE_MOVE_TEST_POSITIONS {
+        for game_type in [GameType::Classic, GameType::Rescue] {
+            let mut position = Position::parse_from_fen(fen).unwrap();
+            walk(&mut position, game_type, 2, &check);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        piece_move::{GameType, MoveType},
+        piece_move::{GameType, MoveList, MoveType},
         position::RestorePosition,
-        PieceMove, PieceType, Pos, Position,
+        Bitboard, Color, PieceMove, PieceType, Pos, Position,
     };
 
     #[test]
@@ -1556,7 +1675,10 @@ mod tests {
 
         println!("{}", position.to_board_string());
 
-        let moves = position.get_all_legal_moves(GameType::Rescue).unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
 
         for mv in moves {
             println!("{}", mv);
@@ -1567,7 +1689,8 @@ mod tests {
     fn possible_moves_includes_rescue() {
         let position: Position = "8/8/8/8/8/8/8/3QK3 w - - 0 1".into();
 
-        let moves = position.get_all_moves_unchecked(GameType::Rescue);
+        let mut moves = MoveList::new();
+        position.get_all_moves_unchecked(GameType::Rescue, &mut moves);
 
         for mv in moves {
             println!("{}", mv);
@@ -1578,7 +1701,8 @@ mod tests {
     fn all_possible_moves_start_position() {
         let position: Position = Position::start_position();
 
-        let moves = position.get_all_moves_unchecked(GameType::Rescue);
+        let mut moves = MoveList::new();
+        position.get_all_moves_unchecked(GameType::Rescue, &mut moves);
 
         for mv in moves {
             println!("{}", mv);
@@ -1629,13 +1753,78 @@ mod tests {
         assert_eq!(position.to_fen(), expected.to_fen());
     }
 
+    #[test]
+    fn test_from_uci_moves() {
+        let position =
+            Position::from_uci_moves(&["e2e4", "e7e5", "g1f3"], GameType::Classic).unwrap();
+        let expected = Position::from_moves(&["e4", "e5", "Nf3"], GameType::Classic).unwrap();
+
+        assert_eq!(position, expected);
+        assert_eq!(position.true_active_color, Color::Black);
+    }
+
+    #[test]
+    fn test_from_uci_moves_castling_and_promotion() {
+        let position = Position::from_uci_moves(
+            &[
+                "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1", "f8c5",
+            ],
+            GameType::Classic,
+        )
+        .unwrap();
+        let expected = Position::from_moves(
+            &["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "Kg1", "Bc5"],
+            GameType::Classic,
+        )
+        .unwrap();
+        assert_eq!(position.to_fen(), expected.to_fen());
+
+        let position = Position::from_uci_moves(
+            &[
+                "h2h4", "g7g5", "h4g5", "f8g7", "g5g6", "a7a6", "g6h7", "a6a5", "h7g8n",
+            ],
+            GameType::Classic,
+        )
+        .unwrap();
+        assert!(position.is_piece_at(
+            Pos::from_algebraic("g8").unwrap().invert(),
+            &[PieceType::Knight],
+            Color::Black
+        ));
+    }
+
+    #[test]
+    fn test_from_uci_moves_with_rescue() {
+        let position =
+            Position::from_uci_moves(&["e2e2Sf2", "d7d5", "e1f2Sf1"], GameType::Rescue).unwrap();
+        let expected = Position::from_moves(&["e2Sf2", "d5", "Kf2Sf1"], GameType::Rescue).unwrap();
+
+        assert_eq!(position, expected);
+        assert_eq!(
+            position
+                .inverted()
+                .get_piece_at(Pos::from_algebraic("e2").unwrap())
+                .unwrap()
+                .holding,
+            Some(PieceType::Pawn)
+        );
+    }
+
+    #[test]
+    fn test_from_uci_moves_invalid() {
+        assert!(Position::from_uci_moves(&["e2e5"], GameType::Classic).is_err());
+        assert!(Position::from_uci_moves(&["e2"], GameType::Classic).is_err());
+        assert!(Position::from_uci_moves(&["e2e4", "z7z5"], GameType::Classic).is_err());
+        assert!(Position::from_uci_moves(&["e2e4x"], GameType::Classic).is_err());
+    }
+
     #[test]
     fn test_from_moves_multiple_moves() {
         let position = Position::from_moves(&["e4", "e5", "Nf3"], GameType::Rescue).unwrap();
 
         // Verify sequence of moves
         let expected = Position::parse_from_fen(
-            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 1 1",
         )
         .unwrap()
         .inverted();
@@ -1672,6 +1861,25 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_holders_are_not_rescued() {
+        // The pawn on e2 is holding a pawn, so neither the d2 pawn nor the king can rescue it
+        let position = Position::parse_from_fen("4k3/8/8/8/8/8/3PPxP3/4K3 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
+
+        let e2 = Pos::from_algebraic("e2").unwrap();
+        assert!(moves.iter().all(|mv| !matches!(
+            mv.move_type,
+            MoveType::Normal {
+                rescued_pos: Some(rescued_pos),
+                ..
+            } if rescued_pos == e2
+        )));
+    }
+
     #[test]
     fn test_from_moves_invalid_move() {
         // Try to make an invalid move
@@ -1946,10 +2154,7 @@ mod tests {
                     RestorePosition {
                         en_passant: None,
                         castling_rights: Default::default(),
-                        all_legal_moves: None,
-                        attack_map: None,
-                        piece_maps: None,
-                        pseudolegal_moves: None,
+                        halfmove_clock: 0,
                     }
                 )
                 .is_err(),
@@ -1966,7 +2171,10 @@ mod tests {
 
         println!("{}", position.to_board_string_with_rank_file(false));
 
-        let moves = position.get_all_legal_moves(GameType::Classic).unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
 
         for mv in moves.iter() {
             println!("{}", mv);
@@ -1985,15 +2193,17 @@ mod tests {
             Position::parse_from_fen("rnbq1bnr/ppp2kpp/8/1B1P4/8/8/PPPP1pPP/RNBQK2R w KQ - 0 1")
                 .unwrap();
 
-        let all_moves = position.get_all_legal_moves(GameType::Classic);
+        let mut all_moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut all_moves)
+            .unwrap();
 
-        for mv in all_moves.as_ref().unwrap().iter() {
+        for mv in all_moves.iter() {
             println!("{}", mv);
         }
 
         assert!(
             all_moves
-                .unwrap()
                 .iter()
                 .any(|mv| matches!(mv.move_type, MoveType::Castle { .. }))
                 == false
@@ -2021,7 +2231,10 @@ mod tests {
     fn en_passant_capture() {
         let position = Position::parse_from_fen("8/8/8/3pP3/8/8/8/8 w - d6 0 1").unwrap();
 
-        let moves = position.get_all_legal_moves(GameType::Classic).unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
 
         println!("{}", position.to_board_string_with_rank_file_holding());
 
@@ -2040,4 +2253,123 @@ mod tests {
                 promoted_to: None,
             }));
     }
+
+    #[test]
+    fn rescue_in_place_ends_en_passant() {
+        let mut position = Position::parse_from_fen("8/8/8/3pP3/4N3/8/8/8 w - d6 0 1").unwrap();
+
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
+
+        let rescue = *moves
+            .iter()
+            .find(|mv| mv.from == mv.to && mv.is_rescue_or_drop())
+            .unwrap();
+
+        position.apply_move(rescue).unwrap();
+
+        assert_eq!(position.en_passant, None);
+    }
+
+    #[test]
+    fn test_position_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Position>();
+    }
+
+    #[test]
+    fn test_piece_maps_kept_up_to_date() {
+        // Castling, en passant, promotions, and rescues and drops in Rescue games
+        let mut position =
+            Position::parse_from_fen("r3k2r/pPpp1ppp/8/3Pp3/8/8/PPP2PPP/R3K2R w KQkq e6 0 1")
+                .unwrap();
+
+        for game_type in [GameType::Classic, GameType::Rescue] {
+            let mut moves = MoveList::new();
+            position.get_all_legal_moves(game_type, &mut moves).unwrap();
+
+            for mv in moves {
+                let before = position.piece_maps;
+                let restore = position.apply_move(mv).unwrap();
+
+                assert_eq!(
+                    position.piece_maps,
+                    super::calculate_piece_maps(&position.white_pieces, &position.black_pieces),
+                    "{}",
+                    mv
+                );
+
+                position.unapply_move(mv, restore).unwrap();
+                assert_eq!(position.piece_maps, before, "{}", mv);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pieces_and_occupied() {
+        let mut position =
+            Position::parse_from_fen("4k3/3pp3/8/8/8/8/3PP3/RN2K3 w - - 0 1").unwrap();
+        let squares = |names: &[&str]| {
+            let squares: Vec<Pos> = names
+                .iter()
+                .map(|name| Pos::from_algebraic(name).unwrap())
+                .collect();
+            Bitboard::from_squares(&squares)
+        };
+
+        assert_eq!(
+            position.pieces(Color::White, PieceType::Pawn),
+            squares(&["d2", "e2"])
+        );
+        assert_eq!(
+            position.pieces(Color::White, PieceType::Rook),
+            squares(&["a1"])
+        );
+        assert_eq!(
+            position.pieces(Color::Black, PieceType::Queen),
+            Bitboard::new()
+        );
+        assert_eq!(
+            position.occupied(Color::White),
+            squares(&["a1", "b1", "d2", "e2", "e1"])
+        );
+
+        // Kept up to date as moves are made
+        let mv = PieceMove::from_algebraic(&position, "Nc3", GameType::Classic).unwrap();
+        position.apply_move(mv).unwrap();
+        assert_eq!(
+            position.pieces(Color::White, PieceType::Knight),
+            squares(&["c3"])
+        );
+        assert_eq!(
+            position.occupied(Color::White),
+            squares(&["a1", "c3", "d2", "e2", "e1"])
+        );
+        assert_eq!(
+            position.occupied(Color::Black),
+            squares(&["d7", "e7", "e8"])
+        );
+    }
+
+    #[test]
+    fn test_is_black_king_in_check() {
+        let in_check = |fen: &str| {
+            Position::parse_from_fen(fen)
+                .unwrap()
+                .is_black_king_in_check()
+                .unwrap()
+        };
+
+        assert!(!in_check("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(in_check("4k3/3P4/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(in_check("4k3/8/5N2/8/8/8/8/4K3 w - - 0 1"));
+        assert!(in_check("4k3/8/8/8/B7/8/8/4K3 w - - 0 1"));
+        assert!(in_check("4k3/8/8/8/4R3/8/8/4K3 w - - 0 1"));
+
+        // Blocked, and a pawn in front of the king doesn't attack it
+        assert!(!in_check("4k3/4p3/8/8/4R3/8/8/4K3 w - - 0 1"));
+        assert!(!in_check("4k3/4P3/8/8/8/8/8/4K3 w - - 0 1"));
+    }
 }
@@ -0,0 +1,222 @@
+//! Which pieces attack a square and which pieces are pinned, for check detection, castling,
+//! legal move generation, static exchange evaluation and the evaluation.
+
+use crate::{
+    piece::{bishop, king, knight, pawn, rook},
+    Bitboard, Color, Pos,
+};
+
+use super::Position;
+
+impl Position {
+    /// The squares of `color`'s pieces that attack `pos`, whatever stands on it.
+    pub fn attackers_to(&self, pos: Pos, color: Color) -> Bitboard {
+        self.attackers_to_occupied(pos, color, self.all_map)
+    }
+
+    /// The squares of `color`'s pieces that attack `pos` if only the squares in `occupied` had
+    /// pieces on them, e.g. to see through pieces that have moved away or been captured. Pieces
+    /// outside `occupied` don't attack.
+    pub fn attackers_to_occupied(&self, pos: Pos, color: Color, occupied: Bitboard) -> Bitboard {
+        let maps = &self.piece_maps;
+
+        // A pawn attacks `pos` from where a pawn of the other color on `pos` would attack
+        let (pawns, knights, bishops, rooks, queens, kings, pawn_attacks) = match color {
+            Color::White => (
+                maps.white_pawns,
+                maps.white_knights,
+                maps.white_bishops,
+                maps.white_rooks,
+                maps.white_queens,
+                maps.white_king,
+                *pawn::attack_map_black(pos),
+            ),
+            Color::Black => (
+                maps.black_pawns,
+                maps.black_knights,
+                maps.black_bishops,
+                maps.black_rooks,
+                maps.black_queens,
+                maps.black_king,
+                *pawn::attack_map(pos),
+            ),
+        };
+
+        ((pawns & pawn_attacks)
+            | (knights & *knight::attack_map(pos))
+            | ((bishops | queens) & bishop::magic::get_bishop_moves_magic(pos, occupied))
+            | ((rooks | queens) & rook::magic::get_rook_moves_magic(pos, occupied))
+            | (kings & *king::attack_map(pos)))
+            & occupied
+    }
+
+    /// Whether any of `color`'s pieces attack `pos`.
+    pub fn is_attacked_by(&self, pos: Pos, color: Color) -> bool {
+        self.attackers_to(pos, color) != Bitboard::new()
+    }
+
+    /// The pieces of `color` pinned to their own king, i.e. standing alone between it and an
+    /// enemy bishop, rook or queen on the same line.
+    pub fn pinned_pieces(&self, color: Color) -> Bitboard {
+        let mut pinned = Bitboard::new();
+        self.for_each_pin(color, |pos, _| pinned.set(pos));
+        pinned
+    }
+
+    /// The squares the piece on `pos` can move to without exposing its king: the squares
+    /// between the king and the piece pinning it, and the pinning piece itself. Every square if
+    /// the piece isn't pinned.
+    pub fn pin_ray(&self, pos: Pos) -> Bitboard {
+        let color = if self.white_map.get(pos) {
+            Color::White
+        } else if self.black_map.get(pos) {
+            Color::Black
+        } else {
+            return Bitboard(!0);
+        };
+
+        let mut ray = Bitboard(!0);
+        self.for_each_pin(color, |pinned, ray_of_pin| {
+            if pinned == pos {
+                ray = ray_of_pin;
+            }
+        });
+        ray
+    }
+
+    /// Calls `f` with each of `color`'s pinned pieces and its pin ray.
+    fn for_each_pin(&self, color: Color, mut f: impl FnMut(Pos, Bitboard)) {
+        let maps = &self.piece_maps;
+
+        let (king, own, enemy, diagonal_sliders, straight_sliders) = match color {
+            Color::White => (
+                self.white_king,
+                self.white_map,
+                self.black_map,
+                maps.black_bishops | maps.black_queens,
+                maps.black_rooks | maps.black_queens,
+            ),
+            Color::Black => (
+                self.black_king,
+                self.black_map,
+                self.white_map,
+                maps.white_bishops | maps.white_queens,
+                maps.white_rooks | maps.white_queens,
+            ),
+        };
+
+        let Some(king) = king else {
+            return;
+        };
+
+        // Sliders that would attack the king if only the king's own pieces were removed
+        let snipers = (diagonal_sliders & bishop::magic::get_bishop_moves_magic(king, enemy))
+            | (straight_sliders & rook::magic::get_rook_moves_magic(king, enemy));
+
+        for sniper in snipers {
+            let between = Bitboard::between(king, sniper);
+            let blockers = between & self.all_map;
+
+            if blockers.count() == 1 && blockers.intersects(own) {
+                let mut ray = between;
+                ray.set(sniper);
+                f(blockers.into_iter().next().unwrap(), ray);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(names: &[&str]) -> Bitboard {
+        let squares: Vec<Pos> = names
+            .iter()
+            .map(|name| Pos::from_algebraic(name).unwrap())
+            .collect();
+        Bitboard::from_squares(&squares)
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let position =
+            Position::parse_from_fen("3rk3/8/2n5/3p4/8/3Q1N2/8/3RK2B w - - 0 1").unwrap();
+        let d5 = Pos::from_algebraic("d5").unwrap();
+
+        // The rook behind the queen doesn't attack yet, and neither does the bishop behind the
+        // knight
+        assert_eq!(position.attackers_to(d5, Color::White), squares(&["d3"]));
+        assert_eq!(position.attackers_to(d5, Color::Black), squares(&["d8"]));
+
+        let e4 = Pos::from_algebraic("e4").unwrap();
+        assert_eq!(position.attackers_to(e4, Color::White), squares(&["d3"]));
+        assert_eq!(position.attackers_to(e4, Color::Black), squares(&["d5"]));
+
+        let e5 = Pos::from_algebraic("e5").unwrap();
+        assert_eq!(position.attackers_to(e5, Color::White), squares(&["f3"]));
+        assert_eq!(position.attackers_to(e5, Color::Black), squares(&["c6"]));
+    }
+
+    #[test]
+    fn test_attackers_to_occupied() {
+        let position =
+            Position::parse_from_fen("3rk3/8/2n5/3p4/8/3Q1N2/8/3RK2B w - - 0 1").unwrap();
+        let d5 = Pos::from_algebraic("d5").unwrap();
+
+        // With the queen gone, the rook behind it attacks, and the queen no longer does
+        let mut occupied = position.all_map;
+        occupied.clear(Pos::from_algebraic("d3").unwrap());
+
+        assert_eq!(
+            position.attackers_to_occupied(d5, Color::White, occupied),
+            squares(&["d1"])
+        );
+    }
+
+    #[test]
+    fn test_is_attacked_by() {
+        let position = Position::parse_from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+
+        assert!(position.is_attacked_by(Pos::from_algebraic("h8").unwrap(), Color::White));
+        assert!(position.is_attacked_by(Pos::from_algebraic("d7").unwrap(), Color::Black));
+        assert!(!position.is_attacked_by(Pos::from_algebraic("a8").unwrap(), Color::White));
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        // The e2 rook is pinned by the e7 rook and the c3 knight by the a5 bishop. The d7 pawn
+        // would be pinned by the a4 queen if the c6 knight weren't also in the way, and the
+        // black e7 rook is pinned by the white e2 rook
+        let position =
+            Position::parse_from_fen("4k3/3pr3/2n5/b7/Q7/2N5/4R3/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(position.pinned_pieces(Color::White), squares(&["c3", "e2"]));
+        assert_eq!(position.pinned_pieces(Color::Black), squares(&["e7"]));
+    }
+
+    #[test]
+    fn test_pin_ray() {
+        let position =
+            Position::parse_from_fen("4k3/3pr3/2n5/b7/Q7/2N5/4R3/4K3 w - - 0 1").unwrap();
+
+        let c3 = Pos::from_algebraic("c3").unwrap();
+        assert_eq!(position.pin_ray(c3), squares(&["d2", "c3", "b4", "a5"]));
+
+        let e2 = Pos::from_algebraic("e2").unwrap();
+        assert_eq!(
+            position.pin_ray(e2),
+            squares(&["e2", "e3", "e4", "e5", "e6", "e7"])
+        );
+
+        // Pieces that aren't pinned, and empty squares, can go anywhere
+        assert_eq!(
+            position.pin_ray(Pos::from_algebraic("c6").unwrap()),
+            Bitboard(!0)
+        );
+        assert_eq!(
+            position.pin_ray(Pos::from_algebraic("h4").unwrap()),
+            Bitboard(!0)
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Castling, in standard chess and in Chess960, where the king and rooks start on any files of
+//! the back rank. Wherever they start, castling puts them on the same squares as in standard
+//! chess: the king on the g-file and the rook on the f-file castling king side, and the king on
+//! the c-file and the rook on the d-file castling queen side.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitboard::Bitboard,
+    piece_move::{MoveList, MoveType, PieceMove},
+    Color, PieceType, Pos,
+};
+
+use super::{CastlingRights, Position};
+
+/// The number of Chess960 start positions.
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// The Chess960 start position that's the standard one.
+pub const STANDARD_CHESS960_POSITION: u16 = 518;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CastlingSide {
+    KingSide,
+    QueenSide,
+}
+
+impl CastlingSide {
+    pub const ALL: [CastlingSide; 2] = [CastlingSide::KingSide, CastlingSide::QueenSide];
+}
+
+/// The files the kings and the rooks they castle with start on. Both sides start on the same
+/// files, the standard ones except in Chess960.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CastlingFiles {
+    pub king: u8,
+    pub king_side_rook: u8,
+    pub queen_side_rook: u8,
+}
+
+impl CastlingFiles {
+    pub const STANDARD: CastlingFiles = CastlingFiles {
+        king: 4,
+        king_side_rook: 7,
+        queen_side_rook: 0,
+    };
+
+    pub fn is_standard(&self) -> bool {
+        *self == Self::STANDARD
+    }
+
+    pub fn rook(&self, side: CastlingSide) -> u8 {
+        match side {
+            CastlingSide::KingSide => self.king_side_rook,
+            CastlingSide::QueenSide => self.queen_side_rook,
+        }
+    }
+}
+
+impl Default for CastlingFiles {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl CastlingRights {
+    /// Whether `color` can still castle on `side`, if nothing is in the way.
+    pub fn has(&self, color: Color, side: CastlingSide) -> bool {
+        match (color, side) {
+            (Color::White, CastlingSide::KingSide) => self.white_king_side,
+            (Color::White, CastlingSide::QueenSide) => self.white_queen_side,
+            (Color::Black, CastlingSide::KingSide) => self.black_king_side,
+            (Color::Black, CastlingSide::QueenSide) => self.black_queen_side,
+        }
+    }
+
+    pub fn remove(&mut self, color: Color, side: CastlingSide) {
+        match (color, side) {
+            (Color::White, CastlingSide::KingSide) => self.white_king_side = false,
+            (Color::White, CastlingSide::QueenSide) => self.white_queen_side = false,
+            (Color::Black, CastlingSide::KingSide) => self.black_king_side = false,
+            (Color::Black, CastlingSide::QueenSide) => self.black_queen_side = false,
+        }
+    }
+}
+
+/// The squares castling moves the king and rook between, from the side to move's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Castling {
+    pub king_from: Pos,
+    pub king_to: Pos,
+    pub rook_from: Pos,
+    pub rook_to: Pos,
+}
+
+impl Castling {
+    /// Which side a castling move to `king_to` castles on. The board is seen from black's side
+    /// when black is to move, so its king castles king side to the b-file and queen side to
+    /// the f-file.
+    pub fn side_of(king_to: Pos) -> CastlingSide {
+        match king_to.get_col() {
+            1 | 6 => CastlingSide::KingSide,
+            _ => CastlingSide::QueenSide,
+        }
+    }
+}
+
+impl Position {
+    /// Whether the kings and rooks start anywhere but their standard files.
+    pub fn is_chess960(&self) -> bool {
+        !self.castling_rights.files.is_standard()
+    }
+
+    /// The squares castling on `side` moves the side to move's king and rook between.
+    pub fn castling(&self, side: CastlingSide) -> Castling {
+        let files = self.castling_rights.files;
+
+        let (king_to, rook_to) = match side {
+            CastlingSide::KingSide => (6, 5),
+            CastlingSide::QueenSide => (2, 3),
+        };
+
+        // Black's back rank is seen from the other side of the board, with the files reversed
+        let square = |file: u8| match self.true_active_color {
+            Color::White => Pos::xy(file, 7),
+            Color::Black => Pos::xy(7 - file, 7),
+        };
+
+        Castling {
+            king_from: square(files.king),
+            king_to: square(king_to),
+            rook_from: square(files.rook(side)),
+            rook_to: square(rook_to),
+        }
+    }
+
+    /// Whether the side to move can castle on `side`: it has the right to, nothing but the king
+    /// and rook stands between them and where they're going, and the king isn't in check and
+    /// doesn't pass through an attacked square. Whether the king ends up in check is left to
+    /// the legal move check, as for any other king move.
+    pub fn can_castle(&self, side: CastlingSide) -> bool {
+        if !self.castling_rights.has(self.true_active_color, side) {
+            return false;
+        }
+
+        let castling = self.castling(side);
+
+        if self.white_king != Some(castling.king_from)
+            || !self.piece_maps.white_rooks.get(castling.rook_from)
+        {
+            return false;
+        }
+
+        let mut occupied = self.all_map;
+        occupied.clear(castling.king_from);
+        occupied.clear(castling.rook_from);
+
+        let path = Bitboard::between(castling.king_from, castling.king_to)
+            | Bitboard::between(castling.rook_from, castling.rook_to)
+            | Bitboard::from_squares(&[castling.king_to, castling.rook_to]);
+
+        if occupied.intersects(path) {
+            return false;
+        }
+
+        let king_path =
+            Bitboard::between(castling.king_from, castling.king_to).with(castling.king_from);
+
+        !king_path
+            .into_iter()
+            .any(|pos| self.attackers_to_occupied(pos, Color::Black, occupied) != Bitboard::new())
+    }
+
+    /// Adds the side to move's castling moves to `moves`, without checking whether the king
+    /// ends up in check.
+    pub(super) fn add_castling_moves(&self, moves: &mut MoveList) {
+        for side in CastlingSide::ALL {
+            if self.can_castle(side) {
+                let castling = self.castling(side);
+
+                moves.push(PieceMove {
+                    from: castling.king_from,
+                    to: castling.king_to,
+                    piece_type: PieceType::King,
+                    move_type: MoveType::Castle {
+                        king: castling.king_from,
+                        rook: castling.rook_from,
+                    },
+                });
+            }
+        }
+    }
+
+    /// Moves the king and rook for castling. In Chess960 either can start where the other one
+    /// ends up, so the rook is taken off the board while the king moves.
+    pub(super) fn move_castling_pieces(
+        &mut self,
+        king_from: Pos,
+        king_to: Pos,
+        rook_from: Pos,
+        rook_to: Pos,
+    ) -> Result<(), anyhow::Error> {
+        let mut rook = self
+            .get_piece_at(rook_from)
+            .ok_or_else(|| anyhow::anyhow!("No rook to castle with on {}", rook_from))?
+            .clone();
+
+        self.remove_piece_at(rook_from)?;
+
+        if king_from != king_to {
+            self.move_piece(king_from, king_to)?;
+        }
+
+        rook.position = rook_to;
+        self.add_piece(rook)
+    }
+
+    /// The Chess960 start position numbered `index`, from 0 to 959, in the standard
+    /// (Scharnagl) numbering, where 518 is the standard start position.
+    pub fn chess960_start_position(index: u16) -> Result<Position, anyhow::Error> {
+        if index >= CHESS960_POSITIONS {
+            return Err(anyhow::anyhow!(
+                "Chess960 positions are numbered 0 to {}, not {}",
+                CHESS960_POSITIONS - 1,
+                index
+            ));
+        }
+
+        let back_rank = chess960_back_rank(index);
+        let black: String = back_rank.iter().collect();
+
+        Position::parse_from_fen(&format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+            black,
+            black.to_uppercase()
+        ))
+    }
+
+    /// A Chess960 start position picked at random.
+    pub fn random_chess960_start_position() -> Position {
+        let index = rand::thread_rng().gen_range(0..CHESS960_POSITIONS);
+        Self::chess960_start_position(index).unwrap()
+    }
+}
+
+/// The pieces of the back rank of the Chess960 start position numbered `index`, from the a-file
+/// to the h-file.
+fn chess960_back_rank(index: u16) -> [char; 8] {
+    // Where the knights go among the five squares left after the bishops and queen
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+
+    let mut rank = [' '; 8];
+    let index = index as usize;
+
+    rank[(index % 4) * 2 + 1] = 'b';
+    rank[(index / 4 % 4) * 2] = 'b';
+
+    let empty = |rank: &[char; 8]| (0..8).filter(|&file| rank[file] == ' ').collect::<Vec<_>>();
+
+    let queen = empty(&rank)[index / 16 % 6];
+    rank[queen] = 'q';
+
+    let (first, second) = KNIGHTS[index / 96];
+    let remaining = empty(&rank);
+    rank[remaining[first]] = 'n';
+    rank[remaining[second]] = 'n';
+
+    // The king goes between the rooks
+    for (file, piece) in empty(&rank).into_iter().zip(['r', 'k', 'r']) {
+        rank[file] = piece;
+    }
+
+    rank
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::piece_move::GameType;
+
+    use super::*;
+
+    #[test]
+    fn test_chess960_start_positions() {
+        assert_eq!(
+            Position::chess960_start_position(STANDARD_CHESS960_POSITION)
+                .unwrap()
+                .to_fen(),
+            Position::start_position().to_fen()
+        );
+        assert!(!Position::start_position().is_chess960());
+
+        assert_eq!(chess960_back_rank(0).iter().collect::<String>(), "bbqnnrkr");
+        assert_eq!(
+            chess960_back_rank(959).iter().collect::<String>(),
+            "rkrnnqbb"
+        );
+
+        let position = Position::chess960_start_position(0).unwrap();
+        assert!(position.is_chess960());
+        assert_eq!(
+            position.to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+
+        assert!(Position::chess960_start_position(960).is_err());
+    }
+
+    #[test]
+    fn test_every_chess960_position_is_valid() {
+        for index in 0..CHESS960_POSITIONS {
+            let rank = chess960_back_rank(index);
+            let files = |piece: char| (0..8).filter(move |&file| rank[file] == piece);
+
+            let bishops: Vec<_> = files('b').collect();
+            let rooks: Vec<_> = files('r').collect();
+            let king = files('k').next().unwrap();
+
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            assert!(rooks[0] < king && king < rooks[1]);
+            assert_eq!(files('q').count(), 1);
+            assert_eq!(files('n').count(), 2);
+        }
+    }
+
+    /// Plays `moves` in UCI notation from `fen` and returns the FEN reached.
+    fn play(fen: &str, moves: &[&str]) -> String {
+        let mut position = Position::parse_from_fen(fen).unwrap();
+
+        for uci in moves {
+            let mv = match position.true_active_color {
+                Color::White => PieceMove::from_uci(&position, uci, GameType::Classic),
+                Color::Black => PieceMove::from_uci_inverted(&position, uci, GameType::Classic),
+            }
+            .unwrap();
+
+            position.apply_move(mv).unwrap();
+            position.invert();
+        }
+
+        position.to_fen()
+    }
+
+    #[test]
+    fn test_standard_castling() {
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+
+        assert_eq!(
+            play(fen, &["e1g1", "e8c8"]),
+            "2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 1"
+        );
+        assert_eq!(
+            play(fen, &["e1c1", "e8g8"]),
+            "r4rk1/pppppppp/8/8/8/8/PPPPPPPP/2KR3R w - - 2 1"
+        );
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // The king castles onto its own rook's square, and the rook onto the king's
+        let fen = "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1";
+
+        assert_eq!(
+            play(fen, &["f1g1", "f8b8"]),
+            "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 2 1"
+        );
+        assert_eq!(
+            play(fen, &["f1b1", "f8g8"]),
+            "1r3rk1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 w - - 2 1"
+        );
+
+        // Castling is undone the same way
+        let mut position = Position::parse_from_fen(fen).unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
+
+        let castles: Vec<_> = moves
+            .iter()
+            .filter(|mv| matches!(mv.move_type, MoveType::Castle { .. }))
+            .copied()
+            .collect();
+        assert_eq!(castles.len(), 2);
+
+        for mv in castles {
+            let restore = position.apply_move(mv).unwrap();
+            position.unapply_move(mv, restore).unwrap();
+            assert_eq!(position.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_castling_through_check() {
+        // The rook on f8 attacks f1, which the king passes through castling king side
+        let position = Position::parse_from_fen("5r1k/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(!position.can_castle(CastlingSide::KingSide));
+        assert!(position.can_castle(CastlingSide::QueenSide));
+
+        // In Chess960 the rook can be what blocks the line to a square the king passes through
+        let position = Position::parse_from_fen("k7/8/8/8/8/8/8/r1RK4 w C - 0 1").unwrap();
+        assert!(!position.can_castle(CastlingSide::QueenSide));
+    }
+}
@@ -56,8 +56,16 @@ impl ExtendedPosition {
         // The rest of the string contains operations
         let operations_str = fields_iter.collect::<Vec<_>>().join(" ");
 
-        // Split operations by semicolons
-        for op_str in operations_str.split(';') {
+        // Split operations by semicolons, except those inside quoted strings
+        let mut in_string = false;
+        let op_strs = operations_str.split(|c| {
+            if c == '"' {
+                in_string = !in_string;
+            }
+            c == ';' && !in_string
+        });
+
+        for op_str in op_strs {
             let op_str = op_str.trim();
             if op_str.is_empty() {
                 continue;
@@ -150,6 +158,45 @@ impl ExtendedPosition {
         self.operations.get(opcode)
     }
 
+    /// The moves in SAN of an operation made of moves, such as `bm` or `am`.
+    fn moves(&self, opcode: &str) -> Vec<&str> {
+        self.get_operation(opcode)
+            .into_iter()
+            .flatten()
+            .filter_map(|operand| match operand {
+                EpdOperand::SanMove(mv) => Some(mv.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The best moves (`bm`): finding any of them solves the position.
+    pub fn best_moves(&self) -> Vec<&str> {
+        self.moves("bm")
+    }
+
+    /// The moves to avoid (`am`): any other move solves the position.
+    pub fn avoid_moves(&self) -> Vec<&str> {
+        self.moves("am")
+    }
+
+    /// The position's name in its test suite (`id`), e.g. "WAC.001".
+    pub fn id(&self) -> Option<&str> {
+        match self.get_operation("id")?.first()? {
+            EpdOperand::String(id) | EpdOperand::SanMove(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The number of moves the side to move can force mate in (`dm`).
+    pub fn direct_mate(&self) -> Option<u32> {
+        match self.get_operation("dm")?.first()? {
+            EpdOperand::Unsigned(moves) => Some(*moves),
+            EpdOperand::Integer(moves) => u32::try_from(*moves).ok(),
+            _ => None,
+        }
+    }
+
     /// Sets an operation with its operands
     pub fn set_operation(&mut self, opcode: String, operands: Vec<EpdOperand>) {
         self.operations.insert(opcode, operands);
@@ -192,6 +239,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_test_suite_opcodes() {
+        let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+        let pos = ExtendedPosition::parse_from_epd(epd).unwrap();
+        assert_eq!(pos.best_moves(), vec!["Qg6"]);
+        assert!(pos.avoid_moves().is_empty());
+        assert_eq!(pos.id(), Some("WAC.001"));
+        assert_eq!(pos.direct_mate(), None);
+
+        let epd = "8/8/8/8/8/8/8/K1k5 w - - am Kb1 Kb2; dm 3; c0 \"Not a; real mate\";";
+        let pos = ExtendedPosition::parse_from_epd(epd).unwrap();
+        assert_eq!(pos.avoid_moves(), vec!["Kb1", "Kb2"]);
+        assert_eq!(pos.direct_mate(), Some(3));
+        assert_eq!(pos.id(), None);
+        assert_eq!(
+            pos.get_operation("c0"),
+            Some(&vec![EpdOperand::String("Not a; real mate".to_string())])
+        );
+    }
+
     #[test]
     fn test_roundtrip() {
         let original = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - hmvc 0; fmvn 1;";
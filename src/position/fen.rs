@@ -1,9 +1,16 @@
-use crate::{position::CastlingRights, Color, Piece, PieceType, Pos, Position};
+use crate::{
+    position::{CastlingFiles, CastlingRights, CastlingSide},
+    Color, Piece, PieceType, Pos, Position,
+};
 
 /// Parses a position from FEN notation.
 ///
-/// Note that this can only parse when the active color is white, because
-/// the engine only supports playing as white.
+/// The engine always plays as white, so when black is to move the position is inverted, with
+/// `true_active_color` keeping track of whose turn it really is.
+///
+/// Castling rights can be given as `KQkq`, or for Chess960 as the files of the castling rooks
+/// (Shredder-FEN, e.g. `HAha`). `K` and `Q` mean the outermost rook on that side of the king,
+/// as in X-FEN.
 ///
 /// # Example
 ///
@@ -21,6 +28,7 @@ pub fn parse_position_from_fen(notation: &str) -> Result<Position, anyhow::Error
         white_queen_side: false,
         black_king_side: false,
         black_queen_side: false,
+        files: CastlingFiles::STANDARD,
     };
 
     let mut notation = notation.split_whitespace();
@@ -33,184 +41,33 @@ pub fn parse_position_from_fen(notation: &str) -> Result<Position, anyhow::Error
 
     for character in piece_placement.chars() {
         match character {
-            'P' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Pawn);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Pawn,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-
-                position += 1;
-            }
-            'N' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Knight);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Knight,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'B' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Bishop);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Bishop,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'R' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Rook);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Rook,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'Q' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Queen);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Queen,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'K' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::King);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::King,
-                        color: Color::White,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'p' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Pawn);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Pawn,
-                        color: Color::Black,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'n' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Knight);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Knight,
-                        color: Color::Black,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'b' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Bishop);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Bishop,
-                        color: Color::Black,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
-            }
-            'r' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Rook);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Rook,
-                        color: Color::Black,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
+            'x' => {
+                holding = true;
             }
-            'q' => {
-                if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::Queen);
-                    holding = false;
-                } else {
-                    pieces.push(Piece {
-                        piece_type: PieceType::Queen,
-                        color: Color::Black,
-                        position,
-                        holding: None,
-                    });
-                }
-                position += 1;
+            '1'..='8' => {
+                position += character.to_digit(10).unwrap() as u8;
             }
-            'k' => {
+            '/' => {}
+            _ => {
+                let (piece_type, color) = piece_from_fen(character)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid character in FEN notation"))?;
+
+                // A held piece is written straight after the piece holding it, and shares its square
                 if holding {
-                    pieces.last_mut().unwrap().holding = Some(PieceType::King);
+                    let holder = pieces.last_mut().ok_or_else(|| {
+                        anyhow::anyhow!("Held piece in FEN notation must follow a piece")
+                    })?;
+                    holder.holding = Some(piece_type);
                     holding = false;
                 } else {
                     pieces.push(Piece {
-                        piece_type: PieceType::King,
-                        color: Color::Black,
+                        piece_type,
+                        color,
                         position,
                         holding: None,
                     });
+                    position += 1;
                 }
-                position += 1;
-            }
-            'x' => {
-                holding = true;
-            }
-            '1'..='8' => {
-                position += character.to_digit(10).unwrap() as u8;
-            }
-            '/' => {}
-            _ => {
-                return Err(anyhow::anyhow!("Invalid character in FEN notation"));
             }
         }
     }
@@ -233,19 +90,8 @@ pub fn parse_position_from_fen(notation: &str) -> Result<Position, anyhow::Error
             anyhow::anyhow!("FEN notation must contain castling rights information")
         })?;
 
-        for character in castling.chars() {
-            match character {
-                'K' => castling_rights.white_king_side = true,
-                'Q' => castling_rights.white_queen_side = true,
-                'k' => castling_rights.black_king_side = true,
-                'q' => castling_rights.black_queen_side = true,
-                '-' => break,
-                _ => {
-                    return Err(anyhow::anyhow!(
-                        "Invalid character in castling rights information"
-                    ));
-                }
-            }
+        if castling != "-" {
+            castling_rights = parse_castling_rights(castling, &pieces)?;
         }
 
         let en_passant_str = notation
@@ -256,17 +102,14 @@ pub fn parse_position_from_fen(notation: &str) -> Result<Position, anyhow::Error
             en_passant = Some(Pos::from_algebraic(en_passant_str)?);
         }
 
-        let halfmove_clock_str = notation.next().ok_or_else(|| {
-            anyhow::anyhow!("FEN notation must contain halfmove clock information")
-        })?;
-
-        halfmove_clock = halfmove_clock_str.parse()?;
-
-        let fullmove_number_str = notation.next().ok_or_else(|| {
-            anyhow::anyhow!("FEN notation must contain fullmove number information")
-        })?;
+        // The move clocks are often left out by other tools, and default to a new game's
+        if let Some(halfmove_clock_str) = notation.next() {
+            halfmove_clock = halfmove_clock_str.parse()?;
+        }
 
-        fullmove_number = fullmove_number_str.parse()?;
+        if let Some(fullmove_number_str) = notation.next() {
+            fullmove_number = fullmove_number_str.parse()?;
+        }
     }
 
     let mut position = Position::new(
@@ -285,8 +128,135 @@ pub fn parse_position_from_fen(notation: &str) -> Result<Position, anyhow::Error
     }
 }
 
+/// Parses castling rights, working out which files the king and castling rooks start on from
+/// where they are on the back ranks. If they aren't there, the standard files are assumed, and
+/// the position fails validation.
+fn parse_castling_rights(
+    castling: &str,
+    pieces: &[Piece],
+) -> Result<CastlingRights, anyhow::Error> {
+    let mut rights = CastlingRights {
+        white_king_side: false,
+        white_queen_side: false,
+        black_king_side: false,
+        black_queen_side: false,
+        files: CastlingFiles::STANDARD,
+    };
+
+    let mut king_file = None;
+    let mut rook_files = [None, None];
+
+    for character in castling.chars() {
+        let color = if character.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        let row = match color {
+            Color::White => 7,
+            Color::Black => 0,
+        };
+
+        let files_of = |piece_type: PieceType| {
+            pieces
+                .iter()
+                .filter(move |p| {
+                    p.color == color && p.piece_type == piece_type && p.position.get_row() == row
+                })
+                .map(|p| p.position.get_col())
+        };
+
+        let king = files_of(PieceType::King).next();
+        let king_or_standard = king.unwrap_or(CastlingFiles::STANDARD.king);
+
+        let (side, rook) = match character.to_ascii_lowercase() {
+            'k' => (
+                CastlingSide::KingSide,
+                files_of(PieceType::Rook)
+                    .filter(|&f| f > king_or_standard)
+                    .max(),
+            ),
+            'q' => (
+                CastlingSide::QueenSide,
+                files_of(PieceType::Rook)
+                    .filter(|&f| f < king_or_standard)
+                    .min(),
+            ),
+            file @ 'a'..='h' => {
+                let file = file as u8 - b'a';
+                let side = if file > king_or_standard {
+                    CastlingSide::KingSide
+                } else {
+                    CastlingSide::QueenSide
+                };
+                (side, Some(file))
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Invalid character in castling rights information"
+                ));
+            }
+        };
+
+        match (color, side) {
+            (Color::White, CastlingSide::KingSide) => rights.white_king_side = true,
+            (Color::White, CastlingSide::QueenSide) => rights.white_queen_side = true,
+            (Color::Black, CastlingSide::KingSide) => rights.black_king_side = true,
+            (Color::Black, CastlingSide::QueenSide) => rights.black_queen_side = true,
+        }
+
+        // Both sides start with their pieces on the same files
+        if let (Some(king), Some(rook)) = (king, rook) {
+            let rook_file = &mut rook_files[side as usize];
+
+            if king_file.is_some_and(|f| f != king) || rook_file.is_some_and(|f| f != rook) {
+                return Err(anyhow::anyhow!(
+                    "Castling rights '{}' need white and black's kings and rooks on the same files",
+                    castling
+                ));
+            }
+
+            king_file = Some(king);
+            *rook_file = Some(rook);
+        }
+    }
+
+    let standard = CastlingFiles::STANDARD;
+    rights.files = CastlingFiles {
+        king: king_file.unwrap_or(standard.king),
+        king_side_rook: rook_files[CastlingSide::KingSide as usize]
+            .unwrap_or(standard.king_side_rook),
+        queen_side_rook: rook_files[CastlingSide::QueenSide as usize]
+            .unwrap_or(standard.queen_side_rook),
+    };
+
+    Ok(rights)
+}
+
+/// Converts a FEN piece letter to its piece type and color.
+pub(super) fn piece_from_fen(character: char) -> Option<(PieceType, Color)> {
+    let piece_type = match character.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+
+    let color = if character.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    Some((piece_type, color))
+}
+
 /// Converts a piece type and color to FEN notation.
-fn piece_type_to_fen(piece_type: PieceType, color: Color) -> char {
+pub(super) fn piece_type_to_fen(piece_type: PieceType, color: Color) -> char {
     match piece_type {
         PieceType::Pawn => match color {
             Color::White => 'P',
@@ -383,26 +353,34 @@ pub fn position_to_fen(position: &Position) -> String {
 
     fen.push(' ');
 
-    if position.castling_rights.white_king_side {
-        fen.push('K');
-    }
-
-    if position.castling_rights.white_queen_side {
-        fen.push('Q');
-    }
+    let rights = position.castling_rights;
+    let files = rights.files;
 
-    if position.castling_rights.black_king_side {
-        fen.push('k');
-    }
+    // Chess960 castling rights are written with the files of the rooks, as in Shredder-FEN
+    let (king_side, queen_side) = if files.is_standard() {
+        ('k', 'q')
+    } else {
+        (
+            (b'a' + files.king_side_rook) as char,
+            (b'a' + files.queen_side_rook) as char,
+        )
+    };
 
-    if position.castling_rights.black_queen_side {
-        fen.push('q');
+    for (has_right, right) in [
+        (rights.white_king_side, king_side.to_ascii_uppercase()),
+        (rights.white_queen_side, queen_side.to_ascii_uppercase()),
+        (rights.black_king_side, king_side),
+        (rights.black_queen_side, queen_side),
+    ] {
+        if has_right {
+            fen.push(right);
+        }
     }
 
-    if !position.castling_rights.white_king_side
-        && !position.castling_rights.white_queen_side
-        && !position.castling_rights.black_king_side
-        && !position.castling_rights.black_queen_side
+    if !rights.white_king_side
+        && !rights.white_queen_side
+        && !rights.black_king_side
+        && !rights.black_queen_side
     {
         fen.push('-');
     }
@@ -425,3 +403,82 @@ pub fn position_to_fen(position: &Position) -> String {
 
     fen
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::piece_move::GameType;
+
+    use super::*;
+
+    #[test]
+    fn test_black_to_move_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/8/8/8/8/8/8/4K2R b Kq - 3 17",
+            "4k3/3nxp4/8/8/3NxB4/2P5/8/4K3 b - - 0 1",
+        ] {
+            let position = parse_position_from_fen(fen).unwrap();
+            assert_eq!(position.true_active_color, Color::Black);
+            assert_eq!(position_to_fen(&position), fen);
+        }
+    }
+
+    #[test]
+    fn test_black_to_move_matches_played_moves() {
+        let played = Position::from_moves(&["e4"], GameType::Rescue).unwrap();
+        let parsed =
+            parse_position_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+
+        assert_eq!(parsed.to_hashable(), played.to_hashable());
+
+        // Black's pieces are white's from the engine's side of the board
+        let e7 = Pos::from_algebraic("e7").unwrap().invert();
+        let piece = parsed.get_piece_at(e7).unwrap();
+        assert_eq!(piece.piece_type, PieceType::Pawn);
+        assert_eq!(piece.color, Color::White);
+    }
+
+    #[test]
+    fn test_move_clocks_optional() {
+        let position = parse_position_from_fen("4k3/8/8/8/8/8/4P3/4K3 b - -").unwrap();
+        assert_eq!(position.true_active_color, Color::Black);
+        assert_eq!(
+            position_to_fen(&position),
+            "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_chess960_castling_rights() {
+        // Shredder-FEN gives the rook files, and round trips
+        let fen = "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1";
+        let position = parse_position_from_fen(fen).unwrap();
+        assert_eq!(
+            position.castling_rights.files,
+            CastlingFiles {
+                king: 5,
+                king_side_rook: 6,
+                queen_side_rook: 1,
+            }
+        );
+        assert_eq!(position_to_fen(&position), fen);
+
+        // X-FEN's KQkq are the outermost rooks
+        let position =
+            parse_position_from_fen("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(position_to_fen(&position), fen);
+
+        // Standard castling is still written as KQkq
+        let position = parse_position_from_fen("r3k2r/8/8/8/8/8/8/R3K2R b HAha - 0 1").unwrap();
+        assert!(position.castling_rights.files.is_standard());
+        assert_eq!(
+            position_to_fen(&position),
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"
+        );
+
+        // Both sides' pieces have to start on the same files
+        assert!(parse_position_from_fen("r3k1r1/8/8/8/8/8/8/R3K2R w KQkq - 0 1").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    piece_move::{GameType, MoveList},
+    Bitboard, Color, PieceType, Position,
+};
+
+/// The number of plies without a capture or pawn move after which the game is drawn.
+pub const FIFTY_MOVE_RULE_PLIES: u8 = 100;
+
+/// The number of times a position must occur for the game to be drawn by repetition.
+pub const REPETITION_COUNT: usize = 3;
+
+/// The state of a game at a given position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum GameStatus {
+    /// The game is still in progress.
+    Ongoing,
+
+    /// The side to move has been checkmated. Contains the color of the winner.
+    Checkmate(Color),
+
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+
+    /// The current position has occurred three times.
+    DrawByRepetition,
+
+    /// Fifty moves have been made by each side without a capture or pawn move.
+    DrawByFiftyMoves,
+
+    /// Neither side has enough material left to deliver checkmate.
+    DrawByInsufficientMaterial,
+
+    /// A player resigned. Contains the color of the winner. Never returned by
+    /// `Position::game_status`, since it doesn't depend on the position.
+    Resignation(Color),
+
+    /// The players agreed to a draw. Never returned by `Position::game_status`.
+    DrawByAgreement,
+
+    /// A player ran out of time. Contains the color of the winner. Never returned by
+    /// `Position::game_status`.
+    Timeout(Color),
+}
+
+impl GameStatus {
+    pub fn is_ongoing(&self) -> bool {
+        *self == GameStatus::Ongoing
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        !self.is_ongoing()
+    }
+
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self,
+            GameStatus::Stalemate
+                | GameStatus::DrawByRepetition
+                | GameStatus::DrawByFiftyMoves
+                | GameStatus::DrawByInsufficientMaterial
+                | GameStatus::DrawByAgreement
+        )
+    }
+
+    /// The winner of the game, if there is one.
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameStatus::Checkmate(winner)
+            | GameStatus::Resignation(winner)
+            | GameStatus::Timeout(winner) => Some(*winner),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "Ongoing"),
+            GameStatus::Checkmate(Color::White) => write!(f, "Checkmate, white wins"),
+            GameStatus::Checkmate(Color::Black) => write!(f, "Checkmate, black wins"),
+            GameStatus::Stalemate => write!(f, "Draw by stalemate"),
+            GameStatus::DrawByRepetition => write!(f, "Draw by threefold repetition"),
+            GameStatus::DrawByFiftyMoves => write!(f, "Draw by fifty-move rule"),
+            GameStatus::DrawByInsufficientMaterial => write!(f, "Draw by insufficient material"),
+            GameStatus::Resignation(Color::White) => write!(f, "Black resigned, white wins"),
+            GameStatus::Resignation(Color::Black) => write!(f, "White resigned, black wins"),
+            GameStatus::DrawByAgreement => write!(f, "Draw by agreement"),
+            GameStatus::Timeout(Color::White) => write!(f, "Black ran out of time, white wins"),
+            GameStatus::Timeout(Color::Black) => write!(f, "White ran out of time, black wins"),
+        }
+    }
+}
+
+impl Position {
+    /// Determines whether the game is over at this position, and how.
+    ///
+    /// `history` holds the Zobrist keys (see `Position::zobrist_key`) of every position in the
+    /// game so far, including the current position. Pass an empty slice to skip repetition detection.
+    ///
+    /// Checkmate and stalemate take priority over the draw rules.
+    pub fn game_status(
+        &self,
+        game_type: GameType,
+        history: &[u64],
+    ) -> Result<GameStatus, anyhow::Error> {
+        let mut moves = MoveList::new();
+        self.get_all_legal_moves(game_type, &mut moves)?;
+
+        if moves.is_empty() {
+            return if self.is_king_in_check()? {
+                Ok(GameStatus::Checkmate(self.true_active_color.invert()))
+            } else {
+                Ok(GameStatus::Stalemate)
+            };
+        }
+
+        if self.is_insufficient_material(game_type) {
+            return Ok(GameStatus::DrawByInsufficientMaterial);
+        }
+
+        if self.is_draw_by_fifty_moves() {
+            return Ok(GameStatus::DrawByFiftyMoves);
+        }
+
+        if !history.is_empty() && repetition_count(history, self.zobrist_key()) >= REPETITION_COUNT
+        {
+            return Ok(GameStatus::DrawByRepetition);
+        }
+
+        Ok(GameStatus::Ongoing)
+    }
+
+    /// Returns true if the current position has occurred at least three times in `history`.
+    pub fn is_threefold_repetition(&self, history: &[u64]) -> bool {
+        repetition_count(history, self.zobrist_key()) >= REPETITION_COUNT
+    }
+
+    /// Returns true if fifty moves have been made by each side without a capture or pawn move.
+    pub fn is_draw_by_fifty_moves(&self) -> bool {
+        self.halfmove_clock >= FIFTY_MOVE_RULE_PLIES
+    }
+
+    /// Returns true if neither side can possibly deliver checkmate: a lone king against
+    /// a king and at most one minor piece, or kings and bishops that all stand on squares of
+    /// the same color. In Rescue, any held piece could still be dropped, so the material is
+    /// never considered insufficient while a piece is being held.
+    pub fn is_insufficient_material(&self, game_type: GameType) -> bool {
+        let mut knights = 0;
+        let mut bishops = Bitboard::new();
+
+        for piece in self
+            .white_pieces
+            .iter()
+            .chain(self.black_pieces.iter())
+            .flatten()
+        {
+            if game_type.rules().counts_held_pieces() && piece.holding.is_some() {
+                return false;
+            }
+
+            match piece.piece_type {
+                PieceType::King => {}
+                PieceType::Knight => knights += 1,
+                PieceType::Bishop => bishops.set(piece.position),
+                PieceType::Pawn | PieceType::Rook | PieceType::Queen => return false,
+            }
+        }
+
+        match (knights, bishops.count()) {
+            (0, 0) | (1, 0) | (0, 1) => true,
+
+            // Bishops on one color can never attack the squares of the other color, so the
+            // king can't be mated however many there are
+            (0, _) => {
+                !bishops.intersects(Bitboard::light_squares())
+                    || !bishops.intersects(Bitboard::dark_squares())
+            }
+
+            _ => false,
+        }
+    }
+}
+
+/// The number of times the position with the given key appears in `history`.
+pub fn repetition_count(history: &[u64], key: u64) -> usize {
+    history.iter().filter(|&&k| k == key).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(fen: &str, game_type: GameType) -> GameStatus {
+        let position = Position::parse_from_fen(fen).unwrap();
+        position.game_status(game_type, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_ongoing() {
+        let position = Position::start_position();
+        assert_eq!(
+            position.game_status(GameType::Classic, &[]).unwrap(),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn test_checkmate() {
+        // Fool's mate, white to move and mated
+        let position =
+            Position::from_moves(&["f3", "e5", "g4", "Qh4#"], GameType::Classic).unwrap();
+        let status = position.game_status(GameType::Classic, &[]).unwrap();
+        assert_eq!(status, GameStatus::Checkmate(Color::Black));
+        assert_eq!(status.winner(), Some(Color::Black));
+        assert!(!status.is_draw());
+
+        // Scholar's mate, black to move and mated
+        let position = Position::from_moves(
+            &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"],
+            GameType::Classic,
+        )
+        .unwrap();
+        assert_eq!(
+            position.game_status(GameType::Classic, &[]).unwrap(),
+            GameStatus::Checkmate(Color::White)
+        );
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let status = status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", GameType::Classic);
+        assert_eq!(status, GameStatus::Stalemate);
+        assert!(status.is_draw());
+        assert_eq!(status.winner(), None);
+    }
+
+    #[test]
+    fn test_fifty_moves() {
+        assert_eq!(
+            status("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80", GameType::Classic),
+            GameStatus::DrawByFiftyMoves
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/4P3/4K3 w - - 99 80", GameType::Classic),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/4K3 w - - 0 1", GameType::Classic),
+            GameStatus::DrawByInsufficientMaterial
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/3BK3 w - - 0 1", GameType::Classic),
+            GameStatus::DrawByInsufficientMaterial
+        );
+        assert_eq!(
+            status("4kn2/8/8/8/8/8/8/4K3 w - - 0 1", GameType::Classic),
+            GameStatus::DrawByInsufficientMaterial
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/2NBK3 w - - 0 1", GameType::Classic),
+            GameStatus::Ongoing
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", GameType::Classic),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material_same_colored_bishops() {
+        // c1 and f8 are both dark squares
+        assert_eq!(
+            status("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", GameType::Classic),
+            GameStatus::DrawByInsufficientMaterial
+        );
+        assert_eq!(
+            status("4kb2/8/8/8/8/8/8/1B2K3 w - - 0 1", GameType::Classic),
+            GameStatus::Ongoing
+        );
+
+        // However many there are
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1", GameType::Classic),
+            GameStatus::DrawByInsufficientMaterial
+        );
+
+        // A knight can still help mate
+        assert_eq!(
+            status("4kb2/8/8/8/8/8/8/2BNK3 w - - 0 1", GameType::Classic),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material_with_held_piece() {
+        // The king holding a knight isn't a draw in Rescue, since the knight can be dropped
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/2NKxN3 w - - 0 1", GameType::Rescue),
+            GameStatus::Ongoing
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/4KxN3 w - - 0 1", GameType::Rescue),
+            GameStatus::Ongoing
+        );
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut position = Position::start_position();
+        let mut history = vec![position.zobrist_key()];
+
+        for _ in 0..2 {
+            for mv in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                let piece_move = if position.true_active_color == Color::White {
+                    crate::PieceMove::from_algebraic(&position, mv, GameType::Classic)
+                } else {
+                    crate::PieceMove::from_algebraic_inverted(&position, mv, GameType::Classic)
+                }
+                .unwrap();
+
+                position.apply_move(piece_move).unwrap();
+                position.invert();
+                history.push(position.zobrist_key());
+            }
+        }
+
+        assert_eq!(
+            position.game_status(GameType::Classic, &history).unwrap(),
+            GameStatus::DrawByRepetition
+        );
+        assert!(position.is_threefold_repetition(&history));
+        assert!(!position.is_threefold_repetition(&history[..history.len() - 1]));
+        assert_eq!(
+            position.game_status(GameType::Classic, &[]).unwrap(),
+            GameStatus::Ongoing
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pos::{self, Pos},
+    Color, PieceType, Position,
+};
+
+/// Material that one side gives up at the start of the game, so a stronger player can give
+/// odds to a weaker one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Handicap {
+    /// The piece is removed from the board: the queenside knight, bishop or rook, the queen,
+    /// or the f-pawn.
+    Odds(PieceType),
+
+    /// The same piece starts the game held by the king, so it has to be dropped before it
+    /// can play. A milder handicap than removing it, only meaningful in Rescue.
+    Held(PieceType),
+
+    /// The other side's king starts the game holding an extra piece of this type, ready to be
+    /// dropped. Only meaningful in Rescue.
+    Extra(PieceType),
+}
+
+impl Handicap {
+    pub fn piece_type(&self) -> PieceType {
+        match self {
+            Handicap::Odds(piece_type)
+            | Handicap::Held(piece_type)
+            | Handicap::Extra(piece_type) => *piece_type,
+        }
+    }
+
+    /// The square of the piece that the handicap takes away from `color`.
+    fn square(&self, color: Color) -> Result<Pos, anyhow::Error> {
+        let file = match self.piece_type() {
+            PieceType::Pawn => 'f',
+            PieceType::Knight => 'b',
+            PieceType::Bishop => 'c',
+            PieceType::Rook => 'a',
+            PieceType::Queen => 'd',
+            PieceType::King => return Err(anyhow::anyhow!("The king can't be given as odds")),
+        };
+
+        let rank = match (self.piece_type(), color) {
+            (PieceType::Pawn, Color::White) => '2',
+            (PieceType::Pawn, Color::Black) => '7',
+            (_, Color::White) => '1',
+            (_, Color::Black) => '8',
+        };
+
+        Pos::from_algebraic(&format!("{}{}", file, rank))
+    }
+}
+
+impl std::str::FromStr for Handicap {
+    type Err = anyhow::Error;
+
+    /// Parses a handicap like "knight" for knight odds, "held-queen" for a held queen or
+    /// "extra-rook" for an extra rook held by the other side's king.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+
+        let (handicap, piece): (fn(PieceType) -> Handicap, &str) =
+            if let Some(piece) = s.strip_prefix("held-") {
+                (Handicap::Held, piece)
+            } else if let Some(piece) = s.strip_prefix("extra-") {
+                (Handicap::Extra, piece)
+            } else {
+                (Handicap::Odds, s.as_str())
+            };
+
+        let piece_type = match piece {
+            "pawn" => PieceType::Pawn,
+            "knight" => PieceType::Knight,
+            "bishop" => PieceType::Bishop,
+            "rook" => PieceType::Rook,
+            "queen" => PieceType::Queen,
+            _ => return Err(anyhow::anyhow!("Invalid handicap: {}", s)),
+        };
+
+        Ok(handicap(piece_type))
+    }
+}
+
+impl Position {
+    /// The starting position with `color` giving the handicap. White is to move.
+    pub fn start_position_with_handicap(
+        handicap: Handicap,
+        color: Color,
+    ) -> Result<Position, anyhow::Error> {
+        let mut position = Position::start_position();
+        let square = handicap.square(color)?;
+
+        match handicap {
+            Handicap::Odds(_) => position.remove_piece_at(square)?,
+            Handicap::Held(_) => position.rescue_piece(king_square(color), square)?,
+            Handicap::Extra(piece_type) => {
+                let king = king_square(color.invert());
+                if !position.rescue_config.can_hold(PieceType::King, piece_type) {
+                    return Err(anyhow::anyhow!("The king can't hold a {:?}", piece_type));
+                }
+                position.set_holding(king, Some(piece_type))?;
+            }
+        }
+
+        // Without its rook, that side can't castle queenside
+        if matches!(handicap, Handicap::Odds(_) | Handicap::Held(_))
+            && handicap.piece_type() == PieceType::Rook
+        {
+            match color {
+                Color::White => position.castling_rights.white_queen_side = false,
+                Color::Black => position.castling_rights.black_queen_side = false,
+            }
+        }
+
+        Ok(position)
+    }
+}
+
+/// Where `color`'s king starts the game.
+fn king_square(color: Color) -> Pos {
+    match color {
+        Color::White => pos::E1,
+        Color::Black => pos::E8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handicap_fen(handicap: Handicap, color: Color) -> String {
+        let position = Position::start_position_with_handicap(handicap, color).unwrap();
+        assert!(position.validate().is_empty());
+        position.to_fen()
+    }
+
+    #[test]
+    fn test_odds() {
+        assert_eq!(
+            handicap_fen(Handicap::Odds(PieceType::Knight), Color::White),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            handicap_fen(Handicap::Odds(PieceType::Rook), Color::Black),
+            "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1"
+        );
+        assert_eq!(
+            handicap_fen(Handicap::Odds(PieceType::Queen), Color::Black),
+            "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            handicap_fen(Handicap::Odds(PieceType::Pawn), Color::White),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_held() {
+        assert_eq!(
+            handicap_fen(Handicap::Held(PieceType::Queen), Color::Black),
+            "rnb1kxqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            handicap_fen(Handicap::Held(PieceType::Rook), Color::White),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKxRBNR w Kkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_extra() {
+        assert_eq!(
+            handicap_fen(Handicap::Extra(PieceType::Knight), Color::White),
+            "rnbqkxnbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            handicap_fen(Handicap::Extra(PieceType::Rook), Color::Black),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKxRBNR w KQkq - 0 1"
+        );
+
+        // The extra piece counts for the side it was given to
+        let position =
+            Position::start_position_with_handicap(Handicap::Extra(PieceType::Queen), Color::White)
+                .unwrap();
+        assert!(position.material.black.material > position.material.white.material);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "knight".parse::<Handicap>().unwrap(),
+            Handicap::Odds(PieceType::Knight)
+        );
+        assert_eq!(
+            "Held-Queen".parse::<Handicap>().unwrap(),
+            Handicap::Held(PieceType::Queen)
+        );
+        assert_eq!(
+            "extra-rook".parse::<Handicap>().unwrap(),
+            Handicap::Extra(PieceType::Rook)
+        );
+        assert!("king".parse::<Handicap>().is_err());
+        assert!("held-".parse::<Handicap>().is_err());
+    }
+
+    #[test]
+    fn test_king_odds() {
+        assert!(Position::start_position_with_handicap(
+            Handicap::Odds(PieceType::King),
+            Color::White
+        )
+        .is_err());
+    }
+}
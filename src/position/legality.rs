@@ -0,0 +1,276 @@
+use crate::{
+    bitboard::Bitboard,
+    piece::{bishop, king, knight, pawn, rook, Color},
+    piece_move::{GameType, MoveList, MoveType, PieceMove},
+    pos::Pos,
+};
+
+use super::Position;
+
+/// What the side to move has to respect for its moves to be legal, worked out once per position
+/// from bitboards, so most pseudo-legal moves can be accepted or rejected without being made.
+///
+/// Pinned pieces may only move along the line through the king and the pinning piece, and in
+/// check every move other than a king move has to capture the checking piece or block it. Moves
+/// that change the board in other ways, like en passant captures, castling, rescues and drops,
+/// aren't decided here and have to be made to see whether they leave the king in check.
+pub(super) struct Legality {
+    king: Pos,
+
+    /// The squares a piece other than the king can move to without leaving the king in check:
+    /// every square out of check, the checking piece and the squares between it and the king
+    /// in check from one piece, and none in double check.
+    check_mask: Bitboard,
+
+    /// Pieces of the side to move pinned to their king.
+    pinned: Bitboard,
+
+    /// The squares the other side attacks, seeing through the king, so that a king can't step
+    /// back along the line of a slider checking it.
+    king_danger: Bitboard,
+}
+
+impl Legality {
+    pub fn new(position: &Position, king: Pos) -> Self {
+        let maps = position.get_piece_maps();
+        let occupied = position.all_map;
+
+        let diagonal_sliders = maps.black_bishops | maps.black_queens;
+        let straight_sliders = maps.black_rooks | maps.black_queens;
+
+        let checkers = position.attackers_to(king, Color::Black);
+
+        let check_mask = match checkers.count() {
+            0 => Bitboard(!0),
+            1 => {
+                let checker = checkers.into_iter().next().unwrap();
+                Bitboard::between(king, checker) | checkers
+            }
+            _ => Bitboard::new(),
+        };
+
+        let pinned = position.pinned_pieces(Color::White);
+
+        let mut without_king = occupied;
+        without_king.clear(king);
+
+        let mut king_danger = Bitboard::new();
+        for pos in maps.black_pawns {
+            king_danger = king_danger | *pawn::attack_map_black(pos);
+        }
+        for pos in maps.black_knights {
+            king_danger = king_danger | *knight::attack_map(pos);
+        }
+        for pos in diagonal_sliders {
+            king_danger = king_danger | bishop::magic::get_bishop_moves_magic(pos, without_king);
+        }
+        for pos in straight_sliders {
+            king_danger = king_danger | rook::magic::get_rook_moves_magic(pos, without_king);
+        }
+        for pos in maps.black_king {
+            king_danger = king_danger | *king::attack_map(pos);
+        }
+
+        Self {
+            king,
+            check_mask,
+            pinned,
+            king_danger,
+        }
+    }
+
+    /// Whether the move leaves the king out of check, or `None` if the move has to be made to
+    /// tell.
+    pub fn is_legal(&self, mv: &PieceMove) -> Option<bool> {
+        let dropped = match mv.move_type {
+            MoveType::Normal {
+                captured_pos,
+                rescued_pos,
+                dropped_pos,
+                ..
+            } => {
+                // En passant captures take a piece off a square other than the one moved to,
+                // and rescues take one off the board altogether, which can open a line to the
+                // king
+                if rescued_pos.is_some() || captured_pos.is_some_and(|pos| pos != mv.to) {
+                    return None;
+                }
+
+                dropped_pos.is_some()
+            }
+            MoveType::Castle { .. } => return None,
+        };
+
+        let legal = if mv.from == self.king {
+            !self.king_danger.get(mv.to)
+        } else {
+            self.check_mask.get(mv.to)
+                && (!self.pinned.get(mv.from) || Bitboard::line(self.king, mv.from).get(mv.to))
+        };
+
+        // A dropped piece can block a check or a pin, so a move that would otherwise be illegal
+        // may not be
+        if !legal && dropped {
+            return None;
+        }
+
+        Some(legal)
+    }
+}
+
+impl Position {
+    /// Whether white can play `mv`, checking only the moves of the piece it moves rather than
+    /// generating every legal move. The move has to match one of the piece's moves exactly,
+    /// including what it captures, rescues and drops.
+    pub fn is_legal(&self, game_type: GameType, mv: &PieceMove) -> Result<bool, anyhow::Error> {
+        let mut moves = MoveList::new();
+        self.get_piece_moves_unchecked(game_type, mv.from, &mut moves);
+
+        if !moves.contains(mv) {
+            return Ok(false);
+        }
+
+        if !self.rescue_config.drops_can_mate && self.is_mating_drop(game_type, mv)? {
+            return Ok(false);
+        }
+
+        let Some(white_king) = self.white_king else {
+            return Ok(true);
+        };
+
+        match Legality::new(self, white_king).is_legal(mv) {
+            Some(legal) => Ok(legal),
+            None => {
+                let mut position = self.clone();
+                position.apply_move(*mv)?;
+                Ok(!position.is_king_in_check()?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::{GameType, MoveList},
+        position::{check_make_and_unmake, MAKE_MOVE_TEST_POSITIONS},
+    };
+
+    use super::*;
+
+    /// The legal moves found by making every pseudo-legal move and looking for check.
+    fn legal_by_making_moves(position: &Position, game_type: GameType) -> MoveList {
+        let mut moves = MoveList::new();
+        position.get_all_moves_unchecked(game_type, &mut moves);
+
+        let mut position = position.clone();
+
+        moves
+            .into_iter()
+            .filter(|&mv| {
+                let restore = position.apply_move(mv).unwrap();
+                let in_check = position.is_king_in_check().unwrap();
+                position.unapply_move(mv, restore).unwrap();
+                !in_check
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_legal_moves_match_making_moves() {
+        check_make_and_unmake(|position, game_type| {
+            let mut moves = MoveList::new();
+            position.get_all_legal_moves(game_type, &mut moves).unwrap();
+
+            assert_eq!(
+                moves,
+                legal_by_making_moves(position, game_type),
+                "{}",
+                position.to_fen()
+            );
+        });
+    }
+
+    #[test]
+    fn test_is_legal_matches_legal_moves() {
+        for fen in MAKE_MOVE_TEST_POSITIONS {
+            for game_type in [GameType::Classic, GameType::Rescue] {
+                let position = Position::parse_from_fen(fen).unwrap();
+
+                let mut legal = MoveList::new();
+                position.get_all_legal_moves(game_type, &mut legal).unwrap();
+
+                let mut candidates = MoveList::new();
+                position.get_all_moves_unchecked(game_type, &mut candidates);
+
+                for mv in candidates {
+                    assert_eq!(
+                        position.is_legal(game_type, &mv).unwrap(),
+                        legal.contains(&mv),
+                        "{} in {}",
+                        mv,
+                        fen
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_legal_rejects_impossible_moves() {
+        let position = Position::start_position();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
+
+        // A rescue is only legal in Rescue chess
+        let rescue = *moves
+            .iter()
+            .find(|mv| {
+                matches!(
+                    mv.move_type,
+                    MoveType::Normal {
+                        rescued_pos: Some(_),
+                        ..
+                    }
+                )
+            })
+            .unwrap();
+        assert!(position.is_legal(GameType::Rescue, &rescue).unwrap());
+        assert!(!position.is_legal(GameType::Classic, &rescue).unwrap());
+
+        // A pawn can't move three squares, and nothing can move from an empty square
+        let e2e4 = PieceMove::from_algebraic(&position, "e4", GameType::Rescue).unwrap();
+        let e2e5 = PieceMove {
+            to: Pos::from_algebraic("e5").unwrap(),
+            ..e2e4
+        };
+        let e3e4 = PieceMove {
+            from: Pos::from_algebraic("e3").unwrap(),
+            ..e2e4
+        };
+        assert!(position.is_legal(GameType::Rescue, &e2e4).unwrap());
+        assert!(!position.is_legal(GameType::Rescue, &e2e5).unwrap());
+        assert!(!position.is_legal(GameType::Rescue, &e3e4).unwrap());
+    }
+
+    #[test]
+    fn test_pinned_piece_moves_along_pin() {
+        // The e2 rook is pinned by the e7 rook, and the c3 knight by the a5 bishop
+        let position = Position::parse_from_fen("4k3/4r3/8/b7/8/2N5/4R3/4K3 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+
+        assert!(!moves.iter().any(|mv| mv.from == Pos::xy(2, 5)));
+        assert!(moves
+            .iter()
+            .filter(|mv| mv.from == Pos::xy(4, 6))
+            .all(|mv| mv.to.is_col(4)));
+        assert!(moves
+            .iter()
+            .any(|mv| mv.from == Pos::xy(4, 6) && mv.to == Pos::xy(4, 1)));
+    }
+}
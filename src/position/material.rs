@@ -0,0 +1,163 @@
+use crate::{
+    evaluation::piece_value,
+    piece::{Color, Piece},
+    piece_move::GameType,
+    variant::SquareTable,
+};
+
+/// Running totals of the material and piece-square terms of the evaluation, kept up to date as
+/// pieces are added, moved, captured, promoted, rescued and dropped, so evaluation doesn't have
+/// to walk every piece at every leaf.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Material {
+    /// The totals of the side to move.
+    pub white: SideMaterial,
+
+    /// The totals of the side not to move.
+    pub black: SideMaterial,
+}
+
+/// The material and piece-square totals of one side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SideMaterial {
+    /// The values of the side's pieces, including the pieces they hold.
+    pub material: i32,
+
+    /// The square bonuses of the side's pieces in Classic games.
+    pub classic_squares: i32,
+
+    /// The square bonuses of the side's pieces in Rescue games.
+    pub rescue_squares: i32,
+}
+
+impl SideMaterial {
+    /// The material and square bonuses together, for the game type.
+    pub fn total(&self, game_type: GameType) -> i32 {
+        self.material
+            + match game_type.rules().square_table() {
+                SquareTable::Classic => self.classic_squares,
+                SquareTable::Rescue => self.rescue_squares,
+            }
+    }
+
+    fn add_squares(&mut self, piece: &Piece, sign: i32) {
+        // Square bonuses are from each side's own point of view, as if it were white
+        let piece = Piece {
+            color: Color::White,
+            position: match piece.color {
+                Color::White => piece.position,
+                Color::Black => piece.position.invert(),
+            },
+            ..piece.clone()
+        };
+
+        self.classic_squares += sign * piece.square_bonus(GameType::Classic);
+        self.rescue_squares += sign * piece.square_bonus(GameType::Rescue);
+    }
+}
+
+impl Material {
+    /// Totals up the pieces from scratch.
+    pub fn from_pieces<'a>(pieces: impl IntoIterator<Item = &'a Piece>) -> Self {
+        let mut material = Self::default();
+
+        for piece in pieces {
+            material.add(piece);
+        }
+
+        material
+    }
+
+    /// The material and square bonuses of the side to move less those of the other side.
+    pub fn score(&self, game_type: GameType) -> i32 {
+        self.white.total(game_type) - self.black.total(game_type)
+    }
+
+    fn side_mut(&mut self, color: Color) -> &mut SideMaterial {
+        match color {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+
+    /// Counts a piece, and the piece it holds, that has been put on the board.
+    pub fn add(&mut self, piece: &Piece) {
+        self.update(piece, 1);
+    }
+
+    /// Stops counting a piece, and the piece it holds, that has been taken off the board.
+    pub fn remove(&mut self, piece: &Piece) {
+        self.update(piece, -1);
+    }
+
+    fn update(&mut self, piece: &Piece, sign: i32) {
+        let side = self.side_mut(piece.color);
+
+        side.material += sign * piece_value(piece.piece_type);
+        side.add_squares(piece, sign);
+
+        if let Some(holding) = piece.holding {
+            side.material += sign * piece_value(holding);
+        }
+    }
+
+    /// Updates the totals for a piece that has changed in place: moved, promoted, or picked up
+    /// or dropped a piece.
+    pub fn replace(&mut self, before: &Piece, after: &Piece) {
+        self.remove(before);
+        self.add(after);
+    }
+
+    /// Swaps the sides, as when the position is inverted. Each side's totals are from its own
+    /// point of view, so they don't change.
+    pub fn invert(&mut self) {
+        std::mem::swap(&mut self.white, &mut self.black);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{position::check_make_and_unmake, PieceType, Pos, Position};
+
+    use super::*;
+
+    fn assert_up_to_date(position: &Position) {
+        let expected = Material::from_pieces(
+            position
+                .white_pieces
+                .iter()
+                .chain(position.black_pieces.iter())
+                .flatten(),
+        );
+
+        assert_eq!(position.material, expected, "{}", position.to_fen());
+    }
+
+    #[test]
+    fn test_material_kept_up_to_date() {
+        check_make_and_unmake(|position, _| assert_up_to_date(position));
+    }
+
+    #[test]
+    fn test_material_score() {
+        let position = Position::start_position();
+        assert_eq!(position.material.score(GameType::Classic), 0);
+        assert_eq!(position.material.white, position.material.black);
+
+        // A white knight held by the d4 knight
+        let position = Position::parse_from_fen("4k3/8/8/8/3NxN4/8/8/4K3 w - - 0 1").unwrap();
+        let knight = Piece {
+            holding: Some(PieceType::Knight),
+            ..Piece::new_white(PieceType::Knight, Pos::xy(3, 4))
+        };
+
+        assert_eq!(
+            position.material.score(GameType::Rescue),
+            2 * piece_value(PieceType::Knight) + knight.square_bonus(GameType::Rescue)
+        );
+        assert_eq!(
+            position.inverted().material.score(GameType::Rescue),
+            -position.material.score(GameType::Rescue)
+        );
+    }
+}
@@ -0,0 +1,853 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, Piece, PieceType, Pos, Position};
+
+use super::fen::{piece_from_fen, piece_type_to_fen};
+
+/// The maximum number of pieces each side can have, the size of `Position::white_pieces`.
+const MAX_PIECES: usize = 16;
+
+/// The fields of a FEN string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FenField {
+    PiecePlacement,
+    ActiveColor,
+    CastlingRights,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+/// The rules a FEN string or position can break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PositionRule {
+    /// A field is missing from the FEN string.
+    MissingField,
+
+    /// A field contains something that can't be parsed.
+    InvalidSyntax,
+
+    /// The piece placement doesn't have eight ranks of eight squares.
+    WrongSquareCount,
+
+    /// A side has more pieces than fit on the board at the start of a game.
+    TooManyPieces,
+
+    /// A side has more than eight pawns, counting held pawns.
+    TooManyPawns,
+
+    /// Each side must have exactly one king.
+    KingCount,
+
+    /// Pawns can't stand on the first or last rank.
+    PawnOnBackRank,
+
+    /// A piece is holding a piece it isn't allowed to hold, or a king.
+    InvalidHolding,
+
+    /// The side that just moved has left its king in check.
+    OpponentInCheck,
+
+    /// A castling right is given without the king and rook on their starting squares.
+    CastlingWithoutKingOrRook,
+
+    /// The en passant square isn't behind a pawn that could have just moved two squares.
+    InvalidEnPassant,
+}
+
+/// A problem with a FEN string or position. `square` is on the board as it's shown, with white
+/// at the bottom, whichever side is to move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionError {
+    pub field: FenField,
+    pub square: Option<Pos>,
+    pub rule: PositionRule,
+    pub message: String,
+}
+
+impl PositionError {
+    fn new(field: FenField, rule: PositionRule, message: impl Into<String>) -> Self {
+        PositionError {
+            field,
+            square: None,
+            rule,
+            message: message.into(),
+        }
+    }
+
+    fn at(field: FenField, square: Pos, rule: PositionRule, message: impl Into<String>) -> Self {
+        PositionError {
+            field,
+            square: Some(square),
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+/// A position set up piece by piece, e.g. in a board editor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionSetup {
+    /// The pieces, on the board as it's shown, with white at the bottom.
+    pub pieces: Vec<SetupPiece>,
+
+    pub to_move: Color,
+
+    /// The castling rights as FEN writes them, e.g. "KQkq", or "-" for none.
+    pub castling_rights: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetupPiece {
+    pub piece_type: PieceType,
+    pub color: Color,
+    pub position: Pos,
+
+    /// The piece of the same color it's holding, if any.
+    pub holding: Option<PieceType>,
+}
+
+impl PositionSetup {
+    /// The setup as a FEN string, with no en passant square and the clocks at the start of a
+    /// game. Fails only if two pieces are on the same square; everything else is left for
+    /// `Position::validate_fen` to check.
+    pub fn to_fen(&self) -> Result<String, Vec<PositionError>> {
+        let mut board: [[Option<&SetupPiece>; 8]; 8] = [[None; 8]; 8];
+        let mut errors = Vec::new();
+
+        for piece in &self.pieces {
+            let square =
+                &mut board[piece.position.get_row() as usize][piece.position.get_col() as usize];
+
+            if square.is_some() {
+                errors.push(PositionError::at(
+                    FenField::PiecePlacement,
+                    piece.position,
+                    PositionRule::InvalidSyntax,
+                    format!(
+                        "More than one piece is on {}",
+                        piece.position.to_algebraic()
+                    ),
+                ));
+            }
+            *square = Some(piece);
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let ranks: Vec<String> = board
+            .iter()
+            .map(|rank| {
+                let mut fen = String::new();
+                let mut empty = 0;
+
+                for square in rank {
+                    let Some(piece) = square else {
+                        empty += 1;
+                        continue;
+                    };
+
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+
+                    fen.push(piece_type_to_fen(piece.piece_type, piece.color));
+                    if let Some(holding) = piece.holding {
+                        fen.push('x');
+                        fen.push(piece_type_to_fen(holding, piece.color));
+                    }
+                }
+
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                }
+
+                fen
+            })
+            .collect();
+
+        let active_color = match self.to_move {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+
+        let castling_rights = match self.castling_rights.trim() {
+            "" => "-",
+            castling_rights => castling_rights,
+        };
+
+        Ok(format!(
+            "{} {} {} - 0 1",
+            ranks.join("/"),
+            active_color,
+            castling_rights
+        ))
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+/// Checks the syntax of the piece placement field. Rules about which pieces are where are
+/// left to `Position::validate`, once the FEN has been parsed.
+fn validate_piece_placement(placement: &str, errors: &mut Vec<PositionError>) {
+    let ranks: Vec<&str> = placement.split('/').collect();
+
+    if ranks.len() != 8 {
+        errors.push(PositionError::new(
+            FenField::PiecePlacement,
+            PositionRule::WrongSquareCount,
+            format!("Expected 8 ranks, found {}", ranks.len()),
+        ));
+    }
+
+    let mut piece_counts = [0; 2];
+
+    for (row, rank) in ranks.iter().enumerate().take(8) {
+        let rank_number = 8 - row;
+        let mut squares = 0u32;
+        let mut chars = rank.chars();
+        let mut last_piece: Option<(PieceType, Color)> = None;
+
+        while let Some(character) = chars.next() {
+            match character {
+                '1'..='8' => {
+                    squares += character.to_digit(10).unwrap();
+                    last_piece = None;
+                }
+                'x' => {
+                    let square = Pos::xy(squares.saturating_sub(1).min(7) as u8, row as u8);
+
+                    let Some((holder_type, holder_color)) = last_piece.take() else {
+                        errors.push(PositionError::new(
+                            FenField::PiecePlacement,
+                            PositionRule::InvalidSyntax,
+                            format!("'x' on rank {} must follow a piece", rank_number),
+                        ));
+                        continue;
+                    };
+
+                    match chars.next().and_then(piece_from_fen) {
+                        Some((_, held_color)) if held_color != holder_color => {
+                            errors.push(PositionError::at(
+                                FenField::PiecePlacement,
+                                square,
+                                PositionRule::InvalidHolding,
+                                format!(
+                                    "{} {} on {} is holding a piece of the other color",
+                                    color_name(holder_color),
+                                    holder_type,
+                                    square.to_algebraic()
+                                ),
+                            ));
+                        }
+                        Some(_) => {}
+                        None => {
+                            errors.push(PositionError::at(
+                                FenField::PiecePlacement,
+                                square,
+                                PositionRule::InvalidSyntax,
+                                format!(
+                                    "'x' on {} must be followed by the held piece",
+                                    square.to_algebraic()
+                                ),
+                            ));
+                        }
+                    }
+                }
+                _ => match piece_from_fen(character) {
+                    Some(piece) => {
+                        squares += 1;
+                        match piece.1 {
+                            Color::White => piece_counts[0] += 1,
+                            Color::Black => piece_counts[1] += 1,
+                        }
+                        last_piece = Some(piece);
+                    }
+                    None => {
+                        errors.push(PositionError::new(
+                            FenField::PiecePlacement,
+                            PositionRule::InvalidSyntax,
+                            format!("Invalid character '{}' on rank {}", character, rank_number),
+                        ));
+                        last_piece = None;
+                    }
+                },
+            }
+        }
+
+        if squares != 8 {
+            errors.push(PositionError::new(
+                FenField::PiecePlacement,
+                PositionRule::WrongSquareCount,
+                format!("Rank {} has {} squares, expected 8", rank_number, squares),
+            ));
+        }
+    }
+
+    for (color, count) in [
+        (Color::White, piece_counts[0]),
+        (Color::Black, piece_counts[1]),
+    ] {
+        if count > MAX_PIECES {
+            errors.push(PositionError::new(
+                FenField::PiecePlacement,
+                PositionRule::TooManyPieces,
+                format!(
+                    "{} has {} pieces, at most {} are allowed",
+                    color_name(color),
+                    count,
+                    MAX_PIECES
+                ),
+            ));
+        }
+    }
+}
+
+/// Checks the fields after the piece placement. They can all be left out, in which case white
+/// is to move with no castling rights, but if the active color is given the castling rights
+/// and en passant square must be too. The move clocks are often left out by other tools, and
+/// default to a new game's.
+fn validate_other_fields(fields: &[&str], errors: &mut Vec<PositionError>) {
+    let Some(&active_color) = fields.get(1) else {
+        return;
+    };
+
+    if active_color != "w" && active_color != "b" {
+        errors.push(PositionError::new(
+            FenField::ActiveColor,
+            PositionRule::InvalidSyntax,
+            format!("Active color must be 'w' or 'b', found '{}'", active_color),
+        ));
+    }
+
+    let required = [
+        (FenField::CastlingRights, "castling rights"),
+        (FenField::EnPassant, "en passant square"),
+    ];
+
+    for (i, (field, name)) in required.iter().enumerate() {
+        if fields.len() <= i + 2 {
+            errors.push(PositionError::new(
+                *field,
+                PositionRule::MissingField,
+                format!("FEN is missing the {}", name),
+            ));
+        }
+    }
+
+    if let Some(&castling) = fields.get(2) {
+        let valid = castling == "-"
+            || (castling
+                .chars()
+                .all(|c| "KQkq".contains(c) || matches!(c, 'A'..='H' | 'a'..='h'))
+                && castling
+                    .chars()
+                    .enumerate()
+                    .all(|(i, c)| !castling[i + 1..].contains(c)));
+
+        if !valid {
+            errors.push(PositionError::new(
+                FenField::CastlingRights,
+                PositionRule::InvalidSyntax,
+                format!(
+                    "Castling rights must be '-' or some of 'KQkq' or the rook files, found '{}'",
+                    castling
+                ),
+            ));
+        }
+    }
+
+    if let Some(&en_passant) = fields.get(3) {
+        if en_passant != "-" && Pos::from_algebraic(en_passant).is_err() {
+            errors.push(PositionError::new(
+                FenField::EnPassant,
+                PositionRule::InvalidSyntax,
+                format!(
+                    "En passant square must be '-' or a square, found '{}'",
+                    en_passant
+                ),
+            ));
+        }
+    }
+
+    if let Some(&halfmove_clock) = fields.get(4) {
+        if halfmove_clock.parse::<u8>().is_err() {
+            errors.push(PositionError::new(
+                FenField::HalfmoveClock,
+                PositionRule::InvalidSyntax,
+                format!(
+                    "Halfmove clock must be a number from 0 to 255, found '{}'",
+                    halfmove_clock
+                ),
+            ));
+        }
+    }
+
+    if let Some(&fullmove_number) = fields.get(5) {
+        if !matches!(fullmove_number.parse::<u16>(), Ok(n) if n > 0) {
+            errors.push(PositionError::new(
+                FenField::FullmoveNumber,
+                PositionRule::InvalidSyntax,
+                format!(
+                    "Fullmove number must be a positive number, found '{}'",
+                    fullmove_number
+                ),
+            ));
+        }
+    }
+}
+
+impl Position {
+    /// Parses a FEN string, checking it for every problem rather than stopping at the first.
+    /// Returns all of the problems found if the FEN can't be parsed or the position it
+    /// describes can't occur in a game.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rescue_chess::{position::PositionRule, Position};
+    ///
+    /// let errors = Position::validate_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err();
+    /// assert_eq!(errors[0].rule, PositionRule::KingCount);
+    /// ```
+    pub fn validate_fen(fen: &str) -> Result<Position, Vec<PositionError>> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let mut errors = Vec::new();
+
+        match fields.first() {
+            Some(placement) => validate_piece_placement(placement, &mut errors),
+            None => errors.push(PositionError::new(
+                FenField::PiecePlacement,
+                PositionRule::MissingField,
+                "FEN is empty",
+            )),
+        }
+
+        validate_other_fields(&fields, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let position = Position::parse_from_fen(fen).map_err(|e| {
+            vec![PositionError::new(
+                FenField::PiecePlacement,
+                PositionRule::InvalidSyntax,
+                e.to_string(),
+            )]
+        })?;
+
+        let errors = position.validate();
+        if errors.is_empty() {
+            Ok(position)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Sets up a position piece by piece, checking it for every problem the way
+    /// `validate_fen` does.
+    pub fn from_setup(setup: &PositionSetup) -> Result<Position, Vec<PositionError>> {
+        Position::validate_fen(&setup.to_fen()?)
+    }
+
+    /// Checks that the position could occur in a game: one king each, no pawns on the back
+    /// ranks, only pieces that can be held are held, the side that just moved isn't in check,
+    /// and the castling rights and en passant square match the board.
+    pub fn validate(&self) -> Vec<PositionError> {
+        let mut errors = Vec::new();
+
+        // Work on the board as it's shown, so squares in errors are where the user sees them
+        let board = match self.true_active_color {
+            Color::White => self.clone(),
+            Color::Black => self.inverted(),
+        };
+
+        let pieces: Vec<&Piece> = board
+            .white_pieces
+            .iter()
+            .chain(board.black_pieces.iter())
+            .flatten()
+            .collect();
+
+        for color in [Color::White, Color::Black] {
+            let kings = pieces
+                .iter()
+                .filter(|p| p.color == color && p.piece_type == PieceType::King)
+                .count();
+
+            if kings != 1 {
+                errors.push(PositionError::new(
+                    FenField::PiecePlacement,
+                    PositionRule::KingCount,
+                    format!("{} has {} kings, expected 1", color_name(color), kings),
+                ));
+            }
+
+            let pawns = pieces
+                .iter()
+                .filter(|p| p.color == color)
+                .map(|p| {
+                    (p.piece_type == PieceType::Pawn) as usize
+                        + (p.holding == Some(PieceType::Pawn)) as usize
+                })
+                .sum::<usize>();
+
+            if pawns > 8 {
+                errors.push(PositionError::new(
+                    FenField::PiecePlacement,
+                    PositionRule::TooManyPawns,
+                    format!(
+                        "{} has {} pawns, at most 8 are allowed",
+                        color_name(color),
+                        pawns
+                    ),
+                ));
+            }
+        }
+
+        for piece in &pieces {
+            let square = piece.position;
+
+            if piece.piece_type == PieceType::Pawn
+                && (square.get_row() == 0 || square.get_row() == 7)
+            {
+                errors.push(PositionError::at(
+                    FenField::PiecePlacement,
+                    square,
+                    PositionRule::PawnOnBackRank,
+                    format!(
+                        "{} pawn on {} is on the back rank",
+                        color_name(piece.color),
+                        square.to_algebraic()
+                    ),
+                ));
+            }
+
+            if let Some(held) = piece.holding {
+                if !board.rescue_config.can_hold(piece.piece_type, held) {
+                    errors.push(PositionError::at(
+                        FenField::PiecePlacement,
+                        square,
+                        PositionRule::InvalidHolding,
+                        format!(
+                            "{} {} on {} can't hold a {}",
+                            color_name(piece.color),
+                            piece.piece_type,
+                            square.to_algebraic(),
+                            held
+                        ),
+                    ));
+                }
+            }
+        }
+
+        validate_castling_rights(&board, &mut errors);
+        validate_en_passant(&board, self.true_active_color, &mut errors);
+
+        // Only check for check once there's exactly one king each to look at
+        if errors.iter().all(|e| e.rule != PositionRule::KingCount)
+            && self.is_black_king_in_check().unwrap_or(false)
+        {
+            let opponent = self.true_active_color.invert();
+            let king = pieces
+                .iter()
+                .find(|p| p.color == opponent && p.piece_type == PieceType::King)
+                .map(|p| p.position);
+
+            let message = format!(
+                "{} is in check but it's {}'s turn to move",
+                color_name(opponent),
+                color_name(self.true_active_color).to_lowercase()
+            );
+
+            errors.push(match king {
+                Some(king) => PositionError::at(
+                    FenField::ActiveColor,
+                    king,
+                    PositionRule::OpponentInCheck,
+                    message,
+                ),
+                None => PositionError::new(
+                    FenField::ActiveColor,
+                    PositionRule::OpponentInCheck,
+                    message,
+                ),
+            });
+        }
+
+        errors
+    }
+}
+
+fn validate_castling_rights(board: &Position, errors: &mut Vec<PositionError>) {
+    let rights = board.castling_rights;
+    let files = rights.files;
+
+    let rights = [
+        (
+            rights.white_king_side,
+            Color::White,
+            files.king_side_rook,
+            "K",
+        ),
+        (
+            rights.white_queen_side,
+            Color::White,
+            files.queen_side_rook,
+            "Q",
+        ),
+        (
+            rights.black_king_side,
+            Color::Black,
+            files.king_side_rook,
+            "k",
+        ),
+        (
+            rights.black_queen_side,
+            Color::Black,
+            files.queen_side_rook,
+            "q",
+        ),
+    ];
+
+    for (has_right, color, rook_file, right) in rights {
+        if !has_right {
+            continue;
+        }
+
+        let row = match color {
+            Color::White => 7,
+            Color::Black => 0,
+        };
+
+        for (square, piece_type) in [
+            (Pos::xy(files.king, row), PieceType::King),
+            (Pos::xy(rook_file, row), PieceType::Rook),
+        ] {
+            let in_place = board
+                .get_piece_at(square)
+                .is_some_and(|p| p.color == color && p.piece_type == piece_type);
+
+            if !in_place {
+                errors.push(PositionError::at(
+                    FenField::CastlingRights,
+                    square,
+                    PositionRule::CastlingWithoutKingOrRook,
+                    format!(
+                        "Castling right '{}' needs a {} {} on {}",
+                        right,
+                        color_name(color).to_lowercase(),
+                        piece_type,
+                        square.to_algebraic()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_en_passant(board: &Position, to_move: Color, errors: &mut Vec<PositionError>) {
+    let Some(square) = board.en_passant else {
+        return;
+    };
+
+    // The pawn that moved two squares belongs to the side that isn't to move. A white pawn
+    // passes rank 3 (row 5) and a black pawn passes rank 6 (row 2).
+    let (row, step) = match to_move {
+        Color::White => (2, 1),
+        Color::Black => (5, -1),
+    };
+    let mover = to_move.invert();
+
+    let (x, y) = square.get_xy();
+    let is_valid = y == row
+        && board.get_piece_at(square).is_none()
+        && board
+            .get_piece_at(Pos::xy(x, (row as i8 - step) as u8))
+            .is_none()
+        && board
+            .get_piece_at(Pos::xy(x, (row as i8 + step) as u8))
+            .is_some_and(|p| p.color == mover && p.piece_type == PieceType::Pawn);
+
+    if !is_valid {
+        errors.push(PositionError::at(
+            FenField::EnPassant,
+            square,
+            PositionRule::InvalidEnPassant,
+            format!(
+                "{} isn't behind a {} pawn that just moved two squares",
+                square.to_algebraic(),
+                color_name(mover).to_lowercase()
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(fen: &str) -> Vec<PositionRule> {
+        match Position::validate_fen(fen) {
+            Ok(_) => vec![],
+            Err(errors) => errors.into_iter().map(|e| e.rule).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_positions() {
+        assert!(
+            Position::validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .is_ok()
+        );
+        assert!(Position::validate_fen(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        )
+        .is_ok());
+        assert!(Position::validate_fen("4k3/8/8/8/8/8/8/4KxP3 w - - 0 1").is_ok());
+        assert!(Position::validate_fen("4k3/8/8/8/8/8/8/4K3").is_ok());
+
+        // Without the move clocks, or with only the halfmove clock
+        let position =
+            Position::validate_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3")
+                .unwrap();
+        assert_eq!(position.halfmove_clock, 0);
+        assert_eq!(position.fullmove_number, 1);
+        assert!(Position::validate_fen("4k3/8/8/8/8/8/8/4K3 w - - 5").is_ok());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let errors = Position::validate_fen("4k3/8/8/8/8/8/8/4K2 w KK e9 x 0").unwrap_err();
+        let fields: Vec<FenField> = errors.iter().map(|e| e.field).collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                FenField::PiecePlacement,
+                FenField::CastlingRights,
+                FenField::EnPassant,
+                FenField::HalfmoveClock,
+                FenField::FullmoveNumber,
+            ]
+        );
+        assert_eq!(errors[0].rule, PositionRule::WrongSquareCount);
+
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/4K3 w"),
+            vec![PositionRule::MissingField, PositionRule::MissingField]
+        );
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/4K3 w -"),
+            vec![PositionRule::MissingField]
+        );
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/4Kxp3 w - - 0 1"),
+            vec![PositionRule::InvalidHolding]
+        );
+    }
+
+    #[test]
+    fn test_board_rules() {
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/8 w - - 0 1"),
+            vec![PositionRule::KingCount]
+        );
+
+        let errors = Position::validate_fen("4k2P/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err();
+        assert_eq!(errors[0].rule, PositionRule::PawnOnBackRank);
+        assert_eq!(errors[0].square, Some(Pos::from_algebraic("h8").unwrap()));
+
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/3BxQK3 w - - 0 1"),
+            vec![PositionRule::InvalidHolding]
+        );
+
+        // White to move with black in check
+        let errors = Position::validate_fen("4k3/8/8/8/8/8/8/4KR2 w - - 0 1");
+        assert!(errors.is_ok());
+        let errors = Position::validate_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").unwrap_err();
+        assert_eq!(errors[0].rule, PositionRule::OpponentInCheck);
+        assert_eq!(errors[0].square, Some(Pos::from_algebraic("e8").unwrap()));
+
+        // Black to move with white in check
+        let errors = Position::validate_fen("4r1k1/8/8/8/8/8/8/4K3 b - - 0 1").unwrap_err();
+        assert_eq!(errors[0].rule, PositionRule::OpponentInCheck);
+        assert_eq!(errors[0].square, Some(Pos::from_algebraic("e1").unwrap()));
+    }
+
+    #[test]
+    fn test_castling_and_en_passant() {
+        let errors = Position::validate_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").unwrap_err();
+        assert_eq!(errors[0].rule, PositionRule::CastlingWithoutKingOrRook);
+        assert_eq!(errors[0].square, Some(Pos::from_algebraic("h1").unwrap()));
+
+        assert_eq!(
+            rules("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"),
+            vec![PositionRule::InvalidEnPassant]
+        );
+        assert!(Position::validate_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").is_ok());
+    }
+
+    #[test]
+    fn test_from_setup() {
+        let piece = |piece_type, color, square, holding| SetupPiece {
+            piece_type,
+            color,
+            position: Pos::from_algebraic(square).unwrap(),
+            holding,
+        };
+
+        let mut setup = PositionSetup {
+            pieces: vec![
+                piece(PieceType::King, Color::White, "e1", Some(PieceType::Pawn)),
+                piece(PieceType::Rook, Color::White, "h1", None),
+                piece(PieceType::King, Color::Black, "e8", None),
+            ],
+            to_move: Color::Black,
+            castling_rights: "K".to_string(),
+        };
+
+        assert_eq!(setup.to_fen().unwrap(), "4k3/8/8/8/8/8/8/4KxP2R b K - 0 1");
+        let position = Position::from_setup(&setup).unwrap();
+        assert_eq!(position.true_active_color, Color::Black);
+
+        // Kings can't be held, pawns can't stand on the back rank, and every problem is reported
+        setup.pieces.push(piece(
+            PieceType::Queen,
+            Color::Black,
+            "d8",
+            Some(PieceType::King),
+        ));
+        setup
+            .pieces
+            .push(piece(PieceType::Pawn, Color::White, "a8", None));
+        let errors = Position::from_setup(&setup).unwrap_err();
+        let rules: Vec<PositionRule> = errors.iter().map(|e| e.rule).collect();
+        assert!(rules.contains(&PositionRule::InvalidHolding));
+        assert!(rules.contains(&PositionRule::PawnOnBackRank));
+
+        setup
+            .pieces
+            .push(piece(PieceType::Knight, Color::Black, "a8", None));
+        let errors = setup.to_fen().unwrap_err();
+        assert_eq!(errors[0].square, Pos::from_algebraic("a8").ok());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{Color, Piece, PieceType, Position};
+
+/// Random keys for each part of a position, generated at compile time so that
+/// keys are stable across runs.
+struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    holding: [[[u64; 64]; 6]; 2],
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+    black_to_move: u64,
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[[0; 64]; 6]; 2],
+        holding: [[[0; 64]; 6]; 2],
+        castling: [0; 4],
+        en_passant_file: [0; 8],
+        black_to_move: 0,
+    };
+
+    let mut state = 0x5265736375654368; // "RescueCh"
+    let mut key;
+
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 6 {
+            let mut square = 0;
+            while square < 64 {
+                (state, key) = splitmix64(state);
+                keys.pieces[color][piece][square] = key;
+                (state, key) = splitmix64(state);
+                keys.holding[color][piece][square] = key;
+                square += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+
+    let mut i = 0;
+    while i < 4 {
+        (state, key) = splitmix64(state);
+        keys.castling[i] = key;
+        i += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        (state, key) = splitmix64(state);
+        keys.en_passant_file[file] = key;
+        file += 1;
+    }
+
+    (_, key) = splitmix64(state);
+    keys.black_to_move = key;
+
+    keys
+}
+
+static KEYS: ZobristKeys = generate_keys();
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+/// The key of a piece, and the piece it holds, as it stands on the real board. The pieces are
+/// from the point of view of `true_active_color`, as in a `Position`.
+pub(crate) fn piece_key(piece: &Piece, true_active_color: Color) -> u64 {
+    let (color, square) = match true_active_color {
+        Color::White => (piece.color, piece.position),
+        Color::Black => (piece.color.invert(), piece.position.invert()),
+    };
+
+    let color = color_index(color);
+    let square = square.0 as usize;
+
+    let mut key = KEYS.pieces[color][piece_index(piece.piece_type)][square];
+
+    if let Some(holding) = piece.holding {
+        key ^= KEYS.holding[color][piece_index(holding)][square];
+    }
+
+    key
+}
+
+/// The key of all of the pieces, worked out from scratch.
+pub(crate) fn pieces_key<'a>(
+    pieces: impl IntoIterator<Item = &'a Piece>,
+    true_active_color: Color,
+) -> u64 {
+    pieces
+        .into_iter()
+        .fold(0, |key, piece| key ^ piece_key(piece, true_active_color))
+}
+
+impl Position {
+    /// The Zobrist hash of the position as it stands on the real board, including held pieces
+    /// and the side to move. A position gets the same key whether or not it is currently
+    /// inverted for black to move, so keys can be compared across a game.
+    ///
+    /// The pieces' part of the key is kept up to date as they change, in `pieces_key`, so this
+    /// only adds in the castling rights, en passant square and side to move.
+    pub fn zobrist_key(&self) -> u64 {
+        let inverted = self.true_active_color == Color::Black;
+        let mut key = self.pieces_key;
+
+        let castling = [
+            self.castling_rights.white_king_side,
+            self.castling_rights.white_queen_side,
+            self.castling_rights.black_king_side,
+            self.castling_rights.black_queen_side,
+        ];
+        for (i, has_right) in castling.into_iter().enumerate() {
+            if has_right {
+                key ^= KEYS.castling[i];
+            }
+        }
+
+        if let Some(en_passant) = self.en_passant {
+            let en_passant = if inverted {
+                en_passant.invert()
+            } else {
+                en_passant
+            };
+            key ^= KEYS.en_passant_file[en_passant.get_col() as usize];
+        }
+
+        if inverted {
+            key ^= KEYS.black_to_move;
+        }
+
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{piece_move::GameType, position::check_make_and_unmake, Position};
+
+    use super::*;
+
+    fn assert_up_to_date(position: &Position) {
+        let expected = pieces_key(
+            position
+                .white_pieces
+                .iter()
+                .chain(position.black_pieces.iter())
+                .flatten(),
+            position.true_active_color,
+        );
+
+        assert_eq!(position.pieces_key, expected, "{}", position.to_fen());
+    }
+
+    #[test]
+    fn test_key_kept_up_to_date() {
+        check_make_and_unmake(|position, _| assert_up_to_date(position));
+    }
+
+    #[test]
+    fn test_same_position_same_key() {
+        let a = Position::from_moves(&["e3", "e5", "Nf3"], GameType::Classic).unwrap();
+        let b = Position::from_moves(&["Nf3", "e5", "e3"], GameType::Classic).unwrap();
+        assert_eq!(a.zobrist_key(), b.zobrist_key());
+    }
+
+    #[test]
+    fn test_key_independent_of_inversion() {
+        let position = Position::from_moves(&["e4"], GameType::Classic).unwrap();
+        let fen = position.to_fen();
+        let parsed = Position::parse_from_fen(&fen).unwrap();
+
+        assert_eq!(position.zobrist_key(), parsed.zobrist_key());
+        assert_ne!(
+            position.zobrist_key(),
+            Position::start_position().zobrist_key()
+        );
+    }
+
+    #[test]
+    fn test_side_to_move_changes_key() {
+        let white = Position::parse_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Position::parse_from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(white.zobrist_key(), black.zobrist_key());
+    }
+
+    #[test]
+    fn test_holding_changes_key() {
+        let holding = Position::parse_from_fen("4k3/8/8/8/8/8/8/4KxP3 w - - 0 1").unwrap();
+        let not_holding = Position::parse_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_ne!(holding.zobrist_key(), not_holding.zobrist_key());
+    }
+
+    #[test]
+    fn test_castling_and_en_passant_change_key() {
+        let castling = Position::parse_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let no_castling = Position::parse_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        assert_ne!(castling.zobrist_key(), no_castling.zobrist_key());
+
+        let en_passant = Position::parse_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let no_en_passant = Position::parse_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_ne!(en_passant.zobrist_key(), no_en_passant.zobrist_key());
+    }
+}
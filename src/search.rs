@@ -1,8 +1,20 @@
 pub mod alpha_beta;
+pub mod backend;
+pub mod eval_cache;
 pub mod game_state;
 pub mod history;
 pub mod iterative_deepening;
 pub mod killer_moves;
+pub mod mcts;
+pub mod params_builder;
 pub mod quiescence_search;
+pub mod score;
 pub mod search_results;
+pub mod statistics;
+pub mod stepped;
+pub mod strength;
+#[cfg(feature = "syzygy")]
+pub mod tablebase;
+pub mod time_control;
 pub mod transposition_table;
+pub mod weak;
@@ -1,15 +1,21 @@
+use std::sync::atomic::Ordering;
+
 use tracing::trace;
 
 use crate::{
     evaluation::{ordering::order_moves, piece_value},
     features::{EvaluationWeights, Features},
-    piece_move::GameType,
-    Color, PieceMove, PieceType, Position,
+    piece_move::{GameType, MoveList},
+    position::{repetition_count, GameStatus, REPETITION_COUNT},
+    Bitboard, Color, PieceMove, PieceType, Position,
 };
 
 use super::{
+    backend::SearchAlgorithm,
+    eval_cache::cached_evaluation,
     quiescence_search::quiescence_search,
-    search_results::{SearchResults, SearchState},
+    score::{is_mate_score, mated_in, MATE_THRESHOLD},
+    search_results::{SearchInfo, SearchResults, SearchState},
     transposition_table::{NodeType, TranspositionTableEntry},
 };
 
@@ -19,7 +25,6 @@ pub struct SearchResult {
     pub score: i32,
 }
 
-pub const CHECKMATE: i32 = -1000000;
 pub const STALEMATE: i32 = 0;
 
 #[derive(Debug, Clone)]
@@ -41,6 +46,9 @@ pub struct SearchParams {
 
     pub features: Features,
     pub weights: EvaluationWeights,
+
+    /// The search algorithm that `backend::search` and `backend::score_all_moves` run.
+    pub algorithm: SearchAlgorithm,
 }
 
 impl Default for SearchParams {
@@ -59,6 +67,7 @@ impl Default for SearchParams {
             window_size: 50,
             features: Features::default(),
             weights: EvaluationWeights::default(),
+            algorithm: SearchAlgorithm::default(),
         }
     }
 }
@@ -77,12 +86,20 @@ impl std::ops::Neg for SearchResult {
 #[derive(Debug, Clone)]
 pub enum AlphaBetaError {
     Timeout,
+
+    /// The search was stopped through `SearchStateData::stop`.
+    Stopped,
+
+    /// The search reached `SearchStateData::node_limit`.
+    NodeLimit,
 }
 
 impl std::fmt::Display for AlphaBetaError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AlphaBetaError::Timeout => write!(f, "Search timed out"),
+            AlphaBetaError::Stopped => write!(f, "Search stopped"),
+            AlphaBetaError::NodeLimit => write!(f, "Search reached its node limit"),
         }
     }
 }
@@ -94,15 +111,32 @@ pub struct ScorePV {
 
 const WINDOW_MODIFIER: i32 = 2;
 
+/// How far above beta the static evaluation must be, per ply of remaining depth, for reverse
+/// futility pruning to cut a node.
+const REVERSE_FUTILITY_MARGIN: i32 = 120;
+
+/// The deepest remaining depth reverse futility pruning is tried at.
+const REVERSE_FUTILITY_MAX_DEPTH: u32 = 6;
+
+/// How far below alpha the static evaluation must be for razoring, by remaining depth.
+const RAZORING_MARGINS: [i32; 4] = [0, 400, 800, 1200];
+
+/// The deepest remaining depth razoring is tried at.
+const RAZORING_MAX_DEPTH: u32 = 3;
+
 pub const MIN_ALPHA: i32 = -2_000_000;
 pub const MAX_BETA: i32 = 2_000_000;
 
+#[derive(Debug, Clone)]
 pub struct MoveScore {
     pub mv: PieceMove,
     pub score: i32,
     pub principal_variation: Option<Vec<PieceMove>>,
 }
 
+/// Called by `score_all_moves` as each root move is scored, in search order rather than by score.
+pub type OnMoveScored = dyn Fn(&MoveScore) + Send + Sync;
+
 pub fn score_all_moves(
     position: &Position,
     state: &mut SearchState,
@@ -113,23 +147,24 @@ pub fn score_all_moves(
     let mut position = position.clone();
 
     // Get all legal moves
-    let moves = position.get_all_legal_moves(params.game_type).unwrap();
+    let mut moves = MoveList::new();
+    position
+        .get_all_legal_moves(params.game_type, &mut moves)
+        .unwrap();
     if moves.is_empty() {
-        // Handle checkmate or stalemate
-        if position.is_checkmate(params.game_type).unwrap() {
-            return Ok(vec![]);
-        }
+        // Checkmate or stalemate, nothing to score
         return Ok(vec![]);
     }
 
     // Order moves using the existing move ordering function
     let prev_pv = state.data.previous_pv.as_ref();
-    let ordered_moves = order_moves(&mut position, moves, prev_pv, state, ply, &params);
+    order_moves(&mut position, &mut moves, prev_pv, state, ply, &params);
 
     // Evaluate each move
-    for mv in ordered_moves {
+    for mv in moves {
         let restore = position.apply_move(mv).unwrap();
         position.invert();
+        state.data.history.push(position.zobrist_key());
 
         // Use a wide window for accurate scoring
         let result = alpha_beta(
@@ -142,16 +177,23 @@ pub fn score_all_moves(
             ply + 1,
         );
 
+        state.data.history.pop();
         position.invert();
         position.unapply_move(mv, restore).unwrap();
 
         match result {
             Ok(search_result) => {
-                scores.push(MoveScore {
+                let move_score = MoveScore {
                     mv,
                     score: -search_result.score, // Negate score since it's from opponent's perspective
                     principal_variation: search_result.principal_variation,
-                });
+                };
+
+                if let Some(on_move_scored) = state.callbacks.on_move_scored {
+                    on_move_scored(&move_score);
+                }
+
+                scores.push(move_score);
             }
             Err(e) => return Err(e),
         }
@@ -189,16 +231,53 @@ pub fn search(
     // Fine to clone the root position
     let mut position = position.clone();
 
-    loop {
-        if position
-            .get_all_legal_moves(params.game_type)
-            .unwrap()
-            .is_empty()
+    // The top of the history stack is always the position being searched.
+    let root_key = position.zobrist_key();
+    if state.data.history.last() != Some(&root_key) {
+        state.data.history.push(root_key);
+    }
+
+    // Endgames in the tablebases are played from them, without searching
+    #[cfg(feature = "syzygy")]
+    if params.features.enable_tablebases {
+        if let Some(tablebase_move) = state
+            .data
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.best_move(&position, params.game_type))
         {
+            return Ok(SearchResults {
+                best_move: Some(tablebase_move.mv),
+                principal_variation: Some(vec![tablebase_move.mv]),
+                score: tablebase_move.wdl.score(0),
+                nodes_searched: state.data.nodes_searched,
+                cached_positions: state.data.cached_positions,
+                depth: params.depth,
+                time_taken_ms: state.data.start_time.elapsed().as_millis(),
+                pruned: state.data.pruned,
+                alpha,
+                beta,
+                statistics: state.data.statistics,
+            });
+        }
+    }
+
+    loop {
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(params.game_type, &mut moves)
+            .unwrap();
+
+        if moves.is_empty() {
+            let score = match position.game_status(params.game_type, &[]).unwrap() {
+                GameStatus::Checkmate(_) => mated_in(0),
+                _ => STALEMATE,
+            };
+
             return Ok(SearchResults {
                 best_move: None,
                 principal_variation: None,
-                score: CHECKMATE,
+                score,
                 nodes_searched: state.data.nodes_searched,
                 cached_positions: state.data.cached_positions,
                 depth: params.depth,
@@ -206,6 +285,7 @@ pub fn search(
                 pruned: state.data.pruned,
                 alpha,
                 beta,
+                statistics: state.data.statistics,
             });
         }
 
@@ -239,11 +319,12 @@ pub fn search(
                             pruned: state.data.pruned,
                             alpha,
                             beta,
+                            statistics: state.data.statistics,
                         });
                     }
                 }
             }
-            Err(AlphaBetaError::Timeout) => return Err(AlphaBetaError::Timeout),
+            Err(e) => return Err(e),
         }
 
         if !params.features.enable_window_search {
@@ -304,6 +385,11 @@ impl<'a, 'b, 'c> std::fmt::Debug for SearchIteration<'a, 'b, 'c> {
     }
 }
 
+/// Whether drops that give check are extended in the search and searched in quiescence.
+pub(crate) fn drop_check_extension_enabled(params: &SearchParams) -> bool {
+    params.features.enable_drop_check_extension && params.game_type.rules().has_rescues()
+}
+
 // Late move reduction
 fn should_reduce_move(
     mv: &PieceMove,
@@ -313,7 +399,7 @@ fn should_reduce_move(
     alpha: i32,
     beta: i32,
 ) -> bool {
-    if alpha > 900_000 || beta > 900_000 || alpha < -900_000 || beta < -900_000 {
+    if is_mate_score(alpha) || is_mate_score(beta) {
         return false;
     }
 
@@ -338,14 +424,38 @@ pub fn alpha_beta(
 ) -> Result<SearchResult, AlphaBetaError> {
     let original_alpha = alpha;
 
+    // A position repeated for the third time, counting the moves played before the root,
+    // is a draw. This must come before the transposition table, whose entries don't know
+    // how the position was reached. Nothing before the last capture or pawn move can be the
+    // same position, so only the moves since then are looked at.
+    if depth < params.depth {
+        let history = &state.data.history;
+        let since_irreversible = &history[history
+            .len()
+            .saturating_sub(position.halfmove_clock as usize + 1)..];
+
+        if let Some(&key) = since_irreversible.last() {
+            if repetition_count(since_irreversible, key) >= REPETITION_COUNT {
+                return Ok(SearchResult {
+                    principal_variation: Some(vec![]),
+                    score: STALEMATE,
+                });
+            }
+        }
+    }
+
     // If we have already searched this position to the same depth or greater,
     // we can use the cached result directly.
     if params.features.enable_transposition_table {
-        if let Some(entry) =
-            state
-                .transposition_table
-                .try_get(&position.to_hashable(), depth, alpha, beta)
-        {
+        state.data.statistics.tt_probes += 1;
+
+        if let Some(entry) = state.transposition_table.try_get(
+            &position.to_hashable(),
+            depth,
+            state.data.moves.len() as u32,
+            alpha,
+            beta,
+        ) {
             if params.debug_print_verbose {
                 trace!(
                     "{}Cached position found: {}",
@@ -354,22 +464,8 @@ pub fn alpha_beta(
                 );
             }
 
-            if depth >= params.depth - 2 {
-                // Only log near root
-                println!(
-                    "TT hit: depth={}, node_type={:?}, score={}, pos={}, entry.alpha={}, entry.beta={}, alpha={}, beta={}",
-                    depth,
-                    entry.node_type,
-                    entry.score,
-                    position.to_fen(),
-                    entry.alpha,
-                    entry.beta,
-                    alpha,
-                    beta
-                );
-            }
-
             state.data.cached_positions += 1;
+            state.data.statistics.tt_hits += 1;
             return Ok(SearchResult {
                 principal_variation: Some(entry.principal_variation.clone()),
                 score: entry.score,
@@ -382,8 +478,20 @@ pub fn alpha_beta(
         return Err(AlphaBetaError::Timeout);
     }
 
+    if state.data.stop.load(Ordering::Relaxed) {
+        return Err(AlphaBetaError::Stopped);
+    }
+
+    if state.data.nodes_searched >= state.data.node_limit {
+        return Err(AlphaBetaError::NodeLimit);
+    }
+
     // Increment the total number of nodes searched.
     state.data.nodes_searched += 1;
+    state
+        .data
+        .statistics
+        .record_ply(state.data.moves.len() as u32);
 
     if state.data.nodes_searched % 1_000_000 == 0 {
         trace!("Nodes searched: {}M", state.data.nodes_searched / 1_000_000);
@@ -391,7 +499,7 @@ pub fn alpha_beta(
 
     // If the position is a checkmate, we should return a very low score.
     if position.is_checkmate(params.game_type).unwrap() {
-        let score = CHECKMATE - (depth as i32);
+        let score = mated_in(state.data.moves.len() as u32);
 
         if params.debug_print_verbose {
             trace!(
@@ -407,6 +515,33 @@ pub fn alpha_beta(
         });
     }
 
+    // Positions drawn by rule are scored as draws below the root, whatever the material says.
+    if depth < params.depth
+        && (position.is_draw_by_fifty_moves()
+            || position.is_insufficient_material(params.game_type))
+    {
+        return Ok(SearchResult {
+            principal_variation: Some(vec![]),
+            score: STALEMATE,
+        });
+    }
+
+    // Below the root, positions in the tablebases have an exact result to return
+    #[cfg(feature = "syzygy")]
+    if depth < params.depth && params.features.enable_tablebases {
+        if let Some(wdl) = state
+            .data
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.probe_wdl(position, params.game_type))
+        {
+            return Ok(SearchResult {
+                principal_variation: Some(vec![]),
+                score: wdl.score((params.depth - depth) as usize),
+            });
+        }
+    }
+
     // If we have reached the maximum depth, we should evaluate the position
     // and return the result.
     if depth == 0 {
@@ -436,6 +571,51 @@ pub fn alpha_beta(
         });
     }
 
+    let try_reverse_futility =
+        params.features.enable_reverse_futility_pruning && depth <= REVERSE_FUTILITY_MAX_DEPTH;
+    let try_razoring = params.features.enable_razoring && depth <= RAZORING_MAX_DEPTH;
+
+    if (try_reverse_futility || try_razoring)
+        && is_static_pruning_node(position, alpha, beta, depth, params)
+    {
+        let static_eval = cached_evaluation(position, state, params);
+
+        // Reverse futility pruning: when the position is so good that even losing a
+        // depth-scaled margin keeps it above beta, assume searching it would fail high too.
+        if try_reverse_futility && static_eval - REVERSE_FUTILITY_MARGIN * depth as i32 >= beta {
+            state.data.pruned += 1;
+
+            return Ok(SearchResult {
+                principal_variation: None,
+                score: beta,
+            });
+        }
+
+        // Razoring: when the position is so bad that not even a large margin would bring it up
+        // to alpha, only check that no capture saves it before giving up on it.
+        if try_razoring && static_eval + RAZORING_MARGINS[depth as usize] <= alpha {
+            let result = quiescence_search(
+                position,
+                alpha,
+                alpha + 1,
+                params.quiescence_depth,
+                state,
+                params,
+                params.depth,
+                ply,
+            )?;
+
+            if result.score <= alpha {
+                state.data.pruned += 1;
+
+                return Ok(SearchResult {
+                    principal_variation: None,
+                    score: result.score,
+                });
+            }
+        }
+    }
+
     if params.features.enable_null_move_pruning && should_try_null_move(position, depth, beta) {
         // Make a null move - essentially just switch sides without making a move
         let mut null_pos = position.clone();
@@ -454,6 +634,8 @@ pub fn alpha_beta(
             2
         };
         let null_depth = (depth - 1 - r).max(0);
+        state.data.moves.push(None);
+        state.data.statistics.null_move_tries += 1;
 
         // Search with a null window around beta
         match alpha_beta(
@@ -486,6 +668,8 @@ pub fn alpha_beta(
                                     // Verification failed, continue with normal search
                                     // Fall through to regular move generation
                                 } else {
+                                    state.data.moves.pop();
+                                    state.data.statistics.null_move_cutoffs += 1;
                                     return Ok(SearchResult {
                                         principal_variation: None,
                                         score: beta,
@@ -496,7 +680,9 @@ pub fn alpha_beta(
                         }
                     } else {
                         // Don't return mate scores from null move
-                        if null_score < 900_000 {
+                        if null_score < MATE_THRESHOLD {
+                            state.data.moves.pop();
+                            state.data.statistics.null_move_cutoffs += 1;
                             return Ok(SearchResult {
                                 principal_variation: None,
                                 score: beta,
@@ -507,11 +693,16 @@ pub fn alpha_beta(
             }
             Err(e) => return Err(e),
         }
+
+        state.data.moves.pop();
     }
 
-    let moves = position.get_all_legal_moves(params.game_type).unwrap();
+    let mut ordered_moves = MoveList::new();
+    position
+        .get_all_legal_moves(params.game_type, &mut ordered_moves)
+        .unwrap();
 
-    if moves.is_empty() {
+    if ordered_moves.is_empty() {
         if params.debug_print_verbose {
             trace!(
                 "{}Stalemate found, scoring {}",
@@ -527,7 +718,31 @@ pub fn alpha_beta(
     }
 
     let prev_best_move = state.data.previous_pv.as_ref();
-    let ordered_moves = order_moves(position, moves, prev_best_move, state, ply, params);
+    order_moves(
+        position,
+        &mut ordered_moves,
+        prev_best_move,
+        state,
+        ply,
+        params,
+    );
+
+    // The best move from an earlier search of this position, even one too shallow or with the
+    // wrong bounds to reuse its score, is the most likely to be best again. With PVS, this is
+    // also what makes re-searching a node after its null window failed cheap.
+    if params.features.enable_transposition_table {
+        let tt_move = state
+            .transposition_table
+            .get(&position.to_hashable())
+            .and_then(|entry| entry.principal_variation.first().copied());
+
+        if let Some(index) =
+            tt_move.and_then(|tt_move| ordered_moves.iter().position(|mv| *mv == tt_move))
+        {
+            let tt_move = ordered_moves.remove(index);
+            ordered_moves.insert(0, tt_move);
+        }
+    }
 
     let mut iteration = SearchIteration {
         alpha,
@@ -604,7 +819,9 @@ pub fn alpha_beta(
                     node_type,
                     alpha: original_alpha,
                     beta,
+                    generation: iteration.state.transposition_table.generation(),
                 },
+                iteration.state.data.moves.len() as u32,
             );
         }
     }
@@ -615,6 +832,22 @@ pub fn alpha_beta(
     });
 }
 
+/// Tells the `on_new_best_move` handler, if any, about the new best move at the root.
+fn report_new_best_move(iteration: &SearchIteration, params: &SearchParams) {
+    let state = &iteration.state;
+    if let Some(on_new_best_move) = state.callbacks.on_new_best_move {
+        on_new_best_move(&SearchInfo {
+            depth: params.depth,
+            seldepth: state.data.statistics.seldepth,
+            score: iteration.alpha,
+            nodes: state.data.nodes_searched,
+            time_ms: state.data.start_time.elapsed().as_millis(),
+            hashfull: state.transposition_table.hashfull(),
+            principal_variation: iteration.principal_variation.clone().unwrap_or_default(),
+        });
+    }
+}
+
 // Modify the test_move function to implement LMR
 fn test_move(
     mv: PieceMove,
@@ -648,10 +881,22 @@ fn test_move(
     let restore = position.apply_move(mv).unwrap();
 
     let in_check = position.is_king_in_check().unwrap();
+
+    // Drops that give check are forcing enough to search a ply deeper. The remaining depth
+    // stays the same, and every drop uses up a held piece, so this can't extend forever.
+    let extension = u32::from(
+        mv.is_drop()
+            && drop_check_extension_enabled(params)
+            && position.is_black_king_in_check().unwrap(),
+    );
+
     position.invert();
+    iteration.state.data.history.push(position.zobrist_key());
+    iteration.state.data.moves.push(Some(mv));
 
     // Implement Late Move Reduction
     let mut score_pv: Option<ScorePV> = if params.features.enable_lmr
+        && extension == 0
         && should_reduce_move(
             &mv,
             depth,
@@ -679,6 +924,7 @@ fn test_move(
         }
 
         // Reduced depth search
+        iteration.state.data.statistics.lmr_reductions += 1;
         let result = alpha_beta(
             position,
             -iteration.alpha - 1, // Use a null window for reduced search
@@ -694,6 +940,7 @@ fn test_move(
                 let reduced_score = -reduced_result.score;
                 // If the reduced search beats alpha, we need to do a full-depth search
                 if reduced_score > iteration.alpha {
+                    iteration.state.data.statistics.lmr_researches += 1;
                     None // Signal that we need a full-depth search
                 } else {
                     Some(ScorePV {
@@ -722,11 +969,42 @@ fn test_move(
             );
         }
 
+        // Principal variation search: once the first move has been searched, the rest are
+        // expected to be worse, which a null window around alpha proves more cheaply. Only a
+        // move that turns out better is searched again with the full window.
+        if params.features.enable_pvs && move_index > 0 {
+            match alpha_beta(
+                position,
+                -iteration.alpha - 1,
+                -iteration.alpha,
+                iteration.depth - 1 + extension,
+                iteration.state,
+                params,
+                ply + 1,
+            ) {
+                Ok(result) => {
+                    let score = -result.score;
+
+                    if score <= iteration.alpha || score >= iteration.beta {
+                        score_pv = Some(ScorePV {
+                            score,
+                            pv: result.principal_variation.unwrap_or_default(),
+                        });
+                    } else {
+                        iteration.state.data.statistics.pvs_researches += 1;
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    if score_pv.is_none() {
         match alpha_beta(
             position,
             -iteration.beta,
             -iteration.alpha,
-            iteration.depth - 1,
+            iteration.depth - 1 + extension,
             iteration.state,
             params,
             ply + 1,
@@ -746,6 +1024,7 @@ fn test_move(
     // Rest of the move processing remains the same
     if score_pv.score >= iteration.beta {
         iteration.state.data.pruned += 1;
+        iteration.state.data.statistics.record_cutoff(move_index);
 
         if params.features.enable_killer_moves {
             iteration.state.killer_moves.add_killer(mv, ply);
@@ -755,18 +1034,13 @@ fn test_move(
             iteration.state.history.update_history(&mv, depth, true);
         }
 
-        if params.features.enable_transposition_table {
-            iteration.state.transposition_table.insert(
-                position.to_hashable(),
-                TranspositionTableEntry {
-                    depth,
-                    score: iteration.beta,
-                    principal_variation: vec![mv],
-                    node_type: NodeType::LowerBound,
-                    alpha: iteration.alpha,
-                    beta: iteration.beta,
-                },
-            );
+        if params.features.enable_continuation_history {
+            let previous_moves =
+                &iteration.state.data.moves[..iteration.state.data.moves.len() - 1];
+            iteration
+                .state
+                .history
+                .update_continuation_history(previous_moves, &mv, depth, true);
         }
 
         if params.debug_print_verbose {
@@ -779,9 +1053,27 @@ fn test_move(
             );
         }
 
+        iteration.state.data.history.pop();
+        iteration.state.data.moves.pop();
         position.invert();
         position.unapply_move(mv, restore).unwrap();
 
+        if params.features.enable_transposition_table {
+            iteration.state.transposition_table.insert(
+                position.to_hashable(),
+                TranspositionTableEntry {
+                    depth,
+                    score: iteration.beta,
+                    principal_variation: vec![mv],
+                    node_type: NodeType::LowerBound,
+                    alpha: iteration.alpha,
+                    beta: iteration.beta,
+                    generation: iteration.state.transposition_table.generation(),
+                },
+                iteration.state.data.moves.len() as u32,
+            );
+        }
+
         return Some(Ok(SearchResult {
             principal_variation: None,
             score: iteration.beta,
@@ -790,6 +1082,15 @@ fn test_move(
         if params.features.enable_history {
             iteration.state.history.update_history(&mv, depth, false);
         }
+
+        if params.features.enable_continuation_history {
+            let previous_moves =
+                &iteration.state.data.moves[..iteration.state.data.moves.len() - 1];
+            iteration
+                .state
+                .history
+                .update_continuation_history(previous_moves, &mv, depth, false);
+        }
     }
 
     if score_pv.score > iteration.alpha {
@@ -810,10 +1111,7 @@ fn test_move(
         // Update the best move so far if we are at the root
         if depth == params.depth {
             iteration.state.data.best_move_so_far = Some(mv);
-
-            if let Some(on_new_best_move) = iteration.state.callbacks.on_new_best_move {
-                on_new_best_move(mv, iteration.alpha);
-            }
+            report_new_best_move(iteration, params);
         }
     } else if iteration.principal_variation.is_none() {
         // The move didn't beta cutoff, and didn't improve alpha, but we don't yet have a
@@ -823,10 +1121,7 @@ fn test_move(
 
         if depth == params.depth {
             iteration.state.data.best_move_so_far = Some(mv);
-
-            if let Some(on_new_best_move) = iteration.state.callbacks.on_new_best_move {
-                on_new_best_move(mv, iteration.alpha);
-            }
+            report_new_best_move(iteration, params);
         }
     }
 
@@ -839,12 +1134,31 @@ fn test_move(
         );
     }
 
+    iteration.state.data.history.pop();
+    iteration.state.data.moves.pop();
     position.invert();
     position.unapply_move(mv, restore).unwrap();
 
     None
 }
 
+/// Pruning on the static evaluation (reverse futility pruning and razoring) is only used in
+/// non-PV nodes below the root, where the search is just proving a bound, and never in check
+/// or when the bounds are mate scores.
+fn is_static_pruning_node(
+    position: &Position,
+    alpha: i32,
+    beta: i32,
+    depth: u32,
+    params: &SearchParams,
+) -> bool {
+    depth < params.depth
+        && beta - alpha == 1
+        && alpha.abs() < MATE_THRESHOLD
+        && beta.abs() < MATE_THRESHOLD
+        && !position.is_king_in_check().unwrap()
+}
+
 fn should_try_null_move(position: &Position, depth: u32, beta: i32) -> bool {
     // Don't do null move if:
     // 1. In check
@@ -853,7 +1167,7 @@ fn should_try_null_move(position: &Position, depth: u32, beta: i32) -> bool {
     // 4. Previous score indicates zugzwang is likely
     // 5. Beta is close to mate score
 
-    if depth < 3 || position.is_king_in_check().unwrap() || beta > 900_000 || beta < -900_000 {
+    if depth < 3 || position.is_king_in_check().unwrap() || is_mate_score(beta) {
         return false;
     }
 
@@ -865,18 +1179,12 @@ fn should_try_null_move(position: &Position, depth: u32, beta: i32) -> bool {
     }
 
     // Avoid null move in pawn endgames
-    let white_has_major_pieces = position
-        .white_pieces
-        .iter()
-        .filter_map(|p| p.as_ref())
-        .any(|p| p.piece_type == PieceType::Queen || p.piece_type == PieceType::Rook);
-    let black_has_major_pieces = position
-        .black_pieces
-        .iter()
-        .filter_map(|p| p.as_ref())
-        .any(|p| p.piece_type == PieceType::Queen || p.piece_type == PieceType::Rook);
-
-    if !white_has_major_pieces || !black_has_major_pieces {
+    let has_major_pieces = |color| {
+        position.pieces(color, PieceType::Queen) | position.pieces(color, PieceType::Rook)
+            != Bitboard::new()
+    };
+
+    if !has_major_pieces(Color::White) || !has_major_pieces(Color::Black) {
         return false;
     }
 
@@ -884,29 +1192,96 @@ fn should_try_null_move(position: &Position, depth: u32, beta: i32) -> bool {
 }
 
 fn get_material_count(position: &Position, color: Color) -> i32 {
-    let mut material = 0;
-
-    let pieces = if color == Color::White {
-        &position.white_pieces
-    } else {
-        &position.black_pieces
-    };
-
-    for piece in pieces.iter() {
-        if let Some(piece) = piece {
-            material += piece_value(piece.piece_type);
-        }
-    }
-
-    material
+    PieceType::ALL
+        .into_iter()
+        .map(|piece_type| {
+            position.pieces(color, piece_type).count() as i32 * piece_value(piece_type)
+        })
+        .sum()
 }
 
 #[cfg(test)]
 pub mod tests {
+    use std::sync::{Arc, Mutex};
+
     use crate::search::transposition_table::TranspositionTable;
 
     use super::*;
 
+    #[test]
+    fn test_stop_flag() {
+        let position = Position::start_position();
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+        state.data.stop.store(true, Ordering::Relaxed);
+
+        let params = SearchParams::builder().depth(4).build().unwrap();
+
+        assert!(matches!(
+            search(&position, &mut state, params, 0),
+            Err(AlphaBetaError::Stopped)
+        ));
+    }
+
+    #[test]
+    fn test_repetition_before_root_is_draw() {
+        let moves = ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"];
+        let mut history = vec![Position::start_position().zobrist_key()];
+        for i in 1..=moves.len() {
+            let position = Position::from_moves(&moves[..i], GameType::Classic).unwrap();
+            history.push(position.zobrist_key());
+        }
+
+        let position = Position::from_moves(&moves, GameType::Classic).unwrap();
+        let repeating_move =
+            PieceMove::from_algebraic_inverted(&position, "Ng8", GameType::Classic).unwrap();
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+        state.data.history = history;
+
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+
+        let scores = score_all_moves(&position, &mut state, params, 0).unwrap();
+        let repeating = scores.iter().find(|s| s.mv == repeating_move).unwrap();
+
+        // Ng8 repeats the starting position for the third time
+        assert_eq!(repeating.score, STALEMATE);
+    }
+
+    #[test]
+    fn test_on_move_scored() {
+        let position = Position::start_position();
+
+        let scored = Arc::new(Mutex::new(Vec::new()));
+        let on_move_scored = {
+            let scored = scored.clone();
+            move |move_score: &MoveScore| scored.lock().unwrap().push(move_score.mv)
+        };
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+        state.callbacks.on_move_scored = Some(&on_move_scored);
+
+        let params = SearchParams::builder()
+            .depth(2)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+
+        let scores = score_all_moves(&position, &mut state, params, 0).unwrap();
+        let scored = scored.lock().unwrap();
+
+        assert_eq!(scored.len(), 20);
+        assert_eq!(scored.len(), scores.len());
+        assert!(scores.iter().all(|s| scored.contains(&s.mv)));
+    }
+
     #[test]
     fn test_scholars_mate_defense() {
         // Set up a position one move before Scholar's Mate
@@ -919,11 +1294,11 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth: 4,
-            game_type: GameType::Classic,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(4)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         // Search to depth 4 which should be enough to detect the checkmate threat
         let result = search(&position, &mut state, params, 0).unwrap();
@@ -971,15 +1346,15 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth: 1,
-            game_type: GameType::Classic,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(1)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
         assert!(position.is_checkmate(GameType::Classic).unwrap());
-        assert_eq!(result.score, -1000000);
+        assert_eq!(result.score, mated_in(0));
         assert!(result.best_move.is_none());
     }
 
@@ -995,15 +1370,19 @@ pub mod tests {
             let mut transposition_table = TranspositionTable::new();
             let mut state = SearchState::new(&mut transposition_table);
 
-            let params = SearchParams {
-                depth,
-                game_type: GameType::Classic,
-                debug_print_all_moves: true,
-                debug_print_verbose: true,
-                ..Default::default()
-            };
+            let params = SearchParams::builder()
+                .depth(depth)
+                .game_type(GameType::Classic)
+                .debug_print_all_moves(true)
+                .debug_print_verbose(true)
+                .build()
+                .unwrap();
 
-            dbg!(position.get_all_legal_moves(GameType::Classic)).unwrap();
+            let mut moves = MoveList::new();
+            position
+                .get_all_legal_moves(GameType::Classic, &mut moves)
+                .unwrap();
+            dbg!(moves);
 
             let result = search(&position, &mut state, params, 0).unwrap();
             let best_move = result.best_move.unwrap().to_string();
@@ -1047,13 +1426,12 @@ pub mod tests {
             let mut transposition_table = TranspositionTable::new();
             let mut state = SearchState::new(&mut transposition_table);
 
-            let params = SearchParams {
-                depth,
-                game_type: GameType::Classic,
-                debug_print_all_moves: true,
-                debug_print_verbose: false,
-                ..Default::default()
-            };
+            let params = SearchParams::builder()
+                .depth(depth)
+                .game_type(GameType::Classic)
+                .debug_print_all_moves(true)
+                .build()
+                .unwrap();
 
             let result = search(&position, &mut state, params, 0).unwrap();
 
@@ -1097,11 +1475,11 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth: 3,
-            game_type: GameType::Classic,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(3)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
         let best_move = result.best_move.unwrap().to_string();
@@ -1123,11 +1501,11 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth: 3,
-            game_type: GameType::Classic,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(3)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
         let best_move = result.best_move.unwrap().to_string();
@@ -1148,12 +1526,11 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth: 4,
-            game_type: GameType::Classic,
-            debug_print_verbose: false,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(4)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
         let best_move = result.best_move.unwrap().to_string();
@@ -1172,17 +1549,16 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth,
-            game_type: GameType::Classic,
-            features: Features {
-                enable_lmr: false,
-                enable_window_search: false,
-                // enable_transposition_table: false,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(depth)
+            .game_type(GameType::Classic)
+            .feature(|f| {
+                f.enable_lmr = false;
+                f.enable_window_search = false;
+                // f.enable_transposition_table = false;
+            })
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
         let best_move = result.best_move.unwrap().to_string();
@@ -1196,7 +1572,7 @@ pub mod tests {
             expected_move, best_move
         );
         assert!(
-            result.score >= -CHECKMATE,
+            result.score >= MATE_THRESHOLD,
             "Expected mate, got score {}",
             result.score
         );
@@ -1254,11 +1630,11 @@ pub mod tests {
         let mut transposition_table = TranspositionTable::new();
         let mut state = SearchState::new(&mut transposition_table);
 
-        let params = SearchParams {
-            depth,
-            game_type: GameType::Classic,
-            ..Default::default()
-        };
+        let params = SearchParams::builder()
+            .depth(depth)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
 
         let result = search(&position, &mut state, params, 0).unwrap();
 
@@ -1278,6 +1654,96 @@ pub mod tests {
         );
     }
 
+    /// Searches `position` up to `depth` with iterative deepening, so the earlier depths' best
+    /// moves are in the table to order by, with the features changed by `toggle`. For comparing
+    /// the nodes searched with a feature on and off.
+    fn nodes_with_feature(
+        position: &Position,
+        depth: u32,
+        toggle: impl Fn(&mut Features),
+    ) -> SearchResults {
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+        let mut results: Option<SearchResults> = None;
+
+        for depth in 1..=depth {
+            let params = SearchParams::builder()
+                .depth(depth)
+                .previous_score(results.as_ref().map(|results| results.score))
+                .feature(&toggle)
+                .build()
+                .unwrap();
+
+            results = Some(search(position, &mut state, params, 0).unwrap());
+        }
+
+        results.unwrap()
+    }
+
+    #[test]
+    fn test_reverse_futility_pruning() {
+        // White is a rook up, so most replies are refuted without a search
+        let position: Position = "4k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1".parse().unwrap();
+
+        let search_with = |enabled: bool| {
+            nodes_with_feature(&position, 5, |f| {
+                f.enable_reverse_futility_pruning = enabled;
+                f.enable_razoring = false;
+            })
+        };
+
+        let pruned = search_with(true);
+        let unpruned = search_with(false);
+
+        assert!(
+            pruned.nodes_searched < unpruned.nodes_searched,
+            "{} nodes with pruning, {} without",
+            pruned.nodes_searched,
+            unpruned.nodes_searched
+        );
+        assert!(pruned.score > 300);
+    }
+
+    #[test]
+    fn test_pvs() {
+        let position = Position::start_position();
+
+        let pvs = nodes_with_feature(&position, 6, |f| f.enable_pvs = true);
+        let plain = nodes_with_feature(&position, 6, |f| f.enable_pvs = false);
+
+        assert!(
+            pvs.nodes_searched < plain.nodes_searched,
+            "{} nodes with PVS, {} without",
+            pvs.nodes_searched,
+            plain.nodes_searched
+        );
+    }
+
+    #[test]
+    fn test_razoring() {
+        // Every white move but Rxd5 leaves white a rook down, so most lines fail low quickly
+        let position: Position = "4k3/ppp5/8/3q4/8/8/PPP5/3RK3 w - - 0 1".parse().unwrap();
+
+        let search_with = |enabled: bool| {
+            nodes_with_feature(&position, 5, |f| {
+                f.enable_razoring = enabled;
+                f.enable_reverse_futility_pruning = false;
+            })
+        };
+
+        let pruned = search_with(true);
+        let unpruned = search_with(false);
+
+        assert!(
+            pruned.nodes_searched < unpruned.nodes_searched,
+            "{} nodes with razoring, {} without",
+            pruned.nodes_searched,
+            unpruned.nodes_searched
+        );
+        assert_eq!(pruned.best_move.unwrap().to_string(), "Rxd5");
+        assert_eq!(pruned.best_move, unpruned.best_move);
+    }
+
     #[test]
     fn stockfish_analysis_1() {
         test_one_of_best_moves(
@@ -0,0 +1,205 @@
+//! The search algorithms the engine can run, chosen at runtime by `SearchParams::algorithm`.
+//! Callers go through `search` and `score_all_moves` here rather than calling an algorithm
+//! directly, so a new algorithm only has to implement `SearchBackend` and be added to
+//! `SearchAlgorithm`.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Position;
+
+use super::{
+    alpha_beta::{self, AlphaBetaError, MoveScore, SearchParams},
+    mcts::Mcts,
+    search_results::{SearchResults, SearchState},
+    weak::{DepthOne, GreedyMaterial, RandomMover},
+};
+
+/// A search algorithm. A search runs to `params.depth` with the state of the running search,
+/// like the transposition table, time limit and stop flag, in `state`.
+pub trait SearchBackend: Send + Sync {
+    /// Finds the best move for white in `position`, `ply` plies into the game.
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError>;
+
+    /// Scores every legal move for white in `position`, best first.
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError>;
+}
+
+/// Principal variation alpha-beta search with quiescence, pruning and move ordering.
+pub struct AlphaBeta;
+
+impl SearchBackend for AlphaBeta {
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        alpha_beta::search(position, state, params, ply)
+    }
+
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError> {
+        alpha_beta::score_all_moves(position, state, params, ply)
+    }
+}
+
+/// Which search algorithm to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchAlgorithm {
+    #[default]
+    AlphaBeta,
+
+    /// Monte Carlo tree search, which may suit Rescue games with many drops better.
+    Mcts,
+
+    /// A random legal move, the weakest opponent there is.
+    Random,
+
+    /// The move that wins the most material right away, whatever the reply.
+    Greedy,
+
+    /// A one-ply search, whatever the depth.
+    DepthOne,
+}
+
+impl SearchAlgorithm {
+    pub const ALL: [SearchAlgorithm; 5] = [
+        SearchAlgorithm::AlphaBeta,
+        SearchAlgorithm::Mcts,
+        SearchAlgorithm::Random,
+        SearchAlgorithm::Greedy,
+        SearchAlgorithm::DepthOne,
+    ];
+
+    pub fn backend(self) -> &'static dyn SearchBackend {
+        match self {
+            SearchAlgorithm::AlphaBeta => &AlphaBeta,
+            SearchAlgorithm::Mcts => &Mcts,
+            SearchAlgorithm::Random => &RandomMover,
+            SearchAlgorithm::Greedy => &GreedyMaterial,
+            SearchAlgorithm::DepthOne => &DepthOne,
+        }
+    }
+
+    /// The algorithm's name as a UCI option value, e.g. "AlphaBeta".
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchAlgorithm::AlphaBeta => "AlphaBeta",
+            SearchAlgorithm::Mcts => "MCTS",
+            SearchAlgorithm::Random => "Random",
+            SearchAlgorithm::Greedy => "Greedy",
+            SearchAlgorithm::DepthOne => "DepthOne",
+        }
+    }
+}
+
+impl std::fmt::Display for SearchAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SearchAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SearchAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names = SearchAlgorithm::ALL
+                    .map(|algorithm| algorithm.name())
+                    .join(", ");
+                format!("Unknown search algorithm {}, expected one of {}", s, names)
+            })
+    }
+}
+
+/// Searches `position` with the algorithm chosen in `params`.
+pub fn search(
+    position: &Position,
+    state: &mut SearchState,
+    params: SearchParams,
+    ply: usize,
+) -> Result<SearchResults, AlphaBetaError> {
+    params
+        .algorithm
+        .backend()
+        .search(position, state, params, ply)
+}
+
+/// Scores every legal move in `position` with the algorithm chosen in `params`.
+pub fn score_all_moves(
+    position: &Position,
+    state: &mut SearchState,
+    params: SearchParams,
+    ply: usize,
+) -> Result<Vec<MoveScore>, AlphaBetaError> {
+    params
+        .algorithm
+        .backend()
+        .score_all_moves(position, state, params, ply)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::transposition_table::TranspositionTable;
+
+    use super::*;
+
+    #[test]
+    fn test_search_uses_chosen_algorithm() {
+        let position = Position::parse_from_fen(
+            "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 1",
+        )
+        .unwrap();
+        let params = SearchParams::builder()
+            .depth(2)
+            .algorithm(SearchAlgorithm::AlphaBeta)
+            .build()
+            .unwrap();
+
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+        let results = search(&position, &mut state, params.clone(), 0).unwrap();
+
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+        let direct = alpha_beta::search(&position, &mut state, params, 0).unwrap();
+
+        assert_eq!(results.best_move, direct.best_move);
+        assert_eq!(results.best_move.unwrap().inverted().to_uci(), "d8h4");
+    }
+
+    #[test]
+    fn test_parse_algorithm() {
+        assert_eq!("mcts".parse(), Ok(SearchAlgorithm::Mcts));
+        assert_eq!("DepthOne".parse(), Ok(SearchAlgorithm::DepthOne));
+        assert!("minimax".parse::<SearchAlgorithm>().is_err());
+
+        for algorithm in SearchAlgorithm::ALL {
+            assert_eq!(algorithm.name().parse(), Ok(algorithm));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{evaluation::evaluate_position, Position};
+
+use super::{alpha_beta::SearchParams, search_results::SearchState};
+
+/// How many evaluations the cache holds. A power of two, so keys index it with a mask.
+const EVAL_CACHE_SIZE: usize = 1 << 14;
+
+/// A small hash table of static evaluations by Zobrist key. Transpositions near the leaves
+/// reach the same positions over and over, and quiescence and the pruning heuristics would
+/// otherwise evaluate each of them from scratch every time.
+///
+/// Each key has one slot, and a new evaluation replaces whatever was there. Evaluations depend
+/// on the search parameters, so a cache is only good for searches that share them.
+///
+/// The slots are only allocated when the first evaluation is stored, so a `SearchState` that
+/// never evaluates anything, or has the cache turned off, doesn't pay for them.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    entries: Vec<Option<(u64, i32)>>,
+}
+
+impl EvalCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    fn index(key: u64) -> usize {
+        key as usize & (EVAL_CACHE_SIZE - 1)
+    }
+
+    /// The evaluation stored for the key, if it hasn't been replaced.
+    pub fn get(&self, key: u64) -> Option<i32> {
+        match self.entries.get(Self::index(key)) {
+            Some(&Some((entry_key, score))) if entry_key == key => Some(score),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, score: i32) {
+        if self.entries.is_empty() {
+            self.entries = vec![None; EVAL_CACHE_SIZE];
+        }
+
+        self.entries[Self::index(key)] = Some((key, score));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The static evaluation of a position, from the cache if it has been evaluated earlier in the
+/// search. Positions are looked up by their Zobrist key, which is kept up to date as moves are
+/// made, so a probe costs a few XORs.
+pub fn cached_evaluation(
+    position: &Position,
+    state: &mut SearchState,
+    params: &SearchParams,
+) -> i32 {
+    if !params.features.enable_eval_cache {
+        return evaluate_position(position, params.game_type, params);
+    }
+
+    let key = position.zobrist_key();
+
+    if let Some(score) = state.eval_cache.get(key) {
+        return score;
+    }
+
+    let score = evaluate_position(position, params.game_type, params);
+    state.eval_cache.insert(key, score);
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::GameType,
+        search::{alpha_beta, transposition_table::TranspositionTable},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cache = EvalCache::new();
+        assert_eq!(cache.get(42), None);
+        assert_eq!(cache.entries.capacity(), 0);
+
+        cache.insert(42, 150);
+        assert_eq!(cache.get(42), Some(150));
+
+        // Same slot, different key
+        let clashing = 42 + EVAL_CACHE_SIZE as u64;
+        assert_eq!(cache.get(clashing), None);
+
+        cache.insert(clashing, -30);
+        assert_eq!(cache.get(clashing), Some(-30));
+        assert_eq!(cache.get(42), None);
+
+        cache.clear();
+        assert_eq!(cache.get(clashing), None);
+    }
+
+    #[test]
+    fn test_cache_does_not_change_search() {
+        let position = Position::parse_from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+
+        let search = |enable_eval_cache: bool| {
+            let params = SearchParams::builder()
+                .game_type(GameType::Classic)
+                .depth(3)
+                .feature(|f| f.enable_eval_cache = enable_eval_cache)
+                .build()
+                .unwrap();
+
+            let mut transposition_table = TranspositionTable::new();
+            let mut state = SearchState::new(&mut transposition_table);
+            let results = alpha_beta::search(&position, &mut state, params, 0).unwrap();
+
+            (results.best_move, results.score, results.nodes_searched)
+        };
+
+        assert_eq!(search(true), search(false));
+    }
+}
@@ -3,20 +3,29 @@ use std::collections::HashMap;
 use tracing::trace;
 
 use crate::{
-    features::Features, piece_move::GameType, position::HashablePosition, Color, PieceMove,
-    Position,
+    features::{EvaluationWeights, Features},
+    opening_book::BookPlayer,
+    piece_move::{GameType, MoveList},
+    position::{repetition_count, GameStatus, HashablePosition},
+    Color, PieceMove, Position,
 };
 
 use super::{
     alpha_beta::SearchParams,
+    backend::SearchAlgorithm,
     iterative_deepening::{IterativeDeepeningData, OnNewBestMove},
-    search_results::SearchStats,
+    search_results::{SearchState, SearchStats},
+    strength::StrengthLimit,
+    transposition_table::TranspositionTable,
 };
 
 pub struct GameState {
     /// A map from positions to the number of times that position has been visited.
     pub positions: HashMap<HashablePosition, usize>,
 
+    /// The Zobrist keys of every position in the game, including the current position.
+    pub history: Vec<u64>,
+
     /// The current position.
     pub current_position: Position,
 
@@ -43,13 +52,25 @@ pub struct GameState {
 
     pub features: Features,
 
+    pub weights: EvaluationWeights,
+
     pub time_limit_ms: u64,
+
+    /// The search algorithm to find moves with.
+    pub search_algorithm: SearchAlgorithm,
+
+    /// Plays below full strength when set.
+    pub strength_limit: Option<StrengthLimit>,
+
+    /// The opening book to play from before searching, if any.
+    pub book: Option<BookPlayer>,
 }
 
 impl GameState {
     pub fn new() -> Self {
         let mut state = Self {
             positions: HashMap::new(),
+            history: Vec::new(),
             current_position: Position::start_position(),
             num_plies: 0,
             current_turn: Color::White,
@@ -60,12 +81,17 @@ impl GameState {
             game_type: GameType::Classic,
             debug_logs_verbose: false,
             features: Features::default(),
+            weights: EvaluationWeights::default(),
             time_limit_ms: 5_000,
+            search_algorithm: SearchAlgorithm::default(),
+            strength_limit: None,
+            book: None,
         };
 
         state
             .positions
             .insert(state.current_position.to_hashable(), 1);
+        state.history.push(state.current_position.zobrist_key());
 
         state
     }
@@ -76,18 +102,47 @@ impl GameState {
             ..Default::default()
         };
 
+        state.positions.clear();
         state
             .positions
             .insert(state.current_position.to_hashable(), 1);
+        state.history = vec![state.current_position.zobrist_key()];
 
         state
     }
 
+    /// Starts a new game from the start position, keeping the engine's settings and the size of
+    /// its transposition table, which is cleared.
+    pub fn start_new_game(&mut self) {
+        let mut transposition_table = std::mem::replace(
+            &mut self.iterative_deepening_data.transposition_table,
+            TranspositionTable::new(),
+        );
+        transposition_table.clear();
+
+        let mut book = self.book.take();
+        if let Some(book) = &mut book {
+            book.new_game();
+        }
+
+        *self = Self {
+            game_type: self.game_type,
+            debug_logs_verbose: self.debug_logs_verbose,
+            features: self.features,
+            weights: self.weights,
+            search_algorithm: self.search_algorithm,
+            strength_limit: self.strength_limit,
+            book,
+            ..Self::new()
+        };
+        self.iterative_deepening_data.transposition_table = transposition_table;
+    }
+
     pub fn calculate_time_for_move(&self) -> u64 {
         let base_time = self.time_limit_ms;
-        let legal_moves = self
-            .current_position
-            .get_all_legal_moves(self.game_type)
+        let mut legal_moves = MoveList::new();
+        self.current_position
+            .get_all_legal_moves(self.game_type, &mut legal_moves)
             .unwrap();
 
         // Adjust time based on game phase and complexity
@@ -121,6 +176,7 @@ impl GameState {
             .positions
             .entry(self.current_position.to_hashable())
             .or_insert(0) += 1;
+        self.history.push(self.current_position.zobrist_key());
 
         self.num_plies += 1;
         self.iterative_deepening_data.ply = self.num_plies;
@@ -134,6 +190,25 @@ impl GameState {
         Ok(())
     }
 
+    /// Sets up a position to play on from, given from the perspective of its side to move, as
+    /// if no moves had been played before it.
+    pub fn set_position(&mut self, position: Position) {
+        self.current_turn = position.true_active_color;
+        self.move_number = position.fullmove_number.max(1) as usize;
+        self.current_position = position;
+        self.num_plies = 0;
+        self.iterative_deepening_data.ply = 0;
+        self.reset_history();
+    }
+
+    /// Forgets the moves played so far, starting the position history again from the current position.
+    pub fn reset_history(&mut self) {
+        self.positions.clear();
+        self.positions
+            .insert(self.current_position.to_hashable(), 1);
+        self.history = vec![self.current_position.zobrist_key()];
+    }
+
     pub fn times_current_position_seen(&self) -> usize {
         *self
             .positions
@@ -141,6 +216,22 @@ impl GameState {
             .unwrap_or(&0)
     }
 
+    /// Whether the game is over at the current position, taking repetitions over the whole game into account.
+    pub fn game_status(&self) -> Result<GameStatus, anyhow::Error> {
+        self.current_position
+            .game_status(self.game_type, &self.history)
+    }
+
+    /// The number of times the current position has occurred in the game.
+    pub fn repetition_count(&self) -> usize {
+        repetition_count(&self.history, self.current_position.zobrist_key())
+    }
+
+    /// Whether the side to move can claim a draw by threefold repetition.
+    pub fn can_claim_draw(&self) -> bool {
+        self.current_position.is_threefold_repetition(&self.history)
+    }
+
     pub fn previous_score(&self, color: Color) -> Option<i32> {
         match color {
             Color::White => self.previous_scores.0,
@@ -160,27 +251,43 @@ impl GameState {
     }
 
     pub fn search_and_apply(&mut self) -> Result<(PieceMove, SearchStats), anyhow::Error> {
-        let params = SearchParams {
-            depth: self.search_depth,
-            game_type: self.game_type,
-            previous_score: self.previous_score(self.current_turn),
-            debug_print: true,
-            features: self.features,
-            debug_print_verbose: self.debug_logs_verbose,
-            time_limit: self.time_limit_ms,
-            ..Default::default()
-        };
+        if let Some(book_move) = self.book_move()? {
+            trace!("Playing book move {}", book_move);
+            self.apply_move(book_move)?;
+            return Ok((book_move, SearchStats::default()));
+        }
+
+        let params = SearchParams::builder()
+            .depth(self.search_depth)
+            .game_type(self.game_type)
+            .previous_score(self.previous_score(self.current_turn))
+            .debug_print(true)
+            .features(self.features)
+            .weights(self.weights)
+            .algorithm(self.search_algorithm)
+            .debug_print_verbose(self.debug_logs_verbose)
+            .time_ms(self.time_limit_ms)
+            .build()?;
+
+        if let Some(strength_limit) = self.strength_limit {
+            return self.search_limited_and_apply(strength_limit, params);
+        }
 
         self.iterative_deepening_data
             .update_position(self.current_position.clone());
+        self.iterative_deepening_data
+            .update_history(self.history.clone());
         self.iterative_deepening_data.search(params);
         let stats = self.iterative_deepening_data.stats.clone();
 
         if let Some(best_move) = self.iterative_deepening_data.best_move {
-            self.update_previous_score(
-                self.current_turn,
-                self.iterative_deepening_data.best_score.unwrap(),
-            );
+            // A search stopped before finishing a depth has a move but no score for it
+            if stats.depth > 0 {
+                self.update_previous_score(
+                    self.current_turn,
+                    self.iterative_deepening_data.best_score.unwrap(),
+                );
+            }
             self.apply_move(best_move)?;
         } else {
             return Err(anyhow::anyhow!("No best move found"));
@@ -198,4 +305,102 @@ impl GameState {
 
         Ok((self.iterative_deepening_data.best_move.unwrap(), stats))
     }
+
+    /// The move the opening book plays in the current position, if there's a book and the game
+    /// hasn't left it.
+    pub fn book_move(&mut self) -> Result<Option<PieceMove>, anyhow::Error> {
+        let Some(book) = &mut self.book else {
+            return Ok(None);
+        };
+
+        // Plies since the history started, which each UCI position command starts again
+        book.next_move(
+            &self.current_position,
+            self.history.len() - 1,
+            self.game_type,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Picks a move at the limited strength and applies it. Every move is scored at the
+    /// limited depth, so there are no deepening iterations to report.
+    fn search_limited_and_apply(
+        &mut self,
+        strength_limit: StrengthLimit,
+        params: SearchParams,
+    ) -> Result<(PieceMove, SearchStats), anyhow::Error> {
+        let data = &mut self.iterative_deepening_data;
+        data.transposition_table.new_search();
+
+        let mut state = SearchState::new(&mut data.transposition_table);
+        state.data.history = self.history.clone();
+        state.data.stop = data.stop.clone();
+
+        let results = strength_limit
+            .search(&self.current_position, &mut state, params)
+            .map_err(|e| anyhow::anyhow!("Search failed: {:?}", e))?;
+
+        let best_move = results
+            .best_move
+            .ok_or_else(|| anyhow::anyhow!("No best move found"))?;
+
+        let stats = SearchStats {
+            nodes_searched: results.nodes_searched,
+            cached_positions: results.cached_positions,
+            pruned: results.pruned,
+            time_taken_ms: results.time_taken_ms,
+            depth: results.depth,
+            statistics: results.statistics,
+            ..SearchStats::default()
+        };
+
+        self.update_previous_score(self.current_turn, results.score);
+        self.apply_move(best_move)?;
+
+        Ok((best_move, stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_algebraic(game_state: &mut GameState, moves: &[&str]) {
+        for mv in moves {
+            let piece_move = if game_state.current_turn == Color::White {
+                PieceMove::from_algebraic(&game_state.current_position, mv, game_state.game_type)
+            } else {
+                PieceMove::from_algebraic_inverted(
+                    &game_state.current_position,
+                    mv,
+                    game_state.game_type,
+                )
+            }
+            .unwrap();
+
+            game_state.apply_move(piece_move).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_repetition_across_game() {
+        let mut game_state = GameState::new();
+
+        apply_algebraic(&mut game_state, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(game_state.repetition_count(), 2);
+        assert!(!game_state.can_claim_draw());
+        assert_eq!(game_state.game_status().unwrap(), GameStatus::Ongoing);
+
+        apply_algebraic(&mut game_state, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(game_state.repetition_count(), 3);
+        assert!(game_state.can_claim_draw());
+        assert_eq!(
+            game_state.game_status().unwrap(),
+            GameStatus::DrawByRepetition
+        );
+
+        game_state.reset_history();
+        assert_eq!(game_state.history.len(), 1);
+        assert_eq!(game_state.game_status().unwrap(), GameStatus::Ongoing);
+    }
 }
@@ -1,11 +1,68 @@
 use crate::PieceMove;
 
+/// The largest magnitude a continuation history score can reach.
+const MAX_CONTINUATION_SCORE: i32 = 16384;
+
 #[derive(Debug, Clone)]
 pub struct HistoryTable {
     // Track success of [piece_type][to_square] combinations
     pub success: [[i32; 64]; 6], // 6 piece types, 64 squares
     // Track how many times we tried each move
     pub tried: [[i32; 64]; 6],
+
+    /// How well moves did as replies to the move one ply before them.
+    pub counter_moves: ContinuationHistory,
+
+    /// How well moves did as follow-ups to the side's own move two plies before them.
+    pub follow_up_moves: ContinuationHistory,
+}
+
+/// Scores for pairs of moves, indexed by the piece type and destination of an earlier move,
+/// then of the move played after it. Captures the ordering knowledge that history alone
+/// can't, like which reply refutes a particular move.
+#[derive(Debug, Clone)]
+pub struct ContinuationHistory {
+    scores: Vec<i32>,
+}
+
+impl ContinuationHistory {
+    pub fn new() -> Self {
+        Self {
+            scores: vec![0; 6 * 64 * 6 * 64],
+        }
+    }
+
+    fn index(previous: &PieceMove, mv: &PieceMove) -> usize {
+        let previous_idx = previous.piece_type as usize * 64 + previous.to.0 as usize;
+        let move_idx = mv.piece_type as usize * 64 + mv.to.0 as usize;
+
+        previous_idx * 6 * 64 + move_idx
+    }
+
+    pub fn get(&self, previous: &PieceMove, mv: &PieceMove) -> i32 {
+        self.scores[Self::index(previous, mv)]
+    }
+
+    /// Moves the score of `mv` after `previous` towards +/-`MAX_CONTINUATION_SCORE`. Scores
+    /// that are already large move less, so they stay in range and can still be unlearned.
+    pub fn update(&mut self, previous: &PieceMove, mv: &PieceMove, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_CONTINUATION_SCORE, MAX_CONTINUATION_SCORE);
+        let score = &mut self.scores[Self::index(previous, mv)];
+
+        *score += bonus - *score * bonus.abs() / MAX_CONTINUATION_SCORE;
+    }
+
+    fn age(&mut self) {
+        for score in self.scores.iter_mut() {
+            *score /= 2;
+        }
+    }
+}
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HistoryTable {
@@ -13,6 +70,8 @@ impl HistoryTable {
         Self {
             success: [[0; 64]; 6],
             tried: [[0; 64]; 6],
+            counter_moves: ContinuationHistory::new(),
+            follow_up_moves: ContinuationHistory::new(),
         }
     }
 
@@ -27,6 +86,39 @@ impl HistoryTable {
         }
     }
 
+    /// Updates the continuation history of `mv`, given the moves on the search path before it,
+    /// the last being the opponent's move it replies to. Null moves are `None`.
+    pub fn update_continuation_history(
+        &mut self,
+        previous_moves: &[Option<PieceMove>],
+        mv: &PieceMove,
+        depth: u32,
+        caused_cutoff: bool,
+    ) {
+        let bonus = (depth * depth) as i32;
+        let bonus = if caused_cutoff { bonus } else { -bonus };
+
+        if let Some(Some(previous)) = previous_moves.last() {
+            self.counter_moves.update(previous, mv, bonus);
+        }
+
+        if let Some(Some(previous)) = previous_moves.iter().rev().nth(1) {
+            self.follow_up_moves.update(previous, mv, bonus);
+        }
+    }
+
+    /// Halves all counts, so that statistics from earlier searches count for less than new ones.
+    pub fn age(&mut self) {
+        for row in self.success.iter_mut().chain(self.tried.iter_mut()) {
+            for count in row.iter_mut() {
+                *count /= 2;
+            }
+        }
+
+        self.counter_moves.age();
+        self.follow_up_moves.age();
+    }
+
     pub fn get_history_score(&self, mv: &PieceMove) -> i32 {
         let piece_idx = mv.piece_type as usize;
         let square_idx = mv.to.0 as usize;
@@ -40,4 +132,66 @@ impl HistoryTable {
 
         (successes * 2000) / attempts
     }
+
+    /// The continuation history score of `mv` after the moves on the search path before it.
+    pub fn get_continuation_score(
+        &self,
+        previous_moves: &[Option<PieceMove>],
+        mv: &PieceMove,
+    ) -> i32 {
+        let mut score = 0;
+
+        if let Some(Some(previous)) = previous_moves.last() {
+            score += self.counter_moves.get(previous, mv);
+        }
+
+        if let Some(Some(previous)) = previous_moves.iter().rev().nth(1) {
+            score += self.follow_up_moves.get(previous, mv);
+        }
+
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{piece_move::GameType, Position};
+
+    use super::*;
+
+    #[test]
+    fn test_continuation_history() {
+        let position = Position::start_position();
+        let e4 = PieceMove::from_algebraic(&position, "e4", GameType::Classic).unwrap();
+        let nf3 = PieceMove::from_algebraic(&position, "Nf3", GameType::Classic).unwrap();
+        let d4 = PieceMove::from_algebraic(&position, "d4", GameType::Classic).unwrap();
+        let nc3 = PieceMove::from_algebraic(&position, "Nc3", GameType::Classic).unwrap();
+
+        let mut history = HistoryTable::new();
+        history.update_continuation_history(&[Some(e4)], &nf3, 4, true);
+
+        assert_eq!(history.get_continuation_score(&[Some(e4)], &nf3), 16);
+        assert_eq!(history.get_continuation_score(&[Some(d4)], &nf3), 0);
+        assert_eq!(history.get_continuation_score(&[None], &nf3), 0);
+        assert_eq!(history.get_continuation_score(&[], &nf3), 0);
+
+        // The move two plies back counts through the follow-up table
+        history.update_continuation_history(&[Some(e4), Some(d4)], &nc3, 4, false);
+        assert_eq!(history.counter_moves.get(&d4, &nc3), -16);
+        assert_eq!(history.follow_up_moves.get(&e4, &nc3), -16);
+        assert_eq!(
+            history.get_continuation_score(&[Some(e4), Some(d4)], &nc3),
+            -32
+        );
+        assert_eq!(history.get_continuation_score(&[None, Some(d4)], &nc3), -16);
+
+        // Scores never leave their range however often they're updated
+        for _ in 0..1000 {
+            history.update_continuation_history(&[Some(e4)], &nf3, 20, true);
+        }
+        assert!(history.counter_moves.get(&e4, &nf3) <= MAX_CONTINUATION_SCORE);
+
+        history.age();
+        assert!(history.counter_moves.get(&e4, &nf3) <= MAX_CONTINUATION_SCORE / 2);
+    }
 }
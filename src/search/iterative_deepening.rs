@@ -1,16 +1,27 @@
-use std::time::Instant;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use tracing::trace;
 
-use crate::{PieceMove, Position};
+use crate::{piece_move::MoveList, PieceMove, Position};
 
+#[cfg(feature = "syzygy")]
+use super::tablebase::Tablebase;
 use super::{
     alpha_beta::{self, SearchParams},
-    search_results::{SearchResults, SearchState, SearchStats},
+    backend,
+    search_results::{SearchInfo, SearchResults, SearchState, SearchStats},
     transposition_table::TranspositionTable,
 };
 
-pub type OnNewBestMove = dyn Fn(PieceMove, i32) + Send + Sync;
+/// Called whenever the best move at the root changes during a search, and whenever a depth of
+/// the search finishes.
+pub type OnNewBestMove = dyn Fn(&SearchInfo) + Send + Sync;
 
 pub struct IterativeDeepeningData {
     pub current_position: Position,
@@ -26,7 +37,21 @@ pub struct IterativeDeepeningData {
     pub previous_pv: Option<Vec<PieceMove>>,
     pub ply: usize,
 
+    /// Zobrist keys of the positions played in the game up to and including the current position.
+    pub history: Vec<u64>,
+
     pub on_new_best_move: Option<Box<OnNewBestMove>>,
+
+    /// Set to stop the running search, which keeps the best move of the deepest finished depth,
+    /// or if it hasn't finished one, the best move it had found so far.
+    pub stop: Arc<AtomicBool>,
+
+    /// The most nodes to search, over all depths of a search.
+    pub node_limit: u64,
+
+    /// Endgame tablebases for the search to look positions up in.
+    #[cfg(feature = "syzygy")]
+    pub tablebase: Option<Arc<Tablebase>>,
 }
 
 const WINDOW_SIZE: i32 = 50;
@@ -42,8 +67,13 @@ impl IterativeDeepeningData {
             previous_pv: None,
             on_new_best_move: None,
             ply: 0,
+            history: Vec::new(),
             prev_alpha: -2_000_000,
             prev_beta: 2_000_000,
+            stop: Arc::new(AtomicBool::new(false)),
+            node_limit: u64::MAX,
+            #[cfg(feature = "syzygy")]
+            tablebase: None,
         }
     }
 
@@ -51,12 +81,24 @@ impl IterativeDeepeningData {
         self.current_position = position;
     }
 
+    pub fn update_history(&mut self, history: Vec<u64>) {
+        self.history = history;
+    }
+
     pub fn search(&mut self, params: SearchParams) {
         let mut depth = 1;
         let start_time = Instant::now();
+        let start_nodes = self.stats.nodes_searched as u64;
+
+        // Results of an earlier search are for a different position
+        self.best_move = None;
+        self.transposition_table.new_search();
+        self.stats.depth = 0;
+        self.stats.effective_branching_factor = 0.0;
+        let mut previous_iteration_nodes = None;
 
         loop {
-            if depth > params.depth {
+            if depth > params.depth || self.stop.load(Ordering::Relaxed) {
                 break;
             }
 
@@ -65,7 +107,20 @@ impl IterativeDeepeningData {
                 break;
             }
 
-            let search_results = self.search_at_depth(depth, start_time, &params, self.ply);
+            let nodes_searched = self.stats.nodes_searched as u64 - start_nodes;
+            let node_limit = self.node_limit.saturating_sub(nodes_searched);
+            if node_limit == 0 {
+                break;
+            }
+
+            let search_results = self.search_at_depth(
+                depth,
+                start_time,
+                &params,
+                self.ply,
+                node_limit.min(u32::MAX as u64) as u32,
+                start_nodes,
+            );
 
             match search_results {
                 Ok(search_results) => {
@@ -87,15 +142,48 @@ impl IterativeDeepeningData {
                     self.best_score = Some(search_results.score);
                     self.previous_pv = search_results.principal_variation;
 
+                    self.stats.depth = depth;
+                    if let Some(previous_nodes) = previous_iteration_nodes {
+                        self.stats.effective_branching_factor =
+                            search_results.nodes_searched as f64 / previous_nodes as f64;
+                    }
+                    previous_iteration_nodes = Some(search_results.nodes_searched.max(1));
+
+                    if let Some(on_new_best_move) = &self.on_new_best_move {
+                        on_new_best_move(&SearchInfo {
+                            depth,
+                            seldepth: search_results.statistics.seldepth,
+                            score: search_results.score,
+                            nodes: (self.stats.nodes_searched as u64 - start_nodes) as u32,
+                            time_ms: start_time.elapsed().as_millis(),
+                            hashfull: self.transposition_table.hashfull(),
+                            principal_variation: self.previous_pv.clone().unwrap_or_default(),
+                        });
+                    }
+
                     depth += 1;
                 }
                 Err(e) => match e {
-                    alpha_beta::AlphaBetaError::Timeout => {
+                    alpha_beta::AlphaBetaError::Timeout
+                    | alpha_beta::AlphaBetaError::Stopped
+                    | alpha_beta::AlphaBetaError::NodeLimit => {
                         break;
                     }
                 },
             }
         }
+
+        // Stopped before searching a single move: any legal move is better than none
+        if self.best_move.is_none() {
+            let mut moves = MoveList::new();
+            if self
+                .current_position
+                .get_all_legal_moves(params.game_type, &mut moves)
+                .is_ok()
+            {
+                self.best_move = moves.first().copied();
+            }
+        }
     }
 
     fn search_at_depth(
@@ -104,13 +192,32 @@ impl IterativeDeepeningData {
         start_time: Instant,
         params_base: &SearchParams,
         ply: usize,
+        node_limit: u32,
+        start_nodes: u64,
     ) -> Result<SearchResults, alpha_beta::AlphaBetaError> {
         let mut state = SearchState::new(&mut self.transposition_table);
         state.data.start_time = start_time;
         state.data.time_limit = params_base.time_limit;
+        state.data.stop = self.stop.clone();
+        state.data.node_limit = node_limit;
         state.data.previous_pv = self.previous_pv.clone();
+        state.data.history = self.history.clone();
+        #[cfg(feature = "syzygy")]
+        {
+            state.data.tablebase = self.tablebase.clone();
+        }
 
-        if let Some(on_new_best_move) = self.on_new_best_move.as_deref() {
+        // The nodes of the depths already searched count towards the search's nodes
+        let nodes_before = (self.stats.nodes_searched as u64 - start_nodes) as u32;
+        let on_new_best_move = self.on_new_best_move.as_deref().map(|on_new_best_move| {
+            move |info: &SearchInfo| {
+                on_new_best_move(&SearchInfo {
+                    nodes: nodes_before + info.nodes,
+                    ..info.clone()
+                })
+            }
+        });
+        if let Some(on_new_best_move) = &on_new_best_move {
             state.callbacks.on_new_best_move = Some(on_new_best_move);
         }
 
@@ -120,7 +227,12 @@ impl IterativeDeepeningData {
         params.initial_beta = self.prev_beta + WINDOW_SIZE;
         params.previous_score = self.best_score;
 
-        let results = alpha_beta::search(&self.current_position, &mut state, params, ply);
+        let results = backend::search(&self.current_position, &mut state, params, ply);
+
+        // A search stopped during its first depth still has the best move at the root so far
+        if results.is_err() && self.best_move.is_none() {
+            self.best_move = state.data.best_move_so_far;
+        }
 
         self.stats.add(state.to_stats());
 
@@ -134,6 +246,8 @@ impl IterativeDeepeningData {
 
 #[cfg(test)]
 pub mod tests {
+    use std::sync::atomic::Ordering;
+
     use crate::search::alpha_beta::SearchParams;
 
     use super::IterativeDeepeningData;
@@ -143,12 +257,24 @@ pub mod tests {
         let mut data = IterativeDeepeningData::new();
 
         data.update_position("2K5/7p/RPp5/1rPP4/1b4p1/PbN5/3k4/2q4Q w - - 0 1".into());
-        data.search(SearchParams {
-            depth: 5,
-            time_limit: 1000,
-            ..Default::default()
-        });
+        data.search(
+            SearchParams::builder()
+                .depth(5)
+                .time_ms(1000)
+                .build()
+                .unwrap(),
+        );
 
         println!("{}", data.get_best_move().unwrap());
     }
+
+    #[test]
+    pub fn stopped_search_still_has_a_move() {
+        let mut data = IterativeDeepeningData::new();
+        data.stop.store(true, Ordering::Relaxed);
+
+        data.search(SearchParams::builder().depth(5).build().unwrap());
+
+        assert!(data.get_best_move().is_some());
+    }
 }
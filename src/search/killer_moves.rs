@@ -1,5 +1,6 @@
 use crate::PieceMove;
 
+#[derive(Debug, Clone)]
 pub struct KillerMoves {
     moves: Vec<[Option<PieceMove>; 2]>, // Store 2 killer moves per ply
     max_ply: usize,
@@ -33,6 +34,14 @@ impl KillerMoves {
         self.moves[ply][0] = Some(mv);
     }
 
+    /// Moves the killer moves `plies` plies closer to the root, for reuse after that many
+    /// moves have been played on the board.
+    pub fn shift(&mut self, plies: usize) {
+        let plies = plies.min(self.max_ply);
+        self.moves.drain(0..plies);
+        self.moves.resize(self.max_ply, [None, None]);
+    }
+
     pub fn get_killers(&self, ply: usize) -> [Option<PieceMove>; 2] {
         if ply >= self.max_ply {
             return [None, None];
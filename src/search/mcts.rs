@@ -0,0 +1,456 @@
+//! Monte Carlo tree search with PUCT selection, using the static evaluation both as the prior
+//! for each move and as the value of new leaves instead of random playouts.
+//!
+//! Rescues and drops give Rescue positions many more moves than classic chess, most of them
+//! poor. Alpha-beta has to look at all of them at every depth, while MCTS spends its playouts
+//! on the moves the evaluation likes and the moves that keep turning out well.
+
+use std::sync::atomic::Ordering;
+
+use crate::{piece_move::MoveList, PieceMove, Position};
+
+use super::{
+    alpha_beta::{AlphaBetaError, MoveScore, SearchParams},
+    backend::SearchBackend,
+    eval_cache::cached_evaluation,
+    score::{mate_in, mated_in},
+    search_results::{SearchResults, SearchState},
+};
+
+/// How strongly selection explores moves with few visits relative to their prior.
+const EXPLORATION: f32 = 1.5;
+
+/// The centipawn difference that makes one move's prior e times another's.
+const PRIOR_TEMPERATURE: f32 = 100.0;
+
+/// The centipawn scale of the squashing of evaluations into values between -1 and 1.
+const VALUE_SCALE: f32 = 400.0;
+
+/// The playouts of a search at depth 1, doubling with each depth so that iterative deepening
+/// spends about as much time on the last depth as on all the ones before it.
+const BASE_PLAYOUTS: u32 = 32;
+
+/// The depth past which searches get no more playouts.
+const MAX_PLAYOUT_DEPTH: u32 = 16;
+
+struct Node {
+    /// The move from the parent, from the parent's side to move's perspective.
+    mv: Option<PieceMove>,
+
+    /// The parent's side to move's static score after `mv`, in centipawns.
+    score: i32,
+
+    prior: f32,
+    visits: u32,
+
+    /// The sum of the playouts' values for the parent's side to move.
+    value_sum: f32,
+
+    /// The indexes of the children in the tree, once the node has been expanded.
+    children: Option<std::ops::Range<usize>>,
+
+    /// The value for the node's side to move if the game is over in the node.
+    terminal: Option<f32>,
+}
+
+impl Node {
+    fn new(mv: Option<PieceMove>, score: i32, prior: f32) -> Self {
+        Self {
+            mv,
+            score,
+            prior,
+            visits: 0,
+            value_sum: 0.0,
+            children: None,
+            terminal: None,
+        }
+    }
+
+    /// The average value of the playouts through the node, for the parent's side to move.
+    fn q(&self) -> f32 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value_sum / self.visits as f32
+        }
+    }
+
+    /// Whether the parent's side to move mates by playing the node's move.
+    fn is_mate(&self) -> bool {
+        self.terminal == Some(-1.0)
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(None, 0, 1.0)],
+        }
+    }
+
+    fn children(&self, node: usize) -> std::ops::Range<usize> {
+        self.nodes[node].children.clone().unwrap_or(0..0)
+    }
+
+    /// The child with the best upper confidence bound, mating moves first.
+    fn select(&self, node: usize) -> usize {
+        let sqrt_visits = (self.nodes[node].visits.max(1) as f32).sqrt();
+
+        self.children(node)
+            .max_by(|&a, &b| {
+                let ucb = |child: usize| {
+                    let child = &self.nodes[child];
+                    if child.is_mate() {
+                        return f32::INFINITY;
+                    }
+
+                    child.q() + EXPLORATION * child.prior * sqrt_visits / (1 + child.visits) as f32
+                };
+
+                ucb(a).total_cmp(&ucb(b))
+            })
+            .unwrap()
+    }
+
+    /// The child to play: a mate if there is one, otherwise the most visited child.
+    fn best_child(&self, node: usize) -> Option<usize> {
+        self.children(node).max_by_key(|&child| {
+            let child = &self.nodes[child];
+            (child.is_mate(), child.visits, child.score)
+        })
+    }
+
+    /// The most visited line from `node`.
+    fn principal_variation(&self, mut node: usize) -> Vec<PieceMove> {
+        let mut pv = Vec::new();
+
+        while let Some(child) = self.best_child(node) {
+            if self.nodes[child].visits == 0 && !self.nodes[child].is_mate() {
+                break;
+            }
+
+            pv.push(self.nodes[child].mv.unwrap());
+            node = child;
+        }
+
+        pv
+    }
+
+    /// The score of playing the child, for the parent's side to move.
+    fn child_score(&self, child: usize) -> i32 {
+        let node = &self.nodes[child];
+
+        if node.is_mate() {
+            mate_in(1)
+        } else if node.visits == 0 {
+            node.score
+        } else {
+            to_centipawns(node.q())
+        }
+    }
+
+    /// Adds the children of a leaf and returns its value for its side to move.
+    fn expand(
+        &mut self,
+        node: usize,
+        position: &mut Position,
+        state: &mut SearchState,
+        params: &SearchParams,
+    ) -> f32 {
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(params.game_type, &mut moves)
+            .unwrap();
+
+        if moves.is_empty() {
+            let value = if position.is_king_in_check().unwrap() {
+                -1.0
+            } else {
+                0.0
+            };
+            self.nodes[node].terminal = Some(value);
+            return value;
+        }
+
+        if position.is_insufficient_material(params.game_type) {
+            self.nodes[node].terminal = Some(0.0);
+            return 0.0;
+        }
+
+        let scores: Vec<i32> = moves
+            .iter()
+            .map(|&mv| {
+                let restore = position.apply_move(mv).unwrap();
+                position.invert();
+                let score = -cached_evaluation(position, state, params);
+                position.invert();
+                position.unapply_move(mv, restore).unwrap();
+                score
+            })
+            .collect();
+
+        // Softmax of the scores, relative to the best so the exponents can't overflow
+        let best = *scores.iter().max().unwrap();
+        let weights: Vec<f32> = scores
+            .iter()
+            .map(|&score| ((score - best) as f32 / PRIOR_TEMPERATURE).exp())
+            .collect();
+        let total: f32 = weights.iter().sum();
+
+        let first = self.nodes.len();
+        for ((&mv, &score), weight) in moves.iter().zip(&scores).zip(weights) {
+            self.nodes.push(Node::new(Some(mv), score, weight / total));
+        }
+        self.nodes[node].children = Some(first..self.nodes.len());
+
+        // The best reply by the evaluation stands in for a playout
+        to_value(best)
+    }
+
+    /// Runs one playout from the root and backs its value up the path it took.
+    fn playout(
+        &mut self,
+        root: &Position,
+        state: &mut SearchState,
+        params: &SearchParams,
+    ) -> Result<(), AlphaBetaError> {
+        if state.data.start_time.elapsed().as_millis() >= state.data.time_limit as u128 {
+            return Err(AlphaBetaError::Timeout);
+        }
+
+        if state.data.stop.load(Ordering::Relaxed) {
+            return Err(AlphaBetaError::Stopped);
+        }
+
+        if state.data.nodes_searched >= state.data.node_limit {
+            return Err(AlphaBetaError::NodeLimit);
+        }
+
+        let mut position = root.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+
+        while self.nodes[node].children.is_some() && self.nodes[node].terminal.is_none() {
+            node = self.select(node);
+            position.apply_move(self.nodes[node].mv.unwrap()).unwrap();
+            position.invert();
+            path.push(node);
+        }
+
+        let value = match self.nodes[node].terminal {
+            Some(value) => value,
+            None => {
+                state.data.nodes_searched += 1;
+                state.data.statistics.record_ply(path.len() as u32 - 1);
+                self.expand(node, &mut position, state, params)
+            }
+        };
+
+        // Each node's value is for the side that moved into it, the other side from the one to
+        // move in it
+        let mut value = -value;
+        for &node in path.iter().rev() {
+            self.nodes[node].visits += 1;
+            self.nodes[node].value_sum += value;
+            value = -value;
+        }
+
+        Ok(())
+    }
+
+    fn run(
+        &mut self,
+        position: &Position,
+        state: &mut SearchState,
+        params: &SearchParams,
+    ) -> Result<(), AlphaBetaError> {
+        let playouts = BASE_PLAYOUTS << (params.depth.clamp(1, MAX_PLAYOUT_DEPTH) - 1);
+
+        for _ in 0..playouts {
+            self.playout(position, state, params)?;
+
+            // Nothing left to search once the root is over or has a mate
+            if self.nodes[0].terminal.is_some()
+                || self.children(0).any(|child| self.nodes[child].is_mate())
+            {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Squashes centipawns into a value between -1 and 1.
+fn to_value(score: i32) -> f32 {
+    (score as f32 / VALUE_SCALE).tanh()
+}
+
+/// The centipawns a value between -1 and 1 stands for.
+fn to_centipawns(value: f32) -> i32 {
+    (value.clamp(-0.999, 0.999).atanh() * VALUE_SCALE) as i32
+}
+
+/// Monte Carlo tree search. `params.depth` sets the number of playouts rather than a depth in
+/// plies.
+pub struct Mcts;
+
+impl SearchBackend for Mcts {
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        _ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        let mut tree = Tree::new();
+        tree.run(position, state, &params)?;
+
+        let best_child = tree.best_child(0);
+        let score = match best_child {
+            Some(child) => tree.child_score(child),
+            None if tree.nodes[0].terminal == Some(-1.0) => mated_in(0),
+            None => 0,
+        };
+        let principal_variation = tree.principal_variation(0);
+
+        Ok(SearchResults {
+            best_move: best_child.and_then(|child| tree.nodes[child].mv),
+            score,
+            nodes_searched: state.data.nodes_searched,
+            cached_positions: state.data.cached_positions,
+            depth: params.depth,
+            time_taken_ms: state.data.start_time.elapsed().as_millis(),
+            pruned: state.data.pruned,
+            principal_variation: (!principal_variation.is_empty()).then_some(principal_variation),
+            alpha: params.initial_alpha,
+            beta: params.initial_beta,
+            statistics: state.data.statistics,
+        })
+    }
+
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        _ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError> {
+        let mut tree = Tree::new();
+        tree.run(position, state, &params)?;
+
+        let mut scores: Vec<MoveScore> = tree
+            .children(0)
+            .map(|child| {
+                let mv = tree.nodes[child].mv.unwrap();
+                let mut principal_variation = vec![mv];
+                principal_variation.extend(tree.principal_variation(child));
+
+                MoveScore {
+                    mv,
+                    score: tree.child_score(child),
+                    principal_variation: Some(principal_variation),
+                }
+            })
+            .collect();
+
+        if let Some(on_move_scored) = state.callbacks.on_move_scored {
+            for move_score in &scores {
+                on_move_scored(move_score);
+            }
+        }
+
+        scores.sort_by_key(|score| std::cmp::Reverse(score.score));
+
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::GameType,
+        search::{backend::SearchAlgorithm, transposition_table::TranspositionTable},
+    };
+
+    use super::*;
+
+    fn search(fen: &str, game_type: GameType, depth: u32) -> SearchResults {
+        let position = Position::parse_from_fen(fen).unwrap();
+        let params = SearchParams::builder()
+            .depth(depth)
+            .game_type(game_type)
+            .algorithm(SearchAlgorithm::Mcts)
+            .build()
+            .unwrap();
+
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+        Mcts.search(&position, &mut state, params, 0).unwrap()
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let results = search(
+            "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 1",
+            GameType::Classic,
+            4,
+        );
+
+        assert_eq!(results.best_move.unwrap().inverted().to_uci(), "d8h4");
+        assert_eq!(results.score, mate_in(1));
+    }
+
+    #[test]
+    fn test_takes_hanging_queen() {
+        let results = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", GameType::Rescue, 6);
+
+        assert_eq!(results.best_move.unwrap().to_uci(), "d2d5");
+        assert!(results.score > 300);
+        assert_eq!(
+            results.principal_variation.unwrap()[0],
+            results.best_move.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_checkmated_root() {
+        let results = search(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 2",
+            GameType::Classic,
+            2,
+        );
+
+        assert_eq!(results.best_move, None);
+        assert_eq!(results.score, mated_in(0));
+    }
+
+    #[test]
+    fn test_score_all_moves() {
+        let position = Position::parse_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let params = SearchParams::builder()
+            .depth(6)
+            .algorithm(SearchAlgorithm::Mcts)
+            .build()
+            .unwrap();
+
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+        let scores = Mcts
+            .score_all_moves(&position, &mut state, params, 0)
+            .unwrap();
+
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+
+        assert_eq!(scores.len(), moves.len());
+        assert_eq!(scores[0].mv.to_uci(), "d2d5");
+        assert!(scores.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    features::{EvaluationWeights, Features},
+    piece_move::GameType,
+};
+
+use super::{
+    alpha_beta::{SearchParams, MAX_BETA, MIN_ALPHA},
+    backend::SearchAlgorithm,
+};
+
+/// The maximum number of plies a search can reach, including quiescence. The killer move
+/// table is sized to this.
+pub const MAX_SEARCH_PLY: u32 = 64;
+
+/// A fluent builder for `SearchParams` that validates the combination of settings.
+///
+/// ```rust
+/// use rescue_chess::{piece_move::GameType, search::alpha_beta::SearchParams};
+///
+/// let params = SearchParams::builder()
+///     .depth(8)
+///     .time_ms(3000)
+///     .game_type(GameType::Rescue)
+///     .feature(|f| f.enable_lmr = false)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SearchParamsBuilder {
+    params: SearchParams,
+}
+
+impl SearchParams {
+    pub fn builder() -> SearchParamsBuilder {
+        SearchParamsBuilder {
+            params: SearchParams::default(),
+        }
+    }
+}
+
+impl SearchParamsBuilder {
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.params.depth = depth;
+        self
+    }
+
+    pub fn quiescence_depth(mut self, quiescence_depth: u32) -> Self {
+        self.params.quiescence_depth = quiescence_depth;
+        self
+    }
+
+    pub fn time_ms(mut self, time_limit: u64) -> Self {
+        self.params.time_limit = time_limit;
+        self
+    }
+
+    pub fn game_type(mut self, game_type: GameType) -> Self {
+        self.params.game_type = game_type;
+        self
+    }
+
+    /// Sets the initial alpha-beta window.
+    pub fn window(mut self, alpha: i32, beta: i32) -> Self {
+        self.params.initial_alpha = alpha;
+        self.params.initial_beta = beta;
+        self
+    }
+
+    pub fn window_size(mut self, window_size: i32) -> Self {
+        self.params.window_size = window_size;
+        self
+    }
+
+    pub fn previous_score(mut self, previous_score: Option<i32>) -> Self {
+        self.params.previous_score = previous_score;
+        self
+    }
+
+    pub fn features(mut self, features: Features) -> Self {
+        self.params.features = features;
+        self
+    }
+
+    /// Modifies individual features, e.g. `.feature(|f| f.enable_lmr = false)`.
+    pub fn feature(mut self, modify: impl FnOnce(&mut Features)) -> Self {
+        modify(&mut self.params.features);
+        self
+    }
+
+    pub fn weights(mut self, weights: EvaluationWeights) -> Self {
+        self.params.weights = weights;
+        self
+    }
+
+    /// Modifies individual evaluation weights, e.g. `.weight(|w| w.king_safety = 0)`.
+    pub fn weight(mut self, modify: impl FnOnce(&mut EvaluationWeights)) -> Self {
+        modify(&mut self.params.weights);
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: SearchAlgorithm) -> Self {
+        self.params.algorithm = algorithm;
+        self
+    }
+
+    pub fn debug_print(mut self, debug_print: bool) -> Self {
+        self.params.debug_print = debug_print;
+        self
+    }
+
+    pub fn debug_print_verbose(mut self, debug_print_verbose: bool) -> Self {
+        self.params.debug_print_verbose = debug_print_verbose;
+        self
+    }
+
+    pub fn debug_print_all_moves(mut self, debug_print_all_moves: bool) -> Self {
+        self.params.debug_print_all_moves = debug_print_all_moves;
+        self
+    }
+
+    /// Validates the parameters and returns them.
+    pub fn build(self) -> Result<SearchParams, anyhow::Error> {
+        let params = self.params;
+
+        if params.depth == 0 {
+            return Err(anyhow::anyhow!("Search depth must be at least 1"));
+        }
+
+        if params.depth + params.quiescence_depth > MAX_SEARCH_PLY {
+            return Err(anyhow::anyhow!(
+                "Search depth ({}) plus quiescence depth ({}) must not exceed {}",
+                params.depth,
+                params.quiescence_depth,
+                MAX_SEARCH_PLY
+            ));
+        }
+
+        if params.window_size <= 0 {
+            return Err(anyhow::anyhow!(
+                "Window size must be positive, got {}",
+                params.window_size
+            ));
+        }
+
+        if params.window_size >= MAX_BETA {
+            return Err(anyhow::anyhow!(
+                "Window size must be less than {}, got {}",
+                MAX_BETA,
+                params.window_size
+            ));
+        }
+
+        if params.initial_alpha < MIN_ALPHA
+            || params.initial_beta > MAX_BETA
+            || params.initial_alpha >= params.initial_beta
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid initial window ({}, {}), alpha must be less than beta and within ({}, {})",
+                params.initial_alpha,
+                params.initial_beta,
+                MIN_ALPHA,
+                MAX_BETA
+            ));
+        }
+
+        if params.time_limit == 0 {
+            return Err(anyhow::anyhow!("Time limit must be greater than 0"));
+        }
+
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        let params = SearchParams::builder().build().unwrap();
+        let default = SearchParams::default();
+
+        assert_eq!(params.depth, default.depth);
+        assert_eq!(params.quiescence_depth, default.quiescence_depth);
+        assert_eq!(params.time_limit, default.time_limit);
+        assert_eq!(params.game_type, default.game_type);
+        assert_eq!(params.window_size, default.window_size);
+    }
+
+    #[test]
+    fn test_builder_sets_fields() {
+        let params = SearchParams::builder()
+            .depth(8)
+            .quiescence_depth(6)
+            .time_ms(3000)
+            .game_type(GameType::Rescue)
+            .feature(|f| f.enable_lmr = false)
+            .weight(|w| w.material = 120)
+            .previous_score(Some(35))
+            .debug_print_verbose(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(params.depth, 8);
+        assert_eq!(params.quiescence_depth, 6);
+        assert_eq!(params.time_limit, 3000);
+        assert_eq!(params.game_type, GameType::Rescue);
+        assert!(!params.features.enable_lmr);
+        assert_eq!(params.weights.material, 120);
+        assert_eq!(params.previous_score, Some(35));
+        assert!(params.debug_print_verbose);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(SearchParams::builder().depth(0).build().is_err());
+        assert!(SearchParams::builder()
+            .depth(40)
+            .quiescence_depth(40)
+            .build()
+            .is_err());
+        assert!(SearchParams::builder().window_size(0).build().is_err());
+        assert!(SearchParams::builder().window(100, -100).build().is_err());
+        assert!(SearchParams::builder()
+            .window(MIN_ALPHA - 1, 0)
+            .build()
+            .is_err());
+        assert!(SearchParams::builder().time_ms(0).build().is_err());
+    }
+}
@@ -1,16 +1,22 @@
 use tracing::trace;
 
 use crate::{
-    evaluation::{evaluate_position, ordering::order_moves},
-    piece_move::MoveType,
-    Position,
+    evaluation::{ordering::order_moves, see::static_exchange_evaluation},
+    piece_move::{MoveList, MoveType},
+    PieceMove, Position,
 };
 
 use super::{
-    alpha_beta::{AlphaBetaError, SearchParams, SearchResult, CHECKMATE},
+    alpha_beta::{drop_check_extension_enabled, AlphaBetaError, SearchParams, SearchResult},
+    eval_cache::cached_evaluation,
+    score::mated_in,
     search_results::SearchState,
 };
 
+/// How many plies into quiescence quiet moves that give check are searched, so each side gets
+/// a chance to give one.
+const QUIESCENCE_CHECK_PLIES: u32 = 2;
+
 pub fn quiescence_search(
     position: &mut Position,
     mut alpha: i32,
@@ -21,6 +27,12 @@ pub fn quiescence_search(
     initial_depth: u32,
     ply: usize,
 ) -> Result<SearchResult, AlphaBetaError> {
+    state.data.statistics.quiescence_nodes += 1;
+    state
+        .data
+        .statistics
+        .record_ply(state.data.moves.len() as u32 + params.quiescence_depth - depth);
+
     if position.is_checkmate(params.game_type).unwrap() {
         if params.debug_print_verbose {
             trace!(
@@ -29,14 +41,15 @@ pub fn quiescence_search(
             );
         }
 
+        // The mated node is as many plies into quiescence past the main search's path
         return Ok(SearchResult {
             principal_variation: None,
-            score: CHECKMATE - (depth as i32),
+            score: mated_in(state.data.moves.len() as u32 + params.quiescence_depth - depth),
         });
     }
 
     // First, do a standing pat evaluation
-    let stand_pat = evaluate_position(position, params.game_type, params);
+    let stand_pat = cached_evaluation(position, state, params);
 
     // Fail-high if standing pat beats beta
     if stand_pat >= beta {
@@ -83,20 +96,49 @@ pub fn quiescence_search(
         });
     }
 
-    // Get only capture moves
-    let mut moves = position.get_all_legal_moves(params.game_type).unwrap();
+    // Get only capture moves, and in the first plies, quiet moves that give check
+    let mut moves = MoveList::new();
+    position
+        .get_all_legal_moves(params.game_type, &mut moves)
+        .unwrap();
+
+    let quiescence_ply = params.quiescence_depth.saturating_sub(depth);
+    let search_checking_drops =
+        drop_check_extension_enabled(params) && depth == params.quiescence_depth;
+    let search_checking_moves =
+        params.features.enable_quiescence_checks && quiescence_ply < QUIESCENCE_CHECK_PLIES;
+
+    let checking_moves = if search_checking_drops || search_checking_moves {
+        moves
+            .iter()
+            .filter(|mv| {
+                let searched = if mv.is_drop() {
+                    search_checking_drops
+                } else {
+                    search_checking_moves
+                };
+
+                searched && !mv.is_capture() && !is_promotion(mv) && gives_check(position, **mv)
+            })
+            .copied()
+            .collect()
+    } else {
+        MoveList::new()
+    };
 
     moves.retain(|mv| {
+        if is_promotion(mv) {
+            return true;
+        }
+
+        // Skip captures that lose material once all recaptures are played out
         mv.is_capture()
-            || matches!(
-                mv.move_type,
-                MoveType::Normal {
-                    promoted_to: Some(_),
-                    ..
-                }
-            )
+            && (!params.features.enable_see_pruning
+                || static_exchange_evaluation(position, mv) >= 0)
     });
 
+    moves.extend(checking_moves);
+
     // If no captures are available, return standing pat
     if moves.is_empty() {
         if params.debug_print_verbose {
@@ -115,10 +157,10 @@ pub fn quiescence_search(
 
     let mut best_line = None;
 
-    let ordered_moves = order_moves(position, moves, None, state, ply, params);
+    order_moves(position, &mut moves, None, state, ply, params);
 
     // Search capture moves
-    for mv in ordered_moves {
+    for mv in moves {
         // Apply move
         let restore = position.apply_move(mv).unwrap();
         position.invert();
@@ -172,3 +214,83 @@ pub fn quiescence_search(
         score: alpha,
     })
 }
+
+fn is_promotion(mv: &PieceMove) -> bool {
+    matches!(
+        mv.move_type,
+        MoveType::Normal {
+            promoted_to: Some(_),
+            ..
+        }
+    )
+}
+
+/// Whether a move puts the opponent's king in check.
+fn gives_check(position: &mut Position, mv: PieceMove) -> bool {
+    let restore = position.apply_move(mv).unwrap();
+    let check = position.is_black_king_in_check().unwrap();
+    position.unapply_move(mv, restore).unwrap();
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::GameType,
+        search::{score::MATE_THRESHOLD, transposition_table::TranspositionTable},
+    };
+
+    use super::*;
+
+    fn quiescence_score(position: &mut Position, params: &SearchParams) -> i32 {
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+
+        quiescence_search(
+            position,
+            -2_000_000,
+            2_000_000,
+            params.quiescence_depth,
+            &mut state,
+            params,
+            0,
+            0,
+        )
+        .unwrap()
+        .score
+    }
+
+    #[test]
+    fn test_drop_mate_at_horizon() {
+        // The king can drop its rook on the back rank for mate
+        let mut position: Position = "7k/4KxR1pp/8/8/8/8/8/8 w - - 0 1".parse().unwrap();
+
+        let params = SearchParams::builder()
+            .game_type(GameType::Rescue)
+            .build()
+            .unwrap();
+        assert!(quiescence_score(&mut position, &params) > MATE_THRESHOLD);
+
+        let params = SearchParams::builder()
+            .game_type(GameType::Rescue)
+            .feature(|f| f.enable_drop_check_extension = false)
+            .build()
+            .unwrap();
+        assert!(quiescence_score(&mut position, &params) < MATE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_quiet_mate_at_horizon() {
+        // Ra8 is mate, but isn't a capture
+        let mut position: Position = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".parse().unwrap();
+
+        let params = SearchParams::builder().build().unwrap();
+        assert!(quiescence_score(&mut position, &params) > MATE_THRESHOLD);
+
+        let params = SearchParams::builder()
+            .feature(|f| f.enable_quiescence_checks = false)
+            .build()
+            .unwrap();
+        assert!(quiescence_score(&mut position, &params) < MATE_THRESHOLD);
+    }
+}
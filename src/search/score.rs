@@ -0,0 +1,105 @@
+//! Scores of searched positions: centipawns, or for forced mates, how many plies from the root
+//! the mate is, so that the search prefers the quickest mate and the slowest way to be mated.
+
+use std::{fmt::Display, ops::Neg};
+
+use serde::{Deserialize, Serialize};
+
+/// The score of mating at the root. A mate `n` plies from the root scores `MATE - n`, and being
+/// mated `n` plies from the root scores `-(MATE - n)`.
+pub const MATE: i32 = 1_000_000;
+
+/// Scores with an absolute value at least this large are mate scores.
+pub const MATE_THRESHOLD: i32 = 900_000;
+
+/// The score of mating `plies` plies from the root.
+pub const fn mate_in(plies: u32) -> i32 {
+    MATE - plies as i32
+}
+
+/// The score of being mated `plies` plies from the root.
+pub const fn mated_in(plies: u32) -> i32 {
+    -mate_in(plies)
+}
+
+pub fn is_mate_score(score: i32) -> bool {
+    (MATE_THRESHOLD..=MATE).contains(&score.abs())
+}
+
+/// The plies from the root to mate, positive when the side to move mates and negative when
+/// it's mated, or `None` if the score isn't a mate score.
+pub fn plies_to_mate(score: i32) -> Option<i32> {
+    is_mate_score(score).then(|| (MATE - score.abs()) * score.signum())
+}
+
+/// A search score as reported to players: centipawns, or moves to mate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Score {
+    Centipawns(i32),
+
+    /// Moves until mate, negative when the side the score is for is being mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Converts a score from the search, from the point of view of the side to move at the root.
+    pub fn from_search(score: i32) -> Self {
+        match plies_to_mate(score) {
+            Some(plies) => Score::Mate((plies.abs() + 1) / 2 * plies.signum()),
+            None => Score::Centipawns(score),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Score::Centipawns(centipawns) => Score::Centipawns(-centipawns),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    }
+}
+
+/// Formats the score as UCI's `info score` does, e.g. "cp 35" or "mate -2".
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "cp {}", centipawns),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mate_scores() {
+        assert!(mate_in(3) > mate_in(5));
+        assert!(mated_in(3) < mated_in(5));
+        assert!(is_mate_score(mate_in(40)));
+        assert!(is_mate_score(mated_in(0)));
+        assert!(!is_mate_score(2_500));
+
+        assert_eq!(plies_to_mate(mate_in(3)), Some(3));
+        assert_eq!(plies_to_mate(mated_in(4)), Some(-4));
+        assert_eq!(plies_to_mate(-150), None);
+    }
+
+    #[test]
+    fn test_score_from_search() {
+        assert_eq!(Score::from_search(35), Score::Centipawns(35));
+        assert_eq!(Score::from_search(mate_in(1)), Score::Mate(1));
+        assert_eq!(Score::from_search(mate_in(5)), Score::Mate(3));
+        assert_eq!(Score::from_search(mated_in(2)), Score::Mate(-1));
+        assert_eq!(Score::from_search(mated_in(4)), Score::Mate(-2));
+
+        assert_eq!(Score::from_search(mate_in(3)).to_string(), "mate 2");
+        assert_eq!((-Score::from_search(mate_in(3))).to_string(), "mate -2");
+        assert_eq!(Score::from_search(-20).to_string(), "cp -20");
+    }
+}
@@ -1,14 +1,26 @@
-use std::time::Instant;
+use std::{
+    fmt::Display,
+    sync::{atomic::AtomicBool, Arc},
+    time::Instant,
+};
 
 use serde::Serialize;
 
 use crate::PieceMove;
 
 use super::{
-    history::HistoryTable, iterative_deepening::OnNewBestMove, killer_moves::KillerMoves,
+    alpha_beta::OnMoveScored,
+    eval_cache::EvalCache,
+    history::HistoryTable,
+    killer_moves::KillerMoves,
+    params_builder::MAX_SEARCH_PLY,
+    statistics::{effective_branching_factor, SearchStatistics},
     transposition_table::TranspositionTable,
 };
 
+#[cfg(feature = "syzygy")]
+use super::tablebase::Tablebase;
+
 #[derive(Clone, Serialize)]
 pub struct SearchResults {
     pub best_move: Option<PieceMove>,
@@ -21,6 +33,57 @@ pub struct SearchResults {
     pub principal_variation: Option<Vec<PieceMove>>,
     pub alpha: i32,
     pub beta: i32,
+    pub statistics: SearchStatistics,
+}
+
+impl SearchResults {
+    pub fn effective_branching_factor(&self) -> f64 {
+        effective_branching_factor(self.nodes_searched, self.depth)
+    }
+}
+
+impl Display for SearchResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Depth: {}", self.depth)?;
+        writeln!(f, "Score: {}", self.score)?;
+        writeln!(f, "Nodes searched: {}", self.nodes_searched)?;
+        writeln!(f, "Time taken: {}ms", self.time_taken_ms)?;
+        writeln!(
+            f,
+            "Effective branching factor: {:.2}",
+            self.effective_branching_factor()
+        )?;
+        write!(f, "{}", self.statistics)
+    }
+}
+
+/// How a search is going, as reported to `OnNewBestMove` handlers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchInfo {
+    pub depth: u32,
+
+    /// The deepest ply the search has reached, quiescence included.
+    pub seldepth: u32,
+
+    pub score: i32,
+
+    /// The nodes searched so far, over every depth of the search.
+    pub nodes: u32,
+
+    pub time_ms: u128,
+
+    /// How full the transposition table is, in permille.
+    pub hashfull: u32,
+
+    /// From the root's side to move's perspective, each move from the perspective of the side
+    /// playing it.
+    pub principal_variation: Vec<PieceMove>,
+}
+
+impl SearchInfo {
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.time_ms.max(1)) as u64
+    }
 }
 
 pub struct SearchState<'table, 'a> {
@@ -29,6 +92,9 @@ pub struct SearchState<'table, 'a> {
     pub callbacks: SearchStateCallbacks<'a>,
     pub killer_moves: KillerMoves,
     pub history: HistoryTable,
+
+    /// Static evaluations of positions already seen in the search.
+    pub eval_cache: EvalCache,
 }
 
 pub struct SearchStateData {
@@ -40,10 +106,85 @@ pub struct SearchStateData {
     pub pruned: u32,
     pub best_move_so_far: Option<PieceMove>,
     pub previous_pv: Option<Vec<PieceMove>>,
+
+    /// Zobrist keys of the positions in the game before the root, followed by the positions
+    /// on the current search path. The top of the stack is the position being searched.
+    pub history: Vec<u64>,
+
+    /// The moves on the current search path, the last being the move that led to the position
+    /// being searched. Null moves are `None`.
+    pub moves: Vec<Option<PieceMove>>,
+
+    /// Set from another thread to abandon the search, which then returns `AlphaBetaError::Stopped`.
+    pub stop: Arc<AtomicBool>,
+
+    /// Abandons the search with `AlphaBetaError::NodeLimit` once this many nodes have been
+    /// searched.
+    pub node_limit: u32,
+
+    pub statistics: SearchStatistics,
+
+    /// Endgame tablebases to look positions with few pieces up in, instead of searching them.
+    #[cfg(feature = "syzygy")]
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+/// Move ordering tables and the principal variation from a previous search, kept between the
+/// searches for each move of a game so that every search doesn't start from scratch.
+#[derive(Debug, Clone)]
+pub struct SearchMemory {
+    pub killer_moves: KillerMoves,
+    pub history: HistoryTable,
+    pub previous_pv: Option<Vec<PieceMove>>,
+
+    /// The game ply the memory was last used at.
+    pub ply: usize,
+}
+
+impl Default for SearchMemory {
+    fn default() -> Self {
+        Self {
+            killer_moves: KillerMoves::new(MAX_SEARCH_PLY as usize),
+            history: HistoryTable::new(),
+            previous_pv: None,
+            ply: 0,
+        }
+    }
+}
+
+impl SearchMemory {
+    /// Ages the memory for a search at game ply `ply`: killer moves and the previous principal
+    /// variation are shifted by the plies played since the last search, and history scores
+    /// are decayed. Going back to an earlier ply, e.g. after a new game, clears the memory.
+    pub fn advance_to(&mut self, ply: usize) {
+        if ply < self.ply {
+            *self = SearchMemory {
+                ply,
+                ..Default::default()
+            };
+            return;
+        }
+
+        let plies = ply - self.ply;
+        if plies == 0 {
+            return;
+        }
+
+        self.killer_moves.shift(plies);
+        self.history.age();
+        self.previous_pv = self
+            .previous_pv
+            .take()
+            .filter(|pv| pv.len() > plies)
+            .map(|pv| pv[plies..].to_vec());
+        self.ply = ply;
+    }
 }
 
 pub struct SearchStateCallbacks<'a> {
-    pub on_new_best_move: Option<&'a OnNewBestMove>,
+    /// Like `OnNewBestMove`, but may borrow from the caller of the search.
+    pub on_new_best_move: Option<&'a (dyn Fn(&SearchInfo) + Send + Sync + 'a)>,
+    pub on_move_scored: Option<&'a OnMoveScored>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +193,15 @@ pub struct SearchStats {
     pub cached_positions: u32,
     pub pruned: u32,
     pub time_taken_ms: u128,
+
+    /// The depth of the last search's deepest finished iteration.
+    pub depth: u32,
+
+    /// The nodes the last search's deepest finished iteration searched for each node of the
+    /// iteration before it.
+    pub effective_branching_factor: f64,
+
+    pub statistics: SearchStatistics,
 }
 
 impl Default for SearchStats {
@@ -61,6 +211,9 @@ impl Default for SearchStats {
             cached_positions: 0,
             pruned: 0,
             time_taken_ms: 0,
+            depth: 0,
+            effective_branching_factor: 0.0,
+            statistics: SearchStatistics::default(),
         }
     }
 }
@@ -71,6 +224,22 @@ impl SearchStats {
         self.cached_positions += stats.cached_positions;
         self.pruned += stats.pruned;
         self.time_taken_ms = stats.time_taken_ms;
+        self.statistics.add(&stats.statistics);
+    }
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Depth: {}", self.depth)?;
+        writeln!(f, "Nodes searched: {}", self.nodes_searched)?;
+        writeln!(f, "Time taken: {}ms", self.time_taken_ms)?;
+        writeln!(f, "Pruned: {}", self.pruned)?;
+        writeln!(
+            f,
+            "Effective branching factor: {:.2}",
+            self.effective_branching_factor
+        )?;
+        write!(f, "{}", self.statistics)
     }
 }
 
@@ -81,6 +250,9 @@ impl<'table, 'a> SearchState<'table, 'a> {
             cached_positions: self.data.cached_positions,
             pruned: self.data.pruned,
             time_taken_ms: self.data.start_time.elapsed().as_millis(),
+            depth: 0,
+            effective_branching_factor: 0.0,
+            statistics: self.data.statistics,
         }
     }
 }
@@ -97,13 +269,82 @@ impl<'table, 'a> SearchState<'table, 'a> {
                 pruned: 0,
                 best_move_so_far: None,
                 previous_pv: None,
+                history: Vec::new(),
+                moves: Vec::new(),
+                stop: Arc::new(AtomicBool::new(false)),
+                node_limit: u32::MAX,
+                statistics: SearchStatistics::default(),
+                #[cfg(feature = "syzygy")]
+                tablebase: None,
             },
             transposition_table,
             callbacks: SearchStateCallbacks {
                 on_new_best_move: None,
+                on_move_scored: None,
             },
-            killer_moves: KillerMoves::new(64),
+            killer_moves: KillerMoves::new(MAX_SEARCH_PLY as usize),
             history: HistoryTable::new(),
+            eval_cache: EvalCache::new(),
         }
     }
+
+    /// Creates a search state that continues from the tables of an earlier search.
+    pub fn with_memory(
+        transposition_table: &'table mut TranspositionTable,
+        memory: SearchMemory,
+    ) -> Self {
+        let mut state = Self::new(transposition_table);
+        state.killer_moves = memory.killer_moves;
+        state.history = memory.history;
+        state.data.previous_pv = memory.previous_pv;
+        state
+    }
+
+    /// Takes the tables learned during the search, to pass to a later search with `with_memory`.
+    pub fn into_memory(self, ply: usize) -> SearchMemory {
+        SearchMemory {
+            killer_moves: self.killer_moves,
+            history: self.history,
+            previous_pv: self.data.previous_pv,
+            ply,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{piece_move::GameType, PieceMove, Position};
+
+    use super::*;
+
+    #[test]
+    fn test_search_memory_advance() {
+        let position = Position::start_position();
+        let e4 = PieceMove::from_algebraic(&position, "e4", GameType::Classic).unwrap();
+        let nf3 = PieceMove::from_algebraic(&position, "Nf3", GameType::Classic).unwrap();
+        let d4 = PieceMove::from_algebraic(&position, "d4", GameType::Classic).unwrap();
+
+        let mut memory = SearchMemory::default();
+        memory.killer_moves.add_killer(nf3, 3);
+        memory.history.update_history(&nf3, 4, true);
+        memory.previous_pv = Some(vec![e4, d4, nf3]);
+
+        memory.advance_to(2);
+        assert_eq!(memory.ply, 2);
+        assert_eq!(memory.killer_moves.get_killers(1), [Some(nf3), None]);
+        assert_eq!(memory.killer_moves.get_killers(3), [None, None]);
+        assert_eq!(
+            memory.history.success[nf3.piece_type as usize][nf3.to.0 as usize],
+            2
+        );
+        assert_eq!(memory.previous_pv, Some(vec![nf3]));
+
+        memory.advance_to(4);
+        assert_eq!(memory.previous_pv, None);
+
+        // Starting a new game forgets everything
+        memory.advance_to(0);
+        assert_eq!(memory.ply, 0);
+        assert_eq!(memory.killer_moves.get_killers(1), [None, None]);
+    }
 }
@@ -0,0 +1,202 @@
+//! Counters kept while searching, to see how well the search's move ordering and pruning are
+//! working: how often the transposition table answers, how often null moves and late move
+//! reductions pay off, and which moves cause beta cutoffs.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+
+/// The number of buckets in the cutoff histogram. The last counts cutoffs by every later move.
+pub const CUTOFF_HISTOGRAM_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SearchStatistics {
+    /// Positions looked up in the transposition table, and the lookups that returned a score.
+    pub tt_probes: u32,
+    pub tt_hits: u32,
+
+    /// Null move searches, and those that failed high and pruned the node.
+    pub null_move_tries: u32,
+    pub null_move_cutoffs: u32,
+
+    /// Moves searched at a reduced depth, and those that beat alpha and had to be searched
+    /// again at full depth.
+    pub lmr_reductions: u32,
+    pub lmr_researches: u32,
+
+    /// Null window searches that landed inside the window and had to be searched again with
+    /// the full window.
+    pub pvs_researches: u32,
+
+    pub quiescence_nodes: u32,
+
+    /// The deepest ply from the root the search reached, quiescence included.
+    pub seldepth: u32,
+
+    /// Beta cutoffs by the index of the move in the move ordering that caused them. With good
+    /// move ordering, nearly all are caused by the first move.
+    pub cutoffs: [u32; CUTOFF_HISTOGRAM_SIZE],
+}
+
+impl SearchStatistics {
+    pub fn record_ply(&mut self, ply: u32) {
+        self.seldepth = self.seldepth.max(ply);
+    }
+
+    pub fn record_cutoff(&mut self, move_index: usize) {
+        self.cutoffs[move_index.min(CUTOFF_HISTOGRAM_SIZE - 1)] += 1;
+    }
+
+    pub fn total_cutoffs(&self) -> u32 {
+        self.cutoffs.iter().sum()
+    }
+
+    /// The share of transposition table lookups that returned a score.
+    pub fn tt_hit_rate(&self) -> f64 {
+        ratio(self.tt_hits, self.tt_probes)
+    }
+
+    /// The share of beta cutoffs caused by the first move searched.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        ratio(self.cutoffs[0], self.total_cutoffs())
+    }
+
+    pub fn add(&mut self, statistics: &SearchStatistics) {
+        self.tt_probes += statistics.tt_probes;
+        self.tt_hits += statistics.tt_hits;
+        self.null_move_tries += statistics.null_move_tries;
+        self.null_move_cutoffs += statistics.null_move_cutoffs;
+        self.lmr_reductions += statistics.lmr_reductions;
+        self.lmr_researches += statistics.lmr_researches;
+        self.pvs_researches += statistics.pvs_researches;
+        self.quiescence_nodes += statistics.quiescence_nodes;
+        self.seldepth = self.seldepth.max(statistics.seldepth);
+
+        for (total, cutoffs) in self.cutoffs.iter_mut().zip(statistics.cutoffs) {
+            *total += cutoffs;
+        }
+    }
+}
+
+/// The number of moves searched per node that would give `nodes` nodes in a search `depth`
+/// plies deep with no pruning.
+pub fn effective_branching_factor(nodes: u32, depth: u32) -> f64 {
+    if depth == 0 {
+        return 0.0;
+    }
+
+    (nodes as f64).powf(1.0 / depth as f64)
+}
+
+fn ratio(count: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+impl Display for SearchStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "TT hits: {}/{} ({:.1}%)",
+            self.tt_hits,
+            self.tt_probes,
+            self.tt_hit_rate() * 100.0
+        )?;
+        writeln!(
+            f,
+            "Null move cutoffs: {}/{} ({:.1}%)",
+            self.null_move_cutoffs,
+            self.null_move_tries,
+            ratio(self.null_move_cutoffs, self.null_move_tries) * 100.0
+        )?;
+        writeln!(
+            f,
+            "LMR re-searches: {}/{} ({:.1}%)",
+            self.lmr_researches,
+            self.lmr_reductions,
+            ratio(self.lmr_researches, self.lmr_reductions) * 100.0
+        )?;
+        writeln!(f, "PVS re-searches: {}", self.pvs_researches)?;
+        writeln!(f, "Quiescence nodes: {}", self.quiescence_nodes)?;
+        writeln!(f, "Selective depth: {}", self.seldepth)?;
+        writeln!(
+            f,
+            "Beta cutoffs: {} ({:.1}% on the first move)",
+            self.total_cutoffs(),
+            self.first_move_cutoff_rate() * 100.0
+        )?;
+
+        for (index, cutoffs) in self.cutoffs.iter().enumerate() {
+            let label = if index == CUTOFF_HISTOGRAM_SIZE - 1 {
+                format!("{}+", index + 1)
+            } else {
+                format!("{}", index + 1)
+            };
+
+            let share = ratio(*cutoffs, self.total_cutoffs());
+            writeln!(
+                f,
+                "  {:>3} {:>5.1}% {}",
+                label,
+                share * 100.0,
+                "#".repeat((share * 50.0).round() as usize)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::GameType,
+        search::{
+            alpha_beta::{search, SearchParams},
+            search_results::SearchState,
+            transposition_table::TranspositionTable,
+        },
+        Position,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_record_cutoff() {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_cutoff(0);
+        statistics.record_cutoff(0);
+        statistics.record_cutoff(3);
+        statistics.record_cutoff(40);
+
+        assert_eq!(statistics.cutoffs, [2, 0, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(statistics.total_cutoffs(), 4);
+        assert_eq!(statistics.first_move_cutoff_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_search_keeps_statistics() {
+        let position = Position::start_position();
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+
+        let params = SearchParams::builder()
+            .depth(4)
+            .game_type(GameType::Classic)
+            .build()
+            .unwrap();
+
+        let results = search(&position, &mut state, params, 0).unwrap();
+        let statistics = results.statistics;
+
+        assert!(statistics.tt_probes > 0);
+        assert!(statistics.quiescence_nodes > 0);
+        assert!(statistics.total_cutoffs() > 0);
+        assert!(statistics.seldepth > 4);
+        assert!(statistics.tt_hits <= statistics.tt_probes);
+        assert!(results.effective_branching_factor() > 1.0);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{PieceMove, Position};
+
+use super::{
+    alpha_beta::SearchParams,
+    backend,
+    search_results::{SearchMemory, SearchResults, SearchState},
+    transposition_table::TranspositionTable,
+};
+
+/// Where a `SteppedSearch` is after a call to `step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStep {
+    /// There's more to search. Call `step` again to continue.
+    InProgress,
+
+    /// Every depth up to the search depth has been searched.
+    Done,
+}
+
+/// An iterative deepening search that does a bounded amount of work per call to `step`, for
+/// hosts that can't block or start threads, like a browser's main thread in the WASM build.
+///
+/// Each step searches at most the given number of nodes. A depth that doesn't finish within a
+/// step is started again on the next one, with the transposition table kept between steps.
+/// Every restart of the same depth is given one more step's worth of nodes, so a depth that
+/// needs more than a step's budget still finishes, at the cost of its last steps taking
+/// longer. The parameters' time limit isn't used: the host decides how long to keep stepping,
+/// and can take `best_move` at any point.
+pub struct SteppedSearch {
+    position: Position,
+    params: SearchParams,
+    transposition_table: TranspositionTable,
+    memory: SearchMemory,
+
+    /// Zobrist keys of the positions in the game up to and including the position searched.
+    history: Vec<u64>,
+
+    /// The game ply of the position searched.
+    ply: usize,
+
+    /// The depth currently being searched.
+    depth: u32,
+
+    /// How many times the current depth has been started again after running out of nodes.
+    restarts: u32,
+
+    results: Option<SearchResults>,
+    done: bool,
+
+    /// The total number of nodes searched over all steps.
+    pub nodes_searched: u64,
+
+    /// The number of steps taken so far.
+    pub steps: u32,
+}
+
+impl SteppedSearch {
+    /// Starts a search of `position`, from the perspective of its side to move.
+    pub fn new(position: Position, params: SearchParams) -> Self {
+        Self {
+            history: vec![position.zobrist_key()],
+            position,
+            params,
+            transposition_table: TranspositionTable::new(),
+            memory: SearchMemory::default(),
+            ply: 0,
+            depth: 1,
+            restarts: 0,
+            results: None,
+            done: false,
+            nodes_searched: 0,
+            steps: 0,
+        }
+    }
+
+    /// Sets the positions played in the game so far, so repetitions are scored as draws.
+    pub fn with_history(mut self, history: Vec<u64>, ply: usize) -> Self {
+        self.history = history;
+        self.ply = ply;
+        self.memory.ply = ply;
+        self
+    }
+
+    /// Searches at most `node_budget` nodes (more if the current depth has been restarted),
+    /// continuing from where the last step stopped.
+    pub fn step(&mut self, node_budget: u32) -> SearchStep {
+        if self.done {
+            return SearchStep::Done;
+        }
+
+        let mut state = SearchState::with_memory(
+            &mut self.transposition_table,
+            std::mem::take(&mut self.memory),
+        );
+        state.data.history = self.history.clone();
+        state.data.node_limit = node_budget.saturating_mul(self.restarts + 1);
+
+        let mut params = self.params.clone();
+        params.depth = self.depth;
+        params.previous_score = self.results.as_ref().map(|results| results.score);
+
+        let result = backend::search(&self.position, &mut state, params, self.ply);
+
+        self.nodes_searched += state.data.nodes_searched as u64;
+        self.steps += 1;
+
+        match result {
+            Ok(results) => {
+                state.data.previous_pv = results.principal_variation.clone();
+
+                // A position without legal moves has nothing deeper to search
+                self.done = self.depth >= self.params.depth || results.best_move.is_none();
+                self.depth += 1;
+                self.restarts = 0;
+                self.results = Some(results);
+            }
+
+            // The step ran out of nodes, so the depth is searched again next step
+            Err(_) => self.restarts += 1,
+        }
+
+        self.memory = state.into_memory(self.ply);
+
+        if self.done {
+            SearchStep::Done
+        } else {
+            SearchStep::InProgress
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The results of the deepest finished depth.
+    pub fn results(&self) -> Option<&SearchResults> {
+        self.results.as_ref()
+    }
+
+    pub fn best_move(&self) -> Option<PieceMove> {
+        self.results.as_ref().and_then(|results| results.best_move)
+    }
+
+    /// The deepest depth searched to completion, or 0 if none has finished yet.
+    pub fn completed_depth(&self) -> u32 {
+        self.depth - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::piece_move::{GameType, MoveList};
+
+    use super::*;
+
+    fn run(search: &mut SteppedSearch, node_budget: u32) {
+        while search.step(node_budget) == SearchStep::InProgress {
+            assert!(search.steps < 10_000, "Search made no progress");
+        }
+    }
+
+    #[test]
+    fn test_stepped_search_finishes() {
+        let position = Position::start_position();
+        let params = SearchParams::builder().depth(4).build().unwrap();
+
+        let mut search = SteppedSearch::new(position.clone(), params);
+        assert_eq!(search.step(100), SearchStep::InProgress);
+        assert!(search.nodes_searched <= 100);
+
+        run(&mut search, 1_000);
+
+        assert!(search.is_done());
+        assert!(search.steps > 1);
+        assert_eq!(search.completed_depth(), 4);
+        assert_eq!(search.results().unwrap().depth, 4);
+
+        let best_move = search.best_move().unwrap();
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+        assert!(moves.contains(&best_move));
+
+        assert_eq!(search.step(1_000), SearchStep::Done);
+    }
+
+    #[test]
+    fn test_stepped_search_finds_mate() {
+        let position: Position = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".parse().unwrap();
+        let params = SearchParams::builder().depth(3).build().unwrap();
+
+        let mut search = SteppedSearch::new(position.clone(), params);
+        run(&mut search, 200);
+
+        // Depth 3 needs more than 200 nodes, so it had to be restarted
+        assert_eq!(search.completed_depth(), 3);
+        assert!(search.steps > 3);
+
+        let best_move = search.best_move().unwrap();
+        assert_eq!(best_move.to_string(), "Ra8");
+    }
+
+    #[test]
+    fn test_no_legal_moves() {
+        let position: Position = "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".parse().unwrap();
+        let params = SearchParams::builder().depth(4).build().unwrap();
+
+        let mut search = SteppedSearch::new(position, params);
+        assert_eq!(search.step(1_000), SearchStep::Done);
+        assert_eq!(search.best_move(), None);
+    }
+}
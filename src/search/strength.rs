@@ -0,0 +1,235 @@
+//! Playing below full strength, for UCI's `UCI_LimitStrength` and `UCI_Elo` options and for
+//! weaker opponents in the app. A weaker engine searches less deeply, scores every move, and
+//! picks among them at random, favoring the better ones less the weaker it is.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    alpha_beta::{AlphaBetaError, MoveScore, SearchParams},
+    backend::score_all_moves,
+    score::{is_mate_score, plies_to_mate},
+    search_results::{SearchResults, SearchState},
+};
+use crate::PieceMove;
+
+/// The weakest strength the engine can be limited to.
+pub const MIN_ELO: u32 = 600;
+
+/// The strongest limited strength, about what the engine plays at full strength at the depths
+/// it's limited to.
+pub const MAX_ELO: u32 = 2400;
+
+/// The strength the `UCI_Elo` option starts at.
+pub const DEFAULT_ELO: u32 = 1500;
+
+/// How much worse than the best move, in centipawns, a mate is counted as, so that even weak
+/// play usually finds mates and avoids being mated.
+const MATE_PENALTY: f64 = 2_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrengthLimit {
+    pub elo: u32,
+}
+
+impl StrengthLimit {
+    /// A limit at `elo`, clamped to between `MIN_ELO` and `MAX_ELO`.
+    pub fn new(elo: u32) -> Self {
+        Self {
+            elo: elo.clamp(MIN_ELO, MAX_ELO),
+        }
+    }
+
+    /// How far the strength is from `MIN_ELO` to `MAX_ELO`, from 0 to 1.
+    fn skill(&self) -> f64 {
+        (self.elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64
+    }
+
+    /// The deepest the engine searches, from 1 ply at `MIN_ELO` to 6 at `MAX_ELO`.
+    pub fn depth(&self) -> u32 {
+        1 + (self.skill() * 5.0).round() as u32
+    }
+
+    /// How deep captures are followed past the search depth.
+    pub fn quiescence_depth(&self) -> u32 {
+        (self.skill() * 6.0).round() as u32
+    }
+
+    /// How many centipawns worse a move has to be to be picked about a third as often as the
+    /// best one. The weakest play picks moves 300 centipawns worse a third as often, and the
+    /// strongest nearly always picks the best move.
+    pub fn temperature(&self) -> f64 {
+        5.0 + 295.0 * (1.0 - self.skill())
+    }
+
+    /// Picks one of the scored moves at random, each weighted by how close its score is to the
+    /// best one's. Returns `None` if there are no moves.
+    pub fn choose_move<'a>(
+        &self,
+        scores: &'a [MoveScore],
+        rng: &mut impl Rng,
+    ) -> Option<&'a MoveScore> {
+        let best = scores.iter().map(|score| score.score).max()?;
+        let temperature = self.temperature();
+
+        let weights: Vec<f64> = scores
+            .iter()
+            .map(|score| (-score_loss(best, score.score) / temperature).exp())
+            .collect();
+
+        let mut pick = rng.gen_range(0.0..weights.iter().sum::<f64>());
+
+        for (score, weight) in scores.iter().zip(&weights) {
+            if pick < *weight {
+                return Some(score);
+            }
+            pick -= weight;
+        }
+
+        scores.last()
+    }
+
+    /// Searches `position` at the limited strength and picks a move, returning the results as
+    /// if it were the best one. The depth and quiescence depth are the lower of `params`' and
+    /// the limit's.
+    pub fn search(
+        &self,
+        position: &crate::Position,
+        state: &mut SearchState,
+        params: SearchParams,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        let params = SearchParams {
+            depth: params.depth.min(self.depth()).max(1),
+            quiescence_depth: params.quiescence_depth.min(self.quiescence_depth()),
+            ..params
+        };
+
+        let scores = score_all_moves(position, state, params.clone(), 0)?;
+        let chosen = self.choose_move(&scores, &mut state.data.rng);
+
+        Ok(SearchResults {
+            best_move: chosen.map(|score| score.mv),
+            score: chosen.map_or(0, |score| score.score),
+            nodes_searched: state.data.nodes_searched,
+            cached_positions: state.data.cached_positions,
+            depth: params.depth,
+            time_taken_ms: state.data.start_time.elapsed().as_millis(),
+            pruned: state.data.pruned,
+            principal_variation: chosen.map(principal_variation),
+            alpha: params.initial_alpha,
+            beta: params.initial_beta,
+            statistics: state.data.statistics,
+        })
+    }
+}
+
+/// How much worse `score` is than `best`, counting mates as much worse than anything else.
+fn score_loss(best: i32, score: i32) -> f64 {
+    match (plies_to_mate(best), plies_to_mate(score)) {
+        (Some(best_plies), Some(plies)) if best_plies.signum() == plies.signum() => {
+            // Slower mates, or being mated sooner, lose a little for each ply
+            (best - score) as f64
+        }
+        _ if is_mate_score(best) || is_mate_score(score) => MATE_PENALTY.min((best - score) as f64),
+        _ => (best - score) as f64,
+    }
+}
+
+/// The principal variation starting with the scored move.
+fn principal_variation(score: &MoveScore) -> Vec<PieceMove> {
+    let mut pv = vec![score.mv];
+    pv.extend(score.principal_variation.iter().flatten());
+    pv
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        piece_move::GameType,
+        search::{score::mate_in, transposition_table::TranspositionTable},
+        Position,
+    };
+
+    fn move_scores(scores: &[i32]) -> Vec<MoveScore> {
+        let position = Position::start_position();
+        let mut moves = crate::piece_move::MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+
+        moves
+            .iter()
+            .zip(scores)
+            .map(|(&mv, &score)| MoveScore {
+                mv,
+                score,
+                principal_variation: None,
+            })
+            .collect()
+    }
+
+    /// How often each move is chosen out of 1,000 tries.
+    fn choices(limit: StrengthLimit, scores: &[MoveScore]) -> Vec<usize> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = vec![0; scores.len()];
+
+        for _ in 0..1_000 {
+            let chosen = limit.choose_move(scores, &mut rng).unwrap();
+            counts[scores.iter().position(|s| s.mv == chosen.mv).unwrap()] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(StrengthLimit::new(0).elo, MIN_ELO);
+        assert_eq!(StrengthLimit::new(5_000).elo, MAX_ELO);
+        assert_eq!(StrengthLimit::new(MIN_ELO).depth(), 1);
+        assert_eq!(StrengthLimit::new(MAX_ELO).depth(), 6);
+        assert!(StrengthLimit::new(1200).temperature() > StrengthLimit::new(2000).temperature());
+    }
+
+    #[test]
+    fn test_weaker_play_picks_worse_moves() {
+        let scores = move_scores(&[50, 0, -100, -300]);
+
+        let strong = choices(StrengthLimit::new(MAX_ELO), &scores);
+        assert!(strong[0] > 950);
+
+        let weak = choices(StrengthLimit::new(MIN_ELO), &scores);
+        assert!(weak[0] < 700);
+        assert!(weak[0] > weak[1] && weak[1] > weak[2] && weak[2] > weak[3]);
+        assert!(weak[3] > 0);
+    }
+
+    #[test]
+    fn test_mates_are_rarely_missed() {
+        let scores = move_scores(&[mate_in(3), 200, 100]);
+        let weak = choices(StrengthLimit::new(MIN_ELO), &scores);
+        assert!(weak[0] > 990);
+    }
+
+    #[test]
+    fn test_search() {
+        let position = Position::start_position();
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+
+        let params = SearchParams::builder().depth(4).build().unwrap();
+
+        let results = StrengthLimit::new(MIN_ELO)
+            .search(&position, &mut state, params)
+            .unwrap();
+
+        assert_eq!(results.depth, 1);
+        assert!(results.best_move.is_some());
+        assert_eq!(
+            results.principal_variation.unwrap()[0],
+            results.best_move.unwrap()
+        );
+    }
+}
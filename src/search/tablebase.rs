@@ -0,0 +1,150 @@
+//! Probing Syzygy endgame tablebases, for exact results in classic positions with few pieces.
+//! Only built with the `syzygy` feature.
+//!
+//! Positions are handed to the tablebases as FEN, so a position can only be probed when its
+//! FEN is one classic chess understands: Rescue positions with held pieces never are.
+
+use std::path::Path;
+
+use shakmaty::{fen::Fen, CastlingMode, Chess};
+use shakmaty_syzygy::{AmbiguousWdl, Tablebase as SyzygyTables};
+
+use crate::{piece_move::GameType, Color, PieceMove, Position};
+
+/// The score of a position the tablebases say is won, less the plies from the root, so that
+/// quicker wins score higher. Below any mate score, since a tablebase win isn't a mate found
+/// by the search, but well above any evaluation.
+pub const TABLEBASE_WIN: i32 = 800_000;
+
+/// A position's result with best play, from the perspective of its side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl {
+    Win,
+
+    /// A win that takes too long under the fifty-move rule, so it's a draw.
+    CursedWin,
+
+    Draw,
+
+    /// A loss the fifty-move rule saves, so it's a draw.
+    BlessedLoss,
+
+    Loss,
+}
+
+impl Wdl {
+    /// The search score for the result, `ply` plies from the root.
+    pub fn score(&self, ply: usize) -> i32 {
+        match self {
+            Wdl::Win => TABLEBASE_WIN - ply as i32,
+            Wdl::Loss => -TABLEBASE_WIN + ply as i32,
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0,
+        }
+    }
+}
+
+/// The best move in a position according to the tablebases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TablebaseMove {
+    /// The move, from the perspective of the side to move.
+    pub mv: PieceMove,
+
+    /// The result of the position before the move.
+    pub wdl: Wdl,
+
+    /// The plies until the next capture or pawn move with best play, negative if the side to
+    /// move is losing. May be off by one.
+    pub dtz: i32,
+}
+
+/// A set of Syzygy tables loaded from one or more directories.
+pub struct Tablebase {
+    tables: SyzygyTables<Chess>,
+}
+
+impl Tablebase {
+    /// Loads the tables from a directory, or several separated by `;` (or `:` on Unix) as in
+    /// UCI's `SyzygyPath` option.
+    pub fn open(paths: &str) -> Result<Self, anyhow::Error> {
+        let mut tables = SyzygyTables::new();
+        let separator = if cfg!(windows) { ';' } else { ':' };
+
+        for path in paths
+            .split([separator, ';'])
+            .filter(|path| !path.is_empty())
+        {
+            tables
+                .add_directory(Path::new(path))
+                .map_err(|e| anyhow::anyhow!("Couldn't load tablebases from {}: {}", path, e))?;
+        }
+
+        Ok(Self { tables })
+    }
+
+    /// The most pieces, kings included, of any loaded table.
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+
+    /// The position as the tablebases take it, if they might have it: a classic position with
+    /// no more pieces than the largest table.
+    fn to_chess(&self, position: &Position, game_type: GameType) -> Option<Chess> {
+        if game_type != GameType::Classic {
+            return None;
+        }
+
+        let pieces = position
+            .white_pieces
+            .iter()
+            .chain(position.black_pieces.iter())
+            .flatten()
+            .collect::<Vec<_>>();
+
+        if pieces.len() > self.max_pieces() || pieces.iter().any(|piece| piece.holding.is_some()) {
+            return None;
+        }
+
+        position
+            .to_fen()
+            .parse::<Fen>()
+            .ok()?
+            .into_position(CastlingMode::Standard)
+            .ok()
+    }
+
+    /// The result of a position (from the perspective of its side to move), if the tables
+    /// have it and can tell it exactly.
+    pub fn probe_wdl(&self, position: &Position, game_type: GameType) -> Option<Wdl> {
+        let chess = self.to_chess(position, game_type)?;
+
+        match self.tables.probe_wdl(&chess).ok()? {
+            AmbiguousWdl::Win => Some(Wdl::Win),
+            AmbiguousWdl::CursedWin => Some(Wdl::CursedWin),
+            AmbiguousWdl::Draw => Some(Wdl::Draw),
+            AmbiguousWdl::BlessedLoss => Some(Wdl::BlessedLoss),
+            AmbiguousWdl::Loss => Some(Wdl::Loss),
+
+            // Depends on how many moves ago the last capture or pawn move was
+            AmbiguousWdl::MaybeWin | AmbiguousWdl::MaybeLoss => None,
+        }
+    }
+
+    /// The move that keeps the best result and makes progress towards it, if the tables have
+    /// the position. Used to play endgames perfectly from the root, without searching.
+    pub fn best_move(&self, position: &Position, game_type: GameType) -> Option<TablebaseMove> {
+        let chess = self.to_chess(position, game_type)?;
+        let wdl = self.probe_wdl(position, game_type)?;
+
+        let (mv, _) = self.tables.best_move(&chess).ok()??;
+        let dtz = self.tables.probe_dtz(&chess).ok()?.ignore_rounding().0;
+
+        let uci = mv.to_uci(CastlingMode::Standard).to_string();
+        let mv = match position.true_active_color {
+            Color::White => PieceMove::from_uci(position, &uci, game_type),
+            Color::Black => PieceMove::from_uci_inverted(position, &uci, game_type),
+        }
+        .ok()?;
+
+        Some(TablebaseMove { mv, wdl, dtz })
+    }
+}
@@ -0,0 +1,261 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Color;
+
+/// Roughly how many more moves to budget the remaining time for.
+const MOVES_TO_GO: u64 = 30;
+
+/// Time held back so the engine doesn't lose on time to its own overhead.
+const SAFETY_MARGIN_MS: u64 = 50;
+
+/// How long to think about a move with `remaining_ms` left on the clock, spreading it over
+/// `moves_to_go` moves (or a rough guess if the time control doesn't say) and spending most of
+/// the increment.
+pub fn time_for_move(remaining_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> u64 {
+    let moves_to_go = moves_to_go.map_or(MOVES_TO_GO, |moves| moves.max(1) as u64);
+
+    let budget = remaining_ms / moves_to_go + increment_ms * 3 / 4;
+    let limit = remaining_ms.saturating_sub(SAFETY_MARGIN_MS);
+
+    budget.min(limit).max(10)
+}
+
+/// One side's clock setting: a starting amount of time, plus an increment added after each move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    pub initial_ms: u64,
+    pub increment_ms: u64,
+}
+
+impl Clock {
+    pub fn new(initial_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            initial_ms,
+            increment_ms,
+        }
+    }
+}
+
+impl FromStr for Clock {
+    type Err = anyhow::Error;
+
+    /// Parses the usual "minutes+seconds" notation, e.g. "10+5" or "0.5+0". The increment is
+    /// optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (minutes, seconds) = s.trim().split_once('+').unwrap_or((s.trim(), "0"));
+
+        let minutes: f64 = minutes
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid clock: {}", s))?;
+        let seconds: f64 = seconds
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid clock: {}", s))?;
+
+        if minutes <= 0.0 || seconds < 0.0 {
+            return Err(anyhow::anyhow!("Invalid clock: {}", s));
+        }
+
+        Ok(Clock::new(
+            (minutes * 60_000.0) as u64,
+            (seconds * 1000.0) as u64,
+        ))
+    }
+}
+
+impl Display for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.initial_ms as f64 / 60_000.0,
+            self.increment_ms as f64 / 1000.0
+        )
+    }
+}
+
+/// The clock settings for both sides. They don't have to be the same, so one side can be
+/// given time odds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub white: Clock,
+    pub black: Clock,
+}
+
+impl TimeControl {
+    /// The same clock for both sides.
+    pub fn symmetric(clock: Clock) -> Self {
+        Self {
+            white: clock,
+            black: clock,
+        }
+    }
+
+    pub fn new(white: Clock, black: Clock) -> Self {
+        Self { white, black }
+    }
+
+    pub fn clock(&self, color: Color) -> Clock {
+        match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        }
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.white == self.black
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = anyhow::Error;
+
+    /// Parses a clock for both sides, e.g. "5+3", or separate clocks for white and black, e.g.
+    /// "1+0:10+0" for white to play with one minute against black's ten.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((white, black)) => Ok(TimeControl::new(white.parse()?, black.parse()?)),
+            None => Ok(TimeControl::symmetric(s.parse()?)),
+        }
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_symmetric() {
+            write!(f, "{}", self.white)
+        } else {
+            write!(f, "{}:{}", self.white, self.black)
+        }
+    }
+}
+
+/// The time left on both sides' clocks during a game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    pub time_control: TimeControl,
+
+    /// The time remaining for white and black respectively.
+    pub remaining_ms: (u64, u64),
+
+    /// The side that ran out of time, if any.
+    pub flagged: Option<Color>,
+}
+
+impl GameClock {
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            time_control,
+            remaining_ms: (time_control.white.initial_ms, time_control.black.initial_ms),
+            flagged: None,
+        }
+    }
+
+    pub fn remaining(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.remaining_ms.0,
+            Color::Black => self.remaining_ms.1,
+        }
+    }
+
+    fn remaining_mut(&mut self, color: Color) -> &mut u64 {
+        match color {
+            Color::White => &mut self.remaining_ms.0,
+            Color::Black => &mut self.remaining_ms.1,
+        }
+    }
+
+    /// How long `color` should think about its next move, spreading its remaining time over
+    /// the moves it still has to make and spending most of its increment.
+    pub fn time_for_move(&self, color: Color) -> u64 {
+        time_for_move(
+            self.remaining(color),
+            self.time_control.clock(color).increment_ms,
+            None,
+        )
+    }
+
+    /// Takes the time `color` spent on a move off its clock and adds its increment. Returns
+    /// false if it ran out of time.
+    pub fn record_move(&mut self, color: Color, elapsed_ms: u64) -> bool {
+        let increment = self.time_control.clock(color).increment_ms;
+        let remaining = self.remaining_mut(color);
+
+        if elapsed_ms > *remaining {
+            *remaining = 0;
+            self.flagged = Some(color);
+            return false;
+        }
+
+        *remaining = *remaining - elapsed_ms + increment;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let time_control: TimeControl = "5+3".parse().unwrap();
+        assert!(time_control.is_symmetric());
+        assert_eq!(time_control.white, Clock::new(300_000, 3_000));
+        assert_eq!(time_control.to_string(), "5+3");
+
+        let time_control: TimeControl = "1+0:10".parse().unwrap();
+        assert!(!time_control.is_symmetric());
+        assert_eq!(time_control.white, Clock::new(60_000, 0));
+        assert_eq!(time_control.black, Clock::new(600_000, 0));
+        assert_eq!(time_control.to_string(), "1+0:10+0");
+
+        assert_eq!("0.5+0".parse::<Clock>().unwrap(), Clock::new(30_000, 0));
+
+        assert!("".parse::<TimeControl>().is_err());
+        assert!("0+5".parse::<TimeControl>().is_err());
+        assert!("5+x".parse::<TimeControl>().is_err());
+        assert!("1+0:".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn test_time_odds() {
+        let clock = GameClock::new("1+0:10+0".parse().unwrap());
+
+        assert_eq!(clock.remaining(Color::White), 60_000);
+        assert_eq!(clock.remaining(Color::Black), 600_000);
+        assert_eq!(clock.time_for_move(Color::White), 2_000);
+        assert_eq!(clock.time_for_move(Color::Black), 20_000);
+    }
+
+    #[test]
+    fn test_moves_to_go() {
+        assert_eq!(time_for_move(60_000, 0, None), 2_000);
+        assert_eq!(time_for_move(60_000, 0, Some(10)), 6_000);
+        assert_eq!(time_for_move(60_000, 2_000, Some(10)), 7_500);
+
+        // The last move before the time control can use almost everything that's left
+        assert_eq!(time_for_move(5_000, 0, Some(1)), 4_950);
+        assert_eq!(time_for_move(5_000, 0, Some(0)), 4_950);
+    }
+
+    #[test]
+    fn test_record_move() {
+        let mut clock = GameClock::new(TimeControl::new(
+            Clock::new(1_000, 500),
+            Clock::new(60_000, 0),
+        ));
+
+        assert!(clock.record_move(Color::White, 800));
+        assert_eq!(clock.remaining(Color::White), 700);
+        assert!(clock.record_move(Color::Black, 800));
+        assert_eq!(clock.remaining(Color::Black), 59_200);
+
+        // Never budgets more than what's left on the clock
+        assert!(clock.time_for_move(Color::White) <= 650);
+
+        assert!(!clock.record_move(Color::White, 701));
+        assert_eq!(clock.flagged, Some(Color::White));
+        assert_eq!(clock.remaining(Color::White), 0);
+    }
+}
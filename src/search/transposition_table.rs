@@ -2,6 +2,8 @@ use fxhash::FxHashMap;
 
 use crate::{position::HashablePosition, PieceMove};
 
+use super::score::is_mate_score;
+
 /// A transposition table that stores positions and their scores and depths.
 ///
 /// This table is used to store the results of previous searches so that they
@@ -9,6 +11,17 @@ use crate::{position::HashablePosition, PieceMove};
 #[derive(Clone, Debug)]
 pub struct TranspositionTable {
     table: FxHashMap<HashablePosition, TranspositionTableEntry>,
+
+    /// The most positions the table will hold, or `None` for no limit.
+    max_entries: Option<usize>,
+
+    /// Bumped at the start of every search, so entries left over from earlier searches can be
+    /// told apart from the current one's.
+    generation: u8,
+
+    /// Whether entries from earlier generations have already been evicted to make room during
+    /// the current search.
+    evicted_stale: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -19,6 +32,9 @@ pub struct TranspositionTableEntry {
     pub depth: u32,
     pub principal_variation: Vec<PieceMove>,
     pub node_type: NodeType,
+
+    /// The generation of the search that stored the entry.
+    pub generation: u8,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Copy)]
@@ -33,26 +49,114 @@ impl TranspositionTable {
     pub fn new() -> Self {
         Self {
             table: FxHashMap::default(),
+            max_entries: None,
+            generation: 0,
+            evicted_stale: false,
+        }
+    }
+
+    /// Creates a transposition table that uses roughly `megabytes` of memory at most.
+    pub fn with_size_mb(megabytes: usize) -> Self {
+        let mut table = Self::new();
+        table.set_size_mb(megabytes);
+        table
+    }
+
+    /// Limits the table to roughly `megabytes` of memory. Once full, entries from earlier
+    /// searches make way for new positions; after that new positions are no longer added, but
+    /// the positions already in the table can still be updated. If the table is already over
+    /// the new limit it's cleared.
+    pub fn set_size_mb(&mut self, megabytes: usize) {
+        let max_entries = megabytes * 1024 * 1024 / Self::entry_size();
+        self.max_entries = Some(max_entries);
+
+        if self.table.len() > max_entries {
+            self.table.clear();
+        }
+    }
+
+    /// The approximate memory used by each position in the table, not counting principal
+    /// variations, which are usually short.
+    fn entry_size() -> usize {
+        std::mem::size_of::<HashablePosition>() + std::mem::size_of::<TranspositionTableEntry>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// The most positions the table will hold, or `None` if it isn't limited.
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// How full the table is in permille, as reported by UCI's `hashfull`. A table without a
+    /// size limit is never full.
+    pub fn hashfull(&self) -> u32 {
+        match self.max_entries {
+            Some(max_entries) if max_entries > 0 => {
+                (self.table.len() * 1000 / max_entries).min(1000) as u32
+            }
+            _ => 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_entries
+            .is_some_and(|max_entries| self.table.len() >= max_entries)
+    }
+
+    /// Starts a new generation for the next search. Entries from earlier generations are kept
+    /// for as long as there's room, but are the first to go once the table fills up.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.evicted_stale = false;
+    }
+
+    /// The generation of the current search, to store in new entries.
+    pub fn generation(&self) -> u8 {
+        self.generation
+    }
+
+    /// Whether a new position can be added. When the table is full, entries from earlier
+    /// generations are evicted to make room, at most once per search so a table full of
+    /// current entries isn't scanned again for every position.
+    fn make_room(&mut self) -> bool {
+        if self.is_full() && !self.evicted_stale {
+            let generation = self.generation;
+            self.table.retain(|_, entry| entry.generation == generation);
+            self.evicted_stale = true;
         }
+
+        !self.is_full()
     }
 
-    /// Gets the score and depth of a position from the table.
+    /// Gets the score and depth of a position from the table. Mate scores in the
+    /// returned entry are relative to the stored node, see `score_to_tt`.
     pub fn get(&self, position: &HashablePosition) -> Option<TranspositionTableEntry> {
         self.table.get(&position).cloned()
     }
 
     /// Tries to get the score of a position from the table. If the depth of the
     /// stored score is greater than or equal to the given depth, the score is
-    /// returned. Otherwise, `None` is returned.
+    /// returned. Otherwise, `None` is returned. `ply` is the probing node's distance
+    /// from the root, which mate scores are adjusted for.
     pub fn try_get(
         &self,
         position: &HashablePosition,
         depth: u32,
+        ply: u32,
         alpha: i32,
         beta: i32,
-    ) -> Option<&TranspositionTableEntry> {
+    ) -> Option<TranspositionTableEntry> {
         if let Some(entry) = self.table.get(&position) {
             if entry.depth >= depth {
+                let entry = entry.for_ply(ply);
+
                 match entry.node_type {
                     // For exact scores, just check if score is within current window
                     NodeType::Exact if entry.score > alpha && entry.score < beta => Some(entry),
@@ -74,24 +178,274 @@ impl TranspositionTable {
         }
     }
 
-    /// Inserts a position into the table with the given score and depth.
-    pub fn insert(&mut self, position: HashablePosition, entry: TranspositionTableEntry) {
+    /// Inserts a position into the table with the given score and depth, found `ply`
+    /// plies from the root.
+    pub fn insert(&mut self, position: HashablePosition, entry: TranspositionTableEntry, ply: u32) {
+        if !self.table.contains_key(&position) && !self.make_room() {
+            return;
+        }
+
+        let entry = entry.into_tt(ply);
         self.table.insert(position, entry);
     }
 
-    pub fn insert_if_better(&mut self, position: HashablePosition, entry: TranspositionTableEntry) {
+    pub fn insert_if_better(
+        &mut self,
+        position: HashablePosition,
+        entry: TranspositionTableEntry,
+        ply: u32,
+    ) {
+        let entry = entry.into_tt(ply);
+
         if let Some(existing_entry) = self.table.get(&position) {
-            if entry.depth > existing_entry.depth {
-                self.table.insert(position, entry);
-            } else if entry.depth == existing_entry.depth && entry.node_type == NodeType::Exact {
+            // An entry from an earlier search is replaced whatever its depth
+            if existing_entry.generation != entry.generation
+                || entry.depth > existing_entry.depth
+                || (entry.depth == existing_entry.depth && entry.node_type == NodeType::Exact)
+            {
                 self.table.insert(position, entry);
             }
-        } else {
+        } else if self.make_room() {
             self.table.insert(position, entry);
         }
     }
 
+    /// Removes every entry, keeping the size limit.
     pub fn clear(&mut self) {
         self.table.clear();
     }
 }
+
+impl TranspositionTableEntry {
+    /// Converts the scores of an entry found `ply` plies from the root into scores relative to
+    /// its node.
+    fn into_tt(mut self, ply: u32) -> Self {
+        self.score = score_to_tt(self.score, ply);
+        self.alpha = score_to_tt(self.alpha, ply);
+        self.beta = score_to_tt(self.beta, ply);
+        self
+    }
+
+    /// Converts the node-relative scores of a stored entry into scores for a node probing the
+    /// table `ply` plies from the root.
+    fn for_ply(&self, ply: u32) -> Self {
+        Self {
+            score: score_from_tt(self.score, ply),
+            alpha: score_from_tt(self.alpha, ply),
+            beta: score_from_tt(self.beta, ply),
+            ..self.clone()
+        }
+    }
+}
+
+/// Mate scores count plies from the root, so they depend on where in the tree the mate was
+/// found. Before storing, they are converted into the distance to mate from the node being
+/// stored, so that the same position reached at a different ply (or in a later search) gets
+/// the right mate distance.
+pub fn score_to_tt(score: i32, ply: u32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score < 0 {
+        score - ply as i32
+    } else {
+        score + ply as i32
+    }
+}
+
+/// Reverses `score_to_tt` for the node probing the table, `ply` plies from the root.
+pub fn score_from_tt(score: i32, ply: u32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score < 0 {
+        score + ply as i32
+    } else {
+        score - ply as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::{GameType, MoveList},
+        search::{
+            alpha_beta::{MAX_BETA, MIN_ALPHA},
+            score::{mate_in, mated_in},
+        },
+        Position,
+    };
+
+    use super::*;
+
+    fn entry(score: i32, depth: u32, node_type: NodeType) -> TranspositionTableEntry {
+        TranspositionTableEntry {
+            score,
+            alpha: score - 1,
+            beta: score + 1,
+            depth,
+            principal_variation: vec![],
+            node_type,
+            generation: 0,
+        }
+    }
+
+    #[test]
+    fn test_score_adjustment_round_trip() {
+        for score in [0, 150, -150, mated_in(6), mate_in(6), MAX_BETA, -MAX_BETA] {
+            for ply in 0..6 {
+                assert_eq!(score_from_tt(score_to_tt(score, ply), ply), score);
+            }
+        }
+    }
+
+    #[test]
+    fn test_normal_scores_not_adjusted() {
+        assert_eq!(score_to_tt(350, 5), 350);
+        assert_eq!(score_from_tt(-350, 5), -350);
+        assert_eq!(score_to_tt(MAX_BETA, 5), MAX_BETA);
+    }
+
+    #[test]
+    fn test_mate_score_adjusted_for_probing_ply() {
+        let position = crate::Position::start_position().to_hashable();
+        let mut table = TranspositionTable::new();
+
+        // Found 2 plies from the root, mated 5 plies from the root: mate is 3 plies away.
+        let mated = mated_in(5);
+        table.insert(position.clone(), entry(mated, 5, NodeType::Exact), 2);
+
+        // Probed 4 plies from the root, the mate is still 3 plies away, so 7 from the root
+        let probed = table.try_get(&position, 3, 4, MIN_ALPHA, MAX_BETA).unwrap();
+        assert_eq!(probed.score, mated_in(7));
+        assert_eq!(probed.alpha, mated_in(7) - 1);
+        assert_eq!(probed.beta, mated_in(7) + 1);
+
+        // Probed at the same ply, the score is unchanged
+        let probed = table.try_get(&position, 5, 2, MIN_ALPHA, MAX_BETA).unwrap();
+        assert_eq!(probed.score, mated);
+    }
+
+    #[test]
+    fn test_winning_mate_score_adjusted() {
+        let position = crate::Position::start_position().to_hashable();
+        let mut table = TranspositionTable::new();
+
+        let winning = mate_in(4);
+        table.insert_if_better(position.clone(), entry(winning, 6, NodeType::Exact), 1);
+
+        let probed = table.try_get(&position, 4, 3, MIN_ALPHA, MAX_BETA).unwrap();
+        assert_eq!(probed.score, mate_in(6));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut table = TranspositionTable::new();
+        table.max_entries = Some(3);
+
+        let start = Position::start_position();
+        let mut moves = MoveList::new();
+        start
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+        let positions: Vec<HashablePosition> = moves
+            .into_iter()
+            .take(4)
+            .map(|mv| {
+                let mut position = start.clone();
+                position.apply_move(mv).unwrap();
+                position.to_hashable()
+            })
+            .collect();
+
+        for position in &positions {
+            table.insert(position.clone(), entry(0, 1, NodeType::Exact), 0);
+        }
+
+        // The fourth position doesn't fit, but the ones already stored can still be updated
+        assert_eq!(table.len(), 3);
+        assert!(table.get(&positions[3]).is_none());
+
+        table.insert(positions[0].clone(), entry(50, 3, NodeType::Exact), 0);
+        assert_eq!(table.get(&positions[0]).unwrap().depth, 3);
+        assert_eq!(table.len(), 3);
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let mut table = TranspositionTable::new();
+        table.insert(
+            Position::start_position().to_hashable(),
+            entry(0, 1, NodeType::Exact),
+            0,
+        );
+        assert_eq!(table.hashfull(), 0);
+
+        table.max_entries = Some(4);
+        assert_eq!(table.hashfull(), 250);
+
+        table.clear();
+        assert_eq!(table.hashfull(), 0);
+        assert_eq!(table.max_entries(), Some(4));
+    }
+
+    #[test]
+    fn test_set_size_mb() {
+        let mut table = TranspositionTable::with_size_mb(1);
+        assert_eq!(
+            table.max_entries,
+            Some(1024 * 1024 / TranspositionTable::entry_size())
+        );
+
+        table.insert(
+            Position::start_position().to_hashable(),
+            entry(0, 1, NodeType::Exact),
+            0,
+        );
+        table.set_size_mb(0);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_generations() {
+        let mut table = TranspositionTable::new();
+        table.max_entries = Some(3);
+
+        let start = Position::start_position();
+        let mut moves = MoveList::new();
+        start
+            .get_all_legal_moves(GameType::Classic, &mut moves)
+            .unwrap();
+        let positions: Vec<HashablePosition> = moves
+            .into_iter()
+            .take(5)
+            .map(|mv| {
+                let mut position = start.clone();
+                position.apply_move(mv).unwrap();
+                position.to_hashable()
+            })
+            .collect();
+
+        for position in &positions[..3] {
+            table.insert(position.clone(), entry(0, 1, NodeType::Exact), 0);
+        }
+
+        // A shallower entry from a newer search replaces a deeper stale one
+        table.new_search();
+        let mut fresh = entry(0, 1, NodeType::UpperBound);
+        fresh.generation = table.generation();
+        table.insert_if_better(positions[0].clone(), entry(0, 4, NodeType::Exact), 0);
+        table.insert_if_better(positions[0].clone(), fresh.clone(), 0);
+        assert_eq!(table.get(&positions[0]).unwrap().generation, 1);
+
+        // Once full, the stale entries are evicted to make room
+        table.insert_if_better(positions[3].clone(), fresh.clone(), 0);
+        assert_eq!(table.len(), 2);
+        assert!(table.get(&positions[1]).is_none());
+        assert!(table.get(&positions[3]).is_some());
+
+        // But entries from the current search aren't
+        table.insert(positions[1].clone(), fresh.clone(), 0);
+        table.insert(positions[4].clone(), fresh, 0);
+        assert_eq!(table.len(), 3);
+        assert!(table.get(&positions[4]).is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Deliberately weak opponents, for beginners to play against and as baselines in engine
+//! matches: a random mover, a greedy mover that only counts material, and a one-ply search.
+
+use rand::seq::SliceRandom;
+
+use crate::{piece_move::MoveList, Position};
+
+use super::{
+    alpha_beta::{self, AlphaBetaError, MoveScore, SearchParams},
+    backend::SearchBackend,
+    search_results::{SearchResults, SearchState},
+};
+
+/// Plays a legal move chosen uniformly at random. Every move scores 0.
+pub struct RandomMover;
+
+impl SearchBackend for RandomMover {
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        let scores = self.score_all_moves(position, state, params.clone(), ply)?;
+        let chosen = scores.choose(&mut state.data.rng);
+
+        Ok(results(chosen, state, &params))
+    }
+
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        _ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError> {
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(params.game_type, &mut moves)
+            .unwrap();
+
+        Ok(scored(moves.iter().map(|&mv| (mv, 0)), state))
+    }
+}
+
+/// Plays the move that leaves it with the most material, ignoring the replies, square bonuses
+/// and everything else in the evaluation. Ties are broken at random, so it doesn't shuffle the
+/// same piece back and forth when nothing can be captured.
+pub struct GreedyMaterial;
+
+impl SearchBackend for GreedyMaterial {
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        let scores = self.score_all_moves(position, state, params.clone(), ply)?;
+        let best: Vec<&MoveScore> = scores
+            .iter()
+            .take_while(|score| score.score == scores[0].score)
+            .collect();
+        let chosen = best.choose(&mut state.data.rng).copied();
+
+        Ok(results(chosen, state, &params))
+    }
+
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        _ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError> {
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(params.game_type, &mut moves)
+            .unwrap();
+
+        let mut position = position.clone();
+        let mut move_scores = Vec::with_capacity(moves.len());
+
+        for &mv in moves.iter() {
+            let restore = position.apply_move(mv).unwrap();
+            let material = &position.material;
+            move_scores.push((mv, material.white.material - material.black.material));
+            position.unapply_move(mv, restore).unwrap();
+        }
+
+        Ok(scored(move_scores, state))
+    }
+}
+
+/// Searches a single ply, with quiescence, whatever depth it's asked for. It takes hanging
+/// pieces and sees recaptures but walks into any two-move tactic.
+pub struct DepthOne;
+
+impl SearchBackend for DepthOne {
+    fn search(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<SearchResults, AlphaBetaError> {
+        alpha_beta::search(position, state, SearchParams { depth: 1, ..params }, ply)
+    }
+
+    fn score_all_moves(
+        &self,
+        position: &Position,
+        state: &mut SearchState,
+        params: SearchParams,
+        ply: usize,
+    ) -> Result<Vec<MoveScore>, AlphaBetaError> {
+        alpha_beta::score_all_moves(position, state, SearchParams { depth: 1, ..params }, ply)
+    }
+}
+
+/// The moves with their scores, reported to `on_move_scored` and sorted best first.
+fn scored(
+    move_scores: impl IntoIterator<Item = (crate::PieceMove, i32)>,
+    state: &mut SearchState,
+) -> Vec<MoveScore> {
+    let mut scores: Vec<MoveScore> = move_scores
+        .into_iter()
+        .map(|(mv, score)| MoveScore {
+            mv,
+            score,
+            principal_variation: Some(vec![mv]),
+        })
+        .collect();
+
+    if let Some(on_move_scored) = state.callbacks.on_move_scored {
+        for move_score in &scores {
+            on_move_scored(move_score);
+        }
+    }
+
+    state.data.nodes_searched += scores.len() as u32;
+    scores.sort_by_key(|score| std::cmp::Reverse(score.score));
+
+    scores
+}
+
+fn results(
+    chosen: Option<&MoveScore>,
+    state: &SearchState,
+    params: &SearchParams,
+) -> SearchResults {
+    SearchResults {
+        best_move: chosen.map(|score| score.mv),
+        score: chosen.map_or(0, |score| score.score),
+        nodes_searched: state.data.nodes_searched,
+        cached_positions: state.data.cached_positions,
+        depth: params.depth,
+        time_taken_ms: state.data.start_time.elapsed().as_millis(),
+        pruned: state.data.pruned,
+        principal_variation: chosen.map(|score| vec![score.mv]),
+        alpha: params.initial_alpha,
+        beta: params.initial_beta,
+        statistics: state.data.statistics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        piece_move::GameType,
+        search::{
+            backend::{self, SearchAlgorithm},
+            transposition_table::TranspositionTable,
+        },
+    };
+
+    use super::*;
+
+    fn params(algorithm: SearchAlgorithm) -> SearchParams {
+        SearchParams::builder()
+            .depth(4)
+            .game_type(GameType::Classic)
+            .algorithm(algorithm)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_random_plays_legal_moves() {
+        let position = Position::start_position();
+        let mut legal = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Classic, &mut legal)
+            .unwrap();
+
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+
+        for _ in 0..20 {
+            let results =
+                backend::search(&position, &mut state, params(SearchAlgorithm::Random), 0).unwrap();
+            assert!(legal.contains(&results.best_move.unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_greedy_grabs_material() {
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+
+        // The queen is free
+        let position = Position::parse_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let results =
+            backend::search(&position, &mut state, params(SearchAlgorithm::Greedy), 0).unwrap();
+        assert_eq!(results.best_move.unwrap().to_uci(), "d2d5");
+        assert!(results.score > 0);
+
+        // The knight is defended, but greedy takes it anyway where a search wouldn't
+        let position = Position::parse_from_fen("4k3/2p5/3n4/8/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let results =
+            backend::search(&position, &mut state, params(SearchAlgorithm::Greedy), 0).unwrap();
+        assert_eq!(results.best_move.unwrap().to_uci(), "d2d6");
+
+        let results =
+            backend::search(&position, &mut state, params(SearchAlgorithm::AlphaBeta), 0).unwrap();
+        assert_ne!(results.best_move.unwrap().to_uci(), "d2d6");
+    }
+
+    #[test]
+    fn test_depth_one_searches_one_ply() {
+        let position = Position::parse_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut table);
+
+        let results =
+            backend::search(&position, &mut state, params(SearchAlgorithm::DepthOne), 0).unwrap();
+
+        assert_eq!(results.depth, 1);
+        assert_eq!(results.best_move.unwrap().to_uci(), "d2d5");
+    }
+}
@@ -0,0 +1,829 @@
+//! A headless server for using the engine from a web frontend or from other languages, over
+//! HTTP and WebSocket. Only built with the `server` feature.
+//!
+//! Requests and responses are JSON objects tagged with their `type`, and are the same either
+//! way they're sent:
+//!
+//! - POST a request to `/` and the response comes back as the body.
+//! - Connect a WebSocket to `/ws` and send requests as text messages. Each is answered in
+//!   order, and an `analyze` request then streams the analysis of its game as `analysis`
+//!   messages until it's stopped, a move is made, or the connection closes.
+//!
+//! Moves are sent and returned in UCI notation, e.g. `g1f3`, and `make_move` also takes SAN.
+//! Scores are from the side to move's point of view.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, trace};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+use crate::{
+    game::Game,
+    piece_move::GameType,
+    position::GameStatus,
+    search::{
+        alpha_beta::SearchParams, iterative_deepening::IterativeDeepeningData,
+        params_builder::MAX_SEARCH_PLY, score::Score, search_results::SearchInfo,
+        transposition_table::TranspositionTable,
+    },
+    Color, PieceMove,
+};
+
+/// How deep an analysis searches unless it's asked for a depth. Deeper than it gets in
+/// practice before it's stopped.
+const ANALYSIS_DEPTH: u32 = MAX_SEARCH_PLY / 2;
+
+/// How often a WebSocket connection checks for analysis updates to send while it waits for
+/// requests.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The longest request header the server reads.
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// The longest request body the server reads. Requests are small JSON objects, so this leaves
+/// plenty of room for a whole game's moves.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// How the server's engine searches.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// The deepest a best move is searched unless the request asks for a depth.
+    pub depth: u32,
+
+    /// The longest a best move is searched unless the request asks for a time limit.
+    pub time_limit_ms: u64,
+
+    /// The size of each game's transposition table.
+    pub hash_mb: usize,
+}
+
+impl ServerConfig {
+    pub fn new() -> Self {
+        Self {
+            depth: 8,
+            time_limit_ms: 5000,
+            hash_mb: 16,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A request to the server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Starts a game, from the standard starting position unless given a FEN.
+    NewGame {
+        #[serde(default)]
+        fen: Option<String>,
+
+        #[serde(default = "default_game_type")]
+        game_type: GameType,
+    },
+    GetGame {
+        game_id: u64,
+    },
+
+    /// Plays a move in UCI notation or SAN.
+    MakeMove {
+        game_id: u64,
+
+        #[serde(rename = "move")]
+        mv: String,
+    },
+
+    /// Searches the game's position for the best move, without playing it.
+    BestMove {
+        game_id: u64,
+
+        #[serde(default)]
+        depth: Option<u32>,
+
+        #[serde(default)]
+        time_limit_ms: Option<u64>,
+    },
+
+    /// Starts streaming the analysis of the game's position. WebSocket only.
+    Analyze {
+        game_id: u64,
+
+        #[serde(default)]
+        depth: Option<u32>,
+    },
+    StopAnalysis {
+        game_id: u64,
+    },
+    CloseGame {
+        game_id: u64,
+    },
+}
+
+fn default_game_type() -> GameType {
+    GameType::Rescue
+}
+
+/// A game's current state.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameInfo {
+    pub game_id: u64,
+    pub game_type: GameType,
+    pub fen: String,
+    pub to_move: Color,
+    pub status: GameStatus,
+
+    /// Every move played so far, in UCI notation.
+    pub moves: Vec<String>,
+
+    /// The legal moves in UCI notation, none once the game is over.
+    pub legal_moves: Vec<String>,
+}
+
+/// The result of a `best_move` search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BestMove {
+    pub game_id: u64,
+
+    /// `None` when there are no legal moves.
+    pub best_move: Option<String>,
+    pub score: Option<Score>,
+    pub depth: u32,
+    pub principal_variation: Vec<String>,
+}
+
+/// How a game's analysis is going, sent whenever its best line changes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Analysis {
+    pub game_id: u64,
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u32,
+    pub time_ms: u128,
+    pub principal_variation: Vec<String>,
+}
+
+/// A response to a request, or an update sent over a WebSocket.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Game(GameInfo),
+    BestMove(BestMove),
+    Analysis(Analysis),
+
+    /// The game's analysis has stopped, and no more `analysis` messages will be sent for it.
+    AnalysisStopped {
+        game_id: u64,
+    },
+    Closed {
+        game_id: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A game being played on the server, with the engine that searches it.
+struct ServerGame {
+    game: Game,
+    search: Arc<Mutex<IterativeDeepeningData>>,
+
+    /// Stops the game's running search. Replaced for every search, so stopping one never
+    /// stops the next.
+    stop: Arc<AtomicBool>,
+}
+
+impl ServerGame {
+    fn info(&self, game_id: u64) -> Result<GameInfo, anyhow::Error> {
+        let color = self.game.to_move();
+
+        Ok(GameInfo {
+            game_id,
+            game_type: self.game.game_type(),
+            fen: self.game.position().to_fen(),
+            to_move: color,
+            status: self.game.status(),
+            moves: self.game.moves().iter().map(|mv| mv.uci.clone()).collect(),
+            legal_moves: self
+                .game
+                .legal_moves()?
+                .iter()
+                .map(|mv| uci_move(mv, color))
+                .collect(),
+        })
+    }
+
+    /// Stops the running search, and returns the flag to stop the next one with.
+    fn restart_search(&mut self) -> Arc<AtomicBool> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.stop.clone()
+    }
+}
+
+/// The server's games, shared by every connection.
+pub struct Server {
+    config: ServerConfig,
+    games: Mutex<HashMap<u64, Arc<Mutex<ServerGame>>>>,
+    next_game_id: AtomicU64,
+}
+
+impl Server {
+    pub fn new(config: ServerConfig) -> Self {
+        Self {
+            config,
+            games: Mutex::new(HashMap::new()),
+            next_game_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Listens on `address`, e.g. `127.0.0.1:8080`, serving each connection on its own thread.
+    pub fn serve(self: Arc<Self>, address: &str) -> Result<(), anyhow::Error> {
+        let listener = TcpListener::bind(address)?;
+        trace!("Listening on {}", listener.local_addr()?);
+
+        self.serve_listener(listener)
+    }
+
+    pub fn serve_listener(self: Arc<Self>, listener: TcpListener) -> Result<(), anyhow::Error> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+
+            thread::spawn(move || {
+                if let Err(e) = server.handle_connection(stream) {
+                    error!("Error serving connection: {}", e);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Handles a request. Analysis updates are sent to `updates`, which only WebSocket
+    /// connections have.
+    pub fn handle(&self, request: Request, updates: Option<&Sender<Response>>) -> Response {
+        let result = match request {
+            Request::NewGame { fen, game_type } => self.new_game(fen.as_deref(), game_type),
+            Request::GetGame { game_id } => self.game(game_id).and_then(|game| {
+                let game = game.lock().unwrap();
+                Ok(Response::Game(game.info(game_id)?))
+            }),
+            Request::MakeMove { game_id, mv } => self.make_move(game_id, &mv),
+            Request::BestMove {
+                game_id,
+                depth,
+                time_limit_ms,
+            } => self.best_move(game_id, depth, time_limit_ms),
+            Request::Analyze { game_id, depth } => match updates {
+                Some(updates) => self.analyze(game_id, depth, updates.clone()),
+                None => Err(anyhow::anyhow!("Analysis is only available over WebSocket")),
+            },
+            Request::StopAnalysis { game_id } => self.game(game_id).map(|game| {
+                game.lock().unwrap().restart_search();
+                Response::AnalysisStopped { game_id }
+            }),
+            Request::CloseGame { game_id } => self.close_game(game_id),
+        };
+
+        result.unwrap_or_else(|e| Response::Error {
+            message: e.to_string(),
+        })
+    }
+
+    fn game(&self, game_id: u64) -> Result<Arc<Mutex<ServerGame>>, anyhow::Error> {
+        self.games
+            .lock()
+            .unwrap()
+            .get(&game_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No game with ID {}", game_id))
+    }
+
+    fn new_game(&self, fen: Option<&str>, game_type: GameType) -> Result<Response, anyhow::Error> {
+        let game = match fen {
+            Some(fen) => Game::from_fen(fen, game_type)?,
+            None => Game::new(game_type),
+        };
+
+        let mut search = IterativeDeepeningData::new();
+        search.transposition_table = TranspositionTable::with_size_mb(self.config.hash_mb);
+
+        let game = ServerGame {
+            game,
+            search: Arc::new(Mutex::new(search)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        let game_id = self.next_game_id.fetch_add(1, Ordering::Relaxed);
+        let info = game.info(game_id)?;
+
+        self.games
+            .lock()
+            .unwrap()
+            .insert(game_id, Arc::new(Mutex::new(game)));
+
+        trace!("Started game {}", game_id);
+
+        Ok(Response::Game(info))
+    }
+
+    fn make_move(&self, game_id: u64, notation: &str) -> Result<Response, anyhow::Error> {
+        let game = self.game(game_id)?;
+        let mut game = game.lock().unwrap();
+
+        // Any analysis is of the position before the move
+        game.restart_search();
+
+        if game.game.make_uci_move(notation).is_err() {
+            game.game.make_san_move(notation)?;
+        }
+
+        Ok(Response::Game(game.info(game_id)?))
+    }
+
+    fn best_move(
+        &self,
+        game_id: u64,
+        depth: Option<u32>,
+        time_limit_ms: Option<u64>,
+    ) -> Result<Response, anyhow::Error> {
+        let (search, stop, game, params) = {
+            let game = self.game(game_id)?;
+            let mut game = game.lock().unwrap();
+
+            let params = SearchParams::builder()
+                .depth(depth.unwrap_or(self.config.depth))
+                .time_ms(time_limit_ms.unwrap_or(self.config.time_limit_ms))
+                .game_type(game.game.game_type())
+                .build()?;
+
+            (
+                game.search.clone(),
+                game.restart_search(),
+                game.game.clone(),
+                params,
+            )
+        };
+
+        let color = game.to_move();
+
+        let mut search = search.lock().unwrap();
+        prepare_search(&mut search, &game, stop);
+        search.search(params);
+
+        Ok(Response::BestMove(BestMove {
+            game_id,
+            best_move: search.best_move.map(|mv| uci_move(&mv, color)),
+            score: search.best_score.map(Score::from_search),
+            depth: search.stats.depth,
+            principal_variation: uci_line(search.previous_pv.as_deref().unwrap_or(&[]), color),
+        }))
+    }
+
+    /// Analyzes the game's position on its own thread, sending each new best line to
+    /// `updates` until the analysis is stopped or finishes.
+    fn analyze(
+        &self,
+        game_id: u64,
+        depth: Option<u32>,
+        updates: Sender<Response>,
+    ) -> Result<Response, anyhow::Error> {
+        let game = self.game(game_id)?;
+        let mut game = game.lock().unwrap();
+
+        let params = SearchParams::builder()
+            .depth(depth.unwrap_or(ANALYSIS_DEPTH))
+            .game_type(game.game.game_type())
+            .build()?;
+
+        let info = game.info(game_id)?;
+        let stop = game.restart_search();
+        let search = game.search.clone();
+        let position = game.game.clone();
+        let color = position.to_move();
+
+        thread::spawn(move || {
+            let mut search = search.lock().unwrap();
+            prepare_search(&mut search, &position, stop);
+
+            let analysis_updates = updates.clone();
+            search.on_new_best_move = Some(Box::new(move |info: &SearchInfo| {
+                let _ = analysis_updates.send(Response::Analysis(Analysis {
+                    game_id,
+                    depth: info.depth,
+                    score: Score::from_search(info.score),
+                    nodes: info.nodes,
+                    time_ms: info.time_ms,
+                    principal_variation: uci_line(&info.principal_variation, color),
+                }));
+            }));
+
+            search.search(params);
+            search.on_new_best_move = None;
+
+            // The connection may have closed
+            let _ = updates.send(Response::AnalysisStopped { game_id });
+        });
+
+        Ok(Response::Game(info))
+    }
+
+    fn close_game(&self, game_id: u64) -> Result<Response, anyhow::Error> {
+        let game = self
+            .games
+            .lock()
+            .unwrap()
+            .remove(&game_id)
+            .ok_or_else(|| anyhow::anyhow!("No game with ID {}", game_id))?;
+
+        game.lock().unwrap().restart_search();
+        trace!("Closed game {}", game_id);
+
+        Ok(Response::Closed { game_id })
+    }
+
+    fn handle_json(&self, json: &str, updates: Option<&Sender<Response>>) -> Response {
+        match serde_json::from_str(json) {
+            Ok(request) => self.handle(request, updates),
+            Err(e) => Response::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        }
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> Result<(), anyhow::Error> {
+        let request = match read_http_request(&mut stream) {
+            Ok(request) => request,
+            Err(e) if e.is::<BodyTooLarge>() => {
+                trace!("{}", e);
+                return write_http_response(&mut stream, "413 Payload Too Large", "");
+            }
+            Err(e) => return Err(e),
+        };
+        trace!("{} {}", request.method, request.path);
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/ws") => {
+                let Some(key) = request.websocket_key else {
+                    return write_http_response(&mut stream, "400 Bad Request", "");
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                    derive_accept_key(key.as_bytes())
+                )?;
+
+                self.serve_websocket(stream)
+            }
+            // Preflight requests from web pages on other origins
+            ("OPTIONS", _) => write_http_response(&mut stream, "204 No Content", ""),
+            ("POST", "/") => {
+                let body = String::from_utf8(request.body)?;
+                let response = self.handle_json(&body, None);
+
+                let status = match response {
+                    Response::Error { .. } => "400 Bad Request",
+                    _ => "200 OK",
+                };
+
+                write_http_response(&mut stream, status, &serde_json::to_string(&response)?)
+            }
+            _ => write_http_response(&mut stream, "404 Not Found", ""),
+        }
+    }
+
+    /// Answers a WebSocket connection's requests, and sends it the updates of the analyses it
+    /// started, until it closes. Its analyses are then stopped.
+    fn serve_websocket(&self, stream: TcpStream) -> Result<(), anyhow::Error> {
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+        let (updates, received) = mpsc::channel();
+        let mut analyzing = vec![];
+
+        let result = loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    if let Ok(Request::Analyze { game_id, .. }) = serde_json::from_str(&text) {
+                        analyzing.push(game_id);
+                    }
+
+                    let response = self.handle_json(&text, Some(&updates));
+                    socket.send(Message::Text(serde_json::to_string(&response)?))?;
+                }
+                Ok(Message::Close(_)) => break Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    break Ok(())
+                }
+                Err(e) => break Err(e.into()),
+            }
+
+            for update in received.try_iter() {
+                socket.send(Message::Text(serde_json::to_string(&update)?))?;
+            }
+        };
+
+        for game_id in analyzing {
+            if let Ok(game) = self.game(game_id) {
+                game.lock().unwrap().restart_search();
+            }
+        }
+
+        result
+    }
+}
+
+/// Points the search at the game's current position, to be stopped with `stop`.
+fn prepare_search(search: &mut IterativeDeepeningData, game: &Game, stop: Arc<AtomicBool>) {
+    search.update_position(game.position().clone());
+    search.update_history(game.history().to_vec());
+    search.previous_pv = None;
+    search.best_score = None;
+    search.stop = stop;
+}
+
+/// A move from the perspective of `color`, the side playing it, in UCI notation.
+fn uci_move(mv: &PieceMove, color: Color) -> String {
+    match color {
+        Color::White => mv.to_uci(),
+        Color::Black => mv.inverted().to_uci(),
+    }
+}
+
+/// A line of moves in UCI notation, each from the perspective of the side playing it, starting
+/// with `color`'s move.
+fn uci_line(line: &[PieceMove], color: Color) -> Vec<String> {
+    let mut color = color;
+
+    line.iter()
+        .map(|mv| {
+            let notation = uci_move(mv, color);
+            color = color.invert();
+            notation
+        })
+        .collect()
+}
+
+/// The parts of an HTTP request the server looks at.
+struct HttpRequest {
+    method: String,
+    path: String,
+
+    /// The `Sec-WebSocket-Key` header of a WebSocket handshake.
+    websocket_key: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A request with a `Content-Length` over `MAX_BODY_SIZE`, whose body isn't read.
+#[derive(Debug)]
+struct BodyTooLarge(usize);
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Request body of {} bytes is over the limit of {}",
+            self.0, MAX_BODY_SIZE
+        )
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// Reads an HTTP request's header, and its body if it has a `Content-Length`. Fails with
+/// `BodyTooLarge` without reading the body if it's longer than `MAX_BODY_SIZE`.
+fn read_http_request(stream: &mut impl Read) -> Result<HttpRequest, anyhow::Error> {
+    let mut buffer = vec![];
+    let mut chunk = [0; 4096];
+
+    loop {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Err(anyhow::anyhow!(
+                "Connection closed before the request ended"
+            ));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut headers);
+
+        let httparse::Status::Complete(header_len) = request.parse(&buffer)? else {
+            if buffer.len() > MAX_HEADER_SIZE {
+                return Err(anyhow::anyhow!("Request header too long"));
+            }
+            continue;
+        };
+
+        let header = |name: &str| {
+            request
+                .headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .and_then(|header| std::str::from_utf8(header.value).ok())
+        };
+
+        let content_length: usize = header("Content-Length").unwrap_or("0").trim().parse()?;
+        if content_length > MAX_BODY_SIZE {
+            return Err(BodyTooLarge(content_length).into());
+        }
+
+        let websocket_key = header("Sec-WebSocket-Key").map(|key| key.trim().to_string());
+        let method = request.method.unwrap_or_default().to_string();
+        let path = request.path.unwrap_or_default().to_string();
+
+        let mut body = buffer.split_off(header_len);
+        if body.len() < content_length {
+            let mut rest = vec![0; content_length - body.len()];
+            stream.read_exact(&mut rest)?;
+            body.extend(rest);
+        }
+        body.truncate(content_length);
+
+        return Ok(HttpRequest {
+            method,
+            path,
+            websocket_key,
+            body,
+        });
+    }
+}
+
+/// Writes a JSON response and closes the connection. Any origin may call the server.
+fn write_http_response(
+    stream: &mut impl Write,
+    status: &str,
+    body: &str,
+) -> Result<(), anyhow::Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(server: &Server, json: &str) -> Response {
+        server.handle_json(json, None)
+    }
+
+    fn new_game(server: &Server) -> u64 {
+        match request(server, r#"{"type":"new_game","game_type":"Classic"}"#) {
+            Response::Game(info) => info.game_id,
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_make_move() {
+        let server = Server::new(ServerConfig::new());
+        let game_id = new_game(&server);
+
+        let json = format!(
+            r#"{{"type":"make_move","game_id":{},"move":"e2e4"}}"#,
+            game_id
+        );
+        let Response::Game(info) = request(&server, &json) else {
+            panic!("Expected the game");
+        };
+        assert_eq!(info.to_move, Color::Black);
+        assert_eq!(info.legal_moves.len(), 20);
+
+        // SAN is accepted too
+        let json = format!(
+            r#"{{"type":"make_move","game_id":{},"move":"e5"}}"#,
+            game_id
+        );
+        let Response::Game(info) = request(&server, &json) else {
+            panic!("Expected the game");
+        };
+        assert_eq!(info.moves, ["e2e4", "e7e5"]);
+
+        let json = format!(
+            r#"{{"type":"make_move","game_id":{},"move":"e4e6"}}"#,
+            game_id
+        );
+        assert!(matches!(request(&server, &json), Response::Error { .. }));
+    }
+
+    #[test]
+    fn test_best_move() {
+        let server = Server::new(ServerConfig::new());
+        let game_id = new_game(&server);
+
+        // Black to move, and mate in one with Qh4#
+        for mv in ["f2f3", "e7e5", "g2g4"] {
+            let json = format!(
+                r#"{{"type":"make_move","game_id":{},"move":"{}"}}"#,
+                game_id, mv
+            );
+            request(&server, &json);
+        }
+
+        let json = format!(r#"{{"type":"best_move","game_id":{},"depth":3}}"#, game_id);
+        let Response::BestMove(best_move) = request(&server, &json) else {
+            panic!("Expected the best move");
+        };
+        assert_eq!(best_move.best_move.as_deref(), Some("d8h4"));
+        assert_eq!(best_move.score, Some(Score::Mate(1)));
+        assert_eq!(best_move.principal_variation[0], "d8h4");
+    }
+
+    #[test]
+    fn test_analysis_needs_websocket() {
+        let server = Server::new(ServerConfig::new());
+        let game_id = new_game(&server);
+
+        let json = format!(r#"{{"type":"analyze","game_id":{}}}"#, game_id);
+        assert!(matches!(request(&server, &json), Response::Error { .. }));
+    }
+
+    #[test]
+    fn test_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = Arc::new(Server::new(ServerConfig::new()));
+        thread::spawn(move || server.serve_listener(listener));
+
+        let body = r#"{"type":"new_game"}"#;
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(r#""type":"game""#));
+        assert!(response.contains(r#""game_type":"Rescue""#));
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{{}}",
+            MAX_BODY_SIZE + 1
+        );
+        let error = read_http_request(&mut request.as_bytes()).err().unwrap();
+        assert!(error.is::<BodyTooLarge>());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = Arc::new(Server::new(ServerConfig::new()));
+        thread::spawn(move || server.serve_listener(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            usize::MAX
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Runs suites of EPD test positions, such as Win at Chess (WAC) or the Strategic Test Suite
+//! (STS), against the engine. Each position gives moves to find (`bm`), moves to avoid (`am`)
+//! or a mate to find (`dm`), and is searched for a fixed time.
+
+use std::time::Instant;
+
+use crate::{
+    endgame_training::Verdict,
+    pgn,
+    piece_move::GameType,
+    position::extended_fen::ExtendedPosition,
+    search::{
+        alpha_beta::SearchParams,
+        backend,
+        search_results::{SearchResults, SearchState},
+        transposition_table::TranspositionTable,
+    },
+    PieceMove,
+};
+
+/// Parses the positions of a test suite, one EPD per line. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_suite(text: &str) -> Result<Vec<ExtendedPosition>, anyhow::Error> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            ExtendedPosition::parse_from_epd(line)
+                .map_err(|e| anyhow::anyhow!("Line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// The outcome of searching one test position.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    /// The position's `id`, if it has one.
+    pub id: Option<String>,
+
+    /// Whether the engine's move was one of the best moves and none of the moves to avoid, and
+    /// it found a mate at least as short as the one expected.
+    pub passed: bool,
+
+    /// The move the engine chose, in SAN.
+    pub found: Option<String>,
+
+    pub score: i32,
+
+    /// The deepest depth the search finished in the time it had.
+    pub depth: u32,
+
+    pub nodes_searched: u32,
+    pub time_taken_ms: u128,
+}
+
+/// The results of every position in a suite, in the suite's order.
+#[derive(Debug, Clone, Default)]
+pub struct SuiteResults {
+    pub results: Vec<Result<TestResult, String>>,
+}
+
+impl SuiteResults {
+    /// How many positions were solved.
+    pub fn passed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| matches!(result, Ok(TestResult { passed: true, .. })))
+            .count()
+    }
+
+    /// The fraction of the positions that were solved, counting positions that couldn't be
+    /// tested as failures.
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+
+        self.passed() as f64 / self.results.len() as f64
+    }
+}
+
+/// Searches test positions for a fixed time each and checks the moves found.
+#[derive(Debug, Clone)]
+pub struct TestSuiteRunner {
+    /// How long to search each position for.
+    pub time_limit_ms: u64,
+
+    /// The deepest to search, even with time left.
+    pub max_depth: u32,
+
+    pub game_type: GameType,
+}
+
+impl Default for TestSuiteRunner {
+    fn default() -> Self {
+        Self {
+            time_limit_ms: 1000,
+            max_depth: 30,
+            game_type: GameType::Classic,
+        }
+    }
+}
+
+impl TestSuiteRunner {
+    /// Searches every position in turn, calling `on_result` with each position's index and
+    /// result as it finishes.
+    pub fn run(
+        &self,
+        positions: &[ExtendedPosition],
+        mut on_result: impl FnMut(usize, &Result<TestResult, String>),
+    ) -> SuiteResults {
+        let mut results = SuiteResults::default();
+
+        for (i, position) in positions.iter().enumerate() {
+            let result = self.run_position(position).map_err(|e| e.to_string());
+            on_result(i, &result);
+            results.results.push(result);
+        }
+
+        results
+    }
+
+    /// Searches one position and checks its move against the position's `bm`, `am` and `dm`
+    /// operations. Fails if it has none of them, or one of its moves isn't legal.
+    pub fn run_position(&self, epd: &ExtendedPosition) -> Result<TestResult, anyhow::Error> {
+        let position = &epd.position;
+
+        let parse_moves = |moves: Vec<&str>| {
+            moves
+                .into_iter()
+                .map(|mv| pgn::parse_san(position, mv, self.game_type))
+                .collect::<Result<Vec<PieceMove>, _>>()
+        };
+
+        let best_moves = parse_moves(epd.best_moves())?;
+        let avoid_moves = parse_moves(epd.avoid_moves())?;
+        let direct_mate = epd.direct_mate();
+
+        if best_moves.is_empty() && avoid_moves.is_empty() && direct_mate.is_none() {
+            return Err(anyhow::anyhow!("Position has no bm, am or dm to test"));
+        }
+
+        let start_time = Instant::now();
+        let results = self.search(epd)?;
+
+        let passed = results.best_move.is_some_and(|found| {
+            (best_moves.is_empty() || best_moves.contains(&found)) && !avoid_moves.contains(&found)
+        }) && direct_mate.is_none_or(|moves| {
+            matches!(
+                Verdict::from_score(results.score),
+                Verdict::Win { mate_in: Some(mate_in) } if mate_in <= moves
+            )
+        });
+
+        let found = match results.best_move {
+            Some(found) => Some(found.to_san(position)?),
+            None => None,
+        };
+
+        Ok(TestResult {
+            id: epd.id().map(str::to_string),
+            passed,
+            found,
+            score: results.score,
+            depth: results.depth,
+            nodes_searched: results.nodes_searched,
+            time_taken_ms: start_time.elapsed().as_millis(),
+        })
+    }
+
+    /// Deepens the search one ply at a time until the time limit, keeping the deepest depth
+    /// that finished.
+    fn search(&self, epd: &ExtendedPosition) -> Result<SearchResults, anyhow::Error> {
+        let params = SearchParams::builder()
+            .game_type(self.game_type)
+            .time_ms(self.time_limit_ms)
+            .build()?;
+
+        let mut transposition_table = TranspositionTable::new();
+        let mut state = SearchState::new(&mut transposition_table);
+        state.data.start_time = Instant::now();
+        state.data.time_limit = self.time_limit_ms;
+
+        let mut best: Option<SearchResults> = None;
+
+        for depth in 1..=self.max_depth {
+            let mut iteration_params = params.clone();
+            iteration_params.depth = depth;
+            iteration_params.previous_score = best.as_ref().map(|results| results.score);
+
+            match backend::search(&epd.position, &mut state, iteration_params, 0) {
+                Ok(results) => {
+                    state.data.previous_pv = results.principal_variation.clone();
+
+                    // Nothing deeper to search without legal moves
+                    let finished = results.best_move.is_none();
+                    best = Some(results);
+
+                    if finished {
+                        break;
+                    }
+                }
+                Err(_) if best.is_some() => break,
+                Err(e) => return Err(anyhow::anyhow!("Search failed: {}", e)),
+            }
+        }
+
+        let mut best = best.ok_or_else(|| anyhow::anyhow!("Search depth is at least 1"))?;
+
+        // Including the nodes of a depth that ran out of time
+        best.nodes_searched = state.data.nodes_searched;
+
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+# Mate in one, and a rook that has to be taken
+6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Ra8#; id "back rank";
+4k3/8/8/8/8/8/3r4/3QK3 w - - am Kf1; id "take the rook"; dm 1;
+
+4k3/8/8/8/8/8/8/4K3 w - - id "nothing to test";
+"#;
+
+    #[test]
+    fn test_parse_suite() {
+        let positions = parse_suite(SUITE).unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0].id(), Some("back rank"));
+
+        assert!(parse_suite("not an epd").is_err());
+    }
+
+    #[test]
+    fn test_run_suite() {
+        let positions = parse_suite(SUITE).unwrap();
+        let runner = TestSuiteRunner {
+            time_limit_ms: 5000,
+            max_depth: 3,
+            ..Default::default()
+        };
+
+        let mut reported = vec![];
+        let results = runner.run(&positions, |i, _| reported.push(i));
+
+        assert_eq!(reported, vec![0, 1, 2]);
+
+        let back_rank = results.results[0].as_ref().unwrap();
+        assert!(back_rank.passed);
+        assert_eq!(back_rank.found.as_deref(), Some("Ra8#"));
+        assert_eq!(back_rank.id.as_deref(), Some("back rank"));
+
+        // Taking the rook avoids Kf1, but isn't mate
+        assert!(!results.results[1].as_ref().unwrap().passed);
+        assert!(results.results[2].is_err());
+
+        assert_eq!(results.passed(), 1);
+        assert!((results.pass_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
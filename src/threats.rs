@@ -0,0 +1,242 @@
+//! What the opponent is threatening in a position, for pointing out dangers to learners: the
+//! squares they attack, the pieces they can win, and whether they threaten mate.
+
+use serde::Serialize;
+
+use crate::{
+    evaluation::see::static_exchange_evaluation,
+    piece::{bishop, king, knight, pawn, rook},
+    piece_move::{GameType, MoveList, MoveType},
+    search::{
+        alpha_beta::{search, SearchParams},
+        score::plies_to_mate,
+        search_results::SearchState,
+        transposition_table::TranspositionTable,
+    },
+    Bitboard, PieceMove, PieceType, Pos, Position,
+};
+
+/// How many plies the opponent is searched to when looking for a mate threat.
+pub const MATE_THREAT_DEPTH: u32 = 2;
+
+/// A piece of the side to move that the opponent can win material by capturing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HangingPiece {
+    pub pos: Pos,
+    pub piece_type: PieceType,
+    pub holding: Option<PieceType>,
+
+    /// The opponent's best capture of the piece.
+    pub capture: PieceMove,
+
+    /// The material the opponent can expect to win with `capture`.
+    pub material: i32,
+}
+
+/// A mate the opponent could deliver if it were their move.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MateThreat {
+    /// The opponent's first move towards mate.
+    pub mv: PieceMove,
+
+    /// How many opponent moves the mate takes.
+    pub moves: u32,
+}
+
+/// The opponent's threats against the side to move. Squares and moves are all on the board of
+/// the position they were found in, i.e. from the perspective of the side to move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threats {
+    /// Every square the opponent's pieces attack.
+    pub attacked: Bitboard,
+
+    /// Pieces the opponent can win material by capturing, most material first.
+    pub hanging: Vec<HangingPiece>,
+
+    /// A mate the opponent threatens, if any. Not looked for when the side to move is already in
+    /// check, since then the threat has already been carried out.
+    pub mate_threat: Option<MateThreat>,
+}
+
+impl Threats {
+    /// Finds the threats the opponent (black) has against the side to move (white) in `position`.
+    pub fn find(position: &Position, game_type: GameType) -> Result<Self, anyhow::Error> {
+        // The opponent's moves are found as if it were their turn. There's no en passant for them,
+        // since the side to move didn't just move a pawn.
+        let mut opponent = position.inverted();
+        opponent.en_passant = None;
+
+        let mate_threat = if position.is_king_in_check()? {
+            None
+        } else {
+            find_mate_threat(&opponent, game_type)?
+        };
+
+        Ok(Threats {
+            attacked: attacked_squares(position),
+            hanging: find_hanging_pieces(&opponent, game_type)?,
+            mate_threat,
+        })
+    }
+}
+
+/// The squares black attacks in `position`, whether or not moving there would be legal.
+pub fn attacked_squares(position: &Position) -> Bitboard {
+    let maps = position.get_piece_maps();
+    let occupied = position.all_map;
+    let mut attacked = Bitboard::new();
+
+    for pos in maps.black_pawns {
+        attacked = attacked | *pawn::attack_map_black(pos);
+    }
+
+    for pos in maps.black_knights {
+        attacked = attacked | *knight::attack_map(pos);
+    }
+
+    for pos in maps.black_bishops | maps.black_queens {
+        attacked = attacked | bishop::magic::get_bishop_moves_magic(pos, occupied);
+    }
+
+    for pos in maps.black_rooks | maps.black_queens {
+        attacked = attacked | rook::magic::get_rook_moves_magic(pos, occupied);
+    }
+
+    for pos in maps.black_king {
+        attacked = attacked | *king::attack_map(pos);
+    }
+
+    attacked
+}
+
+/// The pieces the side to move in `opponent` (the inverted position) can win material by
+/// capturing, with squares and moves inverted back to the original position.
+pub(crate) fn find_hanging_pieces(
+    opponent: &Position,
+    game_type: GameType,
+) -> Result<Vec<HangingPiece>, anyhow::Error> {
+    let mut moves = MoveList::new();
+    opponent.get_all_legal_moves(game_type, &mut moves)?;
+
+    let mut hanging: Vec<HangingPiece> = Vec::new();
+
+    for mv in moves.iter().filter(|mv| mv.is_capture()) {
+        let MoveType::Normal {
+            captured: Some(captured),
+            captured_pos,
+            captured_holding,
+            ..
+        } = mv.move_type
+        else {
+            continue;
+        };
+
+        if captured == PieceType::King {
+            continue;
+        }
+
+        let material = static_exchange_evaluation(opponent, mv);
+        if material <= 0 {
+            continue;
+        }
+
+        let pos = captured_pos.unwrap_or(mv.to).invert();
+        let piece = HangingPiece {
+            pos,
+            piece_type: captured,
+            holding: captured_holding,
+            capture: mv.inverted(),
+            material,
+        };
+
+        match hanging.iter_mut().find(|existing| existing.pos == pos) {
+            Some(existing) if existing.material < material => *existing = piece,
+            Some(_) => {}
+            None => hanging.push(piece),
+        }
+    }
+
+    hanging.sort_by_key(|piece| -piece.material);
+
+    Ok(hanging)
+}
+
+/// Searches `opponent` (the inverted position) a couple of plies deep for a forced mate, with the
+/// mating move inverted back to the original position.
+fn find_mate_threat(
+    opponent: &Position,
+    game_type: GameType,
+) -> Result<Option<MateThreat>, anyhow::Error> {
+    let params = SearchParams::builder()
+        .depth(MATE_THREAT_DEPTH)
+        .game_type(game_type)
+        .build()?;
+
+    let mut transposition_table = TranspositionTable::with_size_mb(1);
+    let mut state = SearchState::new(&mut transposition_table);
+
+    let results = search(opponent, &mut state, params, 0)
+        .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+
+    Ok(match (plies_to_mate(results.score), results.best_move) {
+        (Some(plies), Some(mv)) if plies >= 0 => Some(MateThreat {
+            mv: mv.inverted(),
+            moves: plies as u32 / 2 + 1,
+        }),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attacked_squares() {
+        let position = Position::parse_from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1").unwrap();
+        let attacked = attacked_squares(&position);
+
+        assert!(attacked.get(Pos::from_algebraic("c4").unwrap()));
+        assert!(attacked.get(Pos::from_algebraic("e4").unwrap()));
+        assert!(!attacked.get(Pos::from_algebraic("d4").unwrap()));
+        assert!(attacked.get(Pos::from_algebraic("d7").unwrap()));
+        assert!(!attacked.get(Pos::from_algebraic("e1").unwrap()));
+    }
+
+    #[test]
+    fn test_hanging_pieces() {
+        // The bishop on h5 is attacked by the queen and defended by nothing, and the knight on e4
+        // is defended but attacked by a pawn. The rook on a1 isn't attacked at all.
+        let position = Position::parse_from_fen("4q1k1/8/8/3p3B/4N3/5P2/8/R3K3 w - - 0 1").unwrap();
+        let threats = Threats::find(&position, GameType::Classic).unwrap();
+
+        let hanging: Vec<_> = threats.hanging.iter().map(|piece| piece.pos).collect();
+        assert_eq!(
+            hanging,
+            vec![
+                Pos::from_algebraic("h5").unwrap(),
+                Pos::from_algebraic("e4").unwrap()
+            ]
+        );
+        assert_eq!(
+            threats.hanging[1].capture.from,
+            Pos::from_algebraic("d5").unwrap()
+        );
+        assert_eq!(threats.mate_threat, None);
+    }
+
+    #[test]
+    fn test_mate_threat() {
+        // Black threatens Qxf2#
+        let position = Position::parse_from_fen(
+            "r1b1k1nr/pppp1ppp/2n5/2b1p3/4P2q/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 0 1",
+        )
+        .unwrap();
+        let threats = Threats::find(&position, GameType::Classic).unwrap();
+
+        let mate_threat = threats.mate_threat.unwrap();
+        assert_eq!(mate_threat.mv.from, Pos::from_algebraic("h4").unwrap());
+        assert_eq!(mate_threat.mv.to, Pos::from_algebraic("f2").unwrap());
+        assert_eq!(mate_threat.moves, 1);
+    }
+}
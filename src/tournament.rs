@@ -0,0 +1,569 @@
+//! Engine-vs-engine matches for testing changes to the search and the evaluation. Two engine
+//! configurations play each opening of a suite once with each color, games that are clearly
+//! decided or going nowhere are adjudicated, and a sequential probability ratio test (SPRT)
+//! stops the match once it's played enough games to tell whether the change is an improvement.
+
+use std::fmt::Display;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    features::{EvaluationWeights, Features},
+    game::Game,
+    pgn::PgnResult,
+    piece_move::GameType,
+    search::{backend::SearchAlgorithm, game_state::GameState},
+    Color,
+};
+
+/// Short opening lines in SAN, so that games between deterministic engines aren't all the same.
+pub const DEFAULT_OPENINGS: [&str; 12] = [
+    "e4 e5 Nf3 Nc6",
+    "e4 e5 Nf3 Nc6 Bb5 a6",
+    "e4 c5 Nf3 d6",
+    "e4 e6 d4 d5",
+    "e4 c6 d4 d5",
+    "e4 d5 exd5 Qxd5",
+    "d4 d5 c4 e6",
+    "d4 d5 Bf4 Nf6",
+    "d4 Nf6 c4 g6",
+    "d4 Nf6 c4 e6 Nc3 Bb4",
+    "c4 e5 Nc3 Nf6",
+    "Nf3 d5 g3 Nf6",
+];
+
+/// One side of a match: the search algorithm, features and evaluation weights it plays with.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub name: String,
+    pub features: Features,
+
+    /// The evaluation weights, or the defaults for the match's type of game.
+    pub weights: Option<EvaluationWeights>,
+
+    /// Weak algorithms like `random` or `greedy` make baselines to measure against.
+    pub algorithm: SearchAlgorithm,
+}
+
+impl EngineConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn game_state(&self, config: &MatchConfig) -> GameState {
+        let mut state = GameState {
+            features: self.features,
+            weights: self
+                .weights
+                .unwrap_or_else(|| EvaluationWeights::for_game_type(config.game_type)),
+            search_depth: config.depth,
+            time_limit_ms: config.time_limit_ms,
+            search_algorithm: self.algorithm,
+            ..GameState::new()
+        };
+        state.game_type = config.game_type;
+        state
+    }
+}
+
+/// When to end a game before it's over on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    /// Games this many plies long, opening included, are drawn.
+    pub max_plies: usize,
+
+    /// Once both engines' scores have been at least this far in one side's favor, in
+    /// centipawns, for `resign_plies` plies in a row, the other side resigns.
+    pub resign_score: i32,
+    pub resign_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            max_plies: 300,
+            resign_score: 1000,
+            resign_plies: 8,
+        }
+    }
+}
+
+/// How a match is played. Both engines search with the same limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    pub game_type: GameType,
+    pub depth: u32,
+    pub time_limit_ms: u64,
+
+    /// The most games to play, rounded up to a whole number of pairs.
+    pub games: usize,
+
+    /// How many pairs of games to play at the same time.
+    pub concurrency: usize,
+
+    /// Opening lines in SAN, each played once with each engine as white. They're used in
+    /// turn, starting over once they run out.
+    pub openings: Vec<String>,
+
+    pub adjudication: Adjudication,
+
+    /// The test to stop the match early with, if any.
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            game_type: GameType::Rescue,
+            depth: 6,
+            time_limit_ms: 100,
+            games: 100,
+            concurrency: 1,
+            openings: DEFAULT_OPENINGS
+                .iter()
+                .map(|line| line.to_string())
+                .collect(),
+            adjudication: Adjudication::default(),
+            sprt: None,
+        }
+    }
+}
+
+/// A game's result for the first engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Win => write!(f, "win"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss => write!(f, "loss"),
+        }
+    }
+}
+
+/// A finished game of a match.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub opening: String,
+
+    /// The first engine's color.
+    pub first_color: Color,
+
+    pub game: Game,
+    pub outcome: Outcome,
+
+    /// Whether the game was ended by adjudication rather than on the board.
+    pub adjudicated: bool,
+}
+
+/// The first engine's wins, draws and losses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Loss => self.losses += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The first engine's share of the points, from 0 to 1.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The variance of the score of a single game.
+    fn variance(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+
+        let games = self.games() as f64;
+        let score = self.score();
+        let wins = self.wins as f64 / games;
+        let draws = self.draws as f64 / games;
+
+        wins + draws / 4.0 - score * score
+    }
+
+    /// The first engine's estimated Elo advantage over the second. Infinite if either won
+    /// every game.
+    pub fn elo(&self) -> f64 {
+        score_to_elo(self.score())
+    }
+
+    /// The half-width of the 95% confidence interval around `elo`.
+    pub fn elo_margin(&self) -> f64 {
+        if self.games() == 0 {
+            return f64::INFINITY;
+        }
+
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let low = score_to_elo(self.score() - 1.96 * deviation);
+        let high = score_to_elo(self.score() + 1.96 * deviation);
+
+        (high - low) / 2.0
+    }
+}
+
+impl Display for MatchScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} ={} -{}, Elo {:.1} +/- {:.1}",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.elo(),
+            self.elo_margin()
+        )
+    }
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    if score <= 0.0 {
+        return f64::NEG_INFINITY;
+    } else if score >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test of whether the first engine is `elo0` Elo stronger
+/// than the second (the null hypothesis) or `elo1` Elo stronger, with `alpha` the chance of
+/// wrongly accepting `elo1` and `beta` the chance of wrongly accepting `elo0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+/// Which of its hypotheses an SPRT accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    /// The first engine is no more than `elo0` stronger.
+    AcceptH0,
+
+    /// The first engine is `elo1` stronger.
+    AcceptH1,
+}
+
+impl Display for SprtDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SprtDecision::AcceptH0 => write!(f, "H0 accepted"),
+            SprtDecision::AcceptH1 => write!(f, "H1 accepted"),
+        }
+    }
+}
+
+impl Sprt {
+    /// The log-likelihood ratio of `elo1` against `elo0` given the score so far, using the
+    /// normal approximation to the distribution of game results.
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let variance = score.variance();
+        if variance <= 0.0 {
+            return 0.0;
+        }
+
+        let score0 = elo_to_score(self.elo0);
+        let score1 = elo_to_score(self.elo1);
+
+        score.games() as f64 * (score1 - score0) * (2.0 * score.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+
+    /// The log-likelihood ratios below which H0 is accepted and above which H1 is.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The test's decision given the score so far, if it's made one.
+    pub fn decision(&self, score: &MatchScore) -> Option<SprtDecision> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+
+        if llr <= lower {
+            Some(SprtDecision::AcceptH0)
+        } else if llr >= upper {
+            Some(SprtDecision::AcceptH1)
+        } else {
+            None
+        }
+    }
+}
+
+/// The totals of a finished match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchResults {
+    pub score: MatchScore,
+
+    /// The SPRT's decision, if it made one before the games ran out.
+    pub sprt: Option<SprtDecision>,
+}
+
+/// Plays a match of `first` against `second`, calling `on_game` with each finished game and
+/// the score so far.
+pub fn run_match(
+    config: &MatchConfig,
+    first: &EngineConfig,
+    second: &EngineConfig,
+    mut on_game: impl FnMut(&GameRecord, &MatchScore),
+) -> Result<MatchResults, anyhow::Error> {
+    if config.openings.is_empty() {
+        return Err(anyhow::anyhow!("A match needs at least one opening"));
+    }
+
+    let pairs = config.games.div_ceil(2);
+    let mut score = MatchScore::default();
+    let mut sprt = None;
+    let mut pair = 0;
+
+    while pair < pairs && sprt.is_none() {
+        let batch = (pair..pairs.min(pair + config.concurrency.max(1))).collect::<Vec<_>>();
+        pair += batch.len();
+
+        let records = batch
+            .into_par_iter()
+            .map(|pair| {
+                let opening = &config.openings[pair % config.openings.len()];
+
+                Ok([
+                    play_game(config, first, second, Color::White, opening)?,
+                    play_game(config, first, second, Color::Black, opening)?,
+                ])
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+        for record in records.iter().flatten() {
+            score.add(record.outcome);
+            on_game(record, &score);
+        }
+
+        sprt = config.sprt.and_then(|sprt| sprt.decision(&score));
+    }
+
+    Ok(MatchResults { score, sprt })
+}
+
+/// Plays one game from an opening, with `first` playing `first_color`. Each engine starts the
+/// game with an empty transposition table.
+pub fn play_game(
+    config: &MatchConfig,
+    first: &EngineConfig,
+    second: &EngineConfig,
+    first_color: Color,
+    opening: &str,
+) -> Result<GameRecord, anyhow::Error> {
+    let mut game = Game::new(config.game_type);
+    for mv in opening.split_whitespace() {
+        game.make_san_move(mv)
+            .map_err(|e| anyhow::anyhow!("Invalid opening {}: {}", opening, e))?;
+    }
+
+    let mut first_engine = first.game_state(config);
+    let mut second_engine = second.game_state(config);
+
+    let adjudication = config.adjudication;
+    let mut adjudicated = false;
+
+    // The side both engines have agreed is winning, and for how many plies in a row
+    let mut winning: Option<Color> = None;
+    let mut winning_plies = 0;
+
+    while !game.is_over() {
+        if game.moves().len() >= adjudication.max_plies {
+            game.agree_draw()?;
+            adjudicated = true;
+            break;
+        }
+
+        let color = game.to_move();
+        let engine = if color == first_color {
+            &mut first_engine
+        } else {
+            &mut second_engine
+        };
+
+        engine.current_position = game.position().clone();
+        engine.current_turn = color;
+        engine.reset_history();
+        engine.history = game.history().to_vec();
+
+        let (mv, _) = engine.search_and_apply()?;
+        let score = engine.previous_score(color).unwrap_or(0);
+        game.make_move(mv)?;
+
+        let leader = if score >= adjudication.resign_score {
+            Some(color)
+        } else if score <= -adjudication.resign_score {
+            Some(color.invert())
+        } else {
+            None
+        };
+
+        if leader.is_some() && leader == winning {
+            winning_plies += 1;
+        } else {
+            winning = leader;
+            winning_plies = 1;
+        }
+
+        if let Some(winner) = winning {
+            if winning_plies >= adjudication.resign_plies && !game.is_over() {
+                game.resign(winner.invert())?;
+                adjudicated = true;
+            }
+        }
+    }
+
+    let outcome = match (game.result(), first_color) {
+        (PgnResult::WhiteWins, Color::White) | (PgnResult::BlackWins, Color::Black) => Outcome::Win,
+        (PgnResult::WhiteWins, Color::Black) | (PgnResult::BlackWins, Color::White) => {
+            Outcome::Loss
+        }
+        _ => Outcome::Draw,
+    };
+
+    Ok(GameRecord {
+        opening: opening.to_string(),
+        first_color,
+        game,
+        outcome,
+        adjudicated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_openings_are_legal() {
+        for game_type in [GameType::Classic, GameType::Rescue] {
+            for opening in DEFAULT_OPENINGS {
+                let mut game = Game::new(game_type);
+                for mv in opening.split_whitespace() {
+                    game.make_san_move(mv).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_elo() {
+        let even = MatchScore {
+            wins: 10,
+            draws: 10,
+            losses: 10,
+        };
+        assert_eq!(even.elo(), 0.0);
+
+        // Three quarters of the points is about 191 Elo
+        let ahead = MatchScore {
+            wins: 30,
+            draws: 0,
+            losses: 10,
+        };
+        assert!((ahead.elo() - 190.8).abs() < 1.0);
+        assert!(ahead.elo_margin() > 0.0);
+    }
+
+    #[test]
+    fn test_sprt_decisions() {
+        let sprt = Sprt::default();
+
+        assert_eq!(sprt.decision(&MatchScore::default()), None);
+
+        let winning = MatchScore {
+            wins: 600,
+            draws: 400,
+            losses: 400,
+        };
+        assert_eq!(sprt.decision(&winning), Some(SprtDecision::AcceptH1));
+
+        let losing = MatchScore {
+            wins: 300,
+            draws: 400,
+            losses: 500,
+        };
+        assert_eq!(sprt.decision(&losing), Some(SprtDecision::AcceptH0));
+    }
+
+    #[test]
+    fn test_match_alternates_colors() {
+        let config = MatchConfig {
+            depth: 1,
+            games: 2,
+            adjudication: Adjudication {
+                max_plies: 12,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut records = vec![];
+        let results = run_match(
+            &config,
+            &EngineConfig::new("first"),
+            &EngineConfig::new("second"),
+            |record, _| records.push((record.first_color, record.opening.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(results.score.games(), 2);
+        assert_eq!(
+            records,
+            vec![
+                (Color::White, DEFAULT_OPENINGS[0].to_string()),
+                (Color::Black, DEFAULT_OPENINGS[0].to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Training data from self-play games: positions with the engine's score for them and how the
+//! game they came from ended, for training an NNUE or tuning evaluation weights.
+//!
+//! A training data file starts with an 8 byte header: the bytes `RCTD`, the format version,
+//! the game type (0 for classic, 1 for Rescue) and two zero bytes. Then come its records, 48
+//! little endian bytes each:
+//!
+//! | Bytes | Contents                                                                          |
+//! |-------|-----------------------------------------------------------------------------------|
+//! | 0-7   | The occupied squares, bit 0 being a8 and bit 63 h1                                |
+//! | 8-39  | A byte for each occupied square in order, then zeros (see `encode_piece`)         |
+//! | 40    | Bit 0 set if black is to move, bits 1-4 white's and black's castling rights, KQkq |
+//! | 41    | The en passant square, or 255 if there isn't one                                  |
+//! | 42    | The halfmove clock                                                                |
+//! | 43    | The result for the side to move: 1 for a win, 0 for a draw and -1 for a loss      |
+//! | 44-45 | The fullmove number                                                               |
+//! | 46-47 | The search score for the side to move, in centipawns, mates clamped to ±32000     |
+//!
+//! Castling rights are standard ones, so Chess960 positions don't round trip.
+
+use std::io::Write;
+
+use rand::Rng;
+
+use crate::{
+    game::Game,
+    pgn::PgnResult,
+    piece_move::GameType,
+    search::{
+        alpha_beta::SearchParams, backend, search_results::SearchState,
+        transposition_table::TranspositionTable,
+    },
+    Bitboard, Color, PieceType, Pos, Position,
+};
+
+const MAGIC: &[u8; 4] = b"RCTD";
+const VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = 8;
+pub const RECORD_SIZE: usize = 48;
+
+/// The most pieces that can be on the board, one byte each in a record.
+const MAX_PIECES: usize = 32;
+
+/// Scores are clamped to this, so mates fit in an `i16`.
+pub const MAX_SCORE: i32 = 32_000;
+
+/// A position from a self-play game, with its score and the result of the game.
+#[derive(Debug, Clone)]
+pub struct TrainingRecord {
+    /// The position, from the perspective of its side to move.
+    pub position: Position,
+
+    /// The search score for the side to move, in centipawns.
+    pub score: i16,
+
+    /// The result of the game for the side to move: 1 for a win, 0 for a draw and -1 for a
+    /// loss.
+    pub result: i8,
+}
+
+impl TrainingRecord {
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+
+        // Squares are written from white's side of the board, whoever is to move
+        let black_to_move = self.position.true_active_color == Color::Black;
+        let position = if black_to_move {
+            self.position.inverted()
+        } else {
+            self.position.clone()
+        };
+
+        bytes[0..8].copy_from_slice(&position.all_map.0.to_le_bytes());
+        for (i, pos) in position.all_map.into_iter().enumerate() {
+            let piece = position.get_piece_at(pos).unwrap();
+            bytes[8 + i] = encode_piece(piece.piece_type, piece.color, piece.holding);
+        }
+
+        let rights = position.castling_rights;
+        bytes[40] = black_to_move as u8
+            | (rights.white_king_side as u8) << 1
+            | (rights.white_queen_side as u8) << 2
+            | (rights.black_king_side as u8) << 3
+            | (rights.black_queen_side as u8) << 4;
+        bytes[41] = position.en_passant.map_or(255, |pos| pos.0);
+        bytes[42] = position.halfmove_clock;
+        bytes[43] = self.result as u8;
+        bytes[44..46].copy_from_slice(&position.fullmove_number.to_le_bytes());
+        bytes[46..48].copy_from_slice(&self.score.to_le_bytes());
+
+        bytes
+    }
+
+    /// Reads a record written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if bytes.len() != RECORD_SIZE {
+            return Err(anyhow::anyhow!(
+                "A training record is {} bytes, not {}",
+                RECORD_SIZE,
+                bytes.len()
+            ));
+        }
+
+        let occupied = Bitboard(u64::from_le_bytes(bytes[0..8].try_into().unwrap()));
+        if occupied.count() as usize > MAX_PIECES {
+            return Err(anyhow::anyhow!("Too many pieces in training record"));
+        }
+
+        // Written as FEN, the one way in to a position with held pieces
+        let mut pieces = bytes[8..40].iter();
+        let mut board = String::new();
+        for rank in 0..8 {
+            let mut empty = 0;
+
+            for file in 0..8 {
+                if !occupied.get(Pos::xy(file, rank)) {
+                    empty += 1;
+                    continue;
+                }
+
+                if empty > 0 {
+                    board.push_str(&empty.to_string());
+                    empty = 0;
+                }
+
+                let (piece_type, color, holding) = decode_piece(*pieces.next().unwrap())?;
+                board.push_str(piece_type.to_algebraic(color));
+                if let Some(holding) = holding {
+                    board.push('x');
+                    board.push_str(holding.to_algebraic(color));
+                }
+            }
+
+            if empty > 0 {
+                board.push_str(&empty.to_string());
+            }
+
+            if rank < 7 {
+                board.push('/');
+            }
+        }
+
+        let flags = bytes[40];
+        let castling: String = [(1, 'K'), (2, 'Q'), (3, 'k'), (4, 'q')]
+            .into_iter()
+            .filter(|&(bit, _)| flags & 1 << bit != 0)
+            .map(|(_, right)| right)
+            .collect();
+        let en_passant = match bytes[41] {
+            255 => "-".to_string(),
+            square if square < 64 => Pos(square).to_algebraic(),
+            square => {
+                return Err(anyhow::anyhow!(
+                    "Invalid en passant square in training record: {}",
+                    square
+                ))
+            }
+        };
+
+        let fen = format!(
+            "{} {} {} {} {} {}",
+            board,
+            if flags & 1 != 0 { 'b' } else { 'w' },
+            if castling.is_empty() { "-" } else { &castling },
+            en_passant,
+            bytes[42],
+            u16::from_le_bytes(bytes[44..46].try_into().unwrap())
+        );
+
+        Ok(Self {
+            position: Position::parse_from_fen(&fen)?,
+            score: i16::from_le_bytes(bytes[46..48].try_into().unwrap()),
+            result: bytes[43] as i8,
+        })
+    }
+}
+
+/// A piece as a byte: its type and color in the low 4 bits, 0 to 5 for white's pawn up to king
+/// and 6 to 11 for black's, and the type of piece it holds in the high 4 bits, 0 for none and
+/// otherwise 1 for a pawn up to 6 for a king.
+fn encode_piece(piece_type: PieceType, color: Color, holding: Option<PieceType>) -> u8 {
+    let color_offset = match color {
+        Color::White => 0,
+        Color::Black => 6,
+    };
+
+    (piece_type as u8 + color_offset) | (holding.map_or(0, |held| held as u8 + 1) << 4)
+}
+
+fn decode_piece(byte: u8) -> Result<(PieceType, Color, Option<PieceType>), anyhow::Error> {
+    let piece = (byte & 0xf) as usize;
+    let held = (byte >> 4) as usize;
+
+    if piece >= 12 || held > 6 {
+        return Err(anyhow::anyhow!(
+            "Invalid piece in training record: {}",
+            byte
+        ));
+    }
+
+    let color = if piece < 6 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let holding = held.checked_sub(1).map(|held| PieceType::ALL[held]);
+
+    Ok((PieceType::ALL[piece % 6], color, holding))
+}
+
+/// Writes the header of a training data file.
+pub fn write_header(writer: &mut impl Write, game_type: GameType) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[
+        VERSION,
+        match game_type {
+            GameType::Classic => 0,
+            GameType::Rescue => 1,
+        },
+        0,
+        0,
+    ])
+}
+
+/// Reads a training data file written by `write_header` and `TrainingRecord::to_bytes`.
+pub fn read_training_data(bytes: &[u8]) -> Result<(GameType, Vec<TrainingRecord>), anyhow::Error> {
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(anyhow::anyhow!("Not a training data file"));
+    }
+
+    if bytes[4] != VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported training data version {}",
+            bytes[4]
+        ));
+    }
+
+    let game_type = match bytes[5] {
+        0 => GameType::Classic,
+        1 => GameType::Rescue,
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid game type in training data: {}",
+                other
+            ))
+        }
+    };
+
+    let records = &bytes[HEADER_SIZE..];
+    if !records.len().is_multiple_of(RECORD_SIZE) {
+        return Err(anyhow::anyhow!("Training data is truncated"));
+    }
+
+    let records = records
+        .chunks_exact(RECORD_SIZE)
+        .map(TrainingRecord::from_bytes)
+        .collect::<Result<_, _>>()?;
+
+    Ok((game_type, records))
+}
+
+/// Generates training data by having the engine play itself at a low depth. The first few
+/// moves of each game are random so the games cover many different positions, and aren't
+/// recorded.
+#[derive(Debug, Clone)]
+pub struct TrainingDataGenerator {
+    pub game_type: GameType,
+
+    /// How deep to search each move.
+    pub depth: u32,
+
+    /// How many plies at the start of each game are played at random.
+    pub random_plies: usize,
+
+    /// Games still going after this many plies are thrown away, since they have no result.
+    pub max_plies: usize,
+
+    /// Positions the side to move is in check in are left out, since their scores depend on
+    /// the search more than on the position.
+    pub skip_checks: bool,
+}
+
+impl Default for TrainingDataGenerator {
+    fn default() -> Self {
+        Self {
+            game_type: GameType::Rescue,
+            depth: 4,
+            random_plies: 8,
+            max_plies: 400,
+            skip_checks: true,
+        }
+    }
+}
+
+impl TrainingDataGenerator {
+    /// Plays one game of the engine against itself and returns the positions it searched, or
+    /// nothing if the game didn't finish.
+    pub fn play_game<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<Vec<TrainingRecord>, anyhow::Error> {
+        let mut game = Game::new(self.game_type);
+        let mut transposition_table = TranspositionTable::new();
+        let mut searched = Vec::new();
+
+        let params = SearchParams::builder()
+            .depth(self.depth)
+            .game_type(self.game_type)
+            .build()?;
+
+        while !game.is_over() && game.moves().len() < self.max_plies {
+            if game.moves().len() < self.random_plies {
+                let moves = game.legal_moves()?;
+                game.make_move(moves[rng.gen_range(0..moves.len())])?;
+                continue;
+            }
+
+            transposition_table.new_search();
+
+            let mut state = SearchState::new(&mut transposition_table);
+            state.data.history = game.history().to_vec();
+
+            let results = backend::search(game.position(), &mut state, params.clone(), 0)
+                .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
+            let Some(best_move) = results.best_move else {
+                break;
+            };
+
+            if !(self.skip_checks && game.position().is_king_in_check()?) {
+                searched.push((
+                    game.position().clone(),
+                    game.to_move(),
+                    results.score.clamp(-MAX_SCORE, MAX_SCORE) as i16,
+                ));
+            }
+
+            game.make_move(best_move)?;
+        }
+
+        let winner = match game.result() {
+            PgnResult::WhiteWins => Some(Color::White),
+            PgnResult::BlackWins => Some(Color::Black),
+            PgnResult::Draw => None,
+            PgnResult::Unknown => return Ok(Vec::new()),
+        };
+
+        Ok(searched
+            .into_iter()
+            .map(|(position, to_move, score)| TrainingRecord {
+                position,
+                score,
+                result: match winner {
+                    Some(winner) if winner == to_move => 1,
+                    Some(_) => -1,
+                    None => 0,
+                },
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/8/8/8/8/8/8/4K2R w Kq - 3 17",
+            "4k3/3nxp4/8/8/3NxB4/2P5/8/4K3 b - - 0 42",
+            "2kr4/1PxQ6/8/8/8/8/8/4KxR3 w - - 12 60",
+        ] {
+            let record = TrainingRecord {
+                position: Position::parse_from_fen(fen).unwrap(),
+                score: -123,
+                result: -1,
+            };
+
+            let read = TrainingRecord::from_bytes(&record.to_bytes()).unwrap();
+            assert_eq!(read.position.to_fen(), fen);
+            assert_eq!(read.score, -123);
+            assert_eq!(read.result, -1);
+        }
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        let mut bytes = TrainingRecord {
+            position: Position::start_position(),
+            score: 0,
+            result: 0,
+        }
+        .to_bytes();
+
+        assert!(TrainingRecord::from_bytes(&bytes[..40]).is_err());
+
+        bytes[8] = 12;
+        assert!(TrainingRecord::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_generate_and_read() {
+        let generator = TrainingDataGenerator {
+            game_type: GameType::Rescue,
+            depth: 1,
+            random_plies: 4,
+            max_plies: 300,
+            skip_checks: true,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let mut records = Vec::new();
+        while records.is_empty() {
+            records = generator.play_game(&mut rng).unwrap();
+        }
+
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, GameType::Rescue).unwrap();
+        for record in &records {
+            bytes.extend_from_slice(&record.to_bytes());
+        }
+        assert_eq!(bytes.len(), HEADER_SIZE + records.len() * RECORD_SIZE);
+
+        let (game_type, read) = read_training_data(&bytes).unwrap();
+        assert_eq!(game_type, GameType::Rescue);
+        assert_eq!(read.len(), records.len());
+
+        for (record, read) in records.iter().zip(&read) {
+            assert_eq!(read.position.to_fen(), record.position.to_fen());
+            assert_eq!((read.score, read.result), (record.score, record.result));
+            assert!(!record.position.is_king_in_check().unwrap());
+        }
+
+        // Both sides' positions get the same result, one as a win and one as a loss, unless
+        // the game was drawn
+        let results: Vec<i8> = records.iter().map(|record| record.result).collect();
+        assert!(
+            results.iter().all(|&result| result == 0)
+                || (results.contains(&1) && results.contains(&-1))
+        );
+    }
+}
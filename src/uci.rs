@@ -3,22 +3,77 @@ pub mod commands;
 use commands::{CommandHandler, UciCommand};
 use tracing::error;
 
-use crate::search::game_state::GameState;
+use crate::{
+    opening_book::{OpeningBook, DEFAULT_VARIETY},
+    search::{game_state::GameState, strength::DEFAULT_ELO},
+    variant::RescueConfig,
+};
 use std::{
-    io::{self},
-    sync::{Arc, Mutex},
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
 };
 
+/// The transposition table size the engine starts with, in megabytes.
+pub const DEFAULT_HASH_MB: usize = 64;
+
+/// The largest transposition table size that can be set with the Hash option, in megabytes.
+pub const MAX_HASH_MB: usize = 16384;
+
 pub struct UciEngine {
     pub game_state: Arc<Mutex<GameState>>,
     pub stdout: Arc<Mutex<Box<dyn io::Write + Send>>>,
+
+    /// Set to stop the running search.
+    pub stop: Arc<AtomicBool>,
+
+    /// The thread running the current search, which prints its best move when it finishes.
+    pub search_thread: Option<JoinHandle<()>>,
+
+    /// Whether the GUI is playing Chess960 and writes castling as the king taking its rook.
+    pub chess960: bool,
+
+    /// The Rescue rules positions are set up with.
+    pub rescue_config: RescueConfig,
+
+    /// Whether the engine plays at `elo` rather than full strength.
+    pub limit_strength: bool,
+    pub elo: u32,
+
+    /// Whether the engine plays from `book`, loaded from the BookFile option, before searching.
+    pub own_book: bool,
+    pub book: Option<Arc<OpeningBook>>,
+    pub book_variety: u32,
 }
 
 impl UciEngine {
     pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    /// Creates an engine that writes its responses to `stdout`.
+    pub fn with_output(stdout: Box<dyn io::Write + Send>) -> Self {
+        let mut game_state = GameState::default();
+        game_state
+            .iterative_deepening_data
+            .transposition_table
+            .set_size_mb(DEFAULT_HASH_MB);
+
         Self {
-            game_state: Arc::new(Mutex::new(GameState::default())),
-            stdout: Arc::new(Mutex::new(Box::new(io::stdout()))),
+            game_state: Arc::new(Mutex::new(game_state)),
+            stdout: Arc::new(Mutex::new(stdout)),
+            stop: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+            chess960: false,
+            rescue_config: RescueConfig::default(),
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+            own_book: false,
+            book: None,
+            book_variety: DEFAULT_VARIETY,
         }
     }
 
@@ -27,14 +82,30 @@ impl UciEngine {
             UciCommand::Uci(cmd) => cmd.execute(self),
             UciCommand::IsReady(cmd) => cmd.execute(self),
             UciCommand::UciNewGame => {
-                self.game_state = Arc::new(Mutex::new(GameState::new()));
+                self.stop_search();
+                self.game_state.lock().unwrap().start_new_game();
                 Ok(true)
             }
-            UciCommand::Position(cmd) => cmd.execute(self),
-            UciCommand::Go(cmd) => cmd.execute(self),
-            UciCommand::Stop => Ok(true), // TODO: Implement search stopping
-            UciCommand::Quit => Ok(false),
-            UciCommand::SetOption(cmd) => cmd.execute(self),
+            UciCommand::Position(cmd) => {
+                self.stop_search();
+                cmd.execute(self)
+            }
+            UciCommand::Go(cmd) => {
+                self.stop_search();
+                cmd.execute(self)
+            }
+            UciCommand::Stop => {
+                self.stop_search();
+                Ok(true)
+            }
+            UciCommand::Quit => {
+                self.stop_search();
+                Ok(false)
+            }
+            UciCommand::SetOption(cmd) => {
+                self.stop_search();
+                cmd.execute(self)
+            }
             UciCommand::Unknown(cmd) => {
                 if cmd.trim().is_empty() {
                     return Ok(true);
@@ -46,13 +117,49 @@ impl UciEngine {
             }
         }
     }
+
+    /// Tells the GUI about a command that couldn't be carried out, as an `info string`, and logs
+    /// it.
+    pub fn report_error(&self, message: &str) -> io::Result<()> {
+        error!("{}", message);
+
+        let mut stdout = self.stdout.lock().unwrap();
+        writeln!(stdout, "info string {}", message)?;
+        stdout.flush()
+    }
+
+    /// Stops the running search, if any, and waits for it to print its best move.
+    pub fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    /// Waits for the running search, if any, to finish on its own.
+    pub fn wait_for_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            handle.join().unwrap();
+        }
+    }
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
+        opening_book::OpeningBook,
+        pgn::import_pgn,
+        piece_move::GameType,
+        search::{
+            backend::SearchAlgorithm, strength::StrengthLimit,
+            transposition_table::TranspositionTable,
+        },
         uci::{commands::UciCommand, UciEngine},
-        Pos,
+        PieceType, Pos,
     };
     use std::{
         io::{self, Write},
@@ -88,10 +195,7 @@ mod tests {
     // Helper function to create engine with captured stdout
     fn create_test_engine() -> (UciEngine, CaptureStdout) {
         let buffer = Arc::new(Mutex::new(Vec::new()));
-        let engine = UciEngine {
-            game_state: Default::default(),
-            stdout: Arc::new(Mutex::new(Box::new(CaptureStdout::new(buffer.clone())))),
-        };
+        let engine = UciEngine::with_output(Box::new(CaptureStdout::new(buffer.clone())));
         (engine, CaptureStdout::new(buffer))
     }
 
@@ -176,6 +280,16 @@ mod tests {
             .current_position
             .get_piece_at(Pos::from_algebraic("c5").unwrap())
             .is_some());
+
+        // Without the move clocks, as many tools write it
+        let (mut engine, capture) = create_test_engine();
+        let cmd = "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - moves e2e4"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        assert!(capture.as_string().is_empty(), "{}", capture.as_string());
+        assert_eq!(engine.game_state.lock().unwrap().num_plies, 1);
     }
 
     #[test]
@@ -189,6 +303,7 @@ mod tests {
         // Send go command with depth 4
         let go_cmd = "go depth 4".parse::<UciCommand>().unwrap();
         engine.handle_command(go_cmd).unwrap();
+        engine.wait_for_search();
 
         let output = capture.as_string();
 
@@ -205,6 +320,368 @@ mod tests {
         assert!(parts[1].len() >= 4);
     }
 
+    #[test]
+    fn test_go_info_lines() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position startpos moves e2e4"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let cmd = "go depth 3".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        engine.wait_for_search();
+
+        let output = capture.as_string();
+        let last_info = output
+            .lines()
+            .rfind(|line| line.starts_with("info depth 3 "))
+            .unwrap();
+
+        for field in [
+            "seldepth", "score cp", "nodes", "nps", "time", "hashfull", "pv",
+        ] {
+            assert!(last_info.contains(&format!(" {} ", field)), "{}", last_info);
+        }
+
+        // Black's moves are reported from black's side of the board, and the principal
+        // variation starts with the best move
+        let best_move = output.lines().last().unwrap().split_whitespace().nth(1);
+        let pv = last_info.split(" pv ").nth(1).unwrap();
+        assert_eq!(pv.split_whitespace().next(), best_move);
+        assert!(pv.split_whitespace().all(|mv| mv.len() >= 4));
+        assert!(best_move.unwrap().ends_with('6') || best_move.unwrap().ends_with('5'));
+    }
+
+    #[test]
+    fn test_go_reports_mate_score() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let cmd = "go depth 3".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        engine.wait_for_search();
+
+        let output = capture.as_string();
+        let last_info = output
+            .lines()
+            .rfind(|line| line.starts_with("info depth 3 "))
+            .unwrap();
+
+        assert!(last_info.contains(" score mate 1 "), "{}", last_info);
+        assert!(output.contains("bestmove a1a8"), "{}", output);
+    }
+
+    #[test]
+    fn test_position_command_illegal_move() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position startpos moves e2e4 e7e5 e4e5 d2d4"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        // The moves before the illegal one are played
+        let output = capture.as_string();
+        assert!(output.starts_with("info string Illegal move e4e5 after 2 moves"));
+        assert!(output.contains("Ignoring it and the 1 moves after it"));
+
+        let game_state = engine.game_state.lock().unwrap();
+        assert_eq!(game_state.num_plies, 2);
+        assert!(game_state
+            .current_position
+            .get_piece_at(Pos::from_algebraic("e4").unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn test_position_command_illegal_move_is_one_line() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position startpos moves e1b1 e8d8"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        // The report names the move and why it's illegal, without the board after it
+        assert_eq!(
+            capture.as_string(),
+            "info string Illegal move e1b1 after 0 moves: No piece can make this move: Ke1b1. \
+             Ignoring it and the 1 moves after it\n"
+        );
+    }
+
+    #[test]
+    fn test_position_command_invalid_fen() {
+        let (mut engine, capture) = create_test_engine();
+
+        for cmd in [
+            "position startpos moves e2e4",
+            "position fen 4k3/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+
+        // The position is left as it was
+        assert!(capture.as_string().starts_with("info string Invalid FEN"));
+        assert_eq!(engine.game_state.lock().unwrap().num_plies, 1);
+    }
+
+    #[test]
+    fn test_position_command_starts_over() {
+        let (mut engine, _capture) = create_test_engine();
+
+        for cmd in [
+            "position startpos moves e2e4",
+            "position startpos moves e2e4 e7e5",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+
+        let game_state = engine.game_state.lock().unwrap();
+        assert_eq!(game_state.num_plies, 2);
+        assert_eq!(game_state.history.len(), 3);
+        assert_eq!(game_state.current_turn, crate::Color::White);
+    }
+
+    #[test]
+    fn test_search_algorithm() {
+        let (mut engine, capture) = create_test_engine();
+
+        for cmd in [
+            "uci",
+            "setoption name SearchAlgorithm value MCTS",
+            "position startpos",
+            "go depth 3",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+        engine.wait_for_search();
+
+        assert!(capture
+            .as_string()
+            .contains("option name SearchAlgorithm type combo"));
+        assert!(capture.as_string().contains("bestmove "));
+        assert_eq!(
+            engine.game_state.lock().unwrap().search_algorithm,
+            SearchAlgorithm::Mcts
+        );
+    }
+
+    #[test]
+    fn test_variant() {
+        let (mut engine, capture) = create_test_engine();
+
+        for cmd in [
+            "uci",
+            "setoption name UCI_Variant value rescue",
+            "position startpos moves g1f3Sf2 e7e5",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+
+        assert!(capture
+            .as_string()
+            .contains("option name UCI_Variant type combo"));
+
+        // The knight picked up the pawn on f2 as it moved
+        let game_state = engine.game_state.lock().unwrap();
+        assert_eq!(game_state.game_type, GameType::Rescue);
+        assert_eq!(
+            game_state
+                .current_position
+                .get_piece_at(Pos::from_algebraic("f3").unwrap())
+                .unwrap()
+                .holding,
+            Some(PieceType::Pawn)
+        );
+    }
+
+    #[test]
+    fn test_limit_strength() {
+        let (mut engine, capture) = create_test_engine();
+
+        for cmd in [
+            "setoption name UCI_LimitStrength value true",
+            "setoption name UCI_Elo value 800",
+            "position startpos",
+            "go depth 8",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+        engine.wait_for_search();
+
+        assert_eq!(
+            engine.game_state.lock().unwrap().strength_limit,
+            Some(StrengthLimit::new(800))
+        );
+        assert!(capture.as_string().contains("bestmove "));
+    }
+
+    #[test]
+    fn test_own_book() {
+        let mut book = OpeningBook::new(GameType::Classic);
+        for game in import_pgn("[Result \"1-0\"]\n1. d4 d5 1-0\n", GameType::Classic).unwrap() {
+            book.add_game(&game, 20);
+        }
+
+        let path = std::env::temp_dir().join(format!("own_book_{}.rcbk", std::process::id()));
+        book.save(&path).unwrap();
+
+        let (mut engine, capture) = create_test_engine();
+        let run = |engine: &mut UciEngine, commands: &[&str]| {
+            for cmd in commands {
+                engine
+                    .handle_command(cmd.parse::<UciCommand>().unwrap())
+                    .unwrap();
+            }
+            engine.wait_for_search();
+        };
+
+        run(
+            &mut engine,
+            &[
+                "setoption name OwnBook value true",
+                &format!("setoption name BookFile value {}", path.display()),
+                "setoption name BookVariety value 0",
+                "position startpos",
+                "go depth 30",
+            ],
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // The book move is played without a search
+        assert!(capture.as_string().ends_with("bestmove d2d4\n"));
+        assert!(!capture.as_string().contains("info depth"));
+
+        // Out of the book, the engine searches, and doesn't go back to the book
+        run(&mut engine, &["position startpos moves a2a3", "go depth 2"]);
+        assert!(capture.as_string().contains("info depth"));
+        assert!(engine
+            .game_state
+            .lock()
+            .unwrap()
+            .book
+            .as_ref()
+            .unwrap()
+            .is_out_of_book());
+    }
+
+    #[test]
+    fn test_go_infinite_and_stop() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position startpos".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let cmd = "go infinite".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        // Still searching, and ready for more commands
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!capture.as_string().contains("bestmove"));
+
+        let cmd = "isready".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert!(capture.as_string().contains("readyok"));
+
+        let cmd = "stop".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let output = capture.as_string();
+        let last_line = output.lines().last().unwrap();
+        assert!(last_line.starts_with("bestmove"));
+        assert_ne!(last_line, "bestmove 0000");
+    }
+
+    #[test]
+    fn test_go_stopped_before_first_depth() {
+        let (mut engine, capture) = create_test_engine();
+
+        // Too few nodes to finish the first depth
+        for command in ["position startpos", "go nodes 1"] {
+            let cmd = command.parse::<UciCommand>().unwrap();
+            engine.handle_command(cmd).unwrap();
+        }
+        engine.wait_for_search();
+
+        let output = capture.as_string();
+        let last_line = output.lines().last().unwrap();
+        assert!(last_line.starts_with("bestmove"));
+        assert_ne!(last_line, "bestmove 0000");
+
+        for command in ["position startpos", "go wtime 1000 btime 1000", "stop"] {
+            let cmd = command.parse::<UciCommand>().unwrap();
+            engine.handle_command(cmd).unwrap();
+        }
+
+        let output = capture.as_string();
+        let last_line = output.lines().last().unwrap();
+        assert!(last_line.starts_with("bestmove"));
+        assert_ne!(last_line, "bestmove 0000");
+    }
+
+    #[test]
+    fn test_go_limits() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "position startpos moves e2e4"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        // Black is to move, so only black's clock counts
+        let cmd = "go wtime 600000 btime 3000 winc 0 binc 0 movestogo 3"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        engine.wait_for_search();
+
+        assert_eq!(engine.game_state.lock().unwrap().time_limit_ms, 1000);
+        assert!(capture.as_string().contains("bestmove"));
+        assert!(!capture.as_string().contains("bestmove 0000"));
+
+        let cmd = "position startpos".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let nodes_before = engine
+            .game_state
+            .lock()
+            .unwrap()
+            .iterative_deepening_data
+            .stats
+            .nodes_searched;
+
+        let cmd = "go nodes 5000".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        engine.wait_for_search();
+
+        let game_state = engine.game_state.lock().unwrap();
+        let nodes_searched =
+            game_state.iterative_deepening_data.stats.nodes_searched - nodes_before;
+        assert_eq!(game_state.time_limit_ms, u64::MAX);
+        assert!(nodes_searched <= 5000, "Searched {} nodes", nodes_searched);
+        let output = capture.as_string();
+        let last_line = output.lines().last().unwrap();
+        assert!(last_line.starts_with("bestmove"));
+        assert_ne!(last_line, "bestmove 0000");
+    }
+
     #[test]
     fn test_unknown_command() {
         let (mut engine, _capture) = create_test_engine();
@@ -245,24 +722,159 @@ mod tests {
     }
 
     #[test]
-    fn test_ucinewgame_command() {
+    fn test_rescue_rule_options() {
         let (mut engine, _capture) = create_test_engine();
 
+        for cmd in [
+            "setoption name RescueMaxHeld value 0",
+            "setoption name RescueDropMate value false",
+            "position startpos",
+        ] {
+            engine
+                .handle_command(cmd.parse::<UciCommand>().unwrap())
+                .unwrap();
+        }
+
+        let game_state = engine.game_state.lock().unwrap();
+        let config = game_state.current_position.rescue_config;
+        assert_eq!(config.max_held, 0);
+        assert!(!config.drops_can_mate);
+        assert!(!config.kings_rescuable);
+    }
+
+    #[test]
+    fn test_uci_command_lists_feature_and_weight_options() {
+        let (mut engine, capture) = create_test_engine();
+
+        let cmd = "uci".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let output = capture.as_string();
+        assert!(output.contains("option name EnableLmr type check default true"));
+        assert!(output.contains("option name EvaluateMobility type check default false"));
+        assert!(
+            output.contains("option name WeightKingSafety type spin default 100 min 0 max 1000")
+        );
+    }
+
+    #[test]
+    fn test_setoption_features_and_weights() {
+        let (mut engine, _capture) = create_test_engine();
+
+        let cmd = "setoption name EnableLMR value false"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert!(!engine.game_state.lock().unwrap().features.enable_lmr);
+
+        let cmd = "setoption name EvaluateKingSafety value true"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert!(
+            engine
+                .game_state
+                .lock()
+                .unwrap()
+                .features
+                .evaluate_king_safety
+        );
+
+        let cmd = "setoption name WeightKingSafety value 150"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert_eq!(engine.game_state.lock().unwrap().weights.king_safety, 150);
+
+        // Out of range values are clamped, invalid values are ignored
+        let cmd = "setoption name WeightMobility value 5000"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert_eq!(engine.game_state.lock().unwrap().weights.mobility, 1000);
+
+        let cmd = "setoption name WeightMobility value lots"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert_eq!(engine.game_state.lock().unwrap().weights.mobility, 1000);
+    }
+
+    #[test]
+    fn test_setoption_hash() {
+        let (mut engine, capture) = create_test_engine();
+
+        let max_entries = |engine: &UciEngine| {
+            engine
+                .game_state
+                .lock()
+                .unwrap()
+                .iterative_deepening_data
+                .transposition_table
+                .max_entries()
+                .unwrap()
+        };
+
+        let entries_128_mb = TranspositionTable::with_size_mb(128).max_entries().unwrap();
+
+        let cmd = "setoption name Hash value 128"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert_eq!(max_entries(&engine), entries_128_mb);
+
+        // Engine settings survive a new game
+        let cmd = "setoption name EnableLMR value false"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let cmd = "ucinewgame".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        assert_eq!(max_entries(&engine), entries_128_mb);
+        assert!(!engine.game_state.lock().unwrap().features.enable_lmr);
+
+        let cmd = "setoption name Threads value 1"
+            .parse::<UciCommand>()
+            .unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let cmd = "uci".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+
+        let output = capture.as_string();
+        assert!(output.contains("option name Hash type spin default 64 min 1 max 16384"));
+        assert!(output.contains("option name Threads type spin default 1 min 1 max 1"));
+        assert!(output.contains("option name MultiPV type spin default 1 min 1 max 1"));
+    }
+
+    #[test]
+    fn test_ucinewgame_command() {
+        let (mut engine, capture) = create_test_engine();
+
         // First set up some position
         let pos_cmd = "position startpos moves e2e4"
             .parse::<UciCommand>()
             .unwrap();
         engine.handle_command(pos_cmd).unwrap();
 
+        // And fill the transposition table a little
+        let cmd = "go depth 3".parse::<UciCommand>().unwrap();
+        engine.handle_command(cmd).unwrap();
+        engine.wait_for_search();
+        assert!(capture.as_string().contains("info hashfull "));
+
         // Send ucinewgame
         let cmd = "ucinewgame".parse::<UciCommand>().unwrap();
         engine.handle_command(cmd).unwrap();
 
-        // Verify position was reset
-        assert_eq!(
-            engine.game_state.lock().unwrap().current_position,
-            Default::default()
-        );
+        // Verify position and transposition table were reset
+        let game_state = engine.game_state.lock().unwrap();
+        assert_eq!(game_state.current_position, Default::default());
+        assert!(game_state
+            .iterative_deepening_data
+            .transposition_table
+            .is_empty());
     }
 
     #[test]
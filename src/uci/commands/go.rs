@@ -1,9 +1,22 @@
+use std::{io::Write, sync::atomic::Ordering, thread, time::Duration};
+
 use tracing::trace;
 
-use crate::{uci::UciEngine, Color};
+use crate::{
+    search::{
+        params_builder::MAX_SEARCH_PLY, score::Score, search_results::SearchInfo,
+        time_control::time_for_move,
+    },
+    uci::UciEngine,
+    Color, PieceMove,
+};
 
 use super::CommandHandler;
 
+/// The depth to search to when the search is only limited by time, nodes, or `stop`. Deeper
+/// than any search gets in practice, while leaving room under `MAX_SEARCH_PLY` for quiescence.
+const MAX_SEARCH_DEPTH: u32 = MAX_SEARCH_PLY / 2;
+
 /// Represents a go command with all possible search parameters
 #[derive(Debug)]
 pub struct GoCommand {
@@ -21,71 +34,151 @@ pub struct GoCommand {
     pub infinite: bool,
 }
 
+impl GoCommand {
+    /// Whether the search is limited by anything other than its depth.
+    fn has_limits_besides_depth(&self) -> bool {
+        self.infinite
+            || self.movetime.is_some()
+            || self.wtime.is_some()
+            || self.btime.is_some()
+            || self.nodes.is_some()
+    }
+
+    /// How long to search for, with `color` to move.
+    fn time_limit_ms(&self, color: Color) -> u64 {
+        if self.infinite {
+            return u64::MAX;
+        }
+
+        if let Some(movetime) = self.movetime {
+            return movetime;
+        }
+
+        let (remaining, increment) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+
+        match remaining {
+            Some(remaining) => time_for_move(remaining, increment.unwrap_or(0), self.movestogo),
+            None => u64::MAX,
+        }
+    }
+}
+
+/// Formats a move in UCI notation, writing castling as the king taking its rook when playing
+/// Chess960.
+fn uci_move(mv: &PieceMove, chess960: bool) -> String {
+    if chess960 {
+        mv.to_uci_chess960()
+    } else {
+        mv.to_uci()
+    }
+}
+
+/// Formats a search's progress as a UCI `info` line, with `is_black` whether black is to move
+/// at the root. Moves in the principal variation are each from the perspective of the side
+/// playing them, so every other one is turned around for black.
+pub fn info_line(info: &SearchInfo, is_black: bool, chess960: bool) -> String {
+    let mut line = format!(
+        "info depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {}",
+        info.depth,
+        info.seldepth,
+        Score::from_search(info.score),
+        info.nodes,
+        info.nodes_per_second(),
+        info.time_ms,
+        info.hashfull
+    );
+
+    if !info.principal_variation.is_empty() {
+        line.push_str(" pv");
+
+        for (ply, mv) in info.principal_variation.iter().enumerate() {
+            let mv = if is_black == (ply % 2 == 0) {
+                mv.inverted()
+            } else {
+                *mv
+            };
+
+            line.push(' ');
+            line.push_str(&uci_move(&mv, chess960));
+        }
+    }
+
+    line
+}
+
 impl CommandHandler for GoCommand {
     fn execute(&self, engine: &mut UciEngine) -> std::io::Result<bool> {
-        // Update search depth if specified
+        engine.stop.store(false, Ordering::Relaxed);
+
         {
             let mut game_state = engine.game_state.lock().unwrap();
+
             if let Some(depth) = self.depth {
                 game_state.search_depth = depth;
+            } else if self.has_limits_besides_depth() {
+                game_state.search_depth = MAX_SEARCH_DEPTH;
             }
 
-            trace!("Searching to depth {}", game_state.search_depth);
-
-            match self.movetime {
-                Some(movetime) => {
-                    game_state.time_limit_ms = movetime;
-                }
-                None => {
-                    if let Some(wtime) = self.wtime {
-                        game_state.time_limit_ms = wtime;
-                    } else if let Some(btime) = self.btime {
-                        game_state.time_limit_ms = btime;
-                    } else {
-                        game_state.time_limit_ms = u64::MAX;
-                    }
-                }
-            }
+            game_state.time_limit_ms = self.time_limit_ms(game_state.current_turn);
+            game_state.iterative_deepening_data.node_limit = self.nodes.unwrap_or(u64::MAX);
+            game_state.iterative_deepening_data.stop = engine.stop.clone();
 
+            trace!("Searching to depth {}", game_state.search_depth);
             trace!("Time limit: {} ms", game_state.time_limit_ms);
-
             trace!("Current position: {}", game_state.current_position.to_fen());
         }
 
         let game_state = engine.game_state.clone();
+        let stdout = engine.stdout.clone();
+        let stop = engine.stop.clone();
+        let infinite = self.infinite;
+        let chess960 = engine.chess960;
 
-        let handle = std::thread::spawn(move || {
+        engine.search_thread = Some(thread::spawn(move || {
             let mut game_state = game_state.lock().unwrap();
             let is_black = game_state.current_turn == Color::Black;
 
-            game_state.set_on_new_best_move_handler(Box::new(move |mut best_move, score| {
-                if is_black {
-                    best_move = best_move.inverted();
-                }
-
-                trace!("New best move: {} with score {}", best_move, score);
-                println!("info score cp {}", score);
-                println!("info pv {}", best_move.to_uci());
+            let info_stdout = stdout.clone();
+            game_state.set_on_new_best_move_handler(Box::new(move |info| {
+                let mut stdout = info_stdout.lock().unwrap();
+                let _ = writeln!(stdout, "{}", info_line(info, is_black, chess960));
+                let _ = stdout.flush();
             }));
 
-            // Perform search
-            match game_state.search_and_apply() {
+            let result = game_state.search_and_apply();
+            let hashfull = game_state
+                .iterative_deepening_data
+                .transposition_table
+                .hashfull();
+
+            // An infinite search must not report its move until it's told to stop
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            let mut stdout = stdout.lock().unwrap();
+            let _ = writeln!(stdout, "info hashfull {}", hashfull);
+
+            match result {
                 Ok((mut best_move, _)) => {
                     if game_state.current_turn == Color::White {
                         best_move = best_move.inverted();
                     }
 
                     trace!("Best move: {}", best_move);
-                    println!("bestmove {}", best_move.to_uci());
+                    let _ = writeln!(stdout, "bestmove {}", uci_move(&best_move, chess960));
                 }
                 Err(e) => {
                     trace!("Error searching: {}", e);
-                    println!("bestmove 0000")
+                    let _ = writeln!(stdout, "bestmove 0000");
                 }
             }
-        });
 
-        handle.join().unwrap();
+            let _ = stdout.flush();
+        }));
 
         Ok(true)
     }
@@ -5,62 +5,61 @@ use crate::{uci::UciEngine, Color, PieceMove, Position};
 use super::CommandHandler;
 
 /// Represents a position command with either a FEN or startpos and optional moves
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct PositionCommand {
     pub fen: Option<String>,
     pub moves: Vec<String>,
 }
 
 impl CommandHandler for PositionCommand {
+    /// Sets up the position and plays the moves on it. An invalid FEN leaves the position as it
+    /// was, and the moves are played up to the first one that isn't legal. Either is reported to
+    /// the GUI, since the engine's position no longer matches its own.
     fn execute(&self, engine: &mut UciEngine) -> std::io::Result<bool> {
-        let mut game_state = engine.game_state.lock().unwrap();
-
-        // Set up initial position
-        match &self.fen {
-            Some(fen) => {
-                if let Ok(pos) = Position::parse_from_fen(fen) {
-                    game_state.current_position = pos;
-                    game_state.current_turn = game_state.current_position.true_active_color;
-                } else {
-                    eprintln!("Invalid FEN: {}", fen);
+        let mut position = match &self.fen {
+            Some(fen) => match Position::validate_fen(fen) {
+                Ok(position) => position,
+                Err(errors) => {
+                    let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                    engine.report_error(&format!("Invalid FEN {}: {}", fen, errors.join("; ")))?;
                     return Ok(true);
                 }
-            }
-            None => {
-                game_state.current_position = Position::start_position();
-                game_state.current_turn = Color::White;
-            }
+            },
+            None => Position::start_position(),
         };
+        position.rescue_config = engine.rescue_config;
+
+        let mut game_state = engine.game_state.lock().unwrap();
+        game_state.set_position(position);
 
-        // Apply moves
-        for move_str in &self.moves {
-            let mv = if game_state.current_turn == Color::White {
-                PieceMove::from_uci(&game_state.current_position, move_str, game_state.game_type)
-            } else {
-                PieceMove::from_uci_inverted(
+        for (i, notation) in self.moves.iter().enumerate() {
+            let mv = match game_state.current_turn {
+                Color::White => PieceMove::from_uci(
                     &game_state.current_position,
-                    move_str,
+                    notation,
                     game_state.game_type,
-                )
+                ),
+                Color::Black => PieceMove::from_uci_inverted(
+                    &game_state.current_position,
+                    notation,
+                    game_state.game_type,
+                ),
             };
 
-            match mv {
-                Ok(mv) => {
-                    println!("applying move: {}", mv);
-                    let result = game_state.apply_move(mv);
-
-                    trace!("Applied move: {}", move_str);
-
-                    if let Err(e) = result {
-                        trace!("Error applying move {}: {}", move_str, e);
-                        panic!("Error applying move {}: {}", move_str, e);
-                    }
-                }
-                Err(e) => {
-                    trace!("Error parsing move {}: {}", move_str, e);
-                    panic!("Error parsing move {}: {}", move_str, e);
-                }
+            if let Err(e) = mv.and_then(|mv| game_state.apply_move(mv)) {
+                drop(game_state);
+                engine.report_error(&format!(
+                    "Illegal move {} after {} moves: {}. Ignoring it and the {} moves after it",
+                    notation,
+                    i,
+                    e,
+                    self.moves.len() - i - 1
+                ))?;
+
+                return Ok(true);
             }
+
+            trace!("Applied move: {}", notation);
         }
 
         Ok(true)
@@ -76,33 +75,54 @@ impl std::str::FromStr for PositionCommand {
             return Err("Invalid position command".to_string());
         }
 
-        let mut moves = Vec::new();
-        let mut fen = None;
+        let moves_idx = parts.iter().position(|&x| x == "moves");
+        let setup_end = moves_idx.unwrap_or(parts.len());
+        let moves = moves_idx
+            .map(|idx| parts[idx + 1..].iter().map(|&s| s.to_string()).collect())
+            .unwrap_or_default();
 
-        match parts[1] {
+        let fen = match parts[1] {
+            "startpos" if setup_end == 2 => None,
             "startpos" => {
-                // Handle moves after startpos
-                if let Some(moves_idx) = parts.iter().position(|&x| x == "moves") {
-                    moves = parts[moves_idx + 1..]
-                        .iter()
-                        .map(|&s| s.to_string())
-                        .collect();
-                }
-            }
-            "fen" => {
-                // Find where the FEN string ends (either at "moves" or end of string)
-                let moves_idx = parts.iter().position(|&x| x == "moves");
-                let fen_end = moves_idx.unwrap_or(parts.len());
-                fen = Some(parts[2..fen_end].join(" "));
-
-                // Collect moves if present
-                if let Some(idx) = moves_idx {
-                    moves = parts[idx + 1..].iter().map(|&s| s.to_string()).collect();
-                }
+                return Err(format!(
+                    "Unexpected {} after startpos",
+                    parts[2..setup_end].join(" ")
+                ))
             }
+            "fen" if setup_end > 2 => Some(parts[2..setup_end].join(" ")),
+            "fen" => return Err("Missing FEN in position command".to_string()),
             _ => return Err("Invalid position command format".to_string()),
-        }
+        };
 
         Ok(PositionCommand { fen, moves })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "position startpos moves e2e4 g1f3Sf2".parse(),
+            Ok(PositionCommand {
+                fen: None,
+                moves: vec!["e2e4".to_string(), "g1f3Sf2".to_string()],
+            })
+        );
+        assert_eq!(
+            "position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1 moves".parse(),
+            Ok(PositionCommand {
+                fen: Some("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string()),
+                moves: vec![],
+            })
+        );
+
+        assert!("position startpos e2e4".parse::<PositionCommand>().is_err());
+        assert!("position fen moves e2e4"
+            .parse::<PositionCommand>()
+            .is_err());
+        assert!("position moves e2e4".parse::<PositionCommand>().is_err());
+    }
+}
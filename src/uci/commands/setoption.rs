@@ -1,6 +1,16 @@
+use std::{path::Path, sync::Arc};
+
 use tracing::trace;
 
-use crate::uci::UciEngine;
+#[cfg(feature = "syzygy")]
+use crate::search::tablebase::Tablebase;
+use crate::{
+    features::{EvaluationWeights, Features, WEIGHT_OPTION_MAX, WEIGHT_OPTION_MIN},
+    opening_book::{BookPlayer, OpeningBook, MAX_VARIETY},
+    piece_move::GameType,
+    search::{backend::SearchAlgorithm, strength::StrengthLimit},
+    uci::{UciEngine, MAX_HASH_MB},
+};
 
 use super::CommandHandler;
 
@@ -17,23 +27,120 @@ impl CommandHandler for SetOptionCommand {
 
         trace!("Setting option: {} = {:?}", self.name, self.value);
 
-        match self.name.as_str() {
-            "EnableLMR" => {
-                game_state.features.enable_lmr = self.value.as_deref() == Some("true");
+        #[cfg(feature = "syzygy")]
+        if self.name.eq_ignore_ascii_case("SyzygyPath") {
+            game_state.iterative_deepening_data.tablebase = match self.value.as_deref() {
+                None | Some("") | Some("<empty>") => None,
+                Some(paths) => match Tablebase::open(paths) {
+                    Ok(tablebase) => Some(Arc::new(tablebase)),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        None
+                    }
+                },
+            };
+
+            return Ok(true);
+        }
+
+        if self.name.eq_ignore_ascii_case("Hash") {
+            match self.value.as_deref().map(|value| value.parse::<usize>()) {
+                Some(Ok(megabytes)) => game_state
+                    .iterative_deepening_data
+                    .transposition_table
+                    .set_size_mb(megabytes.clamp(1, MAX_HASH_MB)),
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
+            }
+        } else if self.name.eq_ignore_ascii_case("UCI_Chess960") {
+            engine.chess960 = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("UCI_Variant") {
+            match self.value.as_deref() {
+                Some(value) if value.eq_ignore_ascii_case("chess") => {
+                    game_state.game_type = GameType::Classic
+                }
+                Some(value) if value.eq_ignore_ascii_case("rescue") => {
+                    game_state.game_type = GameType::Rescue
+                }
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
+            }
+        } else if self.name.eq_ignore_ascii_case("SearchAlgorithm") {
+            match self
+                .value
+                .as_deref()
+                .map(|value| value.parse::<SearchAlgorithm>())
+            {
+                Some(Ok(algorithm)) => game_state.search_algorithm = algorithm,
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
+            }
+        } else if self.name.eq_ignore_ascii_case("UCI_LimitStrength") {
+            engine.limit_strength = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("UCI_Elo") {
+            match self.value.as_deref().map(|value| value.parse::<u32>()) {
+                Some(Ok(elo)) => engine.elo = elo,
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
             }
-            "EnableTranspositionTable" => {
-                game_state.features.enable_transposition_table =
-                    self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("OwnBook") {
+            engine.own_book = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("BookFile") {
+            engine.book = match self.value.as_deref() {
+                None | Some("") | Some("<empty>") => None,
+                Some(path) => match OpeningBook::load(Path::new(path)) {
+                    Ok(book) => Some(Arc::new(book)),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        None
+                    }
+                },
+            };
+        } else if self.name.eq_ignore_ascii_case("BookVariety") {
+            match self.value.as_deref().map(|value| value.parse::<u32>()) {
+                Some(Ok(variety)) => engine.book_variety = variety.min(MAX_VARIETY),
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
             }
-            "EnableWindowSearch" => {
-                game_state.features.enable_window_search = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("RescueMaxHeld") {
+            match self.value.as_deref().map(|value| value.parse::<u8>()) {
+                Some(Ok(max_held)) => engine.rescue_config.max_held = max_held.min(16),
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
             }
-            "EnableKillerMoves" => {
-                game_state.features.enable_killer_moves = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("RescueKings") {
+            engine.rescue_config.kings_rescuable = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("RescueDropMate") {
+            engine.rescue_config.drops_can_mate = self.value.as_deref() == Some("true");
+        } else if self.name.eq_ignore_ascii_case("Threads")
+            || self.name.eq_ignore_ascii_case("MultiPV")
+        {
+            if self.value.as_deref() != Some("1") {
+                eprintln!("Only a value of 1 is supported for {}", self.name);
             }
+        } else if let Some(option) = Features::find_option(&self.name) {
+            (option.set)(
+                &mut game_state.features,
+                self.value.as_deref() == Some("true"),
+            );
+        } else if let Some(option) = EvaluationWeights::find_option(&self.name) {
+            match self.value.as_deref().map(|value| value.parse::<i32>()) {
+                Some(Ok(value)) => {
+                    let value = value.clamp(WEIGHT_OPTION_MIN, WEIGHT_OPTION_MAX);
+                    (option.set)(&mut game_state.weights, value);
+                }
+                _ => eprintln!("Invalid value for {}: {:?}", self.name, self.value),
+            }
+        } else {
             // Add other options as needed
-            _ => eprintln!("Unknown option: {}", self.name),
+            eprintln!("Unknown option: {}", self.name);
         }
+
+        game_state.strength_limit = engine
+            .limit_strength
+            .then(|| StrengthLimit::new(engine.elo));
+
+        game_state.book = match &engine.book {
+            Some(book) if engine.own_book => {
+                Some(BookPlayer::new(book.clone(), engine.book_variety))
+            }
+            _ => None,
+        };
+
         Ok(true)
     }
 }
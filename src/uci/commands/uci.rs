@@ -1,5 +1,14 @@
 use super::CommandHandler;
-use crate::uci::UciEngine;
+use crate::{
+    features::{EvaluationWeights, Features, WEIGHT_OPTION_MAX, WEIGHT_OPTION_MIN},
+    opening_book::{DEFAULT_VARIETY, MAX_VARIETY},
+    search::{
+        backend::SearchAlgorithm,
+        strength::{DEFAULT_ELO, MAX_ELO, MIN_ELO},
+    },
+    uci::{UciEngine, DEFAULT_HASH_MB, MAX_HASH_MB},
+    variant::RescueConfig,
+};
 use std::io::Write;
 
 #[derive(Debug)]
@@ -15,21 +24,100 @@ impl CommandHandler for UciCommand {
         // Send options
         writeln!(
             stdout,
-            "option name Hash type spin default 64 min 1 max 16384"
+            "option name Hash type spin default {} min 1 max {}",
+            DEFAULT_HASH_MB, MAX_HASH_MB
+        )?;
+
+        // The search runs on one thread and finds one line, so these only have one value
+        writeln!(
+            stdout,
+            "option name Threads type spin default 1 min 1 max 1"
         )?;
         writeln!(
             stdout,
-            "option name EnableTranspositionTable type check default true"
+            "option name MultiPV type spin default 1 min 1 max 1"
         )?;
-        writeln!(stdout, "option name EnableLMR type check default true")?;
+
+        writeln!(stdout, "option name UCI_Chess960 type check default false")?;
+
+        // Rescue games take rescues and drops in their moves, written as `ParsedMove::from_uci`
+        // reads them
         writeln!(
             stdout,
-            "option name EnableWindowSearch type check default true"
+            "option name UCI_Variant type combo default chess var chess var rescue"
         )?;
+        let algorithms: Vec<String> = SearchAlgorithm::ALL
+            .iter()
+            .map(|algorithm| format!("var {}", algorithm))
+            .collect();
         writeln!(
             stdout,
-            "option name EnableKillerMoves type check default true"
+            "option name SearchAlgorithm type combo default {} {}",
+            SearchAlgorithm::default(),
+            algorithms.join(" ")
         )?;
+        writeln!(
+            stdout,
+            "option name UCI_LimitStrength type check default false"
+        )?;
+        writeln!(
+            stdout,
+            "option name UCI_Elo type spin default {} min {} max {}",
+            DEFAULT_ELO, MIN_ELO, MAX_ELO
+        )?;
+
+        // An opening book written by build_book, and how far from its heaviest moves to stray
+        writeln!(stdout, "option name OwnBook type check default false")?;
+        writeln!(stdout, "option name BookFile type string default <empty>")?;
+        writeln!(
+            stdout,
+            "option name BookVariety type spin default {} min 0 max {}",
+            DEFAULT_VARIETY, MAX_VARIETY
+        )?;
+
+        // Variations on the Rescue rules
+        let rescue_config = RescueConfig::default();
+        writeln!(
+            stdout,
+            "option name RescueMaxHeld type spin default {} min 0 max 16",
+            rescue_config.max_held
+        )?;
+        writeln!(
+            stdout,
+            "option name RescueKings type check default {}",
+            rescue_config.kings_rescuable
+        )?;
+        writeln!(
+            stdout,
+            "option name RescueDropMate type check default {}",
+            rescue_config.drops_can_mate
+        )?;
+
+        // Directories of Syzygy tables, separated like the PATH environment variable
+        #[cfg(feature = "syzygy")]
+        writeln!(stdout, "option name SyzygyPath type string default <empty>")?;
+
+        let features = Features::default();
+        for option in Features::options() {
+            writeln!(
+                stdout,
+                "option name {} type check default {}",
+                option.name(),
+                (option.get)(&features)
+            )?;
+        }
+
+        let weights = EvaluationWeights::default();
+        for option in EvaluationWeights::options() {
+            writeln!(
+                stdout,
+                "option name {} type spin default {} min {} max {}",
+                option.name(),
+                (option.get)(&weights),
+                WEIGHT_OPTION_MIN,
+                WEIGHT_OPTION_MAX
+            )?;
+        }
 
         writeln!(stdout, "uciok")?;
         stdout.flush()?;
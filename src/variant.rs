@@ -0,0 +1,231 @@
+//! The rules that differ between the variants the engine plays. Move generation, evaluation and
+//! the search ask a `GameType`'s `VariantRules` rather than matching on the game type, so a new
+//! variant only needs its own rules and a `GameType` to go with them.
+
+mod rescue_config;
+
+pub use rescue_config::RescueConfig;
+
+use crate::{
+    bitboard::Bitboard,
+    features::EvaluationWeights,
+    piece::{rescue_drop::rescue_drop_map, PAWN_PROMOTION_TYPES},
+    piece_move::{GameType, MoveList, MoveType, PieceMove},
+    Piece, PieceType, Pos, Position,
+};
+
+/// Which of the pieces' square bonus tables a variant evaluates with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquareTable {
+    Classic,
+    Rescue,
+}
+
+pub trait VariantRules: Send + Sync {
+    /// The variant's name, as the UCI `UCI_Variant` option and PGN's `Variant` tag write it.
+    fn name(&self) -> &'static str;
+
+    /// Whether pieces can rescue and drop each other.
+    fn has_rescues(&self) -> bool;
+
+    /// The square bonus tables pieces are evaluated with.
+    fn square_table(&self) -> SquareTable;
+
+    /// The evaluation weights the variant plays best with.
+    fn evaluation_weights(&self) -> EvaluationWeights;
+
+    /// Adds the moves white's `piece` can make by moving to `to`, besides the normal move itself,
+    /// to `moves`: its rescues and drops there. `holders` are white's pieces that are already
+    /// holding one, which can't be rescued.
+    fn add_extra_moves(
+        &self,
+        position: &Position,
+        piece: &Piece,
+        to: Pos,
+        capture: Capture,
+        holders: Bitboard,
+        moves: &mut MoveList,
+    );
+
+    /// The squares white's `piece` can move to without leaving its square, e.g. to rescue or
+    /// drop in place.
+    fn in_place_moves(&self, piece: &Piece) -> Bitboard;
+
+    /// Whether held pieces can still be dropped back onto the board, so that the material is
+    /// never insufficient to mate while one is being held.
+    fn counts_held_pieces(&self) -> bool {
+        self.has_rescues()
+    }
+}
+
+/// The piece a move to a square captures, if any.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capture {
+    pub captured: Option<PieceType>,
+    pub captured_pos: Option<Pos>,
+    pub captured_holding: Option<PieceType>,
+}
+
+/// Standard chess.
+pub struct ClassicRules;
+
+impl VariantRules for ClassicRules {
+    fn name(&self) -> &'static str {
+        "chess"
+    }
+
+    fn has_rescues(&self) -> bool {
+        false
+    }
+
+    fn square_table(&self) -> SquareTable {
+        SquareTable::Classic
+    }
+
+    fn evaluation_weights(&self) -> EvaluationWeights {
+        EvaluationWeights::default()
+    }
+
+    fn add_extra_moves(
+        &self,
+        _position: &Position,
+        _piece: &Piece,
+        _to: Pos,
+        _capture: Capture,
+        _holders: Bitboard,
+        _moves: &mut MoveList,
+    ) {
+    }
+
+    fn in_place_moves(&self, _piece: &Piece) -> Bitboard {
+        Bitboard::new()
+    }
+}
+
+/// Rescue chess, where a piece can pick up an orthogonally adjacent friendly piece as it moves,
+/// carry it, and drop it next to itself on a later move.
+pub struct RescueRules;
+
+impl VariantRules for RescueRules {
+    fn name(&self) -> &'static str {
+        "rescue"
+    }
+
+    fn has_rescues(&self) -> bool {
+        true
+    }
+
+    fn square_table(&self) -> SquareTable {
+        SquareTable::Rescue
+    }
+
+    fn evaluation_weights(&self) -> EvaluationWeights {
+        EvaluationWeights {
+            // A trapped piece can often be rescued and dropped somewhere else
+            trapped_pieces: 45,
+            // Pieces next to each other can rescue each other and carry pieces forward
+            piece_coordination: 100,
+            piece_protection: 80,
+            ..EvaluationWeights::default()
+        }
+    }
+
+    fn add_extra_moves(
+        &self,
+        position: &Position,
+        piece: &Piece,
+        to: Pos,
+        capture: Capture,
+        holders: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        let from = piece.position;
+        let piece_type = piece.piece_type;
+
+        // The squares a piece can drop its held piece on, or rescue a piece from, when they're
+        // next to where it moves: empty squares when it's holding a piece, and otherwise white's
+        // pieces it can hold that aren't holding one themselves, if white can hold another.
+        // `Position::apply_move` has always refused to rescue a piece that's holding one, so
+        // holders are left out here to only generate moves it accepts
+        let rescue_drop_squares = match piece.holding {
+            None if holders.count() >= position.rescue_config.max_held => Bitboard::new(),
+            Some(_) => {
+                let mut occupied = position.all_map;
+                occupied.clear(from);
+                !occupied
+            }
+            None => {
+                let mut rescuable = position.rescuable_map(piece_type) & !holders;
+                rescuable.clear(from);
+                rescuable
+            }
+        };
+
+        let normal = |rescued_pos, dropped_pos, dropped_promoted_to| PieceMove {
+            from,
+            to,
+            piece_type,
+            move_type: MoveType::Normal {
+                captured_pos: capture.captured_pos,
+                captured: capture.captured,
+                captured_holding: capture.captured_holding,
+                promoted_to: None,
+                dropped_pos,
+                dropped_promoted_to,
+                rescued_pos,
+            },
+        };
+
+        for dir in *rescue_drop_map(to) & rescue_drop_squares {
+            match piece.holding {
+                // A pawn dropped on the last rank promotes
+                Some(PieceType::Pawn) if dir.get_row() == 0 => {
+                    for promoted_to in PAWN_PROMOTION_TYPES {
+                        moves.push(normal(None, Some(dir), Some(promoted_to)));
+                    }
+                }
+                Some(_) => moves.push(normal(None, Some(dir), None)),
+                None => moves.push(normal(Some(dir), None, None)),
+            }
+        }
+    }
+
+    fn in_place_moves(&self, piece: &Piece) -> Bitboard {
+        // A piece can stay still and rescue, as long as there's a neighboring piece
+        Bitboard::new().with(piece.position)
+    }
+}
+
+impl GameType {
+    /// The rules of the variant.
+    pub fn rules(&self) -> &'static dyn VariantRules {
+        match self {
+            GameType::Classic => &ClassicRules,
+            GameType::Rescue => &RescueRules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pos;
+
+    use super::*;
+
+    #[test]
+    fn test_rules_match_game_types() {
+        assert!(!GameType::Classic.rules().has_rescues());
+        assert!(GameType::Rescue.rules().has_rescues());
+        assert_eq!(
+            GameType::Classic.rules().square_table(),
+            SquareTable::Classic
+        );
+        assert_eq!(GameType::Rescue.rules().square_table(), SquareTable::Rescue);
+
+        // Only Rescue lets pieces stay still to rescue
+        let position = Position::start_position();
+        let piece = position.get_piece_at(pos::E1).unwrap();
+        assert_eq!(GameType::Classic.rules().in_place_moves(piece).count(), 0);
+        assert!(GameType::Rescue.rules().in_place_moves(piece).get(pos::E1));
+    }
+}
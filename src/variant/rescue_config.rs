@@ -0,0 +1,168 @@
+//! Settings for the rules of Rescue chess, to try out variations without changing the code.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    piece_move::{GameType, MoveList},
+    PieceMove, PieceType, Position,
+};
+
+/// The rules of Rescue chess that can be changed. The default is the standard rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RescueConfig {
+    /// The most pieces each side can be holding at once.
+    pub max_held: u8,
+
+    /// For each type of piece, by `PieceType as usize`, the bits of the types it can hold.
+    pub can_hold: [u8; 6],
+
+    /// Whether a king can be held. The king has to stay on the board to be checkmated, so this
+    /// only makes sense for experimenting.
+    pub kings_rescuable: bool,
+
+    /// Whether a drop can checkmate.
+    pub drops_can_mate: bool,
+}
+
+impl RescueConfig {
+    /// Whether a piece of type `holder` can hold one of type `held`.
+    pub fn can_hold(&self, holder: PieceType, held: PieceType) -> bool {
+        (held != PieceType::King || self.kings_rescuable)
+            && self.can_hold[holder as usize] & (1 << held as usize) != 0
+    }
+
+    /// Lets pieces of type `holder` hold pieces of type `held`, or stops them.
+    pub fn set_can_hold(&mut self, holder: PieceType, held: PieceType, can_hold: bool) {
+        if can_hold {
+            self.can_hold[holder as usize] |= 1 << held as usize;
+        } else {
+            self.can_hold[holder as usize] &= !(1 << held as usize);
+        }
+    }
+}
+
+impl Default for RescueConfig {
+    fn default() -> Self {
+        let mut can_hold = [0; 6];
+
+        for holder in PieceType::ALL {
+            for held in PieceType::ALL {
+                if holder.can_hold(held) {
+                    can_hold[holder as usize] |= 1 << held as usize;
+                }
+            }
+        }
+
+        Self {
+            max_held: 16,
+            can_hold,
+            kings_rescuable: false,
+            drops_can_mate: true,
+        }
+    }
+}
+
+impl Position {
+    /// Whether `mv` drops a piece and checkmates black. The moves black has are found with drops
+    /// allowed to mate, so that checking doesn't go on back and forth between the sides.
+    pub(crate) fn is_mating_drop(
+        &self,
+        game_type: GameType,
+        mv: &PieceMove,
+    ) -> Result<bool, anyhow::Error> {
+        if !mv.is_drop() {
+            return Ok(false);
+        }
+
+        let mut position = self.clone();
+        position.rescue_config.drops_can_mate = true;
+        position.apply_move(*mv)?;
+        position.invert();
+
+        if !position.is_king_in_check()? {
+            return Ok(false);
+        }
+
+        let mut moves = MoveList::new();
+        position.get_all_legal_moves(game_type, &mut moves)?;
+        Ok(moves.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{piece_move::MoveType, Pos};
+
+    use super::*;
+
+    #[test]
+    fn test_default_matches_pieces() {
+        let config = RescueConfig::default();
+
+        for holder in PieceType::ALL {
+            for held in PieceType::ALL {
+                assert_eq!(
+                    config.can_hold(holder, held),
+                    holder.can_hold(held) && held != PieceType::King
+                );
+            }
+        }
+    }
+
+    fn moves(position: &Position) -> MoveList {
+        let mut moves = MoveList::new();
+        position
+            .get_all_legal_moves(GameType::Rescue, &mut moves)
+            .unwrap();
+        moves
+    }
+
+    #[test]
+    fn test_can_hold() {
+        let mut position = Position::parse_from_fen("4k3/8/8/8/8/8/8/3QRK2 w - - 0 1").unwrap();
+        assert!(moves(&position).iter().any(|mv| mv.is_rescue()));
+
+        // Queens and rooks can hold each other, kings can't hold anything
+        for holder in [PieceType::Queen, PieceType::Rook, PieceType::King] {
+            for held in [PieceType::Queen, PieceType::Rook, PieceType::King] {
+                position.rescue_config.set_can_hold(holder, held, false);
+            }
+        }
+        assert!(!moves(&position).iter().any(|mv| mv.is_rescue()));
+    }
+
+    #[test]
+    fn test_max_held() {
+        let mut position =
+            Position::parse_from_fen("4k3/8/8/8/8/8/PPPP4/RxPNxP4K1 w - - 0 1").unwrap();
+        assert!(moves(&position).iter().any(|mv| mv.is_rescue()));
+
+        position.rescue_config.max_held = 2;
+        assert!(!moves(&position).iter().any(|mv| mv.is_rescue()));
+        assert!(moves(&position).iter().any(|mv| mv.is_drop()));
+    }
+
+    #[test]
+    fn test_drops_can_mate() {
+        // The queen dropping the rook it holds on a8 mates
+        let mut position = Position::parse_from_fen("6k1/QxR4ppp/8/8/8/8/8/6K1 w - - 0 1").unwrap();
+        let a8 = Pos::from_algebraic("a8").unwrap();
+
+        let is_mating_drop = |mv: &&PieceMove| {
+            matches!(
+                mv.move_type,
+                MoveType::Normal {
+                    dropped_pos: Some(dropped),
+                    ..
+                } if dropped == a8
+            )
+        };
+
+        let mating_drops = moves(&position).iter().filter(is_mating_drop).count();
+        assert!(mating_drops > 0);
+
+        position.rescue_config.drops_can_mate = false;
+        assert!(moves(&position).iter().filter(is_mating_drop).count() < mating_drops);
+        assert!(moves(&position).iter().any(|mv| mv.is_drop()));
+    }
+}
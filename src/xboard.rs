@@ -0,0 +1,718 @@
+//! A driver for the Chess Engine Communication Protocol (CECP), spoken by xboard, WinBoard and
+//! many older GUIs and tournament managers.
+//!
+//! Unlike UCI, the engine keeps track of the game itself: the GUI sends the moves one at a time
+//! and the engine replies with its own moves whenever it is its turn, unless it is in force mode.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use tracing::{error, trace};
+
+use crate::{piece_move::GameType, search::game_state::GameState, Color, PieceMove, Position};
+
+/// How many more moves the engine assumes it has to make with its remaining time.
+const MOVES_TO_GO: u64 = 30;
+
+/// Represents CECP commands that can be sent from GUI to engine
+#[derive(Debug, PartialEq)]
+pub enum XboardCommand {
+    Xboard,
+    Protover(u32),
+    New,
+    Force,
+    Go,
+    UserMove(String),
+    /// The engine's remaining time, in centiseconds.
+    Time(u64),
+    /// The opponent's remaining time, in centiseconds.
+    Otim(u64),
+    Level(Level),
+    /// A fixed time for each move, in seconds.
+    St(u64),
+    /// The deepest the engine may search, in plies.
+    Sd(u32),
+    Variant(String),
+    Result(String),
+    SetBoard(String),
+    Ping(String),
+    Accepted(String),
+    Rejected(String),
+    Post,
+    NoPost,
+    Hard,
+    Easy,
+    Quit,
+    Unknown(String),
+}
+
+/// A conventional time control, as set by `level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    /// How many moves have to be made in each time control, or 0 for the whole game.
+    pub moves_per_session: u32,
+
+    /// The time on the clock at the start of each time control, in milliseconds.
+    pub base_ms: u64,
+
+    /// The time added to the clock after each move, in milliseconds.
+    pub increment_ms: u64,
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    /// Parses the arguments of `level`, e.g. "40 5 0" for 40 moves in 5 minutes, or "0 2:30 1"
+    /// for the whole game in 2 minutes 30 seconds with a second added after each move.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid argument for level: {}", s);
+
+        let [moves, base, increment] = s.split_whitespace().collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+
+        let base_seconds = match base.split_once(':') {
+            Some((minutes, seconds)) => {
+                minutes.parse::<u64>().map_err(|_| invalid())? * 60
+                    + seconds.parse::<u64>().map_err(|_| invalid())?
+            }
+            None => base.parse::<u64>().map_err(|_| invalid())? * 60,
+        };
+        let increment_seconds = increment.parse::<f64>().map_err(|_| invalid())?;
+
+        if increment_seconds < 0.0 {
+            return Err(invalid());
+        }
+
+        Ok(Level {
+            moves_per_session: moves.parse().map_err(|_| invalid())?,
+            base_ms: base_seconds * 1000,
+            increment_ms: (increment_seconds * 1000.0) as u64,
+        })
+    }
+}
+
+impl std::str::FromStr for XboardCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let args = args.trim();
+
+        let parse_number = |name: &str| -> Result<u64, String> {
+            args.parse()
+                .map_err(|_| format!("Invalid argument for {}: {}", name, args))
+        };
+
+        match command {
+            "xboard" => Ok(XboardCommand::Xboard),
+            "protover" => Ok(XboardCommand::Protover(parse_number("protover")? as u32)),
+            "new" => Ok(XboardCommand::New),
+            "force" => Ok(XboardCommand::Force),
+            "go" => Ok(XboardCommand::Go),
+            "usermove" => Ok(XboardCommand::UserMove(args.to_string())),
+            "time" => Ok(XboardCommand::Time(parse_number("time")?)),
+            "otim" => Ok(XboardCommand::Otim(parse_number("otim")?)),
+            "level" => Ok(XboardCommand::Level(args.parse()?)),
+            "st" => Ok(XboardCommand::St(parse_number("st")?)),
+            "sd" => Ok(XboardCommand::Sd(parse_number("sd")? as u32)),
+            "variant" => Ok(XboardCommand::Variant(args.to_string())),
+            "result" => Ok(XboardCommand::Result(args.to_string())),
+            "setboard" => Ok(XboardCommand::SetBoard(args.to_string())),
+            "ping" => Ok(XboardCommand::Ping(args.to_string())),
+            "accepted" => Ok(XboardCommand::Accepted(args.to_string())),
+            "rejected" => Ok(XboardCommand::Rejected(args.to_string())),
+            "post" => Ok(XboardCommand::Post),
+            "nopost" => Ok(XboardCommand::NoPost),
+            "hard" => Ok(XboardCommand::Hard),
+            "easy" => Ok(XboardCommand::Easy),
+            "quit" => Ok(XboardCommand::Quit),
+
+            // Without usermove=1, or from older GUIs, moves arrive as bare commands
+            _ if looks_like_move(command) && args.is_empty() => {
+                Ok(XboardCommand::UserMove(command.to_string()))
+            }
+
+            _ => Ok(XboardCommand::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// Whether a command is a move in coordinate notation, like e2e4 or e7e8q.
+fn looks_like_move(command: &str) -> bool {
+    let bytes = command.as_bytes();
+
+    (bytes.len() == 4 || bytes.len() == 5)
+        && (b'a'..=b'h').contains(&bytes[0])
+        && (b'1'..=b'8').contains(&bytes[1])
+        && (b'a'..=b'h').contains(&bytes[2])
+        && (b'1'..=b'8').contains(&bytes[3])
+}
+
+pub struct XboardEngine {
+    pub game_state: Arc<Mutex<GameState>>,
+    pub stdout: Arc<Mutex<Box<dyn io::Write + Send>>>,
+
+    /// The color the engine plays. None in force mode, where the engine only tracks the moves
+    /// it is sent.
+    pub engine_color: Option<Color>,
+
+    /// The engine's remaining time on its clock, in centiseconds, as last sent by `time`.
+    pub engine_time_cs: Option<u64>,
+
+    /// The opponent's remaining time on its clock, in centiseconds, as last sent by `otim`.
+    pub opponent_time_cs: Option<u64>,
+
+    /// The time control, as last set by `level`.
+    pub level: Option<Level>,
+
+    /// A fixed time for each move in seconds, as set by `st`, which the engine uses instead of
+    /// its clock.
+    pub seconds_per_move: Option<u64>,
+}
+
+impl XboardEngine {
+    pub fn new() -> Self {
+        Self {
+            game_state: Arc::new(Mutex::new(GameState::default())),
+            stdout: Arc::new(Mutex::new(Box::new(io::stdout()))),
+            engine_color: Some(Color::Black),
+            engine_time_cs: None,
+            opponent_time_cs: None,
+            level: None,
+            seconds_per_move: None,
+        }
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        trace!("Sending: {}", line);
+
+        let mut stdout = self.stdout.lock().unwrap();
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()
+    }
+
+    /// Handles a single command. Returns false when the engine should exit.
+    pub fn handle_command(&mut self, command: XboardCommand) -> io::Result<bool> {
+        match command {
+            XboardCommand::Xboard => Ok(true),
+            XboardCommand::Protover(_) => {
+                self.send(
+                    "feature myname=\"RescueChess\" usermove=1 setboard=1 ping=1 colors=0 \
+                     sigint=0 sigterm=0 variants=\"normal,rescue\"",
+                )?;
+                self.send("feature done=1")?;
+                Ok(true)
+            }
+            XboardCommand::New => {
+                self.game_state = Arc::new(Mutex::new(GameState::new()));
+                self.engine_color = Some(Color::Black);
+                self.engine_time_cs = None;
+                self.opponent_time_cs = None;
+                Ok(true)
+            }
+            XboardCommand::Force => {
+                self.engine_color = None;
+                Ok(true)
+            }
+            XboardCommand::Go => {
+                self.engine_color = Some(self.game_state.lock().unwrap().current_turn);
+                self.think_and_move()?;
+                Ok(true)
+            }
+            XboardCommand::UserMove(notation) => {
+                if let Err(e) = self.apply_user_move(&notation) {
+                    trace!("Illegal move {}: {}", notation, e);
+                    self.send(&format!("Illegal move: {}", notation))?;
+                    return Ok(true);
+                }
+
+                if self.report_game_over()? {
+                    return Ok(true);
+                }
+
+                if self.engine_color == Some(self.game_state.lock().unwrap().current_turn) {
+                    self.think_and_move()?;
+                }
+
+                Ok(true)
+            }
+            XboardCommand::Time(centiseconds) => {
+                self.engine_time_cs = Some(centiseconds);
+                Ok(true)
+            }
+            XboardCommand::Otim(centiseconds) => {
+                self.opponent_time_cs = Some(centiseconds);
+                Ok(true)
+            }
+            XboardCommand::Level(level) => {
+                self.level = Some(level);
+                self.seconds_per_move = None;
+                Ok(true)
+            }
+            XboardCommand::St(seconds) => {
+                self.seconds_per_move = Some(seconds);
+                Ok(true)
+            }
+            XboardCommand::Sd(depth) => {
+                self.game_state.lock().unwrap().search_depth = depth.max(1);
+                Ok(true)
+            }
+            XboardCommand::Variant(variant) => {
+                let game_type = match variant.as_str() {
+                    "normal" => GameType::Classic,
+                    "rescue" => GameType::Rescue,
+                    _ => {
+                        self.send(&format!("Error (unsupported variant): {}", variant))?;
+                        return Ok(true);
+                    }
+                };
+
+                self.game_state.lock().unwrap().game_type = game_type;
+                Ok(true)
+            }
+            XboardCommand::Result(result) => {
+                trace!("Game over: {}", result);
+                self.engine_color = None;
+                Ok(true)
+            }
+            XboardCommand::SetBoard(fen) => {
+                match Position::parse_from_fen(&fen) {
+                    Ok(position) => {
+                        let mut game_state = self.game_state.lock().unwrap();
+                        game_state.current_turn = position.true_active_color;
+                        game_state.current_position = position;
+                        game_state.reset_history();
+                    }
+                    Err(e) => {
+                        trace!("Invalid FEN {}: {}", fen, e);
+                        self.send(&format!("tellusererror Illegal position: {}", e))?;
+                    }
+                }
+                Ok(true)
+            }
+            XboardCommand::Ping(n) => {
+                self.send(&format!("pong {}", n))?;
+                Ok(true)
+            }
+            // Replies to our features, and settings for thinking output and pondering that the
+            // engine doesn't have
+            XboardCommand::Accepted(_)
+            | XboardCommand::Rejected(_)
+            | XboardCommand::Post
+            | XboardCommand::NoPost
+            | XboardCommand::Hard
+            | XboardCommand::Easy => Ok(true),
+            XboardCommand::Quit => Ok(false),
+            XboardCommand::Unknown(cmd) => {
+                if cmd.trim().is_empty() {
+                    return Ok(true);
+                }
+
+                error!("Unknown command: {}", cmd);
+                self.send(&format!("Error (unknown command): {}", cmd))?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn apply_user_move(&mut self, notation: &str) -> Result<(), anyhow::Error> {
+        let mut game_state = self.game_state.lock().unwrap();
+
+        let mv = if game_state.current_turn == Color::White {
+            PieceMove::from_uci(&game_state.current_position, notation, game_state.game_type)
+        } else {
+            PieceMove::from_uci_inverted(
+                &game_state.current_position,
+                notation,
+                game_state.game_type,
+            )
+        }?;
+
+        game_state.apply_move(mv)
+    }
+
+    /// The time the engine should spend on its next move: the time set by `st`, or otherwise
+    /// its remaining time spread over the moves it still has to make before the next time
+    /// control, plus the increment.
+    fn time_for_move_ms(&self) -> u64 {
+        if let Some(seconds) = self.seconds_per_move {
+            return seconds * 1000;
+        }
+
+        let remaining_ms = match (self.engine_time_cs, self.level) {
+            (Some(centiseconds), _) => centiseconds * 10,
+            (None, Some(level)) => level.base_ms,
+            (None, None) => return u64::MAX,
+        };
+
+        let (moves_to_go, increment_ms) = match self.level {
+            Some(level) if level.moves_per_session > 0 => {
+                let moves_per_session = level.moves_per_session as u64;
+                let moves_made = self.game_state.lock().unwrap().move_number as u64 - 1;
+                (
+                    moves_per_session - moves_made % moves_per_session,
+                    level.increment_ms,
+                )
+            }
+            Some(level) => (MOVES_TO_GO, level.increment_ms),
+            None => (MOVES_TO_GO, 0),
+        };
+
+        // Never plan to use more than half the clock, however large the increment
+        (remaining_ms / moves_to_go + increment_ms)
+            .min(remaining_ms / 2)
+            .max(10)
+    }
+
+    /// Searches the current position, plays the best move and reports it to the GUI.
+    fn think_and_move(&mut self) -> io::Result<()> {
+        if self.report_game_over()? {
+            return Ok(());
+        }
+
+        let time_limit_ms = self.time_for_move_ms();
+
+        let result = {
+            let mut game_state = self.game_state.lock().unwrap();
+            let is_black = game_state.current_turn == Color::Black;
+
+            game_state.time_limit_ms = time_limit_ms;
+            trace!("Time limit: {} ms", game_state.time_limit_ms);
+
+            game_state.search_and_apply().map(|(best_move, _)| {
+                if is_black {
+                    best_move.inverted()
+                } else {
+                    best_move
+                }
+            })
+        };
+
+        match result {
+            Ok(best_move) => {
+                self.send(&format!("move {}", best_move.to_uci()))?;
+                self.report_game_over()?;
+            }
+            Err(e) => {
+                error!("Error searching: {}", e);
+                self.send(&format!("Error (search failed): {}", e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the result if the game has ended at the current position. Returns true if it has.
+    fn report_game_over(&mut self) -> io::Result<bool> {
+        let status = match self.game_state.lock().unwrap().game_status() {
+            Ok(status) => status,
+            Err(e) => {
+                error!("Error getting game status: {}", e);
+                return Ok(false);
+            }
+        };
+
+        if status.is_ongoing() {
+            return Ok(false);
+        }
+
+        let result = match status.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        };
+
+        self.send(&format!("{} {{{}}}", result, status))?;
+        self.engine_color = None;
+
+        Ok(true)
+    }
+}
+
+impl Default for XboardEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper struct to capture stdout
+    struct CaptureStdout {
+        buffer: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl CaptureStdout {
+        fn as_string(&self) -> String {
+            String::from_utf8_lossy(&self.buffer.lock().unwrap()).to_string()
+        }
+    }
+
+    impl Write for CaptureStdout {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffer.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn create_test_engine() -> (XboardEngine, CaptureStdout) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = XboardEngine::new();
+        engine.stdout = Arc::new(Mutex::new(Box::new(CaptureStdout {
+            buffer: buffer.clone(),
+        })));
+        engine.game_state.lock().unwrap().search_depth = 2;
+
+        (engine, CaptureStdout { buffer })
+    }
+
+    fn send(engine: &mut XboardEngine, command: &str) -> bool {
+        let command = command.parse::<XboardCommand>().unwrap();
+        engine.handle_command(command).unwrap()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            "protover 2".parse::<XboardCommand>(),
+            Ok(XboardCommand::Protover(2))
+        );
+        assert_eq!(
+            "usermove e2e4".parse::<XboardCommand>(),
+            Ok(XboardCommand::UserMove("e2e4".to_string()))
+        );
+        assert_eq!(
+            "e7e8q".parse::<XboardCommand>(),
+            Ok(XboardCommand::UserMove("e7e8q".to_string()))
+        );
+        assert_eq!(
+            "time 30000".parse::<XboardCommand>(),
+            Ok(XboardCommand::Time(30000))
+        );
+        assert_eq!(
+            "result 1-0 {White mates}".parse::<XboardCommand>(),
+            Ok(XboardCommand::Result("1-0 {White mates}".to_string()))
+        );
+        assert!("time soon".parse::<XboardCommand>().is_err());
+        assert_eq!("hard".parse::<XboardCommand>(), Ok(XboardCommand::Hard));
+        assert_eq!(
+            "accepted usermove".parse::<XboardCommand>(),
+            Ok(XboardCommand::Accepted("usermove".to_string()))
+        );
+        assert_eq!(
+            "variant rescue".parse::<XboardCommand>(),
+            Ok(XboardCommand::Variant("rescue".to_string()))
+        );
+        assert_eq!("sd 6".parse::<XboardCommand>(), Ok(XboardCommand::Sd(6)));
+        assert_eq!("st 10".parse::<XboardCommand>(), Ok(XboardCommand::St(10)));
+        assert_eq!(
+            "bogus".parse::<XboardCommand>(),
+            Ok(XboardCommand::Unknown("bogus".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(
+            "level 40 5 0".parse::<XboardCommand>(),
+            Ok(XboardCommand::Level(Level {
+                moves_per_session: 40,
+                base_ms: 300_000,
+                increment_ms: 0,
+            }))
+        );
+        assert_eq!(
+            "0 2:30 1.5".parse::<Level>(),
+            Ok(Level {
+                moves_per_session: 0,
+                base_ms: 150_000,
+                increment_ms: 1_500,
+            })
+        );
+        assert!("40 5".parse::<Level>().is_err());
+        assert!("level 40 five 0".parse::<XboardCommand>().is_err());
+    }
+
+    #[test]
+    fn test_protover_sends_features() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "xboard");
+        send(&mut engine, "protover 2");
+
+        let output = capture.as_string();
+        assert!(output.contains("myname=\"RescueChess\""));
+        assert!(output.contains("usermove=1"));
+        assert!(output.contains("variants=\"normal,rescue\""));
+        assert!(output.trim_end().ends_with("feature done=1"));
+    }
+
+    #[test]
+    fn test_engine_replies_to_user_move() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "new");
+        send(&mut engine, "usermove e2e4");
+
+        let output = capture.as_string();
+        let reply = output.lines().last().unwrap();
+        assert!(reply.starts_with("move "), "{}", output);
+
+        let game_state = engine.game_state.lock().unwrap();
+        assert_eq!(game_state.current_turn, Color::White);
+        assert_eq!(game_state.num_plies, 2);
+    }
+
+    #[test]
+    fn test_force_mode() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "new");
+        send(&mut engine, "force");
+        send(&mut engine, "e2e4");
+        send(&mut engine, "e7e5");
+
+        assert!(capture.as_string().is_empty());
+        assert_eq!(engine.game_state.lock().unwrap().num_plies, 2);
+
+        // go makes the engine play the side to move
+        send(&mut engine, "go");
+        assert_eq!(engine.engine_color, Some(Color::White));
+        assert!(capture.as_string().starts_with("move "));
+        assert_eq!(engine.game_state.lock().unwrap().num_plies, 3);
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "new");
+        send(&mut engine, "usermove e2e5");
+
+        assert_eq!(capture.as_string().trim(), "Illegal move: e2e5");
+        assert_eq!(engine.game_state.lock().unwrap().num_plies, 0);
+    }
+
+    #[test]
+    fn test_time_sets_limit() {
+        let (mut engine, _capture) = create_test_engine();
+
+        assert_eq!(engine.time_for_move_ms(), u64::MAX);
+
+        send(&mut engine, "time 30000");
+        send(&mut engine, "otim 25000");
+
+        assert_eq!(engine.engine_time_cs, Some(30000));
+        assert_eq!(engine.opponent_time_cs, Some(25000));
+        assert_eq!(engine.time_for_move_ms(), 10_000);
+    }
+
+    #[test]
+    fn test_level_and_st_set_limit() {
+        let (mut engine, _capture) = create_test_engine();
+
+        // 40 moves in 5 minutes, with all 40 still to play
+        send(&mut engine, "level 40 5 0");
+        assert_eq!(engine.time_for_move_ms(), 7_500);
+
+        send(&mut engine, "time 12000");
+        assert_eq!(engine.time_for_move_ms(), 3_000);
+
+        // A whole game with an increment
+        send(&mut engine, "level 0 1 2");
+        assert_eq!(engine.time_for_move_ms(), 120_000 / 30 + 2_000);
+
+        send(&mut engine, "st 3");
+        assert_eq!(engine.time_for_move_ms(), 3_000);
+
+        send(&mut engine, "sd 3");
+        assert_eq!(engine.game_state.lock().unwrap().search_depth, 3);
+    }
+
+    #[test]
+    fn test_variant() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "new");
+        send(&mut engine, "variant rescue");
+        send(&mut engine, "force");
+        send(&mut engine, "usermove e2e2Sf2");
+
+        assert!(capture.as_string().is_empty(), "{}", capture.as_string());
+        {
+            let game_state = engine.game_state.lock().unwrap();
+            assert_eq!(game_state.game_type, GameType::Rescue);
+            assert_eq!(game_state.num_plies, 1);
+        }
+
+        send(&mut engine, "variant crazyhouse");
+        assert_eq!(
+            capture.as_string().trim(),
+            "Error (unsupported variant): crazyhouse"
+        );
+
+        // A new game is a normal one until the GUI says otherwise
+        send(&mut engine, "new");
+        assert_eq!(
+            engine.game_state.lock().unwrap().game_type,
+            GameType::Classic
+        );
+    }
+
+    #[test]
+    fn test_ignored_commands() {
+        let (mut engine, capture) = create_test_engine();
+
+        for command in [
+            "accepted usermove",
+            "rejected colors",
+            "post",
+            "hard",
+            "easy",
+        ] {
+            assert!(send(&mut engine, command));
+        }
+
+        assert!(capture.as_string().is_empty(), "{}", capture.as_string());
+    }
+
+    #[test]
+    fn test_reports_checkmate() {
+        let (mut engine, capture) = create_test_engine();
+
+        send(&mut engine, "new");
+        send(&mut engine, "force");
+        for mv in ["f2f3", "e7e5", "g2g4"] {
+            send(&mut engine, mv);
+        }
+
+        send(&mut engine, "go");
+        let output = capture.as_string();
+        assert!(output.contains("move d8h4"), "{}", output);
+        assert!(output.contains("0-1 {Checkmate, black wins}"), "{}", output);
+        assert_eq!(engine.engine_color, None);
+    }
+
+    #[test]
+    fn test_result_ping_and_quit() {
+        let (mut engine, capture) = create_test_engine();
+
+        assert!(send(&mut engine, "result 1/2-1/2 {Draw}"));
+        assert_eq!(engine.engine_color, None);
+
+        assert!(send(&mut engine, "ping 7"));
+        assert_eq!(capture.as_string().trim(), "pong 7");
+
+        assert!(!send(&mut engine, "quit"));
+    }
+}